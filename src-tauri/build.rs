@@ -1,3 +1,5 @@
 fn main() {
-    tauri_build::build()
+    // MCP-only builds have no app to bundle
+    #[cfg(feature = "gui")]
+    tauri_build::build();
 }
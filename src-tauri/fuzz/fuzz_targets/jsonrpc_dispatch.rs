@@ -0,0 +1,53 @@
+//! Feeds arbitrary lines to the MCP server's dispatcher, as a client on stdin
+//! would. Every line must be answered with well-formed JSON-RPC (an error
+//! object for anything that isn't a valid request) without panicking, so a
+//! hostile client can't take the stdio loop down.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use prompt_forge_lib::db::{init_default_data, Database};
+use prompt_forge_lib::mcp_server::McpServer;
+use serde_json::Value;
+use std::sync::{Mutex, OnceLock};
+
+/// One server for the whole run, on a library with the default data. The
+/// defaults have no tool skills, so no input can start a process.
+fn server() -> &'static Mutex<McpServer> {
+    static SERVER: OnceLock<Mutex<McpServer>> = OnceLock::new();
+    SERVER.get_or_init(|| {
+        let dir = std::env::temp_dir().join(format!("pf-fuzz-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db_path = dir.join("library.db");
+        let _ = std::fs::remove_file(&db_path);
+        let db = Database::open(&db_path).unwrap();
+        db.migrate().unwrap();
+        init_default_data(&db).unwrap();
+        Mutex::new(McpServer::new(db_path, false, None))
+    })
+}
+
+fuzz_target!(|data: &[u8]| {
+    let text = String::from_utf8_lossy(data);
+    let mut server = server().lock().unwrap();
+
+    for line in text.lines() {
+        let messages = server.handle_line(line);
+        for message in &messages {
+            let message: Value = serde_json::from_str(message).expect("output isn't JSON");
+            assert_eq!(message["jsonrpc"], "2.0");
+            if message.get("method").is_none() {
+                // A response carries exactly one of result and error
+                assert!(message.get("result").is_some() != message.get("error").is_some());
+                if let Some(error) = message.get("error") {
+                    assert!(error["code"].is_i64() && error["message"].is_string());
+                }
+            }
+        }
+
+        // Anything but a notification or a reply to the server gets an answer
+        if !line.is_empty() && serde_json::from_str::<Value>(line).is_err() {
+            assert!(messages.iter().any(|m| m.contains("-32700")));
+        }
+    }
+});
@@ -0,0 +1,14 @@
+-- Advisory edit locks for concurrent editors
+-- Version: 003_add_edit_locks
+
+-- One row per locked entity (agent, skill, or instruction id).
+-- Locks expire on their own so a crashed client never blocks others forever.
+CREATE TABLE IF NOT EXISTS edit_locks (
+    entity_id TEXT PRIMARY KEY NOT NULL,
+    owner_id TEXT NOT NULL,
+    owner_name TEXT NOT NULL DEFAULT '',
+    acquired_at TEXT NOT NULL,
+    expires_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_edit_locks_expires ON edit_locks(expires_at);
@@ -0,0 +1,12 @@
+-- Binary attachments (agent avatar images)
+-- Version: 004_add_attachments
+
+CREATE TABLE IF NOT EXISTS attachments (
+    id TEXT PRIMARY KEY NOT NULL,
+    mime_type TEXT NOT NULL,
+    data BLOB NOT NULL,
+    created_at TEXT NOT NULL
+);
+
+-- Optional image avatar shown instead of the emoji when set
+ALTER TABLE agents ADD COLUMN avatar_image_id TEXT DEFAULT NULL;
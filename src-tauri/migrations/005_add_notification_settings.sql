@@ -0,0 +1,4 @@
+-- Per-event toggles for OS notifications
+-- Version: 005_add_notification_settings
+
+ALTER TABLE settings ADD COLUMN notifications_json TEXT DEFAULT '{}';
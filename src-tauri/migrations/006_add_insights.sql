@@ -0,0 +1,13 @@
+-- Local-only usage insights (opt-in)
+-- Version: 006_add_insights
+
+ALTER TABLE settings ADD COLUMN insights_enabled INTEGER DEFAULT 0;
+
+-- Daily counters keyed by metric (edit, apply, mcp_call, category) and an optional sub-key
+CREATE TABLE IF NOT EXISTS insight_counters (
+    day TEXT NOT NULL,
+    metric TEXT NOT NULL,
+    key TEXT NOT NULL DEFAULT '',
+    count INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (day, metric, key)
+);
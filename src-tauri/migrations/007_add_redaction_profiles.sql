@@ -0,0 +1,4 @@
+-- Named redaction profiles applied to exports
+-- Version: 007_add_redaction_profiles
+
+ALTER TABLE settings ADD COLUMN redaction_profiles_json TEXT DEFAULT '[]';
@@ -0,0 +1,4 @@
+-- Instructions pinned into the MCP system context
+-- Version: 008_add_pinned_instructions
+
+ALTER TABLE instructions ADD COLUMN pin_to_context INTEGER DEFAULT 0;
@@ -0,0 +1,5 @@
+-- Alternative names accepted when looking up agents and skills
+-- Version: 009_add_aliases
+
+ALTER TABLE agents ADD COLUMN aliases_json TEXT DEFAULT '[]';
+ALTER TABLE skills ADD COLUMN aliases_json TEXT DEFAULT '[]';
@@ -0,0 +1,10 @@
+-- Append-only usage events written by the MCP process and drained by the GUI
+-- Version: 010_add_usage_events
+
+CREATE TABLE IF NOT EXISTS usage_events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    kind TEXT NOT NULL,
+    entity_id TEXT NOT NULL DEFAULT '',
+    payload TEXT NOT NULL DEFAULT '',
+    created_at TEXT NOT NULL
+);
@@ -0,0 +1,4 @@
+-- Nested categories: a slash-separated path below the top-level category
+-- Version: 011_add_subcategories
+
+ALTER TABLE instructions ADD COLUMN subcategory TEXT;
@@ -0,0 +1,4 @@
+-- Branch scope: instructions only assembled on git branches matching a glob list
+-- Version: 012_add_branch_patterns
+
+ALTER TABLE instructions ADD COLUMN branch_pattern TEXT;
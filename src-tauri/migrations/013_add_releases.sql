@@ -0,0 +1,12 @@
+-- Tagged snapshots of the enabled library for pinned prompt releases
+-- Version: 013_add_releases
+
+CREATE TABLE IF NOT EXISTS releases (
+    tag TEXT PRIMARY KEY,
+    notes TEXT NOT NULL DEFAULT '',
+    created_at TEXT NOT NULL,
+    agent_count INTEGER NOT NULL DEFAULT 0,
+    skill_count INTEGER NOT NULL DEFAULT 0,
+    instruction_count INTEGER NOT NULL DEFAULT 0,
+    snapshot_json TEXT NOT NULL
+);
@@ -0,0 +1,4 @@
+-- Named channels pointing MCP servers at a release tag
+-- Version: 014_add_release_channels
+
+ALTER TABLE settings ADD COLUMN release_channels_json TEXT DEFAULT '[]';
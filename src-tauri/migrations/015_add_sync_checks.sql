@@ -0,0 +1,4 @@
+-- Optionally refuse to sync the library while checks report errors
+-- Version: 015_add_sync_checks
+
+ALTER TABLE settings ADD COLUMN block_sync_on_errors INTEGER DEFAULT 0;
@@ -0,0 +1,4 @@
+-- Optionally refuse imports whose parse diagnostics include errors
+-- Version: 016_add_strict_imports
+
+ALTER TABLE settings ADD COLUMN strict_imports INTEGER DEFAULT 0;
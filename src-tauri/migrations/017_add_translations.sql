@@ -0,0 +1,21 @@
+-- Locale variants of library entities, and the LLM providers that draft them
+-- Version: 017_add_translations
+
+CREATE TABLE IF NOT EXISTS translations (
+    id TEXT PRIMARY KEY,
+    entity_type TEXT NOT NULL,
+    entity_id TEXT NOT NULL,
+    locale TEXT NOT NULL,
+    name TEXT NOT NULL DEFAULT '',
+    description TEXT NOT NULL DEFAULT '',
+    content TEXT NOT NULL DEFAULT '',
+    needs_review INTEGER NOT NULL DEFAULT 1,
+    provider TEXT,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    UNIQUE (entity_id, locale)
+);
+
+CREATE INDEX IF NOT EXISTS idx_translations_locale ON translations(locale);
+
+ALTER TABLE settings ADD COLUMN llm_providers_json TEXT DEFAULT '[]';
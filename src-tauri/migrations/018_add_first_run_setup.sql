@@ -0,0 +1,8 @@
+-- Scheduled backups and first-run setup state
+-- Version: 018_add_first_run_setup
+
+ALTER TABLE settings ADD COLUMN backup_schedule_json TEXT DEFAULT '{}';
+ALTER TABLE settings ADD COLUMN setup_completed INTEGER DEFAULT 0;
+
+-- Libraries that already hold data were set up before the wizard existed
+UPDATE settings SET setup_completed = 1 WHERE EXISTS (SELECT 1 FROM agents);
@@ -0,0 +1,20 @@
+-- Nested collections (folders) for organizing library entities
+-- Version: 019_add_collections
+
+CREATE TABLE IF NOT EXISTS collections (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    parent_id TEXT,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+-- An entity can sit in any number of collections
+CREATE TABLE IF NOT EXISTS collection_items (
+    collection_id TEXT NOT NULL,
+    entity_type TEXT NOT NULL,
+    entity_id TEXT NOT NULL,
+    PRIMARY KEY (collection_id, entity_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_collection_items_entity ON collection_items(entity_id);
@@ -0,0 +1,12 @@
+-- Intent records for multi-step operations, cleared once each one completes
+-- Version: 020_add_operation_journal
+
+CREATE TABLE IF NOT EXISTS operation_journal (
+    id TEXT PRIMARY KEY,
+    kind TEXT NOT NULL,
+    summary TEXT NOT NULL DEFAULT '',
+    status TEXT NOT NULL DEFAULT 'running', -- 'running' or 'interrupted'
+    payload_json TEXT NOT NULL,
+    started_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
@@ -0,0 +1,9 @@
+-- Hourly usage buckets per entity, for when-is-it-used heatmaps
+-- Version: 021_add_usage_heatmap
+
+CREATE TABLE IF NOT EXISTS usage_hours (
+    entity_id TEXT NOT NULL,
+    hour TEXT NOT NULL, -- UTC, YYYY-MM-DDTHH
+    count INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (entity_id, hour)
+);
@@ -0,0 +1,4 @@
+-- File scope: globs naming the files an instruction is written for, as used by Cursor and Copilot rules
+-- Version: 022_add_file_patterns
+
+ALTER TABLE instructions ADD COLUMN file_pattern TEXT;
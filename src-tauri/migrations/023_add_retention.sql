@@ -0,0 +1,4 @@
+-- How long collected usage data is kept before it is purged automatically
+-- Version: 023_add_retention
+
+ALTER TABLE settings ADD COLUMN retention_json TEXT DEFAULT '{}';
@@ -0,0 +1,8 @@
+-- A/B experiments on instruction wording, served per MCP session
+-- Version: 024_add_experiments
+
+CREATE TABLE IF NOT EXISTS experiments (
+    instruction_id TEXT PRIMARY KEY,
+    variant_b TEXT NOT NULL, -- Variant A is the instruction's own content
+    started_at TEXT NOT NULL
+);
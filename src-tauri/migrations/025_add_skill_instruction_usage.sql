@@ -0,0 +1,7 @@
+-- Usage tracking for skills and instructions, like agents have
+-- Version: 025_add_skill_instruction_usage
+
+ALTER TABLE skills ADD COLUMN usage_count INTEGER DEFAULT 0;
+ALTER TABLE skills ADD COLUMN last_used_at TEXT DEFAULT NULL;
+ALTER TABLE instructions ADD COLUMN usage_count INTEGER DEFAULT 0;
+ALTER TABLE instructions ADD COLUMN last_used_at TEXT DEFAULT NULL;
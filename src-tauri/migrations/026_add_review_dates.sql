@@ -0,0 +1,4 @@
+-- When each instruction is next due for review
+-- Version: 026_add_review_dates
+
+ALTER TABLE instructions ADD COLUMN review_due_at TEXT DEFAULT NULL;
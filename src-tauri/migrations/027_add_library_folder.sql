@@ -0,0 +1,4 @@
+-- Folder the library is mirrored to and watched for outside edits
+-- Version: 027_add_library_folder
+
+ALTER TABLE settings ADD COLUMN library_folder TEXT DEFAULT NULL;
@@ -0,0 +1,4 @@
+-- How each agent's attached instructions are ordered when assembled
+-- Version: 028_add_instruction_order
+
+ALTER TABLE agents ADD COLUMN instruction_order TEXT DEFAULT 'priority';
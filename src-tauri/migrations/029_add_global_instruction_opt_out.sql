@@ -0,0 +1,4 @@
+-- Whether each agent is assembled with the global instructions
+-- Version: 029_add_global_instruction_opt_out
+
+ALTER TABLE agents ADD COLUMN include_global_instructions INTEGER DEFAULT 1;
@@ -0,0 +1,4 @@
+-- Contexts (languages, projects, MCP clients) an instruction is written for, as a JSON array
+-- Version: 030_add_instruction_contexts
+
+ALTER TABLE instructions ADD COLUMN contexts_json TEXT DEFAULT '[]';
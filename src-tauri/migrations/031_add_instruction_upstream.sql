@@ -0,0 +1,7 @@
+-- Source page of an instruction imported from a URL, when it was last taken
+-- from there, and a digest of the page's content at that time
+-- Version: 031_add_instruction_upstream
+
+ALTER TABLE instructions ADD COLUMN upstream_url TEXT;
+ALTER TABLE instructions ADD COLUMN upstream_synced_at TEXT;
+ALTER TABLE instructions ADD COLUMN upstream_hash TEXT;
@@ -0,0 +1,4 @@
+-- Whether MCP clients may create and update instructions and skills
+-- Version: 032_add_mcp_write_tools
+
+ALTER TABLE settings ADD COLUMN mcp_write_tools INTEGER DEFAULT 0;
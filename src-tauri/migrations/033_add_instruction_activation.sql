@@ -0,0 +1,6 @@
+-- Rules for when an instruction is served (dates, weekdays, hours, profiles) as JSON,
+-- and the profile whose instructions are switched on
+-- Version: 033_add_instruction_activation
+
+ALTER TABLE instructions ADD COLUMN activation_json TEXT;
+ALTER TABLE settings ADD COLUMN active_profile TEXT;
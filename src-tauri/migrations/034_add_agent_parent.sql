@@ -0,0 +1,5 @@
+-- Base agent an agent inherits its system prompt, personality, skills and
+-- instructions from
+-- Version: 034_add_agent_parent
+
+ALTER TABLE agents ADD COLUMN parent_agent_id TEXT;
@@ -0,0 +1,11 @@
+-- Reusable text blocks included in prompts with {{> snippet-id}}
+-- Version: 035_add_snippets
+
+CREATE TABLE IF NOT EXISTS snippets (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    description TEXT NOT NULL DEFAULT '',
+    content TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
@@ -0,0 +1,10 @@
+-- Tool skill handlers only run over MCP when turned on and approved
+-- Version: 036_add_tool_approvals
+
+ALTER TABLE settings ADD COLUMN mcp_run_tools INTEGER DEFAULT 0;
+
+CREATE TABLE IF NOT EXISTS tool_approvals (
+    skill_id TEXT PRIMARY KEY,
+    handler_sha256 TEXT NOT NULL,
+    approved_at TEXT NOT NULL
+);
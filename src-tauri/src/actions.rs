@@ -0,0 +1,221 @@
+//! Command palette backend
+//! Builds the catalogue of palette actions over library entities and app
+//! operations, fuzzy-matches it against a query, and parses stable action IDs.
+
+use crate::models::{Agent, Instruction, PaletteAction, Skill};
+
+/// Maximum number of actions returned for a query
+const MAX_RESULTS: usize = 25;
+
+/// Something the palette can execute. Action IDs round-trip through
+/// `id()` / `parse()` so the UI can store and replay them.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ActionTarget {
+    OpenAgent(String),
+    ApplyAgent(String),
+    ExportAgent(String),
+    OpenSkill(String),
+    OpenInstruction(String),
+    ExportInstruction(String),
+    StartMcp,
+    StopMcp,
+    CreateBackup,
+    ExportLibrary,
+    ScanSecrets,
+}
+
+impl ActionTarget {
+    pub fn id(&self) -> String {
+        match self {
+            ActionTarget::OpenAgent(id) => format!("agent.open:{}", id),
+            ActionTarget::ApplyAgent(id) => format!("agent.apply:{}", id),
+            ActionTarget::ExportAgent(id) => format!("agent.export:{}", id),
+            ActionTarget::OpenSkill(id) => format!("skill.open:{}", id),
+            ActionTarget::OpenInstruction(id) => format!("instruction.open:{}", id),
+            ActionTarget::ExportInstruction(id) => format!("instruction.export:{}", id),
+            ActionTarget::StartMcp => "mcp.start".to_string(),
+            ActionTarget::StopMcp => "mcp.stop".to_string(),
+            ActionTarget::CreateBackup => "backup.create".to_string(),
+            ActionTarget::ExportLibrary => "library.export".to_string(),
+            ActionTarget::ScanSecrets => "library.scan_secrets".to_string(),
+        }
+    }
+
+    pub fn parse(action_id: &str) -> Result<Self, String> {
+        let (verb, entity_id) = match action_id.split_once(':') {
+            Some((verb, id)) if !id.is_empty() => (verb, Some(id.to_string())),
+            _ => (action_id, None),
+        };
+
+        match (verb, entity_id) {
+            ("agent.open", Some(id)) => Ok(ActionTarget::OpenAgent(id)),
+            ("agent.apply", Some(id)) => Ok(ActionTarget::ApplyAgent(id)),
+            ("agent.export", Some(id)) => Ok(ActionTarget::ExportAgent(id)),
+            ("skill.open", Some(id)) => Ok(ActionTarget::OpenSkill(id)),
+            ("instruction.open", Some(id)) => Ok(ActionTarget::OpenInstruction(id)),
+            ("instruction.export", Some(id)) => Ok(ActionTarget::ExportInstruction(id)),
+            ("mcp.start", None) => Ok(ActionTarget::StartMcp),
+            ("mcp.stop", None) => Ok(ActionTarget::StopMcp),
+            ("backup.create", None) => Ok(ActionTarget::CreateBackup),
+            ("library.export", None) => Ok(ActionTarget::ExportLibrary),
+            ("library.scan_secrets", None) => Ok(ActionTarget::ScanSecrets),
+            _ => Err(format!("Unknown action: {}", action_id)),
+        }
+    }
+}
+
+fn action(target: ActionTarget, title: String, subtitle: &str, category: &str) -> PaletteAction {
+    PaletteAction {
+        id: target.id(),
+        title,
+        subtitle: subtitle.to_string(),
+        category: category.to_string(),
+        score: 0,
+    }
+}
+
+/// Every action currently available, in a stable order
+pub fn catalogue(
+    agents: &[Agent],
+    skills: &[Skill],
+    instructions: &[Instruction],
+) -> Vec<PaletteAction> {
+    let mut actions: Vec<PaletteAction> = [
+        (ActionTarget::StartMcp, "Start MCP server"),
+        (ActionTarget::StopMcp, "Stop MCP server"),
+        (ActionTarget::CreateBackup, "Create backup"),
+        (ActionTarget::ExportLibrary, "Export library"),
+        (ActionTarget::ScanSecrets, "Scan library for secrets"),
+    ]
+    .into_iter()
+    .map(|(target, title)| action(target, title.to_string(), "", "operation"))
+    .collect();
+
+    for agent in agents {
+        let subtitle = agent.description.as_str();
+        actions.push(action(
+            ActionTarget::OpenAgent(agent.id.clone()),
+            format!("Open agent {}", agent.name),
+            subtitle,
+            "agent",
+        ));
+        actions.push(action(
+            ActionTarget::ApplyAgent(agent.id.clone()),
+            format!("Apply agent {}", agent.name),
+            subtitle,
+            "agent",
+        ));
+        actions.push(action(
+            ActionTarget::ExportAgent(agent.id.clone()),
+            format!("Export agent {}", agent.name),
+            subtitle,
+            "agent",
+        ));
+    }
+
+    for skill in skills {
+        actions.push(action(
+            ActionTarget::OpenSkill(skill.id.clone()),
+            format!("Open skill {}", skill.name),
+            &skill.description,
+            "skill",
+        ));
+    }
+
+    for instruction in instructions {
+        let subtitle = instruction.description.as_str();
+        actions.push(action(
+            ActionTarget::OpenInstruction(instruction.id.clone()),
+            format!("Open instruction {}", instruction.name),
+            subtitle,
+            "instruction",
+        ));
+        actions.push(action(
+            ActionTarget::ExportInstruction(instruction.id.clone()),
+            format!("Export instruction {}", instruction.name),
+            subtitle,
+            "instruction",
+        ));
+    }
+
+    actions
+}
+
+/// Fuzzy-match `query` against the catalogue, best matches first.
+/// An empty query returns the catalogue as-is.
+pub fn search(actions: Vec<PaletteAction>, query: &str) -> Vec<PaletteAction> {
+    let query = query.trim();
+    if query.is_empty() {
+        return actions.into_iter().take(MAX_RESULTS).collect();
+    }
+
+    let mut matched: Vec<PaletteAction> = actions
+        .into_iter()
+        .filter_map(|mut action| {
+            action.score = fuzzy_score(query, &action.title)?;
+            Some(action)
+        })
+        .collect();
+
+    // Stable sort keeps catalogue order between equal scores
+    matched.sort_by_key(|a| std::cmp::Reverse(a.score));
+    matched.truncate(MAX_RESULTS);
+    matched
+}
+
+/// Score `candidate` as a case-insensitive subsequence match of `query`.
+/// Consecutive characters and matches at word starts score higher; `None`
+/// means not every query character was found in order.
+fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
+    let candidate: Vec<char> = candidate.chars().flat_map(|c| c.to_lowercase()).collect();
+    let mut score = 0i64;
+    let mut position = 0usize;
+    let mut previous_match: Option<usize> = None;
+
+    for q in query.chars().flat_map(|c| c.to_lowercase()) {
+        if q.is_whitespace() {
+            continue;
+        }
+        let found = (position..candidate.len()).find(|&i| candidate[i] == q)?;
+
+        score += 1;
+        if previous_match.is_some_and(|prev| prev + 1 == found) {
+            score += 5;
+        }
+        if found == 0 || !candidate[found - 1].is_alphanumeric() {
+            score += 8;
+        }
+        // Penalise gaps so tighter matches win
+        score -= (found - position).min(10) as i64;
+
+        previous_match = Some(found);
+        position = found + 1;
+    }
+
+    Some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_action_ids_round_trip() {
+        for target in [
+            ActionTarget::ApplyAgent("default".to_string()),
+            ActionTarget::ExportInstruction("code-style".to_string()),
+            ActionTarget::CreateBackup,
+        ] {
+            assert_eq!(ActionTarget::parse(&target.id()).unwrap(), target);
+        }
+        assert!(ActionTarget::parse("agent.apply:").is_err());
+    }
+
+    #[test]
+    fn test_fuzzy_score_prefers_word_starts() {
+        assert!(fuzzy_score("xyz", "Apply agent Default").is_none());
+        let tight = fuzzy_score("apdef", "Apply agent Default").unwrap();
+        let loose = fuzzy_score("apdef", "Open agent Paddefoo").unwrap();
+        assert!(tight > loose);
+    }
+}
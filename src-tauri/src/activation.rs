@@ -0,0 +1,218 @@
+//! Scheduled and profile-switched instructions
+//! An enabled instruction can carry activation rules: a date window, the
+//! weekdays and the local hours it is served on, and the profiles it is for
+//! (say `onboarding`, switched on in settings for a new hire's first weeks).
+//! The commands that serve prompts and the MCP server both treat an
+//! instruction whose rules don't hold right now as disabled, so nobody has
+//! to toggle it by hand.
+
+use crate::models::{Instruction, InstructionActivation};
+use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime};
+
+/// Weekday names as stored, Monday first
+pub const WEEKDAYS: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+
+/// Tidy activation rules before they are saved; `None` when none are set
+pub fn normalize(
+    activation: &InstructionActivation,
+) -> Result<Option<InstructionActivation>, String> {
+    let date = |value: &Option<String>, field: &str| -> Result<Option<String>, String> {
+        let Some(value) = value.as_deref().map(str::trim).filter(|v| !v.is_empty()) else {
+            return Ok(None);
+        };
+        NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .map(|date| Some(date.to_string()))
+            .map_err(|_| {
+                format!(
+                    "{} must be a date such as 2026-01-31, not '{}'",
+                    field, value
+                )
+            })
+    };
+    let starts_on = date(&activation.starts_on, "starts_on")?;
+    let ends_on = date(&activation.ends_on, "ends_on")?;
+    if let (Some(starts), Some(ends)) = (&starts_on, &ends_on) {
+        if starts > ends {
+            return Err(format!("starts_on {} is after ends_on {}", starts, ends));
+        }
+    }
+
+    let mut weekdays = Vec::new();
+    for day in &activation.weekdays {
+        let day = day.trim().to_lowercase();
+        let Some(name) = WEEKDAYS.iter().find(|name| day.starts_with(*name)) else {
+            return Err(format!("Unknown weekday '{}'; use mon to sun", day));
+        };
+        if !weekdays.contains(&name.to_string()) {
+            weekdays.push(name.to_string());
+        }
+    }
+    // Every day is the same as no weekday rule
+    if weekdays.len() == WEEKDAYS.len() {
+        weekdays.clear();
+    }
+
+    let hours = match activation.hours.as_deref().map(str::trim) {
+        None | Some("") => None,
+        Some(hours) => {
+            let (start, end) = hour_window(hours)?;
+            Some(format!("{}-{}", start.format("%H:%M"), end.format("%H:%M")))
+        }
+    };
+
+    let mut profiles: Vec<String> = Vec::new();
+    for profile in &activation.profiles {
+        let profile = profile.trim().to_lowercase();
+        if !profile.is_empty() && !profiles.contains(&profile) {
+            profiles.push(profile);
+        }
+    }
+
+    let normalized = InstructionActivation {
+        starts_on,
+        ends_on,
+        weekdays,
+        hours,
+        profiles,
+    };
+    Ok((normalized != InstructionActivation::default()).then_some(normalized))
+}
+
+/// Whether `activation` lets an instruction be served at local time `now`
+/// with `profile` active
+pub fn is_active(
+    activation: &InstructionActivation,
+    now: NaiveDateTime,
+    profile: Option<&str>,
+) -> bool {
+    let today = now.date();
+    let date = |value: &Option<String>| {
+        value
+            .as_deref()
+            .and_then(|v| NaiveDate::parse_from_str(v, "%Y-%m-%d").ok())
+    };
+    if date(&activation.starts_on).is_some_and(|starts| today < starts)
+        || date(&activation.ends_on).is_some_and(|ends| today > ends)
+    {
+        return false;
+    }
+
+    let weekday = WEEKDAYS[today.weekday().num_days_from_monday() as usize];
+    if !activation.weekdays.is_empty() && !activation.weekdays.iter().any(|d| d == weekday) {
+        return false;
+    }
+
+    if let Some(Ok((start, end))) = activation.hours.as_deref().map(hour_window) {
+        let time = now.time();
+        let inside = if start <= end {
+            start <= time && time < end
+        } else {
+            // Wraps past midnight, e.g. 22:00-06:00
+            time >= start || time < end
+        };
+        if !inside {
+            return false;
+        }
+    }
+
+    activation.profiles.is_empty()
+        || profile.is_some_and(|profile| {
+            activation
+                .profiles
+                .iter()
+                .any(|p| p.eq_ignore_ascii_case(profile.trim()))
+        })
+}
+
+/// Disable the instructions whose activation rules don't hold right now
+pub fn apply(instructions: &mut [Instruction], now: NaiveDateTime, profile: Option<&str>) {
+    for instruction in instructions {
+        if let Some(activation) = &instruction.activation {
+            if !is_active(activation, now, profile) {
+                instruction.enabled = false;
+            }
+        }
+    }
+}
+
+fn hour_window(hours: &str) -> Result<(NaiveTime, NaiveTime), String> {
+    let invalid = || {
+        format!(
+            "hours must be a window such as 09:00-17:00, not '{}'",
+            hours
+        )
+    };
+    let (start, end) = hours.split_once('-').ok_or_else(invalid)?;
+    let time =
+        |value: &str| NaiveTime::parse_from_str(value.trim(), "%H:%M").map_err(|_| invalid());
+    let (start, end) = (time(start)?, time(end)?);
+    if start == end {
+        return Err(invalid());
+    }
+    Ok((start, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(text: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_rules_must_all_hold() {
+        let activation = normalize(&InstructionActivation {
+            starts_on: Some("2026-10-05".to_string()),
+            ends_on: Some(" 2026-10-30".to_string()),
+            weekdays: vec!["Monday".to_string(), "tue".to_string(), "mon".to_string()],
+            hours: Some("9:00 - 17:30".to_string()),
+            profiles: vec!["Onboarding".to_string()],
+        })
+        .unwrap()
+        .unwrap();
+        assert_eq!(activation.weekdays, ["mon", "tue"]);
+        assert_eq!(activation.hours.as_deref(), Some("09:00-17:30"));
+
+        let onboarding = Some("onboarding");
+        // 2026-10-12 is a Monday
+        assert!(is_active(&activation, at("2026-10-12 10:00"), onboarding));
+        assert!(!is_active(&activation, at("2026-10-12 10:00"), None));
+        assert!(!is_active(&activation, at("2026-10-12 18:00"), onboarding));
+        assert!(!is_active(&activation, at("2026-10-14 10:00"), onboarding));
+        assert!(!is_active(&activation, at("2026-11-02 10:00"), onboarding));
+
+        let night = InstructionActivation {
+            hours: Some("22:00-06:00".to_string()),
+            ..InstructionActivation::default()
+        };
+        assert!(is_active(&night, at("2026-10-12 23:30"), None));
+        assert!(!is_active(&night, at("2026-10-12 12:00"), None));
+
+        let mut instructions = vec![
+            Instruction {
+                activation: Some(activation),
+                ..Instruction::default()
+            },
+            Instruction::default(),
+        ];
+        apply(&mut instructions, at("2026-10-12 10:00"), None);
+        assert_eq!(
+            (instructions[0].enabled, instructions[1].enabled),
+            (false, true)
+        );
+
+        assert_eq!(normalize(&InstructionActivation::default()), Ok(None));
+        assert!(normalize(&InstructionActivation {
+            weekdays: vec!["someday".to_string()],
+            ..InstructionActivation::default()
+        })
+        .is_err());
+        assert!(normalize(&InstructionActivation {
+            starts_on: Some("2026-12-01".to_string()),
+            ends_on: Some("2026-01-01".to_string()),
+            ..InstructionActivation::default()
+        })
+        .is_err());
+    }
+}
@@ -0,0 +1,682 @@
+//! Prompt assembly shared by the Tauri commands and the MCP server
+
+use crate::categories;
+use crate::models::{
+    Agent, Instruction, InstructionOrder, PromptSection, PromptSectionKind, Skill, SkillDefinition,
+    TraceEntry, TraceReason,
+};
+use crate::resolve::{resolve_entity, Resolvable};
+use crate::scope;
+
+/// Formatting options and context shared by every assembled prompt
+#[derive(Debug, Clone, Default)]
+pub struct AssemblyOptions {
+    /// Number sections and entries ("3.", "3.2") and tag each instruction
+    /// with a `{#instruction-<id>}` anchor so citations trace back to the library
+    pub numbered: bool,
+    /// Git branch being worked on; branch-scoped instructions are only
+    /// assembled when it matches their pattern
+    pub branch: Option<String>,
+}
+
+impl AssemblyOptions {
+    /// Whether an instruction applies in this context
+    fn in_scope(&self, instruction: &Instruction) -> bool {
+        scope::in_branch_scope(instruction, self.branch.as_deref())
+    }
+}
+
+/// Tracks section and entry numbers while a prompt is built
+struct Outline {
+    numbered: bool,
+    section: usize,
+    entry: usize,
+}
+
+impl Outline {
+    fn new(options: &AssemblyOptions) -> Self {
+        Self {
+            numbered: options.numbered,
+            section: 0,
+            entry: 0,
+        }
+    }
+
+    /// Title for the next `##` section
+    fn section(&mut self, title: &str) -> String {
+        self.section += 1;
+        self.entry = 0;
+        if self.numbered {
+            format!("{}. {}", self.section, title)
+        } else {
+            title.to_string()
+        }
+    }
+
+    /// Title for the next `###` entry in the current section
+    fn entry(&mut self, title: &str) -> String {
+        self.entry += 1;
+        if self.numbered {
+            format!("{}.{} {}", self.section, self.entry, title)
+        } else {
+            title.to_string()
+        }
+    }
+
+    /// Entry title for an instruction, with its anchor when numbering
+    fn instruction(&mut self, title: &str, instruction: &Instruction) -> String {
+        let title = self.entry(title);
+        if self.numbered {
+            format!("{} {{#instruction-{}}}", title, instruction.id)
+        } else {
+            title
+        }
+    }
+}
+
+/// Enabled, in-scope instructions attached to an agent, highest priority
+/// first unless the agent keeps them in the order they are attached in
+pub fn attached_instructions<'a>(
+    agent: &Agent,
+    instructions: &'a [Instruction],
+    options: &AssemblyOptions,
+) -> Vec<&'a Instruction> {
+    let mut attached: Vec<&Instruction> = Vec::new();
+    for id in &agent.instructions {
+        let found = instructions
+            .iter()
+            .find(|i| i.id == *id && i.enabled && options.in_scope(i));
+        if let Some(instruction) = found.filter(|i| !attached.iter().any(|a| a.id == i.id)) {
+            attached.push(instruction);
+        }
+    }
+    if agent.instruction_order == InstructionOrder::Priority {
+        by_priority(&mut attached);
+    }
+    attached
+}
+
+/// Sort highest priority first, keeping the order of equal priorities
+fn by_priority(instructions: &mut [&Instruction]) {
+    instructions.sort_by_key(|i| std::cmp::Reverse(i.priority));
+}
+
+/// The system prompt produced by applying an agent in the app: the agent's
+/// prompt followed by its personality, attached skills and attached instructions
+pub fn agent_prompt(
+    agent: &Agent,
+    skills: &[Skill],
+    instructions: &[Instruction],
+    options: &AssemblyOptions,
+) -> String {
+    let mut outline = Outline::new(options);
+    let mut full_prompt = agent.system_prompt.clone();
+
+    // Add personality context
+    full_prompt.push_str(&format!(
+        "\n\n## {}\n- Tone: {}\n- Verbosity: {}\n- Traits: {}",
+        outline.section("Personality"),
+        agent.personality.tone,
+        agent.personality.verbosity,
+        agent.personality.traits.join(", ")
+    ));
+
+    // Add attached skills
+    let agent_skills: Vec<_> = skills
+        .iter()
+        .filter(|s| agent.skills.contains(&s.id) && s.enabled)
+        .collect();
+
+    if !agent_skills.is_empty() {
+        full_prompt.push_str(&format!("\n\n## {}\n", outline.section("Available Skills")));
+        for skill in agent_skills {
+            full_prompt.push_str(&format!(
+                "\n### {}\n{}\n",
+                outline.entry(&skill.name),
+                skill.description
+            ));
+            if let SkillDefinition::Prompt { template, .. } = &skill.definition {
+                full_prompt.push_str(&format!("Template: {}\n", template));
+            }
+        }
+    }
+
+    // Add attached instructions
+    let agent_instructions = attached_instructions(agent, instructions, options);
+    if !agent_instructions.is_empty() {
+        full_prompt.push_str(&format!("\n\n## {}\n", outline.section("Instructions")));
+        for instruction in agent_instructions {
+            // Instructions are untitled here unless numbering needs somewhere to anchor
+            if outline.numbered {
+                full_prompt.push_str(&format!(
+                    "\n### {}",
+                    outline.instruction(&instruction.name, instruction)
+                ));
+            }
+            full_prompt.push_str(&format!("\n{}\n", instruction.content));
+        }
+    }
+
+    full_prompt
+}
+
+/// Rough token count for prompt budgeting: about four characters per token
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+/// Collects the sections of an assembled prompt. A `##` heading is held back
+/// and rendered with the section that follows it.
+#[derive(Default)]
+struct Sections {
+    sections: Vec<PromptSection>,
+    heading: String,
+}
+
+impl Sections {
+    fn heading(&mut self, text: String) {
+        self.heading.push_str(&text);
+    }
+
+    fn push(
+        &mut self,
+        kind: PromptSectionKind,
+        title: &str,
+        entity_id: Option<&str>,
+        text: String,
+    ) {
+        let text = std::mem::take(&mut self.heading) + &text;
+        self.sections.push(PromptSection {
+            kind,
+            title: title.to_string(),
+            entity_id: entity_id.map(str::to_string),
+            characters: text.chars().count(),
+            tokens: estimate_tokens(&text),
+            text,
+        });
+    }
+
+    fn finish(mut self) -> Vec<PromptSection> {
+        // A heading with nothing under it still belongs to the prompt
+        if !self.heading.is_empty() {
+            let heading = std::mem::take(&mut self.heading);
+            match self.sections.last_mut() {
+                Some(last) => {
+                    last.text.push_str(&heading);
+                    last.characters = last.text.chars().count();
+                    last.tokens = estimate_tokens(&last.text);
+                }
+                None => self.push(PromptSectionKind::Header, "", None, heading),
+            }
+        }
+        self.sections
+    }
+}
+
+/// The markdown briefing returned by the MCP `apply_agent` tool: agent header,
+/// system prompt, attached skills and instructions, then every other enabled
+/// instruction as global guidance unless the agent leaves those out
+pub fn agent_configuration(
+    agent: &Agent,
+    skills: &[Skill],
+    instructions: &[Instruction],
+    options: &AssemblyOptions,
+) -> String {
+    agent_configuration_sections(agent, skills, instructions, options)
+        .into_iter()
+        .map(|section| section.text)
+        .collect()
+}
+
+/// `agent_configuration` split into its header, system prompt, skill and
+/// instruction sections
+pub fn agent_configuration_sections(
+    agent: &Agent,
+    skills: &[Skill],
+    instructions: &[Instruction],
+    options: &AssemblyOptions,
+) -> Vec<PromptSection> {
+    let mut outline = Outline::new(options);
+    let mut sections = Sections::default();
+
+    // Agent header
+    let mut header = String::from("# Agent Configuration\n\n");
+    header.push_str(&format!(
+        "**Agent:** {} {}\n\n",
+        agent.avatar_emoji, agent.name
+    ));
+    header.push_str(&format!(
+        "**Tone:** {} | **Verbosity:** {}\n\n",
+        agent.personality.tone, agent.personality.verbosity
+    ));
+
+    if !agent.personality.traits.is_empty() {
+        header.push_str(&format!(
+            "**Traits:** {}\n\n",
+            agent.personality.traits.join(", ")
+        ));
+    }
+    sections.push(PromptSectionKind::Header, &agent.name, None, header);
+
+    // Add agent's system prompt
+    let title = outline.section("System Prompt");
+    sections.push(
+        PromptSectionKind::SystemPrompt,
+        "System Prompt",
+        None,
+        format!("## {}\n\n{}\n\n", title, agent.system_prompt),
+    );
+
+    // Add attached skills
+    if !agent.skills.is_empty() {
+        sections.heading(format!("## {}\n\n", outline.section("Attached Skills")));
+        for skill_id in &agent.skills {
+            if let Some(skill) = skills.iter().find(|s| s.id == *skill_id && s.enabled) {
+                let mut text = format!(
+                    "### {}\n",
+                    outline.entry(&format!("{} {}", skill.icon_emoji, skill.name))
+                );
+                if let SkillDefinition::Prompt { template, .. } = &skill.definition {
+                    text.push_str(template);
+                    text.push_str("\n\n");
+                }
+                sections.push(PromptSectionKind::Skill, &skill.name, Some(&skill.id), text);
+            }
+        }
+    }
+
+    // Add attached instructions
+    if !agent.instructions.is_empty() {
+        sections.heading(format!("## {}\n\n", outline.section("Instructions")));
+        for instruction in attached_instructions(agent, instructions, options) {
+            let title = format!("{} {}", instruction.icon_emoji, instruction.name);
+            sections.push(
+                PromptSectionKind::Instruction,
+                &instruction.name,
+                Some(&instruction.id),
+                format!(
+                    "### {}\n{}\n\n",
+                    outline.instruction(&title, instruction),
+                    instruction.content
+                ),
+            );
+        }
+    }
+
+    // Add all enabled global instructions, highest priority first, unless
+    // the agent leaves them out
+    let mut global_instructions: Vec<_> = instructions
+        .iter()
+        .filter(|i| i.enabled && !agent.instructions.contains(&i.id) && options.in_scope(i))
+        .collect();
+    if !agent.include_global_instructions {
+        global_instructions.clear();
+    }
+    by_priority(&mut global_instructions);
+
+    if !global_instructions.is_empty() {
+        sections.heading(format!("## {}\n\n", outline.section("Global Instructions")));
+        for instruction in global_instructions {
+            let title = format!(
+                "{} {} ({})",
+                instruction.icon_emoji,
+                instruction.name,
+                categories::category_path(instruction)
+            );
+            sections.push(
+                PromptSectionKind::GlobalInstruction,
+                &instruction.name,
+                Some(&instruction.id),
+                format!(
+                    "### {}\n{}\n\n",
+                    outline.instruction(&title, instruction),
+                    instruction.content
+                ),
+            );
+        }
+    }
+
+    sections.finish()
+}
+
+/// Explain every skill and instruction decision made by `agent_prompt`
+pub fn trace_agent_prompt(
+    agent: &Agent,
+    skills: &[Skill],
+    instructions: &[Instruction],
+    options: &AssemblyOptions,
+) -> Vec<TraceEntry> {
+    let mut trace = trace_library(skills, &agent.skills, "Available Skills", None, options);
+    trace.extend(trace_library(
+        instructions,
+        &agent.instructions,
+        "Instructions",
+        None,
+        options,
+    ));
+    trace
+}
+
+/// Explain every skill and instruction decision made by `agent_configuration`,
+/// where unattached instructions are still included as global guidance
+/// unless the agent leaves those out
+pub fn trace_agent_configuration(
+    agent: &Agent,
+    skills: &[Skill],
+    instructions: &[Instruction],
+    options: &AssemblyOptions,
+) -> Vec<TraceEntry> {
+    let mut trace = trace_library(skills, &agent.skills, "Attached Skills", None, options);
+    trace.extend(trace_library(
+        instructions,
+        &agent.instructions,
+        "Instructions",
+        agent
+            .include_global_instructions
+            .then_some("Global Instructions"),
+        options,
+    ));
+    trace
+}
+
+/// Library entities that can be switched off or scoped to a context
+trait Toggleable: Resolvable {
+    fn enabled(&self) -> bool;
+
+    fn in_scope(&self, _options: &AssemblyOptions) -> bool {
+        true
+    }
+}
+
+impl Toggleable for Skill {
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+}
+
+impl Toggleable for Instruction {
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn in_scope(&self, options: &AssemblyOptions) -> bool {
+        options.in_scope(self)
+    }
+}
+
+/// Trace attached entries in attachment order, then the rest of the library.
+/// With `global_section`, enabled unattached entries count as included there.
+fn trace_library<T: Toggleable>(
+    items: &[T],
+    attached: &[String],
+    attached_section: &str,
+    global_section: Option<&str>,
+    options: &AssemblyOptions,
+) -> Vec<TraceEntry> {
+    let entry = |id: &str, name: &str, section: Option<&str>, reason| TraceEntry {
+        entity_type: T::KIND.to_string(),
+        entity_id: id.to_string(),
+        entity_name: name.to_string(),
+        included: reason == TraceReason::Included,
+        section: section.map(str::to_string),
+        reason,
+    };
+
+    let mut trace = Vec::new();
+    for id in attached {
+        trace.push(match items.iter().find(|item| item.id() == id) {
+            None => entry(id, "", None, TraceReason::Missing),
+            Some(item) if !item.enabled() => entry(id, item.name(), None, TraceReason::Disabled),
+            Some(item) if !item.in_scope(options) => {
+                entry(id, item.name(), None, TraceReason::OutOfScope)
+            }
+            Some(item) => entry(
+                id,
+                item.name(),
+                Some(attached_section),
+                TraceReason::Included,
+            ),
+        });
+    }
+
+    for item in items
+        .iter()
+        .filter(|item| !attached.iter().any(|id| id == item.id()))
+    {
+        trace.push(match global_section {
+            Some(_) if !item.enabled() => {
+                entry(item.id(), item.name(), None, TraceReason::Disabled)
+            }
+            Some(_) if !item.in_scope(options) => {
+                entry(item.id(), item.name(), None, TraceReason::OutOfScope)
+            }
+            Some(section) => entry(item.id(), item.name(), Some(section), TraceReason::Included),
+            None => entry(item.id(), item.name(), None, TraceReason::NotAttached),
+        });
+    }
+
+    trace
+}
+
+/// Assemble a one-off prompt from inline text plus skills and instructions
+/// picked by id or name, without saving an agent.
+/// Explicitly requested entries are included even if they are disabled or
+/// scoped to another branch.
+pub fn compose_prompt(
+    skills: &[Skill],
+    instructions: &[Instruction],
+    skill_refs: &[String],
+    instruction_refs: &[String],
+    text: Option<&str>,
+    options: &AssemblyOptions,
+) -> Result<String, String> {
+    let picked_skills = resolve_all(skills, skill_refs)?;
+    let picked_instructions = resolve_all(instructions, instruction_refs)?;
+    let text = text.map(str::trim).filter(|t| !t.is_empty());
+
+    if text.is_none() && picked_skills.is_empty() && picked_instructions.is_empty() {
+        return Err("Nothing to compose: provide text, skills or instructions".to_string());
+    }
+
+    let mut outline = Outline::new(options);
+    let mut prompt = String::new();
+
+    if let Some(text) = text {
+        prompt.push_str(text);
+        prompt.push_str("\n\n");
+    }
+
+    if !picked_skills.is_empty() {
+        prompt.push_str(&format!("## {}\n\n", outline.section("Skills")));
+        for skill in picked_skills {
+            prompt.push_str(&format!(
+                "### {}\n",
+                outline.entry(&format!("{} {}", skill.icon_emoji, skill.name))
+            ));
+            match &skill.definition {
+                SkillDefinition::Prompt { template, .. } => prompt.push_str(template),
+                _ => prompt.push_str(&skill.description),
+            }
+            prompt.push_str("\n\n");
+        }
+    }
+
+    if !picked_instructions.is_empty() {
+        prompt.push_str(&format!("## {}\n\n", outline.section("Instructions")));
+        for instruction in picked_instructions {
+            let title = format!("{} {}", instruction.icon_emoji, instruction.name);
+            prompt.push_str(&format!(
+                "### {}\n",
+                outline.instruction(&title, instruction)
+            ));
+            prompt.push_str(&instruction.content);
+            prompt.push_str("\n\n");
+        }
+    }
+
+    Ok(prompt.trim_end().to_string())
+}
+
+/// Shortest instruction body worth serving cut down rather than left out
+const MIN_TRUNCATED_CHARS: usize = 80;
+
+/// Enabled instructions within roughly `max_tokens`, highest priority first.
+/// Instructions are taken whole while they fit; the first that doesn't is cut
+/// at a word boundary if enough room is left, and the rest are counted in a
+/// closing note.
+pub fn instruction_digest(instructions: &[Instruction], max_tokens: usize) -> String {
+    let mut enabled: Vec<_> = instructions.iter().filter(|i| i.enabled).collect();
+    if enabled.is_empty() {
+        return "No instructions enabled.".to_string();
+    }
+    enabled.sort_by_key(|i| std::cmp::Reverse(i.priority));
+
+    let omitted_note = |count: usize| {
+        format!(
+            "_{} lower-priority instruction(s) omitted to fit {} tokens._\n",
+            count, max_tokens
+        )
+    };
+    let reserve = estimate_tokens(&omitted_note(enabled.len()));
+    let mut digest = String::from("# Prompt Forge Instructions (compact)\n\n");
+    let mut used = estimate_tokens(&digest);
+    let mut served = 0;
+
+    for instruction in &enabled {
+        let heading = format!(
+            "## {} (Priority: {})\n",
+            instruction.name, instruction.priority
+        );
+        let entry = format!("{}{}\n\n", heading, instruction.content.trim());
+        // The note is only needed if something is left out after this entry
+        let needed = if served + 1 == enabled.len() {
+            0
+        } else {
+            reserve
+        };
+        if used + estimate_tokens(&entry) + needed <= max_tokens {
+            used += estimate_tokens(&entry);
+            digest.push_str(&entry);
+            served += 1;
+            continue;
+        }
+
+        let room = max_tokens.saturating_sub(used + reserve) * 4;
+        let body_chars = room.saturating_sub(heading.chars().count() + 3);
+        if body_chars >= MIN_TRUNCATED_CHARS {
+            digest.push_str(&heading);
+            digest.push_str(&truncate_at_word(instruction.content.trim(), body_chars));
+            digest.push_str("…\n\n");
+            served += 1;
+        }
+        break;
+    }
+
+    if served < enabled.len() {
+        digest.push_str(&omitted_note(enabled.len() - served));
+    }
+    digest.trim_end().to_string()
+}
+
+/// At most `max_chars` of `text`, cut after the last whole word that fits
+pub(crate) fn truncate_at_word(text: &str, max_chars: usize) -> String {
+    let cut: String = text.chars().take(max_chars).collect();
+    match cut.rfind(char::is_whitespace) {
+        Some(end) if cut.chars().count() < text.chars().count() => {
+            cut[..end].trim_end().to_string()
+        }
+        _ => cut,
+    }
+}
+
+/// Resolve every reference, keeping first-seen order and dropping duplicates
+fn resolve_all<'a, T: Resolvable>(items: &'a [T], refs: &[String]) -> Result<Vec<&'a T>, String> {
+    let mut resolved: Vec<&T> = Vec::new();
+    for reference in refs {
+        let item = resolve_entity(items, reference)?;
+        if !resolved.iter().any(|r| r.id() == item.id()) {
+            resolved.push(item);
+        }
+    }
+    Ok(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instructions_are_assembled_by_priority_unless_kept_in_order() {
+        let instruction = |id: &str, priority: u8| Instruction {
+            id: id.to_string(),
+            name: id.to_string(),
+            content: format!("{} guidance", id),
+            priority,
+            ..Instruction::default()
+        };
+        let instructions = vec![
+            instruction("global-low", 2),
+            instruction("style", 3),
+            instruction("security", 9),
+            instruction("tests", 3),
+            instruction("global-high", 8),
+        ];
+        let mut agent = Agent {
+            instructions: vec![
+                "style".to_string(),
+                "tests".to_string(),
+                "security".to_string(),
+            ],
+            ..Agent::default()
+        };
+        let options = AssemblyOptions::default();
+        let order = |agent: &Agent| -> Vec<String> {
+            agent_configuration_sections(agent, &[], &instructions, &options)
+                .into_iter()
+                .filter_map(|section| section.entity_id)
+                .collect()
+        };
+
+        assert_eq!(
+            order(&agent),
+            ["security", "style", "tests", "global-high", "global-low"]
+        );
+        agent.instruction_order = InstructionOrder::Attached;
+        assert_eq!(
+            order(&agent),
+            ["style", "tests", "security", "global-high", "global-low"]
+        );
+        let prompt = agent_prompt(&agent, &[], &instructions, &options);
+        assert!(prompt.find("style guidance") < prompt.find("security guidance"));
+    }
+
+    #[test]
+    fn test_agents_can_leave_out_global_instructions() {
+        let instructions = vec![
+            Instruction {
+                id: "attached".to_string(),
+                ..Instruction::default()
+            },
+            Instruction {
+                id: "global".to_string(),
+                ..Instruction::default()
+            },
+        ];
+        let agent = Agent {
+            instructions: vec!["attached".to_string()],
+            include_global_instructions: false,
+            ..Agent::default()
+        };
+        let options = AssemblyOptions::default();
+
+        let sections = agent_configuration_sections(&agent, &[], &instructions, &options);
+        let ids: Vec<_> = sections
+            .iter()
+            .filter_map(|s| s.entity_id.as_deref())
+            .collect();
+        assert_eq!(ids, ["attached"]);
+        let trace = trace_agent_configuration(&agent, &[], &instructions, &options);
+        let global = trace.iter().find(|t| t.entity_id == "global").unwrap();
+        assert_eq!(global.reason, TraceReason::NotAttached);
+    }
+}
@@ -0,0 +1,296 @@
+//! Database backups.
+//! A backup is a standalone SQLite snapshot (`<name>.db`) plus a JSON manifest
+//! (`<name>.manifest.json`) recording its digest and entity counts, so a
+//! snapshot can be checked for corruption long before it is needed.
+
+use crate::db::Database;
+use crate::library_workspaces;
+use crate::models::{BackupManifest, BackupSchedule, BackupVerification};
+use chrono::{Duration, Utc};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const MANIFEST_SUFFIX: &str = ".manifest.json";
+
+/// Snapshot the live database into `dir` and write its manifest alongside it
+pub fn create_backup(db: &Database, dir: &Path) -> Result<BackupManifest, String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create backup directory: {}", e))?;
+
+    let file_name = format!("promptforge-{}.db", Utc::now().format("%Y%m%d-%H%M%S"));
+    let snapshot_path = dir.join(&file_name);
+
+    db.vacuum_into(&snapshot_path)
+        .map_err(|e| format!("Failed to write snapshot: {}", e))?;
+
+    let manifest = BackupManifest {
+        version: "1.0".to_string(),
+        created_at: Utc::now(),
+        file_name,
+        size_bytes: file_size(&snapshot_path)?,
+        sha256: file_digest(&snapshot_path)?,
+        agents: db.get_all_agents().map(|v| v.len()).unwrap_or(0),
+        skills: db.get_all_skills().map(|v| v.len()).unwrap_or(0),
+        instructions: db.get_all_instructions().map(|v| v.len()).unwrap_or(0),
+    };
+
+    let manifest_json = serde_json::to_string_pretty(&manifest).unwrap();
+    fs::write(manifest_path(&snapshot_path), manifest_json)
+        .map_err(|e| format!("Failed to write backup manifest: {}", e))?;
+
+    Ok(manifest)
+}
+
+/// Where backups go unless a directory is given: `backups/` next to the
+/// database, in a folder of their own for a workspace other than the default
+pub fn default_dir(db_path: &Path) -> PathBuf {
+    let dir = db_path
+        .parent()
+        .map(|p| p.join("backups"))
+        .unwrap_or_else(|| PathBuf::from("backups"));
+    match library_workspaces::named_workspace(db_path) {
+        Some(workspace) => dir.join(workspace),
+        None => dir,
+    }
+}
+
+/// Manifests of the backups in `dir`, newest first
+pub fn list_backups(dir: &Path) -> Vec<BackupManifest> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut manifests: Vec<BackupManifest> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name().to_string_lossy().ends_with(MANIFEST_SUFFIX))
+        .filter_map(|entry| fs::read_to_string(entry.path()).ok())
+        .filter_map(|json| serde_json::from_str(&json).ok())
+        .collect();
+    manifests.sort_by_key(|m| std::cmp::Reverse(m.created_at));
+    manifests
+}
+
+/// Take a backup into `dir` if the newest one there is older than the
+/// schedule's interval, then delete all but the newest `keep`. Returns the
+/// new backup's manifest, or `None` when none was due.
+pub fn run_schedule(
+    db: &Database,
+    schedule: &BackupSchedule,
+    dir: &Path,
+) -> Result<Option<BackupManifest>, String> {
+    if schedule.interval_hours == 0 {
+        return Ok(None);
+    }
+    let interval = Duration::hours(i64::from(schedule.interval_hours));
+    if let Some(latest) = list_backups(dir).first() {
+        if Utc::now() - latest.created_at < interval {
+            return Ok(None);
+        }
+    }
+
+    let manifest = create_backup(db, dir)?;
+    for old in list_backups(dir).iter().skip(schedule.keep.max(1)) {
+        let snapshot = dir.join(&old.file_name);
+        fs::remove_file(manifest_path(&snapshot))
+            .and_then(|_| fs::remove_file(&snapshot))
+            .map_err(|e| format!("Failed to delete old backup {}: {}", old.file_name, e))?;
+    }
+    Ok(Some(manifest))
+}
+
+/// Check a snapshot against its manifest and test-restore it into an
+/// in-memory database. Accepts either the snapshot or its manifest path;
+/// `key` unlocks snapshots of an encrypted library.
+pub fn verify_backup(path: &Path, key: Option<&str>) -> BackupVerification {
+    let snapshot_path = snapshot_path(path);
+    let mut report = BackupVerification {
+        path: snapshot_path.to_string_lossy().to_string(),
+        manifest_found: false,
+        digest_matches: false,
+        integrity_ok: false,
+        restorable: false,
+        agents: 0,
+        skills: 0,
+        instructions: 0,
+        errors: Vec::new(),
+    };
+
+    if !snapshot_path.exists() {
+        report.errors.push("Snapshot file does not exist".to_string());
+        return report;
+    }
+
+    // 1. Digest check against the manifest
+    match fs::read_to_string(manifest_path(&snapshot_path)) {
+        Ok(json) => match serde_json::from_str::<BackupManifest>(&json) {
+            Ok(manifest) => {
+                report.manifest_found = true;
+                match file_digest(&snapshot_path) {
+                    Ok(digest) if digest == manifest.sha256 => report.digest_matches = true,
+                    Ok(_) => report
+                        .errors
+                        .push("Snapshot digest does not match the manifest".to_string()),
+                    Err(e) => report.errors.push(e),
+                }
+            }
+            Err(e) => report.errors.push(format!("Manifest is unreadable: {}", e)),
+        },
+        Err(_) => report.errors.push("No manifest found next to snapshot".to_string()),
+    }
+
+    // 2. Test-restore into memory and read every entity back
+    let restored = match Database::restore_in_memory(&snapshot_path, key) {
+        Ok(db) => db,
+        Err(e) => {
+            report.errors.push(format!("Snapshot could not be restored: {}", e));
+            return report;
+        }
+    };
+
+    match restored.integrity_check() {
+        Ok(result) if result == "ok" => report.integrity_ok = true,
+        Ok(result) => report.errors.push(format!("Integrity check failed: {}", result)),
+        Err(e) => report.errors.push(format!("Integrity check failed: {}", e)),
+    }
+
+    if let Err(e) = restored.migrate() {
+        report.errors.push(format!("Snapshot schema could not be migrated: {}", e));
+        return report;
+    }
+
+    match restored.export_all() {
+        Ok(data) => {
+            report.agents = data.agents.len();
+            report.skills = data.skills.len();
+            report.instructions = data.instructions.len();
+            report.restorable = report.integrity_ok;
+        }
+        Err(e) => report.errors.push(format!("Snapshot data is unreadable: {}", e)),
+    }
+
+    report
+}
+
+fn snapshot_path(path: &Path) -> PathBuf {
+    let path_str = path.to_string_lossy();
+    match path_str.strip_suffix(MANIFEST_SUFFIX) {
+        Some(base) => PathBuf::from(format!("{}.db", base)),
+        None => path.to_path_buf(),
+    }
+}
+
+fn manifest_path(snapshot_path: &Path) -> PathBuf {
+    snapshot_path.with_extension(MANIFEST_SUFFIX.trim_start_matches('.'))
+}
+
+fn file_size(path: &Path) -> Result<u64, String> {
+    fs::metadata(path)
+        .map(|m| m.len())
+        .map_err(|e| format!("Failed to read snapshot: {}", e))
+}
+
+fn file_digest(path: &Path) -> Result<String, String> {
+    let bytes = fs::read(path).map_err(|e| format!("Failed to read snapshot: {}", e))?;
+    Ok(format!("{:x}", Sha256::digest(&bytes)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_default_data;
+
+    #[test]
+    fn test_backup_round_trip_and_corruption() {
+        let dir = std::env::temp_dir().join(format!("pf-backup-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let db = Database::open(dir.join("live.db")).unwrap();
+        db.migrate().unwrap();
+        init_default_data(&db).unwrap();
+
+        let manifest = create_backup(&db, &dir.join("backups")).unwrap();
+        let snapshot = dir.join("backups").join(&manifest.file_name);
+
+        let report = verify_backup(&snapshot, None);
+        assert!(report.digest_matches);
+        assert!(report.restorable, "{:?}", report.errors);
+        assert_eq!(report.agents, 1);
+
+        // Flip a byte in the middle of the file
+        let mut bytes = fs::read(&snapshot).unwrap();
+        let mid = bytes.len() / 2;
+        bytes[mid] ^= 0xff;
+        fs::write(&snapshot, bytes).unwrap();
+
+        let report = verify_backup(&snapshot, None);
+        assert!(!report.digest_matches);
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_encrypted_backups_are_verified_with_the_key() {
+        let dir = std::env::temp_dir().join(format!("pf-encrypted-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let db = Database::open_with_key(dir.join("live.db"), Some("secret")).unwrap();
+        db.migrate().unwrap();
+        init_default_data(&db).unwrap();
+
+        let manifest = create_backup(&db, &dir.join("backups")).unwrap();
+        let snapshot = dir.join("backups").join(&manifest.file_name);
+
+        let report = verify_backup(&snapshot, Some("secret"));
+        assert!(report.restorable, "{:?}", report.errors);
+        assert_eq!(report.agents, 1);
+        assert!(!verify_backup(&snapshot, None).restorable);
+        assert!(!verify_backup(&snapshot, Some("wrong")).restorable);
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_schedule_skips_fresh_backups_and_prunes_old_ones() {
+        let dir = std::env::temp_dir().join(format!("pf-schedule-{}", uuid::Uuid::new_v4()));
+        let db = Database::open(":memory:").unwrap();
+        db.migrate().unwrap();
+        let backups = dir.join("backups");
+
+        // Two stale backups from previous days
+        let manifest = create_backup(&db, &backups).unwrap();
+        for days in [2, 3] {
+            let mut old = manifest.clone();
+            old.created_at = Utc::now() - Duration::days(days);
+            old.file_name = format!("promptforge-old-{}.db", days);
+            let snapshot = backups.join(&old.file_name);
+            fs::copy(backups.join(&manifest.file_name), &snapshot).unwrap();
+            fs::write(
+                manifest_path(&snapshot),
+                serde_json::to_string(&old).unwrap(),
+            )
+            .unwrap();
+        }
+
+        let off = BackupSchedule::default();
+        assert!(run_schedule(&db, &off, &backups).unwrap().is_none());
+
+        let daily = BackupSchedule {
+            interval_hours: 24,
+            keep: 2,
+            dir: None,
+        };
+        assert!(run_schedule(&db, &daily, &backups).unwrap().is_none());
+
+        // Once the fresh backup is gone, one is due
+        let fresh = backups.join(&manifest.file_name);
+        fs::remove_file(manifest_path(&fresh)).unwrap();
+        fs::remove_file(&fresh).unwrap();
+        assert!(run_schedule(&db, &daily, &backups).unwrap().is_some());
+        let kept: Vec<String> = list_backups(&backups)
+            .into_iter()
+            .map(|m| m.file_name)
+            .collect();
+        assert_eq!(kept.len(), 2);
+        assert_eq!(kept[1], "promptforge-old-2.db");
+        assert!(!backups.join("promptforge-old-3.db").exists());
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
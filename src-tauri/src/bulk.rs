@@ -0,0 +1,157 @@
+//! Bulk edits to instructions and skills
+//! `bulk_update_instructions` and `bulk_update_skills` make one change to many
+//! items: switching them on or off, adding or removing tags, or refiling
+//! instructions under another category. Every item is changed in memory and
+//! then saved in a single transaction, so an unknown id, a locked item or a
+//! failed write leaves all of them as they were.
+
+use crate::categories;
+use crate::models::{BulkUpdate, Instruction, Skill};
+use chrono::Utc;
+
+/// `instructions` with `update` applied, keeping only the ones it changed
+pub fn update_instructions(
+    instructions: Vec<Instruction>,
+    update: &BulkUpdate,
+) -> Result<Vec<Instruction>, String> {
+    let category = match update.category.as_deref().map(str::trim) {
+        None | Some("") => None,
+        Some(path) => {
+            let top = path.split('/').next().unwrap_or_default();
+            if categories::try_parse_category(top).is_none() {
+                return Err(format!("Unknown category '{}'", top));
+            }
+            Some(categories::parse_category_path(path))
+        }
+    };
+
+    let now = Utc::now();
+    let mut changed = Vec::new();
+    for mut instruction in instructions {
+        let before = (
+            instruction.enabled,
+            instruction.tags.clone(),
+            instruction.category.clone(),
+            instruction.subcategory.clone(),
+        );
+        if let Some(enabled) = update.enabled {
+            instruction.enabled = enabled;
+        }
+        update_tags(&mut instruction.tags, update);
+        if let Some((category, subcategory)) = &category {
+            instruction.category = category.clone();
+            instruction.subcategory = subcategory.clone();
+        }
+        let after = (
+            instruction.enabled,
+            instruction.tags.clone(),
+            instruction.category.clone(),
+            instruction.subcategory.clone(),
+        );
+        if before != after {
+            instruction.updated_at = now;
+            changed.push(instruction);
+        }
+    }
+    Ok(changed)
+}
+
+/// `skills` with `update` applied, keeping only the ones it changed. Skills
+/// have neither tags nor a category, so only `enabled` applies.
+pub fn update_skills(skills: Vec<Skill>, update: &BulkUpdate) -> Result<Vec<Skill>, String> {
+    let has_tags = !update.add_tags.is_empty() || !update.remove_tags.is_empty();
+    if has_tags || update.category.is_some() {
+        return Err("Skills have no tags or category; only enabled can be changed".to_string());
+    }
+
+    let now = Utc::now();
+    Ok(skills
+        .into_iter()
+        .filter_map(|mut skill| {
+            let enabled = update.enabled.filter(|&enabled| enabled != skill.enabled)?;
+            skill.enabled = enabled;
+            skill.updated_at = now;
+            Some(skill)
+        })
+        .collect())
+}
+
+/// Remove, then add, tags; compared ignoring case and surrounding spaces
+fn update_tags(tags: &mut Vec<String>, update: &BulkUpdate) {
+    let same = |a: &str, b: &str| a.trim().eq_ignore_ascii_case(b.trim());
+    tags.retain(|tag| !update.remove_tags.iter().any(|removed| same(tag, removed)));
+    for tag in &update.add_tags {
+        let tag = tag.trim();
+        if !tag.is_empty() && !tags.iter().any(|existing| same(existing, tag)) {
+            tags.push(tag.to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::InstructionCategory;
+
+    #[test]
+    fn test_only_changed_items_are_returned() {
+        let instruction = |id: &str, tags: &[&str], enabled: bool| Instruction {
+            id: id.to_string(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            enabled,
+            ..Instruction::default()
+        };
+        let instructions = vec![
+            instruction("a", &["Legacy", "rust"], true),
+            instruction("b", &["rust"], false),
+            instruction("c", &["rust", "lint"], false),
+        ];
+
+        let update = BulkUpdate {
+            enabled: Some(false),
+            add_tags: vec![" lint".to_string()],
+            remove_tags: vec!["legacy".to_string()],
+            ..BulkUpdate::default()
+        };
+        let changed = update_instructions(instructions.clone(), &update).unwrap();
+        let changed: Vec<(&str, bool, Vec<String>)> = changed
+            .iter()
+            .map(|i| (i.id.as_str(), i.enabled, i.tags.clone()))
+            .collect();
+        assert_eq!(
+            changed,
+            [
+                ("a", false, vec!["rust".to_string(), "lint".to_string()]),
+                ("b", false, vec!["rust".to_string(), "lint".to_string()]),
+            ]
+        );
+
+        let refile = BulkUpdate {
+            category: Some("Code_Style/Rust Tips".to_string()),
+            ..BulkUpdate::default()
+        };
+        let moved = update_instructions(instructions, &refile).unwrap();
+        assert_eq!(moved.len(), 3);
+        assert_eq!(moved[0].category, InstructionCategory::CodeStyle);
+        assert_eq!(moved[0].subcategory.as_deref(), Some("rust_tips"));
+        let unknown = BulkUpdate {
+            category: Some("misc/rust".to_string()),
+            ..BulkUpdate::default()
+        };
+        assert!(update_instructions(Vec::new(), &unknown).is_err());
+
+        let enable = BulkUpdate {
+            enabled: Some(true),
+            ..BulkUpdate::default()
+        };
+        let skills = vec![
+            Skill::default(),
+            Skill {
+                enabled: false,
+                ..Skill::default()
+            },
+        ];
+        assert_eq!(update_skills(skills, &enable).unwrap().len(), 1);
+        assert!(update_skills(Vec::new(), &update).is_err());
+    }
+}
@@ -0,0 +1,111 @@
+//! Short-lived cache for data read from the database
+//! The MCP server reads the library through these instead of holding a
+//! snapshot from startup: a value is fetched on first use and fetched again
+//! once it is older than its TTL, so it is never more than a few seconds
+//! behind edits made in the app.
+
+use std::ops::{Deref, DerefMut};
+use std::time::{Duration, Instant};
+
+pub struct TtlCache<T> {
+    value: T,
+    fetched_at: Option<Instant>,
+    ttl: Duration,
+}
+
+impl<T: Default> TtlCache<T> {
+    /// An empty cache that fetches on first use
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            value: T::default(),
+            fetched_at: None,
+            ttl,
+        }
+    }
+}
+
+impl<T> TtlCache<T> {
+    pub fn is_fresh(&self) -> bool {
+        self.fetched_at
+            .is_some_and(|fetched| fetched.elapsed() < self.ttl)
+    }
+
+    /// Fetch a new value if the cached one is missing or expired, returning
+    /// whether it did. On error the previous value is kept and the next call
+    /// tries again.
+    pub fn refresh_with(
+        &mut self,
+        fetch: impl FnOnce() -> Result<T, String>,
+    ) -> Result<bool, String> {
+        if self.is_fresh() {
+            return Ok(false);
+        }
+        self.set(fetch()?);
+        Ok(true)
+    }
+
+    /// Replace the value and restart its TTL
+    pub fn set(&mut self, value: T) {
+        self.value = value;
+        self.fetched_at = Some(Instant::now());
+    }
+
+    /// Force the next `refresh_with` to fetch
+    pub fn expire(&mut self) {
+        self.fetched_at = None;
+    }
+}
+
+impl<T> Deref for TtlCache<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T> DerefMut for TtlCache<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fetches_only_when_stale() {
+        let mut cache: TtlCache<Vec<u32>> = TtlCache::new(Duration::from_secs(60));
+        let mut fetches = 0;
+
+        for _ in 0..3 {
+            cache
+                .refresh_with(|| {
+                    fetches += 1;
+                    Ok(vec![fetches])
+                })
+                .unwrap();
+        }
+        assert!(!cache.refresh_with(|| Ok(vec![9])).unwrap());
+        assert_eq!((fetches, cache.as_slice()), (1, &[1][..]));
+
+        cache.expire();
+        assert!(cache
+            .refresh_with(|| Err("database is locked".to_string()))
+            .is_err());
+        assert_eq!(cache.as_slice(), &[1]);
+        assert!(!cache.is_fresh());
+
+        assert!(cache.refresh_with(|| Ok(vec![2])).unwrap());
+        assert_eq!(cache.as_slice(), &[2]);
+    }
+
+    #[test]
+    fn test_zero_ttl_always_refetches() {
+        let mut cache: TtlCache<u32> = TtlCache::new(Duration::ZERO);
+        cache.refresh_with(|| Ok(1)).unwrap();
+        cache.refresh_with(|| Ok(2)).unwrap();
+        assert_eq!(*cache, 2);
+    }
+}
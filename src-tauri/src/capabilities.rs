@@ -0,0 +1,161 @@
+//! Agent capability matrix
+//! For every agent, how many of its enabled instructions fall in each
+//! category and how many of its enabled skills are of each type, plus the
+//! categories and skill types that no agent covers at all. Only what an agent
+//! has attached counts; pinned context is served to everyone and isn't
+//! anyone's capability.
+
+use crate::db::{category_to_string, skill_type_to_string};
+use crate::models::{
+    Agent, AgentCapabilities, CapabilityMatrix, Instruction, InstructionCategory, Skill, SkillType,
+};
+use crate::resolve::resolve_entity;
+
+/// Matrix columns, in display order. `Custom` holds whatever users file
+/// there, so it is never reported as a gap.
+const CATEGORIES: [InstructionCategory; 8] = [
+    InstructionCategory::General,
+    InstructionCategory::CodeStyle,
+    InstructionCategory::Communication,
+    InstructionCategory::Workflow,
+    InstructionCategory::Security,
+    InstructionCategory::Testing,
+    InstructionCategory::Documentation,
+    InstructionCategory::Custom,
+];
+
+const SKILL_TYPES: [SkillType; 3] = [SkillType::Prompt, SkillType::Tool, SkillType::Workflow];
+
+pub fn capability_matrix(
+    agents: &[Agent],
+    skills: &[Skill],
+    instructions: &[Instruction],
+) -> CapabilityMatrix {
+    let rows: Vec<AgentCapabilities> = agents
+        .iter()
+        .map(|agent| {
+            let attached: Vec<&Instruction> = instructions
+                .iter()
+                .filter(|i| i.enabled && agent.instructions.contains(&i.id))
+                .collect();
+            let mut used_skills: Vec<&Skill> = Vec::new();
+            for skill in agent
+                .skills
+                .iter()
+                .filter_map(|reference| resolve_entity(skills, reference).ok())
+                .filter(|s| s.enabled)
+            {
+                if !used_skills.iter().any(|s| s.id == skill.id) {
+                    used_skills.push(skill);
+                }
+            }
+
+            let categories: Vec<usize> = CATEGORIES
+                .iter()
+                .map(|category| attached.iter().filter(|i| &i.category == category).count())
+                .collect();
+            let skill_types: Vec<usize> = SKILL_TYPES
+                .iter()
+                .map(|kind| used_skills.iter().filter(|s| &s.skill_type == kind).count())
+                .collect();
+            let missing_categories = gaps(&categories);
+
+            AgentCapabilities {
+                agent_id: agent.id.clone(),
+                agent_name: agent.name.clone(),
+                categories,
+                skill_types,
+                missing_categories,
+            }
+        })
+        .collect();
+
+    let category_totals: Vec<usize> = (0..CATEGORIES.len())
+        .map(|column| rows.iter().map(|r| r.categories[column]).sum())
+        .collect();
+    let uncovered_skill_types = SKILL_TYPES
+        .iter()
+        .enumerate()
+        .filter(|(column, _)| rows.iter().all(|r| r.skill_types[*column] == 0))
+        .map(|(_, kind)| skill_type_to_string(kind).to_string())
+        .collect();
+
+    CapabilityMatrix {
+        categories: CATEGORIES
+            .iter()
+            .map(|c| category_to_string(c).to_string())
+            .collect(),
+        skill_types: SKILL_TYPES
+            .iter()
+            .map(|t| skill_type_to_string(t).to_string())
+            .collect(),
+        uncovered_categories: gaps(&category_totals),
+        uncovered_skill_types,
+        agents: rows,
+    }
+}
+
+/// Categories with no instructions in a row of counts, leaving out `Custom`
+fn gaps(counts: &[usize]) -> Vec<String> {
+    CATEGORIES
+        .iter()
+        .zip(counts)
+        .filter(|(category, count)| **count == 0 && **category != InstructionCategory::Custom)
+        .map(|(category, _)| category_to_string(category).to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{init_default_data, Database};
+
+    #[test]
+    fn test_matrix_counts_attachments_and_flags_gaps() {
+        let db = Database::open(":memory:").unwrap();
+        db.migrate().unwrap();
+        init_default_data(&db).unwrap();
+        let data = db.export_all().unwrap();
+        let mut agents = data.agents.clone();
+        let security = Instruction {
+            id: "security".to_string(),
+            category: InstructionCategory::Security,
+            enabled: false,
+            ..Instruction::default()
+        };
+        let mut instructions = data.instructions.clone();
+        instructions.push(security);
+        agents[0].instructions.push("security".to_string());
+        agents.push(Agent {
+            name: "Bare".to_string(),
+            ..Agent::default()
+        });
+
+        let matrix = capability_matrix(&agents, &data.skills, &instructions);
+        assert_eq!(matrix.agents.len(), 2);
+        let security_column = matrix
+            .categories
+            .iter()
+            .position(|c| c == "security")
+            .unwrap();
+        assert_eq!(matrix.agents[0].categories[security_column], 0);
+        assert!(matrix
+            .uncovered_categories
+            .contains(&"security".to_string()));
+        assert!(!matrix.uncovered_categories.contains(&"custom".to_string()));
+
+        let bare = &matrix.agents[1];
+        assert!(bare.categories.iter().all(|c| *c == 0));
+        assert!(bare.skill_types.iter().all(|c| *c == 0));
+        assert_eq!(bare.missing_categories.len(), CATEGORIES.len() - 1);
+
+        let attached: usize = matrix.agents[0].categories.iter().sum();
+        assert_eq!(
+            attached,
+            data.instructions
+                .iter()
+                .filter(|i| i.enabled && agents[0].instructions.contains(&i.id))
+                .count()
+        );
+    }
+}
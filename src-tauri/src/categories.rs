@@ -0,0 +1,310 @@
+//! Nested instruction categories
+//! An instruction's full category path is its top-level category followed by
+//! an optional slash-separated subcategory, e.g. `code_style/rust/async`.
+//! Filtering by a path matches that node and everything below it.
+
+use crate::db::category_to_string;
+use crate::models::{CategoryNode, CategoryTemplate, Instruction, InstructionCategory};
+use crate::resolve::slugify;
+use std::collections::BTreeMap;
+
+/// Clean up a user-supplied subcategory into lowercase snake_case segments.
+/// Returns `None` when nothing usable is left.
+pub fn normalize_subcategory(raw: &str) -> Option<String> {
+    let segments: Vec<String> = raw
+        .split('/')
+        .map(|segment| slugify(segment).replace('-', "_"))
+        .filter(|segment| !segment.is_empty())
+        .collect();
+
+    if segments.is_empty() {
+        None
+    } else {
+        Some(segments.join("/"))
+    }
+}
+
+/// Map a top-level category name (case-insensitive) to its variant
+pub fn parse_category(name: &str) -> InstructionCategory {
+    try_parse_category(name).unwrap_or(InstructionCategory::General)
+}
+
+/// Like `parse_category`, but `None` for names that aren't a category
+pub fn try_parse_category(name: &str) -> Option<InstructionCategory> {
+    match name.trim().to_lowercase().as_str() {
+        "general" => Some(InstructionCategory::General),
+        "code_style" | "codestyle" => Some(InstructionCategory::CodeStyle),
+        "communication" => Some(InstructionCategory::Communication),
+        "workflow" => Some(InstructionCategory::Workflow),
+        "security" => Some(InstructionCategory::Security),
+        "testing" => Some(InstructionCategory::Testing),
+        "documentation" => Some(InstructionCategory::Documentation),
+        "custom" => Some(InstructionCategory::Custom),
+        _ => None,
+    }
+}
+
+/// Word stems that suggest a category, checked in order
+const CATEGORY_HINTS: &[(InstructionCategory, &[&str])] = &[
+    (
+        InstructionCategory::Security,
+        &[
+            "secur",
+            "secret",
+            "authenticat",
+            "authoriz",
+            "credential",
+            "permission",
+            "privacy",
+            "vulnerab",
+        ],
+    ),
+    (InstructionCategory::Testing, &["test", "coverage"]),
+    (
+        InstructionCategory::Documentation,
+        &[
+            "document",
+            "docs",
+            "readme",
+            "changelog",
+            "comment",
+            "docstring",
+        ],
+    ),
+    (
+        InstructionCategory::Workflow,
+        &[
+            "workflow", "git", "commit", "branch", "pull", "release", "deploy", "build", "setup",
+            "command", "process",
+        ],
+    ),
+    (
+        InstructionCategory::CodeStyle,
+        &["style", "format", "lint", "naming", "convention", "idiom"],
+    ),
+    (
+        InstructionCategory::Communication,
+        &["communicat", "tone", "respon", "writing", "voice"],
+    ),
+];
+
+/// Guess a category from a section heading such as "Running tests" or
+/// "Commit conventions". The first category with a matching word wins.
+pub fn infer_category(heading: &str) -> InstructionCategory {
+    let words: Vec<String> = heading
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    CATEGORY_HINTS
+        .iter()
+        .find(|(_, stems)| {
+            words
+                .iter()
+                .any(|word| stems.iter().any(|stem| word.starts_with(stem)))
+        })
+        .map(|(category, _)| category.clone())
+        .unwrap_or(InstructionCategory::General)
+}
+
+/// Split a full path such as `code_style/rust` into category and subcategory
+pub fn parse_category_path(path: &str) -> (InstructionCategory, Option<String>) {
+    match path.split_once('/') {
+        Some((category, rest)) => (parse_category(category), normalize_subcategory(rest)),
+        None => (parse_category(path), None),
+    }
+}
+
+/// Full category path of an instruction
+pub fn category_path(instruction: &Instruction) -> String {
+    let category = category_to_string(&instruction.category);
+    match &instruction.subcategory {
+        Some(sub) if !sub.is_empty() => format!("{}/{}", category, sub),
+        _ => category.to_string(),
+    }
+}
+
+/// Whether an instruction is filed at `filter` or anywhere below it
+pub fn matches_category(instruction: &Instruction, filter: &str) -> bool {
+    let filter = filter.trim().trim_matches('/').to_lowercase();
+    if filter.is_empty() {
+        return true;
+    }
+    let path = category_path(instruction);
+    path == filter || path.starts_with(&format!("{}/", filter))
+}
+
+/// Flatten the category tree in depth-first order, creating intermediate
+/// nodes as needed and rolling instruction counts up to every ancestor
+pub fn category_tree(instructions: &[Instruction]) -> Vec<CategoryNode> {
+    // Path segments sort after '/', so a BTreeMap keeps children right after their parent
+    let mut direct: BTreeMap<String, usize> = BTreeMap::new();
+    for instruction in instructions {
+        let path = category_path(instruction);
+        let mut prefix = String::new();
+        for segment in path.split('/') {
+            if !prefix.is_empty() {
+                prefix.push('/');
+            }
+            prefix.push_str(segment);
+            direct.entry(prefix.clone()).or_insert(0);
+        }
+        *direct.entry(path).or_insert(0) += 1;
+    }
+
+    direct
+        .iter()
+        .map(|(path, &count)| {
+            let descendants = format!("{}/", path);
+            let total_count = count
+                + direct
+                    .range(descendants.clone()..)
+                    .take_while(|(p, _)| p.starts_with(&descendants))
+                    .map(|(_, c)| c)
+                    .sum::<usize>();
+            CategoryNode {
+                path: path.clone(),
+                name: path.rsplit('/').next().unwrap_or(path).to_string(),
+                depth: path.matches('/').count(),
+                instruction_count: count,
+                total_count,
+            }
+        })
+        .collect()
+}
+
+/// Default icon and skeleton content for new instructions in `category`
+pub fn category_template(category: &InstructionCategory) -> CategoryTemplate {
+    let (icon_emoji, content) = match category {
+        InstructionCategory::General => ("📋", "## Rule\n\n\n## Why\n\n\n## Examples\n\n"),
+        InstructionCategory::CodeStyle => (
+            "📏",
+            "## Applies to\n\n\n## Rules\n\n- \n\n## Examples\n\n```\n// Prefer\n\n// Avoid\n```\n",
+        ),
+        InstructionCategory::Communication => {
+            ("💬", "## Tone\n\n\n## Format\n\n\n## Avoid\n\n- \n")
+        }
+        InstructionCategory::Workflow => {
+            ("🔄", "## When\n\n\n## Steps\n\n1. \n\n## Done when\n\n- \n")
+        }
+        InstructionCategory::Security => (
+            "🔒",
+            "## Rules\n\n- \n\n## Never\n\n- \n\n## If something looks wrong\n\n",
+        ),
+        InstructionCategory::Testing => (
+            "🧪",
+            "## What to test\n\n- \n\n## How\n\n\n## Conventions\n\n- \n",
+        ),
+        InstructionCategory::Documentation => (
+            "📝",
+            "## What to document\n\n- \n\n## Format\n\n\n## Examples\n\n",
+        ),
+        InstructionCategory::Custom => ("📌", "## Rule\n\n\n## Why\n\n"),
+    };
+    CategoryTemplate {
+        category: category.clone(),
+        icon_emoji: icon_emoji.to_string(),
+        content: content.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instruction(category: InstructionCategory, subcategory: Option<&str>) -> Instruction {
+        Instruction {
+            category,
+            subcategory: subcategory.map(str::to_string),
+            ..Instruction::default()
+        }
+    }
+
+    #[test]
+    fn test_matches_category_includes_descendants() {
+        let rust = instruction(InstructionCategory::CodeStyle, Some("rust/async"));
+
+        assert!(matches_category(&rust, "code_style"));
+        assert!(matches_category(&rust, "Code_Style/Rust"));
+        assert!(matches_category(&rust, "code_style/rust/async"));
+        assert!(!matches_category(&rust, "code_style/ru"));
+        assert!(!matches_category(&rust, "testing"));
+        assert_eq!(
+            normalize_subcategory(" Rust / Async IO/"),
+            Some("rust/async_io".to_string())
+        );
+    }
+
+    #[test]
+    fn test_infer_category_from_headings() {
+        let cases = [
+            ("Running tests", InstructionCategory::Testing),
+            ("Commit & PR conventions", InstructionCategory::Workflow),
+            ("Naming conventions", InstructionCategory::CodeStyle),
+            ("Git workflow", InstructionCategory::Workflow),
+            ("Secrets and authentication", InstructionCategory::Security),
+            ("Doc comments", InstructionCategory::Documentation),
+            ("Authors", InstructionCategory::General),
+            ("Architecture", InstructionCategory::General),
+        ];
+        for (heading, expected) in cases {
+            assert_eq!(
+                category_to_string(&infer_category(heading)),
+                category_to_string(&expected),
+                "{}",
+                heading
+            );
+        }
+    }
+
+    #[test]
+    fn test_category_tree_rolls_up_counts() {
+        let instructions = vec![
+            instruction(InstructionCategory::CodeStyle, None),
+            instruction(InstructionCategory::CodeStyle, Some("rust")),
+            instruction(InstructionCategory::CodeStyle, Some("rust/async")),
+            instruction(InstructionCategory::CodeStyle, Some("python")),
+            instruction(InstructionCategory::Testing, None),
+        ];
+
+        let tree: Vec<(String, usize, usize)> = category_tree(&instructions)
+            .into_iter()
+            .map(|n| (n.path, n.instruction_count, n.total_count))
+            .collect();
+
+        assert_eq!(
+            tree,
+            vec![
+                ("code_style".to_string(), 1, 4),
+                ("code_style/python".to_string(), 1, 1),
+                ("code_style/rust".to_string(), 1, 2),
+                ("code_style/rust/async".to_string(), 1, 1),
+                ("testing".to_string(), 1, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_every_category_has_a_template() {
+        for name in [
+            "general",
+            "code_style",
+            "communication",
+            "workflow",
+            "security",
+            "testing",
+            "documentation",
+            "custom",
+        ] {
+            let category = parse_category(name);
+            let template = category_template(&category);
+            assert_eq!(template.category, category);
+            assert!(!template.icon_emoji.is_empty(), "{}", name);
+            assert!(template.content.starts_with("## "), "{}", name);
+        }
+        assert_eq!(
+            category_template(&InstructionCategory::CodeStyle).icon_emoji,
+            "📏"
+        );
+    }
+}
@@ -0,0 +1,207 @@
+//! SillyTavern character cards
+//! Community personas are mostly shared as character cards: JSON in the
+//! `chara_card_v2` spec (or the flat fields of the older v1 cards), usually
+//! embedded base64-encoded in a `chara` text chunk of the character's PNG
+//! portrait. A card becomes an agent whose system prompt holds the card's
+//! own system prompt, description, personality, scenario, first message and
+//! example dialogue, with `{{char}}` and `{{user}}` filled in.
+
+use crate::models::Agent;
+use crate::naming;
+use base64::Engine;
+use regex::Regex;
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// Text chunk keywords holding a card, newest spec first
+const CARD_KEYWORDS: [&str; 2] = ["ccv3", "chara"];
+
+/// Tag every imported card gets, to find them again
+const CARD_TAG: &str = "character-card";
+
+/// The card JSON in the file at `path`, and for a PNG card its image as a
+/// data URL to use as the avatar
+pub fn read_card(path: &Path) -> Result<(String, Option<String>), String> {
+    let bytes = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    if !bytes.starts_with(PNG_SIGNATURE) {
+        let text = String::from_utf8(bytes)
+            .map_err(|_| format!("{} is neither a PNG nor a JSON card", path.display()))?;
+        return Ok((text, None));
+    }
+    let json = card_from_png(&bytes)?;
+    let image = format!(
+        "data:image/png;base64,{}",
+        base64::engine::general_purpose::STANDARD.encode(&bytes)
+    );
+    Ok((json, Some(image)))
+}
+
+/// The card JSON embedded in a PNG's `ccv3` or `chara` text chunk
+pub fn card_from_png(bytes: &[u8]) -> Result<String, String> {
+    let mut chunks = Vec::new();
+    let mut rest = bytes.strip_prefix(PNG_SIGNATURE).ok_or("Not a PNG image")?;
+    // Each chunk is a big-endian length, a four-byte type, the data and a CRC
+    while rest.len() >= 12 {
+        let length = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+        let Some(data) = rest.get(8..8 + length) else {
+            break;
+        };
+        if &rest[4..8] == b"tEXt" {
+            if let Some(split) = data.iter().position(|&b| b == 0) {
+                chunks.push((&data[..split], &data[split + 1..]));
+            }
+        }
+        rest = rest.get(12 + length..).unwrap_or_default();
+    }
+
+    let encoded = CARD_KEYWORDS
+        .iter()
+        .find_map(|keyword| {
+            chunks
+                .iter()
+                .find(|(name, _)| *name == keyword.as_bytes())
+                .map(|(_, text)| *text)
+        })
+        .ok_or("The image holds no character card")?;
+    let decoded = base64::engine::general_purpose::STANDARD
+        .decode(encoded.trim_ascii())
+        .map_err(|e| format!("Failed to decode character card: {}", e))?;
+    String::from_utf8(decoded).map_err(|e| format!("Failed to decode character card: {}", e))
+}
+
+/// The agent a v1, v2 or v3 card describes
+pub fn parse_card(text: &str) -> Result<Agent, String> {
+    let card: Value =
+        serde_json::from_str(text).map_err(|e| format!("Failed to parse character card: {}", e))?;
+    // v2 and v3 cards nest their fields under `data`; v1 cards are flat
+    let fields = match card.get("data") {
+        Some(data @ Value::Object(_)) => data,
+        _ => &card,
+    };
+    let field = |key: &str| {
+        fields
+            .get(key)
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .trim()
+            .to_string()
+    };
+    let name = field("name");
+    if name.is_empty() {
+        return Err("Character card has no name".to_string());
+    }
+
+    let char_pattern = Regex::new(r"(?i)\{\{char\}\}|<bot>").unwrap();
+    let user_pattern = Regex::new(r"(?i)\{\{user\}\}|<user>").unwrap();
+    let fill = |text: String| {
+        let text = text.replace("{{original}}", "");
+        let text = char_pattern.replace_all(&text, name.as_str());
+        user_pattern
+            .replace_all(&text, "the user")
+            .trim()
+            .to_string()
+    };
+
+    let description = fill(field("description"));
+    let personality = fill(field("personality"));
+    let mut sections = vec![fill(field("system_prompt")), description.clone()];
+    for (heading, key) in [
+        ("Personality", "personality"),
+        ("Scenario", "scenario"),
+        ("First message", "first_mes"),
+        ("Example dialogue", "mes_example"),
+    ] {
+        let text = fill(field(key));
+        if !text.is_empty() {
+            sections.push(format!("## {}\n{}", heading, text));
+        }
+    }
+    sections.push(fill(field("post_history_instructions")));
+    sections.retain(|section| !section.is_empty());
+
+    let mut agent = Agent {
+        name,
+        description: naming::derive_description(&description).unwrap_or_default(),
+        system_prompt: sections.join("\n\n"),
+        ..Agent::default()
+    };
+    // A short comma-separated personality doubles as traits
+    let traits: Vec<String> = personality
+        .split(',')
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+        .collect();
+    if traits.len() > 1 && traits.iter().all(|t| t.split_whitespace().count() <= 3) {
+        agent.personality.traits = traits;
+    }
+    agent.tags = fields["tags"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .map(str::to_string)
+        .chain([CARD_TAG.to_string()])
+        .collect();
+    Ok(agent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn png_with_text(keyword: &str, text: &str) -> Vec<u8> {
+        let mut png = PNG_SIGNATURE.to_vec();
+        let data = [keyword.as_bytes(), b"\0", text.as_bytes()].concat();
+        for (kind, data) in [(&b"tEXt"[..], &data[..]), (b"IEND", b"")] {
+            png.extend((data.len() as u32).to_be_bytes());
+            png.extend(kind);
+            png.extend(data);
+            png.extend([0; 4]);
+        }
+        png
+    }
+
+    #[test]
+    fn test_png_card_becomes_agent() {
+        let card = json!({
+            "spec": "chara_card_v2",
+            "spec_version": "2.0",
+            "data": {
+                "name": "Captain Vale",
+                "description": "{{char}} is a retired sky pirate. She tells tall tales.",
+                "personality": "gruff, warm, nostalgic",
+                "scenario": "<USER> meets {{char}} in a harbour tavern.",
+                "first_mes": "*Vale raises her mug.* Sit down, {{user}}.",
+                "mes_example": "",
+                "tags": ["fantasy"]
+            }
+        });
+        let encoded = base64::engine::general_purpose::STANDARD.encode(card.to_string());
+        let png = png_with_text("chara", &encoded);
+
+        let agent = parse_card(&card_from_png(&png).unwrap()).unwrap();
+        assert_eq!(agent.name, "Captain Vale");
+        assert_eq!(agent.description, "Captain Vale is a retired sky pirate.");
+        assert_eq!(
+            agent.system_prompt,
+            "Captain Vale is a retired sky pirate. She tells tall tales.\n\n\
+             ## Personality\ngruff, warm, nostalgic\n\n\
+             ## Scenario\nthe user meets Captain Vale in a harbour tavern.\n\n\
+             ## First message\n*Vale raises her mug.* Sit down, the user."
+        );
+        assert_eq!(agent.personality.traits, ["gruff", "warm", "nostalgic"]);
+        assert_eq!(agent.tags, ["fantasy", "character-card"]);
+
+        // v1 cards keep their fields at the top level
+        let v1 = parse_card(r#"{"name": "Bob", "personality": "Bob never lies."}"#).unwrap();
+        assert_eq!(v1.system_prompt, "## Personality\nBob never lies.");
+        assert_eq!(v1.personality.traits, Agent::default().personality.traits);
+
+        assert!(card_from_png(&png_with_text("Comment", "hi")).is_err());
+        assert!(parse_card(r#"{"data": {"description": "nameless"}}"#).is_err());
+    }
+}
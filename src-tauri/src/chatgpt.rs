@@ -0,0 +1,373 @@
+//! Suggestions from a ChatGPT data export
+//! The export is a zip holding `conversations.json`, one object per chat
+//! with its messages under `mapping`. Custom instructions ride along in
+//! each chat as a hidden context message; every version of them becomes a
+//! suggested instruction. Prompts the user started three or more chats with
+//! the same way become suggested prompt skills, with the rest of the message
+//! as an `{{input}}` variable. Suggestions whose text is much like an item
+//! already in the library name it, so the user can skip them.
+
+use crate::models::{
+    ChatGptSuggestion, Instruction, InstructionCategory, LibraryItem, Skill, SkillDefinition,
+    TemplateVariable,
+};
+use crate::naming;
+use serde_json::Value;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
+use std::io::Read;
+use std::path::Path;
+
+const CONVERSATIONS_FILE: &str = "conversations.json";
+
+/// Chats a prompt must open before it is suggested as a skill
+const MIN_CONVERSATIONS: usize = 3;
+
+/// Words a prompt's first line needs, so greetings and thanks aren't
+/// suggested
+const MIN_WORDS: usize = 4;
+
+/// Share of words two texts must have in common to count as duplicates
+const DUPLICATE_SIMILARITY: f64 = 0.8;
+
+/// The chats in an export: its zip, the folder it was extracted to, or
+/// `conversations.json` itself
+pub fn read_conversations(path: &Path) -> Result<Vec<Value>, String> {
+    let text = if path.is_dir() {
+        let file = path.join(CONVERSATIONS_FILE);
+        fs::read_to_string(&file)
+            .map_err(|e| format!("Failed to read {}: {}", file.display(), e))?
+    } else if path.extension().is_some_and(|ext| ext == "zip") {
+        let file =
+            File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+        let mut archive = zip::ZipArchive::new(file)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let name = archive
+            .file_names()
+            .find(|name| name.rsplit('/').next() == Some(CONVERSATIONS_FILE))
+            .map(str::to_string)
+            .ok_or_else(|| format!("{} has no {}", path.display(), CONVERSATIONS_FILE))?;
+        let mut text = String::new();
+        archive
+            .by_name(&name)
+            .and_then(|mut entry| Ok(entry.read_to_string(&mut text)?))
+            .map_err(|e| format!("Failed to read {}: {}", name, e))?;
+        text
+    } else {
+        fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+    };
+
+    match serde_json::from_str(&text) {
+        Ok(Value::Array(conversations)) => Ok(conversations),
+        Ok(_) => Err(format!("{} isn't a list of chats", CONVERSATIONS_FILE)),
+        Err(e) => Err(format!("Failed to parse {}: {}", CONVERSATIONS_FILE, e)),
+    }
+}
+
+/// Instructions and skills suggested by `conversations`, custom instructions
+/// first, then prompts by how many chats they opened
+pub fn suggest(
+    conversations: &[Value],
+    skills: &[Skill],
+    instructions: &[Instruction],
+) -> Vec<ChatGptSuggestion> {
+    // Text -> the chats it appeared in
+    let mut about_user: BTreeMap<String, HashSet<usize>> = BTreeMap::new();
+    let mut about_model: BTreeMap<String, HashSet<usize>> = BTreeMap::new();
+    let mut prompts: BTreeMap<String, Prompt> = BTreeMap::new();
+
+    for (chat, conversation) in conversations.iter().enumerate() {
+        let Some(mapping) = conversation.get("mapping").and_then(Value::as_object) else {
+            continue;
+        };
+        let mut messages: Vec<&Value> = mapping
+            .values()
+            .filter_map(|node| node.get("message"))
+            .filter(|message| !message.is_null())
+            .collect();
+        messages.sort_by(|a, b| created(a).total_cmp(&created(b)));
+
+        let mut opened = false;
+        for message in messages {
+            let content = &message["content"];
+            let context = &message["metadata"]["user_context_message_data"];
+            for (fields, found) in [
+                (
+                    [&content["user_profile"], &context["about_user_message"]],
+                    &mut about_user,
+                ),
+                (
+                    [
+                        &content["user_instructions"],
+                        &context["about_model_message"],
+                    ],
+                    &mut about_model,
+                ),
+            ] {
+                for text in fields
+                    .into_iter()
+                    .filter_map(Value::as_str)
+                    .map(custom_text)
+                {
+                    if !text.is_empty() {
+                        found.entry(text).or_default().insert(chat);
+                    }
+                }
+            }
+
+            let from_user = message["author"]["role"] == "user";
+            if opened || !from_user || content["content_type"] != "text" {
+                continue;
+            }
+            opened = true;
+            let text = message_text(content);
+            let (first_line, rest) = text.trim().split_once('\n').unwrap_or((text.trim(), ""));
+            let first_line = first_line.trim();
+            if first_line.split_whitespace().count() < MIN_WORDS {
+                continue;
+            }
+            let prompt = prompts
+                .entry(normalize(first_line))
+                .or_insert_with(|| Prompt {
+                    line: first_line.to_string(),
+                    chats: HashSet::new(),
+                    with_input: false,
+                });
+            prompt.chats.insert(chat);
+            prompt.with_input |= !rest.trim().is_empty();
+        }
+    }
+
+    let mut suggestions = Vec::new();
+    for (found, name, category) in [
+        (about_user, "About me", InstructionCategory::General),
+        (
+            about_model,
+            "How to respond",
+            InstructionCategory::Communication,
+        ),
+    ] {
+        let mut versions: Vec<(String, HashSet<usize>)> = found.into_iter().collect();
+        versions.sort_by_key(|(_, chats)| std::cmp::Reverse(chats.len()));
+        for (text, chats) in versions {
+            let instruction = Instruction {
+                name: name.to_string(),
+                description: "Custom instructions from ChatGPT".to_string(),
+                category: category.clone(),
+                content: text.clone(),
+                tags: vec!["chatgpt".to_string()],
+                ..Instruction::default()
+            };
+            suggestions.push(ChatGptSuggestion {
+                key: key("instruction", &format!("{}\n{}", name, text)),
+                duplicate_of: instructions
+                    .iter()
+                    .find(|i| is_duplicate(&i.content, &text))
+                    .map(|i| i.id.clone()),
+                reason: "Custom instructions".to_string(),
+                occurrences: chats.len(),
+                item: LibraryItem::Instruction(instruction),
+            });
+        }
+    }
+
+    let mut prompts: Vec<Prompt> = prompts
+        .into_values()
+        .filter(|p| p.chats.len() >= MIN_CONVERSATIONS)
+        .collect();
+    prompts.sort_by(|a, b| b.chats.len().cmp(&a.chats.len()).then(a.line.cmp(&b.line)));
+    for prompt in prompts {
+        let (template, variables) = if prompt.with_input {
+            let input = TemplateVariable {
+                name: "input".to_string(),
+                description: "The rest of the message".to_string(),
+                default: None,
+            };
+            (format!("{}\n\n{{{{input}}}}", prompt.line), vec![input])
+        } else {
+            (prompt.line.clone(), Vec::new())
+        };
+        let mut item = LibraryItem::Skill(Skill {
+            name: String::new(),
+            description: String::new(),
+            definition: SkillDefinition::Prompt {
+                template,
+                variables,
+                follows: Vec::new(),
+            },
+            ..Skill::default()
+        });
+        naming::fill_missing(&mut item);
+        suggestions.push(ChatGptSuggestion {
+            key: key("skill", &prompt.line),
+            duplicate_of: skills
+                .iter()
+                .find(|skill| match &skill.definition {
+                    SkillDefinition::Prompt { template, .. } => {
+                        is_duplicate(template, &prompt.line)
+                    }
+                    _ => false,
+                })
+                .map(|skill| skill.id.clone()),
+            reason: format!("Opened {} chats", prompt.chats.len()),
+            occurrences: prompt.chats.len(),
+            item,
+        });
+    }
+    suggestions
+}
+
+/// A first line several chats were opened with
+struct Prompt {
+    line: String,
+    chats: HashSet<usize>,
+    with_input: bool, // Some messages carried more than the first line
+}
+
+fn created(message: &Value) -> f64 {
+    message["create_time"].as_f64().unwrap_or_default()
+}
+
+fn message_text(content: &Value) -> String {
+    content["parts"]
+        .as_array()
+        .map(|parts| {
+            parts
+                .iter()
+                .filter_map(Value::as_str)
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .unwrap_or_default()
+}
+
+/// Custom instructions without the explanation ChatGPT wraps the profile
+/// in, which fences the user's own text
+fn custom_text(text: &str) -> String {
+    let fenced = text
+        .split_once("```")
+        .and_then(|(_, rest)| rest.rsplit_once("```"))
+        .map(|(inner, _)| inner);
+    fenced.unwrap_or(text).trim().to_string()
+}
+
+fn normalize(text: &str) -> String {
+    text.split_whitespace()
+        .map(|word| word.to_lowercase())
+        .collect::<Vec<_>>()
+        .join(" ")
+        .trim_end_matches([':', '.'])
+        .to_string()
+}
+
+/// Stable id of a suggestion across scans of the same export
+fn key(kind: &str, text: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    normalize(text).hash(&mut hasher);
+    format!("{}-{:016x}", kind, hasher.finish())
+}
+
+/// Whether `existing` says much the same as `text`, comparing their words
+fn is_duplicate(existing: &str, text: &str) -> bool {
+    let words = |text: &str| -> HashSet<String> {
+        text.split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(str::to_lowercase)
+            .collect()
+    };
+    let (a, b) = (words(existing), words(text));
+    if a.is_empty() || b.is_empty() {
+        return false;
+    }
+    let shared = a.intersection(&b).count() as f64;
+    shared / a.union(&b).count() as f64 >= DUPLICATE_SIMILARITY
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn chat(first_message: &str, profile: Option<&str>) -> Value {
+        let mut mapping = serde_json::Map::new();
+        if let Some(profile) = profile {
+            mapping.insert(
+                "context".to_string(),
+                json!({"message": {
+                    "author": {"role": "user"},
+                    "create_time": 1.0,
+                    "content": {
+                        "content_type": "user_editable_context",
+                        "user_profile": format!("The user provided the following information about themselves.\nUser profile:\n```{}```", profile),
+                        "user_instructions": "Be concise."
+                    }
+                }}),
+            );
+        }
+        mapping.insert(
+            "first".to_string(),
+            json!({"message": {
+                "author": {"role": "user"},
+                "create_time": 2.0,
+                "content": {"content_type": "text", "parts": [first_message]}
+            }}),
+        );
+        mapping.insert(
+            "second".to_string(),
+            json!({"message": {
+                "author": {"role": "user"},
+                "create_time": 3.0,
+                "content": {"content_type": "text", "parts": ["Explain this code step by step"]}
+            }}),
+        );
+        json!({ "title": "Chat", "mapping": mapping })
+    }
+
+    #[test]
+    fn test_custom_instructions_and_repeated_prompts_are_suggested() {
+        let conversations = vec![
+            chat(
+                "Explain this code step by step\nfn main() {}",
+                Some("I write Rust."),
+            ),
+            chat(
+                "Explain this code step by step:\nlet x = 1;",
+                Some("I write Rust."),
+            ),
+            chat("explain this  code step by step", None),
+            chat("Thanks a lot", Some("I write Rust and Go.")),
+            chat("Thanks a lot", None),
+            chat("Thanks a lot", None),
+        ];
+        let instructions = vec![Instruction {
+            id: "concise".to_string(),
+            content: "Be concise!".to_string(),
+            ..Instruction::default()
+        }];
+
+        let suggestions = suggest(&conversations, &[], &instructions);
+        let summary: Vec<(&str, usize, Option<&str>)> = suggestions
+            .iter()
+            .map(|s| {
+                let text = naming::body(&s.item);
+                (text, s.occurrences, s.duplicate_of.as_deref())
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("I write Rust.", 2, None),
+                ("I write Rust and Go.", 1, None),
+                ("Be concise.", 3, Some("concise")),
+                ("Explain this code step by step\n\n{{input}}", 3, None),
+            ]
+        );
+        assert_eq!(
+            suggestions[3].key,
+            key("skill", "Explain this code step by step:")
+        );
+        assert!(matches!(&suggestions[3].item, LibraryItem::Skill(s) if s.name != "New Skill"));
+    }
+}
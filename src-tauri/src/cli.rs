@@ -0,0 +1,393 @@
+//! Headless subcommands for scripts and CI
+//! `prompt-forge list agents`, `export --all -o library.json`,
+//! `import rules.md --as instruction` and `apply <agent> --stdout` work on the
+//! active workspace's library, or the one `--workspace` or `--db-path` picks,
+//! without starting the GUI. Output goes to stdout so it can be piped;
+//! failures are reported on stderr with a non-zero exit.
+
+use crate::assembly::{self, AssemblyOptions};
+use crate::composition::{self, LibraryGraph};
+use crate::db::{self, Database};
+use crate::diagnostics;
+use crate::encryption;
+use crate::formats::{self, FormatRegistry};
+use crate::ids;
+use crate::models::LibraryItem;
+use crate::naming;
+use crate::resolve::resolve_entity;
+use crate::secrets;
+use crate::snippets;
+use crate::workspace::Workspace;
+use chrono::Utc;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Subcommands `prompt-forge` runs without the GUI
+pub const COMMANDS: [&str; 4] = ["list", "export", "import", "apply"];
+
+/// Options that pick the library, read by `main` before the subcommand runs
+const GLOBAL_OPTIONS: [&str; 2] = ["--db-path", "--workspace"];
+
+/// Options followed by a value
+const VALUE_OPTIONS: [&str; 4] = ["-o", "--output", "--as", "--format"];
+
+pub const USAGE: &str = "\
+Usage: prompt-forge [--workspace <name> | --db-path <file>] <command>
+
+Commands:
+  list <agents|skills|instructions> [--json]      List the library, one `id<TAB>name` per line
+  export --all [-o <file>] [--allow-secrets]      Write the whole library as JSON
+  import <file> --as <agent|skill|instruction>    Add the item in a file to the library
+         [--format <id>]                          Read it with another format, e.g. cursor-rule
+  apply <agent> [--stdout | -o <file>]            Print the prompt MCP clients get for an agent
+  --mcp                                           Run the MCP server";
+
+/// The subcommand and its arguments in a command line without the program
+/// name, once any global options before it are skipped
+pub fn find_subcommand(args: &[String]) -> Option<&[String]> {
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            arg if GLOBAL_OPTIONS.contains(&arg) => i += 2,
+            arg if COMMANDS.contains(&arg) => return Some(&args[i..]),
+            _ => return None,
+        }
+    }
+    None
+}
+
+/// Run a subcommand and exit: its output goes to stdout, a failure to stderr
+pub fn run_cli(db_path: PathBuf, args: &[String]) {
+    match run(&db_path, args) {
+        Ok(output) => print!("{}", output),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Run a subcommand against the library at `db_path`, returning what to print
+pub fn run(db_path: &Path, args: &[String]) -> Result<String, String> {
+    let args = Args::parse(args)?;
+    let db = encryption::open_database(db_path)?;
+    db.migrate()
+        .map_err(|e| format!("Failed to run database migrations: {}", e))?;
+    db::init_default_data(&db).map_err(|e| format!("Failed to initialize default data: {}", e))?;
+
+    match args.positional.first().map(String::as_str) {
+        Some("list") => list(&db, &args),
+        Some("export") => export(&db, &args),
+        Some("import") => import(&db, &args),
+        Some("apply") => apply(&db, &args),
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+/// A subcommand's words, flags and option values
+#[derive(Debug, Default)]
+struct Args {
+    positional: Vec<String>,
+    flags: Vec<String>,
+    values: Vec<(String, String)>,
+}
+
+impl Args {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut parsed = Args::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let takes_value = VALUE_OPTIONS.contains(&arg.as_str());
+            if takes_value || GLOBAL_OPTIONS.contains(&arg.as_str()) {
+                let value = args
+                    .next()
+                    .ok_or_else(|| format!("{} needs a value", arg))?;
+                if takes_value {
+                    parsed.values.push((arg.clone(), value.clone()));
+                }
+            } else if arg.starts_with('-') && arg.len() > 1 {
+                parsed.flags.push(arg.clone());
+            } else {
+                parsed.positional.push(arg.clone());
+            }
+        }
+        Ok(parsed)
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.flags.iter().any(|flag| flag == name)
+    }
+
+    fn value(&self, names: &[&str]) -> Option<&str> {
+        self.values
+            .iter()
+            .rev()
+            .find(|(name, _)| names.contains(&name.as_str()))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The `-o` file, if any
+    fn output(&self) -> Option<PathBuf> {
+        self.value(&["-o", "--output"]).map(PathBuf::from)
+    }
+}
+
+fn list(db: &Database, args: &Args) -> Result<String, String> {
+    let error = |e: rusqlite::Error| format!("Failed to load the library: {}", e);
+    let (json, rows): (serde_json::Result<String>, Vec<(String, String)>) =
+        match args.positional.get(1).map(String::as_str) {
+            Some("agents") => {
+                let agents = db.get_all_agents().map_err(error)?;
+                let rows = agents
+                    .iter()
+                    .map(|a| (a.id.clone(), a.name.clone()))
+                    .collect();
+                (serde_json::to_string_pretty(&agents), rows)
+            }
+            Some("skills") => {
+                let skills = db.get_all_skills().map_err(error)?;
+                let rows = skills
+                    .iter()
+                    .map(|s| (s.id.clone(), s.name.clone()))
+                    .collect();
+                (serde_json::to_string_pretty(&skills), rows)
+            }
+            Some("instructions") => {
+                let instructions = db.get_all_instructions().map_err(error)?;
+                let rows = instructions
+                    .iter()
+                    .map(|i| (i.id.clone(), i.name.clone()))
+                    .collect();
+                (serde_json::to_string_pretty(&instructions), rows)
+            }
+            _ => {
+                return Err("Usage: prompt-forge list <agents|skills|instructions> [--json]".into())
+            }
+        };
+
+    if args.flag("--json") {
+        return json
+            .map(|json| json + "\n")
+            .map_err(|e| format!("Failed to serialize the list: {}", e));
+    }
+    Ok(rows
+        .iter()
+        .map(|(id, name)| format!("{}\t{}\n", id, name))
+        .collect())
+}
+
+fn export(db: &Database, args: &Args) -> Result<String, String> {
+    if !args.flag("--all") {
+        return Err("Usage: prompt-forge export --all [-o <file>] [--allow-secrets]".into());
+    }
+    let data = db
+        .export_all()
+        .map_err(|e| format!("Failed to export data: {}", e))?;
+    secrets::guard_export(&secrets::scan_export(&data), args.flag("--allow-secrets"))?;
+    let json = serde_json::to_string_pretty(&data)
+        .map_err(|e| format!("Failed to serialize export: {}", e))?;
+    write_output(args, json + "\n")
+}
+
+fn import(db: &Database, args: &Args) -> Result<String, String> {
+    let (Some(path), Some(entity_type)) = (args.positional.get(1), args.value(&["--as"])) else {
+        return Err(
+            "Usage: prompt-forge import <file> --as <agent|skill|instruction> [--format <id>]"
+                .into(),
+        );
+    };
+    let format = match (args.value(&["--format"]), entity_type) {
+        (Some(format), _) => format,
+        (None, "agent") => "agent-markdown",
+        (None, "skill") => "skill-text",
+        (None, "instruction") => "instruction-markdown",
+        (None, other) => {
+            return Err(format!(
+                "Can't import '{}'; use agent, skill or instruction",
+                other
+            ))
+        }
+    };
+
+    let registry = FormatRegistry::with_builtin_formats();
+    let (info, importer) = registry.importer(format)?;
+    if info.entity_type != entity_type {
+        return Err(format!(
+            "{} files hold {}s, not {}s",
+            info.name, info.entity_type, entity_type
+        ));
+    }
+    let text = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let settings = db
+        .get_settings()
+        .map_err(|e| format!("Failed to load settings: {}", e))?;
+    if settings.strict_imports {
+        if let Some(message) = diagnostics::strict_failure(&importer.check(&text)) {
+            return Err(message);
+        }
+    }
+
+    let mut output = String::new();
+    let mut skill_ids: HashMap<String, String> = HashMap::new();
+    for mut item in importer.import(&text)? {
+        naming::fill_missing(&mut item);
+        formats::link_imported_skills(&mut item, &skill_ids);
+        insert(db, &mut item)?;
+        if let LibraryItem::Skill(skill) = &item {
+            skill_ids.insert(skill.name.clone(), skill.id.clone());
+        }
+        let (id, name) = match &item {
+            LibraryItem::Agent(agent) => (&agent.id, &agent.name),
+            LibraryItem::Skill(skill) => (&skill.id, &skill.name),
+            LibraryItem::Instruction(instruction) => (&instruction.id, &instruction.name),
+        };
+        output.push_str(&format!(
+            "Imported {} {}\t{}\n",
+            item.entity_type(),
+            id,
+            name
+        ));
+    }
+    Ok(output)
+}
+
+/// Save an imported item under a new id claimed from its name
+fn insert(db: &Database, item: &mut LibraryItem) -> Result<(), String> {
+    let now = Utc::now();
+    let entity_type = item.entity_type();
+    let result = match item {
+        LibraryItem::Agent(agent) => {
+            (agent.created_at, agent.updated_at) = (now, now);
+            let name = agent.name.clone();
+            ids::claim(&name, |id| {
+                agent.id = id.to_string();
+                db.insert_agent(agent)
+            })
+        }
+        LibraryItem::Skill(skill) => {
+            (skill.created_at, skill.updated_at) = (now, now);
+            let name = skill.name.clone();
+            ids::claim(&name, |id| {
+                skill.id = id.to_string();
+                db.insert_skill(skill)
+            })
+        }
+        LibraryItem::Instruction(instruction) => {
+            (instruction.created_at, instruction.updated_at) = (now, now);
+            let name = instruction.name.clone();
+            ids::claim(&name, |id| {
+                instruction.id = id.to_string();
+                db.insert_instruction(instruction)
+            })
+        }
+    };
+    result
+        .map(|_| ())
+        .map_err(|e| format!("Failed to import {}: {}", entity_type, e))
+}
+
+/// The agent's prompt as `apply_agent` serves it, with workspace
+/// placeholders filled from the current directory
+fn apply(db: &Database, args: &Args) -> Result<String, String> {
+    let Some(reference) = args.positional.get(1) else {
+        return Err("Usage: prompt-forge apply <agent> [--stdout | -o <file>]".into());
+    };
+    let error = |e: rusqlite::Error| format!("Failed to load the library: {}", e);
+    let mut agents = db.get_all_agents().map_err(error)?;
+    let mut skills = db.get_all_skills().map_err(error)?;
+    let mut instructions = db.get_all_instructions().map_err(error)?;
+    let snippets = db.get_all_snippets().map_err(error)?;
+    snippets::expand_library(&snippets, &mut agents, &mut skills, &mut instructions);
+    let library = LibraryGraph {
+        agents: &agents,
+        skills: &skills,
+        instructions: &instructions,
+    };
+
+    let agent = resolve_entity(&agents, reference)?;
+    composition::ensure_resolvable(&library, agent)?;
+    let agent = &composition::inherited(agent, &agents);
+    let workspace = std::env::current_dir()
+        .ok()
+        .and_then(|dir| Workspace::load(&dir).ok());
+    let options = AssemblyOptions {
+        branch: workspace.as_ref().and_then(|w| w.branch.clone()),
+        ..AssemblyOptions::default()
+    };
+    let mut prompt = assembly::agent_configuration(agent, &skills, &instructions, &options);
+    if let Some(workspace) = workspace {
+        prompt = workspace.render(&prompt);
+    }
+    if !prompt.ends_with('\n') {
+        prompt.push('\n');
+    }
+    write_output(args, prompt)
+}
+
+/// `text` written to the `-o` file, or returned for stdout
+fn write_output(args: &Args, text: String) -> Result<String, String> {
+    let Some(path) = args.output() else {
+        return Ok(text);
+    };
+    fs::write(&path, text).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(String::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_subcommands_manage_the_library() {
+        let dir = std::env::temp_dir().join(format!("pf-cli-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let db_path = dir.join("library.db");
+
+        let line = args("--workspace work list agents");
+        assert_eq!(find_subcommand(&line), Some(&line[2..]));
+        assert_eq!(find_subcommand(&args("--mcp")), None);
+        assert!(run(&db_path, &args("list")).is_err());
+
+        let rules = dir.join("rules.md");
+        fs::write(&rules, "---\nname: Rust Style\n---\nUse rustfmt.\n").unwrap();
+        let command = format!("import {} --as instruction", rules.display());
+        let imported = run(&db_path, &args(&command)).unwrap();
+        assert!(imported.starts_with("Imported instruction rust-style\tRust Style"));
+        assert!(run(&db_path, &args(&command))
+            .unwrap()
+            .starts_with("Imported instruction rust-style-2\t"));
+        assert!(run(
+            &db_path,
+            &args(&format!(
+                "import {} --as skill --format cursor-rule",
+                rules.display()
+            ))
+        )
+        .is_err());
+
+        let listed = run(&db_path, &args("list instructions")).unwrap();
+        assert!(listed.lines().any(|line| line == "rust-style\tRust Style"));
+        let agents = run(&db_path, &args("list agents")).unwrap();
+        let agent = agents.lines().next().unwrap().split('\t').next().unwrap();
+        let prompt = run(&db_path, &args(&format!("apply {} --stdout", agent))).unwrap();
+        assert!(prompt.contains("Use rustfmt."));
+
+        let file = dir.join("library.json");
+        let command = format!(
+            "--db-path {} export --all -o {}",
+            db_path.display(),
+            file.display()
+        );
+        assert_eq!(run(&db_path, &args(&command)).unwrap(), "");
+        let exported: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&file).unwrap()).unwrap();
+        assert!(exported["instructions"].as_array().unwrap().len() >= 2);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
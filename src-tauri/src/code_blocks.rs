@@ -0,0 +1,461 @@
+//! Code blocks in imported text
+//! Pasted guidelines often carry code as plain paragraphs or indented blocks,
+//! which many downstream tools render as prose. On import those snippets are
+//! wrapped in fenced blocks with a language hint. Existing fences, including
+//! their info strings (` ```rust,ignore `, `~~~ {.python}`), are left exactly
+//! as written.
+
+use regex::{Regex, RegexSet};
+use std::sync::OnceLock;
+
+/// Tracks whether a line is inside a fenced code block. A fence opens with
+/// three or more backticks or tildes and closes with at least as many of the
+/// same character and no info string.
+#[derive(Debug, Default)]
+pub struct FenceTracker {
+    open: Option<(char, usize)>,
+}
+
+impl FenceTracker {
+    /// Feed the next line; true when the line is a fence or inside one
+    pub fn update(&mut self, line: &str) -> bool {
+        let trimmed = line.trim_start();
+        let marker = trimmed.chars().next().filter(|c| *c == '`' || *c == '~');
+        let run = marker.map_or(0, |m| trimmed.chars().take_while(|c| *c == m).count());
+
+        match (self.open, marker) {
+            (Some((open, len)), Some(m)) if m == open && run >= len => {
+                if trimmed[run..].trim().is_empty() {
+                    self.open = None;
+                }
+                true
+            }
+            (Some(_), _) => true,
+            (None, Some(m)) if run >= 3 => {
+                // Backtick info strings can't contain backticks
+                if m == '`' && trimmed[run..].contains('`') {
+                    return false;
+                }
+                self.open = Some((m, run));
+                true
+            }
+            (None, _) => false,
+        }
+    }
+}
+
+/// Wrap unfenced code in fenced blocks with a language hint. Text without
+/// unfenced code is returned unchanged.
+pub fn fence_code(text: &str) -> String {
+    let mut out: Vec<String> = Vec::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut tracker = FenceTracker::default();
+    // Closing fence we generated last and its language, so code split by
+    // blank lines ends up in one block
+    let mut generated: Option<(usize, String)> = None;
+    let mut changed = false;
+
+    let flush = |paragraph: &mut Vec<&str>,
+                 out: &mut Vec<String>,
+                 generated: &mut Option<(usize, String)>,
+                 changed: &mut bool| {
+        if paragraph.is_empty() {
+            return;
+        }
+        let after_list = out
+            .iter()
+            .rev()
+            .find(|l| !l.trim().is_empty())
+            .is_some_and(|l| l.starts_with(char::is_whitespace) || is_list_item(l));
+
+        match split_code(paragraph, after_list) {
+            Some((prose, code)) => {
+                *changed = true;
+                if !prose.is_empty() {
+                    out.extend(prose.iter().map(|l| l.to_string()));
+                    *generated = None;
+                }
+                let language = detect_language(&code.join("\n")).unwrap_or("text");
+
+                let continues = generated.as_ref().is_some_and(|(at, lang)| {
+                    prose.is_empty()
+                        && lang == language
+                        && out[at + 1..].iter().all(|l| l.trim().is_empty())
+                });
+                if continues {
+                    let at = generated.as_ref().map(|(at, _)| *at).unwrap_or_default();
+                    out.remove(at);
+                } else {
+                    out.push(format!("```{}", language));
+                }
+                out.extend(code);
+                out.push("```".to_string());
+                *generated = Some((out.len() - 1, language.to_string()));
+            }
+            None => {
+                out.extend(paragraph.iter().map(|l| l.to_string()));
+                *generated = None;
+            }
+        }
+        paragraph.clear();
+    };
+
+    for line in text.lines() {
+        if tracker.update(line) {
+            flush(&mut paragraph, &mut out, &mut generated, &mut changed);
+            out.push(line.to_string());
+            generated = None;
+        } else if line.trim().is_empty() {
+            flush(&mut paragraph, &mut out, &mut generated, &mut changed);
+            out.push(line.to_string());
+        } else {
+            paragraph.push(line);
+        }
+    }
+    flush(&mut paragraph, &mut out, &mut generated, &mut changed);
+
+    if !changed {
+        return text.to_string();
+    }
+    let mut fenced = out.join("\n");
+    if text.ends_with('\n') {
+        fenced.push('\n');
+    }
+    fenced
+}
+
+/// Split a paragraph into leading prose and trailing code, if it ends in
+/// code. Code either makes up the whole paragraph or follows a line ending
+/// in a colon ("Run the tests with:").
+fn split_code<'a>(lines: &[&'a str], after_list: bool) -> Option<(Vec<&'a str>, Vec<String>)> {
+    // Indented code block, unless it continues a list item
+    if !after_list
+        && lines
+            .iter()
+            .all(|l| l.starts_with("    ") || l.starts_with('\t'))
+    {
+        let code = lines
+            .iter()
+            .map(|l| {
+                l.strip_prefix("    ")
+                    .or_else(|| l.strip_prefix('\t'))
+                    .unwrap_or(l)
+                    .to_string()
+            })
+            .collect();
+        return Some((vec![], code));
+    }
+
+    (0..lines.len())
+        .find(|&start| {
+            (start == 0 || lines[start - 1].trim_end().ends_with(':'))
+                && is_strong_code_line(lines[start])
+                && lines[start..].iter().all(|l| is_code_line(l))
+        })
+        .map(|start| {
+            let code = lines[start..].iter().map(|l| l.to_string()).collect();
+            (lines[..start].to_vec(), code)
+        })
+}
+
+fn is_list_item(line: &str) -> bool {
+    static LIST_ITEM: OnceLock<Regex> = OnceLock::new();
+    LIST_ITEM
+        .get_or_init(|| Regex::new(r"^\s*([-*+]|\d+[.)])\s").unwrap())
+        .is_match(line)
+}
+
+/// A line that on its own looks like code rather than prose
+fn is_strong_code_line(line: &str) -> bool {
+    static STRONG: OnceLock<RegexSet> = OnceLock::new();
+    let strong = STRONG.get_or_init(|| {
+        RegexSet::new([
+            r"^(pub(\([\w:]+\))? )?(async )?(unsafe )?fn \w+.*[({]$",
+            r"^(let|const|var) (mut )?[\w{}\[\], ]+(: ?[^=]+)? ?= ?.+$",
+            r"^(async )?def \w+\(.*\)( -> .+)?:$",
+            r"^class \w+(\(.*\))?:$",
+            r"^(export )?(default )?(abstract )?class \w+.*\{$",
+            r"^(impl|struct|enum|trait|interface|type) [\w<>, ]+.*[{=]$",
+            r"^(import [\w.]+( as \w+)?|from [\w.]+ import [\w, *]+)$",
+            r"^import .+ from ['\x22].+['\x22];?$",
+            r"^#include\s*[<\x22]",
+            r"^#!/",
+            r"^\$ \S",
+            r"^(if|for|while|switch) ?\(.*\) ?\{$",
+            r"^(async )?function\*? ?\w*\(.*\) ?\{$",
+            r"^func (\(.*\) )?\w+\(.*\{$",
+            r"^(SELECT|INSERT INTO|UPDATE|DELETE FROM|CREATE (TABLE|INDEX|VIEW)|ALTER TABLE) ",
+            r"^[\w.:!]+(::<[^>]+>)?\(.*\);?$",
+            r"^(sudo|npm|npx|yarn|pnpm|cargo|git|pip3?|python3?|node|docker|kubectl|make|curl|wget|brew|apt(-get)?|rustup|go|deno|bun|uv) [\w\-./@:=\x22'+]+( [\w\-./@:=\x22'+<>|&$]+)*$",
+            r"^(cd|ls|mkdir|rm|cp|mv|chmod|echo|export) .*[-/.=~$]",
+        ])
+        .unwrap()
+    });
+
+    let line = line.trim();
+    !line.ends_with('.') && strong.is_match(line)
+}
+
+/// A line that may appear inside a code paragraph
+fn is_code_line(line: &str) -> bool {
+    let trimmed = line.trim();
+    if trimmed.starts_with("# ") || trimmed.starts_with("> ") || trimmed.starts_with('|') {
+        return false;
+    }
+    is_strong_code_line(line)
+        || line.starts_with(char::is_whitespace)
+        || trimmed.ends_with(';')
+        || trimmed.ends_with('{')
+        || trimmed
+            .trim_end_matches([';', ',', ')'])
+            .chars()
+            .all(|c| "}])".contains(c))
+}
+
+/// Best guess at the language of a snippet, as a fence info string
+pub fn detect_language(code: &str) -> Option<&'static str> {
+    let trimmed = code.trim();
+    if (trimmed.starts_with('{') || trimmed.starts_with('['))
+        && serde_json::from_str::<serde_json::Value>(trimmed).is_ok()
+    {
+        return Some("json");
+    }
+    if let Some(shebang) = trimmed.lines().next().and_then(|l| l.strip_prefix("#!")) {
+        if shebang.contains("python") {
+            return Some("python");
+        }
+        if shebang.contains("node") {
+            return Some("javascript");
+        }
+        return Some("bash");
+    }
+
+    static LANGUAGES: OnceLock<Vec<(&'static str, RegexSet)>> = OnceLock::new();
+    let languages = LANGUAGES.get_or_init(|| {
+        let set = |patterns: &[&str]| RegexSet::new(patterns).unwrap();
+        vec![
+            (
+                "diff",
+                set(&[r"^@@ .* @@", r"^(\+\+\+|---) [ab]/", r"^diff --git "]),
+            ),
+            (
+                "rust",
+                set(&[
+                    r"\bfn \w+",
+                    r"\blet mut\b",
+                    r"^\s*(pub )?(impl|struct|enum|trait|mod|use) ",
+                    r"\w+::\w+",
+                    r"\w+!\(",
+                    r"#\[\w+",
+                    r"&mut |&self\b|-> (Self|Result|Option|String|Vec)",
+                ]),
+            ),
+            (
+                "python",
+                set(&[
+                    r"^\s*(async )?def \w+\(",
+                    r"^\s*(elif|except|finally)\b",
+                    r"^\s*from [\w.]+ import ",
+                    r"^\s*import \w+$",
+                    r"\bself\.\w+",
+                    r"^\s*(if|for|while|with|class) .*:$",
+                    r"\bNone\b|\bTrue\b|\bFalse\b",
+                ]),
+            ),
+            (
+                "typescript",
+                set(&[
+                    r": (string|number|boolean|void|any|unknown)\b",
+                    r"^\s*(export )?(interface|type) \w+",
+                    r"\bas const\b|<\w+>\(",
+                ]),
+            ),
+            (
+                "javascript",
+                set(&[
+                    r"\b(const|let|var) \w+ =",
+                    r"=>",
+                    r"console\.\w+\(",
+                    r"\bfunction\b",
+                    r"require\(",
+                    r"^\s*(import .+ from|export (default|const|function))",
+                ]),
+            ),
+            (
+                "go",
+                set(&[r"^\s*func ", r":= ", r"^\s*package \w+", r"fmt\.\w+\(", r"\berr != nil"]),
+            ),
+            (
+                "java",
+                set(&[
+                    r"\bpublic (static )?(class|void|final)",
+                    r"System\.out\.",
+                    r"^\s*(private|protected) \w+",
+                    r"@Override",
+                ]),
+            ),
+            (
+                "sql",
+                set(&[
+                    r"(?i)^\s*select .+",
+                    r"(?i)^\s*(from|where|join|group by|order by) ",
+                    r"(?i)^\s*(insert into|update \w+ set|delete from|create (table|index|view)|alter table)",
+                ]),
+            ),
+            (
+                "bash",
+                set(&[
+                    r"^\s*\$ ",
+                    r"^\s*(sudo|npm|npx|yarn|pnpm|cargo|git|pip3?|docker|kubectl|make|curl|wget|brew|apt(-get)?|rustup|cd|ls|mkdir|rm|cp|mv|chmod|echo|export) ",
+                    r"\$\{?\w+\}?",
+                    r"\s(&&|\|\|)\s|\s\|\s",
+                ]),
+            ),
+            (
+                "html",
+                set(&[r"^\s*<(!DOCTYPE|html|head|body|div|span|p|a|ul|li|section|script|style)\b"]),
+            ),
+            ("toml", set(&[r"^\s*\[[\w.\-]+\]$", r"^\s*[\w\-]+ = "])),
+            (
+                "dockerfile",
+                set(&[r"^(FROM|RUN|COPY|CMD|ENTRYPOINT|WORKDIR|ENV|EXPOSE) "]),
+            ),
+            ("c", set(&[r"^\s*#include\s*[<\x22]", r"\bprintf\(", r"\bint main\("])),
+            ("yaml", set(&[r"^\s*[\w\-]+:( |$)", r"^\s*- \S"])),
+        ]
+    });
+
+    let hits = |patterns: &RegexSet| {
+        trimmed
+            .lines()
+            .filter(|line| patterns.is_match(line))
+            .count()
+    };
+    let mut best: Option<(&'static str, usize)> = None;
+    for (language, patterns) in languages {
+        let count = hits(patterns);
+        if count > best.map(|(_, most)| most).unwrap_or(0) {
+            best = Some((language, count));
+        }
+    }
+
+    // TypeScript snippets also match the JavaScript patterns
+    let typescript = languages
+        .iter()
+        .find(|(language, _)| *language == "typescript");
+    match (best, typescript) {
+        (Some(("javascript", _)), Some((_, patterns))) if hits(patterns) > 0 => Some("typescript"),
+        (best, _) => best.map(|(language, _)| language),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unfenced_code_is_fenced_with_language() {
+        let text = "Prefer early returns.\n\
+                    \n\
+                    fn parse(input: &str) -> Result<Config, Error> {\n    \
+                    let value = input.parse()?;\n    \
+                    Ok(value)\n\
+                    }\n\
+                    \n\
+                    Run the tests with:\n\
+                    cargo test --workspace\n\
+                    \n\
+                    Indented example:\n\
+                    \n    \
+                    def greet(name):\n        \
+                    print(f\"hi {name}\")\n\
+                    \n\
+                    - use cargo fmt\n\
+                    \n    \
+                    this continues the list item\n";
+
+        assert_eq!(
+            fence_code(text),
+            "Prefer early returns.\n\
+             \n\
+             ```rust\n\
+             fn parse(input: &str) -> Result<Config, Error> {\n    \
+             let value = input.parse()?;\n    \
+             Ok(value)\n\
+             }\n\
+             ```\n\
+             \n\
+             Run the tests with:\n\
+             ```bash\n\
+             cargo test --workspace\n\
+             ```\n\
+             \n\
+             Indented example:\n\
+             \n\
+             ```python\n\
+             def greet(name):\n    \
+             print(f\"hi {name}\")\n\
+             ```\n\
+             \n\
+             - use cargo fmt\n\
+             \n    \
+             this continues the list item\n"
+        );
+    }
+
+    #[test]
+    fn test_existing_fences_and_prose_are_untouched() {
+        let text = "Keep functions small; split them up.\n\
+                    Call helpers instead of repeating code.\n\
+                    \n\
+                    ```rust,ignore title=\"lib.rs\"\n\
+                    # use std::io;\n\
+                    let x = 1;\n\
+                    ```\n\
+                    \n\
+                    ~~~~ {.python}\n\
+                    ~~~\n\
+                    import os\n\
+                    ~~~~";
+        assert_eq!(fence_code(text), text);
+    }
+
+    #[test]
+    fn test_fence_tracker() {
+        let mut tracker = FenceTracker::default();
+        let inside: Vec<bool> = [
+            "text",
+            "````md",
+            "```",
+            "# not a heading",
+            "````",
+            "# heading",
+        ]
+        .iter()
+        .map(|line| tracker.update(line))
+        .collect();
+        assert_eq!(inside, vec![false, true, true, true, true, false]);
+    }
+
+    #[test]
+    fn test_detect_language() {
+        assert_eq!(detect_language("{\"strict\": true}"), Some("json"));
+        assert_eq!(
+            detect_language("const add = (a: number, b: number) => a + b;"),
+            Some("typescript")
+        );
+        assert_eq!(
+            detect_language("const add = (a, b) => a + b;"),
+            Some("javascript")
+        );
+        assert_eq!(
+            detect_language("SELECT id\nFROM users\nWHERE active = 1"),
+            Some("sql")
+        );
+        assert_eq!(
+            detect_language("if err != nil {\n\treturn err\n}"),
+            Some("go")
+        );
+        assert_eq!(detect_language("just some words"), None);
+    }
+}
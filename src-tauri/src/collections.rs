@@ -0,0 +1,214 @@
+//! Nested collections
+//! Collections form a tree through `parent_id`. Moves are checked with the
+//! shared composition engine, so a collection can never end up inside
+//! itself or nest deeper than `composition::MAX_DEPTH`.
+
+use crate::composition::{self, CompositionError, ReferenceGraph, MAX_DEPTH};
+use crate::models::{Collection, CollectionItem};
+
+/// Longest collection name accepted
+const MAX_NAME_CHARS: usize = 100;
+
+/// Tidy a collection name and make sure no sibling under `parent_id` has it
+/// already. `except_id` is the collection being renamed or moved.
+pub fn validate_name(
+    collections: &[Collection],
+    name: &str,
+    parent_id: Option<&str>,
+    except_id: Option<&str>,
+) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Collection name can't be empty".to_string());
+    }
+    if name.chars().count() > MAX_NAME_CHARS {
+        return Err(format!(
+            "Collection names are limited to {} characters",
+            MAX_NAME_CHARS
+        ));
+    }
+    let taken = collections.iter().any(|c| {
+        Some(c.id.as_str()) != except_id
+            && c.parent_id.as_deref() == parent_id
+            && c.name.eq_ignore_ascii_case(name)
+    });
+    if taken {
+        return Err(format!("A collection named '{}' already exists here", name));
+    }
+    Ok(name.to_string())
+}
+
+/// Parent links of the collection tree with `moved` placed under `parent_id`
+struct ParentGraph<'a> {
+    collections: &'a [Collection],
+    moved: &'a str,
+    parent_id: Option<&'a str>,
+}
+
+impl ReferenceGraph for ParentGraph<'_> {
+    fn references(&self, node: &str) -> Option<Vec<String>> {
+        let parent = if node == self.moved {
+            self.parent_id.map(str::to_string)
+        } else {
+            self.collections
+                .iter()
+                .find(|c| c.id == node)?
+                .parent_id
+                .clone()
+        };
+        Some(parent.into_iter().collect())
+    }
+}
+
+/// Check that collection `id` (new or existing) can sit under `parent_id`
+/// together with everything nested in it
+pub fn check_parent(
+    collections: &[Collection],
+    id: &str,
+    parent_id: Option<&str>,
+) -> Result<(), String> {
+    let graph = ParentGraph {
+        collections,
+        moved: id,
+        parent_id,
+    };
+    let subtree = std::iter::once(id.to_string()).chain(descendants(collections, id));
+    for node in subtree {
+        match composition::check(&graph, &node).into_iter().next() {
+            None => {}
+            Some(CompositionError::Missing { reference, .. }) => {
+                return Err(format!("Collection '{}' not found", reference))
+            }
+            Some(CompositionError::Cycle(_)) => {
+                return Err("A collection can't be moved inside itself".to_string())
+            }
+            Some(CompositionError::TooDeep(_)) => {
+                return Err(format!(
+                    "Collections can't nest more than {} levels deep",
+                    MAX_DEPTH
+                ))
+            }
+        }
+    }
+    Ok(())
+}
+
+/// IDs of every collection nested anywhere below `id`
+pub fn descendants(collections: &[Collection], id: &str) -> Vec<String> {
+    let mut found: Vec<String> = Vec::new();
+    let mut queue = vec![id.to_string()];
+    while let Some(parent) = queue.pop() {
+        for child in collections
+            .iter()
+            .filter(|c| c.parent_id.as_deref() == Some(parent.as_str()))
+        {
+            if child.id != id && !found.contains(&child.id) {
+                found.push(child.id.clone());
+                queue.push(child.id.clone());
+            }
+        }
+    }
+    found
+}
+
+/// Items filed in a collection, and with `recursive` in its subcollections
+/// too. Each entity is listed once.
+pub fn items(collections: &[Collection], id: &str, recursive: bool) -> Vec<CollectionItem> {
+    let mut ids = vec![id.to_string()];
+    if recursive {
+        ids.extend(descendants(collections, id));
+    }
+
+    let mut items: Vec<CollectionItem> = Vec::new();
+    for collection in ids
+        .iter()
+        .filter_map(|id| collections.iter().find(|c| &c.id == id))
+    {
+        for item in &collection.items {
+            if !items.iter().any(|i| i.entity_id == item.entity_id) {
+                items.push(item.clone());
+            }
+        }
+    }
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn collection(id: &str, name: &str, parent_id: Option<&str>) -> Collection {
+        Collection {
+            id: id.to_string(),
+            name: name.to_string(),
+            parent_id: parent_id.map(str::to_string),
+            items: vec![CollectionItem {
+                entity_type: "skill".to_string(),
+                entity_id: format!("skill-{}", id),
+            }],
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_moves_are_checked_for_cycles_depth_and_missing_parents() {
+        let mut tree = vec![
+            collection("work", "Work", None),
+            collection("backend", "Backend", Some("work")),
+            collection("rust", "Rust", Some("backend")),
+        ];
+
+        assert!(check_parent(&tree, "new", Some("rust")).is_ok());
+        assert!(check_parent(&tree, "rust", None).is_ok());
+        assert!(check_parent(&tree, "work", Some("rust"))
+            .unwrap_err()
+            .contains("inside itself"));
+        assert!(check_parent(&tree, "work", Some("work")).is_err());
+        assert!(check_parent(&tree, "new", Some("gone"))
+            .unwrap_err()
+            .contains("not found"));
+
+        // Moving "work" under a chain pushes "rust" past the limit
+        for level in 0..MAX_DEPTH - 2 {
+            let parent = (level > 0).then(|| format!("deep-{}", level - 1));
+            tree.push(collection(
+                &format!("deep-{}", level),
+                "Deep",
+                parent.as_deref(),
+            ));
+        }
+        let last = format!("deep-{}", MAX_DEPTH - 3);
+        assert!(check_parent(&tree, "new", Some(&last)).is_ok());
+        assert!(check_parent(&tree, "work", Some(&last))
+            .unwrap_err()
+            .contains("levels deep"));
+    }
+
+    #[test]
+    fn test_names_and_recursive_items() {
+        let tree = vec![
+            collection("work", "Work", None),
+            collection("backend", "Backend", Some("work")),
+            collection("rust", "Rust", Some("backend")),
+        ];
+
+        assert_eq!(
+            validate_name(&tree, "  Ops ", Some("work"), None).unwrap(),
+            "Ops"
+        );
+        assert!(validate_name(&tree, "backend", Some("work"), None).is_err());
+        assert!(validate_name(&tree, "Backend", None, None).is_ok());
+        assert!(validate_name(&tree, "Backend", Some("work"), Some("backend")).is_ok());
+        assert!(validate_name(&tree, " ", None, None).is_err());
+
+        assert_eq!(descendants(&tree, "work"), vec!["backend", "rust"]);
+        assert_eq!(items(&tree, "work", false).len(), 1);
+        let all: Vec<String> = items(&tree, "work", true)
+            .into_iter()
+            .map(|i| i.entity_id)
+            .collect();
+        assert_eq!(all, vec!["skill-work", "skill-backend", "skill-rust"]);
+    }
+}
@@ -1,11 +1,74 @@
-use crate::db::ExportData;
+use crate::actions::{self, ActionTarget};
+use crate::activation;
+use crate::assembly::{self, AssemblyOptions};
+use crate::backup;
+use crate::bulk;
+use crate::capabilities;
+use crate::categories;
+use crate::character_card;
+use crate::chatgpt;
+use crate::collections;
+use crate::composition::{self, LibraryGraph};
+use crate::conflicts;
+use crate::copilot;
+use crate::cursor;
+use crate::db::{category_to_string, init_default_data, ExportData};
+use crate::diagnostics;
+use crate::encryption;
+use crate::experiments;
+use crate::formats;
+use crate::git_sync;
+use crate::html_import;
+use crate::ids;
+use crate::insights::{self, InsightMetric};
+use crate::journal;
+use crate::launcher;
+use crate::library_diff;
+use crate::library_workspaces;
+use crate::listing::{Filter, Table};
+use crate::llm;
+use crate::localization;
+use crate::mcp_kit;
 use crate::models::*;
+use crate::naming;
+use crate::notifications::{self, NotificationEvent};
+use crate::notion;
+use crate::onboarding;
+use crate::openai_assistant;
 use crate::parser;
+use crate::preflight;
+use crate::priorities;
+use crate::prompt_export;
+use crate::redaction::Redactor;
+use crate::releases;
+use crate::resolve::resolve_entity;
+use crate::retention;
+use crate::reviews;
+use crate::scope;
+use crate::secrets;
+use crate::selection;
+use crate::self_check;
+use crate::setup;
+use crate::snippets;
+use crate::subagent;
+use crate::suggestions;
+use crate::support_bundle;
+use crate::template;
+use crate::testing;
+use crate::tool_runner;
+use crate::tool_schema;
+use crate::upstream;
+use crate::usage_stats;
 use crate::AppState;
-use chrono::Utc;
-use std::process::{Command, Stdio};
-use tauri::State;
-use uuid::Uuid;
+use chrono::{Local, Utc};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, State};
+use tauri_plugin_clipboard_manager::ClipboardExt;
 
 // ============================================================================
 // Agent Commands
@@ -13,8 +76,8 @@ use uuid::Uuid;
 
 #[tauri::command]
 pub fn create_agent(state: State<'_, AppState>, agent: CreateAgentInput) -> Result<Agent, String> {
-    let agent = Agent {
-        id: Uuid::new_v4().to_string(),
+    let mut agent = Agent {
+        id: String::new(), // Claimed on insert
         name: agent.name,
         description: agent.description,
         avatar_emoji: agent.avatar_emoji,
@@ -27,12 +90,22 @@ pub fn create_agent(state: State<'_, AppState>, agent: CreateAgentInput) -> Resu
         updated_at: Utc::now(),
         usage_count: 0,
         last_used_at: None,
+        avatar_image_id: None,
+        aliases: agent.aliases,
+        instruction_order: agent.instruction_order,
+        include_global_instructions: agent.include_global_instructions,
+        parent_agent_id: agent.parent_agent_id,
     };
+    ensure_valid_parent(&state, &agent)?;
 
-    state
-        .db
-        .insert_agent(&agent)
-        .map_err(|e| format!("Failed to create agent: {}", e))?;
+    let name = agent.name.clone();
+    ids::claim(&name, |id| {
+        agent.id = id.to_string();
+        state.db.insert_agent(&agent)
+    })
+    .map_err(|e| format!("Failed to create agent: {}", e))?;
+
+    insights::record(&state.db, InsightMetric::Edit, "").ok();
 
     Ok(agent)
 }
@@ -45,6 +118,16 @@ pub fn get_agents(state: State<'_, AppState>) -> Result<Vec<Agent>, String> {
         .map_err(|e| format!("Failed to get agents: {}", e))
 }
 
+/// One page of the agents matching `query`, filtered and sorted in SQL
+#[tauri::command]
+pub fn list_agents(state: State<'_, AppState>, query: ListQuery) -> Result<Page<Agent>, String> {
+    let filter = Filter::new(&query, Table::Agents)?;
+    state
+        .db
+        .list_agents(&filter)
+        .map_err(|e| format!("Failed to list agents: {}", e))
+}
+
 #[tauri::command]
 pub fn get_agent(state: State<'_, AppState>, id: String) -> Result<Option<Agent>, String> {
     state
@@ -55,6 +138,9 @@ pub fn get_agent(state: State<'_, AppState>, id: String) -> Result<Option<Agent>
 
 #[tauri::command]
 pub fn update_agent(state: State<'_, AppState>, agent: Agent) -> Result<Agent, String> {
+    ensure_not_locked(&state, &agent.id)?;
+    ensure_valid_parent(&state, &agent)?;
+
     let mut agent = agent;
     agent.updated_at = Utc::now();
 
@@ -63,41 +149,216 @@ pub fn update_agent(state: State<'_, AppState>, agent: Agent) -> Result<Agent, S
         .update_agent(&agent)
         .map_err(|e| format!("Failed to update agent: {}", e))?;
 
+    insights::record(&state.db, InsightMetric::Edit, "").ok();
+
     Ok(agent)
 }
 
+/// Refuse a parent agent that is missing or would make the agent inherit
+/// from itself
+fn ensure_valid_parent(state: &State<'_, AppState>, agent: &Agent) -> Result<(), String> {
+    if agent.parent_agent_id.is_none() {
+        return Ok(());
+    }
+    let agents = state
+        .db
+        .get_all_agents()
+        .map_err(|e| format!("Failed to get agents: {}", e))?;
+    composition::ensure_valid_parent(&agents, agent)
+}
+
 #[tauri::command]
 pub fn delete_agent(state: State<'_, AppState>, id: String) -> Result<(), String> {
     state
         .db
         .delete_agent(&id)
-        .map_err(|e| format!("Failed to delete agent: {}", e))
+        .map_err(|e| format!("Failed to delete agent: {}", e))?;
+
+    insights::record(&state.db, InsightMetric::Edit, "").ok();
+    Ok(())
+}
+
+/// Parse agent text without saving it, along with anything the parser would
+/// silently default
+#[tauri::command]
+pub fn preview_agent_import(text: String) -> Result<ImportPreview<Agent>, String> {
+    Ok(ImportPreview {
+        entity: parser::parse_agent_from_markdown(&text)?,
+        diagnostics: diagnostics::check_agent(&text),
+    })
 }
 
 #[tauri::command]
 pub fn import_agent_from_text(state: State<'_, AppState>, text: String) -> Result<Agent, String> {
-    let mut agent = parser::parse_agent_from_markdown(&text)?;
-    agent.id = Uuid::new_v4().to_string();
-    agent.created_at = Utc::now();
-    agent.updated_at = Utc::now();
+    match import_text(&state, "agent-markdown", &text, false, None)?.pop() {
+        Some(LibraryItem::Agent(agent)) => Ok(agent),
+        _ => Err("No agent found in text".to_string()),
+    }
+}
+
+#[tauri::command]
+pub fn export_agent_to_markdown(
+    state: State<'_, AppState>,
+    id: String,
+    allow_secrets: Option<bool>,
+    redaction_profile: Option<String>,
+) -> Result<String, String> {
+    export_entity(
+        &state,
+        "agent-markdown",
+        &id,
+        allow_secrets,
+        redaction_profile,
+    )
+}
+
+/// An agent with its inherited fields filled in, redacted with
+/// `redaction_profile` along with the library it draws on, after refusing
+/// any secret in what it references
+fn prepared_agent_export(
+    state: &State<'_, AppState>,
+    id: &str,
+    redaction_profile: Option<String>,
+    allow_secrets: Option<bool>,
+) -> Result<(Agent, Vec<Skill>, Vec<Instruction>), String> {
+    let (agents, mut skills, mut instructions) = load_library(state, None)?;
+    let mut agent = composition::inherited(resolve_entity(&agents, id)?, &agents);
+    if let Some(redactor) = load_redactor(state, redaction_profile)? {
+        agent = redactor.redact_agent(&agent);
+        skills = skills.iter().map(|s| redactor.redact_skill(s)).collect();
+        instructions = instructions
+            .iter()
+            .map(|i| redactor.redact_instruction(i))
+            .collect();
+    }
+
+    // Every referenced instruction, enabled or not, since a kit ships them all
+    let mut findings = secrets::scan_agent(&agent);
+    for reference in &agent.skills {
+        if let Ok(skill) = resolve_entity(&skills, reference) {
+            findings.extend(secrets::scan_skill(skill));
+        }
+    }
+    for instruction in instructions
+        .iter()
+        .filter(|i| agent.instructions.contains(&i.id))
+    {
+        findings.extend(secrets::scan_instruction(instruction));
+    }
+    secrets::guard_export(&findings, allow_secrets.unwrap_or(false))?;
+
+    Ok((agent, skills, instructions))
+}
+
+/// Export an agent as a Claude Code subagent file (`.claude/agents/<name>.md`)
+/// with its skills and instructions assembled into the prompt. `tools` lists
+/// the built-in Claude Code tools it may use; without it the subagent gets
+/// every tool.
+#[tauri::command]
+pub fn export_agent_to_claude_code(
+    state: State<'_, AppState>,
+    id: String,
+    tools: Option<Vec<String>>,
+    allow_secrets: Option<bool>,
+    redaction_profile: Option<String>,
+) -> Result<String, String> {
+    let (agent, skills, instructions) =
+        prepared_agent_export(&state, &id, redaction_profile, allow_secrets)?;
+
+    Ok(subagent::export_subagent(
+        &agent,
+        &skills,
+        &instructions,
+        &tools.unwrap_or_default(),
+    ))
+}
+
+/// Export an agent as an OpenAI Assistants API object, pretty-printed JSON
+/// ready to `POST /v1/assistants`. `model` defaults to `gpt-4o`.
+#[tauri::command]
+pub fn export_agent_to_openai_assistant(
+    state: State<'_, AppState>,
+    id: String,
+    model: Option<String>,
+    allow_secrets: Option<bool>,
+    redaction_profile: Option<String>,
+) -> Result<String, String> {
+    let (agent, skills, instructions) =
+        prepared_agent_export(&state, &id, redaction_profile, allow_secrets)?;
+
+    let assistant =
+        openai_assistant::export_assistant(&agent, &skills, &instructions, model.as_deref());
+    serde_json::to_string_pretty(&assistant)
+        .map_err(|e| format!("Failed to serialize assistant: {}", e))
+}
+
+/// Write a self-contained kit for one agent into `dir`: its subagent file, a
+/// `.mcp.json` serving a release of just this agent, and a README
+#[tauri::command]
+pub fn export_agent_mcp_kit(
+    state: State<'_, AppState>,
+    id: String,
+    dir: String,
+    allow_secrets: Option<bool>,
+    redaction_profile: Option<String>,
+) -> Result<AgentKit, String> {
+    let (agent, skills, instructions) =
+        prepared_agent_export(&state, &id, redaction_profile, allow_secrets)?;
+    let release = mcp_kit::kit_release(&agent, &skills, &instructions)?;
+    mcp_kit::write_kit(&PathBuf::from(dir), &release)
+}
+
+/// Set an agent's image avatar from a data URL, or clear it with `None`
+#[tauri::command]
+pub fn set_agent_avatar(
+    state: State<'_, AppState>,
+    id: String,
+    image: Option<String>,
+) -> Result<Agent, String> {
+    ensure_not_locked(&state, &id)?;
+
+    let attachment = image
+        .as_deref()
+        .map(Attachment::from_data_url)
+        .transpose()?;
+
+    if let Some(ref attachment) = attachment {
+        state
+            .db
+            .insert_attachment(attachment)
+            .map_err(|e| format!("Failed to save avatar: {}", e))?;
+    }
 
     state
         .db
-        .insert_agent(&agent)
-        .map_err(|e| format!("Failed to import agent: {}", e))?;
+        .set_agent_avatar_image(&id, attachment.as_ref().map(|a| a.id.as_str()))
+        .map_err(|e| format!("Failed to set avatar: {}", e))?;
 
-    Ok(agent)
+    state
+        .db
+        .get_agent(&id)
+        .map_err(|e| format!("Failed to get agent: {}", e))?
+        .ok_or_else(|| "Agent not found".to_string())
 }
 
+/// Get an agent's image avatar as a data URL, if it has one
 #[tauri::command]
-pub fn export_agent_to_markdown(state: State<'_, AppState>, id: String) -> Result<String, String> {
+pub fn get_agent_avatar(state: State<'_, AppState>, id: String) -> Result<Option<String>, String> {
     let agent = state
         .db
         .get_agent(&id)
         .map_err(|e| format!("Failed to get agent: {}", e))?
         .ok_or_else(|| "Agent not found".to_string())?;
 
-    Ok(parser::export_agent_to_markdown_text(&agent))
+    let Some(attachment_id) = agent.avatar_image_id else {
+        return Ok(None);
+    };
+
+    Ok(state
+        .db
+        .get_attachment(&attachment_id)
+        .map_err(|e| format!("Failed to get avatar: {}", e))?
+        .map(|a| a.to_data_url()))
 }
 
 // ============================================================================
@@ -106,8 +367,10 @@ pub fn export_agent_to_markdown(state: State<'_, AppState>, id: String) -> Resul
 
 #[tauri::command]
 pub fn create_skill(state: State<'_, AppState>, skill: CreateSkillInput) -> Result<Skill, String> {
-    let skill = Skill {
-        id: Uuid::new_v4().to_string(),
+    tool_schema::validate_definition(&skill.definition)?;
+
+    let mut skill = Skill {
+        id: String::new(), // Claimed on insert
         name: skill.name,
         description: skill.description,
         icon_emoji: skill.icon_emoji,
@@ -116,12 +379,20 @@ pub fn create_skill(state: State<'_, AppState>, skill: CreateSkillInput) -> Resu
         enabled: skill.enabled,
         created_at: Utc::now(),
         updated_at: Utc::now(),
+        aliases: skill.aliases,
+        usage_count: 0,
+        last_used_at: None,
     };
+    ensure_valid_chain(&state, &skill)?;
 
-    state
-        .db
-        .insert_skill(&skill)
-        .map_err(|e| format!("Failed to create skill: {}", e))?;
+    let name = skill.name.clone();
+    ids::claim(&name, |id| {
+        skill.id = id.to_string();
+        state.db.insert_skill(&skill)
+    })
+    .map_err(|e| format!("Failed to create skill: {}", e))?;
+
+    insights::record(&state.db, InsightMetric::Edit, "").ok();
 
     Ok(skill)
 }
@@ -134,6 +405,16 @@ pub fn get_skills(state: State<'_, AppState>) -> Result<Vec<Skill>, String> {
         .map_err(|e| format!("Failed to get skills: {}", e))
 }
 
+/// One page of the skills matching `query`, filtered and sorted in SQL
+#[tauri::command]
+pub fn list_skills(state: State<'_, AppState>, query: ListQuery) -> Result<Page<Skill>, String> {
+    let filter = Filter::new(&query, Table::Skills)?;
+    state
+        .db
+        .list_skills(&filter)
+        .map_err(|e| format!("Failed to list skills: {}", e))
+}
+
 #[tauri::command]
 pub fn get_skill(state: State<'_, AppState>, id: String) -> Result<Option<Skill>, String> {
     state
@@ -144,6 +425,11 @@ pub fn get_skill(state: State<'_, AppState>, id: String) -> Result<Option<Skill>
 
 #[tauri::command]
 pub fn update_skill(state: State<'_, AppState>, skill: Skill) -> Result<Skill, String> {
+    ensure_not_locked(&state, &skill.id)?;
+    tool_schema::validate_definition(&skill.definition)?;
+
+    ensure_valid_chain(&state, &skill)?;
+
     let mut skill = skill;
     skill.updated_at = Utc::now();
 
@@ -152,15 +438,190 @@ pub fn update_skill(state: State<'_, AppState>, skill: Skill) -> Result<Skill, S
         .update_skill(&skill)
         .map_err(|e| format!("Failed to update skill: {}", e))?;
 
+    insights::record(&state.db, InsightMetric::Edit, "").ok();
+
     Ok(skill)
 }
 
+/// Refuse a skill whose `follows` chain loops, names missing skills or
+/// includes skills that aren't prompt skills
+fn ensure_valid_chain(state: &State<'_, AppState>, skill: &Skill) -> Result<(), String> {
+    if composition::follows(skill).is_empty() {
+        return Ok(());
+    }
+    let mut skills = state
+        .db
+        .get_all_skills()
+        .map_err(|e| format!("Failed to get skills: {}", e))?;
+    skills.retain(|s| s.id != skill.id);
+    skills.push(skill.clone());
+    composition::skill_chain(skill, &skills).map(|_| ())
+}
+
+/// Make one change to every listed skill in a single transaction; returns
+/// the skills it changed
+#[tauri::command]
+pub fn bulk_update_skills(
+    state: State<'_, AppState>,
+    ids: Vec<String>,
+    update: BulkUpdate,
+) -> Result<Vec<Skill>, String> {
+    let mut skills: Vec<Skill> = Vec::new();
+    for id in &ids {
+        if skills.iter().any(|s| &s.id == id) {
+            continue;
+        }
+        ensure_not_locked(&state, id)?;
+        let skill = state
+            .db
+            .get_skill(id)
+            .map_err(|e| format!("Failed to get skill: {}", e))?
+            .ok_or_else(|| format!("Skill '{}' not found", id))?;
+        skills.push(skill);
+    }
+
+    let changed = bulk::update_skills(skills, &update)?;
+    state
+        .db
+        .bulk_update_skills(&changed)
+        .map_err(|e| format!("Failed to update skills: {}", e))?;
+
+    if !changed.is_empty() {
+        insights::record(&state.db, InsightMetric::Edit, "").ok();
+    }
+    Ok(changed)
+}
+
 #[tauri::command]
 pub fn delete_skill(state: State<'_, AppState>, id: String) -> Result<(), String> {
     state
         .db
         .delete_skill(&id)
-        .map_err(|e| format!("Failed to delete skill: {}", e))
+        .map_err(|e| format!("Failed to delete skill: {}", e))?;
+
+    insights::record(&state.db, InsightMetric::Edit, "").ok();
+    Ok(())
+}
+
+/// Fill in a prompt skill's `{{variables}}`, after the skills it follows
+#[tauri::command]
+pub fn render_skill(
+    state: State<'_, AppState>,
+    id: String,
+    arguments: Option<serde_json::Map<String, serde_json::Value>>,
+) -> Result<String, String> {
+    let (_, skills, _) = load_library(&state, None)?;
+    let skill = skills
+        .iter()
+        .find(|s| s.id == id)
+        .ok_or_else(|| "Skill not found".to_string())?;
+
+    let prompt = template::render_skill_chain(skill, &skills, &arguments.unwrap_or_default())?;
+    record_skill_usage(&state, &skill.id)?;
+    Ok(prompt)
+}
+
+/// Validate a tool definition's parameters and return the JSON Schema they produce
+#[tauri::command]
+pub fn validate_tool_parameters(definition: SkillDefinition) -> Result<serde_json::Value, String> {
+    let SkillDefinition::Tool { parameters, .. } = definition else {
+        return Err("Only tool skills have parameters".to_string());
+    };
+    tool_schema::validate_parameters(&parameters)?;
+    Ok(tool_schema::parameters_to_input_schema(&parameters))
+}
+
+/// Generate the `inputSchema` for a saved tool skill
+#[tauri::command]
+pub fn generate_input_schema(
+    state: State<'_, AppState>,
+    skill_id: String,
+) -> Result<serde_json::Value, String> {
+    let skill = state
+        .db
+        .get_skill(&skill_id)
+        .map_err(|e| format!("Failed to get skill: {}", e))?
+        .ok_or_else(|| "Skill not found".to_string())?;
+
+    tool_schema::skill_to_mcp_tool(&skill).map(|tool| tool["inputSchema"].clone())
+}
+
+/// Let MCP clients run this tool skill's handler as it reads now. Editing the
+/// handler, or importing or syncing over the skill, withdraws the approval.
+#[tauri::command]
+pub fn approve_tool_skill(state: State<'_, AppState>, skill_id: String) -> Result<(), String> {
+    let skill = state
+        .db
+        .get_skill(&skill_id)
+        .map_err(|e| format!("Failed to get skill: {}", e))?
+        .ok_or_else(|| "Skill not found".to_string())?;
+    let SkillDefinition::Tool { handler, .. } = &skill.definition else {
+        return Err("Only tool skills have handlers".to_string());
+    };
+    if handler.trim().is_empty() {
+        return Err(format!("Skill '{}' has no handler to approve", skill.name));
+    }
+    state
+        .db
+        .approve_tool_handler(&skill.id, &tool_runner::handler_digest(handler))
+        .map_err(|e| format!("Failed to approve handler: {}", e))
+}
+
+/// Stop MCP clients from running this tool skill's handler
+#[tauri::command]
+pub fn revoke_tool_skill(state: State<'_, AppState>, skill_id: String) -> Result<(), String> {
+    state
+        .db
+        .revoke_tool_approval(&skill_id)
+        .map_err(|e| format!("Failed to revoke approval: {}", e))
+}
+
+/// Ids of the tool skills whose current handler is approved
+#[tauri::command]
+pub fn get_approved_tool_skills(state: State<'_, AppState>) -> Result<Vec<String>, String> {
+    let approvals = state
+        .db
+        .get_tool_approvals()
+        .map_err(|e| format!("Failed to get approvals: {}", e))?;
+    let skills = state
+        .db
+        .get_all_skills()
+        .map_err(|e| format!("Failed to get skills: {}", e))?;
+    Ok(skills
+        .into_iter()
+        .filter(|skill| tool_runner::is_approved(skill, &approvals))
+        .map(|skill| skill.id)
+        .collect())
+}
+
+/// Import MCP tool definitions (a single tool, an array, or a `tools/list`
+/// result) as tool skills
+#[tauri::command]
+pub fn import_skills_from_mcp_tools(
+    state: State<'_, AppState>,
+    text: String,
+) -> Result<Vec<Skill>, String> {
+    let skills = import_text(&state, "mcp-tool", &text, false, None)?
+        .into_iter()
+        .filter_map(|item| match item {
+            LibraryItem::Skill(skill) => Some(skill),
+            _ => None,
+        })
+        .collect();
+
+    insights::record(&state.db, InsightMetric::Edit, "").ok();
+    Ok(skills)
+}
+
+/// Export a tool skill as an MCP tool definition (`name`, `description`, `inputSchema`)
+#[tauri::command]
+pub fn export_skill_to_mcp_tool(
+    state: State<'_, AppState>,
+    id: String,
+    allow_secrets: Option<bool>,
+    redaction_profile: Option<String>,
+) -> Result<String, String> {
+    export_entity(&state, "mcp-tool", &id, allow_secrets, redaction_profile)
 }
 
 // ============================================================================
@@ -172,34 +633,144 @@ pub fn create_instruction(
     state: State<'_, AppState>,
     instruction: CreateInstructionInput,
 ) -> Result<Instruction, String> {
-    let instruction = Instruction {
-        id: Uuid::new_v4().to_string(),
+    // Blank fields start from the category's template
+    let template = categories::category_template(&instruction.category);
+    let mut instruction = Instruction {
+        id: String::new(), // Claimed on insert
         name: instruction.name,
         description: instruction.description,
-        icon_emoji: instruction.icon_emoji,
+        icon_emoji: if instruction.icon_emoji.trim().is_empty() {
+            template.icon_emoji
+        } else {
+            instruction.icon_emoji
+        },
         category: instruction.category,
-        content: instruction.content,
+        content: if instruction.content.trim().is_empty() {
+            template.content
+        } else {
+            instruction.content
+        },
         priority: instruction.priority,
         tags: instruction.tags,
         enabled: instruction.enabled,
         created_at: Utc::now(),
         updated_at: Utc::now(),
+        pin_to_context: instruction.pin_to_context,
+        subcategory: instruction
+            .subcategory
+            .as_deref()
+            .and_then(categories::normalize_subcategory),
+        branch_pattern: instruction
+            .branch_pattern
+            .as_deref()
+            .and_then(scope::normalize_branch_pattern),
+        file_pattern: instruction
+            .file_pattern
+            .as_deref()
+            .and_then(scope::normalize_file_pattern),
+        contexts: scope::normalize_contexts(&instruction.contexts),
+        usage_count: 0,
+        last_used_at: None,
+        review_due_at: instruction.review_due_at,
+        upstream_url: None,
+        upstream_synced_at: None,
+        upstream_hash: None,
+        activation: match &instruction.activation {
+            Some(activation) => activation::normalize(activation)?,
+            None => None,
+        },
     };
 
-    state
-        .db
-        .insert_instruction(&instruction)
-        .map_err(|e| format!("Failed to create instruction: {}", e))?;
+    let name = instruction.name.clone();
+    ids::claim(&name, |id| {
+        instruction.id = id.to_string();
+        state.db.insert_instruction(&instruction)
+    })
+    .map_err(|e| format!("Failed to create instruction: {}", e))?;
+
+    insights::record(&state.db, InsightMetric::Edit, "").ok();
 
     Ok(instruction)
 }
 
 #[tauri::command]
-pub fn get_instructions(state: State<'_, AppState>) -> Result<Vec<Instruction>, String> {
+pub fn get_instructions(
+    state: State<'_, AppState>,
+    category: Option<String>,
+) -> Result<Vec<Instruction>, String> {
+    let instructions = state
+        .db
+        .get_all_instructions()
+        .map_err(|e| format!("Failed to get instructions: {}", e))?;
+
+    Ok(match category {
+        Some(filter) => instructions
+            .into_iter()
+            .filter(|i| categories::matches_category(i, &filter))
+            .collect(),
+        None => instructions,
+    })
+}
+
+/// One page of the instructions matching `query`, filtered and sorted in SQL
+#[tauri::command]
+pub fn list_instructions(
+    state: State<'_, AppState>,
+    query: ListQuery,
+) -> Result<Page<Instruction>, String> {
+    let filter = Filter::new(&query, Table::Instructions)?;
     state
+        .db
+        .list_instructions(&filter)
+        .map_err(|e| format!("Failed to list instructions: {}", e))
+}
+
+/// Enabled instructions for a session in `contexts` (languages, projects,
+/// clients) working on a file `path` or the files of a `diff`
+#[tauri::command]
+pub fn get_instructions_for_context(
+    state: State<'_, AppState>,
+    contexts: Vec<String>,
+    path: Option<String>,
+    diff: Option<String>,
+) -> Result<Vec<Instruction>, String> {
+    let (_, _, mut instructions) = load_library(&state, None)?;
+    apply_activation(&state, &mut instructions)?;
+    let paths = suggestions::context_paths(path.as_deref(), diff.as_deref());
+
+    let found = scope::instructions_for_context(&instructions, &contexts, &paths);
+    Ok(found.into_iter().cloned().collect())
+}
+
+/// Default icon and skeleton content for new instructions in a category
+#[tauri::command]
+pub fn get_category_template(category: InstructionCategory) -> CategoryTemplate {
+    categories::category_template(&category)
+}
+
+/// The category hierarchy with instruction counts rolled up to each parent
+#[tauri::command]
+pub fn get_category_tree(state: State<'_, AppState>) -> Result<Vec<CategoryNode>, String> {
+    let instructions = state
+        .db
+        .get_all_instructions()
+        .map_err(|e| format!("Failed to get instructions: {}", e))?;
+
+    Ok(categories::category_tree(&instructions))
+}
+
+/// Enabled instructions whose review date has passed, longest overdue first
+#[tauri::command]
+pub fn list_due_for_review(state: State<'_, AppState>) -> Result<Vec<Instruction>, String> {
+    let instructions = state
         .db
         .get_all_instructions()
-        .map_err(|e| format!("Failed to get instructions: {}", e))
+        .map_err(|e| format!("Failed to get instructions: {}", e))?;
+
+    Ok(reviews::due_for_review(&instructions, Utc::now())
+        .into_iter()
+        .cloned()
+        .collect())
 }
 
 #[tauri::command]
@@ -218,202 +789,2603 @@ pub fn update_instruction(
     state: State<'_, AppState>,
     instruction: Instruction,
 ) -> Result<Instruction, String> {
+    ensure_not_locked(&state, &instruction.id)?;
+
     let mut instruction = instruction;
     instruction.updated_at = Utc::now();
+    instruction.subcategory = instruction
+        .subcategory
+        .as_deref()
+        .and_then(categories::normalize_subcategory);
+    instruction.branch_pattern = instruction
+        .branch_pattern
+        .as_deref()
+        .and_then(scope::normalize_branch_pattern);
+    instruction.file_pattern = instruction
+        .file_pattern
+        .as_deref()
+        .and_then(scope::normalize_file_pattern);
+    instruction.contexts = scope::normalize_contexts(&instruction.contexts);
+    instruction.activation = match &instruction.activation {
+        Some(activation) => activation::normalize(activation)?,
+        None => None,
+    };
 
     state
         .db
         .update_instruction(&instruction)
         .map_err(|e| format!("Failed to update instruction: {}", e))?;
 
+    insights::record(&state.db, InsightMetric::Edit, "").ok();
+
     Ok(instruction)
 }
 
+/// Make one change to every listed instruction in a single transaction;
+/// returns the instructions it changed
+#[tauri::command]
+pub fn bulk_update_instructions(
+    state: State<'_, AppState>,
+    ids: Vec<String>,
+    update: BulkUpdate,
+) -> Result<Vec<Instruction>, String> {
+    let mut instructions: Vec<Instruction> = Vec::new();
+    for id in &ids {
+        if instructions.iter().any(|i| &i.id == id) {
+            continue;
+        }
+        ensure_not_locked(&state, id)?;
+        let instruction = state
+            .db
+            .get_instruction(id)
+            .map_err(|e| format!("Failed to get instruction: {}", e))?
+            .ok_or_else(|| format!("Instruction '{}' not found", id))?;
+        instructions.push(instruction);
+    }
+
+    let changed = bulk::update_instructions(instructions, &update)?;
+    state
+        .db
+        .bulk_update_instructions(&changed)
+        .map_err(|e| format!("Failed to update instructions: {}", e))?;
+
+    if !changed.is_empty() {
+        insights::record(&state.db, InsightMetric::Edit, "").ok();
+    }
+    Ok(changed)
+}
+
 #[tauri::command]
 pub fn delete_instruction(state: State<'_, AppState>, id: String) -> Result<(), String> {
     state
         .db
         .delete_instruction(&id)
-        .map_err(|e| format!("Failed to delete instruction: {}", e))
+        .map_err(|e| format!("Failed to delete instruction: {}", e))?;
+
+    insights::record(&state.db, InsightMetric::Edit, "").ok();
+    Ok(())
 }
 
+/// Propose priorities spread evenly over 1–10, ranked by how widely each
+/// enabled instruction is used, how often its category is applied over the
+/// last 30 days, category coverage and length
 #[tauri::command]
-pub fn import_instruction_from_text(
+pub fn suggest_priorities(state: State<'_, AppState>) -> Result<Vec<PrioritySuggestion>, String> {
+    let (agents, _, instructions) = load_library(&state, None)?;
+    let usage = insights::category_usage(&state.db, 30)
+        .map_err(|e| format!("Failed to get insights: {}", e))?;
+
+    Ok(priorities::suggest(&instructions, &agents, &usage))
+}
+
+/// Set the suggested priority on each listed instruction. Returns how many
+/// instructions changed.
+#[tauri::command]
+pub fn apply_priorities(
     state: State<'_, AppState>,
-    text: String,
-) -> Result<Instruction, String> {
-    let mut instruction = parser::parse_instruction_from_markdown(&text)?;
-    instruction.id = Uuid::new_v4().to_string();
-    instruction.created_at = Utc::now();
-    instruction.updated_at = Utc::now();
+    suggestions: Vec<PrioritySuggestion>,
+) -> Result<usize, String> {
+    for suggestion in &suggestions {
+        ensure_not_locked(&state, &suggestion.instruction_id)?;
+    }
 
-    state
-        .db
-        .insert_instruction(&instruction)
-        .map_err(|e| format!("Failed to import instruction: {}", e))?;
+    let mut changed = 0;
+    for suggestion in &suggestions {
+        let Some(mut instruction) = state
+            .db
+            .get_instruction(&suggestion.instruction_id)
+            .map_err(|e| format!("Failed to get instruction: {}", e))?
+        else {
+            continue;
+        };
+        let priority = suggestion.suggested.clamp(1, 10);
+        if instruction.priority == priority {
+            continue;
+        }
+        instruction.priority = priority;
+        instruction.updated_at = Utc::now();
+        state
+            .db
+            .update_instruction(&instruction)
+            .map_err(|e| format!("Failed to update instruction: {}", e))?;
+        changed += 1;
+    }
 
-    Ok(instruction)
+    if changed > 0 {
+        insights::record(&state.db, InsightMetric::Edit, "").ok();
+    }
+    Ok(changed)
 }
 
+/// Start an A/B experiment on an instruction: MCP sessions are served either
+/// its current content or `variant_b`. Replacing the variant of a running
+/// experiment starts it over.
 #[tauri::command]
-pub fn export_instruction_to_markdown(
+pub fn start_experiment(
     state: State<'_, AppState>,
-    id: String,
-) -> Result<String, String> {
+    instruction_id: String,
+    variant_b: String,
+) -> Result<Experiment, String> {
     let instruction = state
         .db
-        .get_instruction(&id)
+        .get_instruction(&instruction_id)
         .map_err(|e| format!("Failed to get instruction: {}", e))?
-        .ok_or_else(|| "Instruction not found".to_string())?;
+        .ok_or_else(|| format!("Instruction '{}' not found", instruction_id))?;
+    let variant_b = variant_b.trim().to_string();
+    if variant_b.is_empty() {
+        return Err("Variant B can't be empty".to_string());
+    }
+    if variant_b == instruction.content.trim() {
+        return Err("Variant B is the same as the instruction's content".to_string());
+    }
 
-    Ok(parser::export_instruction_to_markdown_text(&instruction))
+    let experiment = Experiment {
+        instruction_id: instruction.id,
+        variant_b,
+        started_at: Utc::now(),
+    };
+    state
+        .db
+        .save_experiment(&experiment)
+        .map_err(|e| format!("Failed to start experiment: {}", e))?;
+    Ok(experiment)
 }
 
-// ============================================================================
-// Settings Commands
-// ============================================================================
-
+/// Stop serving variant B of an instruction. Returns whether an experiment
+/// was running.
 #[tauri::command]
-pub fn get_settings(state: State<'_, AppState>) -> Result<Settings, String> {
-    let mut settings = state
+pub fn stop_experiment(state: State<'_, AppState>, instruction_id: String) -> Result<bool, String> {
+    state
         .db
-        .get_settings()
-        .map_err(|e| format!("Failed to get settings: {}", e))?;
-
-    // Update runtime state
-    settings.mcp_server_enabled = *state.mcp_running.lock().unwrap();
-
-    Ok(settings)
+        .delete_experiment(&instruction_id)
+        .map_err(|e| format!("Failed to stop experiment: {}", e))
 }
 
+/// How many MCP sessions were served each variant of the running experiments.
+/// Sessions are only counted while insights are enabled.
 #[tauri::command]
-pub fn save_settings(state: State<'_, AppState>, settings: Settings) -> Result<Settings, String> {
-    state
+pub fn get_experiment_results(state: State<'_, AppState>) -> Result<Vec<ExperimentResult>, String> {
+    let instructions = state
         .db
-        .save_settings(&settings)
-        .map_err(|e| format!("Failed to save settings: {}", e))?;
+        .get_all_instructions()
+        .map_err(|e| format!("Failed to get instructions: {}", e))?;
+    experiments::results(&state.db, &instructions)
+        .map_err(|e| format!("Failed to get experiment results: {}", e))
+}
+
+/// Parse instruction text without saving it, along with anything the parser
+/// would silently default
+#[tauri::command]
+pub fn preview_instruction_import(text: String) -> Result<ImportPreview<Instruction>, String> {
+    Ok(ImportPreview {
+        entity: parser::parse_instruction_from_markdown(&text)?,
+        diagnostics: diagnostics::check_instruction(&text),
+    })
+}
+
+#[tauri::command]
+pub fn import_instruction_from_text(
+    state: State<'_, AppState>,
+    text: String,
+) -> Result<Instruction, String> {
+    match import_text(&state, "instruction-markdown", &text, false, None)?.pop() {
+        Some(LibraryItem::Instruction(instruction)) => Ok(instruction),
+        _ => Err("No instruction found in text".to_string()),
+    }
+}
+
+/// Fetch a web or Confluence page and import its main content as an instruction.
+/// `token` is sent as a bearer token for wikis that need authentication.
+#[tauri::command]
+pub fn import_instruction_from_url(
+    state: State<'_, AppState>,
+    url: String,
+    category: Option<String>,
+    token: Option<String>,
+) -> Result<Instruction, String> {
+    let url = url.trim();
+    let html = html_import::fetch(url, token.as_deref())?;
+    let page = html_import::convert(&html, url);
+    if page.markdown.trim().is_empty() {
+        return Err(format!("No readable content found at {}", url));
+    }
+
+    let mut instruction = Instruction {
+        name: page.title,
+        description: format!("Imported from {}", url),
+        upstream_url: Some(url.to_string()),
+        upstream_synced_at: Some(Utc::now()),
+        upstream_hash: Some(upstream::content_hash(&page.markdown)),
+        content: page.markdown,
+        ..Instruction::default()
+    };
+    if let Some(category) = category.as_deref().and_then(categories::normalize_subcategory) {
+        (instruction.category, instruction.subcategory) = categories::parse_category_path(&category);
+    }
+
+    let name = instruction.name.clone();
+    ids::claim(&name, |id| {
+        instruction.id = id.to_string();
+        state.db.insert_instruction(&instruction)
+    })
+    .map_err(|e| format!("Failed to import instruction: {}", e))?;
+
+    Ok(instruction)
+}
+
+/// Fetch the page every instruction imported from a URL came from and report
+/// which pages changed since their content was imported
+#[tauri::command]
+pub fn check_upstream_updates(
+    state: State<'_, AppState>,
+    token: Option<String>,
+) -> Result<Vec<UpstreamCheck>, String> {
+    let instructions = state
+        .db
+        .get_all_instructions()
+        .map_err(|e| format!("Failed to get instructions: {}", e))?;
+
+    Ok(upstream::check(&instructions, |url| {
+        let html = html_import::fetch(url, token.as_deref())?;
+        Ok(html_import::convert(&html, url).markdown)
+    }))
+}
+
+/// List the databases in an unzipped Notion "Markdown & CSV" export
+#[tauri::command]
+pub fn get_notion_databases(dir: String) -> Result<Vec<NotionDatabase>, String> {
+    notion::find_databases(&PathBuf::from(dir))
+}
+
+/// Import one database from a Notion export as instructions, mapping its
+/// columns onto instruction fields
+#[tauri::command]
+pub fn import_notion_database(
+    state: State<'_, AppState>,
+    dir: String,
+    file_name: String,
+    mapping: NotionMapping,
+) -> Result<NotionImportResult, String> {
+    let (csv, pages) = notion::read_database(&PathBuf::from(dir), &file_name)?;
+    let result = notion::import_database(&csv, &pages, &mapping)?;
+
+    journal::run(
+        &state.db,
+        &journal::Operation::AddInstructions {
+            instructions: result.imported.clone(),
+        },
+    )?;
+
+    Ok(result)
+}
+
+/// Import a repository's CLAUDE.md or AGENTS.md as one instruction per
+/// section. `path` is the file itself or the repository root.
+#[tauri::command]
+pub fn import_project_instructions(
+    state: State<'_, AppState>,
+    path: String,
+) -> Result<Vec<Instruction>, String> {
+    let instructions = setup::project_instructions(&PathBuf::from(path))?;
+
+    journal::run(
+        &state.db,
+        &journal::Operation::AddInstructions {
+            instructions: instructions.clone(),
+        },
+    )?;
+
+    Ok(instructions)
+}
+
+/// Import Cursor rules as instructions. `path` is a repository root, a
+/// `.cursor/rules` directory, a `.cursorrules` file or a single `.mdc` rule.
+#[tauri::command]
+pub fn import_cursor_rules(
+    state: State<'_, AppState>,
+    path: String,
+) -> Result<Vec<Instruction>, String> {
+    let instructions = cursor::read_rules(&PathBuf::from(path))?;
+
+    journal::run(
+        &state.db,
+        &journal::Operation::AddInstructions {
+            instructions: instructions.clone(),
+        },
+    )?;
+
+    Ok(instructions)
+}
+
+/// Import GitHub Copilot instructions. `path` is a repository root, a
+/// `.github/instructions` directory or a single instructions file.
+#[tauri::command]
+pub fn import_copilot_instructions(
+    state: State<'_, AppState>,
+    path: String,
+) -> Result<Vec<Instruction>, String> {
+    let instructions = copilot::read_instructions(&PathBuf::from(path))?;
+
+    journal::run(
+        &state.db,
+        &journal::Operation::AddInstructions {
+            instructions: instructions.clone(),
+        },
+    )?;
+
+    Ok(instructions)
+}
+
+#[tauri::command]
+pub fn export_instruction_to_markdown(
+    state: State<'_, AppState>,
+    id: String,
+    allow_secrets: Option<bool>,
+    redaction_profile: Option<String>,
+) -> Result<String, String> {
+    export_entity(
+        &state,
+        "instruction-markdown",
+        &id,
+        allow_secrets,
+        redaction_profile,
+    )
+}
+
+/// Render an instruction as a `.cursor/rules` file
+#[tauri::command]
+pub fn export_instruction_to_cursor_rule(
+    state: State<'_, AppState>,
+    id: String,
+    allow_secrets: Option<bool>,
+    redaction_profile: Option<String>,
+) -> Result<String, String> {
+    export_entity(&state, "cursor-rule", &id, allow_secrets, redaction_profile)
+}
+
+/// Render an instruction as a `.github/instructions/*.instructions.md` file
+#[tauri::command]
+pub fn export_instruction_to_copilot(
+    state: State<'_, AppState>,
+    id: String,
+    allow_secrets: Option<bool>,
+    redaction_profile: Option<String>,
+) -> Result<String, String> {
+    export_entity(
+        &state,
+        "copilot-instructions",
+        &id,
+        allow_secrets,
+        redaction_profile,
+    )
+}
+
+// ============================================================================
+// Collection Commands
+// ============================================================================
+
+#[tauri::command]
+pub fn get_collections(state: State<'_, AppState>) -> Result<Vec<Collection>, String> {
+    state
+        .db
+        .get_all_collections()
+        .map_err(|e| format!("Failed to get collections: {}", e))
+}
+
+#[tauri::command]
+pub fn create_collection(
+    state: State<'_, AppState>,
+    name: String,
+    parent_id: Option<String>,
+) -> Result<Collection, String> {
+    let existing = load_collections(&state)?;
+    let id = ids::random_id();
+    collections::check_parent(&existing, &id, parent_id.as_deref())?;
+    let collection = Collection {
+        name: collections::validate_name(&existing, &name, parent_id.as_deref(), None)?,
+        id,
+        parent_id,
+        items: Vec::new(),
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+    };
+
+    state
+        .db
+        .insert_collection(&collection)
+        .map_err(|e| format!("Failed to create collection: {}", e))?;
+
+    insights::record(&state.db, InsightMetric::Edit, "").ok();
+    Ok(collection)
+}
+
+#[tauri::command]
+pub fn rename_collection(
+    state: State<'_, AppState>,
+    id: String,
+    name: String,
+) -> Result<Collection, String> {
+    let existing = load_collections(&state)?;
+    let mut collection = find_collection(&existing, &id)?;
+    collection.name =
+        collections::validate_name(&existing, &name, collection.parent_id.as_deref(), Some(&id))?;
+    save_collection(&state, collection)
+}
+
+/// Move a collection (and everything in it) under `parent_id`, or to the top
+/// level with `None`
+#[tauri::command]
+pub fn move_collection(
+    state: State<'_, AppState>,
+    id: String,
+    parent_id: Option<String>,
+) -> Result<Collection, String> {
+    let existing = load_collections(&state)?;
+    let mut collection = find_collection(&existing, &id)?;
+    collections::check_parent(&existing, &id, parent_id.as_deref())?;
+    collections::validate_name(&existing, &collection.name, parent_id.as_deref(), Some(&id))?;
+    collection.parent_id = parent_id;
+    save_collection(&state, collection)
+}
+
+/// Delete a collection. Its subcollections move up to its parent; the agents,
+/// skills and instructions filed in it are left in the library.
+#[tauri::command]
+pub fn delete_collection(state: State<'_, AppState>, id: String) -> Result<(), String> {
+    state
+        .db
+        .delete_collection(&id)
+        .map_err(|e| format!("Failed to delete collection: {}", e))?;
+
+    insights::record(&state.db, InsightMetric::Edit, "").ok();
+    Ok(())
+}
+
+/// File agents, skills or instructions in a collection. Items already in it
+/// are left where they are.
+#[tauri::command]
+pub fn add_to_collection(
+    state: State<'_, AppState>,
+    collection_id: String,
+    entity_ids: Vec<String>,
+) -> Result<Collection, String> {
+    find_collection(&load_collections(&state)?, &collection_id)?;
+    for entity_id in &entity_ids {
+        let item = CollectionItem {
+            entity_type: load_item(&state, entity_id)?.entity_type().to_string(),
+            entity_id: entity_id.clone(),
+        };
+        state
+            .db
+            .add_collection_item(&collection_id, &item)
+            .map_err(|e| format!("Failed to add to collection: {}", e))?;
+    }
+
+    insights::record(&state.db, InsightMetric::Edit, "").ok();
+    find_collection(&load_collections(&state)?, &collection_id)
+}
+
+#[tauri::command]
+pub fn remove_from_collection(
+    state: State<'_, AppState>,
+    collection_id: String,
+    entity_ids: Vec<String>,
+) -> Result<Collection, String> {
+    for entity_id in &entity_ids {
+        state
+            .db
+            .remove_collection_item(&collection_id, entity_id)
+            .map_err(|e| format!("Failed to remove from collection: {}", e))?;
+    }
+
+    insights::record(&state.db, InsightMetric::Edit, "").ok();
+    find_collection(&load_collections(&state)?, &collection_id)
+}
+
+/// The agents, skills and instructions in a collection, and with `recursive`
+/// those in its subcollections too
+#[tauri::command]
+pub fn get_collection_items(
+    state: State<'_, AppState>,
+    id: String,
+    recursive: Option<bool>,
+) -> Result<Vec<LibraryItem>, String> {
+    let existing = load_collections(&state)?;
+    find_collection(&existing, &id)?;
+    collections::items(&existing, &id, recursive.unwrap_or(false))
+        .iter()
+        .map(|item| load_item(&state, &item.entity_id))
+        .collect()
+}
+
+fn load_collections(state: &State<'_, AppState>) -> Result<Vec<Collection>, String> {
+    state
+        .db
+        .get_all_collections()
+        .map_err(|e| format!("Failed to get collections: {}", e))
+}
+
+fn find_collection(collections: &[Collection], id: &str) -> Result<Collection, String> {
+    collections
+        .iter()
+        .find(|c| c.id == id)
+        .cloned()
+        .ok_or_else(|| format!("Collection '{}' not found", id))
+}
+
+fn save_collection(
+    state: &State<'_, AppState>,
+    mut collection: Collection,
+) -> Result<Collection, String> {
+    collection.updated_at = Utc::now();
+    state
+        .db
+        .update_collection(&collection)
+        .map_err(|e| format!("Failed to update collection: {}", e))?;
+
+    insights::record(&state.db, InsightMetric::Edit, "").ok();
+    Ok(collection)
+}
+
+// ============================================================================
+// Snippet Commands
+// ============================================================================
+
+#[tauri::command]
+pub fn get_snippets(state: State<'_, AppState>) -> Result<Vec<Snippet>, String> {
+    load_snippets(&state)
+}
+
+#[tauri::command]
+pub fn get_snippet(state: State<'_, AppState>, id: String) -> Result<Snippet, String> {
+    load_snippets(&state)?
+        .into_iter()
+        .find(|s| s.id == id)
+        .ok_or_else(|| format!("Snippet '{}' not found", id))
+}
+
+/// Create a snippet; its id, which `{{> id}}` includes name, comes from its name
+#[tauri::command]
+pub fn create_snippet(
+    state: State<'_, AppState>,
+    snippet: CreateSnippetInput,
+) -> Result<Snippet, String> {
+    let mut snippet = Snippet {
+        id: String::new(), // Claimed on insert
+        name: snippet.name.trim().to_string(),
+        description: snippet.description,
+        content: snippet.content,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+    };
+    if snippet.name.is_empty() {
+        return Err("Snippet name can't be empty".to_string());
+    }
+
+    let existing = load_snippets(&state)?;
+    let name = snippet.name.clone();
+    ids::claim(&name, |id| {
+        snippet.id = id.to_string();
+        state.db.insert_snippet(&snippet)
+    })
+    .map_err(|e| format!("Failed to create snippet: {}", e))?;
+    // Includes can only loop back to the snippet once its id is known
+    if let Err(e) = snippets::check(&snippet, &existing) {
+        state
+            .db
+            .delete_snippet(&snippet.id)
+            .map_err(|e| format!("Failed to delete snippet: {}", e))?;
+        return Err(e);
+    }
+
+    insights::record(&state.db, InsightMetric::Edit, "").ok();
+    Ok(snippet)
+}
+
+/// Save a snippet. Every prompt that includes it picks up the change the
+/// next time it is composed.
+#[tauri::command]
+pub fn update_snippet(state: State<'_, AppState>, snippet: Snippet) -> Result<Snippet, String> {
+    let mut snippet = snippet;
+    snippet.name = snippet.name.trim().to_string();
+    if snippet.name.is_empty() {
+        return Err("Snippet name can't be empty".to_string());
+    }
+    snippets::check(&snippet, &load_snippets(&state)?)?;
+    snippet.updated_at = Utc::now();
+
+    state
+        .db
+        .update_snippet(&snippet)
+        .map_err(|e| format!("Failed to update snippet: {}", e))?;
+
+    insights::record(&state.db, InsightMetric::Edit, "").ok();
+    Ok(snippet)
+}
+
+/// Delete a snippet. Includes of it are left as written.
+#[tauri::command]
+pub fn delete_snippet(state: State<'_, AppState>, id: String) -> Result<(), String> {
+    state
+        .db
+        .delete_snippet(&id)
+        .map_err(|e| format!("Failed to delete snippet: {}", e))?;
+
+    insights::record(&state.db, InsightMetric::Edit, "").ok();
+    Ok(())
+}
+
+fn load_snippets(state: &State<'_, AppState>) -> Result<Vec<Snippet>, String> {
+    state
+        .db
+        .get_all_snippets()
+        .map_err(|e| format!("Failed to get snippets: {}", e))
+}
+
+// ============================================================================
+// Format Commands
+// ============================================================================
+
+/// Formats that `import_with_format` and `export_with_format` accept
+#[tauri::command]
+pub fn list_supported_formats(state: State<'_, AppState>) -> Vec<SupportedFormat> {
+    state.formats.formats()
+}
+
+/// Import every entity a registered format reads from `text`. Items without a
+/// name or description get them from their text; with `assisted`, an LLM
+/// provider writes them instead where it can.
+#[tauri::command(async)]
+pub fn import_with_format(
+    state: State<'_, AppState>,
+    format: String,
+    text: String,
+    assisted: Option<bool>,
+    provider: Option<String>,
+) -> Result<Vec<LibraryItem>, String> {
+    let items = import_text(
+        &state,
+        &format,
+        &text,
+        assisted.unwrap_or(false),
+        provider.as_deref(),
+    )?;
+    insights::record(&state.db, InsightMetric::Edit, "").ok();
+    Ok(items)
+}
+
+/// Export an agent, skill or instruction (whichever the format handles) by id
+#[tauri::command]
+pub fn export_with_format(
+    state: State<'_, AppState>,
+    format: String,
+    id: String,
+    allow_secrets: Option<bool>,
+    redaction_profile: Option<String>,
+) -> Result<String, String> {
+    export_entity(&state, &format, &id, allow_secrets, redaction_profile)
+}
+
+/// Import a SillyTavern character card as an agent. `path` is the card's JSON
+/// or its PNG, whose image becomes the agent's avatar.
+#[tauri::command]
+pub fn import_character_card(state: State<'_, AppState>, path: String) -> Result<Agent, String> {
+    let (json, image) = character_card::read_card(&PathBuf::from(path))?;
+    let items = import_text(&state, "character-card", &json, false, None)?;
+    let Some(LibraryItem::Agent(mut agent)) = items.into_iter().next() else {
+        return Err("The card holds no character".to_string());
+    };
+
+    // An image too large for an avatar still leaves the card imported
+    if let Some(attachment) = image.and_then(|image| Attachment::from_data_url(&image).ok()) {
+        state
+            .db
+            .insert_attachment(&attachment)
+            .map_err(|e| format!("Failed to save avatar: {}", e))?;
+        state
+            .db
+            .set_agent_avatar_image(&agent.id, Some(&attachment.id))
+            .map_err(|e| format!("Failed to set avatar: {}", e))?;
+        agent.avatar_image_id = Some(attachment.id);
+    }
+    insights::record(&state.db, InsightMetric::Edit, "").ok();
+    Ok(agent)
+}
+
+/// Instructions and skills a ChatGPT data export suggests: its custom
+/// instructions and the prompts chats were repeatedly opened with. `path` is
+/// the export's zip, the folder it was extracted to or `conversations.json`.
+#[tauri::command]
+pub fn preview_chatgpt_import(
+    state: State<'_, AppState>,
+    path: String,
+) -> Result<Vec<ChatGptSuggestion>, String> {
+    let (_, skills, instructions) = load_library(&state, None)?;
+    let conversations = chatgpt::read_conversations(&PathBuf::from(path))?;
+    Ok(chatgpt::suggest(&conversations, &skills, &instructions))
+}
+
+/// Add the suggestions from `preview_chatgpt_import` whose keys are given
+#[tauri::command]
+pub fn import_chatgpt_suggestions(
+    state: State<'_, AppState>,
+    path: String,
+    keys: Vec<String>,
+) -> Result<Vec<LibraryItem>, String> {
+    let (_, skills, instructions) = load_library(&state, None)?;
+    let conversations = chatgpt::read_conversations(&PathBuf::from(path))?;
+    let mut items: Vec<LibraryItem> = chatgpt::suggest(&conversations, &skills, &instructions)
+        .into_iter()
+        .filter(|suggestion| keys.contains(&suggestion.key))
+        .map(|suggestion| suggestion.item)
+        .collect();
+    for item in &mut items {
+        insert_imported(&state, item)?;
+    }
+    insights::record(&state.db, InsightMetric::Edit, "").ok();
+    Ok(items)
+}
+
+/// Parse `text` with a registered importer and save the results under new ids.
+/// With `strict_imports` on, the import fails when the importer's diagnostics
+/// include errors rather than saving its best guess. Missing names and
+/// descriptions are filled in, by `provider` when `assisted`.
+fn import_text(
+    state: &State<'_, AppState>,
+    format: &str,
+    text: &str,
+    assisted: bool,
+    provider: Option<&str>,
+) -> Result<Vec<LibraryItem>, String> {
+    let (_, importer) = state.formats.importer(format)?;
+
+    let settings = state
+        .db
+        .get_settings()
+        .map_err(|e| format!("Failed to load settings: {}", e))?;
+    if settings.strict_imports {
+        if let Some(message) = diagnostics::strict_failure(&importer.check(text)) {
+            return Err(message);
+        }
+    }
+
+    let mut items = importer.import(text)?;
+    let mut skill_ids: HashMap<String, String> = HashMap::new();
+    for item in &mut items {
+        if assisted && naming::needs_metadata(item) {
+            // Falls back to the names taken from the text
+            let _ = assisted_metadata(&settings, item, provider);
+        }
+        naming::fill_missing(item);
+        formats::link_imported_skills(item, &skill_ids);
+        insert_imported(state, item)?;
+        if let LibraryItem::Skill(skill) = item {
+            skill_ids.insert(skill.name.clone(), skill.id.clone());
+        }
+    }
+    Ok(items)
+}
+
+/// Save an imported item under a new id claimed from its name
+fn insert_imported(state: &State<'_, AppState>, item: &mut LibraryItem) -> Result<(), String> {
+    let now = Utc::now();
+    match item {
+        LibraryItem::Agent(agent) => {
+            agent.created_at = now;
+            agent.updated_at = now;
+            let name = agent.name.clone();
+            ids::claim(&name, |id| {
+                agent.id = id.to_string();
+                state.db.insert_agent(agent)
+            })
+            .map_err(|e| format!("Failed to import agent: {}", e))?;
+        }
+        LibraryItem::Skill(skill) => {
+            skill.created_at = now;
+            skill.updated_at = now;
+            let name = skill.name.clone();
+            ids::claim(&name, |id| {
+                skill.id = id.to_string();
+                state.db.insert_skill(skill)
+            })
+            .map_err(|e| format!("Failed to import skill: {}", e))?;
+        }
+        LibraryItem::Instruction(instruction) => {
+            instruction.created_at = now;
+            instruction.updated_at = now;
+            let name = instruction.name.clone();
+            ids::claim(&name, |id| {
+                instruction.id = id.to_string();
+                state.db.insert_instruction(instruction)
+            })
+            .map_err(|e| format!("Failed to import instruction: {}", e))?;
+        }
+    }
+    Ok(())
+}
+
+/// Have an LLM provider name an imported item
+fn assisted_metadata(
+    settings: &Settings,
+    item: &mut LibraryItem,
+    provider: Option<&str>,
+) -> Result<(), String> {
+    // The text is sent to a third party, so treat this like an export
+    let findings = match &*item {
+        LibraryItem::Agent(agent) => secrets::scan_agent(agent),
+        LibraryItem::Skill(skill) => secrets::scan_skill(skill),
+        LibraryItem::Instruction(instruction) => secrets::scan_instruction(instruction),
+    };
+    secrets::guard_export(&findings, false)?;
+
+    let provider = llm::find_provider(&settings.llm_providers, provider)?;
+    let prompt = naming::metadata_prompt(item.entity_type());
+    let reply = llm::complete(provider, &prompt, naming::body(item))?;
+    naming::apply_reply(item, &reply)
+}
+
+/// Load, redact and secret-check the entity `id`, then write it with a
+/// registered exporter
+fn export_entity(
+    state: &State<'_, AppState>,
+    format: &str,
+    id: &str,
+    allow_secrets: Option<bool>,
+    redaction_profile: Option<String>,
+) -> Result<String, String> {
+    let (info, _) = state.formats.exporter(format)?;
+    let redactor = load_redactor(state, redaction_profile)?;
+
+    let (item, findings) = match info.entity_type.as_str() {
+        "agent" => {
+            let mut agent = state
+                .db
+                .get_agent(id)
+                .map_err(|e| format!("Failed to get agent: {}", e))?
+                .ok_or_else(|| "Agent not found".to_string())?;
+            if let Some(redactor) = &redactor {
+                agent = redactor.redact_agent(&agent);
+            }
+            let findings = secrets::scan_agent(&agent);
+            (LibraryItem::Agent(agent), findings)
+        }
+        "skill" => {
+            let mut skill = state
+                .db
+                .get_skill(id)
+                .map_err(|e| format!("Failed to get skill: {}", e))?
+                .ok_or_else(|| "Skill not found".to_string())?;
+            if let Some(redactor) = &redactor {
+                skill = redactor.redact_skill(&skill);
+            }
+            let findings = secrets::scan_skill(&skill);
+            (LibraryItem::Skill(skill), findings)
+        }
+        _ => {
+            let mut instruction = state
+                .db
+                .get_instruction(id)
+                .map_err(|e| format!("Failed to get instruction: {}", e))?
+                .ok_or_else(|| "Instruction not found".to_string())?;
+            if let Some(redactor) = &redactor {
+                instruction = redactor.redact_instruction(&instruction);
+            }
+            let findings = secrets::scan_instruction(&instruction);
+            (LibraryItem::Instruction(instruction), findings)
+        }
+    };
+
+    secrets::guard_export(&findings, allow_secrets.unwrap_or(false))?;
+    state.formats.export(format, &item)
+}
+
+// ============================================================================
+// Settings Commands
+// ============================================================================
+
+#[tauri::command]
+pub fn get_settings(state: State<'_, AppState>) -> Result<Settings, String> {
+    let mut settings = state
+        .db
+        .get_settings()
+        .map_err(|e| format!("Failed to get settings: {}", e))?;
+
+    // Update runtime state
+    settings.mcp_server_enabled = *state.mcp_running.lock().unwrap();
+
+    Ok(settings)
+}
+
+#[tauri::command]
+pub fn save_settings(state: State<'_, AppState>, settings: Settings) -> Result<Settings, String> {
+    state
+        .db
+        .save_settings(&settings)
+        .map_err(|e| format!("Failed to save settings: {}", e))?;
+
+    Ok(settings)
+}
+
+// ============================================================================
+// Setup Commands
+// ============================================================================
+
+/// Template packs `first_run_setup` can seed the library from
+#[tauri::command]
+pub fn get_template_packs() -> Vec<TemplatePackInfo> {
+    setup::template_packs()
+}
+
+/// Run the setup wizard's choices in one go: seed a template pack, register
+/// the MCP server with clients, schedule backups and import a CLAUDE.md.
+/// A failing step is reported in `errors` without undoing the others, and
+/// setup is marked complete either way so the wizard isn't shown again.
+#[tauri::command]
+pub fn first_run_setup(
+    state: State<'_, AppState>,
+    options: FirstRunOptions,
+) -> Result<FirstRunReport, String> {
+    let mut report = FirstRunReport {
+        seeded: None,
+        registered: Vec::new(),
+        backup_schedule: None,
+        imported: Vec::new(),
+        errors: Vec::new(),
+    };
+
+    if let Some(pack) = options.template_pack.as_deref() {
+        match setup::seed(&state.db, pack) {
+            Ok(summary) => report.seeded = Some(summary),
+            Err(e) => report.errors.push(e),
+        }
+    }
+
+    if !options.clients.is_empty() {
+        match launcher::mcp_command(&state.db_path) {
+            Ok(command) => {
+                for client in &options.clients {
+                    let entry = setup::client_server_entry(
+                        client,
+                        &command,
+                        &state.db_path,
+                        options.read_only_clients,
+                    );
+                    let registered = setup::client_config_path(client)
+                        .and_then(|path| setup::register_client(client, &path, entry));
+                    match registered {
+                        Ok(registration) => report.registered.push(registration),
+                        Err(e) => report.errors.push(e),
+                    }
+                }
+            }
+            Err(e) => report.errors.push(e),
+        }
+    }
+
+    if let Some(path) = options.claude_md_path.as_deref() {
+        let imported =
+            setup::claude_md_instruction(&PathBuf::from(path)).and_then(|mut instruction| {
+                let name = instruction.name.clone();
+                ids::claim(&name, |id| {
+                    instruction.id = id.to_string();
+                    state.db.insert_instruction(&instruction)
+                })
+                .map_err(|e| format!("Failed to import instruction: {}", e))
+                .map(|_| instruction)
+            });
+        match imported {
+            Ok(instruction) => report.imported.push(instruction),
+            Err(e) => report.errors.push(e),
+        }
+    }
+
+    let mut settings = state
+        .db
+        .get_settings()
+        .map_err(|e| format!("Failed to get settings: {}", e))?;
+    if let Some(schedule) = options.backup_schedule {
+        settings.backup_schedule = schedule.clone();
+        report.backup_schedule = Some(schedule);
+    }
+    settings.setup_completed = true;
+    state
+        .db
+        .save_settings(&settings)
+        .map_err(|e| format!("Failed to save settings: {}", e))?;
+
+    insights::record(&state.db, InsightMetric::Edit, "").ok();
+    Ok(report)
+}
+
+// ============================================================================
+// MCP Server Commands
+// ============================================================================
+
+/// Whether the MCP server is running and the tools it offers. Crashes are
+/// noticed by `watch_mcp_server`, not here.
+#[tauri::command]
+pub fn get_mcp_status(state: State<'_, AppState>) -> Result<McpStatus, String> {
+    let actually_running = state
+        .mcp_process
+        .lock()
+        .map_err(|e| e.to_string())?
+        .as_mut()
+        .is_some_and(|child| matches!(child.try_wait(), Ok(None)));
+
+    let settings = state.db.get_settings().unwrap_or_default();
+    let agents = state.db.get_all_agents().unwrap_or_default();
+    let skills = state.db.get_all_skills().unwrap_or_default();
+
+    let mut available_tools = vec![
+        "list_agents".to_string(),
+        "get_agent".to_string(),
+        "list_skills".to_string(),
+        "get_skill".to_string(),
+        "render_skill".to_string(),
+        "get_instructions".to_string(),
+        "get_pinned_context".to_string(),
+        "apply_agent".to_string(),
+        "get_current_agent".to_string(),
+        "configure_agent".to_string(),
+        "compose_prompt".to_string(),
+        "list_releases".to_string(),
+    ];
+
+    // Tool skills the server runs, under their own names
+    let approvals = match settings.mcp_run_tools {
+        true => state.db.get_tool_approvals().unwrap_or_default(),
+        false => Default::default(),
+    };
+    for skill in skills.iter().filter(|s| s.enabled) {
+        if let SkillDefinition::Tool { handler, .. } = &skill.definition {
+            let name = tool_schema::mcp_tool_name(&skill.name);
+            if !handler.trim().is_empty()
+                && tool_runner::is_approved(skill, &approvals)
+                && !available_tools.contains(&name)
+            {
+                available_tools.push(name);
+            }
+        }
+    }
+
+    // Add agent-specific tools
+    for agent in agents.iter() {
+        available_tools.push(format!(
+            "agent:{}",
+            agent.name.to_lowercase().replace(' ', "_")
+        ));
+    }
+
+    // Add skill-specific tools
+    for skill in skills.iter() {
+        available_tools.push(format!(
+            "skill:{}",
+            skill.name.to_lowercase().replace(' ', "_")
+        ));
+    }
+
+    Ok(McpStatus {
+        running: actually_running,
+        port: settings.mcp_server_port,
+        connected_clients: 0,
+        available_tools,
+    })
+}
+
+#[tauri::command]
+pub fn start_mcp_server(state: State<'_, AppState>) -> Result<McpStatus, String> {
+    let mut mcp_process = state.mcp_process.lock().map_err(|e| e.to_string())?;
+
+    if mcp_process.is_some() {
+        return Err("MCP server is already running".to_string());
+    }
+
+    *mcp_process = Some(spawn_mcp_server(&state.db_path)?);
+    *state.mcp_running.lock().unwrap() = true;
+
+    drop(mcp_process);
+    get_mcp_status(state)
+}
+
+/// Start this executable as an MCP server on the database at `db_path`
+fn spawn_mcp_server(db_path: &Path) -> Result<Child, String> {
+    let exe_path = std::env::current_exe().map_err(|e| format!("Failed to get exe path: {}", e))?;
+    Command::new(&exe_path)
+        .arg("--mcp")
+        .arg("--db-path")
+        .arg(db_path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to start MCP server: {}", e))
+}
+
+/// How often `watch_mcp_server` checks on the server
+const MCP_WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Crashes within `MCP_RESTART_WINDOW` after which the server is left stopped
+const MAX_MCP_RESTARTS: usize = 3;
+const MCP_RESTART_WINDOW: Duration = Duration::from_secs(5 * 60);
+
+/// Watch the MCP server the app started. When it exits without being
+/// stopped, notify the user and start it again, unless it has already
+/// crashed `MAX_MCP_RESTARTS` times within `MCP_RESTART_WINDOW`.
+pub fn watch_mcp_server(app: AppHandle) {
+    let mut crashes: Vec<Instant> = Vec::new();
+    loop {
+        thread::sleep(MCP_WATCH_INTERVAL);
+        let state = app.state::<AppState>();
+        let Ok(mut mcp_process) = state.mcp_process.lock() else {
+            continue;
+        };
+        let Some(Ok(Some(exit_status))) = mcp_process.as_mut().map(|child| child.try_wait()) else {
+            continue;
+        };
+
+        crashes.retain(|crashed_at| crashed_at.elapsed() < MCP_RESTART_WINDOW);
+        crashes.push(Instant::now());
+        let restarted = if crashes.len() > MAX_MCP_RESTARTS {
+            Err(format!(
+                "it crashed {} times in a row and was left stopped",
+                crashes.len()
+            ))
+        } else {
+            spawn_mcp_server(&state.db_path)
+        };
+        let message = match restarted {
+            Ok(child) => {
+                *mcp_process = Some(child);
+                format!(
+                    "The MCP server exited unexpectedly ({}) and was restarted",
+                    exit_status
+                )
+            }
+            Err(e) => {
+                *mcp_process = None;
+                *state.mcp_running.lock().unwrap() = false;
+                format!("The MCP server exited unexpectedly ({}): {}", exit_status, e)
+            }
+        };
+        drop(mcp_process);
+        notifications::notify(&app, &state.db, NotificationEvent::McpCrashed, &message);
+    }
+}
+
+/// How long a stopping MCP server gets to save its state before it is killed
+const MCP_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Stop the MCP server, waiting up to `MCP_SHUTDOWN_TIMEOUT` for it to exit;
+/// async so the wait doesn't block the UI
+#[tauri::command(async)]
+pub fn stop_mcp_server(state: State<'_, AppState>) -> Result<McpStatus, String> {
+    let mut mcp_process = state.mcp_process.lock().map_err(|e| e.to_string())?;
+    let child = mcp_process.take();
+    *state.mcp_running.lock().unwrap() = false;
+    drop(mcp_process);
+
+    if let Some(mut child) = child {
+        shut_down_mcp_server(&mut child);
+    }
+    get_mcp_status(state)
+}
+
+/// Ask the server to exit and close its stdin, so it writes queued usage
+/// events and checkpoints the database; kill it only if it is still running
+/// after `MCP_SHUTDOWN_TIMEOUT`
+fn shut_down_mcp_server(child: &mut Child) {
+    if let Some(mut stdin) = child.stdin.take() {
+        let exit = serde_json::json!({ "jsonrpc": "2.0", "method": "notifications/exit" });
+        let _ = writeln!(stdin, "{}", exit);
+    }
+
+    let deadline = Instant::now() + MCP_SHUTDOWN_TIMEOUT;
+    while matches!(child.try_wait(), Ok(None)) {
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            break;
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    let _ = child.wait();
+}
+
+// ============================================================================
+// MCP Tool Handlers (called by MCP server)
+// ============================================================================
+
+/// Get the full configuration for an agent to "become" that persona
+#[tauri::command]
+pub fn apply_agent(
+    state: State<'_, AppState>,
+    agent_name: String,
+    numbered: Option<bool>,
+    trace: Option<bool>,
+    branch: Option<String>,
+    release: Option<String>,
+) -> Result<AppliedAgent, String> {
+    let (agents, skills, mut instructions) = load_library(&state, release.as_deref())?;
+    if release.as_deref().is_none_or(|tag| tag == releases::LATEST) {
+        apply_activation(&state, &mut instructions)?;
+    }
+
+    let agent = resolve_entity(&agents, &agent_name)?;
+    composition::ensure_resolvable(
+        &LibraryGraph {
+            agents: &agents,
+            skills: &skills,
+            instructions: &instructions,
+        },
+        agent,
+    )?;
+    let agent = &composition::inherited(agent, &agents);
+
+    let options = AssemblyOptions {
+        numbered: numbered.unwrap_or(false),
+        branch,
+    };
+    let full_prompt = assembly::agent_prompt(agent, &skills, &instructions, &options);
+    let trace = trace
+        .unwrap_or(false)
+        .then(|| assembly::trace_agent_prompt(agent, &skills, &instructions, &options));
+    for instruction in assembly::attached_instructions(agent, &instructions, &options) {
+        insights::record(
+            &state.db,
+            InsightMetric::Category,
+            category_to_string(&instruction.category),
+        )
+        .ok();
+    }
+
+    // Record agent usage
+    state
+        .db
+        .record_agent_usage(&agent.id)
+        .map_err(|e| format!("Failed to record usage: {}", e))?;
+    insights::record(&state.db, InsightMetric::Apply, "").ok();
+    insights::record_usage(&state.db, &agent.id, &Utc::now())
+        .map_err(|e| format!("Failed to record usage: {}", e))?;
+
+    Ok(AppliedAgent {
+        prompt: full_prompt,
+        trace,
+    })
+}
+
+/// Break down what `apply_agent` serves over MCP for an agent: header, system
+/// prompt, each skill, each attached and global instruction, with their sizes.
+/// Previewing doesn't count as using the agent.
+#[tauri::command]
+pub fn preview_agent_prompt(
+    state: State<'_, AppState>,
+    agent_id: String,
+    branch: Option<String>,
+) -> Result<PromptPreview, String> {
+    let (agents, skills, mut instructions) = load_library(&state, None)?;
+    apply_activation(&state, &mut instructions)?;
+    let agent = resolve_entity(&agents, &agent_id)?;
+    let agent = &composition::inherited(agent, &agents);
+
+    let options = AssemblyOptions {
+        numbered: false,
+        branch,
+    };
+    let sections = assembly::agent_configuration_sections(agent, &skills, &instructions, &options);
+    let prompt: String = sections
+        .iter()
+        .map(|section| section.text.as_str())
+        .collect();
+    Ok(PromptPreview {
+        agent_id: agent.id.clone(),
+        agent_name: agent.name.clone(),
+        characters: prompt.chars().count(),
+        tokens: assembly::estimate_tokens(&prompt),
+        prompt,
+        sections,
+    })
+}
+
+/// Compose what `apply_agent` serves over MCP for an agent and write it to a
+/// file or the clipboard in one step, optionally wrapped for pasting
+#[tauri::command]
+pub fn compose_and_export(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    agent_id: String,
+    target: PromptExportTarget,
+    wrapper: Option<PromptWrapper>,
+    branch: Option<String>,
+    allow_secrets: Option<bool>,
+) -> Result<PromptExport, String> {
+    let (agents, skills, mut instructions) = load_library(&state, None)?;
+    apply_activation(&state, &mut instructions)?;
+    let agent = resolve_entity(&agents, &agent_id)?;
+    composition::ensure_resolvable(
+        &LibraryGraph {
+            agents: &agents,
+            skills: &skills,
+            instructions: &instructions,
+        },
+        agent,
+    )?;
+    let agent = &composition::inherited(agent, &agents);
+
+    let options = AssemblyOptions {
+        numbered: false,
+        branch,
+    };
+    let sections = assembly::agent_configuration_sections(agent, &skills, &instructions, &options);
+
+    // The prompt leaves the app, so check what went into it like an export
+    let included = |id: &String| sections.iter().any(|s| s.entity_id.as_ref() == Some(id));
+    let findings: Vec<SecretFinding> = secrets::scan_agent(agent)
+        .into_iter()
+        .chain(
+            skills
+                .iter()
+                .filter(|s| included(&s.id))
+                .flat_map(secrets::scan_skill),
+        )
+        .chain(
+            instructions
+                .iter()
+                .filter(|i| included(&i.id))
+                .flat_map(secrets::scan_instruction),
+        )
+        .collect();
+    secrets::guard_export(&findings, allow_secrets.unwrap_or(false))?;
+
+    let prompt: String = sections.iter().map(|s| s.text.as_str()).collect();
+    let text = prompt_export::wrap(&prompt, &wrapper.unwrap_or_default(), &agent.id);
+    match &target {
+        PromptExportTarget::File { path } => {
+            std::fs::write(path, &text).map_err(|e| format!("Failed to write {}: {}", path, e))?
+        }
+        PromptExportTarget::Clipboard => app
+            .clipboard()
+            .write_text(text.clone())
+            .map_err(|e| format!("Failed to copy to the clipboard: {}", e))?,
+    }
+
+    state
+        .db
+        .record_agent_usage(&agent.id)
+        .map_err(|e| format!("Failed to record usage: {}", e))?;
+    insights::record(&state.db, InsightMetric::Apply, "").ok();
+    insights::record_usage(&state.db, &agent.id, &Utc::now())
+        .map_err(|e| format!("Failed to record usage: {}", e))?;
+
+    Ok(PromptExport {
+        agent_id: agent.id.clone(),
+        target,
+        characters: text.chars().count(),
+        tokens: assembly::estimate_tokens(&text),
+    })
+}
+
+/// Send an agent's assembled prompt and a sample message to an LLM provider
+/// and return its reply. Testing doesn't count as using the agent.
+#[tauri::command(async)]
+pub fn test_agent(
+    state: State<'_, AppState>,
+    agent_id: String,
+    message: String,
+    provider: Option<String>,
+    branch: Option<String>,
+) -> Result<AgentTestRun, String> {
+    let (agents, skills, mut instructions) = load_library(&state, None)?;
+    apply_activation(&state, &mut instructions)?;
+    let agent = &composition::inherited(resolve_entity(&agents, &agent_id)?, &agents);
+    let settings = state
+        .db
+        .get_settings()
+        .map_err(|e| format!("Failed to get settings: {}", e))?;
+    let provider = llm::find_provider(&settings.llm_providers, provider.as_deref())?;
+
+    let options = AssemblyOptions {
+        numbered: false,
+        branch,
+    };
+    testing::run(provider, agent, &skills, &instructions, &message, &options)
+}
+
+/// Send one message to two agents, or to two releases of one agent, at the
+/// same time and return both replies. `release_a` and `release_b` default to
+/// the live library.
+#[tauri::command(async)]
+pub fn compare_agents(
+    state: State<'_, AppState>,
+    agent_id_a: String,
+    agent_id_b: String,
+    message: String,
+    release_a: Option<String>,
+    release_b: Option<String>,
+    provider: Option<String>,
+) -> Result<AgentComparison, String> {
+    let (agents_a, skills_a, instructions_a) = load_library(&state, release_a.as_deref())?;
+    let (agents_b, skills_b, instructions_b) = load_library(&state, release_b.as_deref())?;
+    let a = testing::Subject {
+        agent: &composition::inherited(resolve_entity(&agents_a, &agent_id_a)?, &agents_a),
+        skills: &skills_a,
+        instructions: &instructions_a,
+        release: release_a,
+    };
+    let b = testing::Subject {
+        agent: &composition::inherited(resolve_entity(&agents_b, &agent_id_b)?, &agents_b),
+        skills: &skills_b,
+        instructions: &instructions_b,
+        release: release_b,
+    };
+
+    let settings = state
+        .db
+        .get_settings()
+        .map_err(|e| format!("Failed to get settings: {}", e))?;
+    let provider = llm::find_provider(&settings.llm_providers, provider.as_deref())?;
+    testing::compare(provider, a, b, &message, &AssemblyOptions::default())
+}
+
+type Library = (Vec<Agent>, Vec<Skill>, Vec<Instruction>);
+
+/// Disable the instructions whose activation rules don't hold right now, as
+/// the MCP server does, so what the app serves matches it
+fn apply_activation(state: &AppState, instructions: &mut [Instruction]) -> Result<(), String> {
+    let settings = state
+        .db
+        .get_settings()
+        .map_err(|e| format!("Failed to get settings: {}", e))?;
+    activation::apply(
+        instructions,
+        Local::now().naive_local(),
+        settings.active_profile.as_deref(),
+    );
+    Ok(())
+}
+
+/// The live library, or the snapshot frozen under a release tag
+fn load_library(state: &AppState, release: Option<&str>) -> Result<Library, String> {
+    if let Some(tag) = release.filter(|tag| *tag != releases::LATEST) {
+        let release = state
+            .db
+            .get_release(tag)
+            .map_err(|e| format!("Failed to get release: {}", e))?
+            .ok_or_else(|| format!("Release '{}' not found", tag))?;
+        return Ok((release.agents, release.skills, release.instructions));
+    }
+
+    let mut agents = state
+        .db
+        .get_all_agents()
+        .map_err(|e| format!("Failed to get agents: {}", e))?;
+    let mut skills = state
+        .db
+        .get_all_skills()
+        .map_err(|e| format!("Failed to get skills: {}", e))?;
+    let mut instructions = state
+        .db
+        .get_all_instructions()
+        .map_err(|e| format!("Failed to get instructions: {}", e))?;
+    let snippets = state
+        .db
+        .get_all_snippets()
+        .map_err(|e| format!("Failed to get snippets: {}", e))?;
+    // Releases freeze the library as composed, snippets already expanded
+    snippets::expand_library(&snippets, &mut agents, &mut skills, &mut instructions);
+    Ok((agents, skills, instructions))
+}
+
+/// Assemble a throwaway prompt from skills, instructions and inline text without saving an agent
+#[tauri::command]
+pub fn compose_prompt(
+    state: State<'_, AppState>,
+    skill_ids: Vec<String>,
+    instruction_ids: Vec<String>,
+    text: Option<String>,
+    numbered: Option<bool>,
+) -> Result<String, String> {
+    let (_, skills, instructions) = load_library(&state, None)?;
+
+    let prompt = assembly::compose_prompt(
+        &skills,
+        &instructions,
+        &skill_ids,
+        &instruction_ids,
+        text.as_deref(),
+        &AssemblyOptions {
+            numbered: numbered.unwrap_or(false),
+            ..AssemblyOptions::default()
+        },
+    )?;
+
+    // Every reference resolved, or composing would have failed
+    for reference in &skill_ids {
+        record_skill_usage(&state, &resolve_entity(&skills, reference)?.id)?;
+    }
+    for reference in &instruction_ids {
+        record_instruction_usage(&state, &resolve_entity(&instructions, reference)?.id)?;
+    }
+    Ok(prompt)
+}
+
+/// Count a use of a skill from the app, as the MCP server does for its tools
+fn record_skill_usage(state: &State<'_, AppState>, id: &str) -> Result<(), String> {
+    let now = Utc::now();
+    state
+        .db
+        .record_skill_usage_at(id, &now)
+        .map_err(|e| format!("Failed to record usage: {}", e))?;
+    insights::record_usage(&state.db, id, &now)
+        .map_err(|e| format!("Failed to record usage: {}", e))?;
+    Ok(())
+}
+
+/// Count a use of an instruction from the app, as the MCP server does for its tools
+fn record_instruction_usage(state: &State<'_, AppState>, id: &str) -> Result<(), String> {
+    let now = Utc::now();
+    state
+        .db
+        .record_instruction_usage_at(id, &now)
+        .map_err(|e| format!("Failed to record usage: {}", e))?;
+    insights::record_usage(&state.db, id, &now)
+        .map_err(|e| format!("Failed to record usage: {}", e))?;
+    Ok(())
+}
+
+/// Get all enabled instructions combined
+#[tauri::command]
+pub fn get_all_enabled_instructions(state: State<'_, AppState>) -> Result<String, String> {
+    let (_, _, instructions) = load_library(&state, None)?;
+
+    let mut sorted: Vec<_> = instructions.iter().filter(|i| i.enabled).collect();
+
+    // Sort by priority (higher first)
+    sorted.sort_by(|a, b| b.priority.cmp(&a.priority));
+
+    let combined = sorted
+        .iter()
+        .map(|i| format!("## {}\n{}", i.name, i.content))
+        .collect::<Vec<_>>()
+        .join("\n\n---\n\n");
+
+    Ok(combined)
+}
+
+// ============================================================================
+// Export/Import Commands
+// ============================================================================
+
+#[tauri::command]
+pub fn export_all_data(
+    state: State<'_, AppState>,
+    allow_secrets: Option<bool>,
+    redaction_profile: Option<String>,
+) -> Result<ExportData, String> {
+    let mut data = state
+        .db
+        .export_all()
+        .map_err(|e| format!("Failed to export data: {}", e))?;
+
+    if let Some(redactor) = load_redactor(&state, redaction_profile)? {
+        data = redactor.redact_export(&data);
+    }
+    secrets::guard_export(&secrets::scan_export(&data), allow_secrets.unwrap_or(false))?;
+    Ok(data)
+}
+
+/// Export the chosen agents, skills and instructions plus the skills,
+/// instructions and avatars the chosen agents use, for sharing as a bundle
+#[tauri::command]
+pub fn export_selection(
+    state: State<'_, AppState>,
+    agent_ids: Vec<String>,
+    skill_ids: Vec<String>,
+    instruction_ids: Vec<String>,
+    allow_secrets: Option<bool>,
+    redaction_profile: Option<String>,
+) -> Result<ExportData, String> {
+    let data = state
+        .db
+        .export_all()
+        .map_err(|e| format!("Failed to export data: {}", e))?;
+    let mut data = selection::select(data, &agent_ids, &skill_ids, &instruction_ids)?;
+
+    if let Some(redactor) = load_redactor(&state, redaction_profile)? {
+        data = redactor.redact_export(&data);
+    }
+    secrets::guard_export(&secrets::scan_export(&data), allow_secrets.unwrap_or(false))?;
+    Ok(data)
+}
+
+/// Compile the named redaction profile from settings, if one was requested
+fn load_redactor(
+    state: &State<'_, AppState>,
+    profile_name: Option<String>,
+) -> Result<Option<Redactor>, String> {
+    let Some(profile_name) = profile_name else {
+        return Ok(None);
+    };
+
+    let settings = state
+        .db
+        .get_settings()
+        .map_err(|e| format!("Failed to get settings: {}", e))?;
+    let profile = settings
+        .redaction_profiles
+        .iter()
+        .find(|p| p.name.eq_ignore_ascii_case(&profile_name))
+        .ok_or_else(|| format!("Redaction profile '{}' not found", profile_name))?;
+
+    Redactor::new(profile).map(Some)
+}
+
+/// Scan the whole library for credentials, tokens, emails and internal hostnames
+#[tauri::command]
+pub fn scan_for_secrets(state: State<'_, AppState>) -> Result<Vec<SecretFinding>, String> {
+    let data = state
+        .db
+        .export_all()
+        .map_err(|e| format!("Failed to load library: {}", e))?;
+    Ok(secrets::scan_export(&data))
+}
+
+/// Check every agent's references for cycles, runaway nesting and dangling IDs
+#[tauri::command]
+pub fn validate_library(state: State<'_, AppState>) -> Result<Vec<LibraryIssue>, String> {
+    let data = state
+        .db
+        .export_all()
+        .map_err(|e| format!("Failed to load library: {}", e))?;
+
+    Ok(composition::validate_library(&LibraryGraph {
+        agents: &data.agents,
+        skills: &data.skills,
+        instructions: &data.instructions,
+    }))
+}
+
+/// Find pairs of rules in enabled instructions that look contradictory, such
+/// as "always use tabs" in one and "use 2 spaces" in another
+#[tauri::command]
+pub fn find_instruction_conflicts(
+    state: State<'_, AppState>,
+) -> Result<Vec<InstructionConflict>, String> {
+    let instructions = state
+        .db
+        .get_all_instructions()
+        .map_err(|e| format!("Failed to get instructions: {}", e))?;
+    Ok(conflicts::find(&instructions))
+}
+
+/// Find agent references to skills, instructions and base agents that no
+/// longer exist.
+/// With `repair`, the references are also removed from their agents.
+#[tauri::command]
+pub fn validate_references(
+    state: State<'_, AppState>,
+    repair: Option<bool>,
+) -> Result<Vec<OrphanedReference>, String> {
+    let (agents, skills, instructions) = load_library(&state, None)?;
+    let orphans = composition::orphaned_references(&LibraryGraph {
+        agents: &agents,
+        skills: &skills,
+        instructions: &instructions,
+    });
+    if !repair.unwrap_or(false) || orphans.is_empty() {
+        return Ok(orphans);
+    }
+
+    let mut affected: Vec<Agent> = agents
+        .into_iter()
+        .filter(|a| orphans.iter().any(|o| o.agent_id == a.id))
+        .collect();
+    for agent in &affected {
+        ensure_not_locked(&state, &agent.id)?;
+    }
+    for agent in &mut affected {
+        if composition::remove_orphans(agent, &orphans) {
+            agent.updated_at = Utc::now();
+            state
+                .db
+                .update_agent(agent)
+                .map_err(|e| format!("Failed to update agent: {}", e))?;
+        }
+    }
+    insights::record(&state.db, InsightMetric::Edit, "").ok();
+    Ok(orphans)
+}
+
+/// Run the checks sync performs before committing, honouring `block_sync_on_errors`
+#[tauri::command]
+pub fn preflight_library(state: State<'_, AppState>) -> Result<PreflightReport, String> {
+    let data = state
+        .db
+        .export_all()
+        .map_err(|e| format!("Failed to load library: {}", e))?;
+
+    Ok(preflight::run(
+        &LibraryGraph {
+            agents: &data.agents,
+            skills: &data.skills,
+            instructions: &data.instructions,
+        },
+        data.settings.block_sync_on_errors,
+    ))
+}
+
+/// Which instruction categories and skill types each agent covers, and which
+/// ones no agent covers
+#[tauri::command]
+pub fn get_capability_matrix(state: State<'_, AppState>) -> Result<CapabilityMatrix, String> {
+    let (agents, skills, instructions) = load_library(&state, None)?;
+    Ok(capabilities::capability_matrix(
+        &agents,
+        &skills,
+        &instructions,
+    ))
+}
+
+#[tauri::command]
+pub fn import_all_data(app: AppHandle, state: State<'_, AppState>, data: ExportData) -> Result<(), String> {
+    let previous = state
+        .db
+        .export_all()
+        .map_err(|e| format!("Failed to export data: {}", e))?;
+    let summary = format!(
+        "Imported {} agents, {} skills, and {} instructions",
+        data.agents.len(),
+        data.skills.len(),
+        data.instructions.len()
+    );
+    journal::run(
+        &state.db,
+        &journal::Operation::ReplaceLibrary {
+            data: Box::new(data),
+            previous: Box::new(previous),
+        },
+    )?;
+
+    notifications::notify(
+        &app,
+        &state.db,
+        NotificationEvent::ImportCompleted,
+        &summary,
+    );
+
+    Ok(())
+}
+
+/// Journaled operations cut short by a crash or a failed step
+#[tauri::command]
+pub fn get_interrupted_operations(state: State<'_, AppState>) -> Result<Vec<JournalEntry>, String> {
+    state
+        .db
+        .get_journal_entries()
+        .map(|entries| {
+            entries
+                .into_iter()
+                .filter(|e| e.status == "interrupted")
+                .collect()
+        })
+        .map_err(|e| format!("Failed to read the operation journal: {}", e))
+}
+
+/// Finish an interrupted operation by running it again
+#[tauri::command]
+pub fn resume_operation(state: State<'_, AppState>, id: String) -> Result<(), String> {
+    journal::resume(&state.db, &id)?;
+    insights::record(&state.db, InsightMetric::Edit, "").ok();
+    Ok(())
+}
+
+/// Undo whatever part of an interrupted operation was applied
+#[tauri::command]
+pub fn roll_back_operation(state: State<'_, AppState>, id: String) -> Result<(), String> {
+    journal::roll_back(&state.db, &id)?;
+    insights::record(&state.db, InsightMetric::Edit, "").ok();
+    Ok(())
+}
+
+// ============================================================================
+// Diagnostics Commands
+// ============================================================================
+
+/// Check everything the app depends on for the troubleshooting page: the
+/// database and its schema, spawning the MCP server, the MCP port, the
+/// directories and client configs we write to, and each LLM provider's key
+#[tauri::command(async)]
+pub fn run_diagnostics(state: State<'_, AppState>) -> Result<SelfCheckReport, String> {
+    Ok(diagnostics_report(&state))
+}
+
+/// Write a zip for bug reports to `path`: the diagnostics report, schema
+/// version, settings with credentials removed, library statistics and the
+/// tail of the MCP server log
+#[tauri::command(async)]
+pub fn export_support_bundle(
+    state: State<'_, AppState>,
+    path: String,
+) -> Result<SupportBundle, String> {
+    let settings = state
+        .db
+        .get_settings()
+        .map_err(|e| format!("Failed to get settings: {}", e))?;
+    let data = state
+        .db
+        .export_all()
+        .map_err(|e| format!("Failed to export data: {}", e))?;
+    let version = state
+        .db
+        .schema_version()
+        .map_err(|e| format!("Failed to read the schema: {}", e))?;
+    let missing = state
+        .db
+        .missing_migrations()
+        .map_err(|e| format!("Failed to read the schema: {}", e))?;
+    let schema = serde_json::json!({ "version": version, "missing_migrations": missing });
+
+    let pretty = |value: &serde_json::Value| serde_json::to_string_pretty(value).unwrap();
+    let mut files = vec![
+        ("about.json".to_string(), pretty(&support_bundle::about())),
+        (
+            "diagnostics.json".to_string(),
+            serde_json::to_string_pretty(&diagnostics_report(&state)).unwrap(),
+        ),
+        ("schema.json".to_string(), pretty(&schema)),
+        (
+            "settings.json".to_string(),
+            serde_json::to_string_pretty(&support_bundle::redact_settings(&settings)).unwrap(),
+        ),
+        (
+            "library_stats.json".to_string(),
+            pretty(&support_bundle::library_stats(&data)),
+        ),
+    ];
+    if let Some(log) = support_bundle::client_log_path().filter(|p| p.exists()) {
+        let tail =
+            support_bundle::log_tail(&log, support_bundle::LOG_TAIL_LINES).unwrap_or_else(|e| e);
+        files.push(("logs/claude-desktop.log".to_string(), tail));
+    }
+
+    let size_bytes = support_bundle::write_zip(std::path::Path::new(&path), &files)?;
+    Ok(SupportBundle {
+        path,
+        files: files.into_iter().map(|(name, _)| name).collect(),
+        size_bytes,
+    })
+}
+
+fn diagnostics_report(state: &State<'_, AppState>) -> SelfCheckReport {
+    let settings = state.db.get_settings().unwrap_or_default();
+    let mut checks = vec![
+        self_check::check_database(&state.db),
+        self_check::check_migrations(&state.db),
+    ];
+
+    checks.push(match std::env::current_exe() {
+        Ok(exe) => self_check::check_mcp_spawn(&exe, &state.db_path),
+        Err(e) => self_check::error(
+            "mcp_spawn",
+            "MCP server",
+            &format!("Failed to get exe path: {}", e),
+            "Reinstall the app",
+        ),
+    });
+    if *state.mcp_running.lock().unwrap() {
+        checks.push(self_check::skipped(
+            "port",
+            "MCP port",
+            "The MCP server is running",
+        ));
+    } else {
+        checks.push(self_check::check_port(settings.mcp_server_port));
+    }
+
+    if let Some(data_dir) = state.db_path.parent() {
+        checks.push(self_check::check_writable_dir(
+            "data_dir",
+            "Data directory",
+            data_dir,
+        ));
+    }
+    let backup_dir = settings
+        .backup_schedule
+        .dir
+        .as_deref()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| backup::default_dir(&state.db_path));
+    checks.push(self_check::check_writable_dir(
+        "backup_dir",
+        "Backup directory",
+        &backup_dir,
+    ));
+    for client in setup::CLIENTS {
+        let id = format!("client:{}", client);
+        let name = format!("{} config", client);
+        let path = match setup::client_config_path(client) {
+            Ok(path) => path,
+            Err(e) => {
+                checks.push(self_check::skipped(&id, &name, &e));
+                continue;
+            }
+        };
+        checks.push(self_check::check_writable_file(&id, &name, &path));
+        if let (Some(registered), Ok(command)) = (
+            setup::registered_command(&path),
+            launcher::mcp_command(&state.db_path),
+        ) {
+            let entry = setup::client_server_entry(client, &command, &state.db_path, false);
+            checks.push(self_check::check_registered_command(
+                client,
+                &registered,
+                entry["command"].as_str().unwrap_or_default(),
+                &launcher::resolve_command(&command),
+            ));
+        }
+    }
+
+    if settings.llm_providers.is_empty() {
+        checks.push(self_check::skipped(
+            "providers",
+            "LLM providers",
+            "No LLM provider configured",
+        ));
+    }
+    for provider in &settings.llm_providers {
+        let id = format!("provider:{}", provider.name);
+        let name = format!("LLM provider '{}'", provider.name);
+        checks.push(match llm::verify_provider(provider) {
+            Ok(()) => self_check::ok(&id, &name, "Reachable and accepts its key"),
+            Err(e) => self_check::error(
+                &id,
+                &name,
+                &e,
+                "Check the provider's API key, model and base URL in Settings",
+            ),
+        });
+    }
+
+    self_check::report(checks)
+}
+
+// ============================================================================
+// Backup Commands
+// ============================================================================
+
+/// Snapshot the database into `dir` (defaults to `backups/` next to the database)
+#[tauri::command]
+pub fn create_backup(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    dir: Option<String>,
+) -> Result<BackupManifest, String> {
+    let dir = dir
+        .map(PathBuf::from)
+        .unwrap_or_else(|| backup::default_dir(&state.db_path));
+
+    backup::create_backup(&state.db, &dir).inspect_err(|e| {
+        notifications::notify(&app, &state.db, NotificationEvent::BackupFailed, e);
+    })
+}
+
+/// Check a backup's digest and test-restore it without touching the live database
+#[tauri::command]
+pub fn verify_backup(
+    state: State<'_, AppState>,
+    path: String,
+) -> Result<BackupVerification, String> {
+    let passphrase = encryption::passphrase_for(&state.db_path)?;
+    Ok(backup::verify_backup(
+        &PathBuf::from(path),
+        passphrase.as_deref(),
+    ))
+}
+
+/// Compare the library with another Prompt Forge database, such as a
+/// teammate's or a backup, or with an `export_all_data` file
+#[tauri::command]
+pub fn compare_libraries(
+    state: State<'_, AppState>,
+    other_db_path: String,
+) -> Result<LibraryComparison, String> {
+    let here = state
+        .db
+        .export_all()
+        .map_err(|e| format!("Failed to export data: {}", e))?;
+    let passphrase = encryption::passphrase_for(&state.db_path)?;
+    let there = library_diff::read_library(&PathBuf::from(&other_db_path), passphrase.as_deref())?;
+    Ok(library_diff::compare(&here, &there, &other_db_path))
+}
+
+// ============================================================================
+// Sync Commands
+// ============================================================================
+
+/// Write the library to `repo_path` as markdown and YAML files and commit
+/// them, pushing to `remote` when given. The preflight summary goes into the
+/// commit message; blocked checks or secrets in the library stop the sync.
+#[tauri::command(async)]
+pub fn sync_to_git(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    repo_path: String,
+    remote: Option<String>,
+    message: Option<String>,
+) -> Result<GitSyncResult, String> {
+    let data = state
+        .db
+        .export_all()
+        .map_err(|e| format!("Failed to load library: {}", e))?;
+    let report = preflight::run(
+        &LibraryGraph {
+            agents: &data.agents,
+            skills: &data.skills,
+            instructions: &data.instructions,
+        },
+        data.settings.block_sync_on_errors,
+    );
+    if report.blocked {
+        return Err(format!(
+            "Sync blocked by library checks.\n{}",
+            report.summary
+        ));
+    }
+    secrets::guard_export(&secrets::scan_export(&data), false)?;
+
+    let dir = PathBuf::from(repo_path);
+    git_sync::write_library(&dir, &data.agents, &data.skills, &data.instructions)?;
+    let title = message
+        .as_deref()
+        .map(str::trim)
+        .filter(|m| !m.is_empty())
+        .unwrap_or("Update prompt library");
+    let notify_failure = |e: &String| {
+        notifications::notify(&app, &state.db, NotificationEvent::SyncFailed, e);
+    };
+    let commit = git_sync::commit(&dir, &format!("{}\n\n{}", title, report.summary))
+        .inspect_err(notify_failure)?;
+
+    let remote = remote.as_deref().map(str::trim).filter(|r| !r.is_empty());
+    if let Some(remote) = remote {
+        git_sync::push(&dir, remote).inspect_err(notify_failure)?;
+    }
+
+    Ok(GitSyncResult {
+        commit,
+        pushed: remote.is_some(),
+        agents: data.agents.len(),
+        skills: data.skills.len(),
+        instructions: data.instructions.len(),
+    })
+}
+
+/// Replace the library's agents, skills and instructions with the files in
+/// `repo_path`, after fast-forwarding it from `remote` when given. Local
+/// usage counts are kept, and the replacement is journaled so it can be
+/// rolled back.
+#[tauri::command(async)]
+pub fn pull_from_git(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    repo_path: String,
+    remote: Option<String>,
+) -> Result<GitSyncResult, String> {
+    let dir = PathBuf::from(repo_path);
+    let remote = remote.as_deref().map(str::trim).filter(|r| !r.is_empty());
+    if let Some(remote) = remote {
+        git_sync::pull(&dir, remote).inspect_err(|e| {
+            notifications::notify(&app, &state.db, NotificationEvent::SyncFailed, e);
+        })?;
+    }
+
+    let previous = state
+        .db
+        .export_all()
+        .map_err(|e| format!("Failed to export data: {}", e))?;
+    let (agents, skills, instructions) = git_sync::read_library(
+        &dir,
+        (&previous.agents, &previous.skills, &previous.instructions),
+    )?;
+    let result = GitSyncResult {
+        commit: git_sync::head(&dir).ok(),
+        pushed: false,
+        agents: agents.len(),
+        skills: skills.len(),
+        instructions: instructions.len(),
+    };
+    let data = ExportData {
+        agents,
+        skills,
+        instructions,
+        ..previous.clone()
+    };
+    journal::run(
+        &state.db,
+        &journal::Operation::ReplaceLibrary {
+            data: Box::new(data),
+            previous: Box::new(previous),
+        },
+    )?;
+
+    notifications::notify(
+        &app,
+        &state.db,
+        NotificationEvent::ImportCompleted,
+        &format!(
+            "Pulled {} agents, {} skills, and {} instructions",
+            result.agents, result.skills, result.instructions
+        ),
+    );
+    Ok(result)
+}
+
+// ============================================================================
+// Release Commands
+// ============================================================================
+
+/// Freeze the enabled library under `tag` so `apply_agent` can assemble from it later
+#[tauri::command]
+pub fn create_release(
+    state: State<'_, AppState>,
+    tag: String,
+    notes: Option<String>,
+) -> Result<ReleaseSummary, String> {
+    let tag = releases::validate_tag(&tag)?;
+    if state
+        .db
+        .get_release(&tag)
+        .map_err(|e| format!("Failed to get release: {}", e))?
+        .is_some()
+    {
+        return Err(format!("Release '{}' already exists", tag));
+    }
+
+    let (agents, skills, instructions) = load_library(&state, None)?;
+    let release = releases::snapshot(
+        &tag,
+        notes.as_deref().unwrap_or(""),
+        agents,
+        skills,
+        instructions,
+    )?;
+
+    state
+        .db
+        .insert_release(&release)
+        .map_err(|e| format!("Failed to create release: {}", e))?;
 
-    Ok(settings)
+    Ok(release.summary())
 }
 
-// ============================================================================
-// MCP Server Commands
-// ============================================================================
+#[tauri::command]
+pub fn get_releases(state: State<'_, AppState>) -> Result<Vec<ReleaseSummary>, String> {
+    state
+        .db
+        .get_releases()
+        .map_err(|e| format!("Failed to get releases: {}", e))
+}
 
 #[tauri::command]
-pub fn get_mcp_status(state: State<'_, AppState>) -> Result<McpStatus, String> {
-    let running = *state.mcp_running.lock().map_err(|e| e.to_string())?;
-
-    // Check if process is still alive
-    let actually_running = if running {
-        let mut mcp_process = state.mcp_process.lock().map_err(|e| e.to_string())?;
-        if let Some(ref mut child) = *mcp_process {
-            match child.try_wait() {
-                Ok(None) => true,       // Still running
-                Ok(Some(_)) => {
-                    // Process exited
-                    *state.mcp_running.lock().unwrap() = false;
-                    *mcp_process = None;
-                    false
-                }
-                Err(_) => false,
-            }
-        } else {
-            false
+pub fn delete_release(state: State<'_, AppState>, tag: String) -> Result<(), String> {
+    let settings = state
+        .db
+        .get_settings()
+        .map_err(|e| format!("Failed to get settings: {}", e))?;
+    if let Some(channel) = settings.release_channels.iter().find(|c| c.tag == tag) {
+        return Err(format!(
+            "Release '{}' is served on channel '{}'; point the channel elsewhere first",
+            tag, channel.name
+        ));
+    }
+
+    state
+        .db
+        .delete_release(&tag)
+        .map_err(|e| format!("Failed to delete release: {}", e))
+}
+
+/// Point a channel at a release tag (or "latest"), or remove it when `tag` is None.
+/// MCP servers started with `--channel <name>` pick the change up on reload.
+#[tauri::command]
+pub fn set_release_channel(
+    state: State<'_, AppState>,
+    name: String,
+    tag: Option<String>,
+) -> Result<Vec<ReleaseChannel>, String> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err("Channel name can't be empty".to_string());
+    }
+
+    let mut settings = state
+        .db
+        .get_settings()
+        .map_err(|e| format!("Failed to get settings: {}", e))?;
+    settings
+        .release_channels
+        .retain(|c| !c.name.eq_ignore_ascii_case(&name));
+
+    if let Some(tag) = tag.map(|t| t.trim().to_string()) {
+        if !tag.eq_ignore_ascii_case(releases::LATEST)
+            && state
+                .db
+                .get_release(&tag)
+                .map_err(|e| format!("Failed to get release: {}", e))?
+                .is_none()
+        {
+            return Err(format!("Release '{}' not found", tag));
         }
-    } else {
-        false
+        settings.release_channels.push(ReleaseChannel { name, tag });
+    }
+
+    state
+        .db
+        .save_settings(&settings)
+        .map_err(|e| format!("Failed to save settings: {}", e))?;
+
+    Ok(settings.release_channels)
+}
+
+// ============================================================================
+// Translation Commands
+// ============================================================================
+
+/// Draft a translation of an agent, skill or instruction with an LLM provider.
+/// The draft needs review before MCP clients asking for `target_locale` get it.
+#[tauri::command(async)]
+pub fn translate_entity(
+    state: State<'_, AppState>,
+    id: String,
+    target_locale: String,
+    provider: Option<String>,
+) -> Result<Translation, String> {
+    let locale = localization::normalize_locale(&target_locale)?;
+    let item = load_item(&state, &id)?;
+
+    // The text is sent to a third party, so treat this like an export
+    let findings = match &item {
+        LibraryItem::Agent(agent) => secrets::scan_agent(agent),
+        LibraryItem::Skill(skill) => secrets::scan_skill(skill),
+        LibraryItem::Instruction(instruction) => secrets::scan_instruction(instruction),
     };
+    secrets::guard_export(&findings, false)?;
 
-    let settings = state.db.get_settings().unwrap_or_default();
-    let agents = state.db.get_all_agents().unwrap_or_default();
-    let skills = state.db.get_all_skills().unwrap_or_default();
+    let settings = state
+        .db
+        .get_settings()
+        .map_err(|e| format!("Failed to get settings: {}", e))?;
+    let provider = llm::find_provider(&settings.llm_providers, provider.as_deref())?;
 
-    let mut available_tools = vec![
-        "list_agents".to_string(),
-        "get_agent".to_string(),
-        "list_skills".to_string(),
-        "get_skill".to_string(),
-        "get_instructions".to_string(),
-        "apply_agent".to_string(),
-    ];
+    let source = serde_json::to_string(&localization::text_of(&item))
+        .map_err(|e| format!("Failed to serialize text: {}", e))?;
+    let prompt = localization::translation_prompt(&locale);
+    let reply = llm::complete(provider, &prompt, &source)?;
+    let text = localization::parse_translation(&reply)?;
 
-    // Add agent-specific tools
-    for agent in agents.iter() {
-        available_tools.push(format!(
-            "agent:{}",
-            agent.name.to_lowercase().replace(' ', "_")
-        ));
-    }
+    let existing = state
+        .db
+        .get_translations(Some(&id))
+        .map_err(|e| format!("Failed to get translations: {}", e))?
+        .into_iter()
+        .find(|t| t.locale == locale);
+    let now = Utc::now();
+    let translation = Translation {
+        id: existing
+            .as_ref()
+            .map(|t| t.id.clone())
+            .unwrap_or_else(ids::random_id),
+        entity_type: item.entity_type().to_string(),
+        entity_id: id,
+        locale,
+        name: text.name,
+        description: text.description,
+        content: text.content,
+        needs_review: true,
+        provider: Some(provider.name.clone()),
+        created_at: existing.map(|t| t.created_at).unwrap_or(now),
+        updated_at: now,
+    };
 
-    // Add skill-specific tools
-    for skill in skills.iter() {
-        available_tools.push(format!(
-            "skill:{}",
-            skill.name.to_lowercase().replace(' ', "_")
-        ));
-    }
+    state
+        .db
+        .upsert_translation(&translation)
+        .map_err(|e| format!("Failed to save translation: {}", e))?;
 
-    Ok(McpStatus {
-        running: actually_running,
-        port: settings.mcp_server_port,
-        connected_clients: 0,
-        available_tools,
-    })
+    Ok(translation)
 }
 
+/// Translations of one entity, or of the whole library
 #[tauri::command]
-pub fn start_mcp_server(state: State<'_, AppState>) -> Result<McpStatus, String> {
-    let mut mcp_process = state.mcp_process.lock().map_err(|e| e.to_string())?;
+pub fn get_translations(
+    state: State<'_, AppState>,
+    entity_id: Option<String>,
+) -> Result<Vec<Translation>, String> {
+    state
+        .db
+        .get_translations(entity_id.as_deref())
+        .map_err(|e| format!("Failed to get translations: {}", e))
+}
 
-    if mcp_process.is_some() {
-        return Err("MCP server is already running".to_string());
+/// Save a reviewer's edits to a translation
+#[tauri::command]
+pub fn update_translation(
+    state: State<'_, AppState>,
+    translation: Translation,
+) -> Result<Translation, String> {
+    let mut translation = translation;
+    translation.locale = localization::normalize_locale(&translation.locale)?;
+    translation.updated_at = Utc::now();
+
+    state
+        .db
+        .upsert_translation(&translation)
+        .map_err(|e| format!("Failed to update translation: {}", e))?;
+
+    Ok(translation)
+}
+
+/// Mark a translation as reviewed so it is served for its locale
+#[tauri::command]
+pub fn approve_translation(state: State<'_, AppState>, id: String) -> Result<Translation, String> {
+    let mut translation = state
+        .db
+        .get_translation(&id)
+        .map_err(|e| format!("Failed to get translation: {}", e))?
+        .ok_or_else(|| "Translation not found".to_string())?;
+    translation.needs_review = false;
+    translation.updated_at = Utc::now();
+
+    state
+        .db
+        .upsert_translation(&translation)
+        .map_err(|e| format!("Failed to approve translation: {}", e))?;
+
+    Ok(translation)
+}
+
+#[tauri::command]
+pub fn delete_translation(state: State<'_, AppState>, id: String) -> Result<(), String> {
+    state
+        .db
+        .delete_translation(&id)
+        .map_err(|e| format!("Failed to delete translation: {}", e))
+}
+
+/// The agent, skill or instruction with this id
+fn load_item(state: &State<'_, AppState>, id: &str) -> Result<LibraryItem, String> {
+    if let Some(agent) = state
+        .db
+        .get_agent(id)
+        .map_err(|e| format!("Failed to get agent: {}", e))?
+    {
+        return Ok(LibraryItem::Agent(agent));
+    }
+    if let Some(skill) = state
+        .db
+        .get_skill(id)
+        .map_err(|e| format!("Failed to get skill: {}", e))?
+    {
+        return Ok(LibraryItem::Skill(skill));
     }
+    state
+        .db
+        .get_instruction(id)
+        .map_err(|e| format!("Failed to get instruction: {}", e))?
+        .map(LibraryItem::Instruction)
+        .ok_or_else(|| format!("No agent, skill or instruction with id '{}'", id))
+}
 
-    // Get path to current executable
-    let exe_path = std::env::current_exe().map_err(|e| format!("Failed to get exe path: {}", e))?;
+// ============================================================================
+// Onboarding Commands
+// ============================================================================
 
-    // Spawn the MCP server as a child process
-    let child = Command::new(&exe_path)
-        .arg("--mcp")
-        .arg("--db-path")
-        .arg(&state.db_path)
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()
-        .map_err(|e| format!("Failed to start MCP server: {}", e))?;
+/// Summarize the enabled guidance in `scope` as an onboarding document.
+/// With `assisted`, an LLM provider writes the summary; the extractive
+/// summary is used when that isn't possible, with the reason in `notice`.
+#[tauri::command(async)]
+pub fn summarize_library(
+    state: State<'_, AppState>,
+    scope: Option<SummaryScope>,
+    format: Option<String>,
+    assisted: Option<bool>,
+    provider: Option<String>,
+) -> Result<OnboardingDocument, String> {
+    let format = match format.as_deref().unwrap_or("markdown") {
+        "markdown" | "md" => "markdown",
+        "html" => "html",
+        other => {
+            return Err(format!(
+                "Unknown format '{}': use 'markdown' or 'html'",
+                other
+            ))
+        }
+    };
 
-    *mcp_process = Some(child);
-    *state.mcp_running.lock().unwrap() = true;
+    let (agents, skills, instructions) = load_library(&state, None)?;
+    let library = LibraryGraph {
+        agents: &agents,
+        skills: &skills,
+        instructions: &instructions,
+    };
+    let mut guidance = onboarding::select(&library, &scope.unwrap_or_default())?;
+    for agent in &guidance.agents {
+        let Some(attachment_id) = &agent.avatar_image_id else {
+            continue;
+        };
+        if let Some(attachment) = state
+            .db
+            .get_attachment(attachment_id)
+            .map_err(|e| format!("Failed to get avatar: {}", e))?
+        {
+            guidance
+                .avatars
+                .insert(agent.id.clone(), attachment.to_data_url());
+        }
+    }
 
-    drop(mcp_process);
-    get_mcp_status(state)
+    let (markdown, generated_by, notice) = match assisted.unwrap_or(false) {
+        true => match assisted_summary(&state, &guidance, provider.as_deref()) {
+            Ok((markdown, provider)) => (markdown, provider, None),
+            Err(e) => (
+                onboarding::extractive(&guidance),
+                "extractive".to_string(),
+                Some(e),
+            ),
+        },
+        false => (
+            onboarding::extractive(&guidance),
+            "extractive".to_string(),
+            None,
+        ),
+    };
+
+    Ok(OnboardingDocument {
+        content: match format {
+            "html" => onboarding::to_html(&guidance.title, &markdown),
+            _ => markdown,
+        },
+        title: guidance.title,
+        format: format.to_string(),
+        generated_by,
+        notice,
+    })
+}
+
+/// An LLM-written summary and the name of the provider that wrote it
+fn assisted_summary(
+    state: &State<'_, AppState>,
+    guidance: &onboarding::Guidance,
+    provider: Option<&str>,
+) -> Result<(String, String), String> {
+    // The guidance is sent to a third party, so treat this like an export
+    let findings: Vec<_> = guidance
+        .agents
+        .iter()
+        .flat_map(|a| secrets::scan_agent(a))
+        .chain(guidance.skills.iter().flat_map(|s| secrets::scan_skill(s)))
+        .chain(
+            guidance
+                .instructions
+                .iter()
+                .flat_map(|i| secrets::scan_instruction(i)),
+        )
+        .collect();
+    secrets::guard_export(&findings, false)?;
+
+    let settings = state
+        .db
+        .get_settings()
+        .map_err(|e| format!("Failed to get settings: {}", e))?;
+    let provider = llm::find_provider(&settings.llm_providers, provider)?;
+
+    let prompt = onboarding::summary_prompt(&guidance.title);
+    let reply = llm::complete(provider, &prompt, &onboarding::source_text(guidance))?;
+    Ok((onboarding::strip_fence(&reply), provider.name.clone()))
 }
 
+// ============================================================================
+// Encryption Commands
+// ============================================================================
+
+/// Whether the library database is encrypted at rest
 #[tauri::command]
-pub fn stop_mcp_server(state: State<'_, AppState>) -> Result<McpStatus, String> {
-    let mut mcp_process = state.mcp_process.lock().map_err(|e| e.to_string())?;
+pub fn get_database_encryption(state: State<'_, AppState>) -> Result<bool, String> {
+    encryption::is_enabled(&state.db_path)
+}
 
-    if let Some(mut child) = mcp_process.take() {
-        // Try graceful shutdown first
-        let _ = child.kill();
-        let _ = child.wait();
+/// Turn database encryption on or off, re-encrypting the file in place
+#[tauri::command]
+pub fn set_database_encryption(state: State<'_, AppState>, enabled: bool) -> Result<(), String> {
+    // The MCP server holds its own handle on the file and would not survive the swap
+    if *state.mcp_running.lock().unwrap() {
+        return Err("Stop the MCP server before changing database encryption".to_string());
     }
+    encryption::set_enabled(&state.db, &state.db_path, enabled)
+}
 
-    *state.mcp_running.lock().unwrap() = false;
+// ============================================================================
+// Workspace Commands
+// ============================================================================
 
-    drop(mcp_process);
-    get_mcp_status(state)
+/// The data directory holding every workspace's database
+fn workspaces_dir(state: &AppState) -> Result<PathBuf, String> {
+    state
+        .db_path
+        .parent()
+        .map(PathBuf::from)
+        .ok_or_else(|| format!("{} has no parent directory", state.db_path.display()))
+}
+
+/// All workspaces, the one the app opens marked active
+#[tauri::command]
+pub fn list_workspaces(state: State<'_, AppState>) -> Result<Vec<LibraryWorkspace>, String> {
+    Ok(library_workspaces::list(&workspaces_dir(&state)?))
+}
+
+/// Create a workspace with a fresh library; the app stays on the current one
+#[tauri::command]
+pub fn create_workspace(
+    state: State<'_, AppState>,
+    name: String,
+) -> Result<LibraryWorkspace, String> {
+    let dir = workspaces_dir(&state)?;
+    let (name, path) = library_workspaces::new_database(&dir, &name)?;
+    let db = encryption::open_database(&path)?;
+    db.migrate()
+        .map_err(|e| format!("Failed to migrate workspace '{}': {}", name, e))?;
+    init_default_data(&db)
+        .map_err(|e| format!("Failed to initialize workspace '{}': {}", name, e))?;
+
+    library_workspaces::list(&dir)
+        .into_iter()
+        .find(|workspace| workspace.name == name)
+        .ok_or_else(|| format!("Failed to create workspace '{}'", name))
+}
+
+/// Make `name` the active workspace and restart the app on its library
+#[tauri::command]
+pub fn switch_workspace(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    name: String,
+) -> Result<(), String> {
+    // The MCP server would keep serving the old workspace
+    if *state.mcp_running.lock().unwrap() {
+        return Err("Stop the MCP server before switching workspaces".to_string());
+    }
+    library_workspaces::set_active(&workspaces_dir(&state)?, &name)?;
+    app.restart()
 }
 
 // ============================================================================
-// MCP Tool Handlers (called by MCP server)
+// Command Palette Commands
 // ============================================================================
 
-/// Get the full configuration for an agent to "become" that persona
+/// Fuzzy-search palette actions over library entities and app operations
 #[tauri::command]
-pub fn apply_agent(state: State<'_, AppState>, agent_name: String) -> Result<String, String> {
+pub fn query_actions(
+    state: State<'_, AppState>,
+    text: String,
+) -> Result<Vec<PaletteAction>, String> {
     let agents = state
         .db
         .get_all_agents()
@@ -427,98 +3399,246 @@ pub fn apply_agent(state: State<'_, AppState>, agent_name: String) -> Result<Str
         .get_all_instructions()
         .map_err(|e| format!("Failed to get instructions: {}", e))?;
 
-    let agent = agents
-        .iter()
-        .find(|a| a.name.to_lowercase() == agent_name.to_lowercase())
-        .ok_or_else(|| format!("Agent '{}' not found", agent_name))?;
-
-    // Build the full system prompt from agent + attached skills + attached instructions
-    let mut full_prompt = agent.system_prompt.clone();
-
-    // Add personality context
-    full_prompt.push_str(&format!(
-        "\n\n## Personality\n- Tone: {}\n- Verbosity: {}\n- Traits: {}",
-        agent.personality.tone,
-        agent.personality.verbosity,
-        agent.personality.traits.join(", ")
-    ));
+    Ok(actions::search(
+        actions::catalogue(&agents, &skills, &instructions),
+        &text,
+    ))
+}
 
-    // Add attached skills
-    let agent_skills: Vec<_> = skills
-        .iter()
-        .filter(|s| agent.skills.contains(&s.id) && s.enabled)
-        .collect();
+/// Execute a palette action by its stable ID.
+/// "Open" actions only tell the UI where to navigate; everything else runs here.
+/// Async because stopping the MCP server waits for it to exit.
+#[tauri::command(async)]
+pub fn run_action(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    id: String,
+) -> Result<ActionOutcome, String> {
+    let navigate = |view: &str, entity_id: String| ActionOutcome {
+        action_id: id.clone(),
+        view: Some(view.to_string()),
+        entity_id: Some(entity_id),
+        output: None,
+        message: format!("Opened {}", view),
+    };
+    let done = |output: Option<String>, message: String| ActionOutcome {
+        action_id: id.clone(),
+        view: None,
+        entity_id: None,
+        output,
+        message,
+    };
 
-    if !agent_skills.is_empty() {
-        full_prompt.push_str("\n\n## Available Skills\n");
-        for skill in agent_skills {
-            full_prompt.push_str(&format!("\n### {}\n{}\n", skill.name, skill.description));
-            if let SkillDefinition::Prompt { template } = &skill.definition {
-                full_prompt.push_str(&format!("Template: {}\n", template));
-            }
+    match ActionTarget::parse(&id)? {
+        ActionTarget::OpenAgent(agent_id) => Ok(navigate("agents", agent_id)),
+        ActionTarget::OpenSkill(skill_id) => Ok(navigate("skills", skill_id)),
+        ActionTarget::OpenInstruction(instruction_id) => {
+            Ok(navigate("instructions", instruction_id))
+        }
+        ActionTarget::ApplyAgent(agent_id) => {
+            // Only the exact id; a deleted agent mustn't fall back to a name match
+            let agent = state
+                .db
+                .get_agent(&agent_id)
+                .map_err(|e| format!("Failed to get agent: {}", e))?
+                .ok_or_else(|| format!("Agent '{}' not found", agent_id))?;
+            let applied = apply_agent(state.clone(), agent.id, None, None, None, None)?;
+            Ok(done(
+                Some(applied.prompt),
+                format!("Applied agent {}", agent.name),
+            ))
+        }
+        ActionTarget::ExportAgent(agent_id) => {
+            let markdown = export_agent_to_markdown(state.clone(), agent_id, None, None)?;
+            Ok(done(Some(markdown), "Exported agent".to_string()))
+        }
+        ActionTarget::ExportInstruction(instruction_id) => {
+            let markdown =
+                export_instruction_to_markdown(state.clone(), instruction_id, None, None)?;
+            Ok(done(Some(markdown), "Exported instruction".to_string()))
+        }
+        ActionTarget::StartMcp => {
+            start_mcp_server(state.clone())?;
+            Ok(done(None, "MCP server started".to_string()))
+        }
+        ActionTarget::StopMcp => {
+            stop_mcp_server(state.clone())?;
+            Ok(done(None, "MCP server stopped".to_string()))
+        }
+        ActionTarget::CreateBackup => {
+            let manifest = create_backup(app, state.clone(), None)?;
+            Ok(done(None, format!("Backup written to {}", manifest.file_name)))
+        }
+        ActionTarget::ExportLibrary => {
+            let data = export_all_data(state.clone(), None, None)?;
+            let json = serde_json::to_string_pretty(&data)
+                .map_err(|e| format!("Failed to serialize export: {}", e))?;
+            Ok(done(Some(json), "Exported library".to_string()))
+        }
+        ActionTarget::ScanSecrets => {
+            let findings = scan_for_secrets(state.clone())?;
+            let report = serde_json::to_string_pretty(&findings)
+                .map_err(|e| format!("Failed to serialize findings: {}", e))?;
+            Ok(done(
+                Some(report),
+                format!("Found {} potential secret(s)", findings.len()),
+            ))
         }
     }
+}
 
-    // Add attached instructions
-    let agent_instructions: Vec<_> = instructions
-        .iter()
-        .filter(|i| agent.instructions.contains(&i.id) && i.enabled)
-        .collect();
+// ============================================================================
+// Edit Lock Commands
+// ============================================================================
 
-    if !agent_instructions.is_empty() {
-        full_prompt.push_str("\n\n## Instructions\n");
-        for instruction in agent_instructions {
-            full_prompt.push_str(&format!("\n{}\n", instruction.content));
-        }
-    }
+/// How long an edit lock lives without being renewed
+const EDIT_LOCK_TTL_SECS: i64 = 300;
 
-    // Record agent usage
-    state
+/// Take or renew the advisory lock on an entity before editing it.
+/// Fails with the current holder's details if someone else is editing.
+#[tauri::command]
+pub fn acquire_edit_lock(state: State<'_, AppState>, entity_id: String) -> Result<EditLock, String> {
+    let lock = state
         .db
-        .record_agent_usage(&agent.id)
-        .map_err(|e| format!("Failed to record usage: {}", e))?;
+        .acquire_edit_lock(&entity_id, &state.client_id, &lock_owner_name(), EDIT_LOCK_TTL_SECS)
+        .map_err(|e| format!("Failed to acquire edit lock: {}", e))?;
+
+    if lock.owner_id != state.client_id {
+        return Err(format!(
+            "Currently being edited by {} (lock expires at {})",
+            lock.owner_name,
+            lock.expires_at.to_rfc3339()
+        ));
+    }
 
-    Ok(full_prompt)
+    Ok(lock)
 }
 
-/// Get all enabled instructions combined
 #[tauri::command]
-pub fn get_all_enabled_instructions(state: State<'_, AppState>) -> Result<String, String> {
-    let instructions = state
+pub fn release_edit_lock(state: State<'_, AppState>, entity_id: String) -> Result<(), String> {
+    state
         .db
-        .get_all_instructions()
-        .map_err(|e| format!("Failed to get instructions: {}", e))?;
-
-    let mut sorted: Vec<_> = instructions.iter().filter(|i| i.enabled).collect();
+        .release_edit_lock(&entity_id, &state.client_id)
+        .map_err(|e| format!("Failed to release edit lock: {}", e))
+}
 
-    // Sort by priority (higher first)
-    sorted.sort_by(|a, b| b.priority.cmp(&a.priority));
+/// List active locks so the UI can show who is editing what
+#[tauri::command]
+pub fn get_edit_locks(state: State<'_, AppState>) -> Result<Vec<EditLock>, String> {
+    state
+        .db
+        .get_active_edit_locks()
+        .map_err(|e| format!("Failed to get edit locks: {}", e))
+}
 
-    let combined = sorted
-        .iter()
-        .map(|i| format!("## {}\n{}", i.name, i.content))
-        .collect::<Vec<_>>()
-        .join("\n\n---\n\n");
+/// Reject writes to an entity that another client currently holds a lock on
+fn ensure_not_locked(state: &State<'_, AppState>, entity_id: &str) -> Result<(), String> {
+    let lock = state
+        .db
+        .get_edit_lock(entity_id)
+        .map_err(|e| format!("Failed to check edit lock: {}", e))?;
+
+    match lock {
+        Some(lock) if lock.owner_id != state.client_id => Err(format!(
+            "Currently being edited by {} (lock expires at {})",
+            lock.owner_name,
+            lock.expires_at.to_rfc3339()
+        )),
+        _ => Ok(()),
+    }
+}
 
-    Ok(combined)
+fn lock_owner_name() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "another user".to_string())
 }
 
 // ============================================================================
-// Export/Import Commands
+// Insight Commands
 // ============================================================================
 
+/// Windows `get_usage_stats` covers unless asked for others, in days
+const USAGE_STATS_WINDOWS: [u32; 2] = [7, 30];
+
+/// Items `get_usage_stats` ranks per window unless asked for another number
+const USAGE_STATS_LIMIT: usize = 10;
+
+/// Local-only usage summary for the last seven days
 #[tauri::command]
-pub fn export_all_data(state: State<'_, AppState>) -> Result<ExportData, String> {
-    state
-        .db
-        .export_all()
-        .map_err(|e| format!("Failed to export data: {}", e))
+pub fn get_weekly_summary(state: State<'_, AppState>) -> Result<WeeklySummary, String> {
+    insights::weekly_summary(&state.db).map_err(|e| format!("Failed to get weekly summary: {}", e))
 }
 
+/// When an agent gets used, by weekday and hour over the last `days` days
+/// (30 by default). `utc_offset_minutes` is the caller's time zone.
 #[tauri::command]
-pub fn import_all_data(state: State<'_, AppState>, data: ExportData) -> Result<(), String> {
-    state
+pub fn get_usage_heatmap(
+    state: State<'_, AppState>,
+    entity_id: String,
+    days: Option<u32>,
+    utc_offset_minutes: Option<i32>,
+) -> Result<UsageHeatmap, String> {
+    let mut heatmap = insights::usage_heatmap(
+        &state.db,
+        &entity_id,
+        days.unwrap_or(30),
+        utc_offset_minutes.unwrap_or(0),
+    )
+    .map_err(|e| format!("Failed to get usage heatmap: {}", e))?;
+    heatmap.updated_at = state
         .db
-        .import_all(&data)
-        .map_err(|e| format!("Failed to import data: {}", e))
+        .get_agent(&entity_id)
+        .map_err(|e| format!("Failed to get agent: {}", e))?
+        .map(|agent| agent.updated_at);
+    Ok(heatmap)
+}
+
+/// Most-used and unused agents, skills and instructions over the last
+/// `windows` days each (7 and 30 by default) and all time. At most `limit`
+/// items (10 by default) are ranked per window; windowed counts need
+/// insights enabled.
+#[tauri::command]
+pub fn get_usage_stats(
+    state: State<'_, AppState>,
+    windows: Option<Vec<u32>>,
+    limit: Option<usize>,
+) -> Result<Vec<UsageWindow>, String> {
+    let (agents, skills, instructions) = load_library(&state, None)?;
+    let items = usage_stats::library_usage(&agents, &skills, &instructions);
+    let now = Utc::now();
+    let limit = limit.unwrap_or(USAGE_STATS_LIMIT);
+
+    let mut stats = Vec::new();
+    for days in windows.unwrap_or_else(|| USAGE_STATS_WINDOWS.to_vec()) {
+        let since = now - chrono::Duration::days(days as i64);
+        let counts = insights::usage_since(&state.db, &since)
+            .map_err(|e| format!("Failed to get usage: {}", e))?;
+        stats.push(usage_stats::usage_window(
+            &items,
+            Some(days),
+            &counts,
+            now,
+            limit,
+        ));
+    }
+    stats.push(usage_stats::usage_window(
+        &items,
+        None,
+        &Default::default(),
+        now,
+        limit,
+    ));
+    Ok(stats)
+}
+
+/// Delete collected usage data of `kind` (see `retention::KINDS`): all of it,
+/// or everything older than `older_than_days`
+#[tauri::command]
+pub fn purge_data(
+    state: State<'_, AppState>,
+    kind: String,
+    older_than_days: Option<u32>,
+) -> Result<PurgeReport, String> {
+    retention::purge(&state.db, &kind, older_than_days)
 }
@@ -0,0 +1,567 @@
+//! Shared resolution engine for entities that reference other entities
+//! Walks a reference graph depth-first and reports cycles, runaway depth and
+//! dangling references as diagnostics instead of recursing forever.
+
+use crate::models::{
+    Agent, Instruction, LibraryIssue, OrphanedReference, Personality, Skill, SkillDefinition,
+};
+use std::fmt;
+
+/// Deepest reference chain followed before giving up
+pub const MAX_DEPTH: usize = 8;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CompositionError {
+    /// The chain of nodes that loops back on itself, first node repeated at the end
+    Cycle(Vec<String>),
+    /// The chain that exceeded `MAX_DEPTH`
+    TooDeep(Vec<String>),
+    Missing {
+        from: String,
+        reference: String,
+    },
+}
+
+impl fmt::Display for CompositionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompositionError::Cycle(path) => write!(f, "Cycle detected: {}", path.join(" → ")),
+            CompositionError::TooDeep(path) => write!(
+                f,
+                "References nest deeper than {} levels: {}",
+                MAX_DEPTH,
+                path.join(" → ")
+            ),
+            CompositionError::Missing { from, reference } => {
+                write!(f, "{} references missing {}", from, reference)
+            }
+        }
+    }
+}
+
+/// A graph of named nodes. `references` returns `None` for nodes that don't exist.
+pub trait ReferenceGraph {
+    fn references(&self, node: &str) -> Option<Vec<String>>;
+}
+
+/// Every problem reachable from `root`. Each offending branch is reported
+/// once and skipped; shared dependencies are only walked once.
+pub fn check(graph: &impl ReferenceGraph, root: &str) -> Vec<CompositionError> {
+    let mut errors = Vec::new();
+    let mut visited = Vec::new();
+    visit(graph, root, &mut Vec::new(), &mut visited, &mut errors);
+    errors
+}
+
+fn visit(
+    graph: &impl ReferenceGraph,
+    node: &str,
+    path: &mut Vec<String>,
+    visited: &mut Vec<String>,
+    errors: &mut Vec<CompositionError>,
+) {
+    let mut report = |error: CompositionError| {
+        if !errors.contains(&error) {
+            errors.push(error);
+        }
+    };
+
+    if let Some(start) = path.iter().position(|n| n == node) {
+        let mut cycle = path[start..].to_vec();
+        cycle.push(node.to_string());
+        return report(CompositionError::Cycle(cycle));
+    }
+    if visited.iter().any(|n| n == node) {
+        return;
+    }
+    if path.len() >= MAX_DEPTH {
+        let mut chain = path.clone();
+        chain.push(node.to_string());
+        return report(CompositionError::TooDeep(chain));
+    }
+
+    let Some(references) = graph.references(node) else {
+        // A missing root is the caller's lookup problem, not a composition one
+        if let Some(from) = path.last() {
+            report(CompositionError::Missing {
+                from: from.clone(),
+                reference: node.to_string(),
+            });
+        }
+        return;
+    };
+
+    path.push(node.to_string());
+    for reference in references {
+        visit(graph, &reference, path, visited, errors);
+    }
+    path.pop();
+    visited.push(node.to_string());
+}
+
+/// The library as a reference graph. Node keys are `<kind>:<id>`.
+pub struct LibraryGraph<'a> {
+    pub agents: &'a [Agent],
+    pub skills: &'a [Skill],
+    pub instructions: &'a [Instruction],
+}
+
+impl LibraryGraph<'_> {
+    pub fn agent_key(id: &str) -> String {
+        format!("agent:{}", id)
+    }
+
+    /// Human-readable label for a node key, e.g. `agent 'Reviewer'`
+    fn label(&self, key: &str) -> String {
+        let (kind, id) = key.split_once(':').unwrap_or(("entity", key));
+        let name = match kind {
+            "agent" => self.agents.iter().find(|a| a.id == id).map(|a| &a.name),
+            "skill" => self.skills.iter().find(|s| s.id == id).map(|s| &s.name),
+            "instruction" => self
+                .instructions
+                .iter()
+                .find(|i| i.id == id)
+                .map(|i| &i.name),
+            _ => None,
+        };
+        match name {
+            Some(name) => format!("{} '{}'", kind, name),
+            None => format!("{} {}", kind, id),
+        }
+    }
+
+    /// Rewrite node keys in an error into readable labels
+    pub fn describe(&self, error: &CompositionError) -> String {
+        let labels = |path: &[String]| path.iter().map(|k| self.label(k)).collect();
+        match error {
+            CompositionError::Cycle(path) => CompositionError::Cycle(labels(path)).to_string(),
+            CompositionError::TooDeep(path) => CompositionError::TooDeep(labels(path)).to_string(),
+            CompositionError::Missing { from, reference } => CompositionError::Missing {
+                from: self.label(from),
+                reference: self.label(reference),
+            }
+            .to_string(),
+        }
+    }
+}
+
+impl ReferenceGraph for LibraryGraph<'_> {
+    fn references(&self, node: &str) -> Option<Vec<String>> {
+        let (kind, id) = node.split_once(':')?;
+        match kind {
+            "agent" => self.agents.iter().find(|a| a.id == id).map(|agent| {
+                let parent = agent.parent_agent_id.iter().map(|p| Self::agent_key(p));
+                let skills = agent.skills.iter().map(|s| format!("skill:{}", s));
+                let instructions = agent
+                    .instructions
+                    .iter()
+                    .map(|i| format!("instruction:{}", i));
+                parent.chain(skills).chain(instructions).collect()
+            }),
+            "skill" => self.skills.iter().find(|s| s.id == id).map(|skill| {
+                follows(skill)
+                    .iter()
+                    .map(|s| format!("skill:{}", s))
+                    .collect()
+            }),
+            "instruction" => self
+                .instructions
+                .iter()
+                .find(|i| i.id == id)
+                .map(|_| Vec::new()),
+            _ => None,
+        }
+    }
+}
+
+/// Check every agent's composition. Cycles and over-deep chains are errors
+/// because assembly refuses them; dangling references are warnings because
+/// assembly skips them.
+pub fn validate_library(graph: &LibraryGraph) -> Vec<LibraryIssue> {
+    let mut issues = Vec::new();
+    for agent in graph.agents {
+        for error in check(graph, &LibraryGraph::agent_key(&agent.id)) {
+            let severity = match error {
+                CompositionError::Missing { .. } => "warning",
+                _ => "error",
+            };
+            issues.push(LibraryIssue {
+                severity: severity.to_string(),
+                entity_type: "agent".to_string(),
+                entity_id: agent.id.clone(),
+                entity_name: agent.name.clone(),
+                message: graph.describe(&error),
+            });
+        }
+    }
+    issues
+}
+
+/// Refuse to assemble an agent whose composition can't be resolved
+pub fn ensure_resolvable(graph: &LibraryGraph, agent: &Agent) -> Result<(), String> {
+    match check(graph, &LibraryGraph::agent_key(&agent.id))
+        .iter()
+        .find(|e| !matches!(e, CompositionError::Missing { .. }))
+    {
+        Some(error) => Err(format!(
+            "Can't assemble agent '{}': {}",
+            agent.name,
+            graph.describe(error)
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Refuse a parent agent that doesn't exist or would make `agent` inherit
+/// from itself
+pub fn ensure_valid_parent(agents: &[Agent], agent: &Agent) -> Result<(), String> {
+    let Some(parent_id) = &agent.parent_agent_id else {
+        return Ok(());
+    };
+    if !agents.iter().any(|a| &a.id == parent_id) {
+        return Err(format!("Parent agent '{}' not found", parent_id));
+    }
+    let mut agents: Vec<Agent> = agents
+        .iter()
+        .filter(|a| a.id != agent.id)
+        .cloned()
+        .collect();
+    agents.push(agent.clone());
+    let graph = ParentGraph(&agents);
+    match check(&graph, &agent.id).first() {
+        Some(error) => Err(format!("Can't inherit from '{}': {}", parent_id, error)),
+        None => Ok(()),
+    }
+}
+
+/// Agents and the parents they inherit from, keyed by id
+struct ParentGraph<'a>(&'a [Agent]);
+
+impl ReferenceGraph for ParentGraph<'_> {
+    fn references(&self, node: &str) -> Option<Vec<String>> {
+        let agent = self.0.iter().find(|a| a.id == node)?;
+        Some(agent.parent_agent_id.iter().cloned().collect())
+    }
+}
+
+/// `agent` as it is assembled, with what it leaves unset inherited from its
+/// parent chain: an empty system prompt, personality settings left at their
+/// defaults, and the parent's skills and instructions ahead of its own. A
+/// missing parent ends the chain; the result has no parent of its own.
+pub fn inherited(agent: &Agent, agents: &[Agent]) -> Agent {
+    let mut resolved = agent.clone();
+    let mut seen = vec![agent.id.clone()];
+    let mut next = agent.parent_agent_id.clone();
+    while let Some(parent) = next.and_then(|id| agents.iter().find(|a| a.id == id)) {
+        if seen.contains(&parent.id) || seen.len() > MAX_DEPTH {
+            break;
+        }
+        seen.push(parent.id.clone());
+
+        if resolved.system_prompt.trim().is_empty() {
+            resolved.system_prompt = parent.system_prompt.clone();
+        }
+        let defaults = Personality::default();
+        let (own, inherited) = (&mut resolved.personality, &parent.personality);
+        if own.tone == defaults.tone {
+            own.tone = inherited.tone.clone();
+        }
+        if own.verbosity == defaults.verbosity {
+            own.verbosity = inherited.verbosity.clone();
+        }
+        if own.creativity == defaults.creativity {
+            own.creativity = inherited.creativity;
+        }
+        if own.formality == defaults.formality {
+            own.formality = inherited.formality;
+        }
+        if own.traits == defaults.traits {
+            own.traits = inherited.traits.clone();
+        }
+        resolved.skills = merged(&parent.skills, &resolved.skills);
+        resolved.instructions = merged(&parent.instructions, &resolved.instructions);
+
+        next = parent.parent_agent_id.clone();
+    }
+    resolved.parent_agent_id = None;
+    resolved
+}
+
+/// `first` followed by whatever `then` adds, without repeats
+fn merged(first: &[String], then: &[String]) -> Vec<String> {
+    let mut ids = first.to_vec();
+    for id in then {
+        if !ids.contains(id) {
+            ids.push(id.clone());
+        }
+    }
+    ids
+}
+
+/// Prompt skills a skill is chained after
+pub fn follows(skill: &Skill) -> &[String] {
+    match &skill.definition {
+        SkillDefinition::Prompt { follows, .. } => follows,
+        _ => &[],
+    }
+}
+
+/// The skills rendered for `skill`, in order: the skills it follows, each
+/// once and after its own predecessors, then `skill` itself. `skills` must
+/// contain `skill`. Cycles, missing skills and steps that aren't prompt
+/// skills are errors.
+pub fn skill_chain<'a>(skill: &'a Skill, skills: &'a [Skill]) -> Result<Vec<&'a Skill>, String> {
+    let graph = LibraryGraph {
+        agents: &[],
+        skills,
+        instructions: &[],
+    };
+    if let Some(error) = check(&graph, &format!("skill:{}", skill.id)).first() {
+        return Err(format!(
+            "Can't chain skill '{}': {}",
+            skill.name,
+            graph.describe(error)
+        ));
+    }
+
+    let mut chain = Vec::new();
+    push_chain(skill, skills, &mut chain);
+    if let Some(step) = chain
+        .iter()
+        .find(|s| !matches!(s.definition, SkillDefinition::Prompt { .. }))
+    {
+        return Err(format!(
+            "Can't chain skill '{}': '{}' is not a prompt skill",
+            skill.name, step.name
+        ));
+    }
+    Ok(chain)
+}
+
+fn push_chain<'a>(skill: &'a Skill, skills: &'a [Skill], chain: &mut Vec<&'a Skill>) {
+    for id in follows(skill) {
+        if let Some(previous) = skills.iter().find(|s| &s.id == id) {
+            push_chain(previous, skills, chain);
+        }
+    }
+    if !chain.iter().any(|s| s.id == skill.id) {
+        chain.push(skill);
+    }
+}
+
+/// Every agent reference to a skill, instruction or base agent that isn't in
+/// the library
+pub fn orphaned_references(graph: &LibraryGraph) -> Vec<OrphanedReference> {
+    let mut orphans = Vec::new();
+    for agent in graph.agents {
+        let mut report = |entity_type: &str, reference: &String| {
+            orphans.push(OrphanedReference {
+                agent_id: agent.id.clone(),
+                agent_name: agent.name.clone(),
+                entity_type: entity_type.to_string(),
+                reference: reference.clone(),
+            });
+        };
+        for id in &agent.skills {
+            if !graph.skills.iter().any(|s| &s.id == id) {
+                report("skill", id);
+            }
+        }
+        for id in &agent.instructions {
+            if !graph.instructions.iter().any(|i| &i.id == id) {
+                report("instruction", id);
+            }
+        }
+        if let Some(parent) = &agent.parent_agent_id {
+            if !graph.agents.iter().any(|a| &a.id == parent) {
+                report("parent", parent);
+            }
+        }
+    }
+    orphans
+}
+
+/// Drop the references in `orphans` that belong to `agent`. Returns whether
+/// anything was removed.
+pub fn remove_orphans(agent: &mut Agent, orphans: &[OrphanedReference]) -> bool {
+    let orphaned = |entity_type: &str, id: &String| {
+        orphans
+            .iter()
+            .any(|o| o.agent_id == agent.id && o.entity_type == entity_type && &o.reference == id)
+    };
+    let before = agent.skills.len() + agent.instructions.len();
+    let parent = agent.parent_agent_id.take();
+    let orphaned_parent = parent.as_ref().is_some_and(|p| orphaned("parent", p));
+    let skills = agent
+        .skills
+        .iter()
+        .filter(|id| !orphaned("skill", id))
+        .cloned()
+        .collect();
+    let instructions = agent
+        .instructions
+        .iter()
+        .filter(|id| !orphaned("instruction", id))
+        .cloned()
+        .collect();
+    agent.skills = skills;
+    agent.instructions = instructions;
+    if !orphaned_parent {
+        agent.parent_agent_id = parent;
+    }
+    orphaned_parent || agent.skills.len() + agent.instructions.len() != before
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    struct MapGraph(HashMap<String, Vec<String>>);
+
+    impl MapGraph {
+        fn new(edges: &[(&str, &[&str])]) -> Self {
+            Self(
+                edges
+                    .iter()
+                    .map(|(node, refs)| {
+                        (
+                            node.to_string(),
+                            refs.iter().map(|r| r.to_string()).collect(),
+                        )
+                    })
+                    .collect(),
+            )
+        }
+    }
+
+    impl ReferenceGraph for MapGraph {
+        fn references(&self, node: &str) -> Option<Vec<String>> {
+            self.0.get(node).cloned()
+        }
+    }
+
+    #[test]
+    fn test_shared_dependencies_are_not_cycles() {
+        let graph = MapGraph::new(&[("a", &["b", "c"]), ("b", &["d"]), ("c", &["d"]), ("d", &[])]);
+        assert!(check(&graph, "a").is_empty());
+    }
+
+    #[test]
+    fn test_cycles_depth_and_missing_are_reported() {
+        let graph = MapGraph::new(&[("a", &["b", "ghost"]), ("b", &["c"]), ("c", &["a"])]);
+        let errors = check(&graph, "a");
+        assert_eq!(errors[0].to_string(), "Cycle detected: a → b → c → a");
+        assert_eq!(errors[1].to_string(), "a references missing ghost");
+
+        let names: Vec<String> = (0..=MAX_DEPTH).map(|i| format!("n{}", i)).collect();
+        let mut deep = MapGraph(HashMap::new());
+        for pair in names.windows(2) {
+            deep.0.insert(pair[0].clone(), vec![pair[1].clone()]);
+        }
+        deep.0.insert(names[MAX_DEPTH].clone(), vec![]);
+        assert!(matches!(
+            check(&deep, "n0").as_slice(),
+            [CompositionError::TooDeep(path)] if path.len() == MAX_DEPTH + 1
+        ));
+    }
+
+    #[test]
+    fn test_agents_inherit_what_they_leave_unset() {
+        let base = Agent {
+            id: "base".to_string(),
+            system_prompt: "You work on the payments service.".to_string(),
+            personality: Personality {
+                tone: "direct".to_string(),
+                creativity: 0.2,
+                ..Personality::default()
+            },
+            skills: vec!["search".to_string()],
+            instructions: vec!["style".to_string()],
+            ..Agent::default()
+        };
+        let reviewer = Agent {
+            id: "reviewer".to_string(),
+            parent_agent_id: Some("base".to_string()),
+            system_prompt: String::new(),
+            personality: Personality {
+                creativity: 0.9,
+                ..Personality::default()
+            },
+            skills: vec!["diff".to_string(), "search".to_string()],
+            ..Agent::default()
+        };
+        let strict = Agent {
+            id: "strict".to_string(),
+            parent_agent_id: Some("reviewer".to_string()),
+            system_prompt: "Block anything untested.".to_string(),
+            instructions: vec!["tests".to_string()],
+            ..Agent::default()
+        };
+        let agents = vec![base, reviewer, strict.clone()];
+
+        let resolved = inherited(&strict, &agents);
+        assert_eq!(resolved.system_prompt, "Block anything untested.");
+        assert_eq!(resolved.personality.tone, "direct");
+        assert_eq!(resolved.personality.creativity, 0.9);
+        assert_eq!(resolved.skills, ["search", "diff"]);
+        assert_eq!(resolved.instructions, ["style", "tests"]);
+        assert_eq!(resolved.parent_agent_id, None);
+        assert_eq!(
+            inherited(&agents[1], &agents).system_prompt,
+            "You work on the payments service."
+        );
+
+        assert!(ensure_valid_parent(&agents, &strict).is_ok());
+        let looped = Agent {
+            parent_agent_id: Some("strict".to_string()),
+            ..agents[0].clone()
+        };
+        assert!(ensure_valid_parent(&agents, &looped)
+            .unwrap_err()
+            .contains("Cycle"));
+        let orphan = Agent {
+            parent_agent_id: Some("ghost".to_string()),
+            ..Agent::default()
+        };
+        assert!(ensure_valid_parent(&agents, &orphan).is_err());
+    }
+
+    #[test]
+    fn test_orphaned_references_are_found_and_removed() {
+        let skill = Skill {
+            id: "search".to_string(),
+            ..Skill::default()
+        };
+        let mut agent = Agent {
+            id: "reviewer".to_string(),
+            skills: vec!["search".to_string(), "deleted-skill".to_string()],
+            instructions: vec!["deleted-rule".to_string()],
+            parent_agent_id: Some("deleted-base".to_string()),
+            ..Agent::default()
+        };
+        let orphans = orphaned_references(&LibraryGraph {
+            agents: std::slice::from_ref(&agent),
+            skills: &[skill],
+            instructions: &[],
+        });
+        let found: Vec<_> = orphans
+            .iter()
+            .map(|o| (o.entity_type.as_str(), o.reference.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("skill", "deleted-skill"),
+                ("instruction", "deleted-rule"),
+                ("parent", "deleted-base")
+            ]
+        );
+
+        assert!(remove_orphans(&mut agent, &orphans));
+        assert_eq!(agent.skills, ["search"]);
+        assert!(agent.instructions.is_empty());
+        assert_eq!(agent.parent_agent_id, None);
+        assert!(!remove_orphans(&mut agent, &orphans));
+    }
+}
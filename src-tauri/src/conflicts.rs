@@ -0,0 +1,339 @@
+//! Contradictory rules across enabled instructions
+//! Every sentence of an enabled instruction is a rule. Two rules from
+//! different instructions conflict when one is negated ("never", "don't",
+//! "avoid") and the other isn't while they share most of their keywords, or
+//! when both pick different options of the same choice, such as tabs and
+//! two-space indentation. Instructions that can't apply together, because
+//! their contexts, file patterns or branch patterns differ, aren't compared.
+//! The heuristics narrow down what to read rather than judge, so expect the
+//! odd false alarm.
+
+use crate::code_blocks::FenceTracker;
+use crate::models::{ConflictingRule, Instruction, InstructionConflict};
+use regex::Regex;
+use std::collections::BTreeSet;
+use std::sync::OnceLock;
+
+/// Words that turn a rule into a prohibition
+const NEGATIONS: &[&str] = &[
+    "never",
+    "not",
+    "no",
+    "nor",
+    "don't",
+    "dont",
+    "doesn't",
+    "shouldn't",
+    "mustn't",
+    "can't",
+    "cannot",
+    "avoid",
+    "without",
+    "instead",
+    "stop",
+    "forbid",
+    "forbidden",
+    "disallow",
+];
+
+/// Words that say nothing about what a rule is about
+const STOPWORDS: &[&str] = &[
+    "a", "all", "always", "an", "and", "any", "are", "as", "at", "be", "but", "by", "can", "do",
+    "each", "every", "for", "from", "if", "in", "into", "is", "it", "its", "make", "must", "of",
+    "on", "only", "or", "our", "over", "please", "prefer", "should", "so", "sure", "than", "that",
+    "the", "these", "this", "those", "to", "use", "using", "we", "when", "will", "with", "you",
+    "your",
+];
+
+/// Choices a rule can make only one way, as (topic, [(option, pattern)])
+const CHOICES: &[(&str, &[(&str, &str)])] = &[
+    (
+        "indentation",
+        &[
+            (
+                "tabs",
+                r"\btabs\b|\b(?:hard tabs?|tab characters?|tab indentation)\b",
+            ),
+            ("2 spaces", r"\b(?:2|two)[- ]spaces?\b"),
+            ("4 spaces", r"\b(?:4|four)[- ]spaces?\b"),
+        ],
+    ),
+    (
+        "quotes",
+        &[
+            ("single quotes", r"\bsingle[- ]quot(?:es?|ed)\b"),
+            ("double quotes", r"\bdouble[- ]quot(?:es?|ed)\b"),
+        ],
+    ),
+    (
+        "naming",
+        &[
+            ("camelCase", r"\bcamel ?case\b"),
+            ("snake_case", r"\bsnake[_ ]?case\b"),
+            ("PascalCase", r"\bpascal ?case\b"),
+            ("kebab-case", r"\bkebab[- ]?case\b"),
+        ],
+    ),
+    (
+        "package manager",
+        &[
+            ("npm", r"\bnpm\b"),
+            ("yarn", r"\byarn\b"),
+            ("pnpm", r"\bpnpm\b"),
+            ("bun", r"\bbun\b"),
+        ],
+    ),
+];
+
+/// Share of the shorter rule's keywords a negated pair must have in common
+const NEGATION_OVERLAP: f64 = 0.6;
+
+struct Rule<'a> {
+    instruction: &'a Instruction,
+    sentence: String,
+    negated: bool,
+    keywords: BTreeSet<String>,
+    choice: Option<(&'static str, &'static str)>,
+    // Keywords besides the words naming the choice
+    subject: BTreeSet<String>,
+}
+
+/// Pairs of rules in the enabled `instructions` that look contradictory
+pub fn find(instructions: &[Instruction]) -> Vec<InstructionConflict> {
+    let rules: Vec<Rule> = instructions
+        .iter()
+        .filter(|i| i.enabled)
+        .flat_map(|instruction| {
+            sentences(&instruction.content)
+                .into_iter()
+                .map(move |sentence| rule(instruction, sentence))
+        })
+        .collect();
+
+    let mut conflicts = Vec::new();
+    for (index, a) in rules.iter().enumerate() {
+        for b in &rules[index + 1..] {
+            if a.instruction.id == b.instruction.id
+                || !may_apply_together(a.instruction, b.instruction)
+            {
+                continue;
+            }
+            if let Some((kind, topic)) = conflict(a, b) {
+                conflicts.push(InstructionConflict {
+                    kind: kind.to_string(),
+                    topic,
+                    first: conflicting_rule(a),
+                    second: conflicting_rule(b),
+                });
+            }
+        }
+    }
+    conflicts
+}
+
+fn conflict(a: &Rule, b: &Rule) -> Option<(&'static str, String)> {
+    if let (Some((topic, first)), Some((other, second))) = (a.choice, b.choice) {
+        // Choices made for different things ("camelCase for variables",
+        // "snake_case for functions") don't clash
+        let unrelated =
+            a.subject.is_disjoint(&b.subject) && !a.subject.is_empty() && !b.subject.is_empty();
+        if topic == other && first != second && !unrelated {
+            return Some(("choice", format!("{}: {} or {}", topic, first, second)));
+        }
+        return None;
+    }
+
+    let shared: Vec<&str> = a
+        .keywords
+        .intersection(&b.keywords)
+        .map(String::as_str)
+        .collect();
+    let shorter = a.keywords.len().min(b.keywords.len());
+    let overlapping =
+        !shared.is_empty() && shared.len() as f64 >= shorter as f64 * NEGATION_OVERLAP;
+    (a.negated != b.negated && overlapping).then(|| ("negation", shared.join(", ")))
+}
+
+fn rule(instruction: &Instruction, sentence: String) -> Rule<'_> {
+    let lower = sentence.to_lowercase().replace('’', "'");
+    let negated = words(&lower).any(|word| NEGATIONS.contains(&word));
+
+    // A rule makes a choice when it asks for exactly one of the options
+    let mut choice = None;
+    let mut rest = lower.clone();
+    if !negated {
+        for ((topic, options), patterns) in CHOICES.iter().zip(choice_patterns()) {
+            let picked: Vec<&str> = options
+                .iter()
+                .zip(patterns)
+                .filter(|(_, pattern)| pattern.is_match(&lower))
+                .map(|((option, _), _)| *option)
+                .collect();
+            if let [option] = picked[..] {
+                choice = Some((*topic, option));
+                for pattern in patterns {
+                    rest = pattern.replace_all(&rest, " ").into_owned();
+                }
+                break;
+            }
+        }
+    }
+
+    Rule {
+        instruction,
+        sentence,
+        negated,
+        keywords: keywords(&lower),
+        choice,
+        subject: keywords(&rest),
+    }
+}
+
+fn words(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|c: char| !(c.is_alphanumeric() || c == '\'' || c == '_'))
+        .map(|word| word.trim_matches('\''))
+        .filter(|word| !word.is_empty())
+}
+
+fn keywords(text: &str) -> BTreeSet<String> {
+    words(text)
+        .filter(|word| word.len() > 2 && !word.chars().all(|c| c.is_ascii_digit()))
+        .filter(|word| !NEGATIONS.contains(word) && !STOPWORDS.contains(word))
+        .map(stem)
+        .collect()
+}
+
+fn choice_patterns() -> &'static Vec<Vec<Regex>> {
+    static PATTERNS: OnceLock<Vec<Vec<Regex>>> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        CHOICES
+            .iter()
+            .map(|(_, options)| {
+                options
+                    .iter()
+                    .map(|(_, pattern)| Regex::new(pattern).unwrap())
+                    .collect()
+            })
+            .collect()
+    })
+}
+
+/// Plural and singular count as the same keyword
+fn stem(word: &str) -> String {
+    match word.strip_suffix('s') {
+        Some(stem) if word.len() > 3 && !stem.ends_with('s') => stem.to_string(),
+        _ => word.to_string(),
+    }
+}
+
+/// The sentences of `text` outside code blocks and headings, list markers
+/// removed
+fn sentences(text: &str) -> Vec<String> {
+    static MARKER: OnceLock<Regex> = OnceLock::new();
+    let marker = MARKER.get_or_init(|| Regex::new(r"^(?:[-*+>]|\d+[.)])\s+").unwrap());
+
+    let mut fences = FenceTracker::default();
+    let mut found = Vec::new();
+    for line in text.lines() {
+        if fences.update(line) || line.trim_start().starts_with('#') {
+            continue;
+        }
+        let line = marker.replace(line.trim(), "");
+        let mut start = 0;
+        for (i, c) in line.char_indices() {
+            let ends = matches!(c, '.' | '!' | '?' | ';')
+                && line[i + 1..].starts_with(char::is_whitespace);
+            if ends {
+                found.push(line[start..=i].trim().to_string());
+                start = i + 1;
+            }
+        }
+        found.push(line[start..].trim().to_string());
+    }
+    found.retain(|sentence| sentence.split_whitespace().count() >= 2);
+    found
+}
+
+/// Whether both instructions can be assembled for the same task
+fn may_apply_together(a: &Instruction, b: &Instruction) -> bool {
+    let disjoint_contexts = !a.contexts.is_empty()
+        && !b.contexts.is_empty()
+        && !a
+            .contexts
+            .iter()
+            .any(|c| b.contexts.iter().any(|d| d.eq_ignore_ascii_case(c)));
+    let differ =
+        |x: &Option<String>, y: &Option<String>| matches!((x, y), (Some(x), Some(y)) if x != y);
+    !disjoint_contexts
+        && !differ(&a.file_pattern, &b.file_pattern)
+        && !differ(&a.branch_pattern, &b.branch_pattern)
+}
+
+fn conflicting_rule(rule: &Rule) -> ConflictingRule {
+    ConflictingRule {
+        instruction_id: rule.instruction.id.clone(),
+        instruction_name: rule.instruction.name.clone(),
+        sentence: rule.sentence.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instruction(id: &str, content: &str) -> Instruction {
+        Instruction {
+            id: id.to_string(),
+            name: id.to_string(),
+            content: content.to_string(),
+            ..Instruction::default()
+        }
+    }
+
+    #[test]
+    fn test_contradictions_are_paired() {
+        let instructions = vec![
+            instruction(
+                "style",
+                "# Style\n- Always indent with tabs.\n- End statements with semicolons.\n\
+                 ```js\nconst a = 'never';\n```",
+            ),
+            instruction(
+                "format",
+                "Use 2 spaces. Never use semicolons.\nUse camelCase for variables.",
+            ),
+            instruction("naming", "Use snake_case for functions; commit often."),
+            // Never assembled together
+            Instruction {
+                contexts: vec!["go".to_string()],
+                ..instruction("go", "Use single quotes.")
+            },
+            Instruction {
+                contexts: vec!["python".to_string()],
+                ..instruction("python", "Use double quotes.")
+            },
+        ];
+
+        let found: Vec<(String, String, String, String)> = find(&instructions)
+            .into_iter()
+            .map(|c| (c.kind, c.topic, c.first.sentence, c.second.sentence))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (
+                    "choice".to_string(),
+                    "indentation: tabs or 2 spaces".to_string(),
+                    "Always indent with tabs.".to_string(),
+                    "Use 2 spaces.".to_string()
+                ),
+                (
+                    "negation".to_string(),
+                    "semicolon".to_string(),
+                    "End statements with semicolons.".to_string(),
+                    "Never use semicolons.".to_string()
+                ),
+            ]
+        );
+    }
+}
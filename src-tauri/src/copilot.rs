@@ -0,0 +1,236 @@
+//! GitHub Copilot instructions
+//! Reads and writes the instruction files VS Code's Copilot picks up: the
+//! repository-wide `.github/copilot-instructions.md`, read one instruction
+//! per section like a CLAUDE.md, and path-specific
+//! `.github/instructions/*.instructions.md` files. Their `applyTo`
+//! frontmatter maps to the instruction's file pattern; `**` (every file)
+//! means no pattern.
+
+use crate::categories;
+use crate::code_blocks;
+use crate::cursor::{split_frontmatter, unquote};
+use crate::models::Instruction;
+use crate::parser;
+use crate::scope;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Repository-wide instructions, relative to the repository root
+pub const REPOSITORY_FILE: &str = ".github/copilot-instructions.md";
+
+/// Directory of path-specific instruction files
+pub const INSTRUCTIONS_DIR: &str = ".github/instructions";
+
+/// Suffix of path-specific instruction files
+pub const INSTRUCTIONS_SUFFIX: &str = ".instructions.md";
+
+/// `applyTo` value matching every file
+const ALL_FILES: &str = "**";
+
+const DEFAULT_NAME: &str = "Copilot instructions";
+
+/// Read one `.instructions.md` file. The name comes from the first `# `
+/// heading; files read from disk are named after the file instead.
+pub fn parse_instructions_file(text: &str) -> Instruction {
+    let mut instruction = Instruction {
+        name: DEFAULT_NAME.to_string(),
+        description: String::new(),
+        tags: vec!["copilot".to_string()],
+        ..Instruction::default()
+    };
+
+    let (frontmatter, body) = split_frontmatter(text);
+    for line in frontmatter.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = unquote(value.trim());
+        match key.trim() {
+            "description" => instruction.description = value.to_string(),
+            "applyTo" => {
+                instruction.file_pattern =
+                    scope::normalize_file_pattern(value).filter(|pattern| pattern != ALL_FILES)
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(heading) = body
+        .lines()
+        .find_map(|line| line.strip_prefix("# "))
+        .map(str::trim)
+        .filter(|heading| !heading.is_empty())
+    {
+        instruction.name = heading.to_string();
+    }
+    instruction.category = categories::infer_category(&instruction.name);
+    instruction.content = code_blocks::fence_code(body.trim());
+    instruction
+}
+
+/// The `.instructions.md` file for an instruction
+pub fn export_instructions_file(instruction: &Instruction) -> String {
+    let apply_to = instruction
+        .file_pattern
+        .as_deref()
+        .map(|pattern| {
+            pattern
+                .split(',')
+                .map(str::trim)
+                .collect::<Vec<_>>()
+                .join(",")
+        })
+        .unwrap_or_else(|| ALL_FILES.to_string());
+
+    let mut output = String::from("---\n");
+    let description = instruction
+        .description
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    if !description.is_empty() {
+        output.push_str(&format!(
+            "description: {}\n",
+            serde_json::to_string(&description).unwrap()
+        ));
+    }
+    output.push_str(&format!(
+        "applyTo: {}\n",
+        serde_json::to_string(&apply_to).unwrap()
+    ));
+    output.push_str("---\n\n");
+    output.push_str(instruction.content.trim());
+    output.push('\n');
+    output
+}
+
+/// Read Copilot instructions from `path`: a repository root (its
+/// `copilot-instructions.md` and every `.github/instructions` file), an
+/// instructions directory, or a single file
+pub fn read_instructions(path: &Path) -> Result<Vec<Instruction>, String> {
+    let files: Vec<PathBuf> = if path.is_dir() {
+        let instructions_dir = if path.join(INSTRUCTIONS_DIR).is_dir() {
+            path.join(INSTRUCTIONS_DIR)
+        } else {
+            path.to_path_buf()
+        };
+        let mut files: Vec<PathBuf> = fs::read_dir(&instructions_dir)
+            .map_err(|e| format!("Failed to read {}: {}", instructions_dir.display(), e))?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|file| is_instructions_file(file))
+            .collect();
+        files.sort();
+        let repository = path.join(REPOSITORY_FILE);
+        if repository.is_file() {
+            files.insert(0, repository);
+        }
+        files
+    } else {
+        vec![path.to_path_buf()]
+    };
+    if files.is_empty() {
+        return Err(format!(
+            "No {} or {}/*{} found in {}",
+            REPOSITORY_FILE,
+            INSTRUCTIONS_DIR,
+            INSTRUCTIONS_SUFFIX,
+            path.display()
+        ));
+    }
+
+    let mut instructions = Vec::new();
+    for file in files {
+        let text = fs::read_to_string(&file)
+            .map_err(|e| format!("Failed to read {}: {}", file.display(), e))?;
+        let source = format!("Imported from {}", file.display());
+
+        if is_instructions_file(&file) {
+            let mut instruction = parse_instructions_file(&text);
+            if let Some(name) = file.file_name().map(|n| n.to_string_lossy()) {
+                instruction.name = name
+                    .trim_end_matches(INSTRUCTIONS_SUFFIX)
+                    .replace(['-', '_'], " ");
+                instruction.category = categories::infer_category(&instruction.name);
+            }
+            if instruction.description.is_empty() {
+                instruction.description = source;
+            }
+            if !instruction.content.is_empty() {
+                instructions.push(instruction);
+            }
+        } else {
+            for mut instruction in parser::parse_project_instructions(&text) {
+                instruction.description = source.clone();
+                instruction.tags = vec!["copilot".to_string()];
+                instructions.push(instruction);
+            }
+        }
+    }
+    if instructions.is_empty() {
+        return Err(format!("{} has no instructions to import", path.display()));
+    }
+    Ok(instructions)
+}
+
+fn is_instructions_file(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name.to_string_lossy().ends_with(INSTRUCTIONS_SUFFIX))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_to_round_trips() {
+        let text = "---\napplyTo: \"**/*.ts,**/*.tsx\"\n---\n# TypeScript\n\nPrefer `unknown` over `any`.\n";
+        let instruction = parse_instructions_file(text);
+        assert_eq!(instruction.name, "TypeScript");
+        assert_eq!(
+            instruction.file_pattern.as_deref(),
+            Some("**/*.ts, **/*.tsx")
+        );
+        assert!(instruction.description.is_empty());
+
+        let exported = export_instructions_file(&instruction);
+        assert!(exported.starts_with("---\napplyTo: \"**/*.ts,**/*.tsx\"\n---\n\n# TypeScript"));
+        assert_eq!(
+            parse_instructions_file(&exported).content,
+            instruction.content
+        );
+
+        let everywhere = parse_instructions_file("---\napplyTo: '**'\n---\nBe brief.");
+        assert_eq!(everywhere.file_pattern, None);
+        assert!(export_instructions_file(&everywhere).contains("applyTo: \"**\"\n"));
+    }
+
+    #[test]
+    fn test_reads_repository_file_and_instructions_dir() {
+        let dir = std::env::temp_dir().join(format!("pf-copilot-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(dir.join(INSTRUCTIONS_DIR)).unwrap();
+        fs::write(
+            dir.join(REPOSITORY_FILE),
+            "# Project\n\n## Testing\n\nRun `cargo test`.\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join(INSTRUCTIONS_DIR).join("react-components.instructions.md"),
+            "---\ndescription: React rules\napplyTo: \"src/**/*.tsx\"\n---\nUse function components.\n",
+        )
+        .unwrap();
+        fs::write(dir.join(INSTRUCTIONS_DIR).join("README.md"), "ignored").unwrap();
+
+        let instructions = read_instructions(&dir).unwrap();
+        let names: Vec<&str> = instructions.iter().map(|i| i.name.as_str()).collect();
+        assert_eq!(names, vec!["Testing", "react components"]);
+        assert_eq!(instructions[1].description, "React rules");
+        assert_eq!(
+            instructions[1].file_pattern.as_deref(),
+            Some("src/**/*.tsx")
+        );
+        assert!(instructions[0].description.starts_with("Imported from"));
+
+        fs::remove_dir_all(&dir).ok();
+        assert!(read_instructions(&dir).is_err());
+    }
+}
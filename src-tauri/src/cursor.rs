@@ -0,0 +1,241 @@
+//! Cursor rules
+//! Reads and writes Cursor's project rules: the legacy `.cursorrules` file,
+//! read one instruction per section like a CLAUDE.md, and
+//! `.cursor/rules/*.mdc` files. An `.mdc` file's frontmatter holds
+//! `description`, `globs` and `alwaysApply`, which map to the instruction's
+//! description, file pattern and pinning. Cursor writes globs unquoted
+//! (`globs: *.ts`), which isn't valid YAML, so the frontmatter is read line
+//! by line.
+
+use crate::categories;
+use crate::code_blocks;
+use crate::models::Instruction;
+use crate::parser;
+use crate::scope;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Legacy single-file rules at the repository root
+pub const LEGACY_FILE: &str = ".cursorrules";
+
+/// Directory of `.mdc` rules, relative to the repository root
+pub const RULES_DIR: &str = ".cursor/rules";
+
+const DEFAULT_NAME: &str = "Cursor rule";
+
+/// Read one `.mdc` rule. The name comes from the first `# ` heading; rules
+/// read from disk are named after their file instead.
+pub fn parse_rule(text: &str) -> Instruction {
+    let mut instruction = Instruction {
+        name: DEFAULT_NAME.to_string(),
+        description: String::new(),
+        tags: vec!["cursor".to_string()],
+        ..Instruction::default()
+    };
+
+    let (frontmatter, body) = split_frontmatter(text);
+    for line in frontmatter.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = unquote(value.trim());
+        match key.trim() {
+            "description" => instruction.description = value.to_string(),
+            "globs" => {
+                let globs = value.trim_start_matches('[').trim_end_matches(']');
+                let globs: Vec<&str> = globs.split(',').map(|g| unquote(g.trim())).collect();
+                instruction.file_pattern = scope::normalize_file_pattern(&globs.join(","));
+            }
+            "alwaysApply" => instruction.pin_to_context = value.eq_ignore_ascii_case("true"),
+            _ => {}
+        }
+    }
+
+    if let Some(heading) = body
+        .lines()
+        .find_map(|line| line.strip_prefix("# "))
+        .map(str::trim)
+        .filter(|heading| !heading.is_empty())
+    {
+        instruction.name = heading.to_string();
+    }
+    instruction.category = categories::infer_category(&instruction.name);
+    instruction.content = code_blocks::fence_code(body.trim());
+    instruction
+}
+
+/// Frontmatter and body of a rules file; the frontmatter is empty when the
+/// file has none. Copilot's `.instructions.md` files share the layout.
+pub fn split_frontmatter(text: &str) -> (&str, &str) {
+    let Some(rest) = text.trim_start().strip_prefix("---").and_then(|rest| {
+        rest.strip_prefix('\n')
+            .or_else(|| rest.strip_prefix("\r\n"))
+    }) else {
+        return ("", text);
+    };
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == "---" {
+            return (&rest[..offset], &rest[offset + line.len()..]);
+        }
+        offset += line.len();
+    }
+    ("", text)
+}
+
+/// A frontmatter value without its surrounding quotes
+pub fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+        .unwrap_or(value)
+}
+
+/// The `.mdc` file for an instruction
+pub fn export_rule(instruction: &Instruction) -> String {
+    let description = instruction
+        .description
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    let globs = instruction
+        .file_pattern
+        .as_deref()
+        .map(|pattern| {
+            pattern
+                .split(',')
+                .map(str::trim)
+                .collect::<Vec<_>>()
+                .join(",")
+        })
+        .unwrap_or_default();
+
+    let mut output = String::from("---\n");
+    output.push_str(&format!("description: {}\n", description));
+    output.push_str(&format!("globs: {}\n", globs));
+    output.push_str(&format!("alwaysApply: {}\n", instruction.pin_to_context));
+    output.push_str("---\n\n");
+    output.push_str(instruction.content.trim());
+    output.push('\n');
+    output
+}
+
+/// Read Cursor rules from `path`: a repository root (its `.cursorrules` and
+/// every `.cursor/rules/*.mdc`), a rules directory, or a single file
+pub fn read_rules(path: &Path) -> Result<Vec<Instruction>, String> {
+    let files: Vec<PathBuf> = if path.is_dir() {
+        let rules_dir = if path.join(RULES_DIR).is_dir() {
+            path.join(RULES_DIR)
+        } else {
+            path.to_path_buf()
+        };
+        let mut files: Vec<PathBuf> = fs::read_dir(&rules_dir)
+            .map_err(|e| format!("Failed to read {}: {}", rules_dir.display(), e))?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|file| file.extension().is_some_and(|ext| ext == "mdc"))
+            .collect();
+        files.sort();
+        let legacy = path.join(LEGACY_FILE);
+        if legacy.is_file() {
+            files.insert(0, legacy);
+        }
+        files
+    } else {
+        vec![path.to_path_buf()]
+    };
+    if files.is_empty() {
+        return Err(format!(
+            "No {} or {}/*.mdc found in {}",
+            LEGACY_FILE,
+            RULES_DIR,
+            path.display()
+        ));
+    }
+
+    let mut instructions = Vec::new();
+    for file in files {
+        let text = fs::read_to_string(&file)
+            .map_err(|e| format!("Failed to read {}: {}", file.display(), e))?;
+        let source = format!("Imported from {}", file.display());
+
+        if file.extension().is_some_and(|ext| ext == "mdc") {
+            let mut instruction = parse_rule(&text);
+            if let Some(stem) = file.file_stem() {
+                instruction.name = stem.to_string_lossy().replace(['-', '_'], " ");
+                instruction.category = categories::infer_category(&instruction.name);
+            }
+            if instruction.description.is_empty() {
+                instruction.description = source;
+            }
+            if !instruction.content.is_empty() {
+                instructions.push(instruction);
+            }
+        } else {
+            for mut instruction in parser::parse_project_instructions(&text) {
+                instruction.description = source.clone();
+                instruction.tags = vec!["cursor".to_string()];
+                instructions.push(instruction);
+            }
+        }
+    }
+    if instructions.is_empty() {
+        return Err(format!("{} has no rules to import", path.display()));
+    }
+    Ok(instructions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rule_round_trips_frontmatter() {
+        let text = "---\ndescription: TypeScript conventions\nglobs: src/**/*.ts, \"*.tsx\"\nalwaysApply: false\n---\n\n# TS Style\n\nUse strict mode.\n";
+        let rule = parse_rule(text);
+        assert_eq!(rule.name, "TS Style");
+        assert_eq!(rule.description, "TypeScript conventions");
+        assert_eq!(rule.file_pattern.as_deref(), Some("src/**/*.ts, *.tsx"));
+        assert!(!rule.pin_to_context);
+
+        let exported = export_rule(&rule);
+        assert!(exported.starts_with(
+            "---\ndescription: TypeScript conventions\nglobs: src/**/*.ts,*.tsx\nalwaysApply: false\n---\n\n# TS Style"
+        ));
+        let again = parse_rule(&exported);
+        assert_eq!(again.file_pattern, rule.file_pattern);
+        assert_eq!(again.content, rule.content);
+
+        let bare = parse_rule("Always answer in English.");
+        assert_eq!(bare.name, DEFAULT_NAME);
+        assert_eq!(bare.content, "Always answer in English.");
+    }
+
+    #[test]
+    fn test_reads_legacy_file_and_rules_dir() {
+        let dir = std::env::temp_dir().join(format!("pf-cursor-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(dir.join(RULES_DIR)).unwrap();
+        fs::write(
+            dir.join(LEGACY_FILE),
+            "# Testing\n\nRun the tests.\n\n# Security\n\nNo secrets in logs.\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join(RULES_DIR).join("api-style.mdc"),
+            "---\nglobs:\nalwaysApply: true\n---\nReturn typed errors.\n",
+        )
+        .unwrap();
+        fs::write(dir.join(RULES_DIR).join("notes.txt"), "ignored").unwrap();
+
+        let rules = read_rules(&dir).unwrap();
+        let names: Vec<&str> = rules.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["Testing", "Security", "api style"]);
+        assert!(rules[2].pin_to_context);
+        assert_eq!(rules[2].file_pattern, None);
+        assert!(rules[2].description.starts_with("Imported from"));
+        assert!(rules.iter().all(|r| r.tags == vec!["cursor".to_string()]));
+
+        assert!(read_rules(&dir.join(RULES_DIR).join("missing.mdc")).is_err());
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -1,9 +1,11 @@
 //! Database module for Prompt Forge
 //! Provides SQLite-backed persistence for agents, skills, instructions, and settings.
 
+use crate::listing::Filter;
 use crate::models::*;
 use chrono::{DateTime, Utc};
-use rusqlite::{params, Connection, Result as SqliteResult};
+use rusqlite::{params, params_from_iter, Connection, OpenFlags, Result as SqliteResult};
+use std::collections::HashMap;
 use std::path::Path;
 use std::sync::Mutex;
 
@@ -15,34 +17,286 @@ pub struct Database {
 impl Database {
     /// Open or create a database at the given path
     pub fn open<P: AsRef<Path>>(path: P) -> SqliteResult<Self> {
-        let conn = Connection::open(path)?;
-        conn.execute_batch("PRAGMA foreign_keys = ON; PRAGMA journal_mode = WAL;")?;
+        Self::open_with_key(path, None)
+    }
+
+    /// Open or create a database, unlocking it with a SQLCipher passphrase when given
+    pub fn open_with_key<P: AsRef<Path>>(path: P, key: Option<&str>) -> SqliteResult<Self> {
+        Ok(Self {
+            conn: Mutex::new(open_connection(path.as_ref(), key)?),
+        })
+    }
+
+    /// Open an existing database for reading only, for processes that share
+    /// the file with the app. The connection never takes a write lock and
+    /// reads through a memory map, so its pages come from the OS page cache
+    /// the app already uses instead of a second private cache.
+    pub fn open_read_only<P: AsRef<Path>>(path: P, key: Option<&str>) -> SqliteResult<Self> {
+        Ok(Self {
+            conn: Mutex::new(open_read_only_connection(path.as_ref(), key)?),
+        })
+    }
+
+    /// Re-encrypt the database file at `path` in place.
+    /// Passing `None` as `new_key` writes a plaintext copy; the live connection is
+    /// swapped over to the rewritten file so callers keep using the same `Database`.
+    pub fn rekey<P: AsRef<Path>>(
+        &self,
+        path: P,
+        current_key: Option<&str>,
+        new_key: Option<&str>,
+    ) -> SqliteResult<()> {
+        let path = path.as_ref();
+        let staging = path.with_extension("db.rekey");
+        std::fs::remove_file(&staging).ok();
+
+        let mut conn = self.conn.lock().unwrap();
+        conn.execute(
+            "ATTACH DATABASE ?1 AS rekeyed KEY ?2",
+            params![staging.to_string_lossy().to_string(), new_key.unwrap_or("")],
+        )?;
+        let exported = conn.query_row("SELECT sqlcipher_export('rekeyed')", [], |_| Ok(()));
+        conn.execute_batch("DETACH DATABASE rekeyed;")?;
+        if let Err(e) = exported {
+            std::fs::remove_file(&staging).ok();
+            return Err(e);
+        }
+
+        // Close the old handle so the WAL is checkpointed before the file is replaced
+        *conn = Connection::open_in_memory()?;
+        for suffix in ["-wal", "-shm"] {
+            let mut side = path.as_os_str().to_owned();
+            side.push(suffix);
+            std::fs::remove_file(side).ok();
+        }
+
+        if let Err(e) = std::fs::rename(&staging, path) {
+            *conn = open_connection(path, current_key)?;
+            return Err(rusqlite::Error::ToSqlConversionFailure(Box::new(e)));
+        }
+
+        *conn = open_connection(path, new_key)?;
+        Ok(())
+    }
+
+    /// Restore a database file into a private in-memory copy, unlocking it
+    /// with `key` when it is encrypted. A plaintext file, such as a snapshot
+    /// taken before encryption was enabled, is read without the key.
+    pub fn restore_in_memory<P: AsRef<Path>>(path: P, key: Option<&str>) -> SqliteResult<Self> {
+        let mut conn = Connection::open_in_memory()?;
+        match key.filter(|_| !is_plaintext(path.as_ref())) {
+            None => conn.restore(
+                rusqlite::DatabaseName::Main,
+                path,
+                None::<fn(rusqlite::backup::Progress)>,
+            )?,
+            Some(key) => {
+                // SQLCipher's backup API refuses encrypted sources, so copy
+                // through an attached database instead
+                conn.execute(
+                    "ATTACH DATABASE ?1 AS source KEY ?2",
+                    params![path.as_ref().to_string_lossy().to_string(), key],
+                )?;
+                let exported =
+                    conn.query_row("SELECT sqlcipher_export('main', 'source')", [], |_| Ok(()));
+                conn.execute_batch("DETACH DATABASE source;")?;
+                exported?;
+            }
+        }
         Ok(Self {
             conn: Mutex::new(conn),
         })
     }
 
+    /// Write a compacted, self-contained copy of the database to `path`
+    pub fn vacuum_into<P: AsRef<Path>>(&self, path: P) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "VACUUM INTO ?1",
+            params![path.as_ref().to_string_lossy().to_string()],
+        )?;
+        Ok(())
+    }
+
+    /// Fold the write-ahead log back into the database file and empty it, so
+    /// nothing is left only in the `-wal` file when the process exits
+    pub fn checkpoint(&self) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))
+    }
+
+    /// Run SQLite's integrity check, returning "ok" when the file is healthy
+    pub fn integrity_check(&self) -> SqliteResult<String> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row("PRAGMA integrity_check", [], |row| row.get(0))
+    }
+
     /// Run database migrations
     pub fn migrate(&self) -> SqliteResult<()> {
         let conn = self.conn.lock().unwrap();
         conn.execute_batch(include_str!("../migrations/001_initial_schema.sql"))?;
 
-        // Check if usage_count column exists before running migration 002
-        let has_usage_count: bool = conn
-            .prepare("SELECT COUNT(*) FROM pragma_table_info('agents') WHERE name='usage_count'")?
-            .query_row([], |row| {
-                let count: i32 = row.get(0)?;
-                Ok(count > 0)
-            })?;
-
-        // Only run migration if columns don't exist
-        if !has_usage_count {
+        // Only run column migrations if the columns don't exist yet
+        if !has_column(&conn, "agents", "usage_count")? {
             conn.execute_batch(include_str!("../migrations/002_add_usage_tracking.sql"))?;
         }
 
+        conn.execute_batch(include_str!("../migrations/003_add_edit_locks.sql"))?;
+
+        if !has_column(&conn, "agents", "avatar_image_id")? {
+            conn.execute_batch(include_str!("../migrations/004_add_attachments.sql"))?;
+        }
+
+        if !has_column(&conn, "settings", "notifications_json")? {
+            conn.execute_batch(include_str!("../migrations/005_add_notification_settings.sql"))?;
+        }
+
+        if !has_column(&conn, "settings", "insights_enabled")? {
+            conn.execute_batch(include_str!("../migrations/006_add_insights.sql"))?;
+        }
+
+        if !has_column(&conn, "settings", "redaction_profiles_json")? {
+            conn.execute_batch(include_str!("../migrations/007_add_redaction_profiles.sql"))?;
+        }
+
+        if !has_column(&conn, "instructions", "pin_to_context")? {
+            conn.execute_batch(include_str!("../migrations/008_add_pinned_instructions.sql"))?;
+        }
+
+        if !has_column(&conn, "agents", "aliases_json")? {
+            conn.execute_batch(include_str!("../migrations/009_add_aliases.sql"))?;
+        }
+
+        conn.execute_batch(include_str!("../migrations/010_add_usage_events.sql"))?;
+
+        if !has_column(&conn, "instructions", "subcategory")? {
+            conn.execute_batch(include_str!("../migrations/011_add_subcategories.sql"))?;
+        }
+
+        if !has_column(&conn, "instructions", "branch_pattern")? {
+            conn.execute_batch(include_str!("../migrations/012_add_branch_patterns.sql"))?;
+        }
+
+        conn.execute_batch(include_str!("../migrations/013_add_releases.sql"))?;
+
+        if !has_column(&conn, "settings", "release_channels_json")? {
+            conn.execute_batch(include_str!("../migrations/014_add_release_channels.sql"))?;
+        }
+
+        if !has_column(&conn, "settings", "block_sync_on_errors")? {
+            conn.execute_batch(include_str!("../migrations/015_add_sync_checks.sql"))?;
+        }
+
+        if !has_column(&conn, "settings", "strict_imports")? {
+            conn.execute_batch(include_str!("../migrations/016_add_strict_imports.sql"))?;
+        }
+
+        if !has_column(&conn, "settings", "llm_providers_json")? {
+            conn.execute_batch(include_str!("../migrations/017_add_translations.sql"))?;
+        }
+
+        if !has_column(&conn, "settings", "setup_completed")? {
+            conn.execute_batch(include_str!("../migrations/018_add_first_run_setup.sql"))?;
+        }
+
+        conn.execute_batch(include_str!("../migrations/019_add_collections.sql"))?;
+
+        conn.execute_batch(include_str!("../migrations/020_add_operation_journal.sql"))?;
+
+        conn.execute_batch(include_str!("../migrations/021_add_usage_heatmap.sql"))?;
+
+        if !has_column(&conn, "instructions", "file_pattern")? {
+            conn.execute_batch(include_str!("../migrations/022_add_file_patterns.sql"))?;
+        }
+
+        if !has_column(&conn, "settings", "retention_json")? {
+            conn.execute_batch(include_str!("../migrations/023_add_retention.sql"))?;
+        }
+
+        conn.execute_batch(include_str!("../migrations/024_add_experiments.sql"))?;
+
+        if !has_column(&conn, "instructions", "usage_count")? {
+            conn.execute_batch(include_str!(
+                "../migrations/025_add_skill_instruction_usage.sql"
+            ))?;
+        }
+
+        if !has_column(&conn, "instructions", "review_due_at")? {
+            conn.execute_batch(include_str!("../migrations/026_add_review_dates.sql"))?;
+        }
+
+        if !has_column(&conn, "settings", "library_folder")? {
+            conn.execute_batch(include_str!("../migrations/027_add_library_folder.sql"))?;
+        }
+
+        if !has_column(&conn, "agents", "instruction_order")? {
+            conn.execute_batch(include_str!("../migrations/028_add_instruction_order.sql"))?;
+        }
+
+        if !has_column(&conn, "agents", "include_global_instructions")? {
+            conn.execute_batch(include_str!(
+                "../migrations/029_add_global_instruction_opt_out.sql"
+            ))?;
+        }
+
+        if !has_column(&conn, "instructions", "contexts_json")? {
+            conn.execute_batch(include_str!(
+                "../migrations/030_add_instruction_contexts.sql"
+            ))?;
+        }
+
+        if !has_column(&conn, "instructions", "upstream_url")? {
+            conn.execute_batch(include_str!(
+                "../migrations/031_add_instruction_upstream.sql"
+            ))?;
+        }
+
+        if !has_column(&conn, "settings", "mcp_write_tools")? {
+            conn.execute_batch(include_str!("../migrations/032_add_mcp_write_tools.sql"))?;
+        }
+
+        if !has_column(&conn, "instructions", "activation_json")? {
+            conn.execute_batch(include_str!(
+                "../migrations/033_add_instruction_activation.sql"
+            ))?;
+        }
+
+        if !has_column(&conn, "agents", "parent_agent_id")? {
+            conn.execute_batch(include_str!("../migrations/034_add_agent_parent.sql"))?;
+        }
+
+        conn.execute_batch(include_str!("../migrations/035_add_snippets.sql"))?;
+
+        if !has_column(&conn, "settings", "mcp_run_tools")? {
+            conn.execute_batch(include_str!("../migrations/036_add_tool_approvals.sql"))?;
+        }
+
         Ok(())
     }
 
+    /// Migrations whose changes are missing from the database, oldest first
+    pub fn missing_migrations(&self) -> SqliteResult<Vec<&'static str>> {
+        let conn = self.conn.lock().unwrap();
+        let mut missing = Vec::new();
+        for (migration, table, column) in MIGRATION_MARKERS {
+            if !has_column(&conn, table, column)? {
+                missing.push(*migration);
+            }
+        }
+        Ok(missing)
+    }
+
+    /// The newest migration applied to the database
+    pub fn schema_version(&self) -> SqliteResult<Option<&'static str>> {
+        let conn = self.conn.lock().unwrap();
+        for (migration, table, column) in MIGRATION_MARKERS.iter().rev() {
+            if has_column(&conn, table, column)? {
+                return Ok(Some(migration));
+            }
+        }
+        Ok(None)
+    }
+
     /// Check if the database has any data (for first-run detection)
     pub fn is_empty(&self) -> SqliteResult<bool> {
         let conn = self.conn.lock().unwrap();
@@ -58,8 +312,10 @@ impl Database {
         let conn = self.conn.lock().unwrap();
         conn.execute(
             "INSERT INTO agents (id, name, description, avatar_emoji, personality_json,
-             system_prompt, skills_json, instructions_json, tags_json, created_at, updated_at, usage_count, last_used_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+             system_prompt, skills_json, instructions_json, tags_json, created_at, updated_at, usage_count, last_used_at,
+             avatar_image_id, aliases_json, instruction_order, include_global_instructions,
+             parent_agent_id)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)",
             params![
                 agent.id,
                 agent.name,
@@ -74,6 +330,11 @@ impl Database {
                 agent.updated_at.to_rfc3339(),
                 agent.usage_count,
                 agent.last_used_at.map(|dt| dt.to_rfc3339()),
+                agent.avatar_image_id,
+                serde_json::to_string(&agent.aliases).unwrap(),
+                instruction_order_to_string(&agent.instruction_order),
+                agent.include_global_instructions,
+                agent.parent_agent_id,
             ],
         )?;
         Ok(())
@@ -83,70 +344,46 @@ impl Database {
         let conn = self.conn.lock().unwrap();
         let mut stmt = conn.prepare(
             "SELECT id, name, description, avatar_emoji, personality_json, system_prompt,
-             skills_json, instructions_json, tags_json, created_at, updated_at, usage_count, last_used_at FROM agents
+             skills_json, instructions_json, tags_json, created_at, updated_at, usage_count, last_used_at,
+             avatar_image_id, aliases_json, instruction_order, include_global_instructions,
+             parent_agent_id
+             FROM agents
              ORDER BY usage_count DESC",
         )?;
 
         let agents = stmt
-            .query_map([], |row| {
-                Ok(Agent {
-                    id: row.get(0)?,
-                    name: row.get(1)?,
-                    description: row.get(2)?,
-                    avatar_emoji: row.get(3)?,
-                    personality: serde_json::from_str(&row.get::<_, String>(4)?).unwrap_or_default(),
-                    system_prompt: row.get(5)?,
-                    skills: serde_json::from_str(&row.get::<_, String>(6)?).unwrap_or_default(),
-                    instructions: serde_json::from_str(&row.get::<_, String>(7)?).unwrap_or_default(),
-                    tags: serde_json::from_str(&row.get::<_, String>(8)?).unwrap_or_default(),
-                    created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(9)?)
-                        .map(|dt| dt.with_timezone(&Utc))
-                        .unwrap_or_else(|_| Utc::now()),
-                    updated_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(10)?)
-                        .map(|dt| dt.with_timezone(&Utc))
-                        .unwrap_or_else(|_| Utc::now()),
-                    usage_count: row.get(11)?,
-                    last_used_at: row.get::<_, Option<String>>(12)?
-                        .and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
-                        .map(|dt| dt.with_timezone(&Utc)),
-                })
-            })?
+            .query_map([], row_to_agent)?
             .collect::<Result<Vec<_>, _>>()?;
 
         Ok(agents)
     }
 
+    /// One page of the agents matching `filter`
+    pub fn list_agents(&self, filter: &Filter) -> SqliteResult<Page<Agent>> {
+        self.list_page(
+            "SELECT id, name, description, avatar_emoji, personality_json, system_prompt,
+             skills_json, instructions_json, tags_json, created_at, updated_at, usage_count, last_used_at,
+             avatar_image_id, aliases_json, instruction_order, include_global_instructions,
+             parent_agent_id
+             FROM agents",
+            filter,
+            row_to_agent,
+        )
+    }
+
     pub fn get_agent(&self, id: &str) -> SqliteResult<Option<Agent>> {
         let conn = self.conn.lock().unwrap();
         let mut stmt = conn.prepare(
             "SELECT id, name, description, avatar_emoji, personality_json, system_prompt,
-             skills_json, instructions_json, tags_json, created_at, updated_at, usage_count, last_used_at
+             skills_json, instructions_json, tags_json, created_at, updated_at, usage_count, last_used_at,
+             avatar_image_id, aliases_json, instruction_order, include_global_instructions,
+             parent_agent_id
              FROM agents WHERE id = ?1",
         )?;
 
         let mut rows = stmt.query(params![id])?;
         if let Some(row) = rows.next()? {
-            Ok(Some(Agent {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                description: row.get(2)?,
-                avatar_emoji: row.get(3)?,
-                personality: serde_json::from_str(&row.get::<_, String>(4)?).unwrap_or_default(),
-                system_prompt: row.get(5)?,
-                skills: serde_json::from_str(&row.get::<_, String>(6)?).unwrap_or_default(),
-                instructions: serde_json::from_str(&row.get::<_, String>(7)?).unwrap_or_default(),
-                tags: serde_json::from_str(&row.get::<_, String>(8)?).unwrap_or_default(),
-                created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(9)?)
-                    .map(|dt| dt.with_timezone(&Utc))
-                    .unwrap_or_else(|_| Utc::now()),
-                updated_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(10)?)
-                    .map(|dt| dt.with_timezone(&Utc))
-                    .unwrap_or_else(|_| Utc::now()),
-                usage_count: row.get(11)?,
-                last_used_at: row.get::<_, Option<String>>(12)?
-                    .and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
-                    .map(|dt| dt.with_timezone(&Utc)),
-            }))
+            Ok(Some(row_to_agent(row)?))
         } else {
             Ok(None)
         }
@@ -157,7 +394,9 @@ impl Database {
         conn.execute(
             "UPDATE agents SET name = ?2, description = ?3, avatar_emoji = ?4,
              personality_json = ?5, system_prompt = ?6, skills_json = ?7,
-             instructions_json = ?8, tags_json = ?9, updated_at = ?10, usage_count = ?11, last_used_at = ?12 WHERE id = ?1",
+             instructions_json = ?8, tags_json = ?9, updated_at = ?10, usage_count = ?11, last_used_at = ?12,
+             avatar_image_id = ?13, aliases_json = ?14, instruction_order = ?15,
+             include_global_instructions = ?16, parent_agent_id = ?17 WHERE id = ?1",
             params![
                 agent.id,
                 agent.name,
@@ -171,6 +410,11 @@ impl Database {
                 agent.updated_at.to_rfc3339(),
                 agent.usage_count,
                 agent.last_used_at.map(|dt| dt.to_rfc3339()),
+                agent.avatar_image_id,
+                serde_json::to_string(&agent.aliases).unwrap(),
+                instruction_order_to_string(&agent.instruction_order),
+                agent.include_global_instructions,
+                agent.parent_agent_id,
             ],
         )?;
         Ok(())
@@ -178,17 +422,30 @@ impl Database {
 
     pub fn delete_agent(&self, id: &str) -> SqliteResult<()> {
         let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "DELETE FROM attachments WHERE id = (SELECT avatar_image_id FROM agents WHERE id = ?1)",
+            params![id],
+        )?;
         conn.execute("DELETE FROM agents WHERE id = ?1", params![id])?;
+        conn.execute(
+            "UPDATE agents SET parent_agent_id = NULL WHERE parent_agent_id = ?1",
+            params![id],
+        )?;
+        conn.execute("DELETE FROM translations WHERE entity_id = ?1", params![id])?;
+        conn.execute(
+            "DELETE FROM collection_items WHERE entity_id = ?1",
+            params![id],
+        )?;
         Ok(())
     }
 
     pub fn record_agent_usage(&self, id: &str) -> SqliteResult<()> {
+        self.record_agent_usage_at(id, &Utc::now())
+    }
+
+    pub fn record_agent_usage_at(&self, id: &str, used_at: &DateTime<Utc>) -> SqliteResult<()> {
         let conn = self.conn.lock().unwrap();
-        conn.execute(
-            "UPDATE agents SET usage_count = usage_count + 1, last_used_at = ?2 WHERE id = ?1",
-            params![id, Utc::now().to_rfc3339()],
-        )?;
-        Ok(())
+        record_usage(&conn, "agents", id, used_at)
     }
 
     // ========================================================================
@@ -199,8 +456,9 @@ impl Database {
         let conn = self.conn.lock().unwrap();
         conn.execute(
             "INSERT INTO skills (id, name, description, icon_emoji, skill_type,
-             definition_json, enabled, created_at, updated_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+             definition_json, enabled, created_at, updated_at, aliases_json, usage_count,
+             last_used_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
             params![
                 skill.id,
                 skill.name,
@@ -211,6 +469,9 @@ impl Database {
                 skill.enabled,
                 skill.created_at.to_rfc3339(),
                 skill.updated_at.to_rfc3339(),
+                serde_json::to_string(&skill.aliases).unwrap(),
+                skill.usage_count,
+                skill.last_used_at.map(|dt| dt.to_rfc3339()),
             ],
         )?;
         Ok(())
@@ -220,64 +481,39 @@ impl Database {
         let conn = self.conn.lock().unwrap();
         let mut stmt = conn.prepare(
             "SELECT id, name, description, icon_emoji, skill_type, definition_json,
-             enabled, created_at, updated_at FROM skills",
+             enabled, created_at, updated_at, aliases_json, usage_count, last_used_at
+             FROM skills",
         )?;
 
         let skills = stmt
-            .query_map([], |row| {
-                Ok(Skill {
-                    id: row.get(0)?,
-                    name: row.get(1)?,
-                    description: row.get(2)?,
-                    icon_emoji: row.get(3)?,
-                    skill_type: string_to_skill_type(&row.get::<_, String>(4)?),
-                    definition: serde_json::from_str(&row.get::<_, String>(5)?).unwrap_or_else(
-                        |_| SkillDefinition::Prompt {
-                            template: String::new(),
-                        },
-                    ),
-                    enabled: row.get(6)?,
-                    created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(7)?)
-                        .map(|dt| dt.with_timezone(&Utc))
-                        .unwrap_or_else(|_| Utc::now()),
-                    updated_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(8)?)
-                        .map(|dt| dt.with_timezone(&Utc))
-                        .unwrap_or_else(|_| Utc::now()),
-                })
-            })?
+            .query_map([], row_to_skill)?
             .collect::<Result<Vec<_>, _>>()?;
 
         Ok(skills)
     }
 
+    /// One page of the skills matching `filter`
+    pub fn list_skills(&self, filter: &Filter) -> SqliteResult<Page<Skill>> {
+        self.list_page(
+            "SELECT id, name, description, icon_emoji, skill_type, definition_json,
+             enabled, created_at, updated_at, aliases_json, usage_count, last_used_at
+             FROM skills",
+            filter,
+            row_to_skill,
+        )
+    }
+
     pub fn get_skill(&self, id: &str) -> SqliteResult<Option<Skill>> {
         let conn = self.conn.lock().unwrap();
         let mut stmt = conn.prepare(
             "SELECT id, name, description, icon_emoji, skill_type, definition_json,
-             enabled, created_at, updated_at FROM skills WHERE id = ?1",
+             enabled, created_at, updated_at, aliases_json, usage_count, last_used_at
+             FROM skills WHERE id = ?1",
         )?;
 
         let mut rows = stmt.query(params![id])?;
         if let Some(row) = rows.next()? {
-            Ok(Some(Skill {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                description: row.get(2)?,
-                icon_emoji: row.get(3)?,
-                skill_type: string_to_skill_type(&row.get::<_, String>(4)?),
-                definition: serde_json::from_str(&row.get::<_, String>(5)?).unwrap_or_else(|_| {
-                    SkillDefinition::Prompt {
-                        template: String::new(),
-                    }
-                }),
-                enabled: row.get(6)?,
-                created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(7)?)
-                    .map(|dt| dt.with_timezone(&Utc))
-                    .unwrap_or_else(|_| Utc::now()),
-                updated_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(8)?)
-                    .map(|dt| dt.with_timezone(&Utc))
-                    .unwrap_or_else(|_| Utc::now()),
-            }))
+            Ok(Some(row_to_skill(row)?))
         } else {
             Ok(None)
         }
@@ -287,7 +523,8 @@ impl Database {
         let conn = self.conn.lock().unwrap();
         conn.execute(
             "UPDATE skills SET name = ?2, description = ?3, icon_emoji = ?4,
-             skill_type = ?5, definition_json = ?6, enabled = ?7, updated_at = ?8 WHERE id = ?1",
+             skill_type = ?5, definition_json = ?6, enabled = ?7, updated_at = ?8,
+             aliases_json = ?9 WHERE id = ?1",
             params![
                 skill.id,
                 skill.name,
@@ -297,14 +534,72 @@ impl Database {
                 serde_json::to_string(&skill.definition).unwrap(),
                 skill.enabled,
                 skill.updated_at.to_rfc3339(),
+                serde_json::to_string(&skill.aliases).unwrap(),
             ],
         )?;
         Ok(())
     }
 
+    /// Save the enabled flag of every skill in one transaction
+    pub fn bulk_update_skills(&self, skills: &[Skill]) -> SqliteResult<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        for skill in skills {
+            tx.execute(
+                "UPDATE skills SET enabled = ?2, updated_at = ?3 WHERE id = ?1",
+                params![skill.id, skill.enabled, skill.updated_at.to_rfc3339()],
+            )?;
+        }
+        tx.commit()
+    }
+
+    pub fn record_skill_usage_at(&self, id: &str, used_at: &DateTime<Utc>) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        record_usage(&conn, "skills", id, used_at)
+    }
+
     pub fn delete_skill(&self, id: &str) -> SqliteResult<()> {
         let conn = self.conn.lock().unwrap();
         conn.execute("DELETE FROM skills WHERE id = ?1", params![id])?;
+        conn.execute(
+            "DELETE FROM tool_approvals WHERE skill_id = ?1",
+            params![id],
+        )?;
+        remove_agent_references(&conn, "skills_json", id)?;
+        conn.execute("DELETE FROM translations WHERE entity_id = ?1", params![id])?;
+        conn.execute(
+            "DELETE FROM collection_items WHERE entity_id = ?1",
+            params![id],
+        )?;
+        Ok(())
+    }
+
+    /// Handler digests the user approved, by skill id
+    pub fn get_tool_approvals(&self) -> SqliteResult<HashMap<String, String>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT skill_id, handler_sha256 FROM tool_approvals")?;
+        let approvals = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect();
+        approvals
+    }
+
+    pub fn approve_tool_handler(&self, skill_id: &str, handler_sha256: &str) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO tool_approvals (skill_id, handler_sha256, approved_at)
+             VALUES (?1, ?2, ?3)",
+            params![skill_id, handler_sha256, Utc::now().to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    pub fn revoke_tool_approval(&self, skill_id: &str) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "DELETE FROM tool_approvals WHERE skill_id = ?1",
+            params![skill_id],
+        )?;
         Ok(())
     }
 
@@ -316,8 +611,11 @@ impl Database {
         let conn = self.conn.lock().unwrap();
         conn.execute(
             "INSERT INTO instructions (id, name, description, icon_emoji, category,
-             content, priority, tags_json, enabled, created_at, updated_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+             content, priority, tags_json, enabled, created_at, updated_at, pin_to_context,
+             subcategory, branch_pattern, file_pattern, usage_count, last_used_at, review_due_at,
+             contexts_json, upstream_url, upstream_synced_at, upstream_hash, activation_json)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17,
+             ?18, ?19, ?20, ?21, ?22, ?23)",
             params![
                 instruction.id,
                 instruction.name,
@@ -330,6 +628,18 @@ impl Database {
                 instruction.enabled,
                 instruction.created_at.to_rfc3339(),
                 instruction.updated_at.to_rfc3339(),
+                instruction.pin_to_context,
+                instruction.subcategory,
+                instruction.branch_pattern,
+                instruction.file_pattern,
+                instruction.usage_count,
+                instruction.last_used_at.map(|dt| dt.to_rfc3339()),
+                instruction.review_due_at.map(|dt| dt.to_rfc3339()),
+                serde_json::to_string(&instruction.contexts).unwrap(),
+                instruction.upstream_url,
+                instruction.upstream_synced_at.map(|dt| dt.to_rfc3339()),
+                instruction.upstream_hash,
+                activation_json(instruction),
             ],
         )?;
         Ok(())
@@ -339,60 +649,77 @@ impl Database {
         let conn = self.conn.lock().unwrap();
         let mut stmt = conn.prepare(
             "SELECT id, name, description, icon_emoji, category, content, priority,
-             tags_json, enabled, created_at, updated_at FROM instructions",
+             tags_json, enabled, created_at, updated_at, pin_to_context, subcategory,
+             branch_pattern, file_pattern, usage_count, last_used_at, review_due_at,
+             contexts_json, upstream_url, upstream_synced_at, upstream_hash,
+             activation_json
+             FROM instructions",
         )?;
 
         let instructions = stmt
-            .query_map([], |row| {
-                Ok(Instruction {
-                    id: row.get(0)?,
-                    name: row.get(1)?,
-                    description: row.get(2)?,
-                    icon_emoji: row.get(3)?,
-                    category: string_to_category(&row.get::<_, String>(4)?),
-                    content: row.get(5)?,
-                    priority: row.get(6)?,
-                    tags: serde_json::from_str(&row.get::<_, String>(7)?).unwrap_or_default(),
-                    enabled: row.get(8)?,
-                    created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(9)?)
-                        .map(|dt| dt.with_timezone(&Utc))
-                        .unwrap_or_else(|_| Utc::now()),
-                    updated_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(10)?)
-                        .map(|dt| dt.with_timezone(&Utc))
-                        .unwrap_or_else(|_| Utc::now()),
-                })
-            })?
+            .query_map([], row_to_instruction)?
             .collect::<Result<Vec<_>, _>>()?;
 
         Ok(instructions)
     }
 
+    /// One page of the instructions matching `filter`
+    pub fn list_instructions(&self, filter: &Filter) -> SqliteResult<Page<Instruction>> {
+        self.list_page(
+            "SELECT id, name, description, icon_emoji, category, content, priority,
+             tags_json, enabled, created_at, updated_at, pin_to_context, subcategory,
+             branch_pattern, file_pattern, usage_count, last_used_at, review_due_at,
+             contexts_json, upstream_url, upstream_synced_at, upstream_hash,
+             activation_json
+             FROM instructions",
+            filter,
+            row_to_instruction,
+        )
+    }
+
+    /// Count the rows of `select` matching `filter`, then read its page
+    fn list_page<T>(
+        &self,
+        select: &str,
+        filter: &Filter,
+        row_to_item: fn(&rusqlite::Row) -> SqliteResult<T>,
+    ) -> SqliteResult<Page<T>> {
+        let conn = self.conn.lock().unwrap();
+        let params = params_from_iter(&filter.params);
+        let total: i64 = conn.query_row(
+            &format!("SELECT COUNT(*) FROM ({} {})", select, filter.where_clause),
+            params.clone(),
+            |row| row.get(0),
+        )?;
+        let mut stmt = conn.prepare(&format!(
+            "{} {} {} LIMIT {} OFFSET {}",
+            select, filter.where_clause, filter.order_by, filter.limit, filter.offset
+        ))?;
+        let items = stmt
+            .query_map(params, row_to_item)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Page {
+            items,
+            total: total as usize,
+            offset: filter.offset,
+            limit: filter.limit,
+        })
+    }
+
     pub fn get_instruction(&self, id: &str) -> SqliteResult<Option<Instruction>> {
         let conn = self.conn.lock().unwrap();
         let mut stmt = conn.prepare(
             "SELECT id, name, description, icon_emoji, category, content, priority,
-             tags_json, enabled, created_at, updated_at FROM instructions WHERE id = ?1",
+             tags_json, enabled, created_at, updated_at, pin_to_context, subcategory,
+             branch_pattern, file_pattern, usage_count, last_used_at, review_due_at,
+             contexts_json, upstream_url, upstream_synced_at, upstream_hash,
+             activation_json
+             FROM instructions WHERE id = ?1",
         )?;
 
         let mut rows = stmt.query(params![id])?;
         if let Some(row) = rows.next()? {
-            Ok(Some(Instruction {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                description: row.get(2)?,
-                icon_emoji: row.get(3)?,
-                category: string_to_category(&row.get::<_, String>(4)?),
-                content: row.get(5)?,
-                priority: row.get(6)?,
-                tags: serde_json::from_str(&row.get::<_, String>(7)?).unwrap_or_default(),
-                enabled: row.get(8)?,
-                created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(9)?)
-                    .map(|dt| dt.with_timezone(&Utc))
-                    .unwrap_or_else(|_| Utc::now()),
-                updated_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(10)?)
-                    .map(|dt| dt.with_timezone(&Utc))
-                    .unwrap_or_else(|_| Utc::now()),
-            }))
+            Ok(Some(row_to_instruction(row)?))
         } else {
             Ok(None)
         }
@@ -403,7 +730,10 @@ impl Database {
         conn.execute(
             "UPDATE instructions SET name = ?2, description = ?3, icon_emoji = ?4,
              category = ?5, content = ?6, priority = ?7, tags_json = ?8, enabled = ?9,
-             updated_at = ?10 WHERE id = ?1",
+             updated_at = ?10, pin_to_context = ?11, subcategory = ?12,
+             branch_pattern = ?13, file_pattern = ?14, review_due_at = ?15,
+             contexts_json = ?16, upstream_url = ?17, upstream_synced_at = ?18,
+             upstream_hash = ?19, activation_json = ?20 WHERE id = ?1",
             params![
                 instruction.id,
                 instruction.name,
@@ -415,17 +745,117 @@ impl Database {
                 serde_json::to_string(&instruction.tags).unwrap(),
                 instruction.enabled,
                 instruction.updated_at.to_rfc3339(),
+                instruction.pin_to_context,
+                instruction.subcategory,
+                instruction.branch_pattern,
+                instruction.file_pattern,
+                instruction.review_due_at.map(|dt| dt.to_rfc3339()),
+                serde_json::to_string(&instruction.contexts).unwrap(),
+                instruction.upstream_url,
+                instruction.upstream_synced_at.map(|dt| dt.to_rfc3339()),
+                instruction.upstream_hash,
+                activation_json(instruction),
             ],
         )?;
         Ok(())
     }
 
+    /// Save the enabled flag, tags and category of every instruction in one
+    /// transaction
+    pub fn bulk_update_instructions(&self, instructions: &[Instruction]) -> SqliteResult<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        for instruction in instructions {
+            tx.execute(
+                "UPDATE instructions SET enabled = ?2, tags_json = ?3, category = ?4,
+                 subcategory = ?5, updated_at = ?6 WHERE id = ?1",
+                params![
+                    instruction.id,
+                    instruction.enabled,
+                    serde_json::to_string(&instruction.tags).unwrap(),
+                    category_to_string(&instruction.category),
+                    instruction.subcategory,
+                    instruction.updated_at.to_rfc3339(),
+                ],
+            )?;
+        }
+        tx.commit()
+    }
+
+    pub fn record_instruction_usage_at(
+        &self,
+        id: &str,
+        used_at: &DateTime<Utc>,
+    ) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        record_usage(&conn, "instructions", id, used_at)
+    }
+
     pub fn delete_instruction(&self, id: &str) -> SqliteResult<()> {
         let conn = self.conn.lock().unwrap();
         conn.execute("DELETE FROM instructions WHERE id = ?1", params![id])?;
+        remove_agent_references(&conn, "instructions_json", id)?;
+        conn.execute("DELETE FROM translations WHERE entity_id = ?1", params![id])?;
+        conn.execute(
+            "DELETE FROM collection_items WHERE entity_id = ?1",
+            params![id],
+        )?;
+        conn.execute(
+            "DELETE FROM experiments WHERE instruction_id = ?1",
+            params![id],
+        )?;
+        Ok(())
+    }
+
+    // ========================================================================
+    // Experiment Operations
+    // ========================================================================
+
+    /// Start an experiment, or replace the variant of a running one. A
+    /// replaced variant restarts the experiment.
+    pub fn save_experiment(&self, experiment: &Experiment) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO experiments (instruction_id, variant_b, started_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT(instruction_id) DO UPDATE SET variant_b = ?2, started_at = ?3",
+            params![
+                experiment.instruction_id,
+                experiment.variant_b,
+                experiment.started_at.to_rfc3339(),
+            ],
+        )?;
         Ok(())
     }
 
+    pub fn get_experiments(&self) -> SqliteResult<Vec<Experiment>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT instruction_id, variant_b, started_at FROM experiments ORDER BY started_at",
+        )?;
+        let experiments = stmt
+            .query_map([], |row| {
+                Ok(Experiment {
+                    instruction_id: row.get(0)?,
+                    variant_b: row.get(1)?,
+                    started_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(2)?)
+                        .map(|dt| dt.with_timezone(&Utc))
+                        .unwrap_or_else(|_| Utc::now()),
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(experiments)
+    }
+
+    /// Stop an experiment; returns whether one was running
+    pub fn delete_experiment(&self, instruction_id: &str) -> SqliteResult<bool> {
+        let conn = self.conn.lock().unwrap();
+        let removed = conn.execute(
+            "DELETE FROM experiments WHERE instruction_id = ?1",
+            params![instruction_id],
+        )?;
+        Ok(removed > 0)
+    }
+
     // ========================================================================
     // Settings Operations
     // ========================================================================
@@ -434,7 +864,11 @@ impl Database {
         let conn = self.conn.lock().unwrap();
         let mut stmt = conn.prepare(
             "SELECT theme_mode, theme_accent_color, theme_emotional_ui, mcp_server_port,
-             auto_start_mcp, data_directory FROM settings WHERE id = 1",
+             auto_start_mcp, data_directory, notifications_json, insights_enabled,
+             redaction_profiles_json, release_channels_json, block_sync_on_errors,
+             strict_imports, llm_providers_json, backup_schedule_json, setup_completed,
+             retention_json, library_folder, mcp_write_tools, active_profile, mcp_run_tools
+             FROM settings WHERE id = 1",
         )?;
 
         let mut rows = stmt.query([])?;
@@ -449,6 +883,38 @@ impl Database {
                 mcp_server_enabled: false, // Runtime state, not persisted
                 auto_start_mcp: row.get(4)?,
                 data_directory: row.get(5)?,
+                notifications: row
+                    .get::<_, Option<String>>(6)?
+                    .and_then(|json| serde_json::from_str(&json).ok())
+                    .unwrap_or_default(),
+                insights_enabled: row.get::<_, Option<bool>>(7)?.unwrap_or(false),
+                redaction_profiles: row
+                    .get::<_, Option<String>>(8)?
+                    .and_then(|json| serde_json::from_str(&json).ok())
+                    .unwrap_or_default(),
+                release_channels: row
+                    .get::<_, Option<String>>(9)?
+                    .and_then(|json| serde_json::from_str(&json).ok())
+                    .unwrap_or_default(),
+                block_sync_on_errors: row.get::<_, Option<bool>>(10)?.unwrap_or(false),
+                strict_imports: row.get::<_, Option<bool>>(11)?.unwrap_or(false),
+                llm_providers: row
+                    .get::<_, Option<String>>(12)?
+                    .and_then(|json| serde_json::from_str(&json).ok())
+                    .unwrap_or_default(),
+                backup_schedule: row
+                    .get::<_, Option<String>>(13)?
+                    .and_then(|json| serde_json::from_str(&json).ok())
+                    .unwrap_or_default(),
+                setup_completed: row.get::<_, Option<bool>>(14)?.unwrap_or(false),
+                retention: row
+                    .get::<_, Option<String>>(15)?
+                    .and_then(|json| serde_json::from_str(&json).ok())
+                    .unwrap_or_default(),
+                library_folder: row.get(16)?,
+                mcp_write_tools: row.get::<_, Option<bool>>(17)?.unwrap_or(false),
+                active_profile: row.get(18)?,
+                mcp_run_tools: row.get::<_, Option<bool>>(19)?.unwrap_or(false),
             })
         } else {
             Ok(Settings::default())
@@ -460,7 +926,13 @@ impl Database {
         conn.execute(
             "UPDATE settings SET theme_mode = ?1, theme_accent_color = ?2,
              theme_emotional_ui = ?3, mcp_server_port = ?4, auto_start_mcp = ?5,
-             data_directory = ?6 WHERE id = 1",
+             data_directory = ?6, notifications_json = ?7, insights_enabled = ?8,
+             redaction_profiles_json = ?9, release_channels_json = ?10,
+             block_sync_on_errors = ?11, strict_imports = ?12, llm_providers_json = ?13,
+             backup_schedule_json = ?14, setup_completed = ?15, retention_json = ?16,
+             library_folder = ?17, mcp_write_tools = ?18, active_profile = ?19,
+             mcp_run_tools = ?20
+             WHERE id = 1",
             params![
                 settings.theme.mode,
                 settings.theme.accent_color,
@@ -468,43 +940,823 @@ impl Database {
                 settings.mcp_server_port,
                 settings.auto_start_mcp,
                 settings.data_directory,
+                serde_json::to_string(&settings.notifications).unwrap(),
+                settings.insights_enabled,
+                serde_json::to_string(&settings.redaction_profiles).unwrap(),
+                serde_json::to_string(&settings.release_channels).unwrap(),
+                settings.block_sync_on_errors,
+                settings.strict_imports,
+                serde_json::to_string(&settings.llm_providers).unwrap(),
+                serde_json::to_string(&settings.backup_schedule).unwrap(),
+                settings.setup_completed,
+                serde_json::to_string(&settings.retention).unwrap(),
+                settings.library_folder,
+                settings.mcp_write_tools,
+                settings.active_profile,
+                settings.mcp_run_tools,
             ],
         )?;
         Ok(())
     }
 
     // ========================================================================
-    // Export/Import Operations
+    // Attachment Operations
     // ========================================================================
 
-    pub fn export_all(&self) -> SqliteResult<ExportData> {
-        Ok(ExportData {
-            agents: self.get_all_agents()?,
-            skills: self.get_all_skills()?,
-            instructions: self.get_all_instructions()?,
-            settings: self.get_settings()?,
-            exported_at: Utc::now(),
-            version: "1.0".to_string(),
-        })
+    pub fn insert_attachment(&self, attachment: &Attachment) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO attachments (id, mime_type, data, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                attachment.id,
+                attachment.mime_type,
+                attachment.data,
+                attachment.created_at.to_rfc3339(),
+            ],
+        )?;
+        Ok(())
     }
 
-    pub fn import_all(&self, data: &ExportData) -> SqliteResult<()> {
+    pub fn get_attachment(&self, id: &str) -> SqliteResult<Option<Attachment>> {
         let conn = self.conn.lock().unwrap();
+        let mut stmt =
+            conn.prepare("SELECT id, mime_type, data, created_at FROM attachments WHERE id = ?1")?;
 
-        // Clear existing data
-        conn.execute("DELETE FROM agents", [])?;
-        conn.execute("DELETE FROM skills", [])?;
-        conn.execute("DELETE FROM instructions", [])?;
+        let mut rows = stmt.query(params![id])?;
+        if let Some(row) = rows.next()? {
+            Ok(Some(row_to_attachment(row)?))
+        } else {
+            Ok(None)
+        }
+    }
 
-        drop(conn); // Release lock before calling other methods
+    pub fn get_all_attachments(&self) -> SqliteResult<Vec<Attachment>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT id, mime_type, data, created_at FROM attachments")?;
 
-        // Import agents
-        for agent in &data.agents {
-            self.insert_agent(agent)?;
-        }
+        let attachments = stmt
+            .query_map([], row_to_attachment)?
+            .collect::<Result<Vec<_>, _>>()?;
 
-        // Import skills
-        for skill in &data.skills {
+        Ok(attachments)
+    }
+
+    pub fn delete_attachment(&self, id: &str) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM attachments WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    /// Point an agent at a new avatar image (or none), dropping the image it replaces
+    pub fn set_agent_avatar_image(&self, agent_id: &str, attachment_id: Option<&str>) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        let previous: Option<String> = conn.query_row(
+            "SELECT avatar_image_id FROM agents WHERE id = ?1",
+            params![agent_id],
+            |row| row.get(0),
+        )?;
+
+        conn.execute(
+            "UPDATE agents SET avatar_image_id = ?2, updated_at = ?3 WHERE id = ?1",
+            params![agent_id, attachment_id, Utc::now().to_rfc3339()],
+        )?;
+
+        if let Some(previous) = previous {
+            if Some(previous.as_str()) != attachment_id {
+                conn.execute("DELETE FROM attachments WHERE id = ?1", params![previous])?;
+            }
+        }
+        Ok(())
+    }
+
+    // ========================================================================
+    // Usage Event Operations
+    // ========================================================================
+
+    pub fn append_usage_event(
+        &self,
+        kind: &str,
+        entity_id: &str,
+        payload: &str,
+        created_at: &DateTime<Utc>,
+    ) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO usage_events (kind, entity_id, payload, created_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![kind, entity_id, payload, created_at.to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    /// Oldest pending events first
+    pub fn get_usage_events(&self, limit: usize) -> SqliteResult<Vec<UsageEvent>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, kind, entity_id, payload, created_at FROM usage_events
+             ORDER BY id LIMIT ?1",
+        )?;
+
+        let events = stmt
+            .query_map(params![limit as i64], |row| {
+                Ok(UsageEvent {
+                    id: row.get(0)?,
+                    kind: row.get(1)?,
+                    entity_id: row.get(2)?,
+                    payload: row.get(3)?,
+                    created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(4)?)
+                        .map(|dt| dt.with_timezone(&Utc))
+                        .unwrap_or_else(|_| Utc::now()),
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(events)
+    }
+
+    pub fn delete_usage_events_through(&self, last_id: i64) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM usage_events WHERE id <= ?1", params![last_id])?;
+        Ok(())
+    }
+
+    // ========================================================================
+    // Release Operations
+    // ========================================================================
+
+    pub fn insert_release(&self, release: &Release) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        let snapshot = serde_json::json!({
+            "agents": release.agents,
+            "skills": release.skills,
+            "instructions": release.instructions,
+        });
+        conn.execute(
+            "INSERT INTO releases (tag, notes, created_at, agent_count, skill_count,
+             instruction_count, snapshot_json)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                release.tag,
+                release.notes,
+                release.created_at.to_rfc3339(),
+                release.agents.len() as i64,
+                release.skills.len() as i64,
+                release.instructions.len() as i64,
+                snapshot.to_string(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Get a release with its full library snapshot
+    pub fn get_release(&self, tag: &str) -> SqliteResult<Option<Release>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn
+            .prepare("SELECT tag, notes, created_at, snapshot_json FROM releases WHERE tag = ?1")?;
+
+        let mut rows = stmt.query(params![tag])?;
+        let Some(row) = rows.next()? else {
+            return Ok(None);
+        };
+
+        let snapshot: ReleaseSnapshot =
+            serde_json::from_str(&row.get::<_, String>(3)?).map_err(|e| {
+                rusqlite::Error::FromSqlConversionFailure(
+                    3,
+                    rusqlite::types::Type::Text,
+                    Box::new(e),
+                )
+            })?;
+        Ok(Some(Release {
+            tag: row.get(0)?,
+            notes: row.get(1)?,
+            created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(2)?)
+                .map(|dt| dt.with_timezone(&Utc))
+                .unwrap_or_else(|_| Utc::now()),
+            agents: snapshot.agents,
+            skills: snapshot.skills,
+            instructions: snapshot.instructions,
+        }))
+    }
+
+    /// Newest releases first, without their snapshots
+    pub fn get_releases(&self) -> SqliteResult<Vec<ReleaseSummary>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT tag, notes, created_at, agent_count, skill_count, instruction_count
+             FROM releases ORDER BY created_at DESC",
+        )?;
+
+        let releases = stmt
+            .query_map([], |row| {
+                Ok(ReleaseSummary {
+                    tag: row.get(0)?,
+                    notes: row.get(1)?,
+                    created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(2)?)
+                        .map(|dt| dt.with_timezone(&Utc))
+                        .unwrap_or_else(|_| Utc::now()),
+                    agent_count: row.get::<_, i64>(3)? as usize,
+                    skill_count: row.get::<_, i64>(4)? as usize,
+                    instruction_count: row.get::<_, i64>(5)? as usize,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(releases)
+    }
+
+    pub fn delete_release(&self, tag: &str) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM releases WHERE tag = ?1", params![tag])?;
+        Ok(())
+    }
+
+    // ========================================================================
+    // Translation Operations
+    // ========================================================================
+
+    /// Insert a translation, replacing any existing one for the same entity and locale
+    pub fn upsert_translation(&self, translation: &Translation) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO translations (id, entity_type, entity_id, locale, name, description,
+             content, needs_review, provider, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+             ON CONFLICT(entity_id, locale) DO UPDATE SET
+             name = excluded.name, description = excluded.description,
+             content = excluded.content, needs_review = excluded.needs_review,
+             provider = excluded.provider, updated_at = excluded.updated_at",
+            params![
+                translation.id,
+                translation.entity_type,
+                translation.entity_id,
+                translation.locale,
+                translation.name,
+                translation.description,
+                translation.content,
+                translation.needs_review,
+                translation.provider,
+                translation.created_at.to_rfc3339(),
+                translation.updated_at.to_rfc3339(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_translation(&self, id: &str) -> SqliteResult<Option<Translation>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!("{} WHERE id = ?1", TRANSLATION_SELECT))?;
+        let mut rows = stmt.query(params![id])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(row_to_translation(row)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Translations of one entity, or of every entity when `entity_id` is `None`
+    pub fn get_translations(&self, entity_id: Option<&str>) -> SqliteResult<Vec<Translation>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "{} WHERE ?1 IS NULL OR entity_id = ?1 ORDER BY entity_id, locale",
+            TRANSLATION_SELECT
+        ))?;
+        let translations = stmt
+            .query_map(params![entity_id], row_to_translation)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(translations)
+    }
+
+    /// Reviewed translations in any of `locales`
+    pub fn get_active_translations(&self, locales: &[&str]) -> SqliteResult<Vec<Translation>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "{} WHERE needs_review = 0 AND locale IN (SELECT value FROM json_each(?1))",
+            TRANSLATION_SELECT
+        ))?;
+        let locales = serde_json::to_string(locales).unwrap();
+        let translations = stmt
+            .query_map(params![locales], row_to_translation)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(translations)
+    }
+
+    pub fn delete_translation(&self, id: &str) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM translations WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    // ========================================================================
+    // Snippet Operations
+    // ========================================================================
+
+    pub fn insert_snippet(&self, snippet: &Snippet) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO snippets (id, name, description, content, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                snippet.id,
+                snippet.name,
+                snippet.description,
+                snippet.content,
+                snippet.created_at.to_rfc3339(),
+                snippet.updated_at.to_rfc3339(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn update_snippet(&self, snippet: &Snippet) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE snippets SET name = ?2, description = ?3, content = ?4, updated_at = ?5
+             WHERE id = ?1",
+            params![
+                snippet.id,
+                snippet.name,
+                snippet.description,
+                snippet.content,
+                snippet.updated_at.to_rfc3339(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Every snippet, ordered by name
+    pub fn get_all_snippets(&self) -> SqliteResult<Vec<Snippet>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, name, description, content, created_at, updated_at FROM snippets
+             ORDER BY name COLLATE NOCASE",
+        )?;
+        let snippets = stmt
+            .query_map([], |row| {
+                Ok(Snippet {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    description: row.get(2)?,
+                    content: row.get(3)?,
+                    created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(4)?)
+                        .map(|dt| dt.with_timezone(&Utc))
+                        .unwrap_or_else(|_| Utc::now()),
+                    updated_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(5)?)
+                        .map(|dt| dt.with_timezone(&Utc))
+                        .unwrap_or_else(|_| Utc::now()),
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(snippets)
+    }
+
+    pub fn delete_snippet(&self, id: &str) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM snippets WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    // ========================================================================
+    // Collection Operations
+    // ========================================================================
+
+    /// Insert a collection along with its items
+    pub fn insert_collection(&self, collection: &Collection) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO collections (id, name, parent_id, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                collection.id,
+                collection.name,
+                collection.parent_id,
+                collection.created_at.to_rfc3339(),
+                collection.updated_at.to_rfc3339(),
+            ],
+        )?;
+        for item in &collection.items {
+            conn.execute(
+                "INSERT OR IGNORE INTO collection_items (collection_id, entity_type, entity_id)
+                 VALUES (?1, ?2, ?3)",
+                params![collection.id, item.entity_type, item.entity_id],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Save a collection's name and parent; items are changed separately
+    pub fn update_collection(&self, collection: &Collection) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE collections SET name = ?2, parent_id = ?3, updated_at = ?4 WHERE id = ?1",
+            params![
+                collection.id,
+                collection.name,
+                collection.parent_id,
+                collection.updated_at.to_rfc3339(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Every collection with its items, ordered by name
+    pub fn get_all_collections(&self) -> SqliteResult<Vec<Collection>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, name, parent_id, created_at, updated_at FROM collections
+             ORDER BY name COLLATE NOCASE",
+        )?;
+        let mut collections = stmt
+            .query_map([], |row| {
+                Ok(Collection {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    parent_id: row.get(2)?,
+                    items: Vec::new(),
+                    created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(3)?)
+                        .map(|dt| dt.with_timezone(&Utc))
+                        .unwrap_or_else(|_| Utc::now()),
+                    updated_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(4)?)
+                        .map(|dt| dt.with_timezone(&Utc))
+                        .unwrap_or_else(|_| Utc::now()),
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut stmt = conn.prepare(
+            "SELECT collection_id, entity_type, entity_id FROM collection_items
+             ORDER BY rowid",
+        )?;
+        let items = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    CollectionItem {
+                        entity_type: row.get(1)?,
+                        entity_id: row.get(2)?,
+                    },
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        for (collection_id, item) in items {
+            if let Some(collection) = collections.iter_mut().find(|c| c.id == collection_id) {
+                collection.items.push(item);
+            }
+        }
+
+        Ok(collections)
+    }
+
+    /// Delete a collection. Its subcollections move up to its parent and
+    /// the entities in it stay in the library.
+    pub fn delete_collection(&self, id: &str) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE collections SET parent_id = (SELECT parent_id FROM collections WHERE id = ?1)
+             WHERE parent_id = ?1",
+            params![id],
+        )?;
+        conn.execute(
+            "DELETE FROM collection_items WHERE collection_id = ?1",
+            params![id],
+        )?;
+        conn.execute("DELETE FROM collections WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    pub fn add_collection_item(
+        &self,
+        collection_id: &str,
+        item: &CollectionItem,
+    ) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR IGNORE INTO collection_items (collection_id, entity_type, entity_id)
+             VALUES (?1, ?2, ?3)",
+            params![collection_id, item.entity_type, item.entity_id],
+        )?;
+        Ok(())
+    }
+
+    pub fn remove_collection_item(&self, collection_id: &str, entity_id: &str) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "DELETE FROM collection_items WHERE collection_id = ?1 AND entity_id = ?2",
+            params![collection_id, entity_id],
+        )?;
+        Ok(())
+    }
+
+    // ========================================================================
+    // Journal Operations
+    // ========================================================================
+
+    pub fn insert_journal_entry(&self, entry: &JournalEntry, payload: &str) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO operation_journal (id, kind, summary, status, payload_json,
+             started_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                entry.id,
+                entry.kind,
+                entry.summary,
+                entry.status,
+                payload,
+                entry.started_at.to_rfc3339(),
+                entry.updated_at.to_rfc3339(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Oldest entries first, without their payloads
+    pub fn get_journal_entries(&self) -> SqliteResult<Vec<JournalEntry>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, kind, summary, status, started_at, updated_at FROM operation_journal
+             ORDER BY started_at",
+        )?;
+
+        let entries = stmt
+            .query_map([], |row| {
+                Ok(JournalEntry {
+                    id: row.get(0)?,
+                    kind: row.get(1)?,
+                    summary: row.get(2)?,
+                    status: row.get(3)?,
+                    started_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(4)?)
+                        .map(|dt| dt.with_timezone(&Utc))
+                        .unwrap_or_else(|_| Utc::now()),
+                    updated_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(5)?)
+                        .map(|dt| dt.with_timezone(&Utc))
+                        .unwrap_or_else(|_| Utc::now()),
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(entries)
+    }
+
+    pub fn get_journal_payload(&self, id: &str) -> SqliteResult<Option<String>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT payload_json FROM operation_journal WHERE id = ?1")?;
+
+        let mut rows = stmt.query(params![id])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(row.get(0)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn set_journal_status(&self, id: &str, status: &str) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE operation_journal SET status = ?2, updated_at = ?3 WHERE id = ?1",
+            params![id, status, Utc::now().to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    /// Mark every running entry as interrupted, returning how many there were
+    pub fn interrupt_running_journal_entries(&self) -> SqliteResult<usize> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE operation_journal SET status = 'interrupted', updated_at = ?1
+             WHERE status = 'running'",
+            params![Utc::now().to_rfc3339()],
+        )
+    }
+
+    pub fn delete_journal_entry(&self, id: &str) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM operation_journal WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    // ========================================================================
+    // Edit Lock Operations
+    // ========================================================================
+
+    /// Try to take (or renew) the advisory edit lock on an entity.
+    /// Returns the lock as it stands afterwards - if another owner still holds
+    /// an unexpired lock, their lock is returned unchanged.
+    pub fn acquire_edit_lock(
+        &self,
+        entity_id: &str,
+        owner_id: &str,
+        owner_name: &str,
+        ttl_secs: i64,
+    ) -> SqliteResult<EditLock> {
+        let conn = self.conn.lock().unwrap();
+        let now = Utc::now();
+        let expires_at = now + chrono::Duration::seconds(ttl_secs);
+
+        conn.execute(
+            "DELETE FROM edit_locks WHERE expires_at <= ?1",
+            params![now.to_rfc3339()],
+        )?;
+
+        // Only overwrite an existing lock if we already own it
+        conn.execute(
+            "INSERT INTO edit_locks (entity_id, owner_id, owner_name, acquired_at, expires_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT(entity_id) DO UPDATE SET expires_at = excluded.expires_at
+             WHERE edit_locks.owner_id = excluded.owner_id",
+            params![
+                entity_id,
+                owner_id,
+                owner_name,
+                now.to_rfc3339(),
+                expires_at.to_rfc3339(),
+            ],
+        )?;
+
+        conn.query_row(
+            "SELECT entity_id, owner_id, owner_name, acquired_at, expires_at
+             FROM edit_locks WHERE entity_id = ?1",
+            params![entity_id],
+            row_to_edit_lock,
+        )
+    }
+
+    /// Release a lock, but only if it is held by the given owner
+    pub fn release_edit_lock(&self, entity_id: &str, owner_id: &str) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "DELETE FROM edit_locks WHERE entity_id = ?1 AND owner_id = ?2",
+            params![entity_id, owner_id],
+        )?;
+        Ok(())
+    }
+
+    /// Get the unexpired lock on an entity, if any
+    pub fn get_edit_lock(&self, entity_id: &str) -> SqliteResult<Option<EditLock>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT entity_id, owner_id, owner_name, acquired_at, expires_at
+             FROM edit_locks WHERE entity_id = ?1 AND expires_at > ?2",
+        )?;
+
+        let mut rows = stmt.query(params![entity_id, Utc::now().to_rfc3339()])?;
+        if let Some(row) = rows.next()? {
+            Ok(Some(row_to_edit_lock(row)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Get all unexpired locks so clients can show who is editing what
+    pub fn get_active_edit_locks(&self) -> SqliteResult<Vec<EditLock>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT entity_id, owner_id, owner_name, acquired_at, expires_at
+             FROM edit_locks WHERE expires_at > ?1 ORDER BY acquired_at",
+        )?;
+
+        let locks = stmt
+            .query_map(params![Utc::now().to_rfc3339()], row_to_edit_lock)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(locks)
+    }
+
+    // ========================================================================
+    // Insight Operations
+    // ========================================================================
+
+    pub fn increment_insight_counter(&self, day: &str, metric: &str, key: &str) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO insight_counters (day, metric, key, count) VALUES (?1, ?2, ?3, 1)
+             ON CONFLICT(day, metric, key) DO UPDATE SET count = count + 1",
+            params![day, metric, key],
+        )?;
+        Ok(())
+    }
+
+    /// Get (day, metric, key, count) rows from `since_day` (YYYY-MM-DD) onwards
+    pub fn get_insight_counters(&self, since_day: &str) -> SqliteResult<Vec<(String, String, String, i64)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT day, metric, key, count FROM insight_counters WHERE day >= ?1 ORDER BY day",
+        )?;
+
+        let counters = stmt
+            .query_map(params![since_day], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(counters)
+    }
+
+    /// Delete insight counters and usage buckets from before `day`
+    /// (YYYY-MM-DD), or all of them. Returns the number of rows removed.
+    pub fn delete_analytics_before(&self, day: Option<&str>) -> SqliteResult<usize> {
+        let conn = self.conn.lock().unwrap();
+        let removed = match day {
+            Some(day) => {
+                conn.execute("DELETE FROM insight_counters WHERE day < ?1", params![day])?
+                    + conn.execute("DELETE FROM usage_hours WHERE hour < ?1", params![day])?
+            }
+            None => {
+                conn.execute("DELETE FROM insight_counters", [])?
+                    + conn.execute("DELETE FROM usage_hours", [])?
+            }
+        };
+        Ok(removed)
+    }
+
+    pub fn increment_usage_hour(&self, entity_id: &str, hour: &str) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO usage_hours (entity_id, hour, count) VALUES (?1, ?2, 1)
+             ON CONFLICT(entity_id, hour) DO UPDATE SET count = count + 1",
+            params![entity_id, hour],
+        )?;
+        Ok(())
+    }
+
+    /// Get (hour, count) rows for `entity_id` from `since_hour` (YYYY-MM-DDTHH, UTC) onwards
+    pub fn get_usage_hours(
+        &self,
+        entity_id: &str,
+        since_hour: &str,
+    ) -> SqliteResult<Vec<(String, i64)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT hour, count FROM usage_hours WHERE entity_id = ?1 AND hour >= ?2 ORDER BY hour",
+        )?;
+
+        let hours = stmt
+            .query_map(params![entity_id, since_hour], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(hours)
+    }
+
+    /// Uses per entity from `since_hour` (YYYY-MM-DDTHH, UTC) onwards
+    pub fn get_usage_totals(&self, since_hour: &str) -> SqliteResult<Vec<(String, i64)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT entity_id, SUM(count) FROM usage_hours WHERE hour >= ?1 GROUP BY entity_id",
+        )?;
+
+        let totals = stmt
+            .query_map(params![since_hour], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(totals)
+    }
+
+    // ========================================================================
+    // Export/Import Operations
+    // ========================================================================
+
+    /// Everything in the library. Provider API keys are left out of the
+    /// settings, so exports and syncs never carry them.
+    pub fn export_all(&self) -> SqliteResult<ExportData> {
+        Ok(ExportData {
+            agents: self.get_all_agents()?,
+            skills: self.get_all_skills()?,
+            instructions: self.get_all_instructions()?,
+            attachments: self.get_all_attachments()?,
+            collections: self.get_all_collections()?,
+            snippets: self.get_all_snippets()?,
+            settings: self.get_settings()?.without_api_keys(),
+            exported_at: Utc::now(),
+            version: "1.0".to_string(),
+        })
+    }
+
+    pub fn import_all(&self, data: &ExportData) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+
+        // Clear existing data
+        conn.execute("DELETE FROM agents", [])?;
+        conn.execute("DELETE FROM skills", [])?;
+        conn.execute("DELETE FROM instructions", [])?;
+        conn.execute("DELETE FROM attachments", [])?;
+        conn.execute("DELETE FROM collections", [])?;
+        conn.execute("DELETE FROM collection_items", [])?;
+        conn.execute("DELETE FROM snippets", [])?;
+        // Imported handlers have to be approved again before MCP runs them
+        conn.execute("DELETE FROM tool_approvals", [])?;
+
+        drop(conn); // Release lock before calling other methods
+
+        // Import attachments first so agents can reference them
+        for attachment in &data.attachments {
+            self.insert_attachment(attachment)?;
+        }
+
+        // Import agents
+        for agent in &data.agents {
+            self.insert_agent(agent)?;
+        }
+
+        // Import skills
+        for skill in &data.skills {
             self.insert_skill(skill)?;
         }
 
@@ -513,8 +1765,20 @@ impl Database {
             self.insert_instruction(instruction)?;
         }
 
-        // Import settings
-        self.save_settings(&data.settings)?;
+        // Import collections
+        for collection in &data.collections {
+            self.insert_collection(collection)?;
+        }
+
+        // Import snippets
+        for snippet in &data.snippets {
+            self.insert_snippet(snippet)?;
+        }
+
+        // Import settings, keeping the providers and keys configured here
+        let local = self.get_settings()?;
+        let settings = data.settings.clone().keeping_providers(&local);
+        self.save_settings(&settings)?;
 
         Ok(())
     }
@@ -529,16 +1793,354 @@ pub struct ExportData {
     pub agents: Vec<Agent>,
     pub skills: Vec<Skill>,
     pub instructions: Vec<Instruction>,
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+    #[serde(default)]
+    pub collections: Vec<Collection>,
+    #[serde(default)]
+    pub snippets: Vec<Snippet>,
     pub settings: Settings,
     pub exported_at: DateTime<Utc>,
     pub version: String,
 }
 
+/// Library stored in a release's `snapshot_json`
+#[derive(serde::Deserialize)]
+struct ReleaseSnapshot {
+    agents: Vec<Agent>,
+    skills: Vec<Skill>,
+    instructions: Vec<Instruction>,
+}
+
 // ============================================================================
 // Helper Functions
 // ============================================================================
 
-fn skill_type_to_string(st: &SkillType) -> &'static str {
+fn open_connection(path: &Path, key: Option<&str>) -> SqliteResult<Connection> {
+    let conn = Connection::open(path)?;
+    if let Some(key) = key {
+        conn.pragma_update(None, "key", key)?;
+    }
+    // SQLCipher only reports a wrong key once the first page is read
+    conn.query_row("SELECT count(*) FROM sqlite_master", [], |_| Ok(()))?;
+    conn.execute_batch("PRAGMA foreign_keys = ON; PRAGMA journal_mode = WAL;")?;
+    Ok(conn)
+}
+
+/// Bytes of the database file a read-only connection maps into memory
+const READ_ONLY_MMAP_SIZE: i64 = 256 * 1024 * 1024;
+
+fn open_read_only_connection(path: &Path, key: Option<&str>) -> SqliteResult<Connection> {
+    // Not `immutable=1`: the app keeps writing to the file, and an immutable
+    // connection would skip locking and serve stale or torn pages
+    let conn = Connection::open_with_flags(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )?;
+    if let Some(key) = key {
+        conn.pragma_update(None, "key", key)?;
+    }
+    conn.query_row("SELECT count(*) FROM sqlite_master", [], |_| Ok(()))?;
+    // SQLCipher ignores mmap_size for encrypted files, whose pages must be decrypted
+    conn.pragma_update(None, "mmap_size", READ_ONLY_MMAP_SIZE)?;
+    conn.pragma_update(None, "query_only", true)?;
+    Ok(conn)
+}
+
+const TRANSLATION_SELECT: &str =
+    "SELECT id, entity_type, entity_id, locale, name, description, content, needs_review,
+     provider, created_at, updated_at FROM translations";
+
+fn row_to_translation(row: &rusqlite::Row) -> SqliteResult<Translation> {
+    Ok(Translation {
+        id: row.get(0)?,
+        entity_type: row.get(1)?,
+        entity_id: row.get(2)?,
+        locale: row.get(3)?,
+        name: row.get(4)?,
+        description: row.get(5)?,
+        content: row.get(6)?,
+        needs_review: row.get(7)?,
+        provider: row.get(8)?,
+        created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(9)?)
+            .map(|dt| dt.with_timezone(&Utc))
+            .unwrap_or_else(|_| Utc::now()),
+        updated_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(10)?)
+            .map(|dt| dt.with_timezone(&Utc))
+            .unwrap_or_else(|_| Utc::now()),
+    })
+}
+
+/// A column each migration creates, so an applied migration can be recognised
+const MIGRATION_MARKERS: &[(&str, &str, &str)] = &[
+    ("001_initial_schema", "agents", "id"),
+    ("002_add_usage_tracking", "agents", "usage_count"),
+    ("003_add_edit_locks", "edit_locks", "entity_id"),
+    ("004_add_attachments", "agents", "avatar_image_id"),
+    ("005_add_notification_settings", "settings", "notifications_json"),
+    ("006_add_insights", "settings", "insights_enabled"),
+    ("007_add_redaction_profiles", "settings", "redaction_profiles_json"),
+    ("008_add_pinned_instructions", "instructions", "pin_to_context"),
+    ("009_add_aliases", "agents", "aliases_json"),
+    ("010_add_usage_events", "usage_events", "kind"),
+    ("011_add_subcategories", "instructions", "subcategory"),
+    ("012_add_branch_patterns", "instructions", "branch_pattern"),
+    ("013_add_releases", "releases", "snapshot_json"),
+    ("014_add_release_channels", "settings", "release_channels_json"),
+    ("015_add_sync_checks", "settings", "block_sync_on_errors"),
+    ("016_add_strict_imports", "settings", "strict_imports"),
+    ("017_add_translations", "translations", "locale"),
+    ("018_add_first_run_setup", "settings", "setup_completed"),
+    ("019_add_collections", "collection_items", "entity_id"),
+    ("020_add_operation_journal", "operation_journal", "kind"),
+    ("021_add_usage_heatmap", "usage_hours", "hour"),
+    ("022_add_file_patterns", "instructions", "file_pattern"),
+    ("023_add_retention", "settings", "retention_json"),
+    ("024_add_experiments", "experiments", "variant_b"),
+    (
+        "025_add_skill_instruction_usage",
+        "instructions",
+        "usage_count",
+    ),
+    ("026_add_review_dates", "instructions", "review_due_at"),
+    ("027_add_library_folder", "settings", "library_folder"),
+    ("028_add_instruction_order", "agents", "instruction_order"),
+    (
+        "029_add_global_instruction_opt_out",
+        "agents",
+        "include_global_instructions",
+    ),
+    (
+        "030_add_instruction_contexts",
+        "instructions",
+        "contexts_json",
+    ),
+    (
+        "031_add_instruction_upstream",
+        "instructions",
+        "upstream_hash",
+    ),
+    ("032_add_mcp_write_tools", "settings", "mcp_write_tools"),
+    (
+        "033_add_instruction_activation",
+        "settings",
+        "active_profile",
+    ),
+    ("034_add_agent_parent", "agents", "parent_agent_id"),
+    ("035_add_snippets", "snippets", "content"),
+    ("036_add_tool_approvals", "settings", "mcp_run_tools"),
+];
+
+/// Count one use of row `id` in `table`, keeping the latest `last_used_at`
+fn record_usage(
+    conn: &Connection,
+    table: &str,
+    id: &str,
+    used_at: &DateTime<Utc>,
+) -> SqliteResult<()> {
+    conn.execute(
+        &format!(
+            "UPDATE {} SET usage_count = COALESCE(usage_count, 0) + 1,
+             last_used_at = MAX(COALESCE(last_used_at, ''), ?2) WHERE id = ?1",
+            table
+        ),
+        params![id, used_at.to_rfc3339()],
+    )?;
+    Ok(())
+}
+
+/// Drop `id` from the ID list in `column` of every agent that references it
+fn remove_agent_references(conn: &Connection, column: &str, id: &str) -> SqliteResult<()> {
+    let mut stmt = conn.prepare(&format!("SELECT id, {} FROM agents", column))?;
+    let agents = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?
+        .collect::<SqliteResult<Vec<_>>>()?;
+
+    for (agent_id, json) in agents {
+        let mut ids: Vec<String> = serde_json::from_str(&json).unwrap_or_default();
+        let count = ids.len();
+        ids.retain(|i| i != id);
+        if ids.len() != count {
+            conn.execute(
+                &format!("UPDATE agents SET {} = ?2 WHERE id = ?1", column),
+                params![agent_id, serde_json::to_string(&ids).unwrap()],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Whether the file at `path` starts with SQLite's plaintext header
+pub fn is_plaintext(path: &Path) -> bool {
+    let mut header = [0u8; 16];
+    std::fs::File::open(path)
+        .and_then(|mut file| std::io::Read::read_exact(&mut file, &mut header))
+        .is_ok_and(|_| &header == b"SQLite format 3\0")
+}
+
+fn has_column(conn: &Connection, table: &str, column: &str) -> SqliteResult<bool> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM pragma_table_info(?1) WHERE name = ?2",
+        params![table, column],
+        |row| {
+            let count: i32 = row.get(0)?;
+            Ok(count > 0)
+        },
+    )
+}
+
+fn row_to_agent(row: &rusqlite::Row) -> SqliteResult<Agent> {
+    Ok(Agent {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        description: row.get(2)?,
+        avatar_emoji: row.get(3)?,
+        personality: serde_json::from_str(&row.get::<_, String>(4)?).unwrap_or_default(),
+        system_prompt: row.get(5)?,
+        skills: serde_json::from_str(&row.get::<_, String>(6)?).unwrap_or_default(),
+        instructions: serde_json::from_str(&row.get::<_, String>(7)?).unwrap_or_default(),
+        tags: serde_json::from_str(&row.get::<_, String>(8)?).unwrap_or_default(),
+        created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(9)?)
+            .map(|dt| dt.with_timezone(&Utc))
+            .unwrap_or_else(|_| Utc::now()),
+        updated_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(10)?)
+            .map(|dt| dt.with_timezone(&Utc))
+            .unwrap_or_else(|_| Utc::now()),
+        usage_count: row.get(11)?,
+        last_used_at: row
+            .get::<_, Option<String>>(12)?
+            .and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
+            .map(|dt| dt.with_timezone(&Utc)),
+        avatar_image_id: row.get(13)?,
+        aliases: row
+            .get::<_, Option<String>>(14)?
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default(),
+        instruction_order: string_to_instruction_order(
+            row.get::<_, Option<String>>(15)?
+                .as_deref()
+                .unwrap_or_default(),
+        ),
+        include_global_instructions: row.get::<_, Option<bool>>(16)?.unwrap_or(true),
+        parent_agent_id: row.get(17)?,
+    })
+}
+
+fn row_to_skill(row: &rusqlite::Row) -> SqliteResult<Skill> {
+    Ok(Skill {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        description: row.get(2)?,
+        icon_emoji: row.get(3)?,
+        skill_type: string_to_skill_type(&row.get::<_, String>(4)?),
+        definition: serde_json::from_str(&row.get::<_, String>(5)?).unwrap_or_else(|_| {
+            SkillDefinition::Prompt {
+                template: String::new(),
+                variables: Vec::new(),
+                follows: Vec::new(),
+            }
+        }),
+        enabled: row.get(6)?,
+        created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(7)?)
+            .map(|dt| dt.with_timezone(&Utc))
+            .unwrap_or_else(|_| Utc::now()),
+        updated_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(8)?)
+            .map(|dt| dt.with_timezone(&Utc))
+            .unwrap_or_else(|_| Utc::now()),
+        aliases: row
+            .get::<_, Option<String>>(9)?
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default(),
+        usage_count: row.get::<_, Option<i32>>(10)?.unwrap_or(0),
+        last_used_at: row
+            .get::<_, Option<String>>(11)?
+            .and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
+            .map(|dt| dt.with_timezone(&Utc)),
+    })
+}
+
+fn row_to_instruction(row: &rusqlite::Row) -> SqliteResult<Instruction> {
+    Ok(Instruction {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        description: row.get(2)?,
+        icon_emoji: row.get(3)?,
+        category: string_to_category(&row.get::<_, String>(4)?),
+        content: row.get(5)?,
+        priority: row.get(6)?,
+        tags: serde_json::from_str(&row.get::<_, String>(7)?).unwrap_or_default(),
+        enabled: row.get(8)?,
+        created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(9)?)
+            .map(|dt| dt.with_timezone(&Utc))
+            .unwrap_or_else(|_| Utc::now()),
+        updated_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(10)?)
+            .map(|dt| dt.with_timezone(&Utc))
+            .unwrap_or_else(|_| Utc::now()),
+        pin_to_context: row.get::<_, Option<bool>>(11)?.unwrap_or(false),
+        subcategory: row.get(12)?,
+        branch_pattern: row.get(13)?,
+        file_pattern: row.get(14)?,
+        usage_count: row.get::<_, Option<i32>>(15)?.unwrap_or(0),
+        last_used_at: row
+            .get::<_, Option<String>>(16)?
+            .and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
+            .map(|dt| dt.with_timezone(&Utc)),
+        review_due_at: row
+            .get::<_, Option<String>>(17)?
+            .and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
+            .map(|dt| dt.with_timezone(&Utc)),
+        contexts: row
+            .get::<_, Option<String>>(18)?
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default(),
+        upstream_url: row.get(19)?,
+        upstream_synced_at: row
+            .get::<_, Option<String>>(20)?
+            .and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
+            .map(|dt| dt.with_timezone(&Utc)),
+        upstream_hash: row.get(21)?,
+        activation: row
+            .get::<_, Option<String>>(22)?
+            .and_then(|s| serde_json::from_str(&s).ok()),
+    })
+}
+
+/// An instruction's activation rules as stored, NULL when it has none
+fn activation_json(instruction: &Instruction) -> Option<String> {
+    instruction
+        .activation
+        .as_ref()
+        .map(|activation| serde_json::to_string(activation).unwrap())
+}
+
+fn row_to_attachment(row: &rusqlite::Row) -> SqliteResult<Attachment> {
+    Ok(Attachment {
+        id: row.get(0)?,
+        mime_type: row.get(1)?,
+        data: row.get(2)?,
+        created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(3)?)
+            .map(|dt| dt.with_timezone(&Utc))
+            .unwrap_or_else(|_| Utc::now()),
+    })
+}
+
+fn row_to_edit_lock(row: &rusqlite::Row) -> SqliteResult<EditLock> {
+    Ok(EditLock {
+        entity_id: row.get(0)?,
+        owner_id: row.get(1)?,
+        owner_name: row.get(2)?,
+        acquired_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(3)?)
+            .map(|dt| dt.with_timezone(&Utc))
+            .unwrap_or_else(|_| Utc::now()),
+        expires_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(4)?)
+            .map(|dt| dt.with_timezone(&Utc))
+            .unwrap_or_else(|_| Utc::now()),
+    })
+}
+
+pub(crate) fn skill_type_to_string(st: &SkillType) -> &'static str {
     match st {
         SkillType::Prompt => "prompt",
         SkillType::Tool => "tool",
@@ -554,7 +2156,21 @@ fn string_to_skill_type(s: &str) -> SkillType {
     }
 }
 
-fn category_to_string(cat: &InstructionCategory) -> &'static str {
+fn instruction_order_to_string(order: &InstructionOrder) -> &'static str {
+    match order {
+        InstructionOrder::Priority => "priority",
+        InstructionOrder::Attached => "attached",
+    }
+}
+
+fn string_to_instruction_order(s: &str) -> InstructionOrder {
+    match s {
+        "attached" => InstructionOrder::Attached,
+        _ => InstructionOrder::Priority,
+    }
+}
+
+pub(crate) fn category_to_string(cat: &InstructionCategory) -> &'static str {
     match cat {
         InstructionCategory::General => "general",
         InstructionCategory::CodeStyle => "code_style",
@@ -609,6 +2225,11 @@ pub fn create_default_agent() -> Agent {
         updated_at: Utc::now(),
         usage_count: 0,
         last_used_at: None,
+        avatar_image_id: None,
+        aliases: vec![],
+        instruction_order: InstructionOrder::default(),
+        include_global_instructions: true,
+        parent_agent_id: None,
     }
 }
 
@@ -622,10 +2243,15 @@ pub fn create_default_skills() -> Vec<Skill> {
             skill_type: SkillType::Prompt,
             definition: SkillDefinition::Prompt {
                 template: "Review the following code for:\n- Bugs and potential issues\n- Performance optimizations\n- Code style and best practices\n- Security concerns\n\nProvide specific, actionable feedback.".to_string(),
+                variables: Vec::new(),
+                follows: Vec::new(),
             },
             enabled: true,
             created_at: Utc::now(),
             updated_at: Utc::now(),
+            aliases: vec![],
+            usage_count: 0,
+            last_used_at: None,
         },
         Skill {
             id: "explain-code".to_string(),
@@ -635,10 +2261,15 @@ pub fn create_default_skills() -> Vec<Skill> {
             skill_type: SkillType::Prompt,
             definition: SkillDefinition::Prompt {
                 template: "Explain this code step by step:\n1. What does it do overall?\n2. Break down each important section\n3. Highlight any clever or tricky parts\n4. Suggest improvements if applicable".to_string(),
+                variables: Vec::new(),
+                follows: Vec::new(),
             },
             enabled: true,
             created_at: Utc::now(),
             updated_at: Utc::now(),
+            aliases: vec![],
+            usage_count: 0,
+            last_used_at: None,
         },
     ]
 }
@@ -666,6 +2297,18 @@ pub fn create_default_instructions() -> Vec<Instruction> {
             enabled: true,
             created_at: Utc::now(),
             updated_at: Utc::now(),
+            pin_to_context: false,
+            subcategory: None,
+            branch_pattern: None,
+            file_pattern: None,
+            contexts: Vec::new(),
+            usage_count: 0,
+            last_used_at: None,
+            review_due_at: None,
+            upstream_url: None,
+            upstream_synced_at: None,
+            upstream_hash: None,
+            activation: None,
         },
         Instruction {
             id: "communication".to_string(),
@@ -688,6 +2331,18 @@ pub fn create_default_instructions() -> Vec<Instruction> {
             enabled: true,
             created_at: Utc::now(),
             updated_at: Utc::now(),
+            pin_to_context: false,
+            subcategory: None,
+            branch_pattern: None,
+            file_pattern: None,
+            contexts: Vec::new(),
+            usage_count: 0,
+            last_used_at: None,
+            review_due_at: None,
+            upstream_url: None,
+            upstream_synced_at: None,
+            upstream_hash: None,
+            activation: None,
         },
     ]
 }
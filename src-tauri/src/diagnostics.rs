@@ -0,0 +1,465 @@
+//! Strict checks for imported text
+//! The parsers are lenient: bad frontmatter falls back to markdown, unknown
+//! categories become General and mistyped values are dropped. These checks
+//! walk the same text and report each of those fallbacks with its position,
+//! so an import preview can show them and strict imports can refuse them.
+
+use crate::categories;
+use crate::code_blocks::FenceTracker;
+use crate::models::ParseDiagnostic;
+use serde_yaml::{Mapping, Value};
+
+const AGENT_KEYS: &[&str] = &[
+    "name",
+    "description",
+    "avatar",
+    "emoji",
+    "tags",
+    "personality",
+];
+const INSTRUCTION_KEYS: &[&str] = &[
+    "name",
+    "description",
+    "icon",
+    "emoji",
+    "category",
+    "subcategory",
+    "branch_pattern",
+    "branches",
+    "file_pattern",
+    "globs",
+    "contexts",
+    "priority",
+    "tags",
+    "enabled",
+    "pin_to_context",
+    "pinned",
+];
+
+/// Problems `parser::parse_agent_from_markdown` would paper over
+pub fn check_agent(text: &str) -> Vec<ParseDiagnostic> {
+    let mut diagnostics = Vec::new();
+    match frontmatter(text, &mut diagnostics) {
+        Some((map, body)) => {
+            let mut yaml = YamlCheck::new(text, &mut diagnostics);
+            yaml.unknown_keys(&map, AGENT_KEYS);
+            yaml.strings(&map, &["name", "description", "avatar", "emoji"]);
+            yaml.string_list(&map, "tags");
+            yaml.required_name(&map, "agent");
+            if let Some(value) = map.get("personality") {
+                match value {
+                    Value::Mapping(personality) => yaml.personality(personality),
+                    _ => yaml.error(
+                        "personality",
+                        "`personality` should be a set of `key: value` pairs",
+                    ),
+                }
+            }
+            if body.trim().is_empty() {
+                diagnostics.push(warning(
+                    1,
+                    1,
+                    "Nothing follows the frontmatter, so the system prompt is empty",
+                ));
+            }
+        }
+        None if diagnostics.is_empty() => check_agent_markdown(text, &mut diagnostics),
+        None => {}
+    }
+    diagnostics
+}
+
+/// Problems `parser::parse_instruction_from_markdown` would paper over
+pub fn check_instruction(text: &str) -> Vec<ParseDiagnostic> {
+    let mut diagnostics = Vec::new();
+    match frontmatter(text, &mut diagnostics) {
+        Some((map, body)) => {
+            let mut yaml = YamlCheck::new(text, &mut diagnostics);
+            yaml.unknown_keys(&map, INSTRUCTION_KEYS);
+            yaml.strings(
+                &map,
+                &[
+                    "name",
+                    "description",
+                    "icon",
+                    "emoji",
+                    "subcategory",
+                    "branch_pattern",
+                    "branches",
+                    "file_pattern",
+                    "globs",
+                ],
+            );
+            yaml.string_list(&map, "tags");
+            yaml.string_list(&map, "contexts");
+            yaml.bools(&map, &["enabled", "pin_to_context", "pinned"]);
+            yaml.required_name(&map, "instruction");
+            match map.get("category") {
+                Some(Value::String(path)) => {
+                    let category = path.split_once('/').map_or(path.as_str(), |(c, _)| c);
+                    if categories::try_parse_category(category).is_none() {
+                        yaml.error(
+                            "category",
+                            &format!(
+                                "Unknown category '{}' would be filed under General",
+                                category.trim()
+                            ),
+                        );
+                    }
+                }
+                Some(_) => yaml.error("category", "`category` should be a string"),
+                None => {}
+            }
+            if let Some(value) = map.get("priority") {
+                if !value.as_u64().is_some_and(|p| (1..=10).contains(&p)) {
+                    yaml.error(
+                        "priority",
+                        "`priority` should be a whole number from 1 to 10",
+                    );
+                }
+            }
+            if body.trim().is_empty() {
+                diagnostics.push(warning(
+                    1,
+                    1,
+                    "Nothing follows the frontmatter, so the content is empty",
+                ));
+            }
+        }
+        None if diagnostics.is_empty() && title_line(text).is_none() => {
+            diagnostics.push(error(
+                1,
+                1,
+                "No `# Title` line, so the instruction has no name",
+            ));
+        }
+        None => {}
+    }
+    diagnostics
+}
+
+/// Error message for a strict import, or `None` when nothing is an error
+pub fn strict_failure(diagnostics: &[ParseDiagnostic]) -> Option<String> {
+    let errors: Vec<String> = diagnostics
+        .iter()
+        .filter(|d| d.severity == "error")
+        .map(|d| format!("line {}, column {}: {}", d.line, d.column, d.message))
+        .collect();
+    if errors.is_empty() {
+        None
+    } else {
+        Some(format!("Strict import failed:\n{}", errors.join("\n")))
+    }
+}
+
+/// The frontmatter mapping and the text after it, mirroring how the parsers
+/// detect it. `None` means the text is parsed as markdown; any reason other
+/// than there being no frontmatter at all is reported.
+fn frontmatter<'a>(
+    text: &'a str,
+    diagnostics: &mut Vec<ParseDiagnostic>,
+) -> Option<(Mapping, &'a str)> {
+    if !text.trim().starts_with("---") {
+        return None;
+    }
+    let Some(yaml_end) = text[3..].find("---") else {
+        diagnostics.push(error(
+            1,
+            1,
+            "Frontmatter is never closed with `---`, so the whole text is read as markdown",
+        ));
+        return None;
+    };
+
+    // The YAML starts right after the opening `---`, so its line numbers
+    // match the text's
+    let yaml = &text[3..yaml_end + 3];
+    let body = text.get(yaml_end + 6..).unwrap_or("");
+    match serde_yaml::from_str::<Value>(yaml) {
+        Ok(Value::Mapping(map)) => Some((map, body)),
+        Ok(Value::Null) => Some((Mapping::new(), body)),
+        Ok(_) => {
+            diagnostics.push(error(
+                2,
+                1,
+                "Frontmatter should be a set of `key: value` pairs",
+            ));
+            None
+        }
+        Err(e) => {
+            let (line, column) = e
+                .location()
+                .map(|l| {
+                    (
+                        l.line(),
+                        if l.line() == 1 {
+                            l.column() + 3
+                        } else {
+                            l.column()
+                        },
+                    )
+                })
+                .unwrap_or((1, 1));
+            diagnostics.push(error(
+                line,
+                column,
+                &format!(
+                    "Invalid YAML frontmatter, so the whole text is read as markdown: {}",
+                    e
+                ),
+            ));
+            None
+        }
+    }
+}
+
+fn check_agent_markdown(text: &str, diagnostics: &mut Vec<ParseDiagnostic>) {
+    if title_line(text).is_none() {
+        diagnostics.push(error(1, 1, "No `# Title` line, so the agent has no name"));
+    }
+
+    let mut fences = FenceTracker::default();
+    let mut section = String::new();
+    let mut has_prompt = false;
+    for (index, line) in text.lines().enumerate() {
+        if fences.update(line) {
+            continue;
+        }
+        if let Some(heading) = line.strip_prefix("## ") {
+            section = heading.trim().to_lowercase();
+            has_prompt |= matches!(
+                section.as_str(),
+                "system prompt" | "instructions" | "prompt" | "system"
+            );
+            continue;
+        }
+        if !matches!(section.as_str(), "personality" | "character" | "traits") {
+            continue;
+        }
+        // Mirrors `parse_personality_section`, which drops values it can't read
+        let Some((key, value)) = line.trim().split_once(':') else {
+            continue;
+        };
+        let key = key.trim().to_lowercase();
+        if key != "creativity" && key != "formality" {
+            continue;
+        }
+        let after_colon = line.find(':').map_or(0, |c| c + 1);
+        let column = line.len() - line[after_colon..].trim_start().len() + 1;
+        match value.trim().parse::<f32>() {
+            Ok(v) if (0.0..=1.0).contains(&v) => {}
+            Ok(_) => diagnostics.push(warning(
+                index + 1,
+                column,
+                &format!("`{}` is outside 0-1 and will be clamped", key),
+            )),
+            Err(_) => diagnostics.push(error(
+                index + 1,
+                column,
+                &format!("`{}` should be a number from 0 to 1", key),
+            )),
+        }
+    }
+
+    if !has_prompt {
+        diagnostics.push(warning(
+            1,
+            1,
+            "No `## System Prompt` section, so the whole text is used as the system prompt",
+        ));
+    }
+}
+
+/// 1-based line of the first `# ` heading outside code fences
+fn title_line(text: &str) -> Option<usize> {
+    let mut fences = FenceTracker::default();
+    text.lines()
+        .position(|line| !fences.update(line) && line.starts_with("# "))
+        .map(|index| index + 1)
+}
+
+/// Type checks for frontmatter values, reported at the line of their key
+struct YamlCheck<'a> {
+    text: &'a str,
+    diagnostics: &'a mut Vec<ParseDiagnostic>,
+}
+
+impl<'a> YamlCheck<'a> {
+    fn new(text: &'a str, diagnostics: &'a mut Vec<ParseDiagnostic>) -> Self {
+        Self { text, diagnostics }
+    }
+
+    fn error(&mut self, key: &str, message: &str) {
+        let (line, column) = key_position(self.text, key);
+        self.diagnostics.push(error(line, column, message));
+    }
+
+    fn unknown_keys(&mut self, map: &Mapping, known: &[&str]) {
+        for key in map.keys() {
+            match key.as_str() {
+                Some(key) if known.contains(&key) => {}
+                Some(key) => {
+                    let (line, column) = key_position(self.text, key);
+                    self.diagnostics.push(warning(
+                        line,
+                        column,
+                        &format!("Unknown key `{}` is ignored", key),
+                    ));
+                }
+                None => self
+                    .diagnostics
+                    .push(error(2, 1, "Frontmatter keys should be strings")),
+            }
+        }
+    }
+
+    fn strings(&mut self, map: &Mapping, keys: &[&str]) {
+        for key in keys {
+            if map.get(*key).is_some_and(|v| !v.is_string()) {
+                self.error(key, &format!("`{}` should be a string", key));
+            }
+        }
+    }
+
+    fn bools(&mut self, map: &Mapping, keys: &[&str]) {
+        for key in keys {
+            if map.get(*key).is_some_and(|v| !v.is_bool()) {
+                self.error(key, &format!("`{}` should be true or false", key));
+            }
+        }
+    }
+
+    fn string_list(&mut self, map: &Mapping, key: &str) {
+        let valid = match map.get(key) {
+            None => true,
+            Some(Value::Sequence(items)) => items.iter().all(Value::is_string),
+            Some(_) => false,
+        };
+        if !valid {
+            self.error(key, &format!("`{}` should be a list of strings", key));
+        }
+    }
+
+    fn required_name(&mut self, map: &Mapping, entity: &str) {
+        let name = map.get("name").and_then(Value::as_str).unwrap_or("");
+        if name.trim().is_empty() {
+            self.diagnostics.push(error(
+                1,
+                1,
+                &format!(
+                    "No `name` in the frontmatter, so the {} has no name",
+                    entity
+                ),
+            ));
+        }
+    }
+
+    /// `parse_yaml_personality` keeps out-of-range numbers as they are
+    fn personality(&mut self, personality: &Mapping) {
+        self.strings(personality, &["tone", "verbosity"]);
+        self.string_list(personality, "traits");
+        for key in ["creativity", "formality"] {
+            if let Some(value) = personality.get(key) {
+                if !value.as_f64().is_some_and(|v| (0.0..=1.0).contains(&v)) {
+                    self.error(key, &format!("`{}` should be a number from 0 to 1", key));
+                }
+            }
+        }
+    }
+}
+
+/// Line and column of the first `key:` in the text, or the start of the
+/// frontmatter when it can't be found
+fn key_position(text: &str, key: &str) -> (usize, usize) {
+    let prefix = format!("{}:", key);
+    text.lines()
+        .enumerate()
+        .find_map(|(index, line)| {
+            let trimmed = line.trim_start();
+            trimmed
+                .starts_with(&prefix)
+                .then(|| (index + 1, line.len() - trimmed.len() + 1))
+        })
+        .unwrap_or((1, 1))
+}
+
+fn error(line: usize, column: usize, message: &str) -> ParseDiagnostic {
+    diagnostic("error", line, column, message)
+}
+
+fn warning(line: usize, column: usize, message: &str) -> ParseDiagnostic {
+    diagnostic("warning", line, column, message)
+}
+
+fn diagnostic(severity: &str, line: usize, column: usize, message: &str) -> ParseDiagnostic {
+    ParseDiagnostic {
+        line,
+        column,
+        severity: severity.to_string(),
+        message: message.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn positions(diagnostics: &[ParseDiagnostic]) -> Vec<(usize, usize, &str)> {
+        diagnostics
+            .iter()
+            .map(|d| (d.line, d.column, d.severity.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_instruction_frontmatter_problems_are_located() {
+        let text = "---\nname: Reviews\ncategory: code_review/rust\npriority: 11\npinned: yes\nmodel: x\n---\n\nBe kind.";
+        let diagnostics = check_instruction(text);
+        assert_eq!(
+            positions(&diagnostics),
+            vec![
+                (6, 1, "warning"),
+                (5, 1, "error"),
+                (3, 1, "error"),
+                (4, 1, "error")
+            ]
+        );
+        assert!(diagnostics[2].message.contains("'code_review'"));
+        assert!(strict_failure(&diagnostics)
+            .unwrap()
+            .contains("line 4, column 1: `priority` should be a whole number from 1 to 10"));
+
+        let clean = "---\nname: Reviews\ncategory: CodeStyle/rust\npriority: 8\n---\n\nBe kind.";
+        assert!(check_instruction(clean).is_empty());
+        assert!(strict_failure(&check_instruction(clean)).is_none());
+    }
+
+    #[test]
+    fn test_bad_yaml_reports_location_instead_of_falling_back() {
+        let text = "---\nname: Writer\npersonality: [unclosed\n---\n\nYou write.";
+        let diagnostics = check_agent(text);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, "error");
+        assert_eq!(diagnostics[0].line, 4);
+        assert!(diagnostics[0]
+            .message
+            .starts_with("Invalid YAML frontmatter"));
+    }
+
+    #[test]
+    fn test_markdown_agent_checks() {
+        let text = "Some notes\n\n## Personality\nCreativity: very\nFormality: 1.5\n";
+        assert_eq!(
+            positions(&check_agent(text)),
+            vec![
+                (1, 1, "error"),
+                (4, 13, "error"),
+                (5, 12, "warning"),
+                (1, 1, "warning")
+            ]
+        );
+
+        let text = "# Reviewer\n\n## System Prompt\nReview code.\n";
+        assert!(check_agent(text).is_empty());
+    }
+}
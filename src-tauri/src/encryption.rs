@@ -0,0 +1,144 @@
+//! Optional at-rest encryption for the library database.
+//! The database is SQLCipher-compatible; when encryption is enabled its passphrase
+//! lives in the OS keychain and never touches the data directory. Each
+//! workspace's database is encrypted, or not, with a passphrase of its own.
+
+use crate::db::{self, Database};
+use crate::library_workspaces;
+use std::path::Path;
+
+const KEYCHAIN_SERVICE: &str = "com.promptforge.app";
+const KEYCHAIN_ACCOUNT: &str = "database-passphrase";
+
+/// The keychain account holding the passphrase of the database at `path`.
+/// The default workspace keeps the account from before workspaces existed.
+fn keychain_account(path: &Path) -> String {
+    match library_workspaces::named_workspace(path) {
+        Some(workspace) => format!("{}-{}", KEYCHAIN_ACCOUNT, workspace),
+        None => KEYCHAIN_ACCOUNT.to_string(),
+    }
+}
+
+fn keychain_entry(path: &Path) -> Result<keyring::Entry, String> {
+    keyring::Entry::new(KEYCHAIN_SERVICE, &keychain_account(path))
+        .map_err(|e| format!("Failed to access keychain: {}", e))
+}
+
+/// Look up the passphrase of the database at `path`, if encryption has been
+/// enabled for it
+pub fn stored_passphrase(path: &Path) -> Result<Option<String>, String> {
+    match keychain_entry(path)?.get_password() {
+        Ok(passphrase) => Ok(Some(passphrase)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(format!("Failed to read passphrase from keychain: {}", e)),
+    }
+}
+
+/// Whether the database at `path` is currently encrypted
+pub fn is_enabled(path: &Path) -> Result<bool, String> {
+    Ok(stored_passphrase(path)?.is_some())
+}
+
+/// The passphrase to open the database at `path` with. Plaintext and new
+/// databases don't touch the keychain, which may be missing altogether on
+/// headless machines.
+pub fn passphrase_for(path: &Path) -> Result<Option<String>, String> {
+    let encrypted = path.metadata().is_ok_and(|m| m.len() > 0) && !db::is_plaintext(path);
+    if !encrypted {
+        return Ok(None);
+    }
+    stored_passphrase(path)
+}
+
+/// Open the library database, unlocking it with the keychain passphrase when encrypted
+pub fn open_database(path: &Path) -> Result<Database, String> {
+    let passphrase = passphrase_for(path)?;
+    Database::open_with_key(path, passphrase.as_deref())
+        .map_err(|e| format!("Failed to open database: {}", e))
+}
+
+/// Open the library database read-only, e.g. for a `--read-only` MCP server
+pub fn open_database_read_only(path: &Path) -> Result<Database, String> {
+    let passphrase = passphrase_for(path)?;
+    Database::open_read_only(path, passphrase.as_deref())
+        .map_err(|e| format!("Failed to open database: {}", e))
+}
+
+/// Encrypt or decrypt the database file in place. Other workspaces keep
+/// their own setting.
+/// If the keychain update fails the file is rekeyed back, so the stored
+/// passphrase and the file never disagree.
+pub fn set_enabled(db: &Database, path: &Path, enabled: bool) -> Result<(), String> {
+    let current = stored_passphrase(path)?;
+    if current.is_some() == enabled {
+        return Ok(());
+    }
+
+    let entry = keychain_entry(path)?;
+    if enabled {
+        let passphrase = generate_passphrase();
+        db.rekey(path, None, Some(&passphrase))
+            .map_err(|e| format!("Failed to encrypt database: {}", e))?;
+        if let Err(e) = entry.set_password(&passphrase) {
+            // Without a stored passphrase the file would be unreadable on next launch
+            db.rekey(path, Some(&passphrase), None).ok();
+            return Err(format!("Failed to store passphrase in keychain: {}", e));
+        }
+    } else {
+        db.rekey(path, current.as_deref(), None)
+            .map_err(|e| format!("Failed to decrypt database: {}", e))?;
+        if let Err(e) = entry.delete_credential() {
+            db.rekey(path, None, current.as_deref()).ok();
+            return Err(format!("Failed to remove passphrase from keychain: {}", e));
+        }
+    }
+
+    Ok(())
+}
+
+fn generate_passphrase() -> String {
+    format!(
+        "{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_workspaces_keep_their_own_passphrase() {
+        let dir = std::env::temp_dir().join(format!("pf-encryption-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let default_path = library_workspaces::database_path(&dir, "default");
+        let client_path = library_workspaces::database_path(&dir, "client");
+        assert_eq!(keychain_account(&default_path), "database-passphrase");
+        assert_eq!(keychain_account(&client_path), "database-passphrase-client");
+
+        for path in [&default_path, &client_path] {
+            let db = Database::open_with_key(path, None).unwrap();
+            db.migrate().unwrap();
+        }
+
+        // Encrypt the default workspace, then switch to the client one
+        let db = Database::open_with_key(&default_path, None).unwrap();
+        db.rekey(&default_path, None, Some("secret")).unwrap();
+        drop(db);
+        let client = Database::open_with_key(&client_path, None).unwrap();
+        assert!(client.get_settings().is_ok());
+        assert!(Database::open_with_key(&default_path, None)
+            .and_then(|db| db.get_settings())
+            .is_err());
+        let default = Database::open_with_key(&default_path, Some("secret")).unwrap();
+        assert!(default.get_settings().is_ok());
+
+        // Only the encrypted file needs its passphrase looked up
+        assert_eq!(passphrase_for(&client_path), Ok(None));
+        assert!(!db::is_plaintext(&default_path));
+        assert_eq!(passphrase_for(&dir.join("new.db")), Ok(None));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,178 @@
+//! Instruction A/B experiments
+//! An experiment gives an instruction a second wording. The MCP server serves
+//! each session one of the two, picked from a hash of the session id and the
+//! instruction id so a session always sees the same variant, and records
+//! which one it served as a `variant` insight. Results compare how many
+//! sessions got each.
+
+use crate::db::Database;
+use crate::insights::InsightMetric;
+use crate::models::{Experiment, ExperimentResult, Instruction};
+use rusqlite::Result as SqliteResult;
+use sha2::{Digest, Sha256};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Variant {
+    A,
+    B,
+}
+
+impl Variant {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Variant::A => "a",
+            Variant::B => "b",
+        }
+    }
+}
+
+/// The variant `session_id` is served of the experiment on `instruction_id`
+pub fn assign(session_id: &str, instruction_id: &str) -> Variant {
+    let digest = Sha256::digest(format!("{}:{}", session_id, instruction_id));
+    if digest[0] & 1 == 0 {
+        Variant::A
+    } else {
+        Variant::B
+    }
+}
+
+/// Swap in variant B wherever `session_id` is assigned it, returning the
+/// variant served for each experiment on an instruction in `instructions`
+pub fn apply(
+    instructions: &mut [Instruction],
+    experiments: &[Experiment],
+    session_id: &str,
+) -> Vec<(String, Variant)> {
+    let mut served = Vec::new();
+    for experiment in experiments {
+        let Some(instruction) = instructions
+            .iter_mut()
+            .find(|i| i.id == experiment.instruction_id)
+        else {
+            continue;
+        };
+        let variant = assign(session_id, &instruction.id);
+        if variant == Variant::B {
+            instruction.content = experiment.variant_b.clone();
+        }
+        served.push((instruction.id.clone(), variant));
+    }
+    served
+}
+
+/// Insight key recording that `variant` of an instruction was served
+pub fn insight_key(instruction_id: &str, variant: Variant) -> String {
+    format!("{}/{}", instruction_id, variant.as_str())
+}
+
+/// Sessions served each variant of every running experiment since it started
+pub fn results(db: &Database, instructions: &[Instruction]) -> SqliteResult<Vec<ExperimentResult>> {
+    let experiments = db.get_experiments()?;
+    let Some(earliest) = experiments.iter().map(|e| e.started_at).min() else {
+        return Ok(Vec::new());
+    };
+    let counters = db.get_insight_counters(&earliest.date_naive().to_string())?;
+
+    Ok(experiments
+        .into_iter()
+        .map(|experiment| {
+            let since = experiment.started_at.date_naive().to_string();
+            let served = |variant| {
+                let key = insight_key(&experiment.instruction_id, variant);
+                counters
+                    .iter()
+                    .filter(|(day, metric, k, _)| {
+                        *day >= since && metric == InsightMetric::Variant.as_str() && *k == key
+                    })
+                    .map(|(_, _, _, count)| count)
+                    .sum()
+            };
+            ExperimentResult {
+                instruction_name: instructions
+                    .iter()
+                    .find(|i| i.id == experiment.instruction_id)
+                    .map(|i| i.name.clone())
+                    .unwrap_or_default(),
+                served_a: served(Variant::A),
+                served_b: served(Variant::B),
+                instruction_id: experiment.instruction_id,
+                started_at: experiment.started_at,
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    #[test]
+    fn test_sessions_are_split_deterministically() {
+        let experiment = Experiment {
+            instruction_id: "tone".to_string(),
+            variant_b: "Be terse.".to_string(),
+            started_at: Utc::now(),
+        };
+        let instruction = Instruction {
+            id: "tone".to_string(),
+            content: "Be friendly.".to_string(),
+            ..Instruction::default()
+        };
+
+        let sessions: Vec<String> = (0..200).map(|n| format!("session-{}", n)).collect();
+        let b_count = sessions
+            .iter()
+            .filter(|s| assign(s, "tone") == Variant::B)
+            .count();
+        assert!((70..=130).contains(&b_count), "{}", b_count);
+
+        for session in sessions.iter().take(20) {
+            let mut served = vec![instruction.clone()];
+            let variants = apply(&mut served, std::slice::from_ref(&experiment), session);
+            assert_eq!(
+                variants,
+                vec![("tone".to_string(), assign(session, "tone"))]
+            );
+            let expected = match variants[0].1 {
+                Variant::A => "Be friendly.",
+                Variant::B => "Be terse.",
+            };
+            assert_eq!(served[0].content, expected);
+        }
+        assert!(apply(&mut [], &[experiment], "s").is_empty());
+    }
+
+    #[test]
+    fn test_results_count_served_variants_since_start() {
+        let db = Database::open(":memory:").unwrap();
+        db.migrate().unwrap();
+        let started_at = Utc::now();
+        db.save_experiment(&Experiment {
+            instruction_id: "tone".to_string(),
+            variant_b: "Be terse.".to_string(),
+            started_at,
+        })
+        .unwrap();
+        let today = started_at.date_naive().to_string();
+        for key in ["tone/a", "tone/b", "tone/b", "other/a"] {
+            db.increment_insight_counter(&today, "variant", key)
+                .unwrap();
+        }
+        db.increment_insight_counter("2000-01-01", "variant", "tone/a")
+            .unwrap();
+
+        let instructions = vec![Instruction {
+            id: "tone".to_string(),
+            name: "Tone".to_string(),
+            ..Instruction::default()
+        }];
+        let report = results(&db, &instructions).unwrap();
+        assert_eq!(report.len(), 1);
+        assert_eq!(report[0].instruction_name, "Tone");
+        assert_eq!((report[0].served_a, report[0].served_b), (1, 2));
+
+        assert!(db.delete_experiment("tone").unwrap());
+        assert!(results(&db, &instructions).unwrap().is_empty());
+    }
+}
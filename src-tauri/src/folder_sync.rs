@@ -0,0 +1,303 @@
+//! Library mirrored to a folder on disk
+//! With a library folder set in settings, every agent, skill and instruction
+//! is also a file there, in the layout git sync uses, so prompts can live in
+//! a dotfiles repository. The folder is polled: files edited, added or
+//! removed outside the app update the library, and edits made in the app
+//! rewrite the files. An item changed on both sides between two polls keeps
+//! the file's version. Items already in a newly set folder are imported;
+//! the rest of the library is written next to them. A folder that goes
+//! missing, or loses every file at once, is treated as unmounted or moved
+//! rather than as the whole library being deleted.
+
+use crate::composition::LibraryGraph;
+use crate::db::Database;
+use crate::git_sync::{self, Library};
+use crate::persona_cache;
+use chrono::Utc;
+use rusqlite::Result as SqliteResult;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// The folder as it was left by the last poll
+#[derive(Default)]
+pub struct FolderSync {
+    dir: Option<PathBuf>,
+    files: BTreeMap<PathBuf, String>,
+    revision: u64,
+}
+
+/// Files of one kind of item that changed since the last poll, by stem
+struct Changes {
+    edited: HashSet<String>,
+    removed: HashSet<String>,
+}
+
+impl FolderSync {
+    /// Bring the library and `dir` in line, returning how many items were
+    /// updated from the files
+    pub fn poll(&mut self, db: &Database, dir: &Path) -> Result<usize, String> {
+        let first = self.dir.as_deref() != Some(dir);
+        if first {
+            self.files.clear();
+        }
+
+        if !first {
+            for kind in [
+                git_sync::AGENTS_DIR,
+                git_sync::SKILLS_DIR,
+                git_sync::INSTRUCTIONS_DIR,
+            ] {
+                if !dir.join(kind).is_dir() {
+                    return Err(format!(
+                        "{} is missing; was the library folder moved or unmounted?",
+                        dir.join(kind).display()
+                    ));
+                }
+            }
+        }
+        let mut files = git_sync::read_files(dir)?;
+        if files.is_empty() && !self.files.is_empty() {
+            return Err(format!(
+                "Every file in {} is gone; not deleting the whole library",
+                dir.display()
+            ));
+        }
+        let changed: Vec<&PathBuf> = files
+            .keys()
+            .chain(self.files.keys())
+            .filter(|path| files.get(*path) != self.files.get(*path))
+            .collect();
+        let applied = if changed.is_empty() {
+            0
+        } else {
+            let changes = |kind: &str| Changes {
+                edited: stems(&changed, kind, |path| files.contains_key(path)),
+                removed: stems(&changed, kind, |path| !files.contains_key(path)),
+            };
+            let (agents, skills, instructions) = load(db)?;
+            let (file_agents, file_skills, file_instructions) =
+                git_sync::read_library(dir, (&agents, &skills, &instructions))?;
+
+            apply(
+                &file_agents,
+                &agents,
+                &changes(git_sync::AGENTS_DIR),
+                |a| &a.id,
+                |a| a.updated_at = Utc::now(),
+                |a, exists| match exists {
+                    true => db.update_agent(a),
+                    false => db.insert_agent(a),
+                },
+                |id| db.delete_agent(id),
+            )? + apply(
+                &file_skills,
+                &skills,
+                &changes(git_sync::SKILLS_DIR),
+                |s| &s.id,
+                |s| s.updated_at = Utc::now(),
+                |s, exists| {
+                    // A handler edited outside the app has to be approved again
+                    db.revoke_tool_approval(&s.id)?;
+                    match exists {
+                        true => db.update_skill(s),
+                        false => db.insert_skill(s),
+                    }
+                },
+                |id| db.delete_skill(id),
+            )? + apply(
+                &file_instructions,
+                &instructions,
+                &changes(git_sync::INSTRUCTIONS_DIR),
+                |i| &i.id,
+                |i| i.updated_at = Utc::now(),
+                |i, exists| match exists {
+                    true => db.update_instruction(i),
+                    false => db.insert_instruction(i),
+                },
+                |id| db.delete_instruction(id),
+            )?
+        };
+
+        let (agents, skills, instructions) = load(db)?;
+        let revision = persona_cache::library_revision(
+            &LibraryGraph {
+                agents: &agents,
+                skills: &skills,
+                instructions: &instructions,
+            },
+            &[],
+            &[],
+        );
+        if first || applied > 0 || revision != self.revision {
+            git_sync::write_library(dir, &agents, &skills, &instructions)?;
+            files = git_sync::read_files(dir)?;
+        }
+
+        self.dir = Some(dir.to_path_buf());
+        self.files = files;
+        self.revision = revision;
+        Ok(applied)
+    }
+}
+
+/// The folder a `library_folder` setting names, with a leading `~` standing
+/// for the home directory
+pub fn folder_path(setting: &str) -> PathBuf {
+    let setting = setting.trim();
+    match (setting.strip_prefix('~'), dirs::home_dir()) {
+        (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with(['/', '\\']) => {
+            home.join(rest.trim_start_matches(['/', '\\']))
+        }
+        _ => PathBuf::from(setting),
+    }
+}
+
+fn load(db: &Database) -> Result<Library, String> {
+    let error = |e: rusqlite::Error| format!("Failed to load the library: {}", e);
+    Ok((
+        db.get_all_agents().map_err(error)?,
+        db.get_all_skills().map_err(error)?,
+        db.get_all_instructions().map_err(error)?,
+    ))
+}
+
+/// Stems of the changed files under `kind`'s directory that pass `filter`
+fn stems(changed: &[&PathBuf], kind: &str, filter: impl Fn(&Path) -> bool) -> HashSet<String> {
+    changed
+        .iter()
+        .filter(|path| path.starts_with(kind) && filter(path))
+        .filter_map(|path| path.file_stem())
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .collect()
+}
+
+/// Save the items whose files were edited when they differ from the library,
+/// and delete those whose files were removed. Returns how many changed.
+fn apply<T: Serialize + Clone>(
+    from_files: &[T],
+    in_db: &[T],
+    changes: &Changes,
+    id: impl Fn(&T) -> &str,
+    touch: impl Fn(&mut T),
+    mut save: impl FnMut(&T, bool) -> SqliteResult<()>,
+    mut delete: impl FnMut(&str) -> SqliteResult<()>,
+) -> Result<usize, String> {
+    let mut applied = 0;
+    for item in from_files {
+        if !changes.edited.contains(&git_sync::file_stem(id(item))) {
+            continue;
+        }
+        let current = in_db.iter().find(|current| id(current) == id(item));
+        if current.is_some_and(|current| same_content(current, item)) {
+            continue;
+        }
+        let mut item = item.clone();
+        touch(&mut item);
+        save(&item, current.is_some()).map_err(|e| {
+            format!(
+                "Failed to save '{}' from the library folder: {}",
+                id(&item),
+                e
+            )
+        })?;
+        applied += 1;
+    }
+
+    for item in in_db {
+        let removed = changes.removed.contains(&git_sync::file_stem(id(item)))
+            && !from_files.iter().any(|file| id(file) == id(item));
+        if removed {
+            delete(id(item)).map_err(|e| format!("Failed to delete '{}': {}", id(item), e))?;
+            applied += 1;
+        }
+    }
+    Ok(applied)
+}
+
+/// Whether two versions of an item differ only in when they were updated
+fn same_content<T: Serialize>(a: &T, b: &T) -> bool {
+    let content = |item: &T| {
+        let mut value = serde_json::to_value(item).unwrap_or_default();
+        if let Some(fields) = value.as_object_mut() {
+            fields.remove("updated_at");
+        }
+        value
+    };
+    content(a) == content(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Instruction;
+    use std::fs;
+
+    #[test]
+    fn test_folder_and_library_follow_each_other() {
+        let dir = std::env::temp_dir().join(format!("pf-folder-sync-{}", uuid::Uuid::new_v4()));
+        let db = Database::open(":memory:").unwrap();
+        db.migrate().unwrap();
+        db.insert_instruction(&Instruction {
+            id: "style".to_string(),
+            content: "Use rustfmt.".to_string(),
+            ..Instruction::default()
+        })
+        .unwrap();
+        db.insert_instruction(&Instruction {
+            id: "tests".to_string(),
+            content: "Test every fix.".to_string(),
+            ..Instruction::default()
+        })
+        .unwrap();
+
+        let mut sync = FolderSync::default();
+        assert_eq!(sync.poll(&db, &dir).unwrap(), 0);
+        let style = dir.join("instructions/style.md");
+        assert!(fs::read_to_string(&style)
+            .unwrap()
+            .ends_with("Use rustfmt.\n"));
+
+        // Edited outside the app
+        let text = fs::read_to_string(&style).unwrap();
+        fs::write(
+            &style,
+            text.replace("Use rustfmt.", "Use rustfmt and clippy."),
+        )
+        .unwrap();
+        assert_eq!(sync.poll(&db, &dir).unwrap(), 1);
+        let instruction = db.get_instruction("style").unwrap().unwrap();
+        assert_eq!(instruction.content, "Use rustfmt and clippy.");
+        assert_eq!(sync.poll(&db, &dir).unwrap(), 0);
+
+        // Edited in the app
+        db.update_instruction(&Instruction {
+            content: "Format everything.".to_string(),
+            updated_at: Utc::now() + chrono::Duration::seconds(1),
+            ..instruction
+        })
+        .unwrap();
+        assert_eq!(sync.poll(&db, &dir).unwrap(), 0);
+        assert!(fs::read_to_string(&style)
+            .unwrap()
+            .ends_with("Format everything.\n"));
+
+        // Removed outside the app
+        fs::remove_file(&style).unwrap();
+        assert_eq!(sync.poll(&db, &dir).unwrap(), 1);
+        assert_eq!(db.get_all_instructions().unwrap().len(), 1);
+
+        // Every file gone at once, then the folder itself
+        fs::remove_file(dir.join("instructions/tests.md")).unwrap();
+        assert!(sync.poll(&db, &dir).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(sync.poll(&db, &dir).is_err());
+        assert_eq!(db.get_all_instructions().unwrap().len(), 1);
+
+        let _ = fs::remove_dir_all(&dir);
+
+        let home = dirs::home_dir().unwrap();
+        assert_eq!(folder_path(" ~/prompts/ "), home.join("prompts/"));
+        assert_eq!(folder_path("~other"), PathBuf::from("~other"));
+    }
+}
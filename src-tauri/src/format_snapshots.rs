@@ -0,0 +1,369 @@
+//! Snapshot tests for every output format
+//! Renders a fixed fixture library through each exporter and compares the
+//! result with the files in `tests/snapshots`, so changes to assembly or
+//! export logic show up as reviewable diffs.
+//!
+//! Run with `UPDATE_SNAPSHOTS=1 cargo test format_snapshots` to accept new
+//! output. New exporters add a row to `render_all`.
+
+use crate::assembly::{self, AssemblyOptions};
+use crate::copilot;
+use crate::cursor;
+use crate::langchain;
+use crate::models::*;
+use crate::openai_assistant;
+use crate::parser;
+use crate::subagent;
+use crate::tool_schema;
+use chrono::{DateTime, TimeZone, Utc};
+use std::path::PathBuf;
+
+fn fixed_time() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2025, 1, 15, 9, 30, 0).unwrap()
+}
+
+struct Library {
+    agents: Vec<Agent>,
+    skills: Vec<Skill>,
+    instructions: Vec<Instruction>,
+}
+
+/// A small library exercising attached, detached, disabled, pinned and
+/// branch-scoped entries
+fn fixture_library() -> Library {
+    let skills = vec![
+        Skill {
+            id: "skill-review".to_string(),
+            name: "Code Review".to_string(),
+            description: "Review a diff for bugs and style issues".to_string(),
+            icon_emoji: "🔍".to_string(),
+            skill_type: SkillType::Prompt,
+            definition: SkillDefinition::Prompt {
+                template: "Review the following change:\n\n{{diff}}".to_string(),
+                variables: Vec::new(),
+                follows: Vec::new(),
+            },
+            enabled: true,
+            created_at: fixed_time(),
+            updated_at: fixed_time(),
+            aliases: vec!["review".to_string()],
+            usage_count: 0,
+            last_used_at: None,
+        },
+        Skill {
+            id: "skill-search".to_string(),
+            name: "search_docs".to_string(),
+            description: "Search the project documentation".to_string(),
+            icon_emoji: "🔧".to_string(),
+            skill_type: SkillType::Tool,
+            definition: SkillDefinition::Tool {
+                parameters: vec![
+                    ToolParameter {
+                        name: "query".to_string(),
+                        description: "Search terms".to_string(),
+                        param_type: "string".to_string(),
+                        required: true,
+                        default: None,
+                        schema: None,
+                    },
+                    ToolParameter {
+                        name: "limit".to_string(),
+                        description: "Maximum results".to_string(),
+                        param_type: "integer".to_string(),
+                        required: false,
+                        default: Some(serde_json::json!(5)),
+                        schema: Some(serde_json::json!({ "minimum": 1, "maximum": 50 })),
+                    },
+                ],
+                handler: "docs-search --json".to_string(),
+                sandbox: None,
+            },
+            enabled: true,
+            created_at: fixed_time(),
+            updated_at: fixed_time(),
+            aliases: vec![],
+            usage_count: 0,
+            last_used_at: None,
+        },
+        Skill {
+            id: "skill-disabled".to_string(),
+            name: "Legacy Formatter".to_string(),
+            enabled: false,
+            created_at: fixed_time(),
+            updated_at: fixed_time(),
+            ..Skill::default()
+        },
+    ];
+
+    let instruction = |id: &str, name: &str, category, content: &str| Instruction {
+        id: id.to_string(),
+        name: name.to_string(),
+        description: format!("{} guidelines", name),
+        icon_emoji: "📋".to_string(),
+        category,
+        content: content.to_string(),
+        priority: 5,
+        enabled: true,
+        tags: vec![],
+        created_at: fixed_time(),
+        updated_at: fixed_time(),
+        pin_to_context: false,
+        subcategory: None,
+        branch_pattern: None,
+        file_pattern: None,
+        contexts: Vec::new(),
+        usage_count: 0,
+        last_used_at: None,
+        review_due_at: None,
+        upstream_url: None,
+        upstream_synced_at: None,
+        upstream_hash: None,
+        activation: None,
+    };
+
+    let instructions = vec![
+        Instruction {
+            priority: 9,
+            tags: vec!["rust".to_string()],
+            subcategory: Some("rust".to_string()),
+            file_pattern: Some("**/*.rs".to_string()),
+            ..instruction(
+                "inst-style",
+                "Code Style",
+                InstructionCategory::CodeStyle,
+                "- Prefer explicit error types\n- Keep functions short",
+            )
+        },
+        Instruction {
+            pin_to_context: true,
+            ..instruction(
+                "inst-security",
+                "Security",
+                InstructionCategory::Security,
+                "Never print credentials.",
+            )
+        },
+        instruction(
+            "inst-tone",
+            "Tone",
+            InstructionCategory::Communication,
+            "Be direct and concise.",
+        ),
+        Instruction {
+            branch_pattern: Some("release/*".to_string()),
+            ..instruction(
+                "inst-release",
+                "Release Freeze",
+                InstructionCategory::Workflow,
+                "Only merge reviewed bug fixes.",
+            )
+        },
+        Instruction {
+            enabled: false,
+            ..instruction(
+                "inst-off",
+                "Retired Rule",
+                InstructionCategory::General,
+                "This should never appear.",
+            )
+        },
+    ];
+
+    let agents = vec![Agent {
+        id: "agent-reviewer".to_string(),
+        name: "Reviewer".to_string(),
+        description: "Careful code reviewer".to_string(),
+        avatar_emoji: "🧐".to_string(),
+        personality: Personality {
+            tone: "professional".to_string(),
+            verbosity: "concise".to_string(),
+            creativity: 0.3,
+            formality: 0.7,
+            traits: vec!["precise".to_string(), "patient".to_string()],
+        },
+        system_prompt: "You review code for correctness first, style second.".to_string(),
+        skills: vec![
+            "skill-review".to_string(),
+            "skill-search".to_string(),
+            "skill-disabled".to_string(),
+            "skill-deleted".to_string(),
+        ],
+        instructions: vec![
+            "inst-style".to_string(),
+            "inst-security".to_string(),
+            "inst-off".to_string(),
+        ],
+        tags: vec!["engineering".to_string()],
+        created_at: fixed_time(),
+        updated_at: fixed_time(),
+        usage_count: 0,
+        last_used_at: None,
+        avatar_image_id: None,
+        aliases: vec!["cr".to_string()],
+        instruction_order: InstructionOrder::Priority,
+        include_global_instructions: true,
+        parent_agent_id: None,
+    }];
+
+    Library {
+        agents,
+        skills,
+        instructions,
+    }
+}
+
+/// Every exporter's output for the fixture library, keyed by snapshot name
+fn render_all(library: &Library) -> Vec<(&'static str, String)> {
+    let agent = &library.agents[0];
+    let tool_skill = &library.skills[1];
+    let plain = AssemblyOptions::default();
+    let numbered = AssemblyOptions {
+        numbered: true,
+        ..AssemblyOptions::default()
+    };
+    let release = AssemblyOptions {
+        branch: Some("release/1.0".to_string()),
+        ..AssemblyOptions::default()
+    };
+
+    vec![
+        (
+            "agent_markdown.md",
+            parser::export_agent_to_markdown_text(agent),
+        ),
+        (
+            "claude_code_subagent.md",
+            subagent::export_subagent(
+                agent,
+                &library.skills,
+                &library.instructions,
+                &["Read".to_string(), "Grep".to_string()],
+            ),
+        ),
+        (
+            "openai_assistant.json",
+            serde_json::to_string_pretty(&openai_assistant::export_assistant(
+                agent,
+                &library.skills,
+                &library.instructions,
+                None,
+            ))
+            .unwrap(),
+        ),
+        (
+            "instruction_markdown.md",
+            parser::export_instruction_to_markdown_text(&library.instructions[0]),
+        ),
+        (
+            "copilot_instructions.md",
+            copilot::export_instructions_file(&library.instructions[0]),
+        ),
+        (
+            "cursor_rule.mdc",
+            cursor::export_rule(&library.instructions[0]),
+        ),
+        (
+            "apply_agent_prompt.md",
+            assembly::agent_prompt(agent, &library.skills, &library.instructions, &plain),
+        ),
+        (
+            "apply_agent_prompt_numbered.md",
+            assembly::agent_prompt(agent, &library.skills, &library.instructions, &numbered),
+        ),
+        (
+            "mcp_apply_agent.md",
+            assembly::agent_configuration(agent, &library.skills, &library.instructions, &plain),
+        ),
+        (
+            "mcp_apply_agent_numbered.md",
+            assembly::agent_configuration(agent, &library.skills, &library.instructions, &numbered),
+        ),
+        (
+            "mcp_apply_agent_release.md",
+            assembly::agent_configuration(agent, &library.skills, &library.instructions, &release),
+        ),
+        (
+            "mcp_instructions_compact.md",
+            assembly::instruction_digest(&library.instructions, 50),
+        ),
+        (
+            "mcp_apply_agent_trace.json",
+            serde_json::to_string_pretty(&assembly::trace_agent_configuration(
+                agent,
+                &library.skills,
+                &library.instructions,
+                &plain,
+            ))
+            .unwrap(),
+        ),
+        (
+            "mcp_apply_agent_sections.json",
+            serde_json::to_string_pretty(&assembly::agent_configuration_sections(
+                agent,
+                &library.skills,
+                &library.instructions,
+                &plain,
+            ))
+            .unwrap(),
+        ),
+        (
+            "compose_prompt.md",
+            assembly::compose_prompt(
+                &library.skills,
+                &library.instructions,
+                &["review".to_string()],
+                &["security".to_string(), "tone".to_string()],
+                Some("Check the attached patch."),
+                &plain,
+            )
+            .unwrap(),
+        ),
+        (
+            "langchain_prompt.json",
+            serde_json::to_string_pretty(&langchain::export_prompt(&library.skills[0]).unwrap())
+                .unwrap(),
+        ),
+        (
+            "mcp_tool.json",
+            serde_json::to_string_pretty(&tool_schema::skill_to_mcp_tool(tool_skill).unwrap())
+                .unwrap(),
+        ),
+    ]
+}
+
+fn snapshot_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("snapshots")
+}
+
+#[test]
+fn test_output_formats_match_snapshots() {
+    let update = std::env::var_os("UPDATE_SNAPSHOTS").is_some();
+    let dir = snapshot_dir();
+    let mut failures = Vec::new();
+
+    for (name, actual) in render_all(&fixture_library()) {
+        let path = dir.join(name);
+        let actual = format!("{}\n", actual.trim_end());
+
+        match std::fs::read_to_string(&path) {
+            Ok(expected) if expected == actual => {}
+            Ok(_) | Err(_) if update => {
+                std::fs::create_dir_all(&dir).unwrap();
+                std::fs::write(&path, &actual).unwrap();
+            }
+            Ok(expected) => failures.push(format!(
+                "{} changed.\n--- expected ---\n{}\n--- actual ---\n{}",
+                name, expected, actual
+            )),
+            Err(_) => failures.push(format!("{} has no snapshot yet", name)),
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{}\n\nRe-run with UPDATE_SNAPSHOTS=1 to accept the new output.",
+        failures.join("\n\n")
+    );
+}
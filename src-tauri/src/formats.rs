@@ -0,0 +1,470 @@
+//! Registry of import and export formats
+//! Every text format the library can be read from or written to is registered
+//! here under a stable id such as `agent-markdown`, as an `Importer`, an
+//! `Exporter` or both. Commands look formats up by id instead of calling
+//! format-specific functions, so a new format (or a plugin) only has to
+//! register itself.
+
+use crate::character_card;
+use crate::copilot;
+use crate::cursor;
+use crate::diagnostics;
+use crate::langchain;
+use crate::models::{LibraryItem, ParseDiagnostic, SupportedFormat};
+use crate::openai_assistant;
+use crate::parser;
+use crate::tool_schema;
+use std::collections::{BTreeMap, HashMap};
+
+/// Reads library entities from text. An agent may list skills by the name of
+/// ones imported ahead of it, to be swapped by `link_imported_skills`.
+pub trait Importer: Send + Sync {
+    fn import(&self, text: &str) -> Result<Vec<LibraryItem>, String>;
+
+    /// Anything `import` would silently default, for previews and strict imports
+    fn check(&self, _text: &str) -> Vec<ParseDiagnostic> {
+        Vec::new()
+    }
+}
+
+/// Writes one library entity as text. The registry only passes entities of
+/// the type the format was registered for.
+pub trait Exporter: Send + Sync {
+    fn export(&self, item: &LibraryItem) -> Result<String, String>;
+}
+
+struct Format {
+    info: SupportedFormat,
+    importer: Option<Box<dyn Importer>>,
+    exporter: Option<Box<dyn Exporter>>,
+}
+
+#[derive(Default)]
+pub struct FormatRegistry {
+    formats: BTreeMap<String, Format>,
+}
+
+impl FormatRegistry {
+    /// Registry holding the formats built into the app
+    pub fn with_builtin_formats() -> Self {
+        let mut registry = Self::default();
+        registry.add_builtin(
+            "agent-markdown",
+            "Agent markdown",
+            "agent",
+            "md",
+            Some(Box::new(AgentMarkdown)),
+            Some(Box::new(AgentMarkdown)),
+        );
+        registry.add_builtin(
+            "instruction-markdown",
+            "Instruction markdown",
+            "instruction",
+            "md",
+            Some(Box::new(InstructionMarkdown)),
+            Some(Box::new(InstructionMarkdown)),
+        );
+        registry.add_builtin(
+            "copilot-instructions",
+            "Copilot instructions file",
+            "instruction",
+            "md",
+            Some(Box::new(CopilotInstructions)),
+            Some(Box::new(CopilotInstructions)),
+        );
+        registry.add_builtin(
+            "cursor-rule",
+            "Cursor rule",
+            "instruction",
+            "mdc",
+            Some(Box::new(CursorRule)),
+            Some(Box::new(CursorRule)),
+        );
+        registry.add_builtin(
+            "skill-text",
+            "Skill YAML, JSON or markdown",
+            "skill",
+            "yaml",
+            Some(Box::new(SkillText)),
+            None,
+        );
+        registry.add_builtin(
+            "character-card",
+            "Character card",
+            "agent",
+            "json",
+            Some(Box::new(CharacterCard)),
+            None,
+        );
+        registry.add_builtin(
+            "openai-assistant",
+            "OpenAI assistant",
+            "agent",
+            "json",
+            Some(Box::new(OpenAiAssistant)),
+            None,
+        );
+        registry.add_builtin(
+            "langchain-prompt",
+            "LangChain prompt",
+            "skill",
+            "json",
+            Some(Box::new(LangChainPrompt)),
+            Some(Box::new(LangChainPrompt)),
+        );
+        registry.add_builtin(
+            "mcp-tool",
+            "MCP tool definition",
+            "skill",
+            "json",
+            Some(Box::new(McpTool)),
+            Some(Box::new(McpTool)),
+        );
+        registry
+    }
+
+    fn add_builtin(
+        &mut self,
+        id: &str,
+        name: &str,
+        entity_type: &str,
+        extension: &str,
+        importer: Option<Box<dyn Importer>>,
+        exporter: Option<Box<dyn Exporter>>,
+    ) {
+        let info = SupportedFormat {
+            id: id.to_string(),
+            name: name.to_string(),
+            entity_type: entity_type.to_string(),
+            extension: extension.to_string(),
+            can_import: false,
+            can_export: false,
+        };
+        self.register(info, importer, exporter)
+            .expect("built-in format ids are unique");
+    }
+
+    /// Add a format. Ids must be unique; `can_import` and `can_export` are
+    /// filled in from the handlers given.
+    pub fn register(
+        &mut self,
+        mut info: SupportedFormat,
+        importer: Option<Box<dyn Importer>>,
+        exporter: Option<Box<dyn Exporter>>,
+    ) -> Result<(), String> {
+        if self.formats.contains_key(&info.id) {
+            return Err(format!("Format '{}' is already registered", info.id));
+        }
+        info.can_import = importer.is_some();
+        info.can_export = exporter.is_some();
+        self.formats.insert(
+            info.id.clone(),
+            Format {
+                info,
+                importer,
+                exporter,
+            },
+        );
+        Ok(())
+    }
+
+    /// Every registered format, sorted by id
+    pub fn formats(&self) -> Vec<SupportedFormat> {
+        self.formats.values().map(|f| f.info.clone()).collect()
+    }
+
+    fn get(&self, id: &str) -> Result<&Format, String> {
+        self.formats
+            .get(id)
+            .ok_or_else(|| format!("Unknown format '{}'", id))
+    }
+
+    /// The format's description and importer
+    pub fn importer(&self, id: &str) -> Result<(&SupportedFormat, &dyn Importer), String> {
+        let format = self.get(id)?;
+        match &format.importer {
+            Some(importer) => Ok((&format.info, importer.as_ref())),
+            None => Err(format!("Format '{}' can't be imported", id)),
+        }
+    }
+
+    /// The format's description and exporter
+    pub fn exporter(&self, id: &str) -> Result<(&SupportedFormat, &dyn Exporter), String> {
+        let format = self.get(id)?;
+        match &format.exporter {
+            Some(exporter) => Ok((&format.info, exporter.as_ref())),
+            None => Err(format!("Format '{}' can't be exported", id)),
+        }
+    }
+
+    pub fn import(&self, id: &str, text: &str) -> Result<Vec<LibraryItem>, String> {
+        self.importer(id)?.1.import(text)
+    }
+
+    pub fn export(&self, id: &str, item: &LibraryItem) -> Result<String, String> {
+        let (info, exporter) = self.exporter(id)?;
+        if info.entity_type != item.entity_type() {
+            return Err(format!(
+                "Format '{}' exports {}s, not {}s",
+                id,
+                info.entity_type,
+                item.entity_type()
+            ));
+        }
+        exporter.export(item)
+    }
+}
+
+/// Point an imported agent's skills named in `skill_ids` (name to saved id)
+/// at the skills saved from the same import
+pub fn link_imported_skills(item: &mut LibraryItem, skill_ids: &HashMap<String, String>) {
+    if let LibraryItem::Agent(agent) = item {
+        for reference in &mut agent.skills {
+            if let Some(id) = skill_ids.get(reference.as_str()) {
+                *reference = id.clone();
+            }
+        }
+    }
+}
+
+fn unexpected(item: &LibraryItem) -> String {
+    format!("Can't export a {} in this format", item.entity_type())
+}
+
+struct AgentMarkdown;
+
+impl Importer for AgentMarkdown {
+    fn import(&self, text: &str) -> Result<Vec<LibraryItem>, String> {
+        Ok(vec![LibraryItem::Agent(parser::parse_agent_from_markdown(
+            text,
+        )?)])
+    }
+
+    fn check(&self, text: &str) -> Vec<ParseDiagnostic> {
+        diagnostics::check_agent(text)
+    }
+}
+
+impl Exporter for AgentMarkdown {
+    fn export(&self, item: &LibraryItem) -> Result<String, String> {
+        match item {
+            LibraryItem::Agent(agent) => Ok(parser::export_agent_to_markdown_text(agent)),
+            _ => Err(unexpected(item)),
+        }
+    }
+}
+
+struct InstructionMarkdown;
+
+impl Importer for InstructionMarkdown {
+    fn import(&self, text: &str) -> Result<Vec<LibraryItem>, String> {
+        Ok(vec![LibraryItem::Instruction(
+            parser::parse_instruction_from_markdown(text)?,
+        )])
+    }
+
+    fn check(&self, text: &str) -> Vec<ParseDiagnostic> {
+        diagnostics::check_instruction(text)
+    }
+}
+
+impl Exporter for InstructionMarkdown {
+    fn export(&self, item: &LibraryItem) -> Result<String, String> {
+        match item {
+            LibraryItem::Instruction(instruction) => {
+                Ok(parser::export_instruction_to_markdown_text(instruction))
+            }
+            _ => Err(unexpected(item)),
+        }
+    }
+}
+
+struct CopilotInstructions;
+
+impl Importer for CopilotInstructions {
+    fn import(&self, text: &str) -> Result<Vec<LibraryItem>, String> {
+        Ok(vec![LibraryItem::Instruction(
+            copilot::parse_instructions_file(text),
+        )])
+    }
+}
+
+impl Exporter for CopilotInstructions {
+    fn export(&self, item: &LibraryItem) -> Result<String, String> {
+        match item {
+            LibraryItem::Instruction(instruction) => {
+                Ok(copilot::export_instructions_file(instruction))
+            }
+            _ => Err(unexpected(item)),
+        }
+    }
+}
+
+struct CursorRule;
+
+impl Importer for CursorRule {
+    fn import(&self, text: &str) -> Result<Vec<LibraryItem>, String> {
+        Ok(vec![LibraryItem::Instruction(cursor::parse_rule(text))])
+    }
+}
+
+impl Exporter for CursorRule {
+    fn export(&self, item: &LibraryItem) -> Result<String, String> {
+        match item {
+            LibraryItem::Instruction(instruction) => Ok(cursor::export_rule(instruction)),
+            _ => Err(unexpected(item)),
+        }
+    }
+}
+
+struct SkillText;
+
+impl Importer for SkillText {
+    fn import(&self, text: &str) -> Result<Vec<LibraryItem>, String> {
+        Ok(vec![LibraryItem::Skill(parser::parse_skill_from_text(
+            text,
+        )?)])
+    }
+}
+
+struct McpTool;
+
+impl Importer for McpTool {
+    fn import(&self, text: &str) -> Result<Vec<LibraryItem>, String> {
+        Ok(tool_schema::skills_from_mcp_tools_json(text)?
+            .into_iter()
+            .map(LibraryItem::Skill)
+            .collect())
+    }
+}
+
+impl Exporter for McpTool {
+    fn export(&self, item: &LibraryItem) -> Result<String, String> {
+        match item {
+            LibraryItem::Skill(skill) => {
+                let tool = tool_schema::skill_to_mcp_tool(skill)?;
+                serde_json::to_string_pretty(&tool)
+                    .map_err(|e| format!("Failed to serialize tool: {}", e))
+            }
+            _ => Err(unexpected(item)),
+        }
+    }
+}
+
+struct LangChainPrompt;
+
+impl Importer for LangChainPrompt {
+    fn import(&self, text: &str) -> Result<Vec<LibraryItem>, String> {
+        Ok(vec![LibraryItem::Skill(langchain::parse_prompt(text)?)])
+    }
+}
+
+impl Exporter for LangChainPrompt {
+    fn export(&self, item: &LibraryItem) -> Result<String, String> {
+        match item {
+            LibraryItem::Skill(skill) => {
+                let prompt = langchain::export_prompt(skill)?;
+                serde_json::to_string_pretty(&prompt)
+                    .map_err(|e| format!("Failed to serialize prompt: {}", e))
+            }
+            _ => Err(unexpected(item)),
+        }
+    }
+}
+
+struct CharacterCard;
+
+impl Importer for CharacterCard {
+    fn import(&self, text: &str) -> Result<Vec<LibraryItem>, String> {
+        Ok(vec![LibraryItem::Agent(character_card::parse_card(text)?)])
+    }
+}
+
+struct OpenAiAssistant;
+
+impl Importer for OpenAiAssistant {
+    fn import(&self, text: &str) -> Result<Vec<LibraryItem>, String> {
+        openai_assistant::parse_assistants(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Agent;
+
+    #[test]
+    fn test_builtin_formats_round_trip() {
+        let registry = FormatRegistry::with_builtin_formats();
+        let ids: Vec<String> = registry.formats().into_iter().map(|f| f.id).collect();
+        assert_eq!(
+            ids,
+            vec![
+                "agent-markdown",
+                "character-card",
+                "copilot-instructions",
+                "cursor-rule",
+                "instruction-markdown",
+                "langchain-prompt",
+                "mcp-tool",
+                "openai-assistant",
+                "skill-text"
+            ]
+        );
+
+        let text =
+            "---\nname: \"Linting\"\ncategory: \"Workflow\"\npriority: 7\n---\n\nRun clippy.";
+        let items = registry.import("instruction-markdown", text).unwrap();
+        let LibraryItem::Instruction(instruction) = &items[0] else {
+            panic!("expected an instruction");
+        };
+        assert_eq!(
+            (instruction.name.as_str(), instruction.priority),
+            ("Linting", 7)
+        );
+        assert!(registry
+            .export("instruction-markdown", &items[0])
+            .unwrap()
+            .ends_with("Run clippy."));
+
+        let agent = LibraryItem::Agent(Agent::default());
+        assert_eq!(
+            registry.export("mcp-tool", &agent),
+            Err("Format 'mcp-tool' exports skills, not agents".to_string())
+        );
+        assert!(registry.export("skill-text", &agent).is_err());
+        assert!(registry.import("docx", "").is_err());
+    }
+
+    #[test]
+    fn test_register_rejects_duplicate_ids() {
+        struct Plain;
+        impl Exporter for Plain {
+            fn export(&self, item: &LibraryItem) -> Result<String, String> {
+                Ok(item.entity_type().to_string())
+            }
+        }
+
+        let mut registry = FormatRegistry::with_builtin_formats();
+        let info = SupportedFormat {
+            id: "plain".to_string(),
+            name: "Plain text".to_string(),
+            entity_type: "agent".to_string(),
+            extension: "txt".to_string(),
+            can_import: true,
+            can_export: false,
+        };
+        registry
+            .register(info.clone(), None, Some(Box::new(Plain)))
+            .unwrap();
+        assert!(registry.register(info, None, None).is_err());
+
+        let plain = registry
+            .formats()
+            .into_iter()
+            .find(|f| f.id == "plain")
+            .unwrap();
+        assert!(!plain.can_import && plain.can_export);
+        assert!(registry.import("plain", "").is_err());
+    }
+}
@@ -0,0 +1,377 @@
+//! Sync of the library with a git repository
+//! The library is written to a directory as one file per item: agents and
+//! instructions as markdown with YAML frontmatter (the system prompt or
+//! instruction text is the body), skills as YAML. Syncing commits the files
+//! and optionally pushes them; pulling reads them back into the library.
+//! Usage counts and avatar images stay local and aren't written.
+
+use crate::models::{Agent, Instruction, Skill};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_yaml::{Mapping, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+pub const AGENTS_DIR: &str = "agents";
+pub const SKILLS_DIR: &str = "skills";
+pub const INSTRUCTIONS_DIR: &str = "instructions";
+
+/// Fields kept out of the repository, per kind of item
+const AGENT_LOCAL_FIELDS: &[&str] = &["usage_count", "last_used_at", "avatar_image_id"];
+const LOCAL_FIELDS: &[&str] = &["usage_count", "last_used_at"];
+
+/// Used for commits when git has no identity configured
+const COMMITTER_NAME: &str = "Prompt Forge";
+const COMMITTER_EMAIL: &str = "prompt-forge@localhost";
+
+pub type Library = (Vec<Agent>, Vec<Skill>, Vec<Instruction>);
+
+/// How one kind of item is stored
+struct Layout {
+    dir: &'static str,
+    extension: &'static str,
+    body_field: Option<&'static str>, // Written as the markdown body
+    local_fields: &'static [&'static str],
+}
+
+const AGENTS: Layout = Layout {
+    dir: AGENTS_DIR,
+    extension: "md",
+    body_field: Some("system_prompt"),
+    local_fields: AGENT_LOCAL_FIELDS,
+};
+const SKILLS: Layout = Layout {
+    dir: SKILLS_DIR,
+    extension: "yaml",
+    body_field: None,
+    local_fields: LOCAL_FIELDS,
+};
+const INSTRUCTIONS: Layout = Layout {
+    dir: INSTRUCTIONS_DIR,
+    extension: "md",
+    body_field: Some("content"),
+    local_fields: LOCAL_FIELDS,
+};
+
+/// Write the library to `dir`, replacing the files of items no longer in it
+pub fn write_library(
+    dir: &Path,
+    agents: &[Agent],
+    skills: &[Skill],
+    instructions: &[Instruction],
+) -> Result<(), String> {
+    write_items(dir, &AGENTS, agents, |a| &a.id)?;
+    write_items(dir, &SKILLS, skills, |s| &s.id)?;
+    write_items(dir, &INSTRUCTIONS, instructions, |i| &i.id)
+}
+
+/// Read the library from `dir`. Local fields are taken from the item with
+/// the same id in `current`, or left at their defaults for new items.
+pub fn read_library(
+    dir: &Path,
+    current: (&[Agent], &[Skill], &[Instruction]),
+) -> Result<Library, String> {
+    let (agents, skills, instructions) = current;
+    Ok((
+        read_items(dir, &AGENTS, agents, |a| &a.id)?,
+        read_items(dir, &SKILLS, skills, |s| &s.id)?,
+        read_items(dir, &INSTRUCTIONS, instructions, |i| &i.id)?,
+    ))
+}
+
+/// Text of every item file in `dir`, by path relative to it
+pub fn read_files(dir: &Path) -> Result<BTreeMap<PathBuf, String>, String> {
+    let mut files = BTreeMap::new();
+    for layout in [&AGENTS, &SKILLS, &INSTRUCTIONS] {
+        let items_dir = dir.join(layout.dir);
+        if !items_dir.exists() {
+            continue;
+        }
+        for path in item_files(&items_dir, layout)? {
+            let text = fs::read_to_string(&path)
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            if let Some(name) = path.file_name() {
+                files.insert(Path::new(layout.dir).join(name), text);
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Commit the library files in `dir`, making it a repository first if it
+/// isn't one. Returns the new commit's hash, or `None` when nothing changed.
+pub fn commit(dir: &Path, message: &str) -> Result<Option<String>, String> {
+    if !dir.join(".git").exists() {
+        git(dir, &["init", "--quiet"])?;
+    }
+    git(
+        dir,
+        &[
+            "add",
+            "--all",
+            "--",
+            AGENTS_DIR,
+            SKILLS_DIR,
+            INSTRUCTIONS_DIR,
+        ],
+    )?;
+    let staged = git(dir, &["diff", "--cached", "--name-only"])?;
+    if staged.trim().is_empty() {
+        return Ok(None);
+    }
+
+    let mut args = Vec::new();
+    if git(dir, &["config", "user.email"]).is_err() {
+        args.extend([
+            "-c".to_string(),
+            format!("user.name={}", COMMITTER_NAME),
+            "-c".to_string(),
+            format!("user.email={}", COMMITTER_EMAIL),
+        ]);
+    }
+    args.extend(["commit", "--quiet", "--message", message].map(str::to_string));
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    git(dir, &args)?;
+    git(dir, &["rev-parse", "HEAD"]).map(|hash| Some(hash.trim().to_string()))
+}
+
+/// Push the current branch to `remote`
+pub fn push(dir: &Path, remote: &str) -> Result<(), String> {
+    let branch = current_branch(dir)?;
+    git(dir, &["push", "--quiet", remote, &branch]).map(|_| ())
+}
+
+/// Fast-forward the current branch from `remote`
+pub fn pull(dir: &Path, remote: &str) -> Result<(), String> {
+    let branch = current_branch(dir)?;
+    git(dir, &["pull", "--quiet", "--ff-only", remote, &branch]).map(|_| ())
+}
+
+/// Hash of the commit checked out in `dir`
+pub fn head(dir: &Path) -> Result<String, String> {
+    git(dir, &["rev-parse", "HEAD"]).map(|hash| hash.trim().to_string())
+}
+
+fn current_branch(dir: &Path) -> Result<String, String> {
+    git(dir, &["rev-parse", "--abbrev-ref", "HEAD"]).map(|branch| branch.trim().to_string())
+}
+
+fn git(dir: &Path, args: &[&str]) -> Result<String, String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .map_err(|e| format!("Failed to run git: {}", e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!(
+            "git {} failed: {}",
+            args.iter()
+                .find(|a| !a.starts_with('-') && !a.contains('='))
+                .unwrap_or(&""),
+            stderr.trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn write_items<T: Serialize>(
+    dir: &Path,
+    layout: &Layout,
+    items: &[T],
+    id: impl Fn(&T) -> &str,
+) -> Result<(), String> {
+    let dir = dir.join(layout.dir);
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+
+    let mut written = Vec::new();
+    for item in items {
+        let name = format!("{}.{}", file_stem(id(item)), layout.extension);
+        let path = dir.join(&name);
+        let text = to_file(item, layout)?;
+        // Unchanged files are left alone so their modification times hold
+        if fs::read_to_string(&path).ok().as_deref() != Some(text.as_str()) {
+            fs::write(&path, text)
+                .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        }
+        written.push(name);
+    }
+
+    for path in item_files(&dir, layout)? {
+        let stale = path
+            .file_name()
+            .is_some_and(|name| !written.iter().any(|w| name == w.as_str()));
+        if stale {
+            fs::remove_file(&path)
+                .map_err(|e| format!("Failed to remove {}: {}", path.display(), e))?;
+        }
+    }
+    Ok(())
+}
+
+fn read_items<T: Serialize + DeserializeOwned + Default>(
+    dir: &Path,
+    layout: &Layout,
+    current: &[T],
+    id: impl Fn(&T) -> &str,
+) -> Result<Vec<T>, String> {
+    let dir = dir.join(layout.dir);
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut paths = item_files(&dir, layout)?;
+    paths.sort();
+
+    paths
+        .iter()
+        .map(|path| {
+            let text = fs::read_to_string(path)
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            from_file(&text, layout, |file_id| {
+                current.iter().find(|item| id(item) == file_id)
+            })
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+        })
+        .collect()
+}
+
+fn item_files(dir: &Path, layout: &Layout) -> Result<Vec<PathBuf>, String> {
+    let entries =
+        fs::read_dir(dir).map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+    Ok(entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == layout.extension))
+        .collect())
+}
+
+/// `id` with anything that isn't safe in a file name replaced by `-`
+pub fn file_stem(id: &str) -> String {
+    id.chars()
+        .map(|c| {
+            if c.is_alphanumeric() || "-_.".contains(c) {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect::<String>()
+        .trim_start_matches('.')
+        .to_string()
+}
+
+fn to_file<T: Serialize>(item: &T, layout: &Layout) -> Result<String, String> {
+    let Value::Mapping(mut fields) =
+        serde_yaml::to_value(item).map_err(|e| format!("Failed to serialize item: {}", e))?
+    else {
+        return Err("Items must serialize to a mapping".to_string());
+    };
+    for field in layout.local_fields {
+        fields.remove(*field);
+    }
+    let body = layout
+        .body_field
+        .and_then(|field| fields.remove(field))
+        .map(|body| body.as_str().unwrap_or_default().to_string());
+
+    let yaml =
+        serde_yaml::to_string(&fields).map_err(|e| format!("Failed to serialize item: {}", e))?;
+    Ok(match body {
+        Some(body) => format!("---\n{}---\n\n{}\n", yaml, body),
+        None => yaml,
+    })
+}
+
+fn from_file<'a, T: Serialize + DeserializeOwned + Default + 'a>(
+    text: &str,
+    layout: &Layout,
+    current: impl Fn(&str) -> Option<&'a T>,
+) -> Result<T, String> {
+    let (yaml, body) = match layout.body_field {
+        Some(_) => {
+            let rest = text
+                .strip_prefix("---\n")
+                .ok_or("The file has no frontmatter")?;
+            let (yaml, body) = rest
+                .split_once("\n---\n")
+                .ok_or("The frontmatter isn't closed")?;
+            let body = body.strip_prefix('\n').unwrap_or(body);
+            (yaml, Some(body.strip_suffix('\n').unwrap_or(body)))
+        }
+        None => (text, None),
+    };
+
+    let mut fields: Mapping = serde_yaml::from_str(yaml).map_err(|e| e.to_string())?;
+    if let (Some(field), Some(body)) = (layout.body_field, body) {
+        fields.insert(field.into(), body.into());
+    }
+
+    let id = fields.get("id").and_then(Value::as_str).unwrap_or_default();
+    let local = match current(id) {
+        Some(item) => serde_yaml::to_value(item),
+        None => serde_yaml::to_value(T::default()),
+    }
+    .map_err(|e| e.to_string())?;
+    for field in layout.local_fields {
+        if let Some(value) = local.get(*field) {
+            fields.insert((*field).into(), value.clone());
+        }
+    }
+    serde_yaml::from_value(Value::Mapping(fields)).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::SkillDefinition;
+
+    #[test]
+    fn test_library_round_trips_through_files_keeping_local_usage() {
+        let dir = std::env::temp_dir().join(format!("pf-git-sync-{}", uuid::Uuid::new_v4()));
+        let agents = vec![Agent {
+            id: "reviewer".to_string(),
+            name: "Reviewer".to_string(),
+            system_prompt: "You review code.\n\n---\n\nBe kind.\n".to_string(),
+            usage_count: 12,
+            ..Agent::default()
+        }];
+        let skills = vec![Skill {
+            id: "explain".to_string(),
+            definition: SkillDefinition::Prompt {
+                template: "Explain:\n{{code}}".to_string(),
+                variables: Vec::new(),
+                follows: Vec::new(),
+            },
+            ..Skill::default()
+        }];
+        let instructions = vec![Instruction {
+            id: "style".to_string(),
+            content: "Use rustfmt.".to_string(),
+            ..Instruction::default()
+        }];
+        write_library(&dir, &agents, &skills, &instructions).unwrap();
+
+        let agent_file = fs::read_to_string(dir.join("agents/reviewer.md")).unwrap();
+        assert!(agent_file.starts_with("---\nid: reviewer\n"));
+        assert!(agent_file.ends_with("---\n\nYou review code.\n\n---\n\nBe kind.\n\n"));
+        assert!(!agent_file.contains("usage_count"));
+
+        let (read_agents, read_skills, read_instructions) =
+            read_library(&dir, (&agents, &[], &[])).unwrap();
+        assert_eq!(read_agents[0].system_prompt, agents[0].system_prompt);
+        assert_eq!(read_agents[0].usage_count, 12);
+        assert_eq!(read_agents[0].created_at, agents[0].created_at);
+        assert!(matches!(
+            &read_skills[0].definition,
+            SkillDefinition::Prompt { template, .. } if template == "Explain:\n{{code}}"
+        ));
+        assert_eq!(read_instructions[0].content, "Use rustfmt.");
+
+        write_library(&dir, &agents, &[], &instructions).unwrap();
+        assert!(!dir.join("skills/explain.yaml").exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,613 @@
+//! Import of web and Confluence pages
+//! The page's main content is found the way reader modes do (known content
+//! containers first, then the element holding the most paragraph text) and
+//! converted to markdown: headings, lists, tables, code blocks with their
+//! language, links and Confluence info panels survive; navigation, scripts
+//! and other page chrome are dropped.
+
+use crate::code_blocks;
+use scraper::node::Element;
+use scraper::{ElementRef, Html, Node, Selector};
+use std::collections::HashMap;
+use std::time::Duration;
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// Containers that hold the article on common wiki and blog layouts,
+/// Confluence first
+const CONTENT_SELECTORS: &[&str] = &[
+    "#main-content",
+    ".wiki-content",
+    "article",
+    "main",
+    "[role=main]",
+];
+
+/// Elements that never carry content worth importing
+const SKIPPED: &[&str] = &[
+    "script", "style", "noscript", "template", "nav", "header", "footer", "aside", "form",
+    "button", "iframe", "svg", "select",
+];
+
+const BLOCKS: &[&str] = &[
+    "p",
+    "div",
+    "section",
+    "article",
+    "main",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "ul",
+    "ol",
+    "pre",
+    "blockquote",
+    "table",
+    "hr",
+    "figure",
+    "dl",
+    "dt",
+    "dd",
+    "details",
+];
+
+/// A page converted to markdown
+#[derive(Debug, Clone, PartialEq)]
+pub struct Page {
+    pub title: String,
+    pub markdown: String,
+}
+
+/// Download a page. `token` is sent as a bearer token, which is how
+/// Confluence personal access tokens authenticate.
+pub fn fetch(url: &str, token: Option<&str>) -> Result<String, String> {
+    if !(url.starts_with("http://") || url.starts_with("https://")) {
+        return Err(format!("Only http(s) URLs can be imported, got '{}'", url));
+    }
+
+    let mut request = ureq::get(url)
+        .timeout(FETCH_TIMEOUT)
+        .set("Accept", "text/html");
+    if let Some(token) = token.map(str::trim).filter(|t| !t.is_empty()) {
+        request = request.set("Authorization", &format!("Bearer {}", token));
+    }
+
+    request
+        .call()
+        .map_err(|e| format!("Failed to fetch {}: {}", url, e))?
+        .into_string()
+        .map_err(|e| format!("Failed to read {}: {}", url, e))
+}
+
+/// Extract the main content of `html` as markdown. `url` is the page's
+/// address, used to make relative links absolute.
+pub fn convert(html: &str, url: &str) -> Page {
+    let document = Html::parse_document(html);
+    let content = main_content(&document);
+    let converter = Converter { url };
+
+    let title = page_title(&document, content).unwrap_or_else(|| url.to_string());
+    let mut markdown = converter.blocks(content, "\n\n");
+
+    // The title becomes the instruction name, so don't repeat it
+    if let Some(rest) = markdown.strip_prefix(&format!("# {}", title)) {
+        if rest.is_empty() || rest.starts_with('\n') {
+            markdown = rest.trim_start().to_string();
+        }
+    }
+
+    Page { title, markdown }
+}
+
+fn selector(css: &str) -> Selector {
+    Selector::parse(css).unwrap()
+}
+
+/// Element holding the page's content: a known container, else the element
+/// whose paragraphs carry the most text (readability-style scoring)
+fn main_content(document: &Html) -> ElementRef<'_> {
+    for css in CONTENT_SELECTORS {
+        if let Some(found) = document.select(&selector(css)).next() {
+            if !text_of(found).trim().is_empty() {
+                return found;
+            }
+        }
+    }
+
+    // Each paragraph scores its parent fully and its grandparent by half
+    let mut scores: HashMap<_, f64> = HashMap::new();
+    for paragraph in document.select(&selector("p, pre, li, td")) {
+        let length = text_of(paragraph).trim().chars().count() as f64;
+        if length < 25.0 {
+            continue;
+        }
+        let mut ancestors = paragraph.ancestors().filter_map(ElementRef::wrap);
+        if let Some(parent) = ancestors.next() {
+            *scores.entry(parent.id()).or_default() += length;
+        }
+        if let Some(grandparent) = ancestors.next() {
+            *scores.entry(grandparent.id()).or_default() += length / 2.0;
+        }
+    }
+
+    scores
+        .into_iter()
+        .filter_map(|(id, score)| {
+            let element = ElementRef::wrap(document.tree.get(id)?)?;
+            Some((element, score * (1.0 - link_density(element))))
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(element, _)| element)
+        .or_else(|| document.select(&selector("body")).next())
+        .unwrap_or_else(|| document.root_element())
+}
+
+/// Share of an element's text that sits inside links
+fn link_density(element: ElementRef) -> f64 {
+    let total = text_of(element).chars().count();
+    if total == 0 {
+        return 1.0;
+    }
+    let linked: usize = element
+        .select(&selector("a"))
+        .map(|a| text_of(a).chars().count())
+        .sum();
+    linked as f64 / total as f64
+}
+
+fn page_title(document: &Html, content: ElementRef) -> Option<String> {
+    let clean = |text: String| {
+        let text = squeeze(&text);
+        Some(text.trim().to_string()).filter(|t| !t.is_empty())
+    };
+
+    document
+        .select(&selector("#title-text"))
+        .next()
+        .or_else(|| content.select(&selector("h1")).next())
+        .and_then(|heading| clean(text_of(heading)))
+        .or_else(|| {
+            document
+                .select(&selector("meta[property='og:title']"))
+                .next()
+                .and_then(|meta| clean(meta.value().attr("content")?.to_string()))
+        })
+        .or_else(|| {
+            let title = text_of(document.select(&selector("title")).next()?);
+            // "Coding Guidelines - Engineering - Confluence" → "Coding Guidelines"
+            clean(title.split(" - ").next().unwrap_or_default().to_string())
+        })
+}
+
+struct Converter<'a> {
+    url: &'a str,
+}
+
+impl Converter<'_> {
+    /// Render an element's children as blocks joined by `separator`. Loose
+    /// text and inline elements between blocks form paragraphs.
+    fn blocks(&self, element: ElementRef, separator: &str) -> String {
+        let mut blocks = Vec::new();
+        let mut inline = String::new();
+
+        for child in element.children() {
+            match child.value() {
+                Node::Text(text) => inline.push_str(&squeeze(text)),
+                Node::Element(value) if !skipped(value) => {
+                    let Some(child) = ElementRef::wrap(child) else {
+                        continue;
+                    };
+                    if BLOCKS.contains(&value.name()) {
+                        push_paragraph(&mut blocks, &mut inline);
+                        let block = self.block(child);
+                        if !block.trim().is_empty() {
+                            blocks.push(block);
+                        }
+                    } else {
+                        inline.push_str(&self.inline(child));
+                    }
+                }
+                _ => {}
+            }
+        }
+        push_paragraph(&mut blocks, &mut inline);
+
+        blocks.join(separator)
+    }
+
+    fn block(&self, element: ElementRef) -> String {
+        let value = element.value();
+        match value.name() {
+            name @ ("h1" | "h2" | "h3" | "h4" | "h5" | "h6") => {
+                let level = name[1..].parse().unwrap_or(1);
+                let text = self.inline_children(element);
+                let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+                format!("{} {}", "#".repeat(level), text)
+            }
+            "ul" => self.list(element, None),
+            "ol" => {
+                let start = value.attr("start").and_then(|s| s.parse().ok());
+                self.list(element, Some(start.unwrap_or(1)))
+            }
+            "pre" => code_block(element),
+            "blockquote" => quote(&self.blocks(element, "\n\n")),
+            "table" => self.table(element),
+            "hr" => "---".to_string(),
+            "div" if value.classes().any(|c| c == "confluence-information-macro") => {
+                self.panel(element)
+            }
+            _ => self.blocks(element, "\n\n"),
+        }
+    }
+
+    fn inline(&self, element: ElementRef) -> String {
+        let value = element.value();
+        match value.name() {
+            "br" => "\n".to_string(),
+            "strong" | "b" => wrap(&self.inline_children(element), "**"),
+            "em" | "i" => wrap(&self.inline_children(element), "*"),
+            "s" | "del" | "strike" => wrap(&self.inline_children(element), "~~"),
+            "code" | "kbd" | "tt" | "samp" => {
+                let code = squeeze(&text_of(element));
+                let code = code.trim();
+                if code.is_empty() {
+                    String::new()
+                } else if code.contains('`') {
+                    format!("`` {} ``", code)
+                } else {
+                    format!("`{}`", code)
+                }
+            }
+            "a" => {
+                let text = self.inline_children(element);
+                match value.attr("href").map(str::trim) {
+                    Some(href)
+                        if !text.trim().is_empty()
+                            && !href.is_empty()
+                            && !href.starts_with('#')
+                            && !href.starts_with("javascript:") =>
+                    {
+                        format!("[{}]({})", text.trim(), self.resolve(href))
+                    }
+                    _ => text,
+                }
+            }
+            "img" => match value.attr("src") {
+                Some(src) => format!(
+                    "![{}]({})",
+                    value.attr("alt").unwrap_or_default(),
+                    self.resolve(src)
+                ),
+                None => String::new(),
+            },
+            _ => self.inline_children(element),
+        }
+    }
+
+    fn inline_children(&self, element: ElementRef) -> String {
+        let mut text = String::new();
+        for child in element.children() {
+            match child.value() {
+                Node::Text(t) => text.push_str(&squeeze(t)),
+                Node::Element(value) if !skipped(value) => {
+                    if let Some(child) = ElementRef::wrap(child) {
+                        text.push_str(&self.inline(child));
+                    }
+                }
+                _ => {}
+            }
+        }
+        text
+    }
+
+    /// `ordered` holds the first item's number for `<ol>`. Item content is
+    /// indented under its marker so nested lists and paragraphs stay inside.
+    fn list(&self, element: ElementRef, ordered: Option<usize>) -> String {
+        let mut lines = Vec::new();
+        let items = element
+            .children()
+            .filter_map(ElementRef::wrap)
+            .filter(|child| child.value().name() == "li");
+
+        for (index, item) in items.enumerate() {
+            let marker = match ordered {
+                Some(start) => format!("{}. ", start + index),
+                None => "- ".to_string(),
+            };
+            let content = self.blocks(item, "\n");
+            for (i, line) in content.lines().enumerate() {
+                if i == 0 {
+                    lines.push(format!("{}{}", marker, line));
+                } else if line.is_empty() {
+                    lines.push(String::new());
+                } else {
+                    lines.push(format!("{}{}", " ".repeat(marker.len()), line));
+                }
+            }
+            if content.is_empty() {
+                lines.push(marker.trim_end().to_string());
+            }
+        }
+
+        lines.join("\n")
+    }
+
+    fn table(&self, element: ElementRef) -> String {
+        let rows: Vec<Vec<String>> = element
+            .select(&selector("tr"))
+            .map(|row| {
+                row.children()
+                    .filter_map(ElementRef::wrap)
+                    .filter(|cell| matches!(cell.value().name(), "th" | "td"))
+                    .map(|cell| {
+                        self.blocks(cell, " ")
+                            .split_whitespace()
+                            .collect::<Vec<_>>()
+                            .join(" ")
+                            .replace('|', "\\|")
+                    })
+                    .collect()
+            })
+            .filter(|row: &Vec<String>| !row.is_empty())
+            .collect();
+
+        let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+        if columns == 0 {
+            return String::new();
+        }
+
+        let line = |row: &[String]| {
+            let cells: Vec<&str> = (0..columns)
+                .map(|i| row.get(i).map(String::as_str).unwrap_or(""))
+                .collect();
+            format!("| {} |", cells.join(" | "))
+        };
+        let mut lines = vec![line(&rows[0]), line(&vec!["---".to_string(); columns])];
+        lines.extend(rows[1..].iter().map(|row| line(row)));
+        lines.join("\n")
+    }
+
+    /// Confluence info, note, tip and warning macros become labelled quotes
+    fn panel(&self, element: ElementRef) -> String {
+        let classes: Vec<&str> = element.value().classes().collect();
+        let label = if classes.iter().any(|c| c.ends_with("-warning")) {
+            "Warning"
+        } else if classes.iter().any(|c| c.ends_with("-note")) {
+            "Note"
+        } else if classes.iter().any(|c| c.ends_with("-tip")) {
+            "Tip"
+        } else {
+            "Info"
+        };
+        quote(&format!("**{}:** {}", label, self.blocks(element, "\n\n")))
+    }
+
+    /// Make a link target absolute relative to the page URL
+    fn resolve(&self, href: &str) -> String {
+        if href.contains("://") || href.starts_with("mailto:") || href.starts_with("data:") {
+            return href.to_string();
+        }
+        let (scheme, rest) = self.url.split_once("://").unwrap_or(("https", self.url));
+        if let Some(path) = href.strip_prefix("//") {
+            return format!("{}://{}", scheme, path);
+        }
+
+        let host = rest.split('/').next().unwrap_or(rest);
+        if href.starts_with('/') {
+            return format!("{}://{}{}", scheme, host, href);
+        }
+        let page = rest.split(['?', '#']).next().unwrap_or(rest);
+        let dir = match page.rfind('/') {
+            Some(end) => &page[..end],
+            None => page,
+        };
+        format!("{}://{}/{}", scheme, dir, href)
+    }
+}
+
+fn code_block(pre: ElementRef) -> String {
+    let code = text_of(pre);
+    let code = code.trim_matches('\n');
+    let fence = if code.contains("```") { "````" } else { "```" };
+    let language = code_language(pre)
+        .or_else(|| code_blocks::detect_language(code).map(str::to_string))
+        .unwrap_or_default();
+    format!("{}{}\n{}\n{}", fence, language, code, fence)
+}
+
+/// Language of a code block from `language-*`/`lang-*` classes (on the
+/// `<pre>` or its `<code>`) or Confluence's `brush:` parameter
+fn code_language(pre: ElementRef) -> Option<String> {
+    let code = selector("code");
+    for element in std::iter::once(pre).chain(pre.select(&code)) {
+        let value = element.value();
+        for class in value.classes() {
+            if let Some(language) = class
+                .strip_prefix("language-")
+                .or_else(|| class.strip_prefix("lang-"))
+            {
+                return Some(language.to_string());
+            }
+        }
+        if let Some(params) = value.attr("data-syntaxhighlighter-params") {
+            let brush = params
+                .split(';')
+                .filter_map(|param| param.trim().strip_prefix("brush:"))
+                .map(str::trim)
+                .next();
+            if let Some(brush) = brush.filter(|b| !b.is_empty()) {
+                return Some(brush.to_string());
+            }
+        }
+    }
+    None
+}
+
+fn quote(text: &str) -> String {
+    text.lines()
+        .map(|line| {
+            if line.is_empty() {
+                ">".to_string()
+            } else {
+                format!("> {}", line)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Surround text with an emphasis marker, keeping edge whitespace outside
+/// so `<b> bold </b>` doesn't become `** bold **`
+fn wrap(text: &str, marker: &str) -> String {
+    let trimmed = text.trim();
+    if trimmed.is_empty() {
+        return text.to_string();
+    }
+    let leading = if text.starts_with(char::is_whitespace) {
+        " "
+    } else {
+        ""
+    };
+    let trailing = if text.ends_with(char::is_whitespace) {
+        " "
+    } else {
+        ""
+    };
+    format!("{}{}{}{}{}", leading, marker, trimmed, marker, trailing)
+}
+
+fn push_paragraph(blocks: &mut Vec<String>, inline: &mut String) {
+    let paragraph = inline
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n");
+    if !paragraph.is_empty() {
+        blocks.push(paragraph);
+    }
+    inline.clear();
+}
+
+/// Collapse runs of HTML whitespace (including newlines) into single spaces
+fn squeeze(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut in_space = false;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            if !in_space {
+                out.push(' ');
+            }
+            in_space = true;
+        } else {
+            out.push(c);
+            in_space = false;
+        }
+    }
+    out
+}
+
+fn skipped(element: &Element) -> bool {
+    SKIPPED.contains(&element.name())
+        || element.attr("hidden").is_some()
+        || element.attr("aria-hidden") == Some("true")
+        || element
+            .attr("style")
+            .is_some_and(|style| style.replace(' ', "").contains("display:none"))
+}
+
+fn text_of(element: ElementRef) -> String {
+    element.text().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_confluence_page_converts_to_markdown() {
+        let html = r#"<html>
+          <head><title>Rust Guidelines - Engineering - Confluence</title></head>
+          <body>
+            <nav><a href="/">Home</a> <a href="/spaces">Spaces</a></nav>
+            <h1 id="title-text">Rust Guidelines</h1>
+            <div id="main-content" class="wiki-content">
+              <p>Prefer <strong>explicit</strong> errors. See
+                 <a href="/display/ENG/Errors">error handling</a>.</p>
+              <h2>Checklist</h2>
+              <ul>
+                <li>Run <code>cargo fmt</code></li>
+                <li>No <em>unwrap</em> in library code
+                  <ol><li>Tests are fine</li></ol>
+                </li>
+              </ul>
+              <div class="confluence-information-macro confluence-information-macro-warning">
+                <span class="aui-icon"></span>
+                <div class="confluence-information-macro-body"><p>Never log tokens.</p></div>
+              </div>
+              <div class="code panel"><div class="codeContent">
+                <pre class="syntaxhighlighter-pre"
+                     data-syntaxhighlighter-params="brush: rust; gutter: false">fn main() {
+    run()?;
+}</pre>
+              </div></div>
+              <table>
+                <tr><th>Lint</th><th>Level</th></tr>
+                <tr><td>unwrap_used</td><td>deny | warn</td></tr>
+              </table>
+              <script>track()</script>
+            </div>
+            <footer>Powered by Confluence</footer>
+          </body></html>"#;
+
+        let page = convert(html, "https://wiki.example.com/display/ENG/Rust");
+        assert_eq!(page.title, "Rust Guidelines");
+        assert_eq!(
+            page.markdown,
+            "Prefer **explicit** errors. See [error handling](https://wiki.example.com/display/ENG/Errors).\n\
+             \n\
+             ## Checklist\n\
+             \n\
+             - Run `cargo fmt`\n\
+             - No *unwrap* in library code\n  \
+             1. Tests are fine\n\
+             \n\
+             > **Warning:** Never log tokens.\n\
+             \n\
+             ```rust\n\
+             fn main() {\n    run()?;\n}\n\
+             ```\n\
+             \n\
+             | Lint | Level |\n\
+             | --- | --- |\n\
+             | unwrap_used | deny \\| warn |"
+        );
+    }
+
+    #[test]
+    fn test_content_found_by_paragraph_density() {
+        let html = r#"<html><head><title>Review etiquette | Team wiki</title></head><body>
+            <div class="sidebar"><a href="a">One link</a><a href="b">Another link</a></div>
+            <div class="body">
+              <h1>Review etiquette</h1>
+              <p>Comment on the code, never on the person who wrote it.</p>
+              <p>Suggest a concrete alternative whenever you ask for a change.</p>
+              <pre><code class="language-diff">- old
++ new</code></pre>
+            </div></body></html>"#;
+
+        let page = convert(html, "https://blog.example.com/posts/review.html");
+        assert_eq!(page.title, "Review etiquette");
+        assert_eq!(
+            page.markdown,
+            "Comment on the code, never on the person who wrote it.\n\
+             \n\
+             Suggest a concrete alternative whenever you ask for a change.\n\
+             \n\
+             ```diff\n- old\n+ new\n```"
+        );
+    }
+}
@@ -0,0 +1,98 @@
+//! Ids for new agents, skills and instructions
+//! A new item gets a readable id slugged from its name ("Code Review" becomes
+//! `code-review`), like the built-in ones. The app, MCP servers and importers
+//! can create items in one database at the same time, so an id is claimed by
+//! inserting the row: when the insert hits an id that is already taken, the
+//! next candidate (`code-review-2`, ...) is tried. Words the MCP server and
+//! releases give a meaning to are never used as ids.
+
+use crate::releases;
+use crate::resolve::slugify;
+use rusqlite::{ffi, Error as SqliteError, Result as SqliteResult};
+use uuid::Uuid;
+
+/// Ids new items never get: the built-in agent's, and path segments and
+/// arguments that MCP clients could mistake for an item
+pub const RESERVED_IDS: &[&str] = &[
+    "all",
+    "compact",
+    "current",
+    "default",
+    releases::LATEST,
+    "pinned",
+    "priority",
+    "tag",
+];
+
+const MAX_SLUG_CHARS: usize = 48;
+
+/// Numbered candidates tried before falling back to a random id
+const MAX_ATTEMPTS: usize = 100;
+
+/// Insert a new item called `name` under the first free id, returning it.
+/// `insert` is called with each candidate until one isn't taken.
+pub fn claim(name: &str, mut insert: impl FnMut(&str) -> SqliteResult<()>) -> SqliteResult<String> {
+    for id in candidates(name) {
+        match insert(&id) {
+            Ok(()) => return Ok(id),
+            Err(e) if is_taken(&e) => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    let id = random_id();
+    insert(&id).map(|_| id)
+}
+
+/// Random id for records that are never referred to by name
+pub fn random_id() -> String {
+    Uuid::new_v4().to_string()
+}
+
+/// `name` slugged, then numbered; none when the name has no letters or digits
+fn candidates(name: &str) -> impl Iterator<Item = String> {
+    let slug: String = slugify(name).chars().take(MAX_SLUG_CHARS).collect();
+    let slug = slug.trim_end_matches('-').to_string();
+    let bare = (!slug.is_empty() && !RESERVED_IDS.contains(&slug.as_str())).then(|| slug.clone());
+    let last = if slug.is_empty() { 0 } else { MAX_ATTEMPTS };
+    bare.into_iter()
+        .chain((2..=last).map(move |n| format!("{}-{}", slug, n)))
+}
+
+/// Whether an insert failed because the id is already in use
+fn is_taken(error: &SqliteError) -> bool {
+    matches!(
+        error,
+        SqliteError::SqliteFailure(e, _)
+            if e.extended_code == ffi::SQLITE_CONSTRAINT_PRIMARYKEY
+                || e.extended_code == ffi::SQLITE_CONSTRAINT_UNIQUE
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::{params, Connection};
+
+    #[test]
+    fn test_taken_and_reserved_ids_are_skipped() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute("CREATE TABLE items (id TEXT PRIMARY KEY NOT NULL)", [])
+            .unwrap();
+        let insert = |name: &str| {
+            claim(name, |id| {
+                conn.execute("INSERT INTO items (id) VALUES (?1)", params![id])
+                    .map(|_| ())
+            })
+            .unwrap()
+        };
+
+        assert_eq!(insert("Code Review"), "code-review");
+        assert_eq!(insert("code review!"), "code-review-2");
+        assert_eq!(insert("Code_Review"), "code-review-3");
+        assert_eq!(insert("Default"), "default-2");
+        assert_eq!(insert("All"), "all-2");
+
+        let untitled = insert("🚀");
+        assert!(Uuid::parse_str(&untitled).is_ok());
+    }
+}
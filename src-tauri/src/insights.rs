@@ -0,0 +1,252 @@
+//! Local-only usage insights.
+//! Counters are aggregated per day in SQLite and never leave the machine;
+//! nothing is collected unless the user opts in via settings.
+
+use crate::db::Database;
+use crate::models::{DailyInsights, DailyUsage, UsageHeatmap, WeeklySummary};
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveDate, NaiveDateTime, Timelike, Utc};
+use rusqlite::Result as SqliteResult;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy)]
+pub enum InsightMetric {
+    Edit,
+    Apply,
+    McpCall,
+    Category,
+    Variant, // Keyed `<instruction id>/<a|b>`
+}
+
+impl InsightMetric {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            InsightMetric::Edit => "edit",
+            InsightMetric::Apply => "apply",
+            InsightMetric::McpCall => "mcp_call",
+            InsightMetric::Category => "category",
+            InsightMetric::Variant => "variant",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "edit" => Some(InsightMetric::Edit),
+            "apply" => Some(InsightMetric::Apply),
+            "mcp_call" => Some(InsightMetric::McpCall),
+            "category" => Some(InsightMetric::Category),
+            "variant" => Some(InsightMetric::Variant),
+            _ => None,
+        }
+    }
+}
+
+/// Usage buckets are whole UTC hours
+const HOUR_FORMAT: &str = "%Y-%m-%dT%H";
+
+/// Longest range a heatmap covers
+pub const MAX_HEATMAP_DAYS: u32 = 366;
+
+fn enabled(db: &Database) -> bool {
+    db.get_settings()
+        .map(|s| s.insights_enabled)
+        .unwrap_or(false)
+}
+
+/// Bump today's counter for `metric` if the user has opted in
+pub fn record(db: &Database, metric: InsightMetric, key: &str) -> SqliteResult<()> {
+    if !enabled(db) {
+        return Ok(());
+    }
+
+    let today = Utc::now().date_naive().to_string();
+    db.increment_insight_counter(&today, metric.as_str(), key)
+}
+
+/// Count one use of `entity_id` in the hour of `used_at` if the user has
+/// opted in
+pub fn record_usage(db: &Database, entity_id: &str, used_at: &DateTime<Utc>) -> SqliteResult<()> {
+    if !enabled(db) {
+        return Ok(());
+    }
+
+    let hour = used_at.format(HOUR_FORMAT).to_string();
+    db.increment_usage_hour(entity_id, &hour)
+}
+
+/// Uses of each entity since `since`, to the hour. Empty unless the user has
+/// opted in, as nothing is bucketed otherwise.
+pub fn usage_since(db: &Database, since: &DateTime<Utc>) -> SqliteResult<HashMap<String, i64>> {
+    let since = since.format(HOUR_FORMAT).to_string();
+    Ok(db.get_usage_totals(&since)?.into_iter().collect())
+}
+
+/// Uses of `entity_id` over the last `days` days (including today), laid
+/// out by weekday and hour. `utc_offset_minutes` is the user's time zone, so
+/// buckets and days line up with their local clock.
+pub fn usage_heatmap(
+    db: &Database,
+    entity_id: &str,
+    days: u32,
+    utc_offset_minutes: i32,
+) -> SqliteResult<UsageHeatmap> {
+    let offset = utc_offset_minutes
+        .checked_mul(60)
+        .and_then(FixedOffset::east_opt)
+        .unwrap_or_else(|| FixedOffset::east_opt(0).unwrap());
+    let days = days.clamp(1, MAX_HEATMAP_DAYS);
+    let end = Utc::now().with_timezone(&offset).date_naive();
+    let start = end - Duration::days(days as i64 - 1);
+    // A day early covers every offset; hours outside the range are dropped below
+    let since = (start - Duration::days(1))
+        .format("%Y-%m-%dT00")
+        .to_string();
+
+    let mut hours = vec![vec![0; 24]; 7];
+    let mut daily: Vec<DailyUsage> = (0..days as i64)
+        .map(|n| DailyUsage {
+            day: (start + Duration::days(n)).to_string(),
+            count: 0,
+        })
+        .collect();
+
+    for (hour, count) in db.get_usage_hours(entity_id, &since)? {
+        let Ok(utc) = NaiveDateTime::parse_from_str(&format!("{}:00", hour), "%Y-%m-%dT%H:%M")
+        else {
+            continue;
+        };
+        let local = utc.and_utc().with_timezone(&offset);
+        let Some(entry) = usize::try_from((local.date_naive() - start).num_days())
+            .ok()
+            .and_then(|index| daily.get_mut(index))
+        else {
+            continue;
+        };
+        entry.count += count;
+        hours[local.weekday().num_days_from_monday() as usize][local.hour() as usize] += count;
+    }
+
+    Ok(UsageHeatmap {
+        entity_id: entity_id.to_string(),
+        start_day: start.to_string(),
+        end_day: end.to_string(),
+        hours,
+        total: daily.iter().map(|d| d.count).sum(),
+        days: daily,
+        updated_at: None,
+    })
+}
+
+/// How often each top-level category was applied over the last `days` days
+pub fn category_usage(db: &Database, days: i64) -> SqliteResult<HashMap<String, i64>> {
+    let start = Utc::now().date_naive() - Duration::days(days - 1);
+    let mut usage: HashMap<String, i64> = HashMap::new();
+    for (_, metric, key, count) in db.get_insight_counters(&start.to_string())? {
+        if metric == InsightMetric::Category.as_str() {
+            let top_level = key.split('/').next().unwrap_or_default().to_string();
+            *usage.entry(top_level).or_insert(0) += count;
+        }
+    }
+    Ok(usage)
+}
+
+/// Summarize the last seven days (including today)
+pub fn weekly_summary(db: &Database) -> SqliteResult<WeeklySummary> {
+    let end = Utc::now().date_naive();
+    let start = end - Duration::days(6);
+    let counters = db.get_insight_counters(&start.to_string())?;
+
+    let mut days: Vec<DailyInsights> = (0..7)
+        .map(|offset| DailyInsights {
+            day: (start + Duration::days(offset)).to_string(),
+            edits: 0,
+            applies: 0,
+            mcp_calls: 0,
+        })
+        .collect();
+    let mut categories: HashMap<String, i64> = HashMap::new();
+
+    for (day, metric, key, count) in counters {
+        if metric == InsightMetric::Category.as_str() {
+            // Subcategory paths roll up into their top-level category
+            let top_level = key.split('/').next().unwrap_or_default().to_string();
+            *categories.entry(top_level).or_insert(0) += count;
+            continue;
+        }
+
+        let Some(index) = NaiveDate::parse_from_str(&day, "%Y-%m-%d")
+            .ok()
+            .map(|d| (d - start).num_days() as usize)
+        else {
+            continue;
+        };
+        let Some(entry) = days.get_mut(index) else {
+            continue;
+        };
+
+        match metric.as_str() {
+            "edit" => entry.edits += count,
+            "apply" => entry.applies += count,
+            "mcp_call" => entry.mcp_calls += count,
+            _ => {}
+        }
+    }
+
+    let mut top_categories: Vec<(String, i64)> = categories.into_iter().collect();
+    top_categories.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    top_categories.truncate(5);
+
+    Ok(WeeklySummary {
+        start_day: start.to_string(),
+        end_day: end.to_string(),
+        total_edits: days.iter().map(|d| d.edits).sum(),
+        total_applies: days.iter().map(|d| d.applies).sum(),
+        total_mcp_calls: days.iter().map(|d| d.mcp_calls).sum(),
+        days,
+        top_categories,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_default_data;
+
+    #[test]
+    fn test_heatmap_buckets_in_local_time_and_needs_opt_in() {
+        let db = Database::open(":memory:").unwrap();
+        db.migrate().unwrap();
+        init_default_data(&db).unwrap();
+
+        let used_at = Utc::now() - Duration::days(2);
+        record_usage(&db, "agent", &used_at).unwrap();
+        assert_eq!(usage_heatmap(&db, "agent", 7, 0).unwrap().total, 0);
+
+        let mut settings = db.get_settings().unwrap();
+        settings.insights_enabled = true;
+        db.save_settings(&settings).unwrap();
+        record_usage(&db, "agent", &used_at).unwrap();
+        record_usage(&db, "agent", &used_at).unwrap();
+        record_usage(&db, "agent", &(used_at - Duration::days(30))).unwrap();
+        record_usage(&db, "other", &used_at).unwrap();
+
+        // Five and a half hours east of UTC: the bucket's hour starts at :30 locally
+        let heatmap = usage_heatmap(&db, "agent", 7, 330).unwrap();
+        let offset = FixedOffset::east_opt(330 * 60).unwrap();
+        let bucket = used_at
+            .date_naive()
+            .and_hms_opt(used_at.hour(), 0, 0)
+            .unwrap()
+            .and_utc()
+            .with_timezone(&offset);
+        assert_eq!(heatmap.total, 2);
+        assert_eq!(heatmap.days.len(), 7);
+        assert_eq!(
+            heatmap.end_day,
+            Utc::now().with_timezone(&offset).date_naive().to_string()
+        );
+        let day = bucket.date_naive().to_string();
+        assert_eq!(heatmap.days.iter().find(|d| d.day == day).unwrap().count, 2);
+        let weekday = bucket.weekday().num_days_from_monday() as usize;
+        assert_eq!(heatmap.hours[weekday][bucket.hour() as usize], 2);
+    }
+}
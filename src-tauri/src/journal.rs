@@ -0,0 +1,242 @@
+//! Operation journal
+//! Multi-step writes record what they are about to do before touching the
+//! library and clear the record once done. A record left behind by a crash
+//! or a failed step can be resumed (the operation runs again from its
+//! recorded input) or rolled back (its recorded undo is applied), so the
+//! library is never left half-written.
+
+use crate::db::{Database, ExportData};
+use crate::models::{Instruction, JournalEntry};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+/// A journaled operation. Applying and undoing must both be safe over a
+/// partial earlier run.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Operation {
+    /// Replace the whole library. `previous` is the library beforehand.
+    ReplaceLibrary {
+        data: Box<ExportData>,
+        previous: Box<ExportData>,
+    },
+    /// Add instructions that aren't in the library yet
+    AddInstructions { instructions: Vec<Instruction> },
+}
+
+impl Operation {
+    fn kind(&self) -> &'static str {
+        match self {
+            Operation::ReplaceLibrary { .. } => "replace_library",
+            Operation::AddInstructions { .. } => "add_instructions",
+        }
+    }
+
+    fn summary(&self) -> String {
+        match self {
+            Operation::ReplaceLibrary { data, .. } => format!(
+                "Import {} agents, {} skills and {} instructions",
+                data.agents.len(),
+                data.skills.len(),
+                data.instructions.len()
+            ),
+            Operation::AddInstructions { instructions } => {
+                format!("Import {} instructions", instructions.len())
+            }
+        }
+    }
+
+    fn apply(&self, db: &Database) -> Result<(), String> {
+        match self {
+            Operation::ReplaceLibrary { data, .. } => db
+                .import_all(data)
+                .map_err(|e| format!("Failed to import data: {}", e)),
+            Operation::AddInstructions { instructions } => {
+                for instruction in instructions {
+                    let exists = db
+                        .get_instruction(&instruction.id)
+                        .map_err(|e| format!("Failed to get instruction: {}", e))?
+                        .is_some();
+                    if !exists {
+                        db.insert_instruction(instruction)
+                            .map_err(|e| format!("Failed to import instruction: {}", e))?;
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn undo(&self, db: &Database) -> Result<(), String> {
+        match self {
+            Operation::ReplaceLibrary { previous, .. } => db
+                .import_all(previous)
+                .map_err(|e| format!("Failed to restore the previous library: {}", e)),
+            Operation::AddInstructions { instructions } => {
+                for instruction in instructions {
+                    db.delete_instruction(&instruction.id)
+                        .map_err(|e| format!("Failed to delete instruction: {}", e))?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Journal `op`, apply it and clear the entry. If a step fails the entry is
+/// kept as interrupted so the user can resume or roll it back.
+pub fn run(db: &Database, op: &Operation) -> Result<(), String> {
+    let entry = begin(db, op)?;
+    finish(db, &entry.id, op.apply(db))
+}
+
+/// Record `op` as running
+fn begin(db: &Database, op: &Operation) -> Result<JournalEntry, String> {
+    let entry = JournalEntry {
+        id: uuid::Uuid::new_v4().to_string(),
+        kind: op.kind().to_string(),
+        summary: op.summary(),
+        status: "running".to_string(),
+        started_at: Utc::now(),
+        updated_at: Utc::now(),
+    };
+    let payload =
+        serde_json::to_string(op).map_err(|e| format!("Failed to serialize operation: {}", e))?;
+    db.insert_journal_entry(&entry, &payload)
+        .map_err(|e| format!("Failed to write the operation journal: {}", e))?;
+    Ok(entry)
+}
+
+fn finish(db: &Database, id: &str, result: Result<(), String>) -> Result<(), String> {
+    match result {
+        Ok(()) => db
+            .delete_journal_entry(id)
+            .map_err(|e| format!("Failed to clear the operation journal: {}", e)),
+        Err(e) => {
+            let _ = db.set_journal_status(id, "interrupted");
+            Err(e)
+        }
+    }
+}
+
+/// Mark entries still running from an earlier process as interrupted.
+/// Called once at startup, before anything new is journaled.
+pub fn recover(db: &Database) -> Result<Vec<JournalEntry>, String> {
+    db.interrupt_running_journal_entries()
+        .and_then(|_| db.get_journal_entries())
+        .map_err(|e| format!("Failed to read the operation journal: {}", e))
+}
+
+/// Run an interrupted operation again from its recorded input
+pub fn resume(db: &Database, id: &str) -> Result<(), String> {
+    let op = load(db, id)?;
+    db.set_journal_status(id, "running")
+        .map_err(|e| format!("Failed to update the operation journal: {}", e))?;
+    finish(db, id, op.apply(db))
+}
+
+/// Undo whatever part of an interrupted operation was applied
+pub fn roll_back(db: &Database, id: &str) -> Result<(), String> {
+    let op = load(db, id)?;
+    db.set_journal_status(id, "running")
+        .map_err(|e| format!("Failed to update the operation journal: {}", e))?;
+    finish(db, id, op.undo(db))
+}
+
+fn load(db: &Database, id: &str) -> Result<Operation, String> {
+    let entry = db
+        .get_journal_entries()
+        .map_err(|e| format!("Failed to read the operation journal: {}", e))?
+        .into_iter()
+        .find(|e| e.id == id)
+        .ok_or_else(|| format!("No journaled operation '{}'", id))?;
+    if entry.status != "interrupted" {
+        return Err(format!("'{}' is still running", entry.summary));
+    }
+
+    let payload = db
+        .get_journal_payload(id)
+        .map_err(|e| format!("Failed to read the operation journal: {}", e))?
+        .unwrap_or_default();
+    serde_json::from_str(&payload).map_err(|e| format!("Failed to read operation: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_default_data;
+
+    fn library() -> Database {
+        let db = Database::open(":memory:").unwrap();
+        db.migrate().unwrap();
+        init_default_data(&db).unwrap();
+        db
+    }
+
+    #[test]
+    fn test_interrupted_replace_can_be_rolled_back_or_resumed() {
+        let db = library();
+        let previous = db.export_all().unwrap();
+        let mut data = db.export_all().unwrap();
+        data.agents.clear();
+        data.instructions.truncate(1);
+        let op = Operation::ReplaceLibrary {
+            data: Box::new(data),
+            previous: Box::new(previous),
+        };
+
+        // Crash after the library was cleared, before anything was re-added
+        let entry = begin(&db, &op).unwrap();
+        let mut emptied = db.export_all().unwrap();
+        emptied.agents.clear();
+        emptied.instructions.clear();
+        db.import_all(&emptied).unwrap();
+        assert!(resume(&db, &entry.id)
+            .unwrap_err()
+            .contains("still running"));
+
+        let interrupted = recover(&db).unwrap();
+        assert_eq!(interrupted.len(), 1);
+        assert_eq!(interrupted[0].status, "interrupted");
+
+        roll_back(&db, &entry.id).unwrap();
+        assert_eq!(db.get_all_agents().unwrap().len(), 1);
+        assert!(db.get_journal_entries().unwrap().is_empty());
+
+        let entry = begin(&db, &op).unwrap();
+        recover(&db).unwrap();
+        resume(&db, &entry.id).unwrap();
+        assert!(db.get_all_agents().unwrap().is_empty());
+        assert_eq!(db.get_all_instructions().unwrap().len(), 1);
+        assert!(db.get_journal_entries().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_partial_instruction_import_resumes_without_duplicates() {
+        let db = library();
+        let before = db.get_all_instructions().unwrap().len();
+        let instructions: Vec<Instruction> = (0..3)
+            .map(|n| Instruction {
+                id: format!("notion-{}", n),
+                name: format!("Page {}", n),
+                ..Instruction::default()
+            })
+            .collect();
+        let op = Operation::AddInstructions { instructions };
+
+        let entry = begin(&db, &op).unwrap();
+        if let Operation::AddInstructions { instructions } = &op {
+            db.insert_instruction(&instructions[0]).unwrap();
+        }
+        recover(&db).unwrap();
+
+        resume(&db, &entry.id).unwrap();
+        assert_eq!(db.get_all_instructions().unwrap().len(), before + 3);
+
+        let entry = begin(&db, &op).unwrap();
+        recover(&db).unwrap();
+        roll_back(&db, &entry.id).unwrap();
+        assert_eq!(db.get_all_instructions().unwrap().len(), before);
+        assert!(roll_back(&db, "missing").is_err());
+    }
+}
@@ -0,0 +1,306 @@
+//! The JSON Schema subset tool parameters are checked with
+//! Besides its type, a parameter can be constrained with `enum`, `minimum`
+//! and `maximum` (and their exclusive forms), `minLength`, `maxLength`,
+//! `pattern`, `items`, `minItems`, `maxItems`, `uniqueItems`, and for
+//! objects `properties`, `required` and `additionalProperties`, nested to
+//! any depth. Other keywords are kept in the schema clients see but not
+//! checked here.
+
+use regex::Regex;
+use serde_json::Value;
+
+/// JSON Schema types a schema may declare
+pub const TYPES: [&str; 7] = [
+    "string", "number", "integer", "boolean", "array", "object", "null",
+];
+
+/// Problems that make `schema` unusable, each prefixed with `path`
+pub fn schema_problems(schema: &Value, path: &str, problems: &mut Vec<String>) {
+    let Some(schema) = schema.as_object() else {
+        problems.push(format!("{}: schema must be an object", path));
+        return;
+    };
+    let mut problem = |message: String| problems.push(format!("{}: {}", path, message));
+
+    match schema.get("type") {
+        None => {}
+        Some(Value::String(t)) if TYPES.contains(&t.as_str()) => {}
+        Some(Value::Array(types))
+            if types
+                .iter()
+                .all(|t| t.as_str().is_some_and(|t| TYPES.contains(&t))) => {}
+        Some(t) => problem(format!("unknown type {}", t)),
+    }
+    if let Some(values) = schema.get("enum") {
+        if values.as_array().is_none_or(|values| values.is_empty()) {
+            problem("enum must be a non-empty list".to_string());
+        }
+    }
+    for (low, high) in [
+        ("minimum", "maximum"),
+        ("exclusiveMinimum", "exclusiveMaximum"),
+    ] {
+        let (min, max) = (schema.get(low), schema.get(high));
+        for (key, value) in [(low, min), (high, max)] {
+            if value.is_some_and(|v| !v.is_number()) {
+                problem(format!("{} must be a number", key));
+            }
+        }
+        if let (Some(min), Some(max)) = (min.and_then(Value::as_f64), max.and_then(Value::as_f64)) {
+            if min > max {
+                problem(format!("{} is above {}", low, high));
+            }
+        }
+    }
+    for (low, high) in [("minLength", "maxLength"), ("minItems", "maxItems")] {
+        let (min, max) = (schema.get(low), schema.get(high));
+        for (key, value) in [(low, min), (high, max)] {
+            if value.is_some_and(|v| !v.is_u64()) {
+                problem(format!("{} must be a whole number of at least 0", key));
+            }
+        }
+        if let (Some(min), Some(max)) = (min.and_then(Value::as_u64), max.and_then(Value::as_u64)) {
+            if min > max {
+                problem(format!("{} is above {}", low, high));
+            }
+        }
+    }
+    if let Some(pattern) = schema.get("pattern") {
+        match pattern.as_str().map(Regex::new) {
+            Some(Ok(_)) => {}
+            Some(Err(e)) => problem(format!("pattern is not a valid regex: {}", e)),
+            None => problem("pattern must be a string".to_string()),
+        }
+    }
+    if let Some(required) = schema.get("required") {
+        if !required
+            .as_array()
+            .is_some_and(|names| names.iter().all(Value::is_string))
+        {
+            problem("required must be a list of property names".to_string());
+        }
+    }
+
+    if let Some(items) = schema.get("items") {
+        schema_problems(items, &format!("{}[]", path), problems);
+    }
+    match schema.get("properties") {
+        None => {}
+        Some(Value::Object(properties)) => {
+            for (name, property) in properties {
+                schema_problems(property, &format!("{}.{}", path, name), problems);
+            }
+        }
+        Some(_) => problems.push(format!("{}: properties must be an object", path)),
+    }
+    match schema.get("additionalProperties") {
+        None | Some(Value::Bool(_)) => {}
+        Some(extra) => schema_problems(extra, &format!("{}.*", path), problems),
+    }
+}
+
+/// Ways `value` breaks `schema`, each naming the offending `path`. Null
+/// stands for a value left out and always passes.
+pub fn value_problems(value: &Value, schema: &Value, path: &str, problems: &mut Vec<String>) {
+    if value.is_null() {
+        return;
+    }
+    let Some(schema) = schema.as_object() else {
+        return;
+    };
+
+    let types: Vec<&str> = match schema.get("type") {
+        Some(Value::String(t)) => vec![t.as_str()],
+        Some(Value::Array(types)) => types.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    };
+    if !types.is_empty() && !types.iter().any(|t| is_type(value, t)) {
+        problems.push(format!("'{}' must be of type {}", path, types.join(" or ")));
+        return;
+    }
+    if let Some(Value::Array(allowed)) = schema.get("enum") {
+        if !allowed.contains(value) {
+            let allowed: Vec<String> = allowed.iter().map(Value::to_string).collect();
+            problems.push(format!("'{}' must be one of {}", path, allowed.join(", ")));
+        }
+    }
+    let bound = |key: &str| schema.get(key).and_then(Value::as_f64);
+    let count = |key: &str| schema.get(key).and_then(Value::as_u64);
+
+    match value {
+        Value::Number(number) => {
+            let number = number.as_f64().unwrap_or_default();
+            let limits = [
+                ("minimum", bound("minimum").filter(|min| number < *min)),
+                ("maximum", bound("maximum").filter(|max| number > *max)),
+                (
+                    "exclusiveMinimum",
+                    bound("exclusiveMinimum").filter(|min| number <= *min),
+                ),
+                (
+                    "exclusiveMaximum",
+                    bound("exclusiveMaximum").filter(|max| number >= *max),
+                ),
+            ];
+            for (key, limit) in limits {
+                if let Some(limit) = limit {
+                    problems.push(format!("'{}' breaks {} {}", path, key, limit));
+                }
+            }
+        }
+        Value::String(text) => {
+            let length = text.chars().count() as u64;
+            if let Some(min) = count("minLength").filter(|min| length < *min) {
+                problems.push(format!("'{}' must be at least {} characters", path, min));
+            }
+            if let Some(max) = count("maxLength").filter(|max| length > *max) {
+                problems.push(format!("'{}' must be at most {} characters", path, max));
+            }
+            let pattern = schema.get("pattern").and_then(Value::as_str);
+            if let Some(Ok(regex)) = pattern.map(Regex::new) {
+                if !regex.is_match(text) {
+                    problems.push(format!("'{}' must match {}", path, regex));
+                }
+            }
+        }
+        Value::Array(items) => {
+            let length = items.len() as u64;
+            if let Some(min) = count("minItems").filter(|min| length < *min) {
+                problems.push(format!("'{}' must have at least {} items", path, min));
+            }
+            if let Some(max) = count("maxItems").filter(|max| length > *max) {
+                problems.push(format!("'{}' must have at most {} items", path, max));
+            }
+            let unique = schema.get("uniqueItems") == Some(&Value::Bool(true));
+            if unique && (1..items.len()).any(|i| items[..i].contains(&items[i])) {
+                problems.push(format!("'{}' must not repeat items", path));
+            }
+            if let Some(item_schema) = schema.get("items") {
+                for (index, item) in items.iter().enumerate() {
+                    value_problems(item, item_schema, &format!("{}[{}]", path, index), problems);
+                }
+            }
+        }
+        Value::Object(fields) => {
+            let properties = schema.get("properties").and_then(Value::as_object);
+            for name in schema
+                .get("required")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(Value::as_str)
+            {
+                if fields.get(name).is_none_or(Value::is_null) {
+                    problems.push(format!("'{}.{}' is required", path, name));
+                }
+            }
+            for (name, field) in fields {
+                let field_path = format!("{}.{}", path, name);
+                match properties.and_then(|p| p.get(name)) {
+                    Some(property) => value_problems(field, property, &field_path, problems),
+                    None => match schema.get("additionalProperties") {
+                        Some(Value::Bool(false)) => {
+                            problems.push(format!("'{}' is not allowed", field_path))
+                        }
+                        Some(extra @ Value::Object(_)) => {
+                            value_problems(field, extra, &field_path, problems)
+                        }
+                        _ => {}
+                    },
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Whether `value` is of JSON Schema type `expected`
+pub fn is_type(value: &Value, expected: &str) -> bool {
+    match expected {
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "boolean" => value.is_boolean(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        "null" => value.is_null(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn problems(value: Value, schema: Value) -> Vec<String> {
+        let mut problems = Vec::new();
+        value_problems(&value, &schema, "options", &mut problems);
+        problems
+    }
+
+    #[test]
+    fn test_nested_values_are_checked() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "mode": { "type": "string", "enum": ["fast", "thorough"] },
+                "depth": { "type": "integer", "minimum": 1, "maximum": 5 },
+                "paths": {
+                    "type": "array",
+                    "items": { "type": "string", "pattern": "^src/" },
+                    "maxItems": 2
+                }
+            },
+            "required": ["mode"],
+            "additionalProperties": false
+        });
+
+        assert!(problems(
+            json!({ "mode": "fast", "paths": ["src/db.rs"] }),
+            schema.clone()
+        )
+        .is_empty());
+        assert_eq!(
+            problems(
+                json!({ "depth": 9, "paths": ["docs/a.md"], "verbose": true }),
+                schema.clone()
+            ),
+            [
+                "'options.mode' is required",
+                "'options.depth' breaks maximum 5",
+                "'options.paths[0]' must match ^src/",
+                "'options.verbose' is not allowed",
+            ]
+        );
+        assert_eq!(
+            problems(json!({ "mode": "slow", "depth": 2.5 }), schema),
+            [
+                "'options.depth' must be of type integer",
+                "'options.mode' must be one of \"fast\", \"thorough\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_broken_schemas_are_reported() {
+        let mut found = Vec::new();
+        schema_problems(
+            &json!({
+                "minimum": 10,
+                "maximum": 1,
+                "enum": [],
+                "items": { "type": "text", "pattern": "(" },
+                "properties": { "size": { "minLength": -1 } }
+            }),
+            "limit",
+            &mut found,
+        );
+        assert_eq!(found.len(), 5, "{:?}", found);
+        assert!(found.contains(&"limit: minimum is above maximum".to_string()));
+        assert!(found.contains(&"limit[]: unknown type \"text\"".to_string()));
+        assert!(found
+            .iter()
+            .any(|p| p.starts_with("limit.size: minLength must be")));
+    }
+}
@@ -0,0 +1,271 @@
+//! LangChain prompt templates
+//! LangChain saves a `PromptTemplate` as JSON or YAML with its `template`,
+//! `input_variables` and `template_format`; LangSmith's hub wraps the same
+//! fields in an `lc` constructor object. Both import as prompt skills.
+//! Exports use LangChain's default `f-string` format, where placeholders are
+//! `{name}` and literal braces are doubled, with variable defaults as
+//! `partial_variables` and the skill's name and descriptions in `metadata`.
+
+use crate::models::{Skill, SkillDefinition, SkillType, TemplateVariable};
+use crate::template;
+use regex::Regex;
+use serde_json::{json, Map, Value};
+
+/// The LangChain prompt for a prompt skill
+pub fn export_prompt(skill: &Skill) -> Result<Value, String> {
+    let SkillDefinition::Prompt {
+        template,
+        variables,
+        ..
+    } = &skill.definition
+    else {
+        return Err(format!(
+            "'{}' is a tool skill; only prompt skills export as LangChain prompts",
+            skill.name
+        ));
+    };
+
+    let mut input_variables = Vec::new();
+    let mut partial_variables = Map::new();
+    let mut descriptions = Map::new();
+    for variable in template::variables(template, variables) {
+        if !variable.description.is_empty() {
+            descriptions.insert(variable.name.clone(), json!(variable.description));
+        }
+        match variable.default {
+            Some(default) => {
+                partial_variables.insert(variable.name, json!(default));
+            }
+            None => input_variables.push(variable.name),
+        }
+    }
+
+    Ok(json!({
+        "_type": "prompt",
+        "input_variables": input_variables,
+        "partial_variables": partial_variables,
+        "template": to_f_string(template),
+        "template_format": "f-string",
+        "metadata": {
+            "name": skill.name,
+            "description": skill.description,
+            "variable_descriptions": descriptions,
+        },
+    }))
+}
+
+/// The prompt skill in a LangChain prompt file, JSON or YAML
+pub fn parse_prompt(text: &str) -> Result<Skill, String> {
+    let value: Value = serde_yaml::from_str(text)
+        .map_err(|e| format!("Failed to parse LangChain prompt: {}", e))?;
+    let prompt = match value.get("kwargs") {
+        Some(kwargs) if value.get("lc").is_some() => {
+            let class = value["id"]
+                .as_array()
+                .and_then(|id| id.last())
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            if class != "PromptTemplate" {
+                return Err(format!(
+                    "Only a PromptTemplate can be imported, not {}",
+                    class
+                ));
+            }
+            kwargs
+        }
+        _ => &value,
+    };
+    if let Some(kind) = prompt.get("_type").and_then(Value::as_str) {
+        if kind != "prompt" {
+            return Err(format!(
+                "Only `prompt` templates can be imported, not `{}`",
+                kind
+            ));
+        }
+    }
+
+    let Some(raw) = prompt["template"].as_str() else {
+        return Err(if prompt.get("template_path").is_some() {
+            "Prompts kept in a template_path file can't be imported; inline the template"
+        } else {
+            "LangChain prompt has no template"
+        }
+        .to_string());
+    };
+    let template = match prompt["template_format"].as_str().unwrap_or("f-string") {
+        "f-string" => from_f_string(raw),
+        // Both already write placeholders as `{{name}}`
+        "mustache" | "jinja2" => raw.to_string(),
+        other => return Err(format!("Unknown template_format '{}'", other)),
+    };
+
+    let metadata = &prompt["metadata"];
+    let description = |name: &str| {
+        metadata["variable_descriptions"][name]
+            .as_str()
+            .unwrap_or_default()
+            .to_string()
+    };
+    let mut variables: Vec<TemplateVariable> = prompt["input_variables"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .map(|name| TemplateVariable {
+            name: name.to_string(),
+            description: description(name),
+            default: None,
+        })
+        .collect();
+    for (name, value) in prompt["partial_variables"]
+        .as_object()
+        .into_iter()
+        .flatten()
+    {
+        variables.push(TemplateVariable {
+            name: name.clone(),
+            description: description(name),
+            default: Some(
+                value
+                    .as_str()
+                    .map_or_else(|| value.to_string(), str::to_string),
+            ),
+        });
+    }
+    template::validate_variables(&variables)?;
+
+    let mut skill = Skill {
+        skill_type: SkillType::Prompt,
+        definition: SkillDefinition::Prompt {
+            template,
+            variables,
+            follows: Vec::new(),
+        },
+        ..Skill::default()
+    };
+    let name = metadata["name"].as_str().or(prompt["name"].as_str());
+    if let Some(name) = name.filter(|name| !name.trim().is_empty()) {
+        skill.name = name.trim().to_string();
+    }
+    if let Some(description) = metadata["description"].as_str().filter(|d| !d.is_empty()) {
+        skill.description = description.to_string();
+    }
+    Ok(skill)
+}
+
+/// `{{name}}` placeholders as `{name}`, with every other brace doubled.
+/// Workspace placeholders such as `{{workspace.root}}` stay literal text.
+fn to_f_string(template: &str) -> String {
+    let placeholder = Regex::new(r"\{\{\s*([A-Za-z_][A-Za-z0-9_\-]*)\s*\}\}").unwrap();
+    let escape = |text: &str| text.replace('{', "{{").replace('}', "}}");
+    let mut output = String::new();
+    let mut last = 0;
+    for caps in placeholder.captures_iter(template) {
+        let whole = caps.get(0).unwrap();
+        output.push_str(&escape(&template[last..whole.start()]));
+        output.push_str(&format!("{{{}}}", &caps[1]));
+        last = whole.end();
+    }
+    output.push_str(&escape(&template[last..]));
+    output
+}
+
+/// The reverse of `to_f_string`. Format specs such as `{score:.2f}` are
+/// dropped, since templates fill in text as it is.
+fn from_f_string(template: &str) -> String {
+    let mut output = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find(['{', '}']) {
+        output.push_str(&rest[..start]);
+        let tail = &rest[start..];
+        if tail.starts_with("{{") || tail.starts_with("}}") {
+            output.push_str(&tail[..1]);
+            rest = &tail[2..];
+        } else if let Some(end) = tail.find('}').filter(|_| tail.starts_with('{')) {
+            let field = &tail[1..end];
+            let name = field.split([':', '!']).next().unwrap_or_default().trim();
+            output.push_str(&format!("{{{{{}}}}}", name));
+            rest = &tail[end + 1..];
+        } else {
+            output.push_str(&tail[..1]);
+            rest = &tail[1..];
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prompt_round_trip() {
+        let skill = Skill {
+            name: "Summarize".to_string(),
+            description: "Summarize a document".to_string(),
+            definition: SkillDefinition::Prompt {
+                template: "Summarize {{text}} in {{ style }} as JSON like {\"summary\": \"...\"} for {{workspace.root}}".to_string(),
+                variables: vec![TemplateVariable {
+                    name: "style".to_string(),
+                    description: "Writing style".to_string(),
+                    default: Some("bullet points".to_string()),
+                }],
+                follows: Vec::new(),
+            },
+            ..Skill::default()
+        };
+
+        let exported = export_prompt(&skill).unwrap();
+        assert_eq!(
+            exported["template"],
+            "Summarize {text} in {style} as JSON like {{\"summary\": \"...\"}} for {{{{workspace.root}}}}"
+        );
+        assert_eq!(exported["input_variables"], json!(["text"]));
+        assert_eq!(
+            exported["partial_variables"],
+            json!({ "style": "bullet points" })
+        );
+
+        let imported = parse_prompt(&exported.to_string()).unwrap();
+        assert_eq!(imported.name, "Summarize");
+        assert_eq!(imported.description, "Summarize a document");
+        let SkillDefinition::Prompt {
+            template,
+            variables,
+            ..
+        } = &imported.definition
+        else {
+            panic!("expected a prompt skill");
+        };
+        assert_eq!(
+            template,
+            "Summarize {{text}} in {{style}} as JSON like {\"summary\": \"...\"} for {{workspace.root}}"
+        );
+        assert_eq!(variables[1].default.as_deref(), Some("bullet points"));
+        assert_eq!(variables[1].description, "Writing style");
+    }
+
+    #[test]
+    fn test_yaml_and_hub_prompts() {
+        let yaml = "_type: prompt\ninput_variables: [adjective]\ntemplate: Tell me a {adjective:>10} joke.\n";
+        let skill = parse_prompt(yaml).unwrap();
+        assert!(matches!(&skill.definition,
+            SkillDefinition::Prompt { template, .. } if template == "Tell me a {{adjective}} joke."));
+
+        let hub = json!({
+            "lc": 1,
+            "type": "constructor",
+            "id": ["langchain", "prompts", "prompt", "PromptTemplate"],
+            "kwargs": {
+                "input_variables": ["question"],
+                "template": "Answer {{question}}",
+                "template_format": "mustache"
+            }
+        });
+        assert!(parse_prompt(&hub.to_string()).is_ok());
+
+        assert!(parse_prompt("_type: few_shot\ntemplate: x").is_err());
+        assert!(parse_prompt("_type: prompt\ntemplate_path: joke.txt").is_err());
+    }
+}
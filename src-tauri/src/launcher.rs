@@ -0,0 +1,137 @@
+//! Stable MCP launcher
+//! Client configs outlive the executable path they were written with: an
+//! AppImage runs from a new mount on every launch, and updates or moving the
+//! app change the path too. Configs therefore run a small script in the app
+//! data directory that execs the installed app; the app rewrites it on every
+//! start, so it follows updates. Windows installs keep a fixed path, so
+//! configs there run the executable directly.
+
+use crate::setup;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// File name of the launcher script
+pub const LAUNCHER_NAME: &str = "prompt-forge-mcp";
+
+/// Where the launcher lives for the data directory `data_dir`
+pub fn launcher_path(data_dir: &Path) -> PathBuf {
+    data_dir.join("bin").join(LAUNCHER_NAME)
+}
+
+/// The installed app: the AppImage file when running from one, since the
+/// running binary sits in a mount that changes on every launch
+pub fn target_exe() -> Result<PathBuf, String> {
+    if let Some(appimage) = std::env::var_os("APPIMAGE").filter(|p| !p.is_empty()) {
+        return Ok(PathBuf::from(appimage));
+    }
+    std::env::current_exe().map_err(|e| format!("Failed to get exe path: {}", e))
+}
+
+/// The launcher script running `target`
+pub fn script(target: &Path) -> String {
+    format!(
+        "#!/bin/sh\n# Written by Prompt Forge on every start; runs the installed app\nexec {} \"$@\"\n",
+        shell_quote(&target.to_string_lossy())
+    )
+}
+
+fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+/// The executable a launcher script runs
+pub fn launcher_target(launcher: &Path) -> Option<PathBuf> {
+    let text = fs::read_to_string(launcher).ok()?;
+    let quoted = text
+        .lines()
+        .find_map(|line| line.strip_prefix("exec "))?
+        .strip_suffix(" \"$@\"")?;
+    let inner = quoted.strip_prefix('\'')?.strip_suffix('\'')?;
+    Some(PathBuf::from(inner.replace(r"'\''", "'")))
+}
+
+/// Write or refresh the launcher for `target` and return the command client
+/// configs should run. A launcher already pointing at an installed app is
+/// kept when `target` is somewhere temporary, like a translocated copy.
+pub fn install(data_dir: &Path, target: &Path) -> Result<PathBuf, String> {
+    if cfg!(windows) {
+        return Ok(target.to_path_buf());
+    }
+    let path = launcher_path(data_dir);
+    if setup::exe_path_problem(target).is_some()
+        && launcher_target(&path).is_some_and(|current| current != target && current.is_file())
+    {
+        return Ok(path);
+    }
+
+    let script = script(target);
+    if fs::read_to_string(&path).ok().as_deref() != Some(script.as_str()) {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        fs::write(&path, script)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755))
+            .map_err(|e| format!("Failed to make {} executable: {}", path.display(), e))?;
+    }
+    Ok(path)
+}
+
+/// The launcher for the database at `db_path`, refreshed for the running app
+pub fn mcp_command(db_path: &Path) -> Result<PathBuf, String> {
+    let data_dir = db_path
+        .parent()
+        .ok_or_else(|| format!("{} has no parent directory", db_path.display()))?;
+    install(data_dir, &target_exe()?)
+}
+
+/// The executable a registered `command` ends up running
+pub fn resolve_command(command: &Path) -> PathBuf {
+    launcher_target(command).unwrap_or_else(|| command.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_launcher_follows_target_but_not_to_temporary_copies() {
+        let dir = std::env::temp_dir().join(format!("pf-launcher-{}", uuid::Uuid::new_v4()));
+        let app = dir.join("Prompt Forge's app").join("prompt-forge");
+        fs::create_dir_all(app.parent().unwrap()).unwrap();
+        fs::write(&app, "#!/bin/sh\n[ \"$1\" = --mcp ]\n").unwrap();
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&app, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let launcher = install(&dir, &app).unwrap();
+        assert_eq!(launcher, launcher_path(&dir));
+        assert_eq!(launcher_target(&launcher), Some(app.clone()));
+        assert_eq!(resolve_command(&launcher), app);
+        assert_eq!(resolve_command(&app), app);
+
+        let output = std::process::Command::new(&launcher)
+            .arg("--mcp")
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+
+        // A translocated copy doesn't replace the working install
+        let translocated = Path::new("/private/var/folders/x/AppTranslocation/1/pf");
+        install(&dir, translocated).unwrap();
+        assert_eq!(launcher_target(&launcher), Some(app.clone()));
+
+        // An update that moves the app does
+        let moved = dir.join("moved");
+        fs::rename(&app, &moved).unwrap();
+        install(&dir, &moved).unwrap();
+        assert_eq!(launcher_target(&launcher), Some(moved));
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
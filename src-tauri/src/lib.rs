@@ -1,28 +1,136 @@
 // Prompt Forge - A local agent/skill/instruction management UI with MCP server
+// Library code only reached from the app's commands is unused in MCP-only builds
+#![cfg_attr(not(feature = "gui"), allow(dead_code))]
 
+#[cfg(feature = "gui")]
+mod actions;
+mod activation;
+mod assembly;
+mod backup;
+mod bulk;
+mod cache;
+mod capabilities;
+mod categories;
+mod character_card;
+mod chatgpt;
+mod cli;
+mod code_blocks;
+mod collections;
+#[cfg(feature = "gui")]
 mod commands;
+mod composition;
+mod conflicts;
+mod copilot;
+mod cursor;
 pub mod db;
+mod diagnostics;
+mod encryption;
+mod experiments;
+mod folder_sync;
+#[cfg(test)]
+mod format_snapshots;
+mod formats;
+mod git_sync;
+#[cfg(feature = "gui")]
+mod html_import;
+mod ids;
+mod insights;
+mod journal;
+mod json_schema;
+mod langchain;
+mod launcher;
+mod library_diff;
+mod library_workspaces;
+mod listing;
+#[cfg(feature = "gui")]
+mod llm;
+mod localization;
+mod mcp_kit;
+mod mcp_log;
+mod mcp_repl;
 pub mod mcp_server;
 mod models;
+mod naming;
+#[cfg(feature = "gui")]
+mod notifications;
+mod notion;
+mod onboarding;
+mod openai_assistant;
 mod parser;
+mod persona_cache;
+mod preflight;
+mod priorities;
+mod prompt_export;
+mod redaction;
+mod releases;
+mod resolve;
+mod retention;
+mod reviews;
+mod scope;
+mod search;
+mod secrets;
+mod selection;
+mod self_check;
+mod setup;
+mod snippets;
+mod subagent;
+mod suggestions;
+mod support_bundle;
+mod template;
+#[cfg(feature = "gui")]
+mod testing;
+mod tool_runner;
+mod tool_schema;
+mod upstream;
+mod usage_events;
+mod usage_stats;
+mod workspace;
 
+pub use cli::{find_subcommand, run_cli};
+#[cfg(feature = "gui")]
 use commands::*;
+#[cfg(feature = "gui")]
 use db::Database;
-pub use mcp_server::run_mcp_server;
+pub use mcp_server::{run_mcp_repl, run_mcp_server};
 use std::path::PathBuf;
+#[cfg(feature = "gui")]
 use std::process::Child;
+#[cfg(feature = "gui")]
 use std::sync::{Arc, Mutex};
+#[cfg(feature = "gui")]
+use std::time::Duration;
 
 /// Application state shared across all Tauri commands
+#[cfg(feature = "gui")]
 pub struct AppState {
     pub db: Arc<Database>,
     pub db_path: PathBuf,
     pub mcp_running: Mutex<bool>,
     pub mcp_process: Mutex<Option<Child>>,
+    /// Identifies this app instance as an edit-lock owner
+    pub client_id: String,
+    /// Import and export formats, looked up by id
+    pub formats: formats::FormatRegistry,
 }
 
-/// Get the default database path for the application
-pub fn get_db_path() -> PathBuf {
+/// How often the GUI folds MCP usage events into stats
+#[cfg(feature = "gui")]
+const USAGE_DRAIN_INTERVAL: Duration = Duration::from_secs(15);
+
+/// How often the GUI checks whether a scheduled backup is due
+#[cfg(feature = "gui")]
+const BACKUP_CHECK_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+/// How often the GUI looks for changes to the library folder
+#[cfg(feature = "gui")]
+const FOLDER_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How often the GUI checks for instructions overdue for review
+#[cfg(feature = "gui")]
+const REVIEW_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Get the application's data directory, creating it if needed
+pub fn get_data_dir() -> PathBuf {
     let app_data = dirs::data_local_dir()
         .unwrap_or_else(|| PathBuf::from("."))
         .join("com.promptforge.app");
@@ -30,14 +138,113 @@ pub fn get_db_path() -> PathBuf {
     // Create directory if it doesn't exist
     std::fs::create_dir_all(&app_data).ok();
 
-    app_data.join("promptforge.db")
+    app_data
 }
 
+/// Get the database path of the active workspace
+pub fn get_db_path() -> PathBuf {
+    let app_data = get_data_dir();
+    let workspace = library_workspaces::active(&app_data);
+    library_workspaces::database_path(&app_data, &workspace)
+}
+
+/// Get the database path of the workspace `name`, which must exist
+pub fn get_workspace_db_path(name: &str) -> Result<PathBuf, String> {
+    library_workspaces::existing_database(&get_data_dir(), name)
+}
+
+/// Take backups on the schedule set in settings. A failure is notified once,
+/// not on every check, until a backup succeeds again.
+#[cfg(feature = "gui")]
+fn take_backups(app: tauri::AppHandle, db: Arc<Database>, db_path: PathBuf) {
+    let mut failing = false;
+    loop {
+        let schedule = db
+            .get_settings()
+            .map(|s| s.backup_schedule)
+            .unwrap_or_default();
+        let dir = schedule
+            .dir
+            .as_deref()
+            .map(PathBuf::from)
+            .unwrap_or_else(|| backup::default_dir(&db_path));
+        match backup::run_schedule(&db, &schedule, &dir) {
+            Ok(_) => failing = false,
+            Err(e) if !failing => {
+                failing = true;
+                notifications::notify(
+                    &app,
+                    &db,
+                    notifications::NotificationEvent::BackupFailed,
+                    &format!("Scheduled backup failed: {}", e),
+                );
+            }
+            Err(_) => {}
+        }
+        std::thread::sleep(BACKUP_CHECK_INTERVAL);
+    }
+}
+
+/// Notify the user whenever instructions fall overdue for review
+#[cfg(feature = "gui")]
+fn remind_reviews(app: tauri::AppHandle, db: Arc<Database>) {
+    let mut reminders = reviews::ReviewReminders::default();
+    loop {
+        match db.get_all_instructions() {
+            Ok(instructions) => {
+                if let Some(body) = reminders.check(&instructions, chrono::Utc::now()) {
+                    notifications::notify(
+                        &app,
+                        &db,
+                        notifications::NotificationEvent::ReviewDue,
+                        &body,
+                    );
+                }
+            }
+            Err(e) => eprintln!("Failed to check instruction reviews: {}", e),
+        }
+        std::thread::sleep(REVIEW_CHECK_INTERVAL);
+    }
+}
+
+/// Mirror the library to the folder set in settings, picking up edits made
+/// to its files outside the app. A failure is notified once, not on every
+/// poll, until the folder syncs again.
+#[cfg(feature = "gui")]
+fn sync_library_folder(app: tauri::AppHandle, db: Arc<Database>) {
+    let mut folder = folder_sync::FolderSync::default();
+    let mut failing = false;
+    loop {
+        let dir = db
+            .get_settings()
+            .ok()
+            .and_then(|s| s.library_folder)
+            .filter(|dir| !dir.trim().is_empty());
+        if let Some(dir) = dir {
+            match folder.poll(&db, &folder_sync::folder_path(&dir)) {
+                Ok(_) => failing = false,
+                Err(e) if !failing => {
+                    failing = true;
+                    notifications::notify(
+                        &app,
+                        &db,
+                        notifications::NotificationEvent::SyncFailed,
+                        &format!("Library folder sync failed: {}", e),
+                    );
+                }
+                Err(_) => {}
+            }
+        }
+        std::thread::sleep(FOLDER_POLL_INTERVAL);
+    }
+}
+
+#[cfg(feature = "gui")]
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     // Initialize database
     let db_path = get_db_path();
-    let db = Database::open(&db_path).expect("Failed to open database");
+    let db = encryption::open_database(&db_path).expect("Failed to open database");
 
     // Run migrations
     db.migrate().expect("Failed to run database migrations");
@@ -45,11 +252,52 @@ pub fn run() {
     // Initialize with default data if empty
     db::init_default_data(&db).expect("Failed to initialize default data");
 
+    // Operations cut short by a crash wait for the user to resume or roll back
+    match journal::recover(&db) {
+        Ok(interrupted) if !interrupted.is_empty() => {
+            eprintln!(
+                "{} interrupted operation(s) in the journal",
+                interrupted.len()
+            )
+        }
+        Ok(_) => {}
+        Err(e) => eprintln!("{}", e),
+    }
+
+    // Keep the launcher registered MCP clients run pointed at this install
+    if let Err(e) = launcher::mcp_command(&db_path) {
+        eprintln!("Failed to install the MCP launcher: {}", e);
+    }
+
+    let db = Arc::new(db);
+
+    // Fold usage recorded by MCP processes into agent stats and insights,
+    // then drop whatever is past its retention period
+    let drain_db = Arc::clone(&db);
+    std::thread::spawn(move || loop {
+        if let Err(e) = usage_events::drain(&drain_db) {
+            eprintln!("Failed to drain usage events: {}", e);
+        }
+        if let Err(e) = retention::enforce(&drain_db) {
+            eprintln!("{}", e);
+        }
+        std::thread::sleep(USAGE_DRAIN_INTERVAL);
+    });
+
+    // Scheduled backups, overdue reviews and the library folder are checked
+    // once the app handle for notifications exists
+    let backup_db = Arc::clone(&db);
+    let backup_db_path = db_path.clone();
+    let review_db = Arc::clone(&db);
+    let folder_db = Arc::clone(&db);
+
     let app_state = AppState {
-        db: Arc::new(db),
+        db,
         db_path,
         mcp_running: Mutex::new(false),
         mcp_process: Mutex::new(None),
+        client_id: uuid::Uuid::new_v4().to_string(),
+        formats: formats::FormatRegistry::with_builtin_formats(),
     };
 
     tauri::Builder::default()
@@ -59,43 +307,176 @@ pub fn run() {
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_store::Builder::default().build())
         .plugin(tauri_plugin_updater::Builder::new().build())
+        .plugin(tauri_plugin_notification::init())
+        .plugin(tauri_plugin_clipboard_manager::init())
         .manage(app_state)
+        .setup(move |app| {
+            let app = app.handle().clone();
+            let backup_app = app.clone();
+            let folder_app = app.clone();
+            std::thread::spawn(move || take_backups(backup_app, backup_db, backup_db_path));
+            let mcp_app = app.clone();
+            std::thread::spawn(move || commands::watch_mcp_server(mcp_app));
+            std::thread::spawn(move || remind_reviews(app, review_db));
+            std::thread::spawn(move || sync_library_folder(folder_app, folder_db));
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             // Agent commands
             create_agent,
             get_agents,
+            list_agents,
             get_agent,
             update_agent,
             delete_agent,
+            preview_agent_import,
             import_agent_from_text,
             export_agent_to_markdown,
+            export_agent_to_claude_code,
+            export_agent_to_openai_assistant,
+            export_agent_mcp_kit,
+            set_agent_avatar,
+            get_agent_avatar,
             // Skill commands
             create_skill,
             get_skills,
+            list_skills,
             get_skill,
             update_skill,
+            render_skill,
+            bulk_update_skills,
             delete_skill,
+            validate_tool_parameters,
+            generate_input_schema,
+            import_skills_from_mcp_tools,
+            export_skill_to_mcp_tool,
+            approve_tool_skill,
+            revoke_tool_skill,
+            get_approved_tool_skills,
             // Instruction commands
             create_instruction,
             get_instructions,
+            list_instructions,
+            get_instructions_for_context,
             get_instruction,
+            get_category_tree,
+            get_category_template,
+            list_due_for_review,
             update_instruction,
+            bulk_update_instructions,
             delete_instruction,
+            suggest_priorities,
+            apply_priorities,
+            start_experiment,
+            stop_experiment,
+            get_experiment_results,
+            preview_instruction_import,
             import_instruction_from_text,
             export_instruction_to_markdown,
+            export_instruction_to_cursor_rule,
+            export_instruction_to_copilot,
+            get_notion_databases,
+            import_notion_database,
+            import_project_instructions,
+            import_cursor_rules,
+            import_copilot_instructions,
+            import_instruction_from_url,
+            check_upstream_updates,
+            // Collection commands
+            get_collections,
+            create_collection,
+            rename_collection,
+            move_collection,
+            delete_collection,
+            add_to_collection,
+            remove_from_collection,
+            get_collection_items,
+            // Snippet commands
+            get_snippets,
+            get_snippet,
+            create_snippet,
+            update_snippet,
+            delete_snippet,
+            // Format commands
+            list_supported_formats,
+            import_with_format,
+            export_with_format,
+            import_character_card,
+            preview_chatgpt_import,
+            import_chatgpt_suggestions,
             // Settings commands
             get_settings,
             save_settings,
+            // Setup commands
+            get_template_packs,
+            first_run_setup,
             // MCP commands
             get_mcp_status,
             start_mcp_server,
             stop_mcp_server,
             // MCP tool helpers
             apply_agent,
+            preview_agent_prompt,
+            compose_and_export,
+            test_agent,
+            compare_agents,
+            compose_prompt,
             get_all_enabled_instructions,
             // Export/Import commands
             export_all_data,
+            export_selection,
             import_all_data,
+            scan_for_secrets,
+            validate_library,
+            validate_references,
+            find_instruction_conflicts,
+            preflight_library,
+            get_capability_matrix,
+            get_interrupted_operations,
+            resume_operation,
+            roll_back_operation,
+            // Diagnostics commands
+            run_diagnostics,
+            export_support_bundle,
+            // Backup commands
+            create_backup,
+            verify_backup,
+            compare_libraries,
+            // Sync commands
+            sync_to_git,
+            pull_from_git,
+            // Release commands
+            create_release,
+            get_releases,
+            delete_release,
+            set_release_channel,
+            // Translation commands
+            translate_entity,
+            get_translations,
+            update_translation,
+            approve_translation,
+            delete_translation,
+            // Onboarding commands
+            summarize_library,
+            // Encryption commands
+            get_database_encryption,
+            set_database_encryption,
+            // Workspace commands
+            list_workspaces,
+            create_workspace,
+            switch_workspace,
+            // Insight commands
+            get_weekly_summary,
+            get_usage_heatmap,
+            get_usage_stats,
+            purge_data,
+            // Command palette commands
+            query_actions,
+            run_action,
+            // Edit lock commands
+            acquire_edit_lock,
+            release_edit_lock,
+            get_edit_locks,
         ])
         .run(tauri::generate_context!())
         .expect("error while running Prompt Forge");
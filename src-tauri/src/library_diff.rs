@@ -0,0 +1,232 @@
+//! Comparing the library with another one
+//! The other library is a Prompt Forge database (a teammate's, another
+//! machine's or a backup snapshot) or an `export_all_data` file. Agents,
+//! skills and instructions are matched by id and reported when only one side
+//! has them or their content differs. Timestamps, usage counts and avatar
+//! attachment ids differ between machines for the same content, so they are
+//! left out of the comparison; `updated_at` only says which side is newer.
+
+use crate::db::{Database, ExportData};
+use crate::models::{DifferenceStatus, LibraryComparison, LibraryDifference};
+use chrono::DateTime;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::Path;
+
+/// Fields that don't count as content
+const IGNORED_FIELDS: &[&str] = &[
+    "created_at",
+    "updated_at",
+    "usage_count",
+    "last_used_at",
+    "avatar_image_id",
+];
+
+const SQLITE_HEADER: &[u8] = b"SQLite format 3\0";
+
+/// The library in a database file or JSON export at `path`. Databases are
+/// copied into memory and migrated, so older ones can be read too; `key`
+/// unlocks a database encrypted with this library's passphrase.
+pub fn read_library(path: &Path, key: Option<&str>) -> Result<ExportData, String> {
+    let bytes = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    if bytes.starts_with(SQLITE_HEADER) {
+        return read_database(path, None);
+    }
+    serde_json::from_slice(&bytes).or_else(|e| match key {
+        Some(key) => read_database(path, Some(key)),
+        None => Err(format!(
+            "{} is neither a Prompt Forge database nor an export: {}",
+            path.display(),
+            e
+        )),
+    })
+}
+
+fn read_database(path: &Path, key: Option<&str>) -> Result<ExportData, String> {
+    let db = Database::restore_in_memory(path, key)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    db.migrate()
+        .map_err(|e| format!("Failed to migrate {}: {}", path.display(), e))?;
+    db.export_all()
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+}
+
+/// Differences between the library `here` and the one `there`, agents first,
+/// each kind by name
+pub fn compare(here: &ExportData, there: &ExportData, other: &str) -> LibraryComparison {
+    let mut comparison = LibraryComparison {
+        other: other.to_string(),
+        differences: Vec::new(),
+        same: 0,
+    };
+    compare_items("agent", &here.agents, &there.agents, &mut comparison);
+    compare_items("skill", &here.skills, &there.skills, &mut comparison);
+    compare_items(
+        "instruction",
+        &here.instructions,
+        &there.instructions,
+        &mut comparison,
+    );
+    comparison
+}
+
+fn compare_items<T: Serialize>(
+    entity_type: &str,
+    here: &[T],
+    there: &[T],
+    comparison: &mut LibraryComparison,
+) {
+    let fields = |items: &[T]| -> Vec<Map<String, Value>> {
+        items
+            .iter()
+            .filter_map(|item| match serde_json::to_value(item) {
+                Ok(Value::Object(fields)) => Some(fields),
+                _ => None,
+            })
+            .collect()
+    };
+    let (here, there) = (fields(here), fields(there));
+    let text = |fields: &Map<String, Value>, key: &str| {
+        fields
+            .get(key)
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string()
+    };
+    let find = |items: &[Map<String, Value>], id: &str| {
+        items.iter().position(|fields| text(fields, "id") == id)
+    };
+
+    let mut differences = Vec::new();
+    for item in &here {
+        let id = text(item, "id");
+        let Some(other) = find(&there, &id).map(|i| &there[i]) else {
+            differences.push(difference(entity_type, item, DifferenceStatus::OnlyHere));
+            continue;
+        };
+        let changed: Vec<String> = item
+            .keys()
+            .chain(other.keys().filter(|key| !item.contains_key(*key)))
+            .filter(|key| !IGNORED_FIELDS.contains(&key.as_str()))
+            .filter(|key| item.get(*key) != other.get(*key))
+            .cloned()
+            .collect();
+        if changed.is_empty() {
+            comparison.same += 1;
+            continue;
+        }
+        let updated = |fields: &Map<String, Value>| {
+            DateTime::parse_from_rfc3339(&text(fields, "updated_at")).ok()
+        };
+        let mut entry = difference(entity_type, item, DifferenceStatus::Differs);
+        entry.fields = changed;
+        entry.newer = match (updated(item), updated(other)) {
+            (Some(here), Some(there)) if here > there => Some("here".to_string()),
+            (Some(here), Some(there)) if here < there => Some("there".to_string()),
+            _ => None,
+        };
+        differences.push(entry);
+    }
+    for item in &there {
+        if find(&here, &text(item, "id")).is_none() {
+            differences.push(difference(entity_type, item, DifferenceStatus::OnlyThere));
+        }
+    }
+
+    differences.sort_by_key(|d| d.name.to_lowercase());
+    comparison.differences.extend(differences);
+}
+
+fn difference(
+    entity_type: &str,
+    fields: &Map<String, Value>,
+    status: DifferenceStatus,
+) -> LibraryDifference {
+    let text = |key: &str| {
+        fields
+            .get(key)
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string()
+    };
+    LibraryDifference {
+        entity_type: entity_type.to_string(),
+        id: text("id"),
+        name: text("name"),
+        status,
+        fields: Vec::new(),
+        newer: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Instruction, Settings, Skill};
+    use chrono::{Duration, Utc};
+
+    fn library(skills: Vec<Skill>, instructions: Vec<Instruction>) -> ExportData {
+        ExportData {
+            agents: Vec::new(),
+            skills,
+            instructions,
+            attachments: Vec::new(),
+            collections: Vec::new(),
+            snippets: Vec::new(),
+            settings: Settings::default(),
+            exported_at: Utc::now(),
+            version: "1.0".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_only_content_differences_are_reported() {
+        let instruction = |id: &str, content: &str| Instruction {
+            id: id.to_string(),
+            name: id.to_string(),
+            content: content.to_string(),
+            ..Instruction::default()
+        };
+        let skill = Skill {
+            id: "explain".to_string(),
+            ..Skill::default()
+        };
+
+        let here = library(
+            vec![skill.clone()],
+            vec![
+                instruction("style", "Use rustfmt."),
+                instruction("tests", "Write tests."),
+            ],
+        );
+        let mut edited = instruction("style", "Use rustfmt and clippy.");
+        edited.updated_at += Duration::hours(1);
+        let there = library(
+            vec![Skill {
+                usage_count: 40,
+                updated_at: skill.updated_at + Duration::days(1),
+                ..skill
+            }],
+            vec![edited, instruction("security", "No secrets.")],
+        );
+
+        let comparison = compare(&here, &there, "laptop.db");
+        assert_eq!(comparison.same, 1);
+        let found: Vec<(&str, &DifferenceStatus)> = comparison
+            .differences
+            .iter()
+            .map(|d| (d.id.as_str(), &d.status))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("security", &DifferenceStatus::OnlyThere),
+                ("style", &DifferenceStatus::Differs),
+                ("tests", &DifferenceStatus::OnlyHere),
+            ]
+        );
+        assert_eq!(comparison.differences[1].fields, ["content"]);
+        assert_eq!(comparison.differences[1].newer.as_deref(), Some("there"));
+    }
+}
@@ -0,0 +1,161 @@
+//! Separate libraries in one install
+//! Each workspace (work, personal, a client) is a database of its own in the
+//! app data directory: `default` is the original `promptforge.db`, any other
+//! is `promptforge-<name>.db`. The workspace the app opens is named in the
+//! `active-workspace` file; MCP servers serve it as well unless started with
+//! `--workspace <name>` or `--db-path`.
+
+use crate::models::LibraryWorkspace;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The workspace holding the library from before workspaces existed
+pub const DEFAULT_WORKSPACE: &str = "default";
+
+const ACTIVE_FILE: &str = "active-workspace";
+const DB_PREFIX: &str = "promptforge";
+
+/// The database file of workspace `name` in the data directory `dir`
+pub fn database_path(dir: &Path, name: &str) -> PathBuf {
+    if name == DEFAULT_WORKSPACE {
+        dir.join(format!("{}.db", DB_PREFIX))
+    } else {
+        dir.join(format!("{}-{}.db", DB_PREFIX, name))
+    }
+}
+
+/// The workspace other than the default whose database is at `path`
+pub fn named_workspace(path: &Path) -> Option<String> {
+    let file_name = path.file_name()?.to_string_lossy();
+    let name = file_name
+        .strip_prefix(DB_PREFIX)?
+        .strip_prefix('-')?
+        .strip_suffix(".db")?;
+    let valid = name != DEFAULT_WORKSPACE && normalize_name(name).ok()? == name;
+    valid.then(|| name.to_string())
+}
+
+/// `name` as a workspace name: lowercase letters, digits and dashes
+pub fn normalize_name(name: &str) -> Result<String, String> {
+    let mut normalized = String::new();
+    for c in name.trim().to_lowercase().chars() {
+        if c.is_ascii_alphanumeric() {
+            normalized.push(c);
+        } else if !normalized.is_empty() && !normalized.ends_with('-') {
+            normalized.push('-');
+        }
+    }
+    let normalized = normalized.trim_end_matches('-').to_string();
+    if normalized.is_empty() {
+        return Err(format!("'{}' can't be used as a workspace name", name));
+    }
+    Ok(normalized)
+}
+
+/// The workspaces in `dir`, the default first and the rest by name
+pub fn list(dir: &Path) -> Vec<LibraryWorkspace> {
+    let active = active(dir);
+    let mut names: Vec<String> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| named_workspace(&entry.path()))
+        .collect();
+    names.sort();
+    names.insert(0, DEFAULT_WORKSPACE.to_string());
+
+    names
+        .into_iter()
+        .map(|name| LibraryWorkspace {
+            db_path: database_path(dir, &name).to_string_lossy().into_owned(),
+            active: name == active,
+            name,
+        })
+        .collect()
+}
+
+/// The workspace the app opens: the one last switched to while its database
+/// still exists, else the default
+pub fn active(dir: &Path) -> String {
+    fs::read_to_string(dir.join(ACTIVE_FILE))
+        .ok()
+        .and_then(|name| normalize_name(&name).ok())
+        .filter(|name| database_path(dir, name).exists())
+        .unwrap_or_else(|| DEFAULT_WORKSPACE.to_string())
+}
+
+/// The database of an existing workspace, for `--workspace`
+pub fn existing_database(dir: &Path, name: &str) -> Result<PathBuf, String> {
+    let name = normalize_name(name)?;
+    let path = database_path(dir, &name);
+    if name != DEFAULT_WORKSPACE && !path.exists() {
+        return Err(format!("No workspace named '{}'", name));
+    }
+    Ok(path)
+}
+
+/// The database a new workspace `name` is created at, once the name is
+/// checked to be free
+pub fn new_database(dir: &Path, name: &str) -> Result<(String, PathBuf), String> {
+    let name = normalize_name(name)?;
+    let path = database_path(dir, &name);
+    if name == DEFAULT_WORKSPACE || path.exists() {
+        return Err(format!("A workspace named '{}' already exists", name));
+    }
+    Ok((name, path))
+}
+
+/// Make `name` the workspace the app and MCP servers open from now on
+pub fn set_active(dir: &Path, name: &str) -> Result<(), String> {
+    let name = normalize_name(name)?;
+    existing_database(dir, &name)?;
+    fs::write(dir.join(ACTIVE_FILE), &name)
+        .map_err(|e| format!("Failed to switch to workspace '{}': {}", name, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_workspaces_are_separate_databases() {
+        let dir = std::env::temp_dir().join(format!("pf-workspaces-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("promptforge.db"), "").unwrap();
+        fs::write(dir.join("promptforge.db-wal"), "").unwrap();
+
+        assert_eq!(normalize_name(" Client X ").unwrap(), "client-x");
+        assert!(normalize_name("--").is_err());
+        assert_eq!(active(&dir), DEFAULT_WORKSPACE);
+
+        let (name, path) = new_database(&dir, "Client X").unwrap();
+        assert_eq!(path, dir.join("promptforge-client-x.db"));
+        assert_eq!(named_workspace(&path).as_deref(), Some("client-x"));
+        assert_eq!(named_workspace(&dir.join("promptforge.db")), None);
+        fs::write(&path, "").unwrap();
+        assert!(new_database(&dir, "client-x").is_err());
+        assert!(new_database(&dir, "Default").is_err());
+        assert!(set_active(&dir, "personal").is_err());
+
+        set_active(&dir, &name).unwrap();
+        let found: Vec<(String, bool)> =
+            list(&dir).into_iter().map(|w| (w.name, w.active)).collect();
+        assert_eq!(
+            found,
+            [
+                ("default".to_string(), false),
+                ("client-x".to_string(), true)
+            ]
+        );
+
+        // A removed workspace falls back to the default
+        fs::remove_file(&path).unwrap();
+        assert_eq!(active(&dir), DEFAULT_WORKSPACE);
+        assert_eq!(
+            existing_database(&dir, "default").unwrap(),
+            dir.join("promptforge.db")
+        );
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
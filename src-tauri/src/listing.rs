@@ -0,0 +1,239 @@
+//! Paged, filtered lists of agents, skills and instructions
+//! `get_agents` and friends load every row, which gets slow with thousands of
+//! items. The `list_*` commands turn a `ListQuery` into SQL instead, so the
+//! database filters, sorts and pages and only one page is read.
+
+use crate::models::ListQuery;
+use rusqlite::types::Value;
+
+/// Page size when a query sets none
+pub const DEFAULT_LIMIT: usize = 50;
+
+/// Largest page a query can ask for
+pub const MAX_LIMIT: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Table {
+    Agents,
+    Skills,
+    Instructions,
+}
+
+impl Table {
+    fn label(self) -> &'static str {
+        match self {
+            Table::Agents => "Agents",
+            Table::Skills => "Skills",
+            Table::Instructions => "Instructions",
+        }
+    }
+
+    /// The column holding the agent's prompt, skill's definition or
+    /// instruction's content
+    fn body_column(self) -> &'static str {
+        match self {
+            Table::Agents => "system_prompt",
+            Table::Skills => "definition_json",
+            Table::Instructions => "content",
+        }
+    }
+}
+
+/// A `ListQuery` as SQL clauses and their parameters
+#[derive(Debug)]
+pub struct Filter {
+    pub where_clause: String,
+    pub order_by: String,
+    pub params: Vec<Value>,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+impl Filter {
+    /// Errors name filters or sort fields `table` doesn't have
+    pub fn new(query: &ListQuery, table: Table) -> Result<Filter, String> {
+        let mut conditions = Vec::new();
+        let mut params = Vec::new();
+        let unsupported = |what: &str| format!("{} can't be filtered by {}", table.label(), what);
+
+        let tags: Vec<String> = query
+            .tags
+            .iter()
+            .map(|t| t.trim().to_lowercase())
+            .filter(|t| !t.is_empty())
+            .collect();
+        if !tags.is_empty() && table == Table::Skills {
+            return Err(unsupported("tags"));
+        }
+        for tag in tags {
+            conditions.push(
+                "EXISTS (SELECT 1 FROM json_each(tags_json) WHERE lower(value) = ?)".to_string(),
+            );
+            params.push(Value::Text(tag));
+        }
+
+        let category = query
+            .category
+            .as_deref()
+            .map(|c| c.trim().trim_matches('/'));
+        if let Some(category) = category.filter(|c| !c.is_empty()) {
+            if table != Table::Instructions {
+                return Err(unsupported("category"));
+            }
+            // The same path `categories::matches_category` compares
+            let path = "lower(category || coalesce('/' || nullif(subcategory, ''), ''))";
+            let category = category.to_lowercase();
+            conditions.push(format!("({0} = ? OR {0} LIKE ? ESCAPE '\\')", path));
+            params.push(Value::Text(category.clone()));
+            params.push(Value::Text(format!("{}/%", escape_like(&category))));
+        }
+
+        if let Some(enabled) = query.enabled {
+            if table == Table::Agents {
+                return Err(unsupported("enabled"));
+            }
+            conditions.push("enabled = ?".to_string());
+            params.push(Value::Integer(enabled.into()));
+        }
+
+        let text = query.query.as_deref().map(str::trim).unwrap_or_default();
+        if !text.is_empty() {
+            let columns = ["name", "description", table.body_column()];
+            let matches: Vec<String> = columns
+                .iter()
+                .map(|column| format!("{} LIKE ? ESCAPE '\\'", column))
+                .collect();
+            conditions.push(format!("({})", matches.join(" OR ")));
+            let pattern = format!("%{}%", escape_like(text));
+            params.extend(columns.map(|_| Value::Text(pattern.clone())));
+        }
+
+        let sort = query.sort.as_deref().map(str::trim).unwrap_or_default();
+        // Agents have always listed most used first
+        let agents_default = sort.is_empty() && table == Table::Agents;
+        let column = match sort {
+            _ if agents_default => "usage_count",
+            "" | "name" => "name COLLATE NOCASE",
+            "created_at" | "updated_at" | "usage_count" => sort,
+            "priority" if table == Table::Instructions => "priority",
+            other => return Err(format!("{} can't be sorted by '{}'", table.label(), other)),
+        };
+        let descending = query.descending || agents_default;
+        let direction = if descending { "DESC" } else { "ASC" };
+
+        Ok(Filter {
+            where_clause: if conditions.is_empty() {
+                String::new()
+            } else {
+                format!("WHERE {}", conditions.join(" AND "))
+            },
+            order_by: format!("ORDER BY {} {}, id", column, direction),
+            params,
+            offset: query.offset,
+            limit: query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT),
+        })
+    }
+}
+
+/// `text` with LIKE's wildcards taken literally, for `ESCAPE '\'`
+fn escape_like(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+    use crate::models::{Instruction, InstructionCategory};
+
+    #[test]
+    fn test_instructions_are_filtered_in_sql() {
+        let db = Database::open(":memory:").unwrap();
+        db.migrate().unwrap();
+        for (id, category, subcategory, tags, enabled) in [
+            (
+                "fmt",
+                InstructionCategory::CodeStyle,
+                Some("rust"),
+                vec!["Rust"],
+                true,
+            ),
+            (
+                "lint",
+                InstructionCategory::CodeStyle,
+                Some("rust_2021"),
+                vec!["rust"],
+                true,
+            ),
+            (
+                "ts",
+                InstructionCategory::CodeStyle,
+                None,
+                vec!["web"],
+                true,
+            ),
+            (
+                "old",
+                InstructionCategory::CodeStyle,
+                Some("rust"),
+                vec!["rust"],
+                false,
+            ),
+            ("sec", InstructionCategory::Security, None, vec![], true),
+        ] {
+            db.insert_instruction(&Instruction {
+                id: id.to_string(),
+                name: format!("{} rules", id),
+                category,
+                subcategory: subcategory.map(str::to_string),
+                tags: tags.into_iter().map(str::to_string).collect(),
+                enabled,
+                content: format!("Follow the {}% rule", id),
+                ..Instruction::default()
+            })
+            .unwrap();
+        }
+        let list = |query: ListQuery| {
+            let page = db
+                .list_instructions(&Filter::new(&query, Table::Instructions).unwrap())
+                .unwrap();
+            let ids: Vec<String> = page.items.into_iter().map(|i| i.id).collect();
+            (ids, page.total)
+        };
+
+        let rust = ListQuery {
+            category: Some("code_style/rust".to_string()),
+            ..ListQuery::default()
+        };
+        // `rust_2021` only shares a prefix with the category
+        assert_eq!(list(rust.clone()).0, ["fmt", "old"]);
+        let enabled_rust = ListQuery {
+            tags: vec!["RUST".to_string()],
+            enabled: Some(true),
+            ..ListQuery::default()
+        };
+        assert_eq!(list(enabled_rust).0, ["fmt", "lint"]);
+        let paged = ListQuery {
+            offset: 1,
+            limit: Some(2),
+            sort: Some("name".to_string()),
+            descending: true,
+            ..ListQuery::default()
+        };
+        assert_eq!(list(paged), (vec!["sec".to_string(), "old".to_string()], 5));
+        let text = ListQuery {
+            query: Some("ts%".to_string()),
+            ..ListQuery::default()
+        };
+        assert_eq!(list(text).0, ["ts"]);
+
+        assert!(Filter::new(&rust, Table::Agents).is_err());
+        let by_priority = ListQuery {
+            sort: Some("priority".to_string()),
+            ..ListQuery::default()
+        };
+        assert!(Filter::new(&by_priority, Table::Skills).is_err());
+    }
+}
@@ -0,0 +1,262 @@
+//! Minimal client for the LLM providers configured in settings
+//! Supports OpenAI-compatible chat completion APIs (OpenAI, Azure gateways,
+//! Ollama, LM Studio, ...) and the Anthropic Messages API. Only single-turn,
+//! non-streaming requests are made.
+
+use crate::models::{LlmProvider, TokenUsage};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+const CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_TOKENS: u32 = 4096;
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+/// An HTTP request to a provider's completion endpoint
+struct Request {
+    url: String,
+    headers: Vec<(&'static str, String)>,
+    body: Value,
+}
+
+/// The provider called `name`, or the first configured one
+pub fn find_provider<'a>(
+    providers: &'a [LlmProvider],
+    name: Option<&str>,
+) -> Result<&'a LlmProvider, String> {
+    match name.map(str::trim).filter(|n| !n.is_empty()) {
+        Some(name) => providers
+            .iter()
+            .find(|p| p.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| format!("LLM provider '{}' not found", name)),
+        None => providers
+            .first()
+            .ok_or_else(|| "No LLM provider configured. Add one in Settings".to_string()),
+    }
+}
+
+/// A provider's reply and the tokens it reported using
+pub struct Completion {
+    pub text: String,
+    pub usage: Option<TokenUsage>,
+}
+
+/// Send one system prompt and user message, returning the reply text
+pub fn complete(provider: &LlmProvider, system: &str, message: &str) -> Result<String, String> {
+    complete_with_usage(provider, system, message).map(|completion| completion.text)
+}
+
+/// `complete`, keeping the provider's token counts
+pub fn complete_with_usage(
+    provider: &LlmProvider,
+    system: &str,
+    message: &str,
+) -> Result<Completion, String> {
+    let Request { url, headers, body } = request(provider, system, message)?;
+
+    let mut request = ureq::post(&url).timeout(REQUEST_TIMEOUT);
+    for (name, value) in &headers {
+        request = request.set(name, value);
+    }
+    let response: Value = match request.send_json(body) {
+        Ok(response) => response
+            .into_json()
+            .map_err(|e| format!("Failed to read response from '{}': {}", provider.name, e))?,
+        Err(ureq::Error::Status(status, response)) => {
+            let detail = response.into_string().unwrap_or_default();
+            return Err(format!(
+                "LLM provider '{}' returned {}: {}",
+                provider.name,
+                status,
+                error_message(&detail)
+            ));
+        }
+        Err(e) => return Err(format!("Failed to reach '{}': {}", provider.name, e)),
+    };
+
+    Ok(Completion {
+        text: reply_text(&provider.kind, &response)?,
+        usage: token_usage(&provider.kind, &response),
+    })
+}
+
+/// Check that the provider is reachable and accepts its key by listing its
+/// models, which costs no tokens
+pub fn verify_provider(provider: &LlmProvider) -> Result<(), String> {
+    let Request { url, headers, .. } = request(provider, "", "")?;
+    let url = match provider.kind.as_str() {
+        "anthropic" => url.replace("/v1/messages", "/v1/models"),
+        _ => url.replace("/chat/completions", "/models"),
+    };
+
+    let mut request = ureq::get(&url).timeout(CHECK_TIMEOUT);
+    for (name, value) in &headers {
+        request = request.set(name, value);
+    }
+    match request.call() {
+        Ok(_) => Ok(()),
+        Err(ureq::Error::Status(status, response)) => {
+            let detail = response.into_string().unwrap_or_default();
+            Err(format!(
+                "LLM provider '{}' returned {}: {}",
+                provider.name,
+                status,
+                error_message(&detail)
+            ))
+        }
+        Err(e) => Err(format!("Failed to reach '{}': {}", provider.name, e)),
+    }
+}
+
+fn request(provider: &LlmProvider, system: &str, message: &str) -> Result<Request, String> {
+    let base_url = |default: &str| {
+        provider
+            .base_url
+            .as_deref()
+            .map(str::trim)
+            .filter(|u| !u.is_empty())
+            .unwrap_or(default)
+            .trim_end_matches('/')
+            .to_string()
+    };
+
+    match provider.kind.as_str() {
+        "openai" => {
+            let mut headers = Vec::new();
+            // Local servers such as Ollama don't need a key
+            if !provider.api_key.is_empty() {
+                headers.push(("Authorization", format!("Bearer {}", provider.api_key)));
+            }
+            Ok(Request {
+                url: format!("{}/chat/completions", base_url("https://api.openai.com/v1")),
+                headers,
+                body: json!({
+                    "model": provider.model,
+                    "messages": [
+                        { "role": "system", "content": system },
+                        { "role": "user", "content": message }
+                    ]
+                }),
+            })
+        }
+        "anthropic" => Ok(Request {
+            url: format!("{}/v1/messages", base_url("https://api.anthropic.com")),
+            headers: vec![
+                ("x-api-key", provider.api_key.clone()),
+                ("anthropic-version", ANTHROPIC_VERSION.to_string()),
+            ],
+            body: json!({
+                "model": provider.model,
+                "max_tokens": MAX_TOKENS,
+                "system": system,
+                "messages": [{ "role": "user", "content": message }]
+            }),
+        }),
+        other => Err(format!(
+            "Unknown LLM provider kind '{}': use 'openai' or 'anthropic'",
+            other
+        )),
+    }
+}
+
+/// Text of the first choice (OpenAI) or the text blocks of the reply (Anthropic)
+fn reply_text(kind: &str, response: &Value) -> Result<String, String> {
+    let text = match kind {
+        "anthropic" => response["content"].as_array().map(|blocks| {
+            blocks
+                .iter()
+                .filter(|b| b["type"] == "text")
+                .filter_map(|b| b["text"].as_str())
+                .collect::<String>()
+        }),
+        _ => response
+            .pointer("/choices/0/message/content")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+    };
+    text.filter(|t| !t.trim().is_empty())
+        .ok_or_else(|| "The LLM provider returned an empty reply".to_string())
+}
+
+/// Token counts from a response's `usage`, which some compatible servers leave out
+fn token_usage(kind: &str, response: &Value) -> Option<TokenUsage> {
+    let (input, output) = match kind {
+        "anthropic" => ("input_tokens", "output_tokens"),
+        _ => ("prompt_tokens", "completion_tokens"),
+    };
+    let usage = &response["usage"];
+    Some(TokenUsage {
+        input_tokens: usage[input].as_u64()?,
+        output_tokens: usage[output].as_u64()?,
+    })
+}
+
+/// The `error.message` of an API error body, or the body itself
+fn error_message(body: &str) -> String {
+    serde_json::from_str::<Value>(body)
+        .ok()
+        .and_then(|v| v.pointer("/error/message")?.as_str().map(str::to_string))
+        .unwrap_or_else(|| body.chars().take(300).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider(kind: &str) -> LlmProvider {
+        LlmProvider {
+            name: "test".to_string(),
+            kind: kind.to_string(),
+            model: "some-model".to_string(),
+            base_url: None,
+            api_key: "key".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_requests_per_kind() {
+        let Request { url, headers, body } =
+            request(&provider("anthropic"), "Be brief", "Hi").unwrap();
+        assert_eq!(url, "https://api.anthropic.com/v1/messages");
+        assert_eq!(headers[0], ("x-api-key", "key".to_string()));
+        assert_eq!(body["system"], "Be brief");
+
+        let mut local = provider("openai");
+        local.base_url = Some("http://localhost:11434/v1/".to_string());
+        local.api_key = String::new();
+        let Request { url, headers, body } = request(&local, "Be brief", "Hi").unwrap();
+        assert_eq!(url, "http://localhost:11434/v1/chat/completions");
+        assert!(headers.is_empty());
+        assert_eq!(body["messages"][1]["content"], "Hi");
+
+        assert!(request(&provider("gemini"), "", "").is_err());
+    }
+
+    #[test]
+    fn test_reply_text() {
+        let anthropic = json!({ "content": [
+            { "type": "text", "text": "Hallo" },
+            { "type": "text", "text": " Welt" }
+        ]});
+        assert_eq!(reply_text("anthropic", &anthropic).unwrap(), "Hallo Welt");
+
+        let openai = json!({ "choices": [{ "message": { "content": "Bonjour" } }] });
+        assert_eq!(reply_text("openai", &openai).unwrap(), "Bonjour");
+        assert!(reply_text("openai", &json!({ "choices": [] })).is_err());
+
+        let usage = token_usage(
+            "openai",
+            &json!({ "usage": { "prompt_tokens": 120, "completion_tokens": 8 } }),
+        );
+        assert_eq!(
+            usage.map(|u| (u.input_tokens, u.output_tokens)),
+            Some((120, 8))
+        );
+        assert!(token_usage("anthropic", &anthropic).is_none());
+
+        assert_eq!(
+            error_message(r#"{"error": {"message": "invalid x-api-key"}}"#),
+            "invalid x-api-key"
+        );
+    }
+}
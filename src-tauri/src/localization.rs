@@ -0,0 +1,274 @@
+//! Locale variants of library entities
+//! A translation replaces an entity's name, description and main text
+//! (system prompt, prompt template or instruction content) for one locale.
+//! Drafts come from an LLM provider and must be reviewed before they are
+//! served; entities without an approved translation are served as written.
+
+use crate::composition::LibraryGraph;
+use crate::models::{Agent, Instruction, LibraryItem, Skill, SkillDefinition, Translation};
+use serde::{Deserialize, Serialize};
+
+/// The text of an entity that gets translated
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TranslatableText {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub content: String,
+}
+
+/// Canonical form of a locale tag: lowercase language, uppercase region,
+/// e.g. "pt_br" becomes "pt-BR"
+pub fn normalize_locale(locale: &str) -> Result<String, String> {
+    let parts: Vec<&str> = locale.trim().split(['-', '_']).collect();
+    let valid = parts[0].len() >= 2
+        && parts[0].len() <= 3
+        && parts
+            .iter()
+            .all(|p| !p.is_empty() && p.len() <= 8 && p.chars().all(|c| c.is_ascii_alphanumeric()))
+        && parts[0].chars().all(|c| c.is_ascii_alphabetic());
+    if !valid {
+        return Err(format!(
+            "Invalid locale '{}': use a language code such as 'de' or 'pt-BR'",
+            locale
+        ));
+    }
+
+    Ok(parts
+        .iter()
+        .enumerate()
+        .map(|(i, part)| match (i, part.len()) {
+            (0, _) => part.to_lowercase(),
+            (_, 2) => part.to_uppercase(),
+            // Scripts such as "Hant" are title case
+            (_, 4) => part[..1].to_uppercase() + &part[1..].to_lowercase(),
+            _ => part.to_lowercase(),
+        })
+        .collect::<Vec<_>>()
+        .join("-"))
+}
+
+/// Locales to look up for `locale`, most specific first: "pt-BR" also
+/// accepts a plain "pt" translation
+pub fn fallback_locales(locale: &str) -> Vec<String> {
+    let mut locales = vec![locale.to_string()];
+    if let Some((language, _)) = locale.split_once('-') {
+        locales.push(language.to_string());
+    }
+    locales
+}
+
+pub fn text_of(item: &LibraryItem) -> TranslatableText {
+    match item {
+        LibraryItem::Agent(agent) => TranslatableText {
+            name: agent.name.clone(),
+            description: agent.description.clone(),
+            content: agent.system_prompt.clone(),
+        },
+        LibraryItem::Skill(skill) => TranslatableText {
+            name: skill.name.clone(),
+            description: skill.description.clone(),
+            content: match &skill.definition {
+                SkillDefinition::Prompt { template, .. } => template.clone(),
+                _ => String::new(),
+            },
+        },
+        LibraryItem::Instruction(instruction) => TranslatableText {
+            name: instruction.name.clone(),
+            description: instruction.description.clone(),
+            content: instruction.content.clone(),
+        },
+    }
+}
+
+/// System prompt asking a model to translate a `TranslatableText` given as JSON
+pub fn translation_prompt(locale: &str) -> String {
+    format!(
+        "You translate entries of a prompt library into the locale '{}'. \
+         The user message is a JSON object with the keys \"name\", \"description\" and \"content\". \
+         Reply with only a JSON object with the same keys holding the translations. \
+         Translate the prose; keep markdown structure, code blocks, inline code, URLs, \
+         identifiers and {{{{placeholders}}}} exactly as they are. Leave empty values empty.",
+        locale
+    )
+}
+
+/// Read the translated text out of a model's reply, which may wrap the JSON
+/// in a code fence or a sentence
+pub fn parse_translation(reply: &str) -> Result<TranslatableText, String> {
+    let start = reply.find('{');
+    let end = reply.rfind('}');
+    let json = match (start, end) {
+        (Some(start), Some(end)) if start < end => &reply[start..=end],
+        _ => return Err("The provider's reply contained no JSON object".to_string()),
+    };
+
+    let text: TranslatableText = serde_json::from_str(json)
+        .map_err(|e| format!("Failed to read the provider's translation: {}", e))?;
+    if text.name.trim().is_empty() {
+        return Err("The provider's translation has no name".to_string());
+    }
+    Ok(text)
+}
+
+/// Owned copy of a library with approved translations swapped in
+pub struct LocalizedLibrary {
+    pub agents: Vec<Agent>,
+    pub skills: Vec<Skill>,
+    pub instructions: Vec<Instruction>,
+}
+
+impl LocalizedLibrary {
+    pub fn new(library: &LibraryGraph, translations: &[Translation], locale: &str) -> Self {
+        Self {
+            agents: library
+                .agents
+                .iter()
+                .map(|a| localize_agent(a, translations, locale))
+                .collect(),
+            skills: library
+                .skills
+                .iter()
+                .map(|s| localize_skill(s, translations, locale))
+                .collect(),
+            instructions: library
+                .instructions
+                .iter()
+                .map(|i| localize_instruction(i, translations, locale))
+                .collect(),
+        }
+    }
+
+    pub fn graph(&self) -> LibraryGraph<'_> {
+        LibraryGraph {
+            agents: &self.agents,
+            skills: &self.skills,
+            instructions: &self.instructions,
+        }
+    }
+}
+
+/// The approved translation of `entity_id` closest to `locale`
+fn find<'a>(
+    translations: &'a [Translation],
+    entity_id: &str,
+    locale: &str,
+) -> Option<&'a Translation> {
+    fallback_locales(locale).into_iter().find_map(|locale| {
+        translations
+            .iter()
+            .find(|t| t.entity_id == entity_id && t.locale == locale && !t.needs_review)
+    })
+}
+
+pub fn localize_agent(agent: &Agent, translations: &[Translation], locale: &str) -> Agent {
+    let mut agent = agent.clone();
+    if let Some(t) = find(translations, &agent.id, locale) {
+        agent.name = t.name.clone();
+        agent.description = t.description.clone();
+        if !t.content.is_empty() {
+            agent.system_prompt = t.content.clone();
+        }
+    }
+    agent
+}
+
+pub fn localize_skill(skill: &Skill, translations: &[Translation], locale: &str) -> Skill {
+    let mut skill = skill.clone();
+    if let Some(t) = find(translations, &skill.id, locale) {
+        skill.name = t.name.clone();
+        skill.description = t.description.clone();
+        if let SkillDefinition::Prompt { template, .. } = &mut skill.definition {
+            if !t.content.is_empty() {
+                *template = t.content.clone();
+            }
+        }
+    }
+    skill
+}
+
+pub fn localize_instruction(
+    instruction: &Instruction,
+    translations: &[Translation],
+    locale: &str,
+) -> Instruction {
+    let mut instruction = instruction.clone();
+    if let Some(t) = find(translations, &instruction.id, locale) {
+        instruction.name = t.name.clone();
+        instruction.description = t.description.clone();
+        if !t.content.is_empty() {
+            instruction.content = t.content.clone();
+        }
+    }
+    instruction
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    #[test]
+    fn test_normalize_locale() {
+        assert_eq!(normalize_locale("DE").unwrap(), "de");
+        assert_eq!(normalize_locale(" pt_br ").unwrap(), "pt-BR");
+        assert_eq!(normalize_locale("zh-hant-tw").unwrap(), "zh-Hant-TW");
+        assert!(normalize_locale("german!").is_err());
+        assert!(normalize_locale("").is_err());
+        assert_eq!(fallback_locales("pt-BR"), vec!["pt-BR", "pt"]);
+    }
+
+    #[test]
+    fn test_parse_translation_from_fenced_reply() {
+        let reply = "Here you go:\n```json\n{\"name\": \"Tests zuerst\", \"description\": \"\", \
+                     \"content\": \"Schreibe zuerst einen Test für `{{module}}`.\"}\n```";
+        assert_eq!(
+            parse_translation(reply).unwrap(),
+            TranslatableText {
+                name: "Tests zuerst".to_string(),
+                description: String::new(),
+                content: "Schreibe zuerst einen Test für `{{module}}`.".to_string(),
+            }
+        );
+        assert!(parse_translation("Sorry, I can't help with that.").is_err());
+    }
+
+    #[test]
+    fn test_only_reviewed_translations_are_applied() {
+        let instruction = Instruction {
+            id: "i1".to_string(),
+            name: "Tests first".to_string(),
+            content: "Write a failing test first.".to_string(),
+            ..Instruction::default()
+        };
+        let mut translation = Translation {
+            id: "t1".to_string(),
+            entity_type: "instruction".to_string(),
+            entity_id: "i1".to_string(),
+            locale: "de".to_string(),
+            name: "Tests zuerst".to_string(),
+            description: String::new(),
+            content: "Schreibe zuerst einen fehlschlagenden Test.".to_string(),
+            needs_review: true,
+            provider: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+
+        let draft = localize_instruction(&instruction, &[translation.clone()], "de");
+        assert_eq!(draft.name, "Tests first");
+
+        translation.needs_review = false;
+        let approved = localize_instruction(&instruction, &[translation.clone()], "de-AT");
+        assert_eq!(approved.name, "Tests zuerst");
+        assert_eq!(
+            approved.content,
+            "Schreibe zuerst einen fehlschlagenden Test."
+        );
+        assert_eq!(
+            localize_instruction(&instruction, &[translation], "fr").content,
+            instruction.content
+        );
+    }
+}
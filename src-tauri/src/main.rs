@@ -1,5 +1,5 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
-#![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
+#![cfg_attr(all(not(debug_assertions), feature = "gui"), windows_subsystem = "windows")]
 
 use std::path::PathBuf;
 
@@ -8,16 +8,75 @@ fn main() {
 
     // Check for --mcp flag to run as MCP server
     if args.iter().any(|arg| arg == "--mcp" || arg == "-m") {
-        // Parse --db-path argument
-        let db_path = args
+        let db_path = db_path(&args);
+
+        // --read-only disables tools that modify the library
+        let read_only = args.iter().any(|arg| arg == "--read-only");
+
+        // --channel serves a release channel (or tag) instead of the live library
+        let channel = args
             .iter()
-            .position(|arg| arg == "--db-path")
+            .position(|arg| arg == "--channel")
             .and_then(|i| args.get(i + 1))
-            .map(PathBuf::from)
-            .unwrap_or_else(|| prompt_forge_lib::get_db_path());
+            .cloned();
 
-        prompt_forge_lib::run_mcp_server(db_path);
+        // --repl reads console commands instead of JSON-RPC, for debugging clients
+        if args.iter().any(|arg| arg == "--repl") {
+            attach_console();
+            prompt_forge_lib::run_mcp_repl(db_path, read_only, channel);
+        } else {
+            prompt_forge_lib::run_mcp_server(db_path, read_only, channel);
+        }
+    } else if let Some(command) = prompt_forge_lib::find_subcommand(&args[1..]) {
+        // Subcommands such as `list agents` manage the library headlessly
+        attach_console();
+        prompt_forge_lib::run_cli(db_path(&args), command);
     } else {
+        #[cfg(feature = "gui")]
         prompt_forge_lib::run();
+
+        #[cfg(not(feature = "gui"))]
+        {
+            eprintln!("This build only includes the MCP server and subcommands; run it with --mcp or a command such as `list agents`");
+            std::process::exit(2);
+        }
+    }
+}
+
+/// Give console output somewhere to go: a windowed release build on Windows
+/// starts without a console, so borrow the one of the shell that ran it
+#[cfg(all(windows, not(debug_assertions), feature = "gui"))]
+fn attach_console() {
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+    // Fails harmlessly when started without a parent console, e.g. from Explorer
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(not(all(windows, not(debug_assertions), feature = "gui")))]
+fn attach_console() {}
+
+/// The database picked by --db-path or --workspace, else the active workspace's
+fn db_path(args: &[String]) -> PathBuf {
+    let value = |name: &str| {
+        args.iter()
+            .position(|arg| arg == name)
+            .and_then(|i| args.get(i + 1))
+    };
+    if let Some(db_path) = value("--db-path") {
+        return PathBuf::from(db_path);
+    }
+    // --workspace opens another workspace's library than the active one
+    match value("--workspace") {
+        Some(name) => prompt_forge_lib::get_workspace_db_path(name).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }),
+        None => prompt_forge_lib::get_db_path(),
     }
 }
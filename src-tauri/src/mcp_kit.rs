@@ -0,0 +1,232 @@
+//! Agent MCP kits
+//! A folder that hands a single agent to someone without access to the
+//! library. The agent is written as a Claude Code subagent, and a project
+//! `.mcp.json` starts the Prompt Forge MCP server read-only against a small
+//! database holding only this agent, pinned to a release so the kit keeps
+//! serving exactly what was exported. The release is also written as JSON
+//! for review, next to a README explaining the setup.
+
+use crate::db::Database;
+use crate::models::{Agent, AgentKit, Instruction, Release, Skill};
+use crate::releases;
+use crate::resolve::resolve_entity;
+use crate::setup;
+use crate::subagent;
+use chrono::Utc;
+use serde_json::json;
+use std::fs;
+use std::path::Path;
+
+/// Command the kit's `.mcp.json` runs; Prompt Forge must be on `PATH`
+pub const KIT_COMMAND: &str = "prompt-forge";
+
+/// Kit files that aren't read by Claude Code itself, relative to the kit root
+pub const KIT_DIR: &str = ".prompt-forge";
+
+/// A release holding `agent` and the enabled skills and instructions it uses.
+/// Usage stats are left behind.
+pub fn kit_release(
+    agent: &Agent,
+    skills: &[Skill],
+    instructions: &[Instruction],
+) -> Result<Release, String> {
+    let mut used_skills: Vec<Skill> = Vec::new();
+    for skill in agent
+        .skills
+        .iter()
+        .filter_map(|reference| resolve_entity(skills, reference).ok())
+    {
+        if !used_skills.iter().any(|s| s.id == skill.id) {
+            used_skills.push(skill.clone());
+        }
+    }
+    let used_instructions: Vec<Instruction> = instructions
+        .iter()
+        .filter(|i| agent.instructions.contains(&i.id))
+        .cloned()
+        .collect();
+
+    let agent = Agent {
+        usage_count: 0,
+        last_used_at: None,
+        ..agent.clone()
+    };
+    releases::snapshot(
+        &format!("kit-{}", Utc::now().format("%Y%m%d-%H%M%S")),
+        &format!("MCP kit for {}", agent.name),
+        vec![agent],
+        used_skills,
+        used_instructions,
+    )
+}
+
+/// `.mcp.json` starting the server read-only on the kit's release. Paths are
+/// relative to the project the kit is copied into.
+pub fn mcp_config(tag: &str) -> serde_json::Value {
+    let mut entry = setup::server_entry(
+        Path::new(KIT_COMMAND),
+        &Path::new(KIT_DIR).join("library.db"),
+        true,
+    );
+    if let Some(args) = entry["args"].as_array_mut() {
+        args.push(json!("--channel"));
+        args.push(json!(tag));
+    }
+    json!({ "mcpServers": { setup::SERVER_NAME: entry } })
+}
+
+fn readme(release: &Release, subagent_path: &str) -> String {
+    let agent = &release.agents[0];
+    let mut output = format!("# {}\n\n", agent.name);
+    if !agent.description.trim().is_empty() {
+        output.push_str(&format!("{}\n\n", agent.description.trim()));
+    }
+    output.push_str(&format!(
+        "Exported from Prompt Forge on {} as release `{}`.\n\n",
+        release.created_at.format("%Y-%m-%d"),
+        release.tag
+    ));
+    output.push_str("## Contents\n\n");
+    output.push_str(&format!(
+        "- `{}`: the agent as a Claude Code subagent\n",
+        subagent_path
+    ));
+    output.push_str(
+        "- `.mcp.json`: starts the Prompt Forge MCP server, read-only and pinned to this release\n",
+    );
+    output.push_str(&format!(
+        "- `{}/library.db`: the agent with its {} skills and {} instructions\n",
+        KIT_DIR,
+        release.skills.len(),
+        release.instructions.len()
+    ));
+    output.push_str(&format!(
+        "- `{}/release.json`: the same release as JSON, for review\n\n",
+        KIT_DIR
+    ));
+    output.push_str("## Setup\n\n");
+    output.push_str(&format!(
+        "1. Install Prompt Forge so `{}` is on your `PATH`.\n",
+        KIT_COMMAND
+    ));
+    output.push_str(&format!(
+        "2. Copy `.mcp.json`, `.claude/` and `{}/` into the root of your project. \
+         If the project already has a `.mcp.json`, add the `{}` entry to its `mcpServers`.\n",
+        KIT_DIR,
+        setup::SERVER_NAME
+    ));
+    output.push_str(&format!(
+        "3. Start Claude Code in the project and approve the `{}` server when asked. \
+         The `{}` subagent is then available.\n",
+        setup::SERVER_NAME,
+        subagent::subagent_name(agent)
+    ));
+    output
+}
+
+/// Write the kit for the agent in `release` into `dir`, which must be empty
+/// or not exist yet
+pub fn write_kit(dir: &Path, release: &Release) -> Result<AgentKit, String> {
+    let agent = release
+        .agents
+        .first()
+        .ok_or_else(|| "The kit release has no agent".to_string())?;
+    if fs::read_dir(dir).is_ok_and(|mut entries| entries.next().is_some()) {
+        return Err(format!("{} isn't empty", dir.display()));
+    }
+    let data_dir = dir.join(KIT_DIR);
+    let agents_dir = dir.join(".claude").join("agents");
+    for path in [&data_dir, &agents_dir] {
+        fs::create_dir_all(path)
+            .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+    }
+
+    let subagent_path = format!(".claude/agents/{}.md", subagent::subagent_name(agent));
+    let files = vec![
+        (
+            subagent_path.clone(),
+            subagent::export_subagent(agent, &release.skills, &release.instructions, &[]),
+        ),
+        (
+            ".mcp.json".to_string(),
+            serde_json::to_string_pretty(&mcp_config(&release.tag)).unwrap() + "\n",
+        ),
+        (
+            format!("{}/release.json", KIT_DIR),
+            serde_json::to_string_pretty(release)
+                .map_err(|e| format!("Failed to serialize release: {}", e))?,
+        ),
+        ("README.md".to_string(), readme(release, &subagent_path)),
+    ];
+    for (name, contents) in &files {
+        let path = dir.join(name);
+        fs::write(&path, contents)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    }
+
+    let db_path = data_dir.join("library.db");
+    let db =
+        Database::open(&db_path).map_err(|e| format!("Failed to create kit database: {}", e))?;
+    db.migrate()
+        .map_err(|e| format!("Failed to create kit database: {}", e))?;
+    db.insert_agent(agent)
+        .and_then(|_| release.skills.iter().try_for_each(|s| db.insert_skill(s)))
+        .and_then(|_| {
+            release
+                .instructions
+                .iter()
+                .try_for_each(|i| db.insert_instruction(i))
+        })
+        .and_then(|_| db.insert_release(release))
+        .map_err(|e| format!("Failed to fill kit database: {}", e))?;
+
+    let mut written: Vec<String> = files.into_iter().map(|(name, _)| name).collect();
+    written.push(format!("{}/library.db", KIT_DIR));
+    Ok(AgentKit {
+        dir: dir.to_string_lossy().to_string(),
+        tag: release.tag.clone(),
+        files: written,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_default_data;
+
+    #[test]
+    fn test_kit_holds_only_the_agent_and_pins_its_release() {
+        let db = Database::open(":memory:").unwrap();
+        db.migrate().unwrap();
+        init_default_data(&db).unwrap();
+        let data = db.export_all().unwrap();
+        let mut agent = data.agents[0].clone();
+        agent.usage_count = 12;
+        agent.instructions = vec![data.instructions[0].id.clone(), "missing".to_string()];
+        let release = kit_release(&agent, &data.skills, &data.instructions).unwrap();
+        assert_eq!(release.instructions.len(), 1);
+        assert!(release.instructions.len() < data.instructions.len());
+        assert_eq!(release.agents[0].usage_count, 0);
+
+        let dir = std::env::temp_dir().join(format!("pf-kit-{}", uuid::Uuid::new_v4()));
+        let kit = write_kit(&dir, &release).unwrap();
+        assert_eq!(kit.files.len(), 5);
+        assert!(kit.files.iter().all(|file| dir.join(file).is_file()));
+
+        let config: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(dir.join(".mcp.json")).unwrap()).unwrap();
+        let args = config["mcpServers"][setup::SERVER_NAME]["args"].to_string();
+        assert!(args.contains("--read-only"));
+        assert!(args.contains(&format!("\"--channel\",\"{}\"", release.tag)));
+
+        let kit_db = Database::open(dir.join(KIT_DIR).join("library.db")).unwrap();
+        let pinned = kit_db.get_release(&release.tag).unwrap().unwrap();
+        assert_eq!(pinned.agents[0].id, agent.id);
+        assert_eq!(kit_db.get_all_agents().unwrap().len(), 1);
+
+        assert!(write_kit(&dir, &release)
+            .unwrap_err()
+            .contains("isn't empty"));
+        fs::remove_dir_all(&dir).ok();
+    }
+}
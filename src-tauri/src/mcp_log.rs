@@ -0,0 +1,167 @@
+//! MCP logging
+//! The stdio server reports its diagnostics (library loads, tool calls,
+//! failures) to the client as `notifications/message` rather than on stderr,
+//! so clients can show them alongside the session. A client picks the least
+//! severe level it wants with `logging/setLevel`; until it does, `info` and
+//! above are sent.
+
+use serde_json::{json, Value};
+use std::fmt;
+
+/// Syslog severities, least severe first, as the MCP spec names them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Notice,
+    Warning,
+    Error,
+    Critical,
+    Alert,
+    Emergency,
+}
+
+const LEVELS: [LogLevel; 8] = [
+    LogLevel::Debug,
+    LogLevel::Info,
+    LogLevel::Notice,
+    LogLevel::Warning,
+    LogLevel::Error,
+    LogLevel::Critical,
+    LogLevel::Alert,
+    LogLevel::Emergency,
+];
+
+impl LogLevel {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LogLevel::Debug => "debug",
+            LogLevel::Info => "info",
+            LogLevel::Notice => "notice",
+            LogLevel::Warning => "warning",
+            LogLevel::Error => "error",
+            LogLevel::Critical => "critical",
+            LogLevel::Alert => "alert",
+            LogLevel::Emergency => "emergency",
+        }
+    }
+
+    pub fn parse(level: &str) -> Option<LogLevel> {
+        LEVELS.into_iter().find(|l| l.as_str() == level)
+    }
+}
+
+/// Level sent before the client sets one
+pub const DEFAULT_LEVEL: LogLevel = LogLevel::Info;
+
+/// One log event; `logger` names the part of the server it came from
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogMessage {
+    pub level: LogLevel,
+    pub logger: &'static str,
+    pub data: String,
+}
+
+impl LogMessage {
+    pub fn to_notification(&self) -> Value {
+        json!({
+            "jsonrpc": "2.0",
+            "method": "notifications/message",
+            "params": {
+                "level": self.level.as_str(),
+                "logger": self.logger,
+                "data": self.data,
+            }
+        })
+    }
+}
+
+impl fmt::Display for LogMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[{}] {}: {}",
+            self.level.as_str(),
+            self.logger,
+            self.data
+        )
+    }
+}
+
+/// Messages waiting to be sent, dropping those below the client's level
+pub struct McpLog {
+    level: LogLevel,
+    pending: Vec<LogMessage>,
+}
+
+impl Default for McpLog {
+    fn default() -> Self {
+        Self {
+            level: DEFAULT_LEVEL,
+            pending: Vec::new(),
+        }
+    }
+}
+
+impl McpLog {
+    pub fn set_level(&mut self, level: LogLevel) {
+        self.level = level;
+    }
+
+    pub fn log(&mut self, level: LogLevel, logger: &'static str, data: impl Into<String>) {
+        if level >= self.level {
+            self.pending.push(LogMessage {
+                level,
+                logger,
+                data: data.into(),
+            });
+        }
+    }
+
+    pub fn debug(&mut self, logger: &'static str, data: impl Into<String>) {
+        self.log(LogLevel::Debug, logger, data);
+    }
+
+    pub fn info(&mut self, logger: &'static str, data: impl Into<String>) {
+        self.log(LogLevel::Info, logger, data);
+    }
+
+    pub fn warning(&mut self, logger: &'static str, data: impl Into<String>) {
+        self.log(LogLevel::Warning, logger, data);
+    }
+
+    pub fn error(&mut self, logger: &'static str, data: impl Into<String>) {
+        self.log(LogLevel::Error, logger, data);
+    }
+
+    /// Messages logged since the last call, oldest first
+    pub fn drain(&mut self) -> Vec<LogMessage> {
+        std::mem::take(&mut self.pending)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_messages_below_the_level_are_dropped() {
+        let mut log = McpLog::default();
+        log.debug("library", "Loaded Agents from database in 2ms");
+        log.warning("library", "Failed to load library: locked");
+        assert_eq!(log.drain().len(), 1);
+        assert!(log.drain().is_empty());
+
+        log.set_level(LogLevel::parse("debug").unwrap());
+        log.debug("tools", "Calling get_agent");
+        let notification = log.drain()[0].to_notification();
+        assert_eq!(notification["method"], "notifications/message");
+        assert_eq!(notification["params"]["level"], "debug");
+        assert_eq!(notification["params"]["logger"], "tools");
+
+        log.set_level(LogLevel::Error);
+        log.warning("session", "Client rejected roots/list");
+        assert!(log.drain().is_empty());
+        assert_eq!(LogLevel::parse("verbose"), None);
+    }
+}
@@ -0,0 +1,398 @@
+//! Interactive MCP console
+//! `prompt-forge --mcp --repl` runs the MCP server's dispatcher on
+//! human-friendly commands instead of JSON-RPC lines, for debugging clients
+//! against the server. `call apply_agent default` calls a tool, with bare
+//! words filling the tool's required parameters in order and `key=value`
+//! (or a JSON object) giving the rest; `read <uri>` reads a resource.
+//! Responses are pretty-printed: tool and resource text as-is, JSON indented,
+//! followed by any log messages the server sent.
+
+use crate::mcp_server::{JsonRpcRequest, JsonRpcResponse, McpServer};
+use serde_json::{json, Map, Value};
+use std::io::{self, BufRead, Write};
+
+const PROMPT: &str = "prompt-forge> ";
+
+const HELP: &str = "\
+Commands:
+  call <tool> [value ...] [key=value ...]   Call a tool; bare values fill its required parameters
+  call <tool> {\"key\": \"value\"}              Call a tool with JSON arguments
+  read <uri>                                Read a resource, e.g. prompt-forge://instructions/all
+  tools                                     List tools and their parameters
+  resources                                 List resources
+  send <method> [json]                      Send any JSON-RPC method, e.g. send ping
+  help                                      Show this help
+  quit                                      Leave the console";
+
+/// One console command
+#[derive(Debug, PartialEq)]
+pub enum ReplCommand {
+    Request {
+        method: String,
+        params: Option<Value>,
+    },
+    Tools,
+    Help,
+    Quit,
+}
+
+/// Parse a console line. `tools` is the server's `tools/list` result, used to
+/// name the bare values given to `call`. Blank lines and `#` comments are `None`.
+pub fn parse_command(line: &str, tools: &Value) -> Result<Option<ReplCommand>, String> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+    let (command, rest) = line
+        .split_once(char::is_whitespace)
+        .map(|(command, rest)| (command, rest.trim()))
+        .unwrap_or((line, ""));
+
+    let command = match command {
+        "call" => {
+            let (tool, rest) = rest
+                .split_once(char::is_whitespace)
+                .map(|(tool, rest)| (tool, rest.trim()))
+                .unwrap_or((rest, ""));
+            if tool.is_empty() {
+                return Err("Usage: call <tool> [value ...] [key=value ...]".to_string());
+            }
+            let arguments = call_arguments(tool, rest, tools)?;
+            ReplCommand::Request {
+                method: "tools/call".to_string(),
+                params: Some(json!({ "name": tool, "arguments": arguments })),
+            }
+        }
+        "read" => {
+            if rest.is_empty() {
+                return Err("Usage: read <uri>".to_string());
+            }
+            ReplCommand::Request {
+                method: "resources/read".to_string(),
+                params: Some(json!({ "uri": rest })),
+            }
+        }
+        "resources" => ReplCommand::Request {
+            method: "resources/list".to_string(),
+            params: None,
+        },
+        "send" => {
+            let (method, params) = rest
+                .split_once(char::is_whitespace)
+                .map(|(method, params)| (method, params.trim()))
+                .unwrap_or((rest, ""));
+            if method.is_empty() {
+                return Err("Usage: send <method> [json]".to_string());
+            }
+            let params = if params.is_empty() {
+                None
+            } else {
+                Some(
+                    serde_json::from_str(params)
+                        .map_err(|e| format!("Failed to parse params: {}", e))?,
+                )
+            };
+            ReplCommand::Request {
+                method: method.to_string(),
+                params,
+            }
+        }
+        "tools" => ReplCommand::Tools,
+        "help" | "?" => ReplCommand::Help,
+        "quit" | "exit" => ReplCommand::Quit,
+        other => return Err(format!("Unknown command '{}'; try help", other)),
+    };
+    Ok(Some(command))
+}
+
+/// Arguments for `call`: a JSON object, or bare values and `key=value` pairs.
+/// Values are read as JSON when they parse (numbers, booleans, arrays) and as
+/// strings otherwise.
+fn call_arguments(tool: &str, text: &str, tools: &Value) -> Result<Value, String> {
+    if text.starts_with('{') {
+        return serde_json::from_str(text).map_err(|e| format!("Failed to parse arguments: {}", e));
+    }
+
+    let schema = tools
+        .get("tools")
+        .and_then(|t| t.as_array())
+        .and_then(|t| t.iter().find(|t| t["name"] == tool))
+        .map(|t| &t["inputSchema"]);
+    let mut required: Vec<&str> = schema
+        .and_then(|s| s["required"].as_array())
+        .map(|r| r.iter().filter_map(|name| name.as_str()).collect())
+        .unwrap_or_default();
+    if required.is_empty() {
+        // Fall back to the declared parameters when none are required
+        required = schema
+            .and_then(|s| s["properties"].as_object())
+            .map(|p| p.keys().map(String::as_str).collect())
+            .unwrap_or_default();
+    }
+
+    let mut arguments = Map::new();
+    let mut positional = Vec::new();
+    for word in split_words(text)? {
+        match word.split_once('=') {
+            Some((key, value)) if !key.is_empty() => {
+                arguments.insert(key.to_string(), value_of(value));
+            }
+            _ => positional.push(word),
+        }
+    }
+    let unnamed: Vec<&str> = required
+        .into_iter()
+        .filter(|name| !arguments.contains_key(*name))
+        .collect();
+    let mut names = unnamed.into_iter();
+    for value in positional {
+        let name = names
+            .next()
+            .ok_or_else(|| format!("Too many values for {}; name them with key=value", tool))?;
+        arguments.insert(name.to_string(), value_of(&value));
+    }
+    Ok(Value::Object(arguments))
+}
+
+fn value_of(text: &str) -> Value {
+    serde_json::from_str(text).unwrap_or_else(|_| Value::String(text.to_string()))
+}
+
+/// Whitespace-separated words; double or single quotes keep spaces together
+fn split_words(text: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quote: Option<char> = None;
+    for c in text.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => word.push(c),
+            None if c == '"' || c == '\'' => {
+                quote = Some(c);
+                in_word = true;
+            }
+            None if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            None => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if quote.is_some() {
+        return Err("Unclosed quote".to_string());
+    }
+    if in_word {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+/// Human-readable rendering of a response
+pub fn pretty(response: &JsonRpcResponse) -> String {
+    if let Some(error) = &response.error {
+        return format!("Error {}: {}", error.code, error.message);
+    }
+    let Some(result) = &response.result else {
+        return String::new();
+    };
+
+    // Tool results and resource contents carry their payload as text
+    let texts: Vec<&str> = ["content", "contents"]
+        .iter()
+        .filter_map(|key| result.get(*key).and_then(|c| c.as_array()))
+        .flatten()
+        .filter_map(|item| item.get("text").and_then(|t| t.as_str()))
+        .collect();
+    if texts.is_empty() {
+        return serde_json::to_string_pretty(result).unwrap();
+    }
+    let body = texts
+        .iter()
+        .map(|text| match serde_json::from_str::<Value>(text) {
+            Ok(value) if value.is_object() || value.is_array() => {
+                serde_json::to_string_pretty(&value).unwrap()
+            }
+            _ => text.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    if result.get("isError") == Some(&Value::Bool(true)) {
+        format!("Tool error: {}", body)
+    } else {
+        body
+    }
+}
+
+fn tools_summary(tools: &Value) -> String {
+    let Some(tools) = tools.get("tools").and_then(|t| t.as_array()) else {
+        return "No tools".to_string();
+    };
+    tools
+        .iter()
+        .map(|tool| {
+            let schema = &tool["inputSchema"];
+            let required: Vec<&str> = schema["required"]
+                .as_array()
+                .map(|r| r.iter().filter_map(|name| name.as_str()).collect())
+                .unwrap_or_default();
+            let parameters: Vec<String> = schema["properties"]
+                .as_object()
+                .map(|p| {
+                    p.keys()
+                        .map(|name| {
+                            if required.contains(&name.as_str()) {
+                                format!("<{}>", name)
+                            } else {
+                                format!("[{}]", name)
+                            }
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            format!(
+                "{} {}\n    {}",
+                tool["name"].as_str().unwrap_or_default(),
+                parameters.join(" "),
+                tool["description"].as_str().unwrap_or_default()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Run the console on stdin until `quit` or end of input
+pub fn run(server: &mut McpServer) -> io::Result<()> {
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+    let mut next_id = 0u64;
+    let mut send = |server: &mut McpServer, method: &str, params: Option<Value>| {
+        next_id += 1;
+        server.handle_request(JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            id: Some(json!(next_id)),
+            method: method.to_string(),
+            params,
+        })
+    };
+
+    send(server, "initialize", Some(json!({ "capabilities": {} })));
+    writeln!(stdout, "Prompt Forge MCP console; type help for commands")?;
+
+    let mut lines = stdin.lock().lines();
+    loop {
+        write!(stdout, "{}", PROMPT)?;
+        stdout.flush()?;
+        let Some(line) = lines.next() else {
+            writeln!(stdout)?;
+            break;
+        };
+        let line = line?;
+
+        // Tool schemas can change as skills are edited, so they're read per line
+        let tools = if line.trim_start().starts_with("call") || line.trim() == "tools" {
+            send(server, "tools/list", None).result.unwrap_or_default()
+        } else {
+            Value::Null
+        };
+        let output = match parse_command(&line, &tools) {
+            Ok(None) => continue,
+            Ok(Some(ReplCommand::Quit)) => break,
+            Ok(Some(ReplCommand::Help)) => HELP.to_string(),
+            Ok(Some(ReplCommand::Tools)) => tools_summary(&tools),
+            Ok(Some(ReplCommand::Request { method, params })) => {
+                pretty(&send(server, &method, params))
+            }
+            Err(e) => e,
+        };
+        writeln!(stdout, "{}", output)?;
+        for message in server.drain_log() {
+            writeln!(stdout, "{}", message)?;
+        }
+    }
+
+    server.shutdown();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tools() -> Value {
+        json!({ "tools": [{
+            "name": "apply_agent",
+            "description": "Apply an agent",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "agent_id": {}, "variables": {}, "max_tokens": {} },
+                "required": ["agent_id"]
+            }
+        }]})
+    }
+
+    #[test]
+    fn test_parses_call_and_read() {
+        let command = parse_command(
+            "call apply_agent default max_tokens=500 variables='{\"lang\": \"Rust\"}'",
+            &tools(),
+        )
+        .unwrap();
+        assert_eq!(
+            command,
+            Some(ReplCommand::Request {
+                method: "tools/call".to_string(),
+                params: Some(json!({
+                    "name": "apply_agent",
+                    "arguments": {
+                        "agent_id": "default",
+                        "max_tokens": 500,
+                        "variables": { "lang": "Rust" }
+                    }
+                })),
+            })
+        );
+        assert!(parse_command("call apply_agent a b", &tools())
+            .unwrap_err()
+            .contains("Too many values"));
+
+        let read = parse_command("read prompt-forge://instructions/all", &Value::Null).unwrap();
+        assert_eq!(
+            read,
+            Some(ReplCommand::Request {
+                method: "resources/read".to_string(),
+                params: Some(json!({ "uri": "prompt-forge://instructions/all" })),
+            })
+        );
+        assert_eq!(parse_command("  # note", &Value::Null).unwrap(), None);
+        assert!(parse_command("launch", &Value::Null).is_err());
+    }
+
+    #[test]
+    fn test_pretty_prints_text_and_errors() {
+        let response = JsonRpcResponse {
+            jsonrpc: "2.0".to_string(),
+            id: Some(json!(1)),
+            result: Some(json!({
+                "content": [{ "type": "text", "text": "{\"id\":\"default\"}" }]
+            })),
+            error: None,
+        };
+        assert_eq!(pretty(&response), "{\n  \"id\": \"default\"\n}");
+
+        let mut server =
+            McpServer::new(std::env::temp_dir().join("pf-repl-missing.db"), true, None);
+        let response = server.handle_request(JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            id: Some(json!(1)),
+            method: "bogus".to_string(),
+            params: None,
+        });
+        assert_eq!(pretty(&response), "Error -32601: Method not found: bogus");
+    }
+}
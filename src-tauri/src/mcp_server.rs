@@ -1,12 +1,41 @@
 // MCP Server implementation using STDIO transport
 // This module handles JSON-RPC 2.0 communication with MCP clients (like Claude Code)
 
+use crate::activation;
+use crate::assembly::{self, AssemblyOptions};
+use crate::cache::TtlCache;
+use crate::categories;
+use crate::composition::{self, LibraryGraph};
 use crate::db::Database;
-use crate::models::{Agent, Instruction, InstructionCategory, Skill, SkillDefinition};
+use crate::encryption;
+use crate::experiments::{self, Variant};
+use crate::ids;
+use crate::insights::InsightMetric;
+use crate::localization::{self, LocalizedLibrary};
+use crate::mcp_log::{self, LogLevel, LogMessage, McpLog};
+use crate::mcp_repl;
+use crate::models::{
+    Agent, AppliedAgent, Instruction, Release, Skill, SkillDefinition, Snippet, ToolParameter,
+    ToolSandbox, Translation,
+};
+use crate::persona_cache::{self, PersonaCache};
+use crate::releases;
+use crate::resolve::resolve_entity;
+use crate::scope;
+use crate::search;
+use crate::snippets;
+use crate::suggestions;
+use crate::template;
+use crate::tool_runner;
+use crate::tool_schema;
+use crate::usage_events::{UsageEventKind, UsageEventQueue};
+use crate::workspace::{self, Workspace};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
 use std::io::{self, BufRead, Write};
 use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
 // ============================================================================
 // JSON-RPC 2.0 Types
@@ -63,6 +92,7 @@ pub struct InitializeResult {
 pub struct ServerCapabilities {
     pub tools: ToolsCapability,
     pub resources: ResourcesCapability,
+    pub logging: LoggingCapability,
 }
 
 #[derive(Debug, Serialize)]
@@ -78,6 +108,10 @@ pub struct ResourcesCapability {
     pub subscribe: bool,
 }
 
+/// Declared empty; the server accepts `logging/setLevel` and sends `notifications/message`
+#[derive(Debug, Serialize)]
+pub struct LoggingCapability {}
+
 #[derive(Debug, Serialize)]
 pub struct Tool {
     pub name: String,
@@ -86,6 +120,18 @@ pub struct Tool {
     pub input_schema: Value,
 }
 
+/// A tool as described by the `help` tool; `skill_id` names the skill a
+/// tool made from a skill runs
+#[derive(Debug, Serialize)]
+pub struct ToolHelp {
+    pub name: String,
+    pub description: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skill_id: Option<String>,
+    pub arguments: Vec<ToolParameter>,
+    pub example: Value,
+}
+
 #[derive(Debug, Serialize)]
 pub struct Resource {
     pub uri: String,
@@ -95,6 +141,16 @@ pub struct Resource {
     pub mime_type: String,
 }
 
+#[derive(Debug, Serialize)]
+pub struct ResourceTemplate {
+    #[serde(rename = "uriTemplate")]
+    pub uri_template: String,
+    pub name: String,
+    pub description: String,
+    #[serde(rename = "mimeType")]
+    pub mime_type: String,
+}
+
 #[derive(Debug, Serialize)]
 pub struct ToolResult {
     pub content: Vec<ToolContent>,
@@ -121,82 +177,392 @@ pub struct ResourceContent {
 // MCP Server State
 // ============================================================================
 
+/// Id of the `roots/list` requests this server sends to the client
+const ROOTS_REQUEST_ID: &str = "prompt-forge/roots";
+
 pub struct McpServer {
     db_path: PathBuf,
-    agents: Vec<Agent>,
-    skills: Vec<Skill>,
-    instructions: Vec<Instruction>,
+    db: Option<Database>, // Opened on the first request that reads the library
+    db_retry: TtlCache<()>, // Limits how often a failed open is retried
+    read_only: bool, // Set by --read-only; opens the database read-only and rejects tools that write to the library
+    channel: Option<String>, // Set by --channel; serves that channel's release instead of the live library
+    serving_release: Option<String>, // Release tag the channel resolved to on the last check
+    channel_checked: TtlCache<()>,
+    current_agent_id: Option<String>, // Agent most recently applied in this session
+    session_id: String,               // Picks the experiment variants this session is served
+    experiment_variants: Vec<(String, Variant)>, // Variant served per experimented instruction
+    recorded_variants: HashSet<String>, // Experiments whose variant this session has recorded
+    usage_events: UsageEventQueue,
+    client_roots: bool, // Client declared the `roots` capability in initialize
+    client_name: Option<String>, // `clientInfo.name` from initialize, e.g. "claude-code"
+    workspace_root: Option<PathBuf>, // First file:// root reported by the client
+    outgoing: Vec<Value>, // Requests to send to the client after the current message
+    log: McpLog,        // Diagnostics sent to the client as notifications/message
+    exit_requested: bool, // Set by notifications/exit; the stdio loop stops after the current line
+    agents: TtlCache<Vec<Agent>>,
+    skills: TtlCache<Vec<Skill>>,
+    instructions: TtlCache<Vec<Instruction>>,
+    snippets: TtlCache<Vec<Snippet>>,
+    personas: PersonaCache, // Assembled apply_agent prompts
 }
 
+/// Results `search_prompts` and `suggest_skills_for_context` return unless asked for another
+/// number, and the most they return
+const SEARCH_LIMIT: usize = 10;
+const MAX_SEARCH_LIMIT: usize = 50;
+
+/// How long library data read from the database is served before it is read again
+const LIBRARY_TTL: Duration = Duration::from_secs(3);
+
+/// Parts of the library a request reads, so each loads only what it needs
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum LibraryPart {
+    Agents,
+    Skills,
+    Instructions,
+}
+
+const ALL_PARTS: &[LibraryPart] = &[
+    LibraryPart::Agents,
+    LibraryPart::Skills,
+    LibraryPart::Instructions,
+];
+
 impl McpServer {
-    pub fn new(db_path: PathBuf) -> Self {
+    pub fn new(db_path: PathBuf, read_only: bool, channel: Option<String>) -> Self {
         Self {
             db_path,
-            agents: Vec::new(),
-            skills: Vec::new(),
-            instructions: Vec::new(),
+            db: None,
+            db_retry: TtlCache::new(LIBRARY_TTL),
+            read_only,
+            channel,
+            serving_release: None,
+            channel_checked: TtlCache::new(LIBRARY_TTL),
+            current_agent_id: None,
+            session_id: uuid::Uuid::new_v4().to_string(),
+            experiment_variants: Vec::new(),
+            recorded_variants: HashSet::new(),
+            usage_events: UsageEventQueue::default(),
+            client_roots: false,
+            client_name: None,
+            workspace_root: None,
+            outgoing: Vec::new(),
+            log: McpLog::default(),
+            exit_requested: false,
+            agents: TtlCache::new(LIBRARY_TTL),
+            skills: TtlCache::new(LIBRARY_TTL),
+            instructions: TtlCache::new(LIBRARY_TTL),
+            snippets: TtlCache::new(LIBRARY_TTL),
+            personas: PersonaCache::default(),
         }
     }
 
+    /// Re-read the whole library now, ignoring cached data
     pub fn load_data(&mut self) -> Result<(), String> {
-        // Load data from SQLite database
-        let db = Database::open(&self.db_path)
-            .map_err(|e| format!("Failed to open database: {}", e))?;
-
-        self.agents = db
-            .get_all_agents()
-            .map_err(|e| format!("Failed to load agents: {}", e))?;
-        self.skills = db
-            .get_all_skills()
-            .map_err(|e| format!("Failed to load skills: {}", e))?;
-        self.instructions = db
-            .get_all_instructions()
-            .map_err(|e| format!("Failed to load instructions: {}", e))?;
-
-        eprintln!(
-            "Loaded {} agents, {} skills, {} instructions from database",
-            self.agents.len(),
-            self.skills.len(),
-            self.instructions.len()
-        );
+        self.db_retry.expire();
+        self.channel_checked.expire();
+        self.agents.expire();
+        self.skills.expire();
+        self.instructions.expire();
+        self.snippets.expire();
+        self.refresh(ALL_PARTS)
+    }
+
+    /// Bring the parts of the library a request reads up to date. Each part
+    /// is read from the database on first use and again once it is older
+    /// than `LIBRARY_TTL`, so a large library is never loaded wholesale just
+    /// to answer one tool call, and edits made in the app show up within a
+    /// few seconds. A release served on a channel is frozen, so only the
+    /// channel itself is re-checked.
+    fn refresh(&mut self, parts: &[LibraryPart]) -> Result<(), String> {
+        self.open_db()?;
+        let Some(db) = &self.db else {
+            return Ok(());
+        };
+
+        if self.channel.is_some() && !self.channel_checked.is_fresh() {
+            let tag = self.channel_tag(db)?;
+            self.channel_checked.set(());
+
+            if tag != self.serving_release {
+                match &tag {
+                    Some(tag) => {
+                        let release = db
+                            .get_release(tag)
+                            .map_err(|e| format!("Failed to load release: {}", e))?
+                            .ok_or_else(|| {
+                                format!(
+                                    "Channel '{}' points at missing release '{}'",
+                                    self.channel.as_deref().unwrap_or_default(),
+                                    tag
+                                )
+                            })?;
+                        self.log.info(
+                            "library",
+                            format!(
+                                "Serving release '{}' on channel '{}'",
+                                release.tag,
+                                self.channel.as_deref().unwrap_or_default()
+                            ),
+                        );
+                        self.agents.set(release.agents);
+                        self.skills.set(release.skills);
+                        self.instructions.set(release.instructions);
+                        // Releases are frozen, so they don't run experiments
+                        self.experiment_variants.clear();
+                    }
+                    None => {
+                        self.agents.expire();
+                        self.skills.expire();
+                        self.instructions.expire();
+                    }
+                }
+                self.serving_release = tag;
+            }
+        }
+        if self.serving_release.is_some() {
+            return Ok(());
+        }
+
+        if !parts.is_empty() {
+            self.snippets.refresh_with(|| Ok(load_snippets(db)))?;
+        }
+        let snippets = &self.snippets;
+        for part in parts {
+            let started = Instant::now();
+            let loaded = match part {
+                LibraryPart::Agents => self.agents.refresh_with(|| {
+                    let mut agents = db
+                        .get_all_agents()
+                        .map_err(|e| format!("Failed to load agents: {}", e))?;
+                    snippets::expand_library(snippets, &mut agents, &mut [], &mut []);
+                    Ok(agents)
+                })?,
+                LibraryPart::Skills => self.skills.refresh_with(|| {
+                    let mut skills = db
+                        .get_all_skills()
+                        .map_err(|e| format!("Failed to load skills: {}", e))?;
+                    snippets::expand_library(snippets, &mut [], &mut skills, &mut []);
+                    Ok(skills)
+                })?,
+                LibraryPart::Instructions => {
+                    let (session_id, variants) = (&self.session_id, &mut self.experiment_variants);
+                    self.instructions.refresh_with(|| {
+                        let mut instructions = db
+                            .get_all_instructions()
+                            .map_err(|e| format!("Failed to load instructions: {}", e))?;
+                        snippets::expand_library(snippets, &mut [], &mut [], &mut instructions);
+                        // A database the app hasn't migrated yet has no experiments
+                        let running = db.get_experiments().unwrap_or_default();
+                        *variants = experiments::apply(&mut instructions, &running, session_id);
+                        let profile = db.get_settings().ok().and_then(|s| s.active_profile);
+                        activation::apply(
+                            &mut instructions,
+                            chrono::Local::now().naive_local(),
+                            profile.as_deref(),
+                        );
+                        Ok(instructions)
+                    })?
+                }
+            };
+            if loaded {
+                self.log.debug(
+                    "library",
+                    format!(
+                        "Loaded {:?} from database in {}ms",
+                        part,
+                        started.elapsed().as_millis()
+                    ),
+                );
+            }
+        }
 
         Ok(())
     }
 
-    /// Run the MCP server (STDIO mode)
-    pub fn run(&mut self) -> io::Result<()> {
-        if let Err(e) = self.load_data() {
-            eprintln!("Warning: Failed to load data from database: {}", e);
-            eprintln!("MCP server will start with empty data");
+    /// Open the database if it isn't open yet. Opening may involve a keychain
+    /// lookup, so a failed attempt is only retried after `LIBRARY_TTL`.
+    fn open_db(&mut self) -> Result<(), String> {
+        if self.db.is_some() || self.db_retry.is_fresh() {
+            return Ok(());
+        }
+        self.db_retry.set(());
+        self.db = Some(if self.read_only {
+            encryption::open_database_read_only(&self.db_path)?
+        } else {
+            encryption::open_database(&self.db_path)?
+        });
+        Ok(())
+    }
+
+    /// Release tag this server's channel points at; `None` serves the live library
+    fn channel_tag(&self, db: &Database) -> Result<Option<String>, String> {
+        let Some(channel) = &self.channel else {
+            return Ok(None);
+        };
+
+        let settings = db
+            .get_settings()
+            .map_err(|e| format!("Failed to load settings: {}", e))?;
+        let newest = db
+            .get_releases()
+            .map_err(|e| format!("Failed to load releases: {}", e))?
+            .into_iter()
+            .next()
+            .map(|r| r.tag);
+
+        releases::channel_release(channel, &settings.release_channels, newest.as_deref())
+    }
+
+    /// Whether tools may write to the library. Servers pinned to a release
+    /// don't serve the live library, so they can't edit it either.
+    fn writable(&self) -> bool {
+        !self.read_only && self.serving_release.is_none()
+    }
+
+    /// Whether `create_instruction`, `update_instruction` and `create_skill`
+    /// are offered: the library must be writable and the user must have
+    /// turned them on in settings
+    fn write_tools_enabled(&self) -> bool {
+        self.writable()
+            && self
+                .db
+                .as_ref()
+                .and_then(|db| db.get_settings().ok())
+                .is_some_and(|settings| settings.mcp_write_tools)
+    }
+
+    /// Handler digests of the tool skills clients may run, by skill id. Empty
+    /// unless the user turned on running tools in settings.
+    fn tool_approvals(&self) -> HashMap<String, String> {
+        let Some(db) = &self.db else {
+            return HashMap::new();
+        };
+        if !db
+            .get_settings()
+            .is_ok_and(|settings| settings.mcp_run_tools)
+        {
+            return HashMap::new();
+        }
+        db.get_tool_approvals().unwrap_or_default()
+    }
+
+    /// Refuse a write tool when the library can't be written
+    fn ensure_writable(&self, tool: &str) -> Result<(), String> {
+        if self.read_only {
+            return Err(format!(
+                "{} is unavailable: the MCP server is running read-only",
+                tool
+            ));
+        }
+        if let Some(tag) = &self.serving_release {
+            return Err(format!(
+                "{} is unavailable: the MCP server is serving release '{}'",
+                tool, tag
+            ));
         }
+        Ok(())
+    }
 
+    fn ensure_write_tools(&self, tool: &str) -> Result<(), String> {
+        self.ensure_writable(tool)?;
+        if !self.write_tools_enabled() {
+            return Err(format!(
+                "{} is unavailable: turn on MCP write tools in Prompt Forge's settings",
+                tool
+            ));
+        }
+        Ok(())
+    }
+    /// Run the MCP server (STDIO mode)
+    pub fn run(&mut self) -> io::Result<()> {
         let stdin = io::stdin();
         let mut stdout = io::stdout();
 
-        eprintln!("Prompt Forge MCP Server started");
-        eprintln!("Database path: {:?}", self.db_path);
+        self.log.info(
+            "server",
+            format!("Prompt Forge MCP server started on {:?}", self.db_path),
+        );
 
-        for line in stdin.lock().lines() {
-            let line = line?;
-            if line.is_empty() {
-                continue;
+        // Lines are read as bytes so invalid UTF-8 gets a parse error instead
+        // of ending the loop
+        let mut stdin = stdin.lock();
+        let mut buffer = Vec::new();
+        while stdin.read_until(b'\n', &mut buffer)? > 0 {
+            let line = String::from_utf8_lossy(&buffer);
+            for message in self.handle_line(line.trim_end_matches(['\r', '\n'])) {
+                writeln!(stdout, "{}", message)?;
+                stdout.flush()?;
+            }
+            buffer.clear();
+            if self.exit_requested {
+                break;
             }
+        }
 
-            // Parse the JSON-RPC request
-            match serde_json::from_str::<JsonRpcRequest>(&line) {
-                Ok(request) => {
-                    // Notifications (no id) should not receive responses
-                    let is_notification = request.id.is_none();
-                    let response = self.handle_request(request);
-
-                    if !is_notification {
-                        let response_json = serde_json::to_string(&response).unwrap();
-                        writeln!(stdout, "{}", response_json)?;
-                        stdout.flush()?;
-                    }
+        // Stdin closed or the app asked the server to exit
+        self.shutdown();
+        Ok(())
+    }
+
+    /// Handle one line from the client and return the messages to write back:
+    /// the response (none for notifications and client replies), then any
+    /// requests queued for the client
+    pub fn handle_line(&mut self, line: &str) -> Vec<String> {
+        let mut messages = Vec::new();
+        if line.is_empty() {
+            return messages;
+        }
+
+        // Parse the JSON-RPC request
+        match serde_json::from_str::<JsonRpcRequest>(line) {
+            Ok(request) if request.jsonrpc != "2.0" => {
+                messages.push(
+                    serde_json::to_string(&JsonRpcResponse {
+                        jsonrpc: "2.0".to_string(),
+                        id: request.id,
+                        result: None,
+                        error: Some(JsonRpcError {
+                            code: -32600,
+                            message: format!("Unsupported JSON-RPC version: {}", request.jsonrpc),
+                            data: None,
+                        }),
+                    })
+                    .unwrap(),
+                );
+            }
+            Ok(request) => {
+                // Notifications (no id) should not receive responses
+                let is_notification = request.id.is_none();
+                let response = self.handle_request(request);
+
+                if !is_notification {
+                    messages.push(serde_json::to_string(&response).unwrap());
                 }
-                Err(e) => {
-                    let error_response = JsonRpcResponse {
+            }
+            Err(e) => {
+                // Replies to our own requests (e.g. roots/list) have no method
+                if let Some(reply) = client_reply(line) {
+                    self.handle_client_reply(&reply);
+                    return messages;
+                }
+                // Valid JSON that isn't a request (a batch, a missing method)
+                // is an invalid request, answered with its id when it has one
+                let error_response = match serde_json::from_str::<Value>(line) {
+                    Ok(message) => JsonRpcResponse {
+                        jsonrpc: "2.0".to_string(),
+                        id: message
+                            .get("id")
+                            .filter(|id| id.is_string() || id.is_number())
+                            .cloned(),
+                        result: None,
+                        error: Some(JsonRpcError {
+                            code: -32600,
+                            message: format!("Invalid request: {}", e),
+                            data: None,
+                        }),
+                    },
+                    Err(_) => JsonRpcResponse {
                         jsonrpc: "2.0".to_string(),
                         id: None,
                         result: None,
@@ -205,31 +571,106 @@ impl McpServer {
                             message: format!("Parse error: {}", e),
                             data: None,
                         }),
-                    };
-                    let response_json = serde_json::to_string(&error_response).unwrap();
-                    writeln!(stdout, "{}", response_json)?;
-                    stdout.flush()?;
-                }
+                    },
+                };
+                messages.push(serde_json::to_string(&error_response).unwrap());
             }
         }
 
-        Ok(())
+        messages.extend(
+            self.log
+                .drain()
+                .iter()
+                .map(|message| message.to_notification().to_string()),
+        );
+        messages.extend(self.outgoing.drain(..).map(|message| message.to_string()));
+        messages
     }
 
-    fn handle_request(&mut self, request: JsonRpcRequest) -> JsonRpcResponse {
-        eprintln!("Received method: {}", request.method);
+    /// Log messages not yet sent, for the console to print
+    pub(crate) fn drain_log(&mut self) -> Vec<LogMessage> {
+        self.log.drain()
+    }
+
+    /// Last chance to write events that were queued while the database was busy
+    pub(crate) fn flush_usage_events(&mut self) -> Result<(), String> {
+        match &self.db {
+            Some(db) => self.usage_events.flush(db),
+            None => Ok(()),
+        }
+    }
+
+    /// Save what is only held in memory before the process exits: queued
+    /// usage events, then the write-ahead log folded into the database file.
+    /// The client is gone by now, so failures go to stderr.
+    pub(crate) fn shutdown(&mut self) {
+        if let Err(e) = self.flush_usage_events() {
+            eprintln!("{}", e);
+        }
+        if let Some(db) = self.db.as_ref().filter(|_| !self.read_only) {
+            if let Err(e) = db.checkpoint() {
+                eprintln!("Failed to checkpoint database: {}", e);
+            }
+        }
+    }
+
+    pub(crate) fn handle_request(&mut self, request: JsonRpcRequest) -> JsonRpcResponse {
+        self.log
+            .debug("server", format!("Received method: {}", request.method));
+        let parts = library_parts(&request.method, request.params.as_ref());
+        if let Some(parts) = parts {
+            if let Err(e) = self.refresh(parts) {
+                self.log
+                    .warning("library", format!("Failed to load library: {}", e));
+            }
+        }
 
         let result = match request.method.as_str() {
-            "initialize" => self.handle_initialize(),
-            "initialized" => Ok(json!({})),
+            "initialize" => {
+                // A new session starts without an applied agent or workspace,
+                // and is assigned its own experiment variants
+                self.current_agent_id = None;
+                self.workspace_root = None;
+                self.session_id = uuid::Uuid::new_v4().to_string();
+                self.recorded_variants.clear();
+                self.instructions.expire();
+                self.log.set_level(mcp_log::DEFAULT_LEVEL);
+                self.client_roots = request
+                    .params
+                    .as_ref()
+                    .and_then(|p| p.pointer("/capabilities/roots"))
+                    .is_some();
+                self.client_name = request
+                    .params
+                    .as_ref()
+                    .and_then(|p| p.pointer("/clientInfo/name"))
+                    .and_then(|name| name.as_str())
+                    .map(|name| scope::normalize_contexts(&[name.to_string()]))
+                    .and_then(|names| names.into_iter().next());
+                self.handle_initialize()
+            }
+            "initialized" | "notifications/initialized" | "notifications/roots/list_changed" => {
+                self.request_roots();
+                Ok(json!({}))
+            }
             "tools/list" => self.handle_tools_list(),
             "tools/call" => self.handle_tools_call(request.params),
             "resources/list" => self.handle_resources_list(),
             "resources/read" => self.handle_resources_read(request.params),
+            "resources/templates/list" => self.handle_resource_templates_list(),
             "ping" => Ok(json!({})),
+            "logging/setLevel" => self.handle_set_level(request.params),
+            // Sent by the app when it stops the server, ahead of closing stdin
+            "notifications/exit" => {
+                self.exit_requested = true;
+                Ok(json!({}))
+            }
             // Reload data from database on request
             "notifications/reload" => {
-                let _ = self.load_data();
+                if let Err(e) = self.load_data() {
+                    self.log
+                        .warning("library", format!("Failed to reload library: {}", e));
+                }
                 Ok(json!({"reloaded": true}))
             }
             _ => Err(JsonRpcError {
@@ -239,6 +680,10 @@ impl McpServer {
             }),
         };
 
+        if result.is_ok() && parts.is_some_and(|parts| parts.contains(&LibraryPart::Instructions)) {
+            self.record_variants_served();
+        }
+
         match result {
             Ok(result) => JsonRpcResponse {
                 jsonrpc: "2.0".to_string(),
@@ -264,6 +709,7 @@ impl McpServer {
                     list_changed: false,
                     subscribe: false
                 },
+                logging: LoggingCapability {},
             },
             server_info: ServerInfo {
                 name: "prompt-forge".to_string(),
@@ -272,8 +718,77 @@ impl McpServer {
         }))
     }
 
+    /// Send log messages at `level` and above from now on
+    fn handle_set_level(&mut self, params: Option<Value>) -> Result<Value, JsonRpcError> {
+        let level = params
+            .as_ref()
+            .and_then(|p| p.get("level"))
+            .and_then(|v| v.as_str());
+        let Some(level) = level.and_then(LogLevel::parse) else {
+            return Err(JsonRpcError {
+                code: -32602,
+                message: format!("Unknown log level: {}", level.unwrap_or("(none)")),
+                data: None,
+            });
+        };
+        self.log.set_level(level);
+        Ok(json!({}))
+    }
+
+    /// Ask the client for its workspace roots, if it supports them
+    fn request_roots(&mut self) {
+        if self.client_roots {
+            self.outgoing.push(json!({
+                "jsonrpc": "2.0",
+                "id": ROOTS_REQUEST_ID,
+                "method": "roots/list"
+            }));
+        }
+    }
+
+    fn handle_client_reply(&mut self, reply: &Value) {
+        if reply.get("id").and_then(|id| id.as_str()) != Some(ROOTS_REQUEST_ID) {
+            return;
+        }
+        if let Some(error) = reply.get("error") {
+            self.log
+                .warning("session", format!("Client rejected roots/list: {}", error));
+            return;
+        }
+
+        // Only local directories can hold a .promptforge.toml
+        self.workspace_root = reply
+            .pointer("/result/roots")
+            .and_then(|roots| roots.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|root| root.get("uri").and_then(|uri| uri.as_str()))
+            .find_map(workspace::root_from_uri);
+
+        if let Some(root) = &self.workspace_root {
+            self.log
+                .info("session", format!("Workspace root: {:?}", root));
+        }
+    }
+
+    /// Workspace for template variables: the `workspace_root` argument if
+    /// given, otherwise the root reported by the client
+    fn workspace_for(&self, args: &Value) -> Result<Option<Workspace>, String> {
+        let root = match args.get("workspace_root").and_then(|v| v.as_str()) {
+            Some(root) => Some(PathBuf::from(root)),
+            None => self.workspace_root.clone(),
+        };
+        root.map(|root| Workspace::load(&root)).transpose()
+    }
+
     fn handle_tools_list(&self) -> Result<Value, JsonRpcError> {
-        let tools = vec![
+        let mut tools = self.built_in_tools();
+        self.add_skill_tools(&mut tools);
+        Ok(json!({ "tools": tools }))
+    }
+
+    fn built_in_tools(&self) -> Vec<Tool> {
+        let mut tools = vec![
             Tool {
                 name: "get_agent".to_string(),
                 description: "Get a Prompt Forge agent's full configuration including system prompt, personality, and attached skills/instructions".to_string(),
@@ -304,11 +819,53 @@ impl McpServer {
                     "properties": {
                         "category": {
                             "type": "string",
-                            "description": "Optional category filter: general, code_style, communication, workflow, security, testing, documentation, custom"
+                            "description": "Optional category filter: general, code_style, communication, workflow, security, testing, documentation, custom. Use a path such as 'code_style/rust' to narrow to a subcategory; a category includes all of its subcategories."
+                        },
+                        "locale": {
+                            "type": "string",
+                            "description": "Return reviewed translations for this locale (e.g. 'de', 'pt-BR') where they exist"
+                        }
+                    }
+                }),
+            },
+            Tool {
+                name: "get_instructions_for_context".to_string(),
+                description: "Get the enabled instructions that apply to the current work: unscoped ones, plus those written for a named context (language, project or client) or for the files being changed".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "contexts": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "description": "Contexts of the session, e.g. [\"rust\", \"frontend\"]. The client's name and the workspace's name are added automatically"
+                        },
+                        "path": {
+                            "type": "string",
+                            "description": "File path (src/db.rs) or extension (.rs) being worked on; its language is added as a context"
+                        },
+                        "diff": {
+                            "type": "string",
+                            "description": "Unified diff whose changed files are used like path"
+                        },
+                        "workspace_root": {
+                            "type": "string",
+                            "description": "Project directory whose name is used as a context. Defaults to the client's first root"
+                        },
+                        "locale": {
+                            "type": "string",
+                            "description": "Return reviewed translations for this locale (e.g. 'de', 'pt-BR') where they exist"
                         }
                     }
                 }),
             },
+            Tool {
+                name: "get_pinned_context".to_string(),
+                description: "Get only the pinned, non-negotiable instructions from Prompt Forge. Small enough to attach to every conversation".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {}
+                }),
+            },
             Tool {
                 name: "get_skill".to_string(),
                 description: "Get a specific skill's full configuration and prompt template. Use the skill name (e.g., 'code-review', 'frontend-design') or ID.".to_string(),
@@ -323,6 +880,29 @@ impl McpServer {
                     "required": ["skill_id"]
                 }),
             },
+            Tool {
+                name: "render_skill".to_string(),
+                description: "Fill in a prompt skill's {{variable}} placeholders and return the finished prompt. get_skill lists the variables a skill takes".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "skill_id": {
+                            "type": "string",
+                            "description": "The ID or name of the prompt skill to render"
+                        },
+                        "arguments": {
+                            "type": "object",
+                            "additionalProperties": true,
+                            "description": "Variable values keyed by name, e.g. {\"diff\": \"...\"}. Variables with a default may be left out"
+                        },
+                        "workspace_root": {
+                            "type": "string",
+                            "description": "Project directory used for {{workspace.name}}, {{workspace.root}} and values from its .promptforge.toml. Defaults to the client's first root"
+                        }
+                    },
+                    "required": ["skill_id"]
+                }),
+            },
             Tool {
                 name: "list_skills".to_string(),
                 description: "List all available skills".to_string(),
@@ -331,6 +911,66 @@ impl McpServer {
                     "properties": {}
                 }),
             },
+            Tool {
+                name: "search_prompts".to_string(),
+                description: "Search agents, skills and instructions by free text; returns ids and short descriptions of the best matches".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "query": {
+                            "type": "string",
+                            "description": "Words that must all appear in a name, tag, description or body"
+                        },
+                        "types": {
+                            "type": "array",
+                            "items": { "type": "string", "enum": ["agent", "skill", "instruction"] },
+                            "description": "Only search these kinds of item. Defaults to all"
+                        },
+                        "limit": {
+                            "type": "integer",
+                            "description": format!("Maximum number of results (default {}, at most {})", SEARCH_LIMIT, MAX_SEARCH_LIMIT)
+                        }
+                    },
+                    "required": ["query"]
+                }),
+            },
+            Tool {
+                name: "suggest_skills_for_context".to_string(),
+                description: "Suggest the skills and instructions most relevant to the files a task touches, given a file path, an extension or a unified diff".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "path": {
+                            "type": "string",
+                            "description": "File path (src/db.rs) or extension (.rs) being worked on"
+                        },
+                        "diff": {
+                            "type": "string",
+                            "description": "Unified diff whose changed files are used"
+                        },
+                        "limit": {
+                            "type": "integer",
+                            "description": format!("Maximum number of suggestions (default {}, at most {})", SEARCH_LIMIT, MAX_SEARCH_LIMIT)
+                        }
+                    }
+                }),
+            },
+            Tool {
+                name: "list_releases".to_string(),
+                description: "List tagged library releases that apply_agent can assemble from".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {}
+                }),
+            },
+            Tool {
+                name: "get_current_agent".to_string(),
+                description: "Get the agent most recently applied in this session, if any".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {}
+                }),
+            },
             Tool {
                 name: "apply_agent".to_string(),
                 description: "Apply an agent's configuration - returns the full system prompt with all attached skills and instructions combined".to_string(),
@@ -340,17 +980,167 @@ impl McpServer {
                         "agent_id": {
                             "type": "string",
                             "description": "The ID of the agent to apply"
+                        },
+                        "numbered": {
+                            "type": "boolean",
+                            "description": "Number sections (e.g. 3.2) and tag each instruction with a {#instruction-<id>} anchor so cited rules can be traced back"
+                        },
+                        "workspace_root": {
+                            "type": "string",
+                            "description": "Project directory used for {{workspace.name}}, {{workspace.root}} and values from its .promptforge.toml. Defaults to the client's first root"
+                        },
+                        "release": {
+                            "type": "string",
+                            "description": "Assemble from the library frozen under this release tag instead of the live library ('latest')"
+                        },
+                        "locale": {
+                            "type": "string",
+                            "description": "Use reviewed translations for this locale (e.g. 'de', 'pt-BR') where they exist"
+                        },
+                        "branch": {
+                            "type": "string",
+                            "description": "Git branch being worked on; instructions scoped to other branches are left out. Defaults to the branch checked out in the workspace"
+                        },
+                        "trace": {
+                            "type": "boolean",
+                            "description": "Return JSON with the prompt plus a trace of every skill and instruction that was included or left out, and why"
                         }
                     },
                     "required": ["agent_id"]
                 }),
             },
+            Tool {
+                name: "compose_prompt".to_string(),
+                description: "Assemble a one-off prompt from any skills and instructions (by id or name) plus inline text, without saving an agent".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "skills": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "description": "Skill ids or names to include"
+                        },
+                        "instructions": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "description": "Instruction ids or names to include"
+                        },
+                        "text": {
+                            "type": "string",
+                            "description": "Inline text placed at the top of the prompt, e.g. a task-specific persona"
+                        },
+                        "workspace_root": {
+                            "type": "string",
+                            "description": "Project directory used for {{workspace.name}}, {{workspace.root}} and values from its .promptforge.toml. Defaults to the client's first root"
+                        },
+                        "numbered": {
+                            "type": "boolean",
+                            "description": "Number sections (e.g. 3.2) and tag each instruction with a {#instruction-<id>} anchor so cited rules can be traced back"
+                        }
+                    }
+                }),
+            },
+            Tool {
+                name: "help".to_string(),
+                description: "Describe the tools this server offers, including the ones made from skills: their arguments and an example call for each".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "tool": {
+                            "type": "string",
+                            "description": "Describe only this tool"
+                        },
+                        "query": {
+                            "type": "string",
+                            "description": "Only tools whose name or description contains all of these words"
+                        }
+                    }
+                }),
+            },
         ];
 
-        Ok(json!({ "tools": tools }))
+        if self.writable() {
+            tools.push(Tool {
+                name: "configure_agent".to_string(),
+                description: "Attach or detach skills and instructions on an agent in one call and save the result. Accepts ids or names".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "agent_id": {
+                            "type": "string",
+                            "description": "The ID or name of the agent to configure"
+                        },
+                        "attach_skills": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "description": "Skill ids or names to attach"
+                        },
+                        "detach_skills": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "description": "Skill ids or names to detach"
+                        },
+                        "attach_instructions": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "description": "Instruction ids or names to attach"
+                        },
+                        "detach_instructions": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "description": "Instruction ids or names to detach"
+                        }
+                    },
+                    "required": ["agent_id"]
+                }),
+            });
+        }
+
+        if self.write_tools_enabled() {
+            tools.extend(write_tools());
+        }
+
+        tools
+    }
+
+    /// Enabled tool skills with a handler run as tools of their own;
+    /// built-in tools win on a name clash
+    fn add_skill_tools(&self, tools: &mut Vec<Tool>) {
+        let approvals = self.tool_approvals();
+        for skill in self.skills.iter() {
+            let Some((parameters, _, _)) = runnable_tool(skill, &approvals) else {
+                continue;
+            };
+            let name = tool_schema::mcp_tool_name(&skill.name);
+            if tools.iter().any(|t| t.name == name) {
+                continue;
+            }
+            tools.push(Tool {
+                name,
+                description: skill.description.clone(),
+                input_schema: tool_schema::parameters_to_input_schema(parameters),
+            });
+        }
+
+        // Enabled prompt skills that follow others are one tool rendering the chain
+        for skill in self.skills.iter().filter(|s| is_chained(s)) {
+            let Ok(chain) = composition::skill_chain(skill, &self.skills) else {
+                continue;
+            };
+            let name = tool_schema::mcp_tool_name(&skill.name);
+            if tools.iter().any(|t| t.name == name) {
+                continue;
+            }
+            let steps: Vec<&str> = chain.iter().map(|s| s.name.as_str()).collect();
+            tools.push(Tool {
+                name,
+                description: format!("{} (renders {})", skill.description, steps.join(" → ")),
+                input_schema: template::variables_input_schema(&template::chain_variables(&chain)),
+            });
+        }
     }
 
-    fn handle_tools_call(&self, params: Option<Value>) -> Result<Value, JsonRpcError> {
+    fn handle_tools_call(&mut self, params: Option<Value>) -> Result<Value, JsonRpcError> {
         let params = params.ok_or(JsonRpcError {
             code: -32602,
             message: "Invalid params".to_string(),
@@ -368,14 +1158,30 @@ impl McpServer {
 
         let arguments = params.get("arguments").cloned().unwrap_or(json!({}));
 
+        self.record_insight(InsightMetric::McpCall, tool_name);
+        self.log.debug("tools", format!("Calling {}", tool_name));
+
         let result = match tool_name {
             "get_agent" => self.tool_get_agent(&arguments),
             "list_agents" => self.tool_list_agents(),
             "get_instructions" => self.tool_get_instructions(&arguments),
+            "get_instructions_for_context" => self.tool_get_instructions_for_context(&arguments),
+            "get_pinned_context" => Ok(self.tool_get_pinned_context()),
             "get_skill" => self.tool_get_skill(&arguments),
             "list_skills" => self.tool_list_skills(),
+            "render_skill" => self.tool_render_skill(&arguments),
             "apply_agent" => self.tool_apply_agent(&arguments),
-            _ => Err(format!("Unknown tool: {}", tool_name)),
+            "get_current_agent" => self.tool_get_current_agent(),
+            "configure_agent" => self.tool_configure_agent(&arguments),
+            "create_instruction" => self.tool_create_instruction(&arguments),
+            "update_instruction" => self.tool_update_instruction(&arguments),
+            "create_skill" => self.tool_create_skill(&arguments),
+            "compose_prompt" => self.tool_compose_prompt(&arguments),
+            "list_releases" => self.tool_list_releases(),
+            "search_prompts" => self.tool_search_prompts(&arguments),
+            "suggest_skills_for_context" => self.tool_suggest_skills_for_context(&arguments),
+            "help" => self.tool_help(&arguments),
+            _ => self.tool_run_skill(tool_name, &arguments),
         };
 
         match result {
@@ -386,13 +1192,17 @@ impl McpServer {
                 }],
                 is_error: None,
             })),
-            Err(error) => Ok(json!(ToolResult {
-                content: vec![ToolContent {
-                    content_type: "text".to_string(),
-                    text: error,
-                }],
-                is_error: Some(true),
-            })),
+            Err(error) => {
+                self.log
+                    .error("tools", format!("{} failed: {}", tool_name, error));
+                Ok(json!(ToolResult {
+                    content: vec![ToolContent {
+                        content_type: "text".to_string(),
+                        text: error,
+                    }],
+                    is_error: Some(true),
+                }))
+            }
         }
     }
 
@@ -400,7 +1210,7 @@ impl McpServer {
         let mut resources = Vec::new();
 
         // Expose agents as resources
-        for agent in &self.agents {
+        for agent in self.agents.iter() {
             resources.push(Resource {
                 uri: format!("prompt-forge://agents/{}", agent.id),
                 name: agent.name.clone(),
@@ -417,9 +1227,52 @@ impl McpServer {
             mime_type: "text/markdown".to_string(),
         });
 
+        resources.push(Resource {
+            uri: "prompt-forge://instructions/pinned".to_string(),
+            name: "Pinned Instructions".to_string(),
+            description: "Only the instructions pinned to the system context".to_string(),
+            mime_type: "text/markdown".to_string(),
+        });
+
+        resources.push(Resource {
+            uri: "prompt-forge://instructions/compact".to_string(),
+            name: "Compact Instructions".to_string(),
+            description: format!(
+                "Highest-priority instructions within {} tokens, for small-context clients; add ?tokens=N for another budget",
+                DEFAULT_COMPACT_TOKENS
+            ),
+            mime_type: "text/markdown".to_string(),
+        });
+
         Ok(json!({ "resources": resources }))
     }
 
+    fn handle_resource_templates_list(&self) -> Result<Value, JsonRpcError> {
+        let templates = vec![
+            ResourceTemplate {
+                uri_template: "prompt-forge://instructions/tag/{tag}".to_string(),
+                name: "Instructions by Tag".to_string(),
+                description: "Enabled instructions carrying a tag".to_string(),
+                mime_type: "text/markdown".to_string(),
+            },
+            ResourceTemplate {
+                uri_template: "prompt-forge://instructions/compact?tokens={tokens}".to_string(),
+                name: "Compact Instructions".to_string(),
+                description: "Highest-priority instructions cut to fit a token budget".to_string(),
+                mime_type: "text/markdown".to_string(),
+            },
+            ResourceTemplate {
+                uri_template: "prompt-forge://instructions/priority/{min}".to_string(),
+                name: "Instructions by Priority".to_string(),
+                description: "Enabled instructions with at least the given priority (1-10)"
+                    .to_string(),
+                mime_type: "text/markdown".to_string(),
+            },
+        ];
+
+        Ok(json!({ "resourceTemplates": templates }))
+    }
+
     fn handle_resources_read(&self, params: Option<Value>) -> Result<Value, JsonRpcError> {
         let params = params.ok_or(JsonRpcError {
             code: -32602,
@@ -436,29 +1289,39 @@ impl McpServer {
                 data: None,
             })?;
 
-        if uri == "prompt-forge://instructions/all" {
-            let content = self.get_all_instructions_markdown();
-            return Ok(json!({
-                "contents": [ResourceContent {
+        let content = match route_resource(uri) {
+            Some(ResourceRoute::Agent(agent_id)) => self
+                .agents
+                .iter()
+                .find(|a| a.id == agent_id)
+                .map(|agent| ResourceContent {
                     uri: uri.to_string(),
-                    mime_type: "text/markdown".to_string(),
-                    text: content,
-                }]
-            }));
-        }
-
-        if uri.starts_with("prompt-forge://agents/") {
-            let agent_id = uri.strip_prefix("prompt-forge://agents/").unwrap();
-            if let Some(agent) = self.agents.iter().find(|a| a.id == agent_id) {
-                let content = serde_json::to_string_pretty(agent).unwrap();
-                return Ok(json!({
-                    "contents": [ResourceContent {
-                        uri: uri.to_string(),
-                        mime_type: "application/json".to_string(),
-                        text: content,
-                    }]
-                }));
-            }
+                    mime_type: "application/json".to_string(),
+                    text: serde_json::to_string_pretty(agent).unwrap(),
+                }),
+            Some(ResourceRoute::Instructions(filter)) => Some(ResourceContent {
+                uri: uri.to_string(),
+                mime_type: "text/markdown".to_string(),
+                text: match filter {
+                    InstructionFilter::All => self.get_all_instructions_markdown(),
+                    InstructionFilter::Pinned => self.get_pinned_context_markdown(),
+                    InstructionFilter::Tag(tag) => self.instructions_markdown(
+                        |i| i.tags.iter().any(|t| t.eq_ignore_ascii_case(&tag)),
+                        &format!("No enabled instructions tagged \"{}\".", tag),
+                    ),
+                    InstructionFilter::MinPriority(min) => self.instructions_markdown(
+                        |i| i.priority >= min,
+                        &format!("No enabled instructions with priority {} or higher.", min),
+                    ),
+                    InstructionFilter::Compact(tokens) => {
+                        assembly::instruction_digest(&self.instructions, tokens)
+                    }
+                },
+            }),
+            None => None,
+        };
+        if let Some(content) = content {
+            return Ok(json!({ "contents": [content] }));
         }
 
         Err(JsonRpcError {
@@ -472,24 +1335,18 @@ impl McpServer {
     // Tool Implementations
     // ========================================================================
 
-    fn tool_get_agent(&self, args: &Value) -> Result<String, String> {
+    fn tool_get_agent(&mut self, args: &Value) -> Result<String, String> {
         let agent_id = args
             .get("agent_id")
             .and_then(|v| v.as_str())
             .ok_or("Missing agent_id")?;
 
-        // Try to find by ID first, then by name (case-insensitive)
-        let agent = self
-            .agents
-            .iter()
-            .find(|a| a.id == agent_id)
-            .or_else(|| {
-                let name_lower = agent_id.to_lowercase();
-                self.agents.iter().find(|a| a.name.to_lowercase() == name_lower)
-            })
-            .ok_or(format!("Agent not found: '{}'. Use list_agents to see available agents.", agent_id))?;
+        let agent = resolve_entity(&self.agents, agent_id)?;
+        let output = serde_json::to_string_pretty(agent).unwrap();
 
-        Ok(serde_json::to_string_pretty(agent).unwrap())
+        let agent_id = agent.id.clone();
+        self.record_usage(UsageEventKind::AgentFetched, &agent_id);
+        Ok(output)
     }
 
     fn tool_list_agents(&self) -> Result<String, String> {
@@ -509,63 +1366,155 @@ impl McpServer {
         Ok(serde_json::to_string_pretty(&summary).unwrap())
     }
 
-    fn tool_get_instructions(&self, args: &Value) -> Result<String, String> {
+    fn tool_get_instructions(&mut self, args: &Value) -> Result<String, String> {
         let category_filter = args.get("category").and_then(|v| v.as_str());
 
-        let filtered: Vec<_> = self
-            .instructions
+        if let Some(cat) = category_filter {
+            self.record_insight(InsightMetric::Category, &cat.to_lowercase());
+        }
+
+        let instructions = self.localized_instructions(args)?;
+        let filtered: Vec<_> = instructions
             .iter()
             .filter(|i| i.enabled)
-            .filter(|i| {
-                if let Some(cat) = category_filter {
-                    category_to_string(&i.category) == cat.to_lowercase()
-                } else {
-                    true
-                }
+            .filter(|i| match category_filter {
+                Some(cat) => categories::matches_category(i, cat),
+                None => true,
             })
             .collect();
 
-        if filtered.is_empty() {
-            return Ok("No instructions found.".to_string());
+        Ok(self.serve_instructions(&filtered))
+    }
+
+    fn tool_get_instructions_for_context(&mut self, args: &Value) -> Result<String, String> {
+        let mut contexts: Vec<String> = args
+            .get("contexts")
+            .and_then(|v| v.as_array())
+            .map(|c| {
+                c.iter()
+                    .filter_map(|c| c.as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default();
+        // The client asking and the project it works in are contexts too
+        contexts.extend(self.client_name.clone());
+        if let Some(workspace) = self.workspace_for(args)? {
+            contexts.push(workspace.name);
+        }
+        let paths = suggestions::context_paths(
+            args.get("path").and_then(|v| v.as_str()),
+            args.get("diff").and_then(|v| v.as_str()),
+        );
+
+        let instructions = self.localized_instructions(args)?;
+        let found = scope::instructions_for_context(&instructions, &contexts, &paths);
+        Ok(self.serve_instructions(&found))
+    }
+
+    /// The instructions in the locale an MCP call asks for, if any
+    fn localized_instructions(&mut self, args: &Value) -> Result<Vec<Instruction>, String> {
+        Ok(match self.translations_for(args)? {
+            Some((locale, translations)) => self
+                .instructions
+                .iter()
+                .map(|i| localization::localize_instruction(i, &translations, &locale))
+                .collect(),
+            None => self.instructions.to_vec(),
+        })
+    }
+
+    /// Instructions as `get_instructions` returns them, counting a use of each
+    fn serve_instructions(&mut self, instructions: &[&Instruction]) -> String {
+        if instructions.is_empty() {
+            return "No instructions found.".to_string();
         }
 
         let mut output = String::new();
-        for instruction in filtered {
+        for instruction in instructions {
             output.push_str(&format!(
                 "## {} {} (Priority: {})\n",
                 instruction.icon_emoji, instruction.name, instruction.priority
             ));
             output.push_str(&format!(
                 "Category: {}\n\n",
-                category_to_string(&instruction.category)
+                categories::category_path(instruction)
             ));
             output.push_str(&instruction.content);
             output.push_str("\n\n---\n\n");
         }
 
-        Ok(output)
+        for instruction in instructions {
+            self.record_usage(UsageEventKind::InstructionUsed, &instruction.id);
+        }
+        output
     }
 
-    fn tool_get_skill(&self, args: &Value) -> Result<String, String> {
+    fn tool_get_pinned_context(&mut self) -> String {
+        let served: Vec<String> = self
+            .instructions
+            .iter()
+            .filter(|i| i.enabled && i.pin_to_context)
+            .map(|i| i.id.clone())
+            .collect();
+        for id in &served {
+            self.record_usage(UsageEventKind::InstructionUsed, id);
+        }
+        self.get_pinned_context_markdown()
+    }
+
+    fn tool_get_skill(&mut self, args: &Value) -> Result<String, String> {
         let skill_id = args
             .get("skill_id")
             .and_then(|v| v.as_str())
             .ok_or("Missing skill_id")?;
 
-        // Try to find by ID first, then by name (case-insensitive)
-        let skill = self
-            .skills
-            .iter()
-            .find(|s| s.id == skill_id)
-            .or_else(|| {
-                let name_lower = skill_id.to_lowercase().replace(' ', "-").replace('_', "-");
-                self.skills.iter().find(|s| {
-                    s.name.to_lowercase().replace(' ', "-").replace('_', "-") == name_lower
-                })
-            })
-            .ok_or(format!("Skill not found: '{}'. Use list_skills to see available skills.", skill_id))?;
+        let skill = resolve_entity(&self.skills, skill_id)?;
 
-        Ok(serde_json::to_string_pretty(skill).unwrap())
+        let mut output = serde_json::to_value(skill).unwrap();
+        match &skill.definition {
+            SkillDefinition::Tool { parameters, .. } => {
+                output["inputSchema"] = tool_schema::parameters_to_input_schema(parameters);
+            }
+            SkillDefinition::Prompt {
+                template,
+                variables,
+                follows,
+            } => {
+                output["variables"] = match composition::skill_chain(skill, &self.skills) {
+                    Ok(chain) if !follows.is_empty() => json!(template::chain_variables(&chain)),
+                    _ => json!(template::variables(template, variables)),
+                };
+            }
+            _ => {}
+        }
+
+        let skill_id = skill.id.clone();
+        self.record_usage(UsageEventKind::SkillUsed, &skill_id);
+        Ok(serde_json::to_string_pretty(&output).unwrap())
+    }
+
+    fn tool_render_skill(&mut self, args: &Value) -> Result<String, String> {
+        let skill_id = args
+            .get("skill_id")
+            .and_then(|v| v.as_str())
+            .ok_or("Missing skill_id")?;
+
+        let skill = resolve_entity(&self.skills, skill_id)?;
+        let arguments = match args.get("arguments") {
+            Some(Value::Object(arguments)) => arguments.clone(),
+            Some(Value::Null) | None => Default::default(),
+            Some(_) => return Err("arguments must be an object".to_string()),
+        };
+
+        let prompt = template::render_skill_chain(skill, &self.skills, &arguments)?;
+        let skill_id = skill.id.clone();
+        let prompt = match self.workspace_for(args)? {
+            Some(workspace) => workspace.render(&prompt),
+            None => prompt,
+        };
+
+        self.record_usage(UsageEventKind::SkillUsed, &skill_id);
+        Ok(prompt)
     }
 
     fn tool_list_skills(&self) -> Result<String, String> {
@@ -590,109 +1539,621 @@ impl McpServer {
         Ok(serde_json::to_string_pretty(&summary).unwrap())
     }
 
-    fn tool_apply_agent(&self, args: &Value) -> Result<String, String> {
+    fn tool_apply_agent(&mut self, args: &Value) -> Result<String, String> {
         let agent_id = args
             .get("agent_id")
             .and_then(|v| v.as_str())
             .ok_or("Missing agent_id")?;
 
-        // Try to find by ID first, then by name (case-insensitive)
-        let agent = self
-            .agents
-            .iter()
-            .find(|a| a.id == agent_id)
-            .or_else(|| {
-                let name_lower = agent_id.to_lowercase();
-                self.agents.iter().find(|a| a.name.to_lowercase() == name_lower)
+        let release = self.release_for(args)?;
+        let revision = persona_cache::library_revision(
+            &LibraryGraph {
+                agents: &self.agents,
+                skills: &self.skills,
+                instructions: &self.instructions,
+            },
+            &self.snippets,
+            &self.experiment_variants,
+        );
+        let library = match &release {
+            Some(release) => LibraryGraph {
+                agents: &release.agents,
+                skills: &release.skills,
+                instructions: &release.instructions,
+            },
+            None => LibraryGraph {
+                agents: &self.agents,
+                skills: &self.skills,
+                instructions: &self.instructions,
+            },
+        };
+        let translations = self.translations_for(args)?;
+        let localized = translations
+            .as_ref()
+            .map(|(locale, translations)| LocalizedLibrary::new(&library, translations, locale));
+        let library = match &localized {
+            Some(localized) => localized.graph(),
+            None => library,
+        };
+
+        let agent = resolve_entity(library.agents, agent_id)?;
+        composition::ensure_resolvable(&library, agent)?;
+        let agent = &composition::inherited(agent, library.agents);
+        let workspace = self.workspace_for(args)?;
+
+        let options = assembly_options(args, workspace.as_ref());
+        let key = persona_cache::persona_key(
+            &agent.id,
+            release.as_ref(),
+            &options,
+            translations.as_ref(),
+        );
+        let mut prompt = self.personas.get_or_assemble(revision, key, || {
+            assembly::agent_configuration(agent, library.skills, library.instructions, &options)
+        });
+        if let Some(workspace) = workspace {
+            prompt = workspace.render(&prompt);
+        }
+
+        let trace = args
+            .get("trace")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false)
+            .then(|| {
+                assembly::trace_agent_configuration(
+                    agent,
+                    library.skills,
+                    library.instructions,
+                    &options,
+                )
+            });
+
+        let agent_id = agent.id.clone();
+        self.record_usage(UsageEventKind::AgentApplied, &agent_id);
+        self.record_insight(InsightMetric::Apply, "");
+        self.current_agent_id = Some(agent_id);
+
+        match trace {
+            None => Ok(prompt),
+            Some(trace) => Ok(serde_json::to_string_pretty(&AppliedAgent {
+                prompt,
+                trace: Some(trace),
             })
-            .ok_or(format!("Agent not found: '{}'. Use list_agents to see available agents.", agent_id))?;
+            .unwrap()),
+        }
+    }
 
-        let mut full_prompt = String::new();
+    /// The release named by the `release` argument; `None` for the library this server serves
+    fn release_for(&self, args: &Value) -> Result<Option<Release>, String> {
+        let Some(tag) = args
+            .get("release")
+            .and_then(|v| v.as_str())
+            .filter(|tag| *tag != releases::LATEST)
+        else {
+            return Ok(None);
+        };
 
-        // Add agent's system prompt
-        full_prompt.push_str("# Agent Configuration\n\n");
-        full_prompt.push_str(&format!(
-            "**Agent:** {} {}\n\n",
-            agent.avatar_emoji, agent.name
-        ));
-        full_prompt.push_str(&format!(
-            "**Tone:** {} | **Verbosity:** {}\n\n",
-            agent.personality.tone, agent.personality.verbosity
-        ));
+        let db = self
+            .db
+            .as_ref()
+            .ok_or("Releases are unavailable: the database could not be opened")?;
+        db.get_release(tag)
+            .map_err(|e| format!("Failed to get release: {}", e))?
+            .map(Some)
+            .ok_or_else(|| format!("Release '{}' not found", tag))
+    }
+
+    /// The locale named by the `locale` argument and its reviewed translations
+    fn translations_for(&self, args: &Value) -> Result<Option<(String, Vec<Translation>)>, String> {
+        let Some(locale) = args
+            .get("locale")
+            .and_then(|v| v.as_str())
+            .filter(|l| !l.trim().is_empty())
+        else {
+            return Ok(None);
+        };
+
+        let locale = localization::normalize_locale(locale)?;
+        let db = self
+            .db
+            .as_ref()
+            .ok_or("Translations are unavailable: the database could not be opened")?;
+        let locales = localization::fallback_locales(&locale);
+        let locales: Vec<&str> = locales.iter().map(String::as_str).collect();
+        let translations = db
+            .get_active_translations(&locales)
+            .map_err(|e| format!("Failed to get translations: {}", e))?;
+        Ok(Some((locale, translations)))
+    }
 
-        if !agent.personality.traits.is_empty() {
-            full_prompt.push_str(&format!(
-                "**Traits:** {}\n\n",
-                agent.personality.traits.join(", ")
+    fn tool_search_prompts(&self, args: &Value) -> Result<String, String> {
+        let query = args
+            .get("query")
+            .and_then(|v| v.as_str())
+            .ok_or("Missing query")?;
+        let limit = args
+            .get("limit")
+            .and_then(|v| v.as_u64())
+            .map_or(SEARCH_LIMIT, |l| (l as usize).clamp(1, MAX_SEARCH_LIMIT));
+
+        let results = search::search(
+            &self.agents,
+            &self.skills,
+            &self.instructions,
+            query,
+            &string_list(args, "types"),
+            limit,
+        );
+        if results.is_empty() {
+            return Ok(format!("Nothing matches '{}'.", query));
+        }
+        Ok(serde_json::to_string_pretty(&results).unwrap())
+    }
+
+    fn tool_suggest_skills_for_context(&self, args: &Value) -> Result<String, String> {
+        let paths = suggestions::context_paths(
+            args.get("path").and_then(|v| v.as_str()),
+            args.get("diff").and_then(|v| v.as_str()),
+        );
+        if paths.is_empty() {
+            return Err("Give a path or a diff".to_string());
+        }
+        let limit = args
+            .get("limit")
+            .and_then(|v| v.as_u64())
+            .map_or(SEARCH_LIMIT, |l| (l as usize).clamp(1, MAX_SEARCH_LIMIT));
+
+        let suggested = suggestions::suggest(&self.skills, &self.instructions, &paths, limit);
+        if suggested.is_empty() {
+            return Ok(format!(
+                "No skills or instructions suggested for {}.",
+                paths.join(", ")
             ));
         }
+        Ok(serde_json::to_string_pretty(&suggested).unwrap())
+    }
 
-        full_prompt.push_str("## System Prompt\n\n");
-        full_prompt.push_str(&agent.system_prompt);
-        full_prompt.push_str("\n\n");
+    fn tool_help(&self, args: &Value) -> Result<String, String> {
+        let only = args.get("tool").and_then(|v| v.as_str());
+        let query = args.get("query").and_then(|v| v.as_str()).unwrap_or("");
+        let words: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
 
-        // Add attached skills
-        if !agent.skills.is_empty() {
-            full_prompt.push_str("## Attached Skills\n\n");
-            for skill_id in &agent.skills {
-                if let Some(skill) = self.skills.iter().find(|s| s.id == *skill_id && s.enabled) {
-                    full_prompt.push_str(&format!("### {} {}\n", skill.icon_emoji, skill.name));
-                    if let SkillDefinition::Prompt { template } = &skill.definition {
-                        full_prompt.push_str(template);
-                        full_prompt.push_str("\n\n");
-                    }
-                }
+        let mut tools = self.built_in_tools();
+        let built_in = tools.len();
+        self.add_skill_tools(&mut tools);
+        let approvals = self.tool_approvals();
+
+        let mut catalog = Vec::new();
+        for (index, tool) in tools.into_iter().enumerate() {
+            if only.is_some_and(|only| only != tool.name) {
+                continue;
             }
+            let text = format!("{} {}", tool.name, tool.description).to_lowercase();
+            if !words.iter().all(|word| text.contains(word.as_str())) {
+                continue;
+            }
+            let skill_id = (index >= built_in)
+                .then(|| {
+                    self.skills.iter().find(|s| {
+                        tool_schema::mcp_tool_name(&s.name) == tool.name
+                            && (runnable_tool(s, &approvals).is_some() || is_chained(s))
+                    })
+                })
+                .flatten()
+                .map(|s| s.id.clone());
+            let arguments = tool_schema::parameters_from_input_schema(&tool.input_schema)?;
+            let example = json!({
+                "name": tool.name,
+                "arguments": tool_schema::example_arguments(&arguments),
+            });
+            catalog.push(ToolHelp {
+                name: tool.name,
+                description: tool.description,
+                skill_id,
+                arguments,
+                example,
+            });
         }
 
-        // Add attached instructions
-        if !agent.instructions.is_empty() {
-            full_prompt.push_str("## Instructions\n\n");
-            for instruction_id in &agent.instructions {
-                if let Some(instruction) = self
-                    .instructions
-                    .iter()
-                    .find(|i| i.id == *instruction_id && i.enabled)
-                {
-                    full_prompt.push_str(&format!(
-                        "### {} {}\n",
-                        instruction.icon_emoji, instruction.name
-                    ));
-                    full_prompt.push_str(&instruction.content);
-                    full_prompt.push_str("\n\n");
-                }
+        match only {
+            Some(name) if catalog.is_empty() => Err(format!(
+                "Unknown tool '{}'. Call help without arguments to list every tool",
+                name
+            )),
+            _ => Ok(serde_json::to_string_pretty(&catalog).unwrap()),
+        }
+    }
+
+    fn tool_list_releases(&self) -> Result<String, String> {
+        let db = self
+            .db
+            .as_ref()
+            .ok_or("Releases are unavailable: the database could not be opened")?;
+        let releases = db
+            .get_releases()
+            .map_err(|e| format!("Failed to get releases: {}", e))?;
+
+        if releases.is_empty() {
+            return Ok("No releases created. apply_agent uses the live library.".to_string());
+        }
+        Ok(serde_json::to_string_pretty(&releases).unwrap())
+    }
+
+    fn tool_compose_prompt(&mut self, args: &Value) -> Result<String, String> {
+        let workspace = self.workspace_for(args)?;
+        let skill_refs = string_list(args, "skills");
+        let instruction_refs = string_list(args, "instructions");
+        let prompt = assembly::compose_prompt(
+            &self.skills,
+            &self.instructions,
+            &skill_refs,
+            &instruction_refs,
+            args.get("text").and_then(|v| v.as_str()),
+            &assembly_options(args, workspace.as_ref()),
+        )?;
+
+        // Every reference resolved, or composing would have failed
+        let mut used = Vec::new();
+        for reference in &skill_refs {
+            let id = resolve_entity(&self.skills, reference)?.id.clone();
+            used.push((UsageEventKind::SkillUsed, id));
+        }
+        for reference in &instruction_refs {
+            let id = resolve_entity(&self.instructions, reference)?.id.clone();
+            used.push((UsageEventKind::InstructionUsed, id));
+        }
+        for (kind, id) in used {
+            self.record_usage(kind, &id);
+        }
+
+        Ok(match workspace {
+            Some(workspace) => workspace.render(&prompt),
+            None => prompt,
+        })
+    }
+
+    fn tool_configure_agent(&mut self, args: &Value) -> Result<String, String> {
+        self.ensure_writable("configure_agent")?;
+
+        let agent_ref = args
+            .get("agent_id")
+            .and_then(|v| v.as_str())
+            .ok_or("Missing agent_id")?;
+        let id = resolve_entity(&self.agents, agent_ref)?.id.clone();
+        let db = self.db.as_ref().ok_or("Database is not available")?;
+        // The served copy has snippets expanded, so edit the stored one
+        let mut agent = db
+            .get_agent(&id)
+            .map_err(|e| format!("Failed to load agent: {}", e))?
+            .ok_or_else(|| format!("Agent '{}' not found", agent_ref))?;
+
+        // Resolve every reference before changing anything so a typo leaves the agent untouched
+        let resolve_skills = |key: &str| -> Result<Vec<String>, String> {
+            string_list(args, key)
+                .iter()
+                .map(|r| resolve_entity(&self.skills, r).map(|s| s.id.clone()))
+                .collect()
+        };
+        let resolve_instructions = |key: &str| -> Result<Vec<String>, String> {
+            string_list(args, key)
+                .iter()
+                .map(|r| resolve_entity(&self.instructions, r).map(|i| i.id.clone()))
+                .collect()
+        };
+        let attach_skills = resolve_skills("attach_skills")?;
+        let detach_skills = resolve_skills("detach_skills")?;
+        let attach_instructions = resolve_instructions("attach_instructions")?;
+        let detach_instructions = resolve_instructions("detach_instructions")?;
+
+        agent.skills.retain(|id| !detach_skills.contains(id));
+        for id in attach_skills {
+            if !agent.skills.contains(&id) {
+                agent.skills.push(id);
             }
         }
+        agent.instructions.retain(|id| !detach_instructions.contains(id));
+        for id in attach_instructions {
+            if !agent.instructions.contains(&id) {
+                agent.instructions.push(id);
+            }
+        }
+        agent.updated_at = chrono::Utc::now();
+
+        if let Some(lock) = db
+            .get_edit_lock(&agent.id)
+            .map_err(|e| format!("Failed to check edit lock: {}", e))?
+        {
+            return Err(format!(
+                "Agent is currently being edited by {}",
+                lock.owner_name
+            ));
+        }
+        db.update_agent(&agent)
+            .map_err(|e| format!("Failed to save agent: {}", e))?;
+        self.record_insight(InsightMetric::Edit, "");
 
-        // Add all enabled global instructions
-        let global_instructions: Vec<_> = self
+        let skill_names: Vec<_> = agent
+            .skills
+            .iter()
+            .filter_map(|id| self.skills.iter().find(|s| s.id == *id))
+            .map(|s| s.name.as_str())
+            .collect();
+        let instruction_names: Vec<_> = agent
             .instructions
             .iter()
-            .filter(|i| i.enabled && !agent.instructions.contains(&i.id))
+            .filter_map(|id| self.instructions.iter().find(|i| i.id == *id))
+            .map(|i| i.name.as_str())
             .collect();
+        let summary = json!({
+            "id": agent.id,
+            "name": agent.name,
+            "skills": skill_names,
+            "instructions": instruction_names
+        });
+
+        // The stored agent has its includes unexpanded, so reload rather than serve it
+        self.agents.expire();
 
-        if !global_instructions.is_empty() {
-            full_prompt.push_str("## Global Instructions\n\n");
-            for instruction in global_instructions {
-                full_prompt.push_str(&format!(
-                    "### {} {} ({})\n",
-                    instruction.icon_emoji,
-                    instruction.name,
-                    category_to_string(&instruction.category)
-                ));
-                full_prompt.push_str(&instruction.content);
-                full_prompt.push_str("\n\n");
+        Ok(serde_json::to_string_pretty(&summary).unwrap())
+    }
+
+    fn tool_create_instruction(&mut self, args: &Value) -> Result<String, String> {
+        self.ensure_write_tools("create_instruction")?;
+
+        let mut instruction = Instruction {
+            name: required_text(args, "name")?,
+            content: required_text(args, "content")?,
+            ..Instruction::default()
+        };
+        apply_instruction_fields(&mut instruction, args)?;
+
+        let db = self.db.as_ref().ok_or("Database is not available")?;
+        let name = instruction.name.clone();
+        ids::claim(&name, |id| {
+            instruction.id = id.to_string();
+            db.insert_instruction(&instruction)
+        })
+        .map_err(|e| format!("Failed to create instruction: {}", e))?;
+        self.record_insight(InsightMetric::Edit, "");
+        self.instructions.expire();
+
+        Ok(format!(
+            "Created instruction '{}' with id {}",
+            instruction.name, instruction.id
+        ))
+    }
+
+    fn tool_update_instruction(&mut self, args: &Value) -> Result<String, String> {
+        self.ensure_write_tools("update_instruction")?;
+
+        let instruction_ref = args
+            .get("instruction_id")
+            .and_then(|v| v.as_str())
+            .ok_or("Missing instruction_id")?;
+        let id = resolve_entity(&self.instructions, instruction_ref)?
+            .id
+            .clone();
+        let db = self.db.as_ref().ok_or("Database is not available")?;
+        // The served copy has experiments and activation applied, so edit the stored one
+        let mut instruction = db
+            .get_instruction(&id)
+            .map_err(|e| format!("Failed to load instruction: {}", e))?
+            .ok_or_else(|| format!("Instruction '{}' not found", instruction_ref))?;
+        if args.get("name").is_some() {
+            instruction.name = required_text(args, "name")?;
+        }
+        if args.get("content").is_some() {
+            instruction.content = required_text(args, "content")?;
+        }
+        apply_instruction_fields(&mut instruction, args)?;
+        if let Some(enabled) = args.get("enabled").and_then(|v| v.as_bool()) {
+            instruction.enabled = enabled;
+        }
+        instruction.updated_at = chrono::Utc::now();
+
+        if let Some(lock) = db
+            .get_edit_lock(&instruction.id)
+            .map_err(|e| format!("Failed to check edit lock: {}", e))?
+        {
+            return Err(format!(
+                "Instruction is currently being edited by {}",
+                lock.owner_name
+            ));
+        }
+        db.update_instruction(&instruction)
+            .map_err(|e| format!("Failed to save instruction: {}", e))?;
+        self.record_insight(InsightMetric::Edit, "");
+        self.instructions.expire();
+
+        Ok(format!(
+            "Updated instruction '{}' ({})",
+            instruction.name, instruction.id
+        ))
+    }
+
+    fn tool_create_skill(&mut self, args: &Value) -> Result<String, String> {
+        self.ensure_write_tools("create_skill")?;
+
+        let variables = match args.get("variables") {
+            Some(variables) => serde_json::from_value(variables.clone())
+                .map_err(|e| format!("Invalid variables: {}", e))?,
+            None => Vec::new(),
+        };
+        let mut skill = Skill {
+            name: required_text(args, "name")?,
+            description: text_arg(args, "description").unwrap_or_default(),
+            definition: SkillDefinition::Prompt {
+                template: required_text(args, "template")?,
+                variables,
+                follows: Vec::new(),
+            },
+            ..Skill::default()
+        };
+        if let Some(icon) = text_arg(args, "icon") {
+            skill.icon_emoji = icon;
+        }
+        tool_schema::validate_definition(&skill.definition)?;
+
+        let db = self.db.as_ref().ok_or("Database is not available")?;
+        let name = skill.name.clone();
+        ids::claim(&name, |id| {
+            skill.id = id.to_string();
+            db.insert_skill(&skill)
+        })
+        .map_err(|e| format!("Failed to create skill: {}", e))?;
+        self.record_insight(InsightMetric::Edit, "");
+        self.skills.expire();
+
+        Ok(format!(
+            "Created skill '{}' with id {}",
+            skill.name, skill.id
+        ))
+    }
+
+    /// Run the handler of the tool skill registered as `name`
+    fn tool_run_skill(&mut self, name: &str, args: &Value) -> Result<String, String> {
+        let approvals = self.tool_approvals();
+        let runnable = self
+            .skills
+            .iter()
+            .filter(|s| tool_schema::mcp_tool_name(&s.name) == name)
+            .find_map(|s| Some((s.id.clone(), runnable_tool(s, &approvals)?)));
+        let Some((skill_id, (parameters, handler, sandbox))) = runnable else {
+            return self.tool_render_chain(name, args);
+        };
+
+        let arguments = tool_schema::prepare_arguments(parameters, args)?;
+        let output = tool_runner::run(
+            handler,
+            &arguments,
+            &sandbox.cloned().unwrap_or_default(),
+            self.workspace_root.as_deref(),
+        )?;
+
+        if output.success() {
+            self.record_usage(UsageEventKind::SkillUsed, &skill_id);
+            Ok(output.text())
+        } else {
+            Err(output.text())
+        }
+    }
+
+    /// Render the chained prompt skill offered as tool `name`
+    fn tool_render_chain(&mut self, name: &str, args: &Value) -> Result<String, String> {
+        let skill = self
+            .skills
+            .iter()
+            .find(|s| is_chained(s) && tool_schema::mcp_tool_name(&s.name) == name)
+            .ok_or_else(|| format!("Unknown tool: {}", name))?;
+        let arguments = match args {
+            Value::Object(arguments) => arguments.clone(),
+            Value::Null => Default::default(),
+            _ => return Err("arguments must be an object".to_string()),
+        };
+
+        let prompt = template::render_skill_chain(skill, &self.skills, &arguments)?;
+        let skill_id = skill.id.clone();
+        let prompt = match self.workspace_for(&Value::Null)? {
+            Some(workspace) => workspace.render(&prompt),
+            None => prompt,
+        };
+
+        self.record_usage(UsageEventKind::SkillUsed, &skill_id);
+        Ok(prompt)
+    }
+
+    fn tool_get_current_agent(&self) -> Result<String, String> {
+        let agent = self
+            .current_agent_id
+            .as_ref()
+            .and_then(|id| self.agents.iter().find(|a| a.id == *id));
+
+        match agent {
+            Some(agent) => Ok(serde_json::to_string_pretty(agent).unwrap()),
+            None => Ok("No agent has been applied in this session. Use apply_agent first.".to_string()),
+        }
+    }
+
+    /// Queue an MCP-driven use of an agent, skill or instruction so the GUI
+    /// folds it into usage stats, and count it in the cached copy meanwhile
+    fn record_usage(&mut self, kind: UsageEventKind, id: &str) {
+        self.record_usage_event(kind, id, "");
+
+        let now = Some(chrono::Utc::now());
+        match kind {
+            UsageEventKind::AgentApplied | UsageEventKind::AgentFetched => {
+                if let Some(agent) = self.agents.iter_mut().find(|a| a.id == id) {
+                    agent.usage_count += 1;
+                    agent.last_used_at = now;
+                }
+            }
+            UsageEventKind::SkillUsed => {
+                if let Some(skill) = self.skills.iter_mut().find(|s| s.id == id) {
+                    skill.usage_count += 1;
+                    skill.last_used_at = now;
+                }
             }
+            UsageEventKind::InstructionUsed => {
+                if let Some(instruction) = self.instructions.iter_mut().find(|i| i.id == id) {
+                    instruction.usage_count += 1;
+                    instruction.last_used_at = now;
+                }
+            }
+            UsageEventKind::Insight => {}
         }
+    }
+
+    /// Record each experiment's variant the first time this session serves it
+    fn record_variants_served(&mut self) {
+        let unrecorded: Vec<(String, Variant)> = self
+            .experiment_variants
+            .iter()
+            .filter(|(id, _)| !self.recorded_variants.contains(id))
+            .cloned()
+            .collect();
+        for (instruction_id, variant) in unrecorded {
+            self.record_insight(
+                InsightMetric::Variant,
+                &experiments::insight_key(&instruction_id, variant),
+            );
+            self.recorded_variants.insert(instruction_id);
+        }
+    }
 
-        Ok(full_prompt)
+    fn record_insight(&mut self, metric: InsightMetric, key: &str) {
+        self.record_usage_event(UsageEventKind::Insight, key, metric.as_str());
+    }
+
+    /// Read-only servers never write to the database, so their usage isn't recorded
+    fn record_usage_event(&mut self, kind: UsageEventKind, entity_id: &str, payload: &str) {
+        if self.read_only {
+            return;
+        }
+        if !self.usage_events.push(kind, entity_id, payload) {
+            self.log
+                .warning("usage", "Usage event queue full, dropped the oldest event");
+        }
+        if let Some(db) = &self.db {
+            if let Err(e) = self.usage_events.flush(db) {
+                self.log.warning("usage", e);
+            }
+        }
     }
 
     fn get_all_instructions_markdown(&self) -> String {
-        let enabled: Vec<_> = self.instructions.iter().filter(|i| i.enabled).collect();
+        self.instructions_markdown(|_| true, "No instructions enabled.")
+    }
+
+    /// Enabled instructions matching `include`, or `empty` when there are none
+    fn instructions_markdown(&self, include: impl Fn(&Instruction) -> bool, empty: &str) -> String {
+        let enabled: Vec<_> = self
+            .instructions
+            .iter()
+            .filter(|i| i.enabled && include(i))
+            .collect();
 
         if enabled.is_empty() {
-            return "No instructions enabled.".to_string();
+            return empty.to_string();
         }
 
         let mut output = String::from("# Prompt Forge Instructions\n\n");
@@ -704,7 +2165,7 @@ impl McpServer {
             ));
             output.push_str(&format!(
                 "*Category: {}*\n\n",
-                category_to_string(&instruction.category)
+                categories::category_path(instruction)
             ));
             output.push_str(&instruction.content);
             output.push_str("\n\n---\n\n");
@@ -712,26 +2173,672 @@ impl McpServer {
 
         output
     }
+
+    /// Enabled instructions flagged `pin_to_context`, highest priority first
+    fn get_pinned_context_markdown(&self) -> String {
+        let mut pinned: Vec<_> = self
+            .instructions
+            .iter()
+            .filter(|i| i.enabled && i.pin_to_context)
+            .collect();
+
+        if pinned.is_empty() {
+            return "No instructions pinned.".to_string();
+        }
+
+        pinned.sort_by_key(|i| std::cmp::Reverse(i.priority));
+
+        let mut output = String::from("# Pinned Instructions\n\n");
+        for instruction in pinned {
+            output.push_str(&format!("## {}\n\n", instruction.name));
+            output.push_str(&instruction.content);
+            output.push_str("\n\n");
+        }
+
+        output
+    }
+}
+
+/// Snippets to expand includes with; a database the app hasn't migrated yet
+/// has none
+fn load_snippets(db: &Database) -> Vec<Snippet> {
+    db.get_all_snippets().unwrap_or_default()
 }
 
-fn category_to_string(cat: &InstructionCategory) -> &'static str {
-    match cat {
-        InstructionCategory::General => "general",
-        InstructionCategory::CodeStyle => "code_style",
-        InstructionCategory::Communication => "communication",
-        InstructionCategory::Workflow => "workflow",
-        InstructionCategory::Security => "security",
-        InstructionCategory::Testing => "testing",
-        InstructionCategory::Documentation => "documentation",
-        InstructionCategory::Custom => "custom",
+/// Read a tool argument that may be a single string or an array of strings
+fn string_list(args: &Value, key: &str) -> Vec<String> {
+    match args.get(key) {
+        Some(Value::String(s)) => vec![s.clone()],
+        Some(Value::Array(items)) => items
+            .iter()
+            .filter_map(|v| v.as_str().map(str::to_string))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// A trimmed, non-empty text argument
+fn text_arg(args: &Value, key: &str) -> Option<String> {
+    args.get(key)
+        .and_then(|v| v.as_str())
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+}
+
+fn required_text(args: &Value, key: &str) -> Result<String, String> {
+    text_arg(args, key).ok_or_else(|| format!("Missing {}", key))
+}
+
+/// Set the optional instruction fields `create_instruction` and
+/// `update_instruction` take
+fn apply_instruction_fields(instruction: &mut Instruction, args: &Value) -> Result<(), String> {
+    if let Some(description) = text_arg(args, "description") {
+        instruction.description = description;
+    }
+    let category = text_arg(args, "category").and_then(|c| categories::normalize_subcategory(&c));
+    if let Some(path) = category {
+        (instruction.category, instruction.subcategory) = categories::parse_category_path(&path);
+    }
+    if let Some(priority) = args.get("priority") {
+        let priority = priority.as_u64().filter(|p| (1..=10).contains(p));
+        let priority = priority.ok_or("priority must be a whole number from 1 to 10")?;
+        instruction.priority = priority as u8;
+    }
+    if args.get("tags").is_some() {
+        instruction.tags = string_list(args, "tags");
+    }
+    if args.get("contexts").is_some() {
+        instruction.contexts = scope::normalize_contexts(&string_list(args, "contexts"));
+    }
+    Ok(())
+}
+
+/// Definitions of the tools that save instructions and skills
+fn write_tools() -> Vec<Tool> {
+    let instruction_properties = json!({
+        "description": {
+            "type": "string",
+            "description": "One line saying what the instruction is for"
+        },
+        "category": {
+            "type": "string",
+            "description": "general, code_style, communication, workflow, security, testing, documentation or custom; a path such as 'code_style/rust' sets a subcategory"
+        },
+        "priority": {
+            "type": "integer",
+            "description": "1-10, higher comes first in assembled prompts (default 5)"
+        },
+        "tags": {
+            "type": "array",
+            "items": { "type": "string" },
+            "description": "Tags for search and resources"
+        },
+        "contexts": {
+            "type": "array",
+            "items": { "type": "string" },
+            "description": "Languages, projects or clients the instruction is for, e.g. [\"rust\"]; empty for all"
+        }
+    });
+    let with = |extra: Value| -> Value {
+        let mut properties = instruction_properties.clone();
+        if let (Some(properties), Value::Object(extra)) = (properties.as_object_mut(), extra) {
+            properties.extend(extra);
+        }
+        properties
+    };
+
+    vec![
+        Tool {
+            name: "create_instruction".to_string(),
+            description: "Save a new instruction to the Prompt Forge library, e.g. guidance refined in this session. Returns its id".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": with(json!({
+                    "name": { "type": "string", "description": "Name of the instruction" },
+                    "content": { "type": "string", "description": "The instruction text (markdown)" }
+                })),
+                "required": ["name", "content"]
+            }),
+        },
+        Tool {
+            name: "update_instruction".to_string(),
+            description: "Change an instruction in the Prompt Forge library. Only the fields given are changed".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": with(json!({
+                    "instruction_id": { "type": "string", "description": "The ID or name of the instruction to update" },
+                    "name": { "type": "string", "description": "New name" },
+                    "content": { "type": "string", "description": "New instruction text (markdown), replacing the old one" },
+                    "enabled": { "type": "boolean", "description": "Whether the instruction is served" }
+                })),
+                "required": ["instruction_id"]
+            }),
+        },
+        Tool {
+            name: "create_skill".to_string(),
+            description: "Save a new prompt skill to the Prompt Forge library. {{name}} placeholders in the template become its variables. Returns its id".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "name": { "type": "string", "description": "Name of the skill" },
+                    "description": { "type": "string", "description": "One line saying what the skill does" },
+                    "template": { "type": "string", "description": "The prompt, with {{name}} placeholders for its inputs" },
+                    "variables": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "name": { "type": "string" },
+                                "description": { "type": "string" },
+                                "default": { "type": "string" }
+                            },
+                            "required": ["name"]
+                        },
+                        "description": "Descriptions and defaults for placeholders; those without a default are required"
+                    },
+                    "icon": { "type": "string", "description": "An emoji for the skill" }
+                },
+                "required": ["name", "template"]
+            }),
+        },
+    ]
+}
+
+/// Read the shared `numbered` flag and `branch` context from tool arguments,
+/// falling back to the branch checked out in the workspace
+fn assembly_options(args: &Value, workspace: Option<&Workspace>) -> AssemblyOptions {
+    AssemblyOptions {
+        numbered: args
+            .get("numbered")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false),
+        branch: args
+            .get("branch")
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+            .or_else(|| workspace.and_then(|w| w.branch.clone())),
+    }
+}
+
+/// Parameters, handler and sandbox of a tool skill the server can run: it
+/// must be enabled and have a handler the user approved as it reads now
+fn runnable_tool<'a>(
+    skill: &'a Skill,
+    approvals: &HashMap<String, String>,
+) -> Option<(&'a [ToolParameter], &'a str, Option<&'a ToolSandbox>)> {
+    match &skill.definition {
+        SkillDefinition::Tool {
+            parameters,
+            handler,
+            sandbox,
+        } if skill.enabled
+            && !handler.trim().is_empty()
+            && tool_runner::is_approved(skill, approvals) =>
+        {
+            Some((parameters, handler, sandbox.as_ref()))
+        }
+        _ => None,
+    }
+}
+
+/// An enabled prompt skill that follows others, offered as a tool of its own
+fn is_chained(skill: &Skill) -> bool {
+    skill.enabled && !composition::follows(skill).is_empty()
+}
+
+/// A JSON-RPC response sent by the client: it has an id but no method
+fn client_reply(line: &str) -> Option<Value> {
+    let message: Value = serde_json::from_str(line).ok()?;
+    let is_reply = message.get("method").is_none()
+        && message.get("id").is_some()
+        && (message.get("result").is_some() || message.get("error").is_some());
+    is_reply.then_some(message)
+}
+
+/// What a `prompt-forge://` resource URI points at
+#[derive(Debug, PartialEq)]
+enum ResourceRoute {
+    Agent(String),
+    Instructions(InstructionFilter),
+}
+
+/// Which enabled instructions an instructions resource serves
+#[derive(Debug, PartialEq)]
+enum InstructionFilter {
+    All,
+    Pinned,
+    Tag(String),
+    MinPriority(u8),
+    /// Highest priority first, cut to fit a token budget
+    Compact(usize),
+}
+
+/// Token budget of `instructions/compact` when the URI doesn't give one
+const DEFAULT_COMPACT_TOKENS: usize = 2000;
+
+/// Route a resource URI: `agents/{id}`, `instructions/all`,
+/// `instructions/pinned`, `instructions/tag/{tag}` (percent-encoded),
+/// `instructions/priority/{min}` and `instructions/compact?tokens={n}`
+fn route_resource(uri: &str) -> Option<ResourceRoute> {
+    let path = uri.strip_prefix("prompt-forge://")?;
+    if let Some(agent_id) = path.strip_prefix("agents/") {
+        return Some(ResourceRoute::Agent(agent_id.to_string()));
+    }
+
+    let (path, query) = path.split_once('?').unwrap_or((path, ""));
+    if path == "instructions/compact" {
+        let tokens = match query.split('&').find_map(|p| p.strip_prefix("tokens=")) {
+            Some(tokens) => tokens.parse().ok().filter(|t| *t > 0)?,
+            None => DEFAULT_COMPACT_TOKENS,
+        };
+        return Some(ResourceRoute::Instructions(InstructionFilter::Compact(
+            tokens,
+        )));
+    }
+
+    let segments: Vec<&str> = path.split('/').collect();
+    let filter = match segments.as_slice() {
+        ["instructions", "all"] => InstructionFilter::All,
+        ["instructions", "pinned"] => InstructionFilter::Pinned,
+        ["instructions", "tag", tag] if !tag.is_empty() => {
+            InstructionFilter::Tag(workspace::percent_decode(tag)?)
+        }
+        ["instructions", "priority", min] => InstructionFilter::MinPriority(min.parse().ok()?),
+        _ => return None,
+    };
+    Some(ResourceRoute::Instructions(filter))
+}
+
+/// Parts of the library a request reads; `None` for the handshake, pings and
+/// notifications, which are answered without touching the database
+fn library_parts(method: &str, params: Option<&Value>) -> Option<&'static [LibraryPart]> {
+    use LibraryPart::*;
+
+    match method {
+        "tools/list" => Some(&[Skills]),
+        "resources/list" => Some(&[Agents]),
+        "resources/read" => {
+            let uri = params.and_then(|p| p.get("uri")).and_then(|v| v.as_str());
+            match uri.and_then(route_resource) {
+                Some(ResourceRoute::Agent(_)) => Some(&[Agents]),
+                _ => Some(&[Instructions]),
+            }
+        }
+        "tools/call" => {
+            let tool = params.and_then(|p| p.get("name")).and_then(|v| v.as_str());
+            Some(match tool.unwrap_or_default() {
+                "get_agent" | "list_agents" | "get_current_agent" => &[Agents],
+                "get_skill" | "list_skills" | "render_skill" | "help" => &[Skills],
+                "get_instructions" | "get_instructions_for_context" | "get_pinned_context" => {
+                    &[Instructions]
+                }
+                "create_instruction" | "update_instruction" => &[Instructions],
+                "create_skill" => &[Skills],
+                "compose_prompt" | "suggest_skills_for_context" => &[Skills, Instructions],
+                "list_releases" => &[],
+                _ => ALL_PARTS,
+            })
+        }
+        _ => None,
     }
 }
 
 /// Entry point for MCP mode
-pub fn run_mcp_server(db_path: PathBuf) {
-    let mut server = McpServer::new(db_path);
+pub fn run_mcp_server(db_path: PathBuf, read_only: bool, channel: Option<String>) {
+    let mut server = McpServer::new(db_path, read_only, channel);
     if let Err(e) = server.run() {
         eprintln!("MCP Server error: {}", e);
         std::process::exit(1);
     }
 }
+
+/// Entry point for the interactive console (`--mcp --repl`)
+pub fn run_mcp_repl(db_path: PathBuf, read_only: bool, channel: Option<String>) {
+    let mut server = McpServer::new(db_path, read_only, channel);
+    if let Err(e) = mcp_repl::run(&mut server) {
+        eprintln!("MCP console error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hostile_lines_get_error_objects() {
+        let dir = std::env::temp_dir().join(format!("pf-mcp-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db_path = dir.join("library.db");
+        let db = Database::open(&db_path).unwrap();
+        db.migrate().unwrap();
+        crate::db::init_default_data(&db).unwrap();
+        drop(db);
+        let mut server = McpServer::new(db_path, false, None);
+
+        let error_code = |server: &mut McpServer, line: &str| {
+            let messages = server.handle_line(line);
+            assert_eq!(messages.len(), 1, "{}", line);
+            let response: Value = serde_json::from_str(&messages[0]).unwrap();
+            assert_eq!(response["jsonrpc"], "2.0");
+            (response["error"]["code"].as_i64(), response["id"].clone())
+        };
+        assert_eq!(
+            error_code(&mut server, "{not json"),
+            (Some(-32700), Value::Null)
+        );
+        assert_eq!(error_code(&mut server, "[]"), (Some(-32600), Value::Null));
+        assert_eq!(
+            error_code(&mut server, r#"{"jsonrpc":"2.0","id":7,"method":5}"#),
+            (Some(-32600), json!(7))
+        );
+        assert_eq!(
+            error_code(&mut server, r#"{"jsonrpc":"1.0","id":"a","method":"ping"}"#),
+            (Some(-32600), json!("a"))
+        );
+        for line in [
+            r#"{"jsonrpc":"2.0","id":1,"method":"tools/call","params":"x"}"#,
+            r#"{"jsonrpc":"2.0","id":1,"method":"tools/call","params":{"name":["apply_agent"]}}"#,
+            r#"{"jsonrpc":"2.0","id":1,"method":"resources/read","params":{"uri":null}}"#,
+            r#"{"jsonrpc":"2.0","id":1,"method":"nope"}"#,
+        ] {
+            assert!(error_code(&mut server, line).0.is_some(), "{}", line);
+        }
+        assert!(server
+            .handle_line(r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#)
+            .is_empty());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_tool_calls_record_usage() {
+        let dir = std::env::temp_dir().join(format!("pf-mcp-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db_path = dir.join("library.db");
+        let db = Database::open(&db_path).unwrap();
+        db.migrate().unwrap();
+        crate::db::init_default_data(&db).unwrap();
+        let mut server = McpServer::new(db_path, false, None);
+
+        for (tool, arguments) in [
+            ("get_agent", json!({ "agent_id": "default" })),
+            ("get_skill", json!({ "skill_id": "code-review" })),
+            ("render_skill", json!({ "skill_id": "code-review" })),
+            ("get_instructions", json!({ "category": "code_style" })),
+        ] {
+            let request = json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "tools/call",
+                "params": { "name": tool, "arguments": arguments },
+            });
+            server.handle_line(&request.to_string());
+        }
+        crate::usage_events::drain(&db).unwrap();
+
+        let agent = db.get_agent("default").unwrap().unwrap();
+        assert_eq!(agent.usage_count, 1);
+        let skill = db.get_skill("code-review").unwrap().unwrap();
+        assert_eq!(skill.usage_count, 2);
+        assert!(skill.last_used_at.is_some());
+        let used = |id: &str| db.get_instruction(id).unwrap().unwrap().usage_count;
+        assert_eq!((used("code-style"), used("communication")), (1, 0));
+
+        // Stopping the server leaves nothing in the write-ahead log
+        let exit = server.handle_line(r#"{"jsonrpc":"2.0","method":"notifications/exit"}"#);
+        assert!(exit.iter().all(|m| m.contains("notifications/message")));
+        assert!(server.exit_requested);
+        server.shutdown();
+        let wal = std::fs::metadata(dir.join("library.db-wal")).unwrap();
+        assert_eq!(wal.len(), 0);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_help_describes_listed_tools() {
+        let dir = std::env::temp_dir().join(format!("pf-mcp-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db_path = dir.join("library.db");
+        let db = Database::open(&db_path).unwrap();
+        db.migrate().unwrap();
+        crate::db::init_default_data(&db).unwrap();
+        drop(db);
+        let mut server = McpServer::new(db_path, true, None);
+
+        let mut call = |method: &str, params: Value| {
+            let response = server.handle_request(JsonRpcRequest {
+                jsonrpc: "2.0".to_string(),
+                id: Some(json!(1)),
+                method: method.to_string(),
+                params: Some(params),
+            });
+            response.result.unwrap()
+        };
+        let listed = call("tools/list", json!({}))["tools"]
+            .as_array()
+            .unwrap()
+            .len();
+        let help = |result: Value| -> Value {
+            serde_json::from_str(result["content"][0]["text"].as_str().unwrap()).unwrap_or_default()
+        };
+
+        let catalog = help(call("tools/call", json!({ "name": "help" })));
+        assert_eq!(catalog.as_array().unwrap().len(), listed);
+        let apply = help(call(
+            "tools/call",
+            json!({ "name": "help", "arguments": { "tool": "apply_agent" } }),
+        ));
+        assert_eq!(
+            apply[0]["example"],
+            json!({ "name": "apply_agent", "arguments": { "agent_id": "<agent_id>" } })
+        );
+        assert!(apply[0]["arguments"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|a| a["name"] == "release" && a["required"] == false));
+        let found = help(call(
+            "tools/call",
+            json!({ "name": "help", "arguments": { "query": "RELEASES" } }),
+        ));
+        assert_eq!(found[0]["name"], "list_releases");
+        let unknown = call(
+            "tools/call",
+            json!({ "name": "help", "arguments": { "tool": "nope" } }),
+        );
+        assert_eq!(unknown["isError"], true);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_tool_handlers_run_only_when_approved() {
+        let dir = std::env::temp_dir().join(format!("pf-mcp-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db_path = dir.join("library.db");
+        let db = Database::open(&db_path).unwrap();
+        db.migrate().unwrap();
+        let mut skill = Skill {
+            id: "greet".to_string(),
+            name: "Greet".to_string(),
+            skill_type: crate::models::SkillType::Tool,
+            definition: SkillDefinition::Tool {
+                parameters: Vec::new(),
+                handler: "echo hello".to_string(),
+                sandbox: None,
+            },
+            enabled: true,
+            ..Skill::default()
+        };
+        db.insert_skill(&skill).unwrap();
+        let mut server = McpServer::new(db_path, false, None);
+        let listed = |server: &mut McpServer| {
+            server.load_data().unwrap();
+            let request = json!({ "jsonrpc": "2.0", "id": 1, "method": "tools/list" });
+            server.handle_line(&request.to_string())[0].contains("\"Greet\"")
+        };
+
+        // Approved, but running tools is off in settings
+        let approve = |skill: &Skill| {
+            let SkillDefinition::Tool { handler, .. } = &skill.definition else {
+                unreachable!()
+            };
+            db.approve_tool_handler(&skill.id, &tool_runner::handler_digest(handler))
+                .unwrap();
+        };
+        approve(&skill);
+        assert!(!listed(&mut server));
+
+        let mut settings = db.get_settings().unwrap();
+        settings.mcp_run_tools = true;
+        db.save_settings(&settings).unwrap();
+        assert!(listed(&mut server));
+
+        // Editing the handler withdraws the approval
+        skill.definition = SkillDefinition::Tool {
+            parameters: Vec::new(),
+            handler: "echo goodbye".to_string(),
+            sandbox: None,
+        };
+        db.update_skill(&skill).unwrap();
+        assert!(!listed(&mut server));
+        approve(&skill);
+        assert!(listed(&mut server));
+        db.revoke_tool_approval("greet").unwrap();
+        assert!(!listed(&mut server));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_write_tools_follow_the_setting() {
+        let dir = std::env::temp_dir().join(format!("pf-mcp-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db_path = dir.join("library.db");
+        let db = Database::open(&db_path).unwrap();
+        db.migrate().unwrap();
+        crate::db::init_default_data(&db).unwrap();
+        let mut server = McpServer::new(db_path, false, None);
+
+        let mut call = |tool: &str, arguments: Value| {
+            let request = json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "tools/call",
+                "params": { "name": tool, "arguments": arguments },
+            });
+            let response: Value =
+                serde_json::from_str(&server.handle_line(&request.to_string())[0]).unwrap();
+            let result = &response["result"];
+            let text = result["content"][0]["text"].as_str().unwrap().to_string();
+            (result["isError"] != true, text)
+        };
+        let review = json!({
+            "name": "Review checklist",
+            "content": "Check error handling.",
+            "category": "workflow/review",
+            "contexts": ["Rust"],
+        });
+
+        let (saved, text) = call("create_instruction", review.clone());
+        assert!(!saved);
+        assert!(text.contains("settings"), "{}", text);
+
+        let mut settings = db.get_settings().unwrap();
+        settings.mcp_write_tools = true;
+        db.save_settings(&settings).unwrap();
+        assert!(call("create_instruction", review).0);
+        let created = db.get_instruction("review-checklist").unwrap().unwrap();
+        assert_eq!(created.subcategory.as_deref(), Some("review"));
+        assert_eq!(created.contexts, ["rust"]);
+
+        let (updated, _) = call(
+            "update_instruction",
+            json!({ "instruction_id": "Review checklist", "priority": 9, "enabled": false }),
+        );
+        assert!(updated);
+        let updated = db.get_instruction("review-checklist").unwrap().unwrap();
+        assert_eq!((updated.priority, updated.enabled), (9, false));
+        assert_eq!(updated.content, "Check error handling.");
+        let too_high = json!({ "instruction_id": "review-checklist", "priority": 11 });
+        assert!(!call("update_instruction", too_high).0);
+
+        assert!(
+            call(
+                "create_skill",
+                json!({ "name": "Explain Diff", "template": "Explain {{diff}}" })
+            )
+            .0
+        );
+        assert!(db.get_skill("explain-diff").unwrap().is_some());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_instruction_resources_route_by_tag_and_priority() {
+        assert_eq!(
+            route_resource("prompt-forge://instructions/tag/code%20review"),
+            Some(ResourceRoute::Instructions(InstructionFilter::Tag(
+                "code review".to_string()
+            )))
+        );
+        assert_eq!(
+            route_resource("prompt-forge://agents/default"),
+            Some(ResourceRoute::Agent("default".to_string()))
+        );
+        assert_eq!(
+            route_resource("prompt-forge://instructions/priority/high"),
+            None
+        );
+        assert_eq!(route_resource("prompt-forge://instructions/tag/"), None);
+        assert_eq!(
+            route_resource("prompt-forge://instructions/compact?tokens=300"),
+            Some(ResourceRoute::Instructions(InstructionFilter::Compact(300)))
+        );
+        assert_eq!(
+            route_resource("prompt-forge://instructions/compact?tokens=0"),
+            None
+        );
+
+        let dir = std::env::temp_dir().join(format!("pf-mcp-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db_path = dir.join("library.db");
+        let db = Database::open(&db_path).unwrap();
+        db.migrate().unwrap();
+        crate::db::init_default_data(&db).unwrap();
+        drop(db);
+        let mut server = McpServer::new(db_path, true, None);
+
+        let mut read = |uri: &str| {
+            let response = server.handle_request(JsonRpcRequest {
+                jsonrpc: "2.0".to_string(),
+                id: Some(json!(1)),
+                method: "resources/read".to_string(),
+                params: Some(json!({ "uri": uri })),
+            });
+            response
+                .result
+                .map(|r| r["contents"][0]["text"].as_str().unwrap().to_string())
+        };
+        let styled = read("prompt-forge://instructions/tag/Style").unwrap();
+        assert!(styled.contains("Code Style Guidelines"));
+        assert!(!styled.contains("Communication Style"));
+        let important = read("prompt-forge://instructions/priority/8").unwrap();
+        assert!(important.contains("Communication Style"));
+        assert!(!important.contains("Code Style Guidelines"));
+        assert_eq!(
+            read("prompt-forge://instructions/tag/none").unwrap(),
+            "No enabled instructions tagged \"none\"."
+        );
+        assert!(read("prompt-forge://instructions/other").is_none());
+        let compact = read("prompt-forge://instructions/compact?tokens=60").unwrap();
+        assert!(assembly::estimate_tokens(&compact) <= 60, "{}", compact);
+        assert!(compact.starts_with("# Prompt Forge Instructions (compact)"));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
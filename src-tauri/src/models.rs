@@ -1,3 +1,4 @@
+use base64::Engine;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
@@ -13,6 +14,14 @@ pub struct CreateAgentInput {
     pub skills: Vec<String>,
     pub instructions: Vec<String>,
     pub tags: Vec<String>,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    #[serde(default)]
+    pub instruction_order: InstructionOrder,
+    #[serde(default = "default_true")]
+    pub include_global_instructions: bool,
+    #[serde(default)]
+    pub parent_agent_id: Option<String>,
 }
 
 /// An Agent represents a customizable AI persona with specific skills and personality
@@ -31,6 +40,29 @@ pub struct Agent {
     pub updated_at: DateTime<Utc>,
     pub usage_count: i32,
     pub last_used_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub avatar_image_id: Option<String>, // Attachment ID of an image avatar, shown instead of the emoji
+    #[serde(default)]
+    pub aliases: Vec<String>, // Alternative names accepted by lookups
+    #[serde(default)]
+    pub instruction_order: InstructionOrder, // How attached instructions are assembled
+    #[serde(default = "default_true")]
+    pub include_global_instructions: bool, // Off for agents that only get their attached ones
+    #[serde(default)]
+    pub parent_agent_id: Option<String>, // Base agent it inherits what it leaves unset from
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Order of an agent's attached instructions in its assembled prompt
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InstructionOrder {
+    #[default]
+    Priority, // Highest priority first; equal priorities in attachment order
+    Attached, // The order they are attached to the agent in
 }
 
 impl Default for Agent {
@@ -49,6 +81,11 @@ impl Default for Agent {
             updated_at: Utc::now(),
             usage_count: 0,
             last_used_at: None,
+            avatar_image_id: None,
+            aliases: vec![],
+            instruction_order: InstructionOrder::default(),
+            include_global_instructions: true,
+            parent_agent_id: None,
         }
     }
 }
@@ -84,6 +121,8 @@ pub struct CreateSkillInput {
     pub skill_type: SkillType,
     pub definition: SkillDefinition,
     pub enabled: bool,
+    #[serde(default)]
+    pub aliases: Vec<String>,
 }
 
 /// A Skill represents a specific capability or tool the agent can use
@@ -98,6 +137,12 @@ pub struct Skill {
     pub enabled: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    #[serde(default)]
+    pub aliases: Vec<String>, // Alternative names accepted by lookups
+    #[serde(default)]
+    pub usage_count: i32, // Times served through the MCP server
+    #[serde(default)]
+    pub last_used_at: Option<DateTime<Utc>>,
 }
 
 impl Default for Skill {
@@ -110,15 +155,20 @@ impl Default for Skill {
             skill_type: SkillType::Prompt,
             definition: SkillDefinition::Prompt {
                 template: String::new(),
+                variables: Vec::new(),
+                follows: Vec::new(),
             },
             enabled: true,
             created_at: Utc::now(),
             updated_at: Utc::now(),
+            aliases: vec![],
+            usage_count: 0,
+            last_used_at: None,
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum SkillType {
     Prompt,      // A prompt template
@@ -131,16 +181,32 @@ pub enum SkillType {
 pub enum SkillDefinition {
     Prompt {
         template: String,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        variables: Vec<TemplateVariable>, // Declared `{{name}}` placeholders
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        follows: Vec<String>, // Prompt skill IDs rendered before this one, in order
     },
     Tool {
         parameters: Vec<ToolParameter>,
         handler: String, // Script or command to execute
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        sandbox: Option<ToolSandbox>, // Limits for running the handler; defaults when unset
     },
     Workflow {
         steps: Vec<WorkflowStep>,
     },
 }
 
+/// A `{{name}}` placeholder in a prompt template
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateVariable {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub default: Option<String>, // Variables without a default are required
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolParameter {
     pub name: String,
@@ -148,6 +214,29 @@ pub struct ToolParameter {
     pub param_type: String, // "string", "number", "boolean", "array", "object"
     pub required: bool,
     pub default: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<serde_json::Value>, // Further JSON Schema keywords: enum, minimum, properties, ...
+}
+
+/// Limits applied when the MCP server runs a tool skill's handler
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ToolSandbox {
+    pub timeout_secs: u64,           // The handler is killed after this long
+    pub working_dir: Option<String>, // Defaults to the client's workspace root, else the temp directory
+    pub inherit_env: bool, // Pass the server's whole environment instead of PATH, HOME and locale only
+    pub max_output_bytes: usize, // Per stream; the rest is dropped
+}
+
+impl Default for ToolSandbox {
+    fn default() -> Self {
+        Self {
+            timeout_secs: 30,
+            working_dir: None,
+            inherit_env: false,
+            max_output_bytes: 64 * 1024,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -170,6 +259,20 @@ pub struct CreateInstructionInput {
     pub priority: u8,
     pub tags: Vec<String>,
     pub enabled: bool,
+    #[serde(default)]
+    pub pin_to_context: bool,
+    #[serde(default)]
+    pub subcategory: Option<String>,
+    #[serde(default)]
+    pub branch_pattern: Option<String>,
+    #[serde(default)]
+    pub file_pattern: Option<String>,
+    #[serde(default)]
+    pub contexts: Vec<String>,
+    #[serde(default)]
+    pub review_due_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub activation: Option<InstructionActivation>,
 }
 
 /// An Instruction set - like CLAUDE.md but structured
@@ -186,6 +289,42 @@ pub struct Instruction {
     pub enabled: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    #[serde(default)]
+    pub pin_to_context: bool, // Always served via the pinned-context MCP tool/resource
+    #[serde(default)]
+    pub subcategory: Option<String>, // Path below the category, e.g. "rust" or "rust/async"
+    #[serde(default)]
+    pub branch_pattern: Option<String>, // Only assembled on matching git branches, e.g. "release/*"
+    #[serde(default)]
+    pub file_pattern: Option<String>, // Files the instruction is written for, e.g. "src/**/*.ts"
+    #[serde(default)]
+    pub contexts: Vec<String>, // Languages, projects or clients it is for, e.g. "rust"; empty for all
+    #[serde(default)]
+    pub usage_count: i32, // Times served through the MCP server
+    #[serde(default)]
+    pub last_used_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub review_due_at: Option<DateTime<Utc>>, // Flagged by `list_due_for_review` once past
+    #[serde(default)]
+    pub upstream_url: Option<String>, // Page the instruction was imported from
+    #[serde(default)]
+    pub upstream_synced_at: Option<DateTime<Utc>>, // When its content was last taken from there
+    #[serde(default)]
+    pub upstream_hash: Option<String>, // Digest of the page's content at that time
+    #[serde(default)]
+    pub activation: Option<InstructionActivation>, // When it is served, on top of being enabled
+}
+
+/// Rules limiting when an enabled instruction is served. Every rule that is
+/// set must hold; an empty list or `None` leaves that rule out.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InstructionActivation {
+    pub starts_on: Option<String>, // First day served, "YYYY-MM-DD"
+    pub ends_on: Option<String>,   // Last day served
+    pub weekdays: Vec<String>,     // "mon" to "sun"
+    pub hours: Option<String>,     // Local window such as "09:00-17:00"; may wrap midnight
+    pub profiles: Vec<String>,     // Served while one of these is the active profile
 }
 
 impl Default for Instruction {
@@ -202,11 +341,23 @@ impl Default for Instruction {
             enabled: true,
             created_at: Utc::now(),
             updated_at: Utc::now(),
+            pin_to_context: false,
+            subcategory: None,
+            branch_pattern: None,
+            file_pattern: None,
+            contexts: Vec::new(),
+            usage_count: 0,
+            last_used_at: None,
+            review_due_at: None,
+            upstream_url: None,
+            upstream_synced_at: None,
+            upstream_hash: None,
+            activation: None,
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum InstructionCategory {
     General,      // General guidelines
@@ -219,6 +370,60 @@ pub enum InstructionCategory {
     Custom,       // User-defined category
 }
 
+/// A multi-step operation recorded in the journal. Entries are removed once
+/// the operation completes, so any left over were cut short.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub id: String,
+    pub kind: String, // "replace_library" or "add_instructions"
+    pub summary: String,
+    pub status: String, // "running" or "interrupted"
+    pub started_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A folder for organizing library entities. Collections nest through
+/// `parent_id`, and an entity can be in several collections at once.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Collection {
+    pub id: String,
+    pub name: String,
+    pub parent_id: Option<String>, // None for top-level collections
+    #[serde(default)]
+    pub items: Vec<CollectionItem>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// An agent, skill or instruction filed in a collection
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CollectionItem {
+    pub entity_type: String, // "agent", "skill" or "instruction"
+    pub entity_id: String,
+}
+
+/// A reusable block of text that system prompts, prompt skill templates and
+/// instructions include with `{{> snippet-id}}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snippet {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub content: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Input for creating a new snippet (doesn't require id, timestamps)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateSnippetInput {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub content: String,
+}
+
 /// Application settings
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Settings {
@@ -227,6 +432,34 @@ pub struct Settings {
     pub mcp_server_enabled: bool,
     pub data_directory: Option<String>,
     pub auto_start_mcp: bool,
+    #[serde(default)]
+    pub notifications: NotificationSettings,
+    #[serde(default)]
+    pub insights_enabled: bool, // Opt-in, local-only usage counters
+    #[serde(default)]
+    pub redaction_profiles: Vec<RedactionProfile>,
+    #[serde(default)]
+    pub release_channels: Vec<ReleaseChannel>,
+    #[serde(default)]
+    pub block_sync_on_errors: bool, // Refuse to sync while library checks report errors
+    #[serde(default)]
+    pub strict_imports: bool, // Refuse imports whose parse diagnostics include errors
+    #[serde(default)]
+    pub llm_providers: Vec<LlmProvider>, // The first one is used unless a command names another
+    #[serde(default)]
+    pub backup_schedule: BackupSchedule,
+    #[serde(default)]
+    pub setup_completed: bool, // Set by `first_run_setup`; the UI shows the setup wizard until then
+    #[serde(default)]
+    pub retention: RetentionSettings,
+    #[serde(default)]
+    pub library_folder: Option<String>, // Mirrored as files and watched for outside edits
+    #[serde(default)]
+    pub mcp_write_tools: bool, // Lets MCP clients create and update instructions and skills
+    #[serde(default)]
+    pub active_profile: Option<String>, // Switches on instructions activated for this profile
+    #[serde(default)]
+    pub mcp_run_tools: bool, // Lets MCP clients run the approved handlers of tool skills
 }
 
 impl Default for Settings {
@@ -237,10 +470,88 @@ impl Default for Settings {
             mcp_server_enabled: false,
             data_directory: None,
             auto_start_mcp: false,
+            notifications: NotificationSettings::default(),
+            insights_enabled: false,
+            redaction_profiles: Vec::new(),
+            release_channels: Vec::new(),
+            block_sync_on_errors: false,
+            strict_imports: false,
+            llm_providers: Vec::new(),
+            backup_schedule: BackupSchedule::default(),
+            setup_completed: false,
+            retention: RetentionSettings::default(),
+            library_folder: None,
+            mcp_write_tools: false,
+            active_profile: None,
+            mcp_run_tools: false,
+        }
+    }
+}
+
+impl Settings {
+    /// These settings with provider API keys blanked, for anything that
+    /// leaves the machine
+    pub fn without_api_keys(&self) -> Settings {
+        let mut settings = self.clone();
+        for provider in &mut settings.llm_providers {
+            provider.api_key.clear();
+        }
+        settings
+    }
+
+    /// Imported settings that keep the providers configured in `local`, keys
+    /// and all. Providers only the import names are added after them.
+    pub fn keeping_providers(mut self, local: &Settings) -> Settings {
+        let imported = std::mem::replace(&mut self.llm_providers, local.llm_providers.clone());
+        for provider in imported {
+            if !self.llm_providers.iter().any(|p| p.name == provider.name) {
+                self.llm_providers.push(provider);
+            }
+        }
+        self
+    }
+}
+
+/// Which background events raise an OS notification
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationSettings {
+    pub mcp_crash: bool,
+    pub backup_failed: bool,
+    pub sync_failed: bool,
+    pub import_completed: bool,
+    pub review_due: bool,
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        Self {
+            mcp_crash: true,
+            backup_failed: true,
+            sync_failed: true,
+            import_completed: true,
+            review_due: true,
         }
     }
 }
 
+/// Named set of rules for producing a sanitized copy of library content on export
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RedactionProfile {
+    pub name: String,
+    pub strip_tags: Vec<String>,      // Tags dropped from exported entities
+    pub remove_sections: Vec<String>, // Regexes matched against markdown headings
+    pub replacements: Vec<RedactionReplacement>,
+}
+
+/// Case-insensitive literal substitution, e.g. an internal project name
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RedactionReplacement {
+    pub find: String,
+    pub replace: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Theme {
     pub mode: String, // "dark", "light", "auto"
@@ -258,6 +569,311 @@ impl Default for Theme {
     }
 }
 
+/// Local usage counters for a single day
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DailyInsights {
+    pub day: String, // YYYY-MM-DD
+    pub edits: i64,
+    pub applies: i64,
+    pub mcp_calls: i64,
+}
+
+/// Seven-day roll-up of local usage insights
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WeeklySummary {
+    pub start_day: String,
+    pub end_day: String,
+    pub days: Vec<DailyInsights>,
+    pub total_edits: i64,
+    pub total_applies: i64,
+    pub total_mcp_calls: i64,
+    pub top_categories: Vec<(String, i64)>, // (category, count), most used first
+}
+
+/// Uses of one entity on a single day
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DailyUsage {
+    pub day: String, // YYYY-MM-DD
+    pub count: i64,
+}
+
+/// When an entity gets used, bucketed by weekday and hour in the user's time zone
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageHeatmap {
+    pub entity_id: String,
+    pub start_day: String,
+    pub end_day: String,
+    pub hours: Vec<Vec<i64>>, // 7 rows (Monday first) of 24 hourly counts
+    pub days: Vec<DailyUsage>,
+    pub total: i64,
+    pub updated_at: Option<DateTime<Utc>>, // Last edit, to line usage shifts up with prompt changes
+}
+
+/// How much one agent, skill or instruction has been used
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageStat {
+    pub entity_type: String, // "agent", "skill" or "instruction"
+    pub id: String,
+    pub name: String,
+    pub uses: i64, // Within the window
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+/// Most- and least-used items over the last `days` days, or all time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageWindow {
+    pub days: Option<u32>, // None for all time
+    pub most_used: Vec<UsageStat>,
+    pub unused: Vec<UsageStat>, // Not used within the window; never used for all time
+}
+
+/// A proposed priority for one instruction, from `suggest_priorities`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrioritySuggestion {
+    pub instruction_id: String,
+    pub name: String,
+    pub current: u8,
+    pub suggested: u8,
+    pub reasons: Vec<String>, // What raised or lowered the instruction's rank
+}
+
+/// What one agent covers, in the columns of its `CapabilityMatrix`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentCapabilities {
+    pub agent_id: String,
+    pub agent_name: String,
+    pub categories: Vec<usize>,          // Enabled attached instructions per category
+    pub skill_types: Vec<usize>,         // Enabled attached skills per skill type
+    pub missing_categories: Vec<String>, // Categories this agent has no instructions in
+}
+
+/// Instruction categories and skill types covered by each agent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapabilityMatrix {
+    pub categories: Vec<String>,  // Column names for `AgentCapabilities::categories`
+    pub skill_types: Vec<String>, // Column names for `AgentCapabilities::skill_types`
+    pub agents: Vec<AgentCapabilities>,
+    pub uncovered_categories: Vec<String>, // Categories no agent carries instructions in
+    pub uncovered_skill_types: Vec<String>,
+}
+
+/// Filters, order and paging for the `list_*` commands; filters left unset
+/// match everything
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ListQuery {
+    pub offset: usize,
+    pub limit: Option<usize>,     // 50 when unset, at most 500
+    pub tags: Vec<String>,        // Items carrying every one of these
+    pub category: Option<String>, // Instructions filed here or below
+    pub enabled: Option<bool>,
+    pub query: Option<String>, // Text in the name, description or body
+    pub sort: Option<String>,  // name, created_at, updated_at, usage_count or priority
+    pub descending: bool,
+}
+
+/// One page of a filtered list
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub total: usize, // Items matching the filters across all pages
+    pub offset: usize,
+    pub limit: usize,
+}
+
+/// One change for `bulk_update_*` to make to every listed item; fields left
+/// unset change nothing
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BulkUpdate {
+    pub enabled: Option<bool>,
+    pub add_tags: Vec<String>,
+    pub remove_tags: Vec<String>,
+    pub category: Option<String>, // Instructions only, a path such as "code_style/rust"
+}
+
+/// One node of the category tree with instruction counts rolled up from its descendants
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategoryNode {
+    pub path: String,             // e.g. "code_style/rust"
+    pub name: String,             // Last path segment
+    pub depth: usize,             // 0 for top-level categories
+    pub instruction_count: usize, // Instructions filed directly under this path
+    pub total_count: usize,       // Including every subcategory
+}
+
+/// Starting point for a new instruction in a category, as returned by
+/// `get_category_template`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategoryTemplate {
+    pub category: InstructionCategory,
+    pub icon_emoji: String,
+    pub content: String, // Skeleton markdown with the recommended sections
+}
+
+/// A separate library with its own database
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LibraryWorkspace {
+    pub name: String,
+    pub db_path: String,
+    pub active: bool, // The workspace the app opens
+}
+
+/// Manifest written next to each backup snapshot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupManifest {
+    pub version: String,
+    pub created_at: DateTime<Utc>,
+    pub file_name: String,
+    pub size_bytes: u64,
+    pub sha256: String,
+    pub agents: usize,
+    pub skills: usize,
+    pub instructions: usize,
+}
+
+/// Backups the app takes on its own while it runs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackupSchedule {
+    pub interval_hours: u32, // 0 turns scheduled backups off
+    pub keep: usize,         // Newest backups kept in the directory; older ones are deleted
+    pub dir: Option<String>, // Defaults to `backups/` next to the database
+}
+
+impl Default for BackupSchedule {
+    fn default() -> Self {
+        Self {
+            interval_hours: 0,
+            keep: 7,
+            dir: None,
+        }
+    }
+}
+
+/// How long collected data is kept. `None` keeps it until purged by hand.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetentionSettings {
+    pub analytics_days: Option<u32>, // Insight counters and usage heatmap buckets
+}
+
+/// Rows removed by a purge
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PurgeReport {
+    pub kind: String,
+    pub before: Option<String>, // YYYY-MM-DD; `None` when everything was purged
+    pub removed: usize,
+}
+
+/// Result of checking a backup snapshot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupVerification {
+    pub path: String,
+    pub manifest_found: bool,
+    pub digest_matches: bool,
+    pub integrity_ok: bool,
+    pub restorable: bool, // Test-restore into memory succeeded and all entities loaded
+    pub agents: usize,
+    pub skills: usize,
+    pub instructions: usize,
+    pub errors: Vec<String>,
+}
+
+/// Outcome of syncing the library with a git repository
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitSyncResult {
+    pub commit: Option<String>, // Commit made or pulled; None when nothing changed
+    pub pushed: bool,
+    pub agents: usize,
+    pub skills: usize,
+    pub instructions: usize,
+}
+
+/// A bundled set of agents, skills and instructions to start a library from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplatePackInfo {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub agents: usize,
+    pub skills: usize,
+    pub instructions: usize,
+}
+
+/// Entities added to the library from a template pack
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SeedSummary {
+    pub pack: String,
+    pub added: usize,
+    pub skipped: usize, // Already in the library, left untouched
+}
+
+/// Prompt Forge's MCP server entry written into a client's config file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClientRegistration {
+    pub client: String, // "claude-desktop", "claude-code" or "cursor"
+    pub config_path: String,
+    pub replaced: bool, // An existing "prompt-forge" entry was overwritten
+}
+
+/// Everything the setup wizard asks for. Steps left unset are skipped.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FirstRunOptions {
+    pub template_pack: Option<String>,
+    pub clients: Vec<String>,    // MCP clients to register the server with
+    pub read_only_clients: bool, // Register with `--read-only`
+    pub backup_schedule: Option<BackupSchedule>,
+    pub claude_md_path: Option<String>, // Existing CLAUDE.md to import as an instruction
+}
+
+/// What `first_run_setup` did. A failed step is listed in `errors` and
+/// doesn't stop the others.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FirstRunReport {
+    pub seeded: Option<SeedSummary>,
+    pub registered: Vec<ClientRegistration>,
+    pub backup_schedule: Option<BackupSchedule>,
+    pub imported: Vec<Instruction>,
+    pub errors: Vec<String>,
+}
+
+/// One check run by `run_diagnostics`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SelfCheck {
+    pub id: String, // Stable, e.g. "database" or "provider:<name>"
+    pub name: String,
+    pub status: String, // "ok", "warning", "error" or "skipped"
+    pub message: String,
+    pub hint: Option<String>, // What to try when the check didn't pass
+}
+
+/// Self-check results for the troubleshooting page
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SelfCheckReport {
+    pub checks: Vec<SelfCheck>,
+    pub errors: usize,
+    pub warnings: usize,
+    pub checked_at: DateTime<Utc>,
+}
+
+/// A support bundle written by `export_support_bundle`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SupportBundle {
+    pub path: String,
+    pub files: Vec<String>, // Entries in the zip, in order
+    pub size_bytes: u64,
+}
+
+/// An agent kit written by `export_agent_mcp_kit`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentKit {
+    pub dir: String,
+    pub tag: String,        // Release the kit's MCP server is pinned to
+    pub files: Vec<String>, // Relative to `dir`
+}
+
 /// MCP Server status
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct McpStatus {
@@ -266,3 +882,638 @@ pub struct McpStatus {
     pub connected_clients: u32,
     pub available_tools: Vec<String>,
 }
+
+/// Row of the append-only `usage_events` table written by the MCP process
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageEvent {
+    pub id: i64,
+    pub kind: String,      // "agent_applied" or "insight"
+    pub entity_id: String, // Agent ID, or the insight key
+    pub payload: String,   // Insight metric for "insight" events
+    pub created_at: DateTime<Utc>,
+}
+
+/// Advisory lock held by a client while it edits an entity
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EditLock {
+    pub entity_id: String,
+    pub owner_id: String,   // Client that holds the lock
+    pub owner_name: String, // Human-readable owner (e.g. OS user name)
+    pub acquired_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Potential secret found in prompt content by the privacy scanner
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecretFinding {
+    pub entity_type: String, // "agent", "skill" or "instruction"
+    pub entity_id: String,
+    pub entity_name: String,
+    pub field: String,
+    pub kind: String, // e.g. "api_key", "email", "internal_hostname"
+    pub line: usize,
+    pub excerpt: String, // Masked so the finding itself doesn't leak the secret
+    pub blocking: bool,  // Credentials block exports; contact details only warn
+}
+
+/// Problem found by `validate_library`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LibraryIssue {
+    pub severity: String, // "error" blocks assembly, "warning" is skipped over
+    pub entity_type: String,
+    pub entity_id: String,
+    pub entity_name: String,
+    pub message: String,
+}
+
+/// An agent's reference to a skill, instruction or base agent missing from
+/// the library, as found by `validate_references`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OrphanedReference {
+    pub agent_id: String,
+    pub agent_name: String,
+    pub entity_type: String, // "skill", "instruction" or "parent"
+    pub reference: String,   // The missing ID
+}
+
+/// Two rules in enabled instructions that look contradictory, as found by
+/// `find_instruction_conflicts`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstructionConflict {
+    pub kind: String,  // "negation" (one forbids what the other asks) or "choice"
+    pub topic: String, // Shared keywords, or the choice such as "indentation: tabs or 2 spaces"
+    pub first: ConflictingRule,
+    pub second: ConflictingRule,
+}
+
+/// One side of an `InstructionConflict`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConflictingRule {
+    pub instruction_id: String,
+    pub instruction_name: String,
+    pub sentence: String,
+}
+
+/// Problem found while parsing imported text, as returned with an import preview
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParseDiagnostic {
+    pub line: usize,      // 1-based
+    pub column: usize,    // 1-based
+    pub severity: String, // "error" fails strict imports, "warning" never does
+    pub message: String,
+}
+
+/// An instruction or skill a ChatGPT data export suggests adding
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatGptSuggestion {
+    pub key: String, // Passed back to `import_chatgpt_suggestions` to add it
+    pub item: LibraryItem,
+    pub reason: String,
+    pub occurrences: usize,           // Chats it appeared in
+    pub duplicate_of: Option<String>, // Id of a library item saying much the same
+}
+
+/// How the library differs from another one, as returned by `compare_libraries`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LibraryComparison {
+    pub other: String, // Path of the other database or export
+    pub differences: Vec<LibraryDifference>,
+    pub same: usize, // Items both sides have with the same content
+}
+
+/// One agent, skill or instruction that isn't the same on both sides
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LibraryDifference {
+    pub entity_type: String,
+    pub id: String,
+    pub name: String,
+    pub status: DifferenceStatus,
+    pub fields: Vec<String>,   // Fields whose content differs
+    pub newer: Option<String>, // "here" or "there", by last edit, when content differs
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DifferenceStatus {
+    OnlyHere,
+    OnlyThere,
+    Differs,
+}
+
+/// A library entity of any type, as read or written by a format
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "entity_type", content = "entity", rename_all = "snake_case")]
+pub enum LibraryItem {
+    Agent(Agent),
+    Skill(Skill),
+    Instruction(Instruction),
+}
+
+impl LibraryItem {
+    /// "agent", "skill" or "instruction"
+    pub fn entity_type(&self) -> &'static str {
+        match self {
+            LibraryItem::Agent(_) => "agent",
+            LibraryItem::Skill(_) => "skill",
+            LibraryItem::Instruction(_) => "instruction",
+        }
+    }
+}
+
+/// Import/export format, as returned by `list_supported_formats`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SupportedFormat {
+    pub id: String, // Stable id passed to `import_with_format` / `export_with_format`
+    pub name: String,
+    pub entity_type: String, // "agent", "skill" or "instruction"
+    pub extension: String,   // Suggested file extension, without the dot
+    pub can_import: bool,
+    pub can_export: bool,
+}
+
+/// What importing a piece of text would produce, without saving it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportPreview<T> {
+    pub entity: T,
+    pub diagnostics: Vec<ParseDiagnostic>,
+}
+
+/// Library checks run before syncing, as returned by `preflight_library`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreflightReport {
+    pub issues: Vec<LibraryIssue>,
+    pub errors: usize,
+    pub warnings: usize,
+    pub blocked: bool,   // Errors found and `block_sync_on_errors` is on
+    pub summary: String, // Included in the sync commit message
+}
+
+/// Entry in the command palette, as returned by `query_actions`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaletteAction {
+    pub id: String, // Stable ID accepted by `run_action`, e.g. "agent.apply:<id>"
+    pub title: String,
+    pub subtitle: String,
+    pub category: String, // "agent", "skill", "instruction" or "operation"
+    pub score: i64,
+}
+
+/// Library item matching a query, as returned by the MCP `search_prompts` tool
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchResult {
+    pub entity_type: String, // "agent", "skill" or "instruction"
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub score: i64,
+}
+
+/// Skill or instruction suggested for the files a task touches, as returned by
+/// the MCP `suggest_skills_for_context` tool
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContextSuggestion {
+    pub entity_type: String, // "skill", "instruction" or "parent"
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub score: i64,
+    pub reasons: Vec<String>,
+}
+
+/// Result of running a palette action
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActionOutcome {
+    pub action_id: String,
+    pub view: Option<String>, // View the UI should switch to, if any
+    pub entity_id: Option<String>,
+    pub output: Option<String>, // Text produced by the action, e.g. an applied prompt
+    pub message: String,
+}
+
+/// Why an entity was or wasn't part of an assembled prompt
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TraceReason {
+    Included,
+    Disabled,
+    NotAttached,
+    Missing,    // Attached by ID but no longer in the library
+    OutOfScope, // Branch pattern doesn't match the assembly context
+}
+
+/// One line of an assembly trace
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TraceEntry {
+    pub entity_type: String, // "skill", "instruction" or "parent"
+    pub entity_id: String,
+    pub entity_name: String,
+    pub included: bool,
+    pub section: Option<String>, // Heading the entity was rendered under
+    pub reason: TraceReason,
+}
+
+/// An applied agent's prompt, with the assembly trace when requested
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppliedAgent {
+    pub prompt: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trace: Option<Vec<TraceEntry>>,
+}
+
+/// Part of an assembled prompt a section comes from
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PromptSectionKind {
+    Header,
+    SystemPrompt,
+    Skill,
+    Instruction,
+    GlobalInstruction,
+}
+
+/// One section of an assembled prompt with its size
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptSection {
+    pub kind: PromptSectionKind,
+    pub title: String,
+    pub entity_id: Option<String>, // Skill or instruction the section renders
+    pub text: String,
+    pub characters: usize,
+    pub tokens: usize, // Estimated
+}
+
+/// An agent's assembled prompt broken down into sections; the section texts
+/// concatenate to `prompt`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptPreview {
+    pub agent_id: String,
+    pub agent_name: String,
+    pub prompt: String,
+    pub sections: Vec<PromptSection>,
+    pub characters: usize,
+    pub tokens: usize,
+}
+
+/// How a composed prompt is wrapped when exported
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PromptWrapper {
+    #[default]
+    Plain,
+    Markdown, // A fenced ```markdown block
+    Xml,      // Between <system_prompt agent="..."> tags
+}
+
+/// Where `compose_and_export` sends a prompt
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PromptExportTarget {
+    File { path: String },
+    Clipboard,
+}
+
+/// What `compose_and_export` wrote and where
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptExport {
+    pub agent_id: String,
+    pub target: PromptExportTarget,
+    pub characters: usize,
+    pub tokens: usize, // Estimated
+}
+
+/// An agent's reply to a sample message from an LLM provider
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentTestRun {
+    pub agent_id: String,
+    pub provider: String,
+    pub model: String,
+    pub system_prompt: String,
+    pub prompt_tokens: usize, // Estimated
+    pub message: String,
+    pub reply: String,
+    pub duration_ms: u64,
+    pub usage: Option<TokenUsage>, // As reported by the provider, when it does
+}
+
+/// Tokens a provider reported for one completion
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+/// Replies of two agents, or two releases of one agent, to the same message
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentComparison {
+    pub message: String,
+    pub a: ComparisonSide,
+    pub b: ComparisonSide,
+}
+
+/// One side of an agent comparison; a failed side has an `error` instead of a run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComparisonSide {
+    pub agent_id: String,
+    pub release: Option<String>, // None for the live library
+    pub run: Option<AgentTestRun>,
+    pub error: Option<String>,
+}
+
+/// An A/B test of an instruction's wording. Each MCP session is served
+/// either the instruction's own content (variant A) or `variant_b`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Experiment {
+    pub instruction_id: String,
+    pub variant_b: String,
+    pub started_at: DateTime<Utc>,
+}
+
+/// How often each variant of an experiment was served since it started
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExperimentResult {
+    pub instruction_id: String,
+    pub instruction_name: String,
+    pub started_at: DateTime<Utc>,
+    pub served_a: i64, // Sessions served the instruction's own content
+    pub served_b: i64,
+}
+
+/// A frozen copy of the enabled library under a tag, so automations can
+/// assemble from a stable release while the live library keeps changing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Release {
+    pub tag: String,
+    pub notes: String,
+    pub created_at: DateTime<Utc>,
+    pub agents: Vec<Agent>,
+    pub skills: Vec<Skill>,             // Enabled skills only
+    pub instructions: Vec<Instruction>, // Enabled instructions only
+}
+
+/// A named pointer to a release that MCP servers can follow with `--channel`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReleaseChannel {
+    pub name: String, // e.g. "stable" or "team"
+    pub tag: String,  // Release served on this channel, or "latest" for the live library
+}
+
+/// A release without its snapshot, for listing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReleaseSummary {
+    pub tag: String,
+    pub notes: String,
+    pub created_at: DateTime<Utc>,
+    pub agent_count: usize,
+    pub skill_count: usize,
+    pub instruction_count: usize,
+}
+
+/// An LLM endpoint used for assisted features such as translation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LlmProvider {
+    pub name: String,
+    pub kind: String, // "openai" (any OpenAI-compatible API) or "anthropic"
+    pub model: String,
+    #[serde(default)]
+    pub base_url: Option<String>, // Defaults to the vendor's public API
+    #[serde(default)]
+    pub api_key: String,
+}
+
+/// A locale variant of an agent, skill or instruction. Translations start out
+/// needing review and are only served once approved.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Translation {
+    pub id: String,
+    pub entity_type: String, // "agent", "skill" or "instruction"
+    pub entity_id: String,
+    pub locale: String, // e.g. "de" or "pt-BR"
+    pub name: String,
+    pub description: String,
+    pub content: String, // System prompt, prompt template or instruction content
+    pub needs_review: bool,
+    pub provider: Option<String>, // LLM provider that produced it, if any
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Which guidance an onboarding document covers. Empty fields mean the
+/// whole library.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SummaryScope {
+    #[serde(default)]
+    pub agent_id: Option<String>, // Only what this agent uses (ID or name)
+    #[serde(default)]
+    pub category: Option<String>, // Category path such as "code_style/rust"
+}
+
+/// An onboarding document produced by `summarize_library`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OnboardingDocument {
+    pub title: String,
+    pub format: String, // "markdown" or "html"
+    pub content: String,
+    pub generated_by: String,   // "extractive", or the LLM provider's name
+    pub notice: Option<String>, // Why an LLM-assisted summary fell back to the extractive one
+}
+
+/// A database found in a Notion export folder
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotionDatabase {
+    pub name: String,      // Database title without Notion's ID suffix
+    pub file_name: String, // CSV file to pass to `import_notion_database`
+    pub columns: Vec<String>,
+    pub row_count: usize,
+}
+
+/// Which Notion columns fill which instruction fields. Unmapped fields keep
+/// their defaults; the title column is used for the name unless overridden.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NotionMapping {
+    #[serde(default)]
+    pub name_column: Option<String>,
+    #[serde(default)]
+    pub category_column: Option<String>, // Values like "Code Style" or "code_style/rust"
+    #[serde(default)]
+    pub tags_column: Option<String>, // Multi-select, exported comma-separated
+    #[serde(default)]
+    pub priority_column: Option<String>, // 1-10 or a label such as "High"
+    #[serde(default)]
+    pub description_column: Option<String>,
+    #[serde(default)]
+    pub content_column: Option<String>, // Used when a row has no page body
+    #[serde(default)]
+    pub default_category: Option<String>, // For rows without a category value
+}
+
+/// Outcome of a Notion import
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotionImportResult {
+    pub imported: Vec<Instruction>,
+    pub skipped: Vec<String>, // Rows with neither a page body nor content
+}
+
+/// What `check_upstream_updates` found at an instruction's source page
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UpstreamStatus {
+    Unchanged,
+    Changed,
+    Unreachable,
+}
+
+/// An instruction imported from a URL, checked against its source page
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpstreamCheck {
+    pub instruction_id: String,
+    pub name: String,
+    pub upstream_url: String,
+    pub status: UpstreamStatus,
+    pub upstream_content: Option<String>, // The page's content now, when it changed
+    pub error: Option<String>,            // Why the page couldn't be fetched
+}
+
+/// Image types accepted as attachments
+pub const ATTACHMENT_MIME_TYPES: &[&str] = &[
+    "image/png",
+    "image/jpeg",
+    "image/gif",
+    "image/webp",
+    "image/svg+xml",
+];
+
+/// Largest attachment we store inline in the database
+pub const MAX_ATTACHMENT_BYTES: usize = 1024 * 1024;
+
+/// A binary blob stored in the library, e.g. an agent's avatar image
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Attachment {
+    pub id: String,
+    pub mime_type: String,
+    #[serde(with = "base64_bytes")]
+    pub data: Vec<u8>, // Base64 in JSON exports, raw bytes in SQLite
+    pub created_at: DateTime<Utc>,
+}
+
+impl Attachment {
+    /// Build an attachment from a `data:<mime>;base64,<payload>` URL
+    pub fn from_data_url(data_url: &str) -> Result<Self, String> {
+        let rest = data_url
+            .strip_prefix("data:")
+            .ok_or("Image must be a data URL")?;
+        let (mime_type, payload) = rest
+            .split_once(";base64,")
+            .ok_or("Image data URL must be base64 encoded")?;
+
+        if !ATTACHMENT_MIME_TYPES.contains(&mime_type) {
+            return Err(format!("Unsupported image type: {}", mime_type));
+        }
+
+        let data = base64::engine::general_purpose::STANDARD
+            .decode(payload.trim())
+            .map_err(|e| format!("Invalid image data: {}", e))?;
+
+        if data.len() > MAX_ATTACHMENT_BYTES {
+            return Err(format!(
+                "Image is too large ({} KB, max {} KB)",
+                data.len() / 1024,
+                MAX_ATTACHMENT_BYTES / 1024
+            ));
+        }
+
+        Ok(Self {
+            id: Uuid::new_v4().to_string(),
+            mime_type: mime_type.to_string(),
+            data,
+            created_at: Utc::now(),
+        })
+    }
+
+    /// Render the attachment as a data URL usable directly in `<img src>`
+    pub fn to_data_url(&self) -> String {
+        format!(
+            "data:{};base64,{}",
+            self.mime_type,
+            base64::engine::general_purpose::STANDARD.encode(&self.data)
+        )
+    }
+}
+
+mod base64_bytes {
+    use base64::Engine;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&base64::engine::general_purpose::STANDARD.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        base64::engine::general_purpose::STANDARD
+            .decode(encoded)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_provider_keys_stay_on_this_machine() {
+        let provider = |name: &str, api_key: &str| LlmProvider {
+            name: name.to_string(),
+            kind: "openai".to_string(),
+            model: "gpt-4o".to_string(),
+            base_url: None,
+            api_key: api_key.to_string(),
+        };
+        let local = Settings {
+            llm_providers: vec![provider("work", "sk-local")],
+            ..Settings::default()
+        };
+        let exported = Settings {
+            llm_providers: vec![provider("work", "sk-theirs"), provider("home", "sk-home")],
+            ..Settings::default()
+        }
+        .without_api_keys();
+        assert!(exported.llm_providers.iter().all(|p| p.api_key.is_empty()));
+
+        let imported = exported.keeping_providers(&local);
+        let providers: Vec<_> = imported
+            .llm_providers
+            .iter()
+            .map(|p| (p.name.as_str(), p.api_key.as_str()))
+            .collect();
+        assert_eq!(providers, [("work", "sk-local"), ("home", "")]);
+    }
+
+    #[test]
+    fn test_attachment_data_urls_are_checked() {
+        let png = Attachment::from_data_url("data:image/png;base64,iVBORw0KGgo=").unwrap();
+        assert_eq!(png.mime_type, "image/png");
+        assert_eq!(png.data, b"\x89PNG\r\n\x1a\n");
+        assert_eq!(png.to_data_url(), "data:image/png;base64,iVBORw0KGgo=");
+
+        for url in [
+            "",
+            "image/png;base64,iVBORw0KGgo=",
+            "data:image/png,iVBORw0KGgo=",
+            "data:;base64,iVBORw0KGgo=",
+            "data:text/html;base64,PGgxPg==",
+        ] {
+            assert!(Attachment::from_data_url(url).is_err(), "{}", url);
+        }
+
+        let not_base64 = Attachment::from_data_url("data:image/png;base64,not base64!");
+        assert!(not_base64.unwrap_err().starts_with("Invalid image data"));
+
+        let encode = |len: usize| {
+            let payload = base64::engine::general_purpose::STANDARD.encode(vec![0u8; len]);
+            format!("data:image/gif;base64,{}", payload)
+        };
+        assert!(Attachment::from_data_url(&encode(MAX_ATTACHMENT_BYTES)).is_ok());
+        let too_large = Attachment::from_data_url(&encode(MAX_ATTACHMENT_BYTES + 1));
+        assert!(too_large.unwrap_err().starts_with("Image is too large"));
+    }
+}
@@ -0,0 +1,278 @@
+//! Names and descriptions for imported content
+//! Imported text often carries no name or description, which used to leave
+//! items called "New Agent" or "New Instruction". The gaps are filled from
+//! the text itself: the name from the first `# ` heading, or failing that
+//! the first sentence, and the description from the first sentence of prose.
+//! The same text always gives the same result. Commands can instead ask an
+//! LLM provider, using `metadata_prompt` and `apply_reply`.
+
+use crate::assembly::truncate_at_word;
+use crate::code_blocks::FenceTracker;
+use crate::models::{Agent, Instruction, LibraryItem, Skill, SkillDefinition};
+use regex::Regex;
+use serde::Deserialize;
+
+const MAX_NAME_CHARS: usize = 60;
+const MAX_DESCRIPTION_CHARS: usize = 160;
+
+/// Whether `item` still has a default or empty name or description
+pub fn needs_metadata(item: &LibraryItem) -> bool {
+    let (name, description) = missing(item);
+    name || description
+}
+
+/// Fill a default or empty name and description from the item's text. Parts
+/// the text gives nothing for are left as they are.
+pub fn fill_missing(item: &mut LibraryItem) {
+    let (name_missing, description_missing) = missing(item);
+    let body = body(item).to_string();
+    let (name, description) = fields(item);
+    if name_missing {
+        if let Some(derived) = derive_name(&body) {
+            *name = derived;
+        }
+    }
+    if description_missing {
+        if let Some(derived) = derive_description(&body) {
+            *description = derived;
+        }
+    }
+}
+
+/// Name from the first `# ` heading, else the first sentence of prose
+pub fn derive_name(text: &str) -> Option<String> {
+    let name = first_heading(text).or_else(|| first_sentence(text))?;
+    let name = name.trim_end_matches(['.', '!', '?', ':']);
+    Some(truncate_at_word(name, MAX_NAME_CHARS))
+}
+
+/// Description from the first sentence of prose
+pub fn derive_description(text: &str) -> Option<String> {
+    let sentence = first_sentence(text)?;
+    Some(truncate_at_word(&sentence, MAX_DESCRIPTION_CHARS))
+}
+
+/// System prompt asking a provider to name the text sent as the user message
+pub fn metadata_prompt(entity_type: &str) -> String {
+    format!(
+        "You name entries of a prompt library. The user message is the text of an imported {}. \
+         Reply with only a JSON object with the keys \"name\" (a short title of at most {} \
+         characters) and \"description\" (one sentence of at most {} characters) describing it.",
+        entity_type, MAX_NAME_CHARS, MAX_DESCRIPTION_CHARS
+    )
+}
+
+/// The text a provider is asked to name
+pub fn body(item: &LibraryItem) -> &str {
+    match item {
+        LibraryItem::Agent(agent) => &agent.system_prompt,
+        LibraryItem::Skill(skill) => match &skill.definition {
+            SkillDefinition::Prompt { template, .. } => template,
+            _ => "",
+        },
+        LibraryItem::Instruction(instruction) => &instruction.content,
+    }
+}
+
+#[derive(Deserialize)]
+struct Metadata {
+    name: String,
+    #[serde(default)]
+    description: String,
+}
+
+/// Set the missing name and description from a provider's reply, which may
+/// wrap the JSON in a code fence or a sentence
+pub fn apply_reply(item: &mut LibraryItem, reply: &str) -> Result<(), String> {
+    let json = match (reply.find('{'), reply.rfind('}')) {
+        (Some(start), Some(end)) if start < end => &reply[start..=end],
+        _ => return Err("The provider's reply contained no JSON object".to_string()),
+    };
+    let metadata: Metadata = serde_json::from_str(json)
+        .map_err(|e| format!("Failed to read the provider's reply: {}", e))?;
+    if metadata.name.trim().is_empty() {
+        return Err("The provider's reply has no name".to_string());
+    }
+
+    let (name_missing, description_missing) = missing(item);
+    let (name, description) = fields(item);
+    if name_missing {
+        *name = truncate_at_word(metadata.name.trim(), MAX_NAME_CHARS);
+    }
+    if description_missing && !metadata.description.trim().is_empty() {
+        *description = truncate_at_word(metadata.description.trim(), MAX_DESCRIPTION_CHARS);
+    }
+    Ok(())
+}
+
+/// Whether the name and the description are default or empty
+fn missing(item: &LibraryItem) -> (bool, bool) {
+    let (name, description, defaults) = match item {
+        LibraryItem::Agent(agent) => {
+            let defaults = Agent::default();
+            (
+                &agent.name,
+                &agent.description,
+                (defaults.name, defaults.description),
+            )
+        }
+        LibraryItem::Skill(skill) => {
+            let defaults = Skill::default();
+            (
+                &skill.name,
+                &skill.description,
+                (defaults.name, defaults.description),
+            )
+        }
+        LibraryItem::Instruction(instruction) => {
+            let defaults = Instruction::default();
+            (
+                &instruction.name,
+                &instruction.description,
+                (defaults.name, defaults.description),
+            )
+        }
+    };
+    (
+        name.trim().is_empty() || *name == defaults.0,
+        description.trim().is_empty() || *description == defaults.1,
+    )
+}
+
+fn fields(item: &mut LibraryItem) -> (&mut String, &mut String) {
+    match item {
+        LibraryItem::Agent(agent) => (&mut agent.name, &mut agent.description),
+        LibraryItem::Skill(skill) => (&mut skill.name, &mut skill.description),
+        LibraryItem::Instruction(instruction) => {
+            (&mut instruction.name, &mut instruction.description)
+        }
+    }
+}
+
+fn first_heading(text: &str) -> Option<String> {
+    let mut fences = FenceTracker::default();
+    text.lines()
+        .filter(|line| !fences.update(line))
+        .find_map(|line| line.strip_prefix("# "))
+        .map(plain_text)
+        .filter(|heading| !heading.is_empty())
+}
+
+/// First sentence of the first paragraph that isn't a heading, code block,
+/// table, rule or HTML. A list item counts as a paragraph of its own.
+fn first_sentence(text: &str) -> Option<String> {
+    let mut fences = FenceTracker::default();
+    let mut paragraph: Vec<String> = Vec::new();
+    for line in text.lines() {
+        let trimmed = line.trim();
+        let skipped = fences.update(line)
+            || trimmed.starts_with('#')
+            || trimmed.starts_with('|')
+            || trimmed.starts_with('<')
+            || trimmed.chars().all(|c| "-*_=".contains(c));
+        if skipped || trimmed.is_empty() {
+            if paragraph.is_empty() {
+                continue;
+            }
+            break;
+        }
+
+        let item = list_item(trimmed);
+        if item.is_some() && !paragraph.is_empty() {
+            break;
+        }
+        paragraph.push(plain_text(item.unwrap_or(trimmed)));
+        if item.is_some() {
+            break;
+        }
+    }
+
+    let paragraph = paragraph.join(" ");
+    let end = paragraph
+        .char_indices()
+        .find(|&(i, c)| {
+            ".!?".contains(c)
+                && paragraph[i + 1..]
+                    .chars()
+                    .next()
+                    .is_none_or(char::is_whitespace)
+        })
+        .map_or(paragraph.len(), |(i, _)| i + 1);
+    Some(paragraph[..end].trim().to_string()).filter(|s| !s.is_empty())
+}
+
+/// The text of a list item or quote line, without its marker
+fn list_item(line: &str) -> Option<&str> {
+    if let Some(rest) = ["- ", "* ", "+ ", "> "]
+        .iter()
+        .find_map(|marker| line.strip_prefix(marker))
+    {
+        return Some(rest.trim_start());
+    }
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    line[digits..]
+        .strip_prefix(". ")
+        .filter(|_| digits > 0)
+        .map(str::trim_start)
+}
+
+/// `text` without emphasis, inline code marks and link targets
+fn plain_text(text: &str) -> String {
+    let link = Regex::new(r"\[([^\]]*)\]\([^)]*\)").unwrap();
+    link.replace_all(text, "$1")
+        .replace(['*', '`'], "")
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_names_come_from_heading_or_first_sentence() {
+        let mut titled = LibraryItem::Instruction(Instruction {
+            content: "```\n# not a title\n```\n\n# Rust **Error** Handling\n\n\
+                      Prefer `Result` over panics. Use thiserror."
+                .to_string(),
+            ..Instruction::default()
+        });
+        fill_missing(&mut titled);
+        let LibraryItem::Instruction(titled) = titled else {
+            unreachable!()
+        };
+        assert_eq!(titled.name, "Rust Error Handling");
+        assert_eq!(titled.description, "Prefer Result over panics.");
+
+        let mut untitled = LibraryItem::Agent(Agent {
+            system_prompt: "## Role\n\n- You review [pull requests](https://example.com) for \
+                            safety! Then you summarize.\n- Second item"
+                .to_string(),
+            ..Agent::default()
+        });
+        assert!(needs_metadata(&untitled));
+        fill_missing(&mut untitled);
+        assert!(!needs_metadata(&untitled));
+        let LibraryItem::Agent(untitled) = untitled else {
+            unreachable!()
+        };
+        assert_eq!(untitled.name, "You review pull requests for safety");
+        assert_eq!(untitled.description, "You review pull requests for safety!");
+
+        let mut named = LibraryItem::Instruction(Instruction {
+            name: "Kept".to_string(),
+            content: "Some text.".to_string(),
+            ..Instruction::default()
+        });
+        apply_reply(
+            &mut named,
+            "Sure:\n```json\n{\"name\": \"Ignored\", \"description\": \"From the model\"}\n```",
+        )
+        .unwrap();
+        let LibraryItem::Instruction(named) = named else {
+            unreachable!()
+        };
+        assert_eq!(named.name, "Kept");
+        assert_eq!(named.description, "From the model");
+    }
+}
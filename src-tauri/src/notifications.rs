@@ -0,0 +1,57 @@
+//! OS-level notifications for background events the user would otherwise miss
+//! (MCP server crashes, failed backups/syncs, finished imports, instructions
+//! overdue for review).
+
+use crate::db::Database;
+use crate::models::NotificationSettings;
+use tauri::AppHandle;
+use tauri_plugin_notification::NotificationExt;
+
+#[derive(Debug, Clone, Copy)]
+pub enum NotificationEvent {
+    McpCrashed,
+    BackupFailed,
+    SyncFailed,
+    ImportCompleted,
+    ReviewDue,
+}
+
+impl NotificationEvent {
+    fn title(&self) -> &'static str {
+        match self {
+            NotificationEvent::McpCrashed => "MCP server stopped",
+            NotificationEvent::BackupFailed => "Backup failed",
+            NotificationEvent::SyncFailed => "Sync failed",
+            NotificationEvent::ImportCompleted => "Import complete",
+            NotificationEvent::ReviewDue => "Instructions due for review",
+        }
+    }
+
+    fn is_enabled(&self, settings: &NotificationSettings) -> bool {
+        match self {
+            NotificationEvent::McpCrashed => settings.mcp_crash,
+            NotificationEvent::BackupFailed => settings.backup_failed,
+            NotificationEvent::SyncFailed => settings.sync_failed,
+            NotificationEvent::ImportCompleted => settings.import_completed,
+            NotificationEvent::ReviewDue => settings.review_due,
+        }
+    }
+}
+
+/// Show a notification for `event` if the user has it enabled in settings
+pub fn notify(app: &AppHandle, db: &Database, event: NotificationEvent, body: &str) {
+    let settings = db.get_settings().unwrap_or_default();
+    if !event.is_enabled(&settings.notifications) {
+        return;
+    }
+
+    if let Err(e) = app
+        .notification()
+        .builder()
+        .title(event.title())
+        .body(body)
+        .show()
+    {
+        eprintln!("Failed to show notification: {}", e);
+    }
+}
@@ -0,0 +1,324 @@
+//! Import of Notion database exports
+//! A "Markdown & CSV" export of a database is a `<Name> <id>.csv` with one row
+//! per page, next to a `<Name> <id>/` folder holding each page as
+//! `<Title> <id>.md`. Each row becomes an instruction: mapped columns fill in
+//! the category, tags and priority, and the page body becomes the content.
+
+use crate::categories;
+use crate::code_blocks;
+use crate::models::{Instruction, NotionDatabase, NotionImportResult, NotionMapping};
+use std::fs;
+use std::path::Path;
+
+/// Instructions built from one database's CSV and its page files. `pages`
+/// holds `(file stem, markdown)` pairs; rows are matched to pages by title.
+pub fn import_database(
+    csv: &str,
+    pages: &[(String, String)],
+    mapping: &NotionMapping,
+) -> Result<NotionImportResult, String> {
+    let mut rows = parse_csv(csv).into_iter();
+    let header = rows
+        .next()
+        .ok_or_else(|| "Notion export CSV is empty".to_string())?;
+
+    let column = |name: &Option<String>| -> Result<Option<usize>, String> {
+        match name.as_deref().map(str::trim).filter(|n| !n.is_empty()) {
+            None => Ok(None),
+            Some(name) => header
+                .iter()
+                .position(|h| h.eq_ignore_ascii_case(name))
+                .map(Some)
+                .ok_or_else(|| format!("Column '{}' not found in Notion export", name)),
+        }
+    };
+    // Notion always exports the page title as the first column
+    let name_col = column(&mapping.name_column)?.unwrap_or(0);
+    let category_col = column(&mapping.category_column)?;
+    let tags_col = column(&mapping.tags_column)?;
+    let priority_col = column(&mapping.priority_column)?;
+    let description_col = column(&mapping.description_column)?;
+    let content_col = column(&mapping.content_column)?;
+
+    let mut result = NotionImportResult {
+        imported: vec![],
+        skipped: vec![],
+    };
+
+    for row in rows {
+        let cell = |col: Option<usize>| {
+            col.and_then(|c| row.get(c))
+                .map(|v| v.trim())
+                .filter(|v| !v.is_empty())
+        };
+        let Some(name) = cell(Some(name_col)) else {
+            continue;
+        };
+
+        let page = pages
+            .iter()
+            .find(|(stem, _)| page_key(strip_notion_id(stem)) == page_key(name))
+            .map(|(_, markdown)| page_body(markdown, &header))
+            .filter(|body| !body.is_empty());
+        let Some(content) = page.or_else(|| cell(content_col).map(str::to_string)) else {
+            result.skipped.push(name.to_string());
+            continue;
+        };
+
+        let mut instruction = Instruction {
+            name: name.to_string(),
+            content: code_blocks::fence_code(&content),
+            ..Instruction::default()
+        };
+        if let Some(description) = cell(description_col) {
+            instruction.description = description.to_string();
+        } else {
+            instruction.description = "Imported from Notion".to_string();
+        }
+        if let Some(category) = cell(category_col)
+            .or(mapping.default_category.as_deref())
+            .and_then(categories::normalize_subcategory)
+        {
+            (instruction.category, instruction.subcategory) =
+                categories::parse_category_path(&category);
+        }
+        if let Some(tags) = cell(tags_col) {
+            instruction.tags = tags
+                .split(',')
+                .map(|t| t.trim().to_string())
+                .filter(|t| !t.is_empty())
+                .collect();
+        }
+        if let Some(priority) = cell(priority_col).and_then(parse_priority) {
+            instruction.priority = priority;
+        }
+
+        result.imported.push(instruction);
+    }
+
+    Ok(result)
+}
+
+/// Database CSVs found at the top of an unzipped export folder
+pub fn find_databases(dir: &Path) -> Result<Vec<NotionDatabase>, String> {
+    let entries = fs::read_dir(dir).map_err(|e| format!("Failed to read export folder: {}", e))?;
+
+    let mut databases = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("csv") {
+            continue;
+        }
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        // Newer exports write both `<Name> <id>.csv` (current view) and
+        // `<Name> <id>_all.csv` (every row); offer only the complete one
+        if !file_name.ends_with("_all.csv")
+            && dir.join(file_name.replace(".csv", "_all.csv")).is_file()
+        {
+            continue;
+        }
+
+        let csv = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {}", file_name, e))?;
+        let mut rows = parse_csv(&csv).into_iter();
+        let columns = rows.next().unwrap_or_default();
+        let stem = file_name.trim_end_matches(".csv").trim_end_matches("_all");
+
+        databases.push(NotionDatabase {
+            name: strip_notion_id(stem).to_string(),
+            file_name,
+            columns,
+            row_count: rows.count(),
+        });
+    }
+
+    databases.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(databases)
+}
+
+/// Read a database CSV and the page files exported alongside it
+pub fn read_database(
+    dir: &Path,
+    file_name: &str,
+) -> Result<(String, Vec<(String, String)>), String> {
+    let csv = fs::read_to_string(dir.join(file_name))
+        .map_err(|e| format!("Failed to read {}: {}", file_name, e))?;
+
+    let stem = file_name.trim_end_matches(".csv").trim_end_matches("_all");
+    let mut pages = Vec::new();
+    if let Ok(entries) = fs::read_dir(dir.join(stem)) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("md") {
+                continue;
+            }
+            let markdown = fs::read_to_string(&path)
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            let page_stem = path
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default();
+            pages.push((page_stem, markdown));
+        }
+    }
+
+    Ok((csv, pages))
+}
+
+/// Split CSV text into rows of fields, handling quoted fields with embedded
+/// commas, quotes and newlines
+pub fn parse_csv(text: &str) -> Vec<Vec<String>> {
+    let text = text.trim_start_matches('\u{feff}');
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => row.push(std::mem::take(&mut field)),
+            '\r' if !quoted => {}
+            '\n' if !quoted => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            _ => field.push(c),
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+
+    rows.retain(|r| r.iter().any(|f| !f.is_empty()));
+    rows
+}
+
+/// Drop the 32-character hex ID Notion appends to exported names
+fn strip_notion_id(stem: &str) -> &str {
+    match stem.rsplit_once(' ') {
+        Some((title, id)) if id.len() == 32 && id.chars().all(|c| c.is_ascii_hexdigit()) => title,
+        _ => stem,
+    }
+}
+
+/// Comparison key for titles, since Notion strips characters such as `/`
+/// and `:` from file names
+fn page_key(title: &str) -> String {
+    title
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Page markdown without the `# Title` heading and the property lines Notion
+/// writes beneath it
+fn page_body(markdown: &str, columns: &[String]) -> String {
+    let mut lines = markdown.lines().peekable();
+    if lines.peek().is_some_and(|l| l.starts_with("# ")) {
+        lines.next();
+    }
+    while let Some(line) = lines.peek() {
+        let is_property = line
+            .split_once(':')
+            .is_some_and(|(key, _)| columns.iter().any(|c| c.eq_ignore_ascii_case(key.trim())));
+        if line.trim().is_empty() || is_property {
+            lines.next();
+        } else {
+            break;
+        }
+    }
+    lines.collect::<Vec<_>>().join("\n").trim().to_string()
+}
+
+/// A 1-10 number, or a Notion select label such as "High"
+fn parse_priority(value: &str) -> Option<u8> {
+    if let Ok(number) = value.parse::<f64>() {
+        return Some(number.round().clamp(1.0, 10.0) as u8);
+    }
+    match value.to_lowercase().as_str() {
+        "critical" | "urgent" => Some(10),
+        "high" => Some(8),
+        "medium" | "normal" => Some(5),
+        "low" => Some(2),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::InstructionCategory;
+
+    #[test]
+    fn test_parse_csv_handles_quoted_fields() {
+        let csv = "\u{feff}Name,Notes\r\n\"Reviews, PRs\",\"Say \"\"why\"\"\nnot what\"\r\n\r\n";
+        assert_eq!(
+            parse_csv(csv),
+            vec![
+                vec!["Name".to_string(), "Notes".to_string()],
+                vec![
+                    "Reviews, PRs".to_string(),
+                    "Say \"why\"\nnot what".to_string()
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rows_map_to_instructions() {
+        let csv = "Name,Area,Labels,Importance,Summary\n\
+                   Rust: error handling,Code Style/Rust,\"rust, errors\",High,Errors as values\n\
+                   Commit messages,Workflow,git,3,\n\
+                   Empty draft,Workflow,,,\n";
+        let pages = vec![
+            (
+                "Rust error handling 0123456789abcdef0123456789abcdef".to_string(),
+                "# Rust: error handling\n\nArea: Code Style/Rust\nLabels: rust, errors\n\n\
+                 Return `Result` instead of panicking."
+                    .to_string(),
+            ),
+            (
+                "Commit messages fedcba9876543210fedcba9876543210".to_string(),
+                "# Commit messages\n\nUse the imperative mood.\n".to_string(),
+            ),
+        ];
+        let mapping = NotionMapping {
+            category_column: Some("Area".to_string()),
+            tags_column: Some("labels".to_string()),
+            priority_column: Some("Importance".to_string()),
+            description_column: Some("Summary".to_string()),
+            ..NotionMapping::default()
+        };
+
+        let result = import_database(csv, &pages, &mapping).unwrap();
+        assert_eq!(result.skipped, vec!["Empty draft".to_string()]);
+
+        let rust = &result.imported[0];
+        assert_eq!(rust.name, "Rust: error handling");
+        assert_eq!(rust.content, "Return `Result` instead of panicking.");
+        assert_eq!(rust.description, "Errors as values");
+        assert!(matches!(rust.category, InstructionCategory::CodeStyle));
+        assert_eq!(rust.subcategory.as_deref(), Some("rust"));
+        assert_eq!(rust.tags, vec!["rust", "errors"]);
+        assert_eq!(rust.priority, 8);
+
+        let commits = &result.imported[1];
+        assert!(matches!(commits.category, InstructionCategory::Workflow));
+        assert_eq!(commits.priority, 3);
+        assert_eq!(commits.description, "Imported from Notion");
+
+        let missing = NotionMapping {
+            tags_column: Some("Owner".to_string()),
+            ..NotionMapping::default()
+        };
+        assert!(import_database(csv, &pages, &missing).is_err());
+    }
+}
@@ -0,0 +1,602 @@
+//! Onboarding documents summarizing the library's active guidance
+//! The extractive summary is built from the library alone: each instruction's
+//! description (or opening sentence) and its top-level list items, grouped by
+//! category with pinned rules first. It is also the fallback whenever an
+//! LLM-assisted summary can't be produced.
+
+use crate::categories;
+use crate::composition::LibraryGraph;
+use crate::models::{Agent, Instruction, Skill, SummaryScope};
+use crate::resolve::resolve_entity;
+use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag};
+use std::collections::{BTreeMap, HashMap};
+
+/// Key points listed per instruction
+const MAX_POINTS: usize = 5;
+/// Longest opening summary taken from an instruction's content
+const MAX_SUMMARY_CHARS: usize = 240;
+
+/// The entities an onboarding document covers
+pub struct Guidance<'a> {
+    pub title: String,
+    pub overview: Option<String>,
+    pub agents: Vec<&'a Agent>,
+    pub skills: Vec<&'a Skill>,
+    pub instructions: Vec<&'a Instruction>,
+    /// Image avatars as data URLs, keyed by agent ID
+    pub avatars: HashMap<String, String>,
+}
+
+impl Guidance<'_> {
+    pub fn is_empty(&self) -> bool {
+        self.skills.is_empty() && self.instructions.is_empty()
+    }
+}
+
+/// Pick the enabled guidance in `scope`
+pub fn select<'a>(
+    library: &LibraryGraph<'a>,
+    scope: &SummaryScope,
+) -> Result<Guidance<'a>, String> {
+    let category = scope
+        .category
+        .as_deref()
+        .map(str::trim)
+        .filter(|c| !c.is_empty());
+    if let Some(category) = category {
+        let top = category.trim_matches('/').split('/').next().unwrap_or("");
+        if categories::try_parse_category(top).is_none() {
+            return Err(format!("Unknown category '{}'", category));
+        }
+    }
+    let in_category = |i: &Instruction| match category {
+        Some(category) => categories::matches_category(i, category),
+        None => true,
+    };
+
+    let agent_id = scope
+        .agent_id
+        .as_deref()
+        .map(str::trim)
+        .filter(|a| !a.is_empty());
+    let guidance = match agent_id {
+        Some(agent_id) => {
+            let agent = resolve_entity(library.agents, agent_id)?;
+            Guidance {
+                title: format!("Working with {}", agent.name),
+                overview: Some(agent.description.clone()).filter(|d| !d.trim().is_empty()),
+                agents: vec![agent],
+                skills: library
+                    .skills
+                    .iter()
+                    .filter(|s| s.enabled && agent.skills.contains(&s.id) && category.is_none())
+                    .collect(),
+                instructions: library
+                    .instructions
+                    .iter()
+                    .filter(|i| i.enabled && agent.instructions.contains(&i.id) && in_category(i))
+                    .collect(),
+                avatars: HashMap::new(),
+            }
+        }
+        None => Guidance {
+            title: match category {
+                Some(category) => format!("{} guidelines", heading(category)),
+                None => "Team guidelines".to_string(),
+            },
+            overview: None,
+            agents: match category {
+                Some(_) => Vec::new(),
+                None => library.agents.iter().collect(),
+            },
+            skills: match category {
+                Some(_) => Vec::new(),
+                None => library.skills.iter().filter(|s| s.enabled).collect(),
+            },
+            instructions: library
+                .instructions
+                .iter()
+                .filter(|i| i.enabled && in_category(i))
+                .collect(),
+            avatars: HashMap::new(),
+        },
+    };
+
+    if guidance.is_empty() {
+        return Err(
+            "Nothing to summarize: no enabled skills or instructions in this scope".to_string(),
+        );
+    }
+    Ok(guidance)
+}
+
+/// Deterministic markdown summary of `guidance`
+pub fn extractive(guidance: &Guidance) -> String {
+    let mut doc = format!("# {}\n\n", guidance.title);
+    if let Some(overview) = &guidance.overview {
+        doc.push_str(&format!("{}\n\n", overview.trim()));
+    }
+    doc.push_str(&format!("{}\n", counts(guidance)));
+
+    let pinned: Vec<&Instruction> = guidance
+        .instructions
+        .iter()
+        .copied()
+        .filter(|i| i.pin_to_context)
+        .collect();
+    if !pinned.is_empty() {
+        doc.push_str("\n## Non-negotiables\n\n");
+        for instruction in sorted(pinned) {
+            doc.push_str(&format!("- **{}**", instruction.name));
+            match summary(instruction) {
+                Some(summary) => doc.push_str(&format!(": {}\n", summary)),
+                None => doc.push('\n'),
+            }
+        }
+    }
+
+    let mut by_category: BTreeMap<String, Vec<&Instruction>> = BTreeMap::new();
+    for instruction in guidance.instructions.iter().filter(|i| !i.pin_to_context) {
+        by_category
+            .entry(categories::category_path(instruction))
+            .or_default()
+            .push(instruction);
+    }
+    if !by_category.is_empty() {
+        doc.push_str("\n## Guidelines\n");
+    }
+    for (path, instructions) in by_category {
+        doc.push_str(&format!("\n### {}\n", heading(&path)));
+        for instruction in sorted(instructions) {
+            doc.push_str(&format!("\n**{}**", instruction.name));
+            if let Some(summary) = summary(instruction) {
+                doc.push_str(&format!(" - {}", summary));
+            }
+            doc.push('\n');
+            let points = key_points(&instruction.content);
+            if !points.is_empty() {
+                doc.push('\n');
+                for point in points {
+                    doc.push_str(&format!("- {}\n", point));
+                }
+            }
+        }
+    }
+
+    if !guidance.agents.is_empty() && guidance.overview.is_none() {
+        doc.push_str("\n## Agents\n\n");
+        for agent in &guidance.agents {
+            let avatar = match guidance.avatars.get(&agent.id) {
+                Some(data_url) => format!("![]({})", data_url),
+                None => agent.avatar_emoji.clone(),
+            };
+            doc.push_str(&format!(
+                "- {} **{}**: {} ({} skills, {} instructions)\n",
+                avatar,
+                agent.name,
+                agent.description.trim(),
+                agent.skills.len(),
+                agent.instructions.len()
+            ));
+        }
+    }
+
+    if !guidance.skills.is_empty() {
+        doc.push_str("\n## Skills\n\n");
+        for skill in &guidance.skills {
+            doc.push_str(&format!(
+                "- {} **{}**: {}\n",
+                skill.icon_emoji,
+                skill.name,
+                skill.description.trim()
+            ));
+        }
+    }
+
+    doc
+}
+
+/// Everything an LLM needs to write the summary itself: the full text of
+/// each instruction plus skill and agent descriptions
+pub fn source_text(guidance: &Guidance) -> String {
+    let mut text = String::new();
+    if let Some(overview) = &guidance.overview {
+        text.push_str(&format!("Overview: {}\n\n", overview.trim()));
+    }
+    for instruction in &guidance.instructions {
+        text.push_str(&format!(
+            "## Instruction: {} (category {}, priority {}{})\n\n{}\n\n",
+            instruction.name,
+            categories::category_path(instruction),
+            instruction.priority,
+            if instruction.pin_to_context {
+                ", non-negotiable"
+            } else {
+                ""
+            },
+            instruction.content.trim()
+        ));
+    }
+    for skill in &guidance.skills {
+        text.push_str(&format!(
+            "## Skill: {}\n\n{}\n\n",
+            skill.name,
+            skill.description.trim()
+        ));
+    }
+    for agent in &guidance.agents {
+        text.push_str(&format!(
+            "## Agent: {}\n\n{}\n\n",
+            agent.name,
+            agent.description.trim()
+        ));
+    }
+    text
+}
+
+/// System prompt asking a model to condense `source_text` into an onboarding document
+pub fn summary_prompt(title: &str) -> String {
+    format!(
+        "You write onboarding documents for people joining a software team. \
+         The user message is the team's guidance for AI assistants and humans alike. \
+         Condense it into a concise markdown document titled \"{}\": a short overview, \
+         then the rules grouped by topic as bullet points, with non-negotiable rules first \
+         and the available skills and agents at the end. Keep concrete rules, names, \
+         commands and thresholds; drop repetition. Reply with only the markdown.",
+        title
+    )
+}
+
+/// A model's markdown reply without a code fence wrapped around all of it
+pub fn strip_fence(reply: &str) -> String {
+    let reply = reply.trim();
+    let Some(rest) = reply.strip_prefix("```") else {
+        return reply.to_string();
+    };
+    match (rest.find('\n'), rest.strip_suffix("```")) {
+        (Some(start), Some(body)) if start < body.len() => body[start + 1..].trim().to_string(),
+        _ => reply.to_string(),
+    }
+}
+
+/// A standalone HTML page rendering `markdown`. Raw HTML in the markdown
+/// is shown as text, and scripted or inline link targets are dropped.
+pub fn to_html(title: &str, markdown: &str) -> String {
+    let events = Parser::new_ext(
+        markdown,
+        Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH,
+    )
+    .map(|event| match event {
+        Event::Html(raw) | Event::InlineHtml(raw) => Event::Text(raw),
+        Event::Start(Tag::Link {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) if !safe_url(&dest_url, false) => Event::Start(Tag::Link {
+            link_type,
+            dest_url: CowStr::Borrowed("#"),
+            title,
+            id,
+        }),
+        Event::Start(Tag::Image {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) if !safe_url(&dest_url, true) => Event::Start(Tag::Image {
+            link_type,
+            dest_url: CowStr::Borrowed(""),
+            title,
+            id,
+        }),
+        event => event,
+    });
+    let mut body = String::new();
+    html::push_html(&mut body, events);
+
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>{}</title>\n<style>\n\
+         body {{ font-family: system-ui, sans-serif; line-height: 1.6; max-width: 48rem; margin: 2rem auto; padding: 0 1rem; }}\n\
+         code, pre {{ background: #f4f4f5; border-radius: 4px; }}\n\
+         pre {{ padding: 0.75rem; overflow-x: auto; }}\n\
+         </style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape_html(title),
+        body
+    )
+}
+
+/// Whether a link (or, with `image`, an image source) may be kept as is.
+/// Images may be inline `data:image/` URLs, such as embedded avatars.
+fn safe_url(url: &str, image: bool) -> bool {
+    // Browsers ignore whitespace and control characters inside a scheme
+    let url: String = url
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .collect::<String>()
+        .to_lowercase();
+    let Some((scheme, _)) = url.split_once(':') else {
+        return true;
+    };
+    if scheme.contains(['/', '?', '#']) {
+        return true;
+    }
+    match scheme {
+        "javascript" | "vbscript" => false,
+        "data" => image && url.starts_with("data:image/"),
+        _ => true,
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn counts(guidance: &Guidance) -> String {
+    let plural = |n: usize, word: &str| format!("{} {}{}", n, word, if n == 1 { "" } else { "s" });
+    let mut parts = vec![plural(guidance.instructions.len(), "instruction")];
+    if !guidance.skills.is_empty() {
+        parts.push(plural(guidance.skills.len(), "skill"));
+    }
+    if !guidance.agents.is_empty() && guidance.overview.is_none() {
+        parts.push(plural(guidance.agents.len(), "agent"));
+    }
+    let last = parts.pop().unwrap_or_default();
+    let list = if parts.is_empty() {
+        last
+    } else {
+        format!("{} and {}", parts.join(", "), last)
+    };
+    format!("This document summarizes {} from Prompt Forge.", list)
+}
+
+/// Highest priority first, then by name
+fn sorted(mut instructions: Vec<&Instruction>) -> Vec<&Instruction> {
+    instructions.sort_by(|a, b| {
+        b.priority
+            .cmp(&a.priority)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    instructions
+}
+
+/// "code_style/rust" as "Code style / Rust"
+fn heading(path: &str) -> String {
+    path.trim_matches('/')
+        .split('/')
+        .map(|segment| {
+            let segment = segment.replace(['_', '-'], " ");
+            let mut chars = segment.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" / ")
+}
+
+/// The instruction's description, or the opening sentences of its content
+/// when it has none
+fn summary(instruction: &Instruction) -> Option<String> {
+    let description = instruction.description.trim();
+    if !description.is_empty() {
+        return Some(description.to_string());
+    }
+    let paragraph = prose(&instruction.content)
+        .take_while(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let summary = opening_sentences(&paragraph);
+    (!summary.is_empty()).then_some(summary)
+}
+
+/// Top-level list items of `content`, outside code blocks
+fn key_points(content: &str) -> Vec<String> {
+    prose(content)
+        .filter_map(list_item)
+        .take(MAX_POINTS)
+        .collect()
+}
+
+/// Lines of `content` outside code blocks, skipping headings and leading blank lines
+fn prose(content: &str) -> impl Iterator<Item = &str> {
+    let mut in_code = false;
+    content
+        .lines()
+        .filter(move |line| {
+            let fence =
+                line.trim_start().starts_with("```") || line.trim_start().starts_with("~~~");
+            if fence {
+                in_code = !in_code;
+            }
+            !fence && !in_code
+        })
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .map(str::trim_end)
+        .skip_while(|line| line.trim().is_empty())
+}
+
+/// The text of an unindented `-`, `*`, `+` or `1.` list item
+fn list_item(line: &str) -> Option<String> {
+    let text = ["- ", "* ", "+ "]
+        .iter()
+        .find_map(|marker| line.strip_prefix(marker))
+        .or_else(|| {
+            let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+            (digits > 0)
+                .then(|| line[digits..].strip_prefix(". "))
+                .flatten()
+        })?;
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// Up to two sentences of `paragraph`, cut at a word boundary if too long
+fn opening_sentences(paragraph: &str) -> String {
+    let mut end = paragraph.len();
+    let mut sentences = 0;
+    for (i, c) in paragraph.char_indices() {
+        let next = paragraph[i + c.len_utf8()..].chars().next();
+        let at_break = match next {
+            Some(next) => next.is_whitespace(),
+            None => true,
+        };
+        if matches!(c, '.' | '!' | '?') && at_break {
+            sentences += 1;
+            if sentences == 2 {
+                end = i + 1;
+                break;
+            }
+        }
+    }
+    let text = paragraph[..end].trim();
+    if text.chars().count() <= MAX_SUMMARY_CHARS {
+        return text.to_string();
+    }
+
+    let cut: String = text.chars().take(MAX_SUMMARY_CHARS).collect();
+    match cut.rfind(' ') {
+        Some(space) => format!("{}…", &cut[..space]),
+        None => format!("{}…", cut),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::InstructionCategory;
+
+    fn instruction(name: &str, content: &str) -> Instruction {
+        Instruction {
+            id: name.to_lowercase(),
+            name: name.to_string(),
+            description: String::new(),
+            content: content.to_string(),
+            ..Instruction::default()
+        }
+    }
+
+    #[test]
+    fn test_extractive_summary() {
+        let mut secrets = instruction("No secrets", "Never commit credentials. Use the vault.");
+        secrets.pin_to_context = true;
+        let mut naming = instruction(
+            "Rust naming",
+            "## Naming\n\nFollow the API guidelines. They are linked from the wiki. Ask if unsure.\n\n\
+             - Types are `CamelCase`\n- Functions are `snake_case`\n\n\
+             ```rust\n- not a point\n```\n1. Avoid abbreviations",
+        );
+        naming.category = InstructionCategory::CodeStyle;
+        naming.subcategory = Some("rust".to_string());
+        let mut disabled = instruction("Old rule", "Use tabs.");
+        disabled.enabled = false;
+        let instructions = vec![secrets, naming, disabled];
+        let library = LibraryGraph {
+            agents: &[],
+            skills: &[],
+            instructions: &instructions,
+        };
+
+        let guidance = select(&library, &SummaryScope::default()).unwrap();
+        assert_eq!(
+            extractive(&guidance),
+            "# Team guidelines\n\n\
+             This document summarizes 2 instructions from Prompt Forge.\n\n\
+             ## Non-negotiables\n\n\
+             - **No secrets**: Never commit credentials. Use the vault.\n\n\
+             ## Guidelines\n\n\
+             ### Code style / Rust\n\n\
+             **Rust naming** - Follow the API guidelines. They are linked from the wiki.\n\n\
+             - Types are `CamelCase`\n\
+             - Functions are `snake_case`\n\
+             - Avoid abbreviations\n"
+        );
+
+        let scope = SummaryScope {
+            agent_id: None,
+            category: Some("code_style".to_string()),
+        };
+        let guidance = select(&library, &scope).unwrap();
+        assert_eq!(guidance.title, "Code style guidelines");
+        assert_eq!(guidance.instructions.len(), 1);
+
+        let scope = SummaryScope {
+            agent_id: None,
+            category: Some("testing".to_string()),
+        };
+        assert!(select(&library, &scope).is_err());
+    }
+
+    #[test]
+    fn test_agent_scope() {
+        let instructions = vec![
+            instruction("Tests first", "Write the failing test first."),
+            instruction("Changelog", "Update CHANGELOG.md."),
+        ];
+        let agents = vec![Agent {
+            name: "Reviewer".to_string(),
+            description: "Reviews pull requests.".to_string(),
+            instructions: vec!["tests first".to_string()],
+            ..Agent::default()
+        }];
+        let library = LibraryGraph {
+            agents: &agents,
+            skills: &[],
+            instructions: &instructions,
+        };
+
+        let scope = SummaryScope {
+            agent_id: Some("reviewer".to_string()),
+            category: None,
+        };
+        let guidance = select(&library, &scope).unwrap();
+        assert_eq!(guidance.title, "Working with Reviewer");
+        let names: Vec<&str> = guidance
+            .instructions
+            .iter()
+            .map(|i| i.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["Tests first"]);
+        assert!(extractive(&guidance).starts_with(
+            "# Working with Reviewer\n\nReviews pull requests.\n\n\
+             This document summarizes 1 instruction from Prompt Forge.\n"
+        ));
+
+        let mut guidance = select(&library, &SummaryScope::default()).unwrap();
+        assert!(extractive(&guidance).contains("- 🤖 **Reviewer**"));
+        guidance.avatars.insert(
+            agents[0].id.clone(),
+            "data:image/png;base64,iVBORw0KGgo=".to_string(),
+        );
+        let doc = extractive(&guidance);
+        assert!(doc.contains("- ![](data:image/png;base64,iVBORw0KGgo=) **Reviewer**"));
+        assert!(to_html(&guidance.title, &doc)
+            .contains("<img src=\"data:image/png;base64,iVBORw0KGgo=\" alt=\"\" />"));
+    }
+
+    #[test]
+    fn test_html_and_fences() {
+        assert_eq!(strip_fence("```markdown\n# Hi\n```"), "# Hi");
+        assert_eq!(strip_fence("# Hi"), "# Hi");
+
+        let page = to_html("Rules & <tips>", "# Rules\n\n- <b>bold</b> claim");
+        assert!(page.contains("<title>Rules &amp; &lt;tips&gt;</title>"));
+        assert!(page.contains("<h1>Rules</h1>"));
+        assert!(page.contains("<li>&lt;b&gt;bold&lt;/b&gt; claim</li>"));
+
+        let page = to_html(
+            "Links",
+            "[docs](https://example.com) [x](javascript:alert(1)) [y](Java&#9;Script:alert(1)) \
+             [z](data:text/html,hi) ![i](data:text/html,hi)",
+        );
+        assert!(page.contains("<a href=\"https://example.com\">docs</a>"));
+        assert!(!page.to_lowercase().contains("javascript"));
+        assert!(!page.contains("data:"));
+        assert_eq!(page.matches("<a href=\"#\">").count(), 3);
+    }
+}
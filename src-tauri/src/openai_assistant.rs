@@ -0,0 +1,257 @@
+//! OpenAI Assistants
+//! Agents move to the OpenAI platform as Assistants API objects: the
+//! assembled prompt becomes `instructions`, attached tool skills become
+//! `function` tools, creativity maps onto `temperature` (0.5 is OpenAI's
+//! default of 1) and the rest of the persona rides along in `metadata`.
+//! Importing reads an assistant (or a list of them, as returned by
+//! `GET /v1/assistants`) back into an agent plus a tool skill per function;
+//! built-in tools such as `file_search` have no counterpart and are skipped.
+
+use crate::assembly::{self, AssemblyOptions};
+use crate::models::{Agent, Instruction, LibraryItem, Skill, SkillDefinition, SkillType};
+use crate::resolve::resolve_entity;
+use crate::tool_schema;
+use serde_json::{json, Map, Value};
+
+/// Model written when none is chosen
+pub const DEFAULT_MODEL: &str = "gpt-4o";
+
+/// Longest `name`, `description` and metadata value the API accepts
+const MAX_NAME_CHARS: usize = 256;
+const MAX_DESCRIPTION_CHARS: usize = 512;
+const MAX_METADATA_CHARS: usize = 512;
+
+/// The Assistants API object for `agent`
+pub fn export_assistant(
+    agent: &Agent,
+    skills: &[Skill],
+    instructions: &[Instruction],
+    model: Option<&str>,
+) -> Value {
+    let prompt = assembly::agent_prompt(agent, skills, instructions, &AssemblyOptions::default());
+    let tools: Vec<Value> = agent
+        .skills
+        .iter()
+        .filter_map(|reference| resolve_entity(skills, reference).ok())
+        .filter(|skill| skill.enabled)
+        .filter_map(|skill| match &skill.definition {
+            SkillDefinition::Tool { parameters, .. } => Some(json!({
+                "type": "function",
+                "function": {
+                    "name": tool_schema::mcp_tool_name(&skill.name),
+                    "description": skill.description,
+                    "parameters": tool_schema::parameters_to_input_schema(parameters),
+                }
+            })),
+            _ => None,
+        })
+        .collect();
+
+    let mut metadata = Map::new();
+    for (key, value) in [
+        ("prompt_forge_id", agent.id.clone()),
+        ("avatar_emoji", agent.avatar_emoji.clone()),
+        ("tone", agent.personality.tone.clone()),
+        ("verbosity", agent.personality.verbosity.clone()),
+        ("formality", agent.personality.formality.to_string()),
+        ("traits", agent.personality.traits.join(", ")),
+        ("tags", agent.tags.join(", ")),
+    ] {
+        if !value.is_empty() {
+            metadata.insert(key.to_string(), json!(truncate(&value, MAX_METADATA_CHARS)));
+        }
+    }
+
+    json!({
+        "name": truncate(&agent.name, MAX_NAME_CHARS),
+        "description": truncate(&agent.description, MAX_DESCRIPTION_CHARS),
+        "model": model.filter(|m| !m.trim().is_empty()).unwrap_or(DEFAULT_MODEL),
+        "instructions": prompt.trim(),
+        "tools": tools,
+        "temperature": round(f64::from(agent.personality.creativity) * 2.0),
+        "metadata": metadata,
+    })
+}
+
+/// The agents and tool skills in an assistant, a list of assistants, or a
+/// `{"data": [...]}` list response. Each agent comes after its tool skills
+/// and lists their names in `skills`, to be swapped for their ids once saved.
+pub fn parse_assistants(text: &str) -> Result<Vec<LibraryItem>, String> {
+    let value: Value =
+        serde_json::from_str(text).map_err(|e| format!("Failed to parse assistant JSON: {}", e))?;
+    let assistants = match &value {
+        Value::Array(assistants) => assistants.clone(),
+        Value::Object(object) => match object.get("data") {
+            Some(Value::Array(assistants)) => assistants.clone(),
+            _ => vec![value.clone()],
+        },
+        _ => return Err("Expected an assistant object or a list of them".to_string()),
+    };
+
+    let mut items = Vec::new();
+    for assistant in &assistants {
+        let text = |key: &str| {
+            assistant
+                .get(key)
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string()
+        };
+        let metadata = |key: &str| {
+            assistant["metadata"]
+                .get(key)
+                .and_then(Value::as_str)
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+        };
+        let list = |value: &str| -> Vec<String> {
+            value
+                .split(',')
+                .map(|item| item.trim().to_string())
+                .filter(|item| !item.is_empty())
+                .collect()
+        };
+        let empty = text("instructions").trim().is_empty() && text("name").is_empty();
+        if !assistant.is_object() || empty {
+            return Err("Assistant has neither a name nor instructions".to_string());
+        }
+
+        let mut agent = Agent {
+            name: text("name"),
+            description: text("description"),
+            system_prompt: text("instructions").trim().to_string(),
+            tags: metadata("tags")
+                .map(list)
+                .unwrap_or_else(|| vec!["openai".to_string()]),
+            ..Agent::default()
+        };
+        if let Some(emoji) = metadata("avatar_emoji") {
+            agent.avatar_emoji = emoji.to_string();
+        }
+        let personality = &mut agent.personality;
+        if let Some(tone) = metadata("tone") {
+            personality.tone = tone.to_string();
+        }
+        if let Some(verbosity) = metadata("verbosity") {
+            personality.verbosity = verbosity.to_string();
+        }
+        if let Some(formality) = metadata("formality").and_then(|f| f.parse::<f32>().ok()) {
+            personality.formality = formality.clamp(0.0, 1.0);
+        }
+        if let Some(traits) = metadata("traits") {
+            personality.traits = list(traits);
+        }
+        if let Some(temperature) = assistant.get("temperature").and_then(Value::as_f64) {
+            personality.creativity = (round(temperature / 2.0) as f32).clamp(0.0, 1.0);
+        }
+
+        let functions = assistant["tools"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|tool| tool["type"] == "function");
+        for function in functions {
+            let function = &function["function"];
+            let name = function["name"].as_str().unwrap_or_default().to_string();
+            if name.is_empty() {
+                continue;
+            }
+            let parameters = tool_schema::parameters_from_input_schema(&function["parameters"])
+                .map_err(|e| format!("Function '{}': {}", name, e))?;
+            agent.skills.push(name.clone());
+            items.push(LibraryItem::Skill(Skill {
+                name,
+                description: function["description"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+                skill_type: SkillType::Tool,
+                definition: SkillDefinition::Tool {
+                    parameters,
+                    handler: String::new(),
+                    sandbox: None,
+                },
+                ..Skill::default()
+            }));
+        }
+        items.push(LibraryItem::Agent(agent));
+    }
+    Ok(items)
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    text.chars().take(max_chars).collect()
+}
+
+/// Two decimals, so 0.7 creativity becomes 1.4 rather than 1.399999976
+fn round(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Personality, ToolParameter};
+
+    #[test]
+    fn test_assistant_round_trip() {
+        let search = Skill {
+            id: "search-docs".to_string(),
+            name: "Search Docs".to_string(),
+            description: "Search the documentation".to_string(),
+            skill_type: SkillType::Tool,
+            definition: SkillDefinition::Tool {
+                parameters: vec![ToolParameter {
+                    name: "query".to_string(),
+                    description: String::new(),
+                    param_type: "string".to_string(),
+                    required: true,
+                    default: None,
+                    schema: None,
+                }],
+                handler: "./search.sh".to_string(),
+                sandbox: None,
+            },
+            ..Skill::default()
+        };
+        let agent = Agent {
+            id: "reviewer".to_string(),
+            name: "Code Reviewer".to_string(),
+            description: "Reviews pull requests".to_string(),
+            system_prompt: "You review code.".to_string(),
+            skills: vec!["search-docs".to_string()],
+            personality: Personality {
+                tone: "direct".to_string(),
+                creativity: 0.7,
+                ..Personality::default()
+            },
+            ..Agent::default()
+        };
+
+        let assistant = export_assistant(&agent, &[search], &[], None);
+        assert_eq!(assistant["model"], DEFAULT_MODEL);
+        assert_eq!(assistant["temperature"], 1.4);
+        assert_eq!(assistant["metadata"]["prompt_forge_id"], "reviewer");
+        assert_eq!(assistant["tools"][0]["function"]["name"], "search_docs");
+        assert!(assistant["instructions"]
+            .as_str()
+            .unwrap()
+            .starts_with("You review code."));
+
+        let listed = json!({ "object": "list", "data": [assistant] });
+        let items = parse_assistants(&listed.to_string()).unwrap();
+        let [LibraryItem::Skill(skill), LibraryItem::Agent(imported)] = &items[..] else {
+            panic!("expected a skill then an agent, got {:?}", items);
+        };
+        assert_eq!(skill.name, "search_docs");
+        assert!(
+            matches!(&skill.definition, SkillDefinition::Tool { parameters, .. } if parameters[0].required)
+        );
+        assert_eq!(imported.name, "Code Reviewer");
+        assert_eq!(imported.skills, ["search_docs"]);
+        assert_eq!(imported.personality.tone, "direct");
+        assert_eq!(imported.personality.creativity, 0.7);
+
+        assert!(parse_assistants("[1]").is_err());
+    }
+}
@@ -1,4 +1,10 @@
-use crate::models::{Agent, Instruction, InstructionCategory, Personality, Skill, SkillDefinition, SkillType};
+use crate::activation;
+use crate::categories;
+use crate::code_blocks::{self, FenceTracker};
+use crate::models::{
+    Agent, Instruction, InstructionActivation, Personality, Skill, SkillDefinition, SkillType,
+};
+use crate::scope;
 use chrono::Utc;
 use regex::Regex;
 
@@ -21,9 +27,13 @@ pub fn parse_agent_from_markdown(text: &str) -> Result<Agent, String> {
     let lines: Vec<&str> = text.lines().collect();
     let mut current_section = String::new();
     let mut section_content = String::new();
+    let mut fences = FenceTracker::default();
 
     for line in lines {
-        if line.starts_with("# ") {
+        if fences.update(line) {
+            section_content.push_str(line);
+            section_content.push('\n');
+        } else if line.starts_with("# ") {
             // Main title - agent name
             agent.name = line[2..].trim().to_string();
         } else if line.starts_with("## ") {
@@ -302,16 +312,17 @@ pub fn parse_instruction_from_markdown(text: &str) -> Result<Instruction, String
     // Parse as markdown sections
     let lines: Vec<&str> = text.lines().collect();
     let mut content_lines = Vec::new();
+    let mut fences = FenceTracker::default();
 
     for line in lines {
-        if line.starts_with("# ") {
+        if !fences.update(line) && line.starts_with("# ") {
             instruction.name = line[2..].trim().to_string();
         } else {
             content_lines.push(line);
         }
     }
 
-    instruction.content = content_lines.join("\n").trim().to_string();
+    instruction.content = code_blocks::fence_code(content_lines.join("\n").trim());
     instruction.updated_at = Utc::now();
     Ok(instruction)
 }
@@ -340,16 +351,36 @@ fn parse_instruction_from_yaml_value(yaml: &serde_yaml::Value, full_text: &str)
                     }
                     "category" => {
                         if let serde_yaml::Value::String(v) = value {
-                            instruction.category = match v.to_lowercase().as_str() {
-                                "code_style" | "codestyle" => InstructionCategory::CodeStyle,
-                                "communication" => InstructionCategory::Communication,
-                                "workflow" => InstructionCategory::Workflow,
-                                "security" => InstructionCategory::Security,
-                                "testing" => InstructionCategory::Testing,
-                                "documentation" => InstructionCategory::Documentation,
-                                "custom" => InstructionCategory::Custom,
-                                _ => InstructionCategory::General,
-                            };
+                            // "code_style/rust" sets the subcategory too
+                            let (category, subcategory) = categories::parse_category_path(v);
+                            instruction.category = category;
+                            if subcategory.is_some() {
+                                instruction.subcategory = subcategory;
+                            }
+                        }
+                    }
+                    "subcategory" => {
+                        if let serde_yaml::Value::String(v) = value {
+                            instruction.subcategory = categories::normalize_subcategory(v);
+                        }
+                    }
+                    "branch_pattern" | "branches" => {
+                        if let serde_yaml::Value::String(v) = value {
+                            instruction.branch_pattern = scope::normalize_branch_pattern(v);
+                        }
+                    }
+                    "file_pattern" | "globs" => {
+                        if let serde_yaml::Value::String(v) = value {
+                            instruction.file_pattern = scope::normalize_file_pattern(v);
+                        }
+                    }
+                    "contexts" => {
+                        if let serde_yaml::Value::Sequence(contexts) = value {
+                            let contexts: Vec<String> = contexts
+                                .iter()
+                                .filter_map(|c| c.as_str().map(str::to_string))
+                                .collect();
+                            instruction.contexts = scope::normalize_contexts(&contexts);
                         }
                     }
                     "priority" => {
@@ -376,6 +407,16 @@ fn parse_instruction_from_yaml_value(yaml: &serde_yaml::Value, full_text: &str)
                             instruction.enabled = *v;
                         }
                     }
+                    "pin_to_context" | "pinned" => {
+                        if let serde_yaml::Value::Bool(v) = value {
+                            instruction.pin_to_context = *v;
+                        }
+                    }
+                    "activation" => {
+                        let rules: InstructionActivation = serde_yaml::from_value(value.clone())
+                            .map_err(|e| format!("Invalid activation: {}", e))?;
+                        instruction.activation = activation::normalize(&rules)?;
+                    }
                     _ => {}
                 }
             }
@@ -386,7 +427,7 @@ fn parse_instruction_from_yaml_value(yaml: &serde_yaml::Value, full_text: &str)
     if let Some(yaml_end) = full_text[3..].find("---") {
         let content_start = yaml_end + 6;
         if content_start < full_text.len() {
-            instruction.content = full_text[content_start..].trim().to_string();
+            instruction.content = code_blocks::fence_code(full_text[content_start..].trim());
         }
     }
 
@@ -406,6 +447,44 @@ pub fn export_instruction_to_markdown_text(instruction: &Instruction) -> String
     output.push_str(&format!("category: \"{:?}\"\n", instruction.category));
     output.push_str(&format!("priority: {}\n", instruction.priority));
     output.push_str(&format!("enabled: {}\n", instruction.enabled));
+    if let Some(subcategory) = &instruction.subcategory {
+        output.push_str(&format!("subcategory: \"{}\"\n", subcategory));
+    }
+    if let Some(branch_pattern) = &instruction.branch_pattern {
+        output.push_str(&format!("branch_pattern: \"{}\"\n", branch_pattern));
+    }
+    if let Some(file_pattern) = &instruction.file_pattern {
+        output.push_str(&format!("file_pattern: \"{}\"\n", file_pattern));
+    }
+    if !instruction.contexts.is_empty() {
+        output.push_str("contexts:\n");
+        for context in &instruction.contexts {
+            output.push_str(&format!("  - \"{}\"\n", context));
+        }
+    }
+    if instruction.pin_to_context {
+        output.push_str("pin_to_context: true\n");
+    }
+    if let Some(activation) = &instruction.activation {
+        output.push_str("activation:\n");
+        for (key, value) in [
+            ("starts_on", &activation.starts_on),
+            ("ends_on", &activation.ends_on),
+            ("hours", &activation.hours),
+        ] {
+            if let Some(value) = value {
+                output.push_str(&format!("  {}: \"{}\"\n", key, value));
+            }
+        }
+        for (key, values) in [
+            ("weekdays", &activation.weekdays),
+            ("profiles", &activation.profiles),
+        ] {
+            if !values.is_empty() {
+                output.push_str(&format!("  {}: [{}]\n", key, values.join(", ")));
+            }
+        }
+    }
     if !instruction.tags.is_empty() {
         output.push_str("tags:\n");
         for tag in &instruction.tags {
@@ -420,6 +499,66 @@ pub fn export_instruction_to_markdown_text(instruction: &Instruction) -> String
     output
 }
 
+// ============================================================================
+// Project File Parsing
+// ============================================================================
+
+/// Split a project guidance file (CLAUDE.md, AGENTS.md) into one instruction
+/// per section. A lone `# ` title is skipped and the file splits on `## `;
+/// otherwise it splits on `# `. Text before the first section becomes an
+/// instruction named after the title. Categories are guessed from headings.
+pub fn parse_project_instructions(text: &str) -> Vec<Instruction> {
+    let mut fences = FenceTracker::default();
+    let lines: Vec<(&str, bool)> = text
+        .lines()
+        .map(|line| (line, fences.update(line)))
+        .collect();
+
+    let titles = lines
+        .iter()
+        .filter(|(line, fenced)| !fenced && heading_level(line) == Some(1))
+        .count();
+    let split_level = if titles <= 1 { 2 } else { 1 };
+
+    let mut title = "Overview".to_string();
+    let mut sections: Vec<(Option<String>, Vec<&str>)> = vec![(None, Vec::new())];
+    for (line, fenced) in lines {
+        match heading_level(line).filter(|_| !fenced) {
+            Some(1) if split_level == 2 => title = line[1..].trim().to_string(),
+            Some(level) if level == split_level => {
+                sections.push((Some(line[level..].trim().to_string()), Vec::new()))
+            }
+            _ => sections.last_mut().unwrap().1.push(line),
+        }
+    }
+
+    sections
+        .into_iter()
+        .filter_map(|(heading, body)| {
+            let content = body.join("\n");
+            let content = content.trim();
+            if content.is_empty() {
+                return None;
+            }
+            let name = heading.unwrap_or_else(|| title.clone());
+            Some(Instruction {
+                category: categories::infer_category(&name),
+                name,
+                content: code_blocks::fence_code(content),
+                ..Instruction::default()
+            })
+        })
+        .collect()
+}
+
+/// Level of an ATX heading (`## Title` is 2), if the line is one
+fn heading_level(line: &str) -> Option<usize> {
+    let (hashes, title) = line.split_once(' ')?;
+    let is_heading =
+        !hashes.is_empty() && hashes.chars().all(|c| c == '#') && !title.trim().is_empty();
+    is_heading.then_some(hashes.len())
+}
+
 /// Parse skill from YAML or JSON text
 pub fn parse_skill_from_text(text: &str) -> Result<Skill, String> {
     // Try YAML first
@@ -439,8 +578,12 @@ pub fn parse_skill_from_text(text: &str) -> Result<Skill, String> {
 fn parse_skill_from_markdown(text: &str) -> Result<Skill, String> {
     let mut skill = Skill::default();
     let lines: Vec<&str> = text.lines().collect();
+    let mut fences = FenceTracker::default();
 
     for line in lines {
+        if fences.update(line) {
+            continue;
+        }
         let line = line.trim();
 
         if line.starts_with("# ") {
@@ -461,6 +604,8 @@ fn parse_skill_from_markdown(text: &str) -> Result<Skill, String> {
     if matches!(skill.skill_type, SkillType::Prompt) {
         skill.definition = SkillDefinition::Prompt {
             template: text.to_string(),
+            variables: Vec::new(),
+            follows: Vec::new(),
         };
     }
 
@@ -471,6 +616,7 @@ fn parse_skill_from_markdown(text: &str) -> Result<Skill, String> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::db::category_to_string;
 
     #[test]
     fn test_parse_simple_markdown_agent() {
@@ -517,4 +663,64 @@ You are a creative writer with a gift for vivid storytelling.
         assert_eq!(agent.avatar_emoji, "✍️");
         assert_eq!(agent.temperature, 0.9);
     }
+
+    #[test]
+    fn test_project_file_splits_by_section() {
+        let md = r#"# Acme API
+
+Rust service behind the public API.
+
+## Build commands
+
+```bash
+# not a heading
+cargo build
+```
+
+## Testing
+
+Run `cargo test` before pushing.
+
+### Fixtures
+
+Keep them small.
+
+## Empty
+"#;
+
+        let instructions = parse_project_instructions(md);
+        let names: Vec<&str> = instructions.iter().map(|i| i.name.as_str()).collect();
+        assert_eq!(names, vec!["Acme API", "Build commands", "Testing"]);
+        assert!(instructions[1].content.contains("# not a heading"));
+        assert!(instructions[2].content.contains("### Fixtures"));
+        assert_eq!(category_to_string(&instructions[1].category), "workflow");
+        assert_eq!(category_to_string(&instructions[2].category), "testing");
+
+        let top_level = parse_project_instructions("# Style\nTabs.\n\n# Security\nNo secrets.");
+        assert_eq!(top_level.len(), 2);
+        assert_eq!(top_level[1].content, "No secrets.");
+    }
+
+    #[test]
+    fn test_instruction_import_keeps_fences_and_fences_bare_code() {
+        let md = r#"# Setup
+
+```bash title="install.sh"
+# install the toolchain
+rustup update
+```
+
+Format before committing:
+cargo fmt --all
+"#;
+
+        let instruction = parse_instruction_from_markdown(md).unwrap();
+        assert_eq!(instruction.name, "Setup");
+        assert_eq!(
+            instruction.content,
+            "```bash title=\"install.sh\"\n# install the toolchain\nrustup update\n```\n\n\
+             Format before committing:\n```bash\ncargo fmt --all\n```"
+        );
+        assert!(export_instruction_to_markdown_text(&instruction).ends_with(&instruction.content));
+    }
 }
@@ -0,0 +1,165 @@
+//! Assembled `apply_agent` prompts kept between calls
+//! Automated pipelines apply the same agent over and over. Each assembled
+//! prompt is kept under its agent, release and context (branch, numbering,
+//! locale), and the whole cache is dropped as soon as the library changes:
+//! an item or snippet is added, removed or edited, or a different experiment
+//! variant is served. Workspace values are filled in after the cache, so they are
+//! always current.
+
+use crate::assembly::AssemblyOptions;
+use crate::composition::LibraryGraph;
+use crate::experiments::Variant;
+use crate::models::{Release, Snippet, Translation};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+/// Most prompts kept; the cache starts over when it is full
+const MAX_ENTRIES: usize = 256;
+
+#[derive(Default)]
+pub struct PersonaCache {
+    revision: u64,
+    prompts: HashMap<u64, String>,
+}
+
+impl PersonaCache {
+    /// The prompt cached under `key`, assembling and keeping it if there is
+    /// none or the library has changed since it was assembled
+    pub fn get_or_assemble(
+        &mut self,
+        revision: u64,
+        key: u64,
+        assemble: impl FnOnce() -> String,
+    ) -> String {
+        if revision != self.revision || self.prompts.len() >= MAX_ENTRIES {
+            self.prompts.clear();
+            self.revision = revision;
+        }
+        self.prompts.entry(key).or_insert_with(assemble).clone()
+    }
+}
+
+/// Fingerprint of the library: which items and snippets exist, when each was
+/// last edited, which instructions activation rules switch off and which
+/// experiment variants are served. Usage counts don't change it.
+pub fn library_revision(
+    library: &LibraryGraph,
+    snippets: &[Snippet],
+    variants: &[(String, Variant)],
+) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for agent in library.agents {
+        (&agent.id, agent.updated_at).hash(&mut hasher);
+    }
+    for skill in library.skills {
+        (&skill.id, skill.updated_at).hash(&mut hasher);
+    }
+    for instruction in library.instructions {
+        (&instruction.id, instruction.updated_at, instruction.enabled).hash(&mut hasher);
+    }
+    for snippet in snippets {
+        (&snippet.id, snippet.updated_at).hash(&mut hasher);
+    }
+    for (instruction_id, variant) in variants {
+        (instruction_id, variant.as_str()).hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// Key of one assembled prompt. A release is identified by its creation time
+/// too, as a deleted tag can be created again.
+pub fn persona_key(
+    agent_id: &str,
+    release: Option<&Release>,
+    options: &AssemblyOptions,
+    translations: Option<&(String, Vec<Translation>)>,
+) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    agent_id.hash(&mut hasher);
+    release.map(|r| (&r.tag, r.created_at)).hash(&mut hasher);
+    (options.numbered, &options.branch).hash(&mut hasher);
+    if let Some((locale, translations)) = translations {
+        locale.hash(&mut hasher);
+        for translation in translations {
+            (&translation.id, translation.updated_at).hash(&mut hasher);
+        }
+    }
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Agent, Instruction};
+    use chrono::{Duration, Utc};
+
+    #[test]
+    fn test_prompts_are_reassembled_only_after_library_changes() {
+        let mut instructions = vec![Instruction {
+            id: "style".to_string(),
+            ..Instruction::default()
+        }];
+        let agents = vec![Agent::default()];
+        let mut snippets = vec![Snippet {
+            id: "json-output".to_string(),
+            name: "JSON output".to_string(),
+            description: String::new(),
+            content: "Reply with JSON only.".to_string(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }];
+        let revision = |instructions: &[Instruction], snippets: &[Snippet]| {
+            let library = LibraryGraph {
+                agents: &agents,
+                skills: &[],
+                instructions,
+            };
+            library_revision(&library, snippets, &[])
+        };
+        let options = AssemblyOptions::default();
+        let key = persona_key("reviewer", None, &options, None);
+        let on_branch = persona_key(
+            "reviewer",
+            None,
+            &AssemblyOptions {
+                branch: Some("main".to_string()),
+                ..AssemblyOptions::default()
+            },
+            None,
+        );
+        assert_ne!(key, on_branch);
+
+        let mut cache = PersonaCache::default();
+        let mut assembled = 0;
+        let mut apply = |cache: &mut PersonaCache, revision, key| {
+            cache.get_or_assemble(revision, key, || {
+                assembled += 1;
+                format!("prompt {}", assembled)
+            })
+        };
+
+        let before = revision(&instructions, &snippets);
+        assert_eq!(apply(&mut cache, before, key), "prompt 1");
+        assert_eq!(apply(&mut cache, before, key), "prompt 1");
+        assert_eq!(apply(&mut cache, before, on_branch), "prompt 2");
+
+        instructions[0].usage_count += 1;
+        assert_eq!(revision(&instructions, &snippets), before);
+        instructions[0].updated_at += Duration::seconds(1);
+        let after = revision(&instructions, &snippets);
+        assert_ne!(after, before);
+        assert_eq!(apply(&mut cache, after, key), "prompt 3");
+        assert_eq!(apply(&mut cache, after, key), "prompt 3");
+
+        // Snippets are expanded into served prompts, so editing one counts
+        snippets[0].updated_at += Duration::seconds(1);
+        let edited = revision(&instructions, &snippets);
+        assert_ne!(edited, after);
+        assert_eq!(apply(&mut cache, edited, key), "prompt 4");
+
+        // Activation rules switch instructions off without editing them
+        instructions[0].enabled = false;
+        assert_ne!(revision(&instructions, &snippets), edited);
+    }
+}
@@ -0,0 +1,76 @@
+//! Library checks run before the library is shared
+//! Sync runs these before committing: the summary goes into the commit
+//! message, and when blocking is enabled any error stops the sync so the
+//! shared copy stays healthy.
+
+use crate::composition::{self, LibraryGraph};
+use crate::models::{LibraryIssue, PreflightReport};
+
+/// Lint the library. `block_on_errors` marks the report as blocked when
+/// any error is found.
+pub fn run(graph: &LibraryGraph, block_on_errors: bool) -> PreflightReport {
+    let issues = composition::validate_library(graph);
+    let errors = issues.iter().filter(|i| i.severity == "error").count();
+    let warnings = issues.len() - errors;
+
+    PreflightReport {
+        summary: summary(&issues, errors, warnings),
+        blocked: block_on_errors && errors > 0,
+        errors,
+        warnings,
+        issues,
+    }
+}
+
+/// Plain-text block for a commit message body
+fn summary(issues: &[LibraryIssue], errors: usize, warnings: usize) -> String {
+    let mut text = format!(
+        "Library checks: {} error{}, {} warning{}",
+        errors,
+        if errors == 1 { "" } else { "s" },
+        warnings,
+        if warnings == 1 { "" } else { "s" }
+    );
+    for issue in issues {
+        text.push_str(&format!(
+            "\n- {}: {} '{}': {}",
+            issue.severity, issue.entity_type, issue.entity_name, issue.message
+        ));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Agent;
+
+    #[test]
+    fn test_warnings_are_summarized_without_blocking() {
+        let agents = vec![
+            Agent {
+                name: "Reviewer".to_string(),
+                skills: vec!["ghost".to_string()],
+                ..Agent::default()
+            },
+            Agent {
+                name: "Writer".to_string(),
+                ..Agent::default()
+            },
+        ];
+        let graph = LibraryGraph {
+            agents: &agents,
+            skills: &[],
+            instructions: &[],
+        };
+
+        let report = run(&graph, true);
+        assert_eq!((report.errors, report.warnings), (0, 1));
+        assert!(!report.blocked);
+        assert_eq!(
+            report.summary,
+            "Library checks: 0 errors, 1 warning\n\
+             - warning: agent 'Reviewer': agent 'Reviewer' references missing skill ghost"
+        );
+    }
+}
@@ -0,0 +1,201 @@
+//! Priority rebalancing
+//! Priorities drift upwards as a library grows until they stop telling
+//! instructions apart. `suggest` scores every enabled instruction on how
+//! widely it is used, how often its category is applied, whether it is the
+//! only guidance in its category and how concise it is, then spreads the
+//! ranking evenly over 1–10 again.
+
+use crate::db::category_to_string;
+use crate::models::{Agent, Instruction, PrioritySuggestion};
+use std::collections::HashMap;
+
+const REACH_WEIGHT: f64 = 0.4;
+const USAGE_WEIGHT: f64 = 0.25;
+const RARITY_WEIGHT: f64 = 0.2;
+const BREVITY_WEIGHT: f64 = 0.15;
+
+/// Suggested priorities for the enabled instructions, highest first.
+/// `category_usage` counts applies per top-level category, from insights.
+pub fn suggest(
+    instructions: &[Instruction],
+    agents: &[Agent],
+    category_usage: &HashMap<String, i64>,
+) -> Vec<PrioritySuggestion> {
+    let enabled: Vec<&Instruction> = instructions.iter().filter(|i| i.enabled).collect();
+    if enabled.is_empty() {
+        return Vec::new();
+    }
+
+    let attached = |instruction: &Instruction| {
+        agents
+            .iter()
+            .filter(|a| a.instructions.contains(&instruction.id))
+            .count()
+    };
+    let max_attached = enabled.iter().map(|i| attached(i)).max().unwrap_or(0);
+    let max_usage = category_usage.values().copied().max().unwrap_or(0);
+    let mut per_category: HashMap<&str, usize> = HashMap::new();
+    for instruction in &enabled {
+        *per_category
+            .entry(category_to_string(&instruction.category))
+            .or_default() += 1;
+    }
+    let mut lengths: Vec<usize> = enabled.iter().map(|i| i.content.chars().count()).collect();
+    lengths.sort_unstable();
+    let median = lengths[lengths.len() / 2].max(1);
+
+    let mut scored: Vec<(f64, PrioritySuggestion)> = enabled
+        .iter()
+        .map(|instruction| {
+            let category = category_to_string(&instruction.category);
+            let mut reasons = Vec::new();
+
+            let agents_attached = attached(instruction);
+            let reach = if instruction.pin_to_context {
+                reasons.push("Pinned to every context".to_string());
+                1.0
+            } else if max_attached > 0 {
+                if agents_attached > 0 {
+                    reasons.push(format!("Attached to {} agent(s)", agents_attached));
+                } else {
+                    reasons.push("Not attached to any agent".to_string());
+                }
+                agents_attached as f64 / max_attached as f64
+            } else {
+                0.0
+            };
+
+            let applies = category_usage.get(category).copied().unwrap_or(0);
+            let usage = if max_usage > 0 {
+                if applies > 0 {
+                    reasons.push(format!(
+                        "{} instructions applied {} time(s) recently",
+                        category, applies
+                    ));
+                }
+                applies as f64 / max_usage as f64
+            } else {
+                0.0
+            };
+
+            let siblings = per_category.get(category).copied().unwrap_or(1);
+            if siblings == 1 {
+                reasons.push(format!("Only instruction in {}", category));
+            }
+            let rarity = 1.0 / siblings as f64;
+
+            let length = instruction.content.chars().count();
+            let brevity = if length <= median {
+                1.0
+            } else {
+                reasons.push(format!("Long ({} characters)", length));
+                median as f64 / length as f64
+            };
+
+            let score = REACH_WEIGHT * reach
+                + USAGE_WEIGHT * usage
+                + RARITY_WEIGHT * rarity
+                + BREVITY_WEIGHT * brevity;
+            let suggestion = PrioritySuggestion {
+                instruction_id: instruction.id.clone(),
+                name: instruction.name.clone(),
+                current: instruction.priority,
+                suggested: 0,
+                reasons,
+            };
+            (score, suggestion)
+        })
+        .collect();
+
+    // Best first; ties go to the higher current priority, then by name
+    scored.sort_by(|a, b| {
+        b.0.total_cmp(&a.0)
+            .then_with(|| b.1.current.cmp(&a.1.current))
+            .then_with(|| a.1.name.cmp(&b.1.name))
+    });
+
+    // Each tenth of the ranking gets one priority step; equal scores share one
+    let count = scored.len();
+    let mut suggestions = Vec::with_capacity(count);
+    let mut previous: Option<(f64, u8)> = None;
+    for (rank, (score, mut suggestion)) in scored.into_iter().enumerate() {
+        suggestion.suggested = match previous {
+            Some((previous_score, priority)) if previous_score == score => priority,
+            _ => 10 - (rank * 10 / count) as u8,
+        };
+        previous = Some((score, suggestion.suggested));
+        suggestions.push(suggestion);
+    }
+    suggestions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::InstructionCategory;
+
+    fn instruction(id: &str, category: InstructionCategory, content: &str) -> Instruction {
+        Instruction {
+            id: id.to_string(),
+            name: id.to_string(),
+            category,
+            content: content.to_string(),
+            priority: 9,
+            ..Instruction::default()
+        }
+    }
+
+    #[test]
+    fn test_spreads_drifted_priorities_over_the_range() {
+        let instructions: Vec<Instruction> = (0..10)
+            .map(|n| {
+                instruction(
+                    &format!("style-{}", n),
+                    InstructionCategory::CodeStyle,
+                    &"x".repeat(100 + n * 50),
+                )
+            })
+            .collect();
+        let agents = vec![Agent {
+            instructions: vec!["style-0".to_string()],
+            ..Agent::default()
+        }];
+
+        let suggestions = suggest(&instructions, &agents, &HashMap::new());
+        assert_eq!(suggestions.len(), 10);
+        assert_eq!(suggestions[0].instruction_id, "style-0");
+        assert_eq!(suggestions[0].suggested, 10);
+        assert!(suggestions[0]
+            .reasons
+            .contains(&"Attached to 1 agent(s)".to_string()));
+        assert_eq!(suggestions.last().unwrap().suggested, 1);
+        let mut priorities: Vec<u8> = suggestions.iter().map(|s| s.suggested).collect();
+        priorities.dedup();
+        assert!(priorities.len() >= 5);
+    }
+
+    #[test]
+    fn test_sole_and_used_categories_rank_higher() {
+        let mut instructions = vec![
+            instruction("style-a", InstructionCategory::CodeStyle, "Short."),
+            instruction("style-b", InstructionCategory::CodeStyle, "Short."),
+            instruction("security", InstructionCategory::Security, "Short."),
+            instruction("tone", InstructionCategory::Communication, "Short."),
+        ];
+        instructions.push(Instruction {
+            enabled: false,
+            ..instruction("off", InstructionCategory::Testing, "Short.")
+        });
+        let usage = HashMap::from([("communication".to_string(), 12)]);
+
+        let suggestions = suggest(&instructions, &[], &usage);
+        let order: Vec<&str> = suggestions
+            .iter()
+            .map(|s| s.instruction_id.as_str())
+            .collect();
+        assert_eq!(order, vec!["tone", "security", "style-a", "style-b"]);
+        assert_eq!(suggestions[2].suggested, suggestions[3].suggested);
+        assert!(suggestions[0].suggested > suggestions[1].suggested);
+        assert!(suggest(&[], &[], &usage).is_empty());
+    }
+}
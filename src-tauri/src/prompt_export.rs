@@ -0,0 +1,56 @@
+//! Composed agent prompts handed to other tools
+//! `compose_and_export` writes what `apply_agent` serves to a file or the
+//! clipboard, as it is or wrapped for pasting into a chat or another prompt:
+//! in a fenced markdown block, or between `<system_prompt>` tags naming the
+//! agent. The prompt itself is never escaped, so models read it unchanged.
+
+use crate::models::PromptWrapper;
+
+/// `prompt` wrapped as `wrapper` says, ending in a newline
+pub fn wrap(prompt: &str, wrapper: &PromptWrapper, agent_id: &str) -> String {
+    let prompt = prompt.trim_end();
+    match wrapper {
+        PromptWrapper::Plain => format!("{}\n", prompt),
+        PromptWrapper::Markdown => {
+            // A fence longer than any backtick run in the prompt can't be closed early
+            let longest = prompt
+                .split(|c| c != '`')
+                .map(str::len)
+                .max()
+                .unwrap_or_default();
+            let fence = "`".repeat((longest + 1).max(3));
+            format!("{}markdown\n{}\n{}\n", fence, prompt, fence)
+        }
+        PromptWrapper::Xml => format!(
+            "<system_prompt agent=\"{}\">\n{}\n</system_prompt>\n",
+            escape_attribute(agent_id),
+            prompt
+        ),
+    }
+}
+
+fn escape_attribute(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrappers() {
+        let prompt = "# Agent\n\nRun `cargo fmt`:\n```sh\ncargo fmt\n```\n";
+        assert_eq!(wrap(prompt, &PromptWrapper::Plain, "rusty"), prompt);
+        assert_eq!(
+            wrap(prompt, &PromptWrapper::Markdown, "rusty"),
+            format!("````markdown\n{}````\n", prompt)
+        );
+        assert_eq!(
+            wrap("Be brief.", &PromptWrapper::Xml, "a\"b"),
+            "<system_prompt agent=\"a&quot;b\">\nBe brief.\n</system_prompt>\n"
+        );
+    }
+}
@@ -0,0 +1,258 @@
+//! Redaction profiles for exports
+//! Produces a sanitized public copy of library content: drops tags, removes
+//! markdown sections by heading and replaces internal names.
+
+use crate::code_blocks::FenceTracker;
+use crate::db::ExportData;
+use crate::models::{
+    Agent, Collection, Instruction, RedactionProfile, Settings, Skill, SkillDefinition, Snippet,
+};
+use regex::Regex;
+
+/// A redaction profile with its patterns compiled
+pub struct Redactor {
+    strip_tags: Vec<String>,
+    remove_sections: Vec<Regex>,
+    replacements: Vec<(Regex, String)>,
+}
+
+impl Redactor {
+    pub fn new(profile: &RedactionProfile) -> Result<Self, String> {
+        let remove_sections = profile
+            .remove_sections
+            .iter()
+            .map(|pattern| {
+                Regex::new(pattern)
+                    .map_err(|e| format!("Invalid section pattern '{}': {}", pattern, e))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let replacements = profile
+            .replacements
+            .iter()
+            .filter(|r| !r.find.is_empty())
+            .map(|r| {
+                let regex = Regex::new(&format!("(?i){}", regex::escape(&r.find)))
+                    .map_err(|e| format!("Invalid replacement '{}': {}", r.find, e))?;
+                Ok((regex, r.replace.clone()))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        Ok(Self {
+            strip_tags: profile
+                .strip_tags
+                .iter()
+                .map(|t| t.to_lowercase())
+                .collect(),
+            remove_sections,
+            replacements,
+        })
+    }
+
+    /// Remove matching sections, then apply name replacements
+    pub fn redact_text(&self, text: &str) -> String {
+        let mut redacted = self.remove_matching_sections(text);
+        for (regex, replace) in &self.replacements {
+            redacted = regex
+                .replace_all(&redacted, regex::NoExpand(replace))
+                .into_owned();
+        }
+        redacted
+    }
+
+    /// Drop every markdown section whose heading matches a pattern, including
+    /// its nested subsections. Headings inside code fences are ignored.
+    fn remove_matching_sections(&self, text: &str) -> String {
+        if self.remove_sections.is_empty() {
+            return text.to_string();
+        }
+
+        let mut kept = Vec::new();
+        let mut skipping_level: Option<usize> = None;
+        let mut fences = FenceTracker::default();
+
+        for line in text.lines() {
+            if !fences.update(line) {
+                if let Some((level, title)) = parse_heading(line.trim_start()) {
+                    if skipping_level.is_some_and(|skip| level <= skip) {
+                        skipping_level = None;
+                    }
+                    if skipping_level.is_none()
+                        && self.remove_sections.iter().any(|re| re.is_match(title))
+                    {
+                        skipping_level = Some(level);
+                    }
+                }
+            }
+
+            if skipping_level.is_none() {
+                kept.push(line);
+            }
+        }
+
+        kept.join("\n")
+    }
+
+    fn redact_tags(&self, tags: &[String]) -> Vec<String> {
+        tags.iter()
+            .filter(|tag| !self.strip_tags.contains(&tag.to_lowercase()))
+            .map(|tag| self.redact_text(tag))
+            .collect()
+    }
+
+    pub fn redact_agent(&self, agent: &Agent) -> Agent {
+        Agent {
+            name: self.redact_text(&agent.name),
+            description: self.redact_text(&agent.description),
+            system_prompt: self.redact_text(&agent.system_prompt),
+            tags: self.redact_tags(&agent.tags),
+            aliases: agent.aliases.iter().map(|a| self.redact_text(a)).collect(),
+            ..agent.clone()
+        }
+    }
+
+    pub fn redact_skill(&self, skill: &Skill) -> Skill {
+        let definition = match &skill.definition {
+            SkillDefinition::Prompt {
+                template,
+                variables,
+                follows,
+            } => SkillDefinition::Prompt {
+                template: self.redact_text(template),
+                variables: variables.clone(),
+                follows: follows.clone(),
+            },
+            SkillDefinition::Tool {
+                parameters,
+                handler,
+                sandbox,
+            } => SkillDefinition::Tool {
+                parameters: parameters
+                    .iter()
+                    .map(|p| {
+                        let mut p = p.clone();
+                        p.description = self.redact_text(&p.description);
+                        p
+                    })
+                    .collect(),
+                handler: self.redact_text(handler),
+                sandbox: sandbox.clone(),
+            },
+            SkillDefinition::Workflow { steps } => SkillDefinition::Workflow {
+                steps: steps
+                    .iter()
+                    .map(|step| {
+                        let mut step = step.clone();
+                        step.name = self.redact_text(&step.name);
+                        step.action = self.redact_text(&step.action);
+                        step
+                    })
+                    .collect(),
+            },
+        };
+
+        Skill {
+            name: self.redact_text(&skill.name),
+            description: self.redact_text(&skill.description),
+            definition,
+            aliases: skill.aliases.iter().map(|a| self.redact_text(a)).collect(),
+            ..skill.clone()
+        }
+    }
+
+    pub fn redact_instruction(&self, instruction: &Instruction) -> Instruction {
+        Instruction {
+            name: self.redact_text(&instruction.name),
+            description: self.redact_text(&instruction.description),
+            content: self.redact_text(&instruction.content),
+            tags: self.redact_tags(&instruction.tags),
+            ..instruction.clone()
+        }
+    }
+
+    pub fn redact_export(&self, data: &ExportData) -> ExportData {
+        ExportData {
+            agents: data.agents.iter().map(|a| self.redact_agent(a)).collect(),
+            skills: data.skills.iter().map(|s| self.redact_skill(s)).collect(),
+            instructions: data
+                .instructions
+                .iter()
+                .map(|i| self.redact_instruction(i))
+                .collect(),
+            collections: data
+                .collections
+                .iter()
+                .map(|c| Collection {
+                    name: self.redact_text(&c.name),
+                    ..c.clone()
+                })
+                .collect(),
+            snippets: data
+                .snippets
+                .iter()
+                .map(|s| Snippet {
+                    name: self.redact_text(&s.name),
+                    description: self.redact_text(&s.description),
+                    content: self.redact_text(&s.content),
+                    ..s.clone()
+                })
+                .collect(),
+            // The profiles themselves list the internal names being hidden
+            settings: Settings {
+                redaction_profiles: Vec::new(),
+                ..data.settings.without_api_keys()
+            },
+            ..data.clone()
+        }
+    }
+}
+
+/// Split a markdown ATX heading into its level and title
+fn parse_heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    if level == 0 || level > 6 {
+        return None;
+    }
+    let rest = &line[level..];
+    if !rest.is_empty() && !rest.starts_with(' ') {
+        return None;
+    }
+    Some((level, rest.trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::RedactionReplacement;
+
+    #[test]
+    fn test_redact_text_removes_sections_and_replaces_names() {
+        let profile = RedactionProfile {
+            name: "public".to_string(),
+            strip_tags: vec![],
+            remove_sections: vec!["(?i)^internal".to_string()],
+            replacements: vec![RedactionReplacement {
+                find: "Project Falcon".to_string(),
+                replace: "the project".to_string(),
+            }],
+        };
+        let redactor = Redactor::new(&profile).unwrap();
+
+        let text = "# Guide\nWork on project falcon.\n## Internal notes\nSecret host list\n### Details\nMore\n## Style\nBe concise.";
+        let redacted = redactor.redact_text(text);
+
+        assert_eq!(
+            redacted,
+            "# Guide\nWork on the project.\n## Style\nBe concise."
+        );
+    }
+
+    #[test]
+    fn test_invalid_section_pattern_is_rejected() {
+        let profile = RedactionProfile {
+            remove_sections: vec!["(".to_string()],
+            ..Default::default()
+        };
+        assert!(Redactor::new(&profile).is_err());
+    }
+}
@@ -0,0 +1,173 @@
+//! Prompt releases
+//! A release freezes the enabled library under a tag. Assembling with a tag
+//! reads the frozen copy, so automations pinned to it keep getting the same
+//! prompt while editing continues on the live library ("latest").
+//!
+//! Channels point MCP servers at a release by name. `latest` serves the
+//! live library and `stable` defaults to the newest release; both can be
+//! redirected, and any other channel name is configured in settings. A
+//! server started with a release tag instead of a channel name serves that tag.
+
+use crate::models::{Agent, Instruction, Release, ReleaseChannel, ReleaseSummary, Skill};
+use chrono::Utc;
+
+/// Name of the live library; reserved so it can't be shadowed by a release
+pub const LATEST: &str = "latest";
+
+/// Built-in channel following the newest release unless configured otherwise
+pub const STABLE: &str = "stable";
+
+/// Check and tidy a release tag
+pub fn validate_tag(tag: &str) -> Result<String, String> {
+    let tag = tag.trim();
+    if tag.is_empty() {
+        return Err("Release tag can't be empty".to_string());
+    }
+    if tag.eq_ignore_ascii_case(LATEST) || tag.eq_ignore_ascii_case(STABLE) {
+        return Err(format!("'{}' is reserved as a channel name", tag));
+    }
+    if tag.len() > 64
+        || !tag
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+    {
+        return Err(format!(
+            "Release tag '{}' may only contain up to 64 letters, digits, '.', '_' and '-'",
+            tag
+        ));
+    }
+    Ok(tag.to_string())
+}
+
+/// Freeze the current library. Disabled skills and instructions are left out,
+/// so the release serves exactly what is live right now.
+pub fn snapshot(
+    tag: &str,
+    notes: &str,
+    agents: Vec<Agent>,
+    skills: Vec<Skill>,
+    instructions: Vec<Instruction>,
+) -> Result<Release, String> {
+    Ok(Release {
+        tag: validate_tag(tag)?,
+        notes: notes.trim().to_string(),
+        created_at: Utc::now(),
+        agents,
+        skills: skills.into_iter().filter(|s| s.enabled).collect(),
+        instructions: instructions.into_iter().filter(|i| i.enabled).collect(),
+    })
+}
+
+/// Release tag served on `channel`, or `None` for the live library.
+/// `newest_release` is the most recently created tag, which `stable`
+/// follows unless a channel of that name is configured.
+pub fn channel_release(
+    channel: &str,
+    channels: &[ReleaseChannel],
+    newest_release: Option<&str>,
+) -> Result<Option<String>, String> {
+    let channel = channel.trim();
+    let configured = channels
+        .iter()
+        .find(|c| c.name.eq_ignore_ascii_case(channel));
+
+    let tag = match configured {
+        Some(configured) => configured.tag.as_str(),
+        None if channel.eq_ignore_ascii_case(STABLE) => newest_release
+            .ok_or_else(|| format!("Channel '{}' has no release to serve yet", STABLE))?,
+        None => channel,
+    };
+
+    if tag.eq_ignore_ascii_case(LATEST) {
+        Ok(None)
+    } else {
+        Ok(Some(tag.to_string()))
+    }
+}
+
+impl Release {
+    pub fn summary(&self) -> ReleaseSummary {
+        ReleaseSummary {
+            tag: self.tag.clone(),
+            notes: self.notes.clone(),
+            created_at: self.created_at,
+            agent_count: self.agents.len(),
+            skill_count: self.skills.len(),
+            instruction_count: self.instructions.len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_keeps_only_enabled_entries() {
+        let skills = vec![
+            Skill::default(),
+            Skill {
+                enabled: false,
+                ..Skill::default()
+            },
+        ];
+        let instructions = vec![
+            Instruction {
+                enabled: false,
+                ..Instruction::default()
+            },
+            Instruction::default(),
+        ];
+
+        let release = snapshot(
+            " v1.2.0 ",
+            "First reviewed release",
+            vec![Agent::default()],
+            skills,
+            instructions,
+        )
+        .unwrap();
+        let summary = release.summary();
+
+        assert_eq!(summary.tag, "v1.2.0");
+        assert_eq!(
+            (
+                summary.agent_count,
+                summary.skill_count,
+                summary.instruction_count
+            ),
+            (1, 1, 1)
+        );
+    }
+
+    #[test]
+    fn test_channel_release() {
+        let channels = vec![
+            ReleaseChannel {
+                name: "team".to_string(),
+                tag: "v1.1.0".to_string(),
+            },
+            ReleaseChannel {
+                name: "canary".to_string(),
+                tag: LATEST.to_string(),
+            },
+        ];
+        let resolve = |channel| channel_release(channel, &channels, Some("v1.2.0"));
+
+        assert_eq!(resolve("latest").unwrap(), None);
+        assert_eq!(resolve("canary").unwrap(), None);
+        assert_eq!(resolve("stable").unwrap(), Some("v1.2.0".to_string()));
+        assert_eq!(resolve("Team").unwrap(), Some("v1.1.0".to_string()));
+        assert_eq!(resolve("v1.0.0").unwrap(), Some("v1.0.0".to_string()));
+        assert!(channel_release(STABLE, &[], None).is_err());
+    }
+
+    #[test]
+    fn test_validate_tag() {
+        assert_eq!(validate_tag("2025-01.stable").unwrap(), "2025-01.stable");
+        assert!(validate_tag("").is_err());
+        assert!(validate_tag("Latest").is_err());
+        assert!(validate_tag("stable").is_err());
+        assert!(validate_tag("release 1").is_err());
+    }
+}
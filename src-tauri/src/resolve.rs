@@ -0,0 +1,163 @@
+//! Shared lookup of agents, skills and instructions by user-supplied reference
+//! Accepts an exact ID, a slug of the name, an alias, or a fuzzy fragment of the
+//! name, and reports every candidate when a reference is ambiguous.
+
+use crate::models::{Agent, Instruction, Skill};
+
+/// Anything that can be looked up by ID, name or alias
+pub trait Resolvable {
+    /// Singular noun used in error messages, e.g. "agent"
+    const KIND: &'static str;
+
+    fn id(&self) -> &str;
+    fn name(&self) -> &str;
+    fn aliases(&self) -> &[String] {
+        &[]
+    }
+}
+
+impl Resolvable for Agent {
+    const KIND: &'static str = "agent";
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+    fn name(&self) -> &str {
+        &self.name
+    }
+    fn aliases(&self) -> &[String] {
+        &self.aliases
+    }
+}
+
+impl Resolvable for Skill {
+    const KIND: &'static str = "skill";
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+    fn name(&self) -> &str {
+        &self.name
+    }
+    fn aliases(&self) -> &[String] {
+        &self.aliases
+    }
+}
+
+impl Resolvable for Instruction {
+    const KIND: &'static str = "instruction";
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// Lowercase a name and collapse spaces, underscores and punctuation into single dashes
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::new();
+    for c in text.trim().chars().flat_map(|c| c.to_lowercase()) {
+        if c.is_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_end_matches('-').to_string()
+}
+
+/// Find the single entity `reference` refers to.
+/// Tried in order: exact ID, name slug, alias slug, then fuzzy name match.
+/// The first stage with any match wins; more than one match is an error
+/// listing the candidates so the caller can retry with an ID.
+pub fn resolve_entity<'a, T: Resolvable>(items: &'a [T], reference: &str) -> Result<&'a T, String> {
+    let reference = reference.trim();
+    if let Some(item) = items.iter().find(|item| item.id() == reference) {
+        return Ok(item);
+    }
+
+    let wanted = slugify(reference);
+    if wanted.is_empty() {
+        return Err(format!("Missing {} reference", T::KIND));
+    }
+
+    let stages: [&dyn Fn(&T) -> bool; 3] = [
+        &|item| slugify(item.name()) == wanted,
+        &|item| item.aliases().iter().any(|alias| slugify(alias) == wanted),
+        &|item| slugify(item.name()).contains(&wanted),
+    ];
+
+    for matches in stages {
+        let candidates: Vec<&T> = items.iter().filter(|item| matches(item)).collect();
+        match candidates.as_slice() {
+            [] => continue,
+            [item] => return Ok(item),
+            _ => {
+                let listing = candidates
+                    .iter()
+                    .map(|item| format!("'{}' (id: {})", item.name(), item.id()))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                return Err(format!(
+                    "'{}' matches several {}s: {}. Use an id to pick one.",
+                    reference,
+                    T::KIND,
+                    listing
+                ));
+            }
+        }
+    }
+
+    Err(format!(
+        "{}{} not found: '{}'. Use list_{}s to see available {}s.",
+        T::KIND[..1].to_uppercase(),
+        &T::KIND[1..],
+        reference,
+        T::KIND,
+        T::KIND
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn agent(id: &str, name: &str, aliases: &[&str]) -> Agent {
+        Agent {
+            id: id.to_string(),
+            name: name.to_string(),
+            aliases: aliases.iter().map(|a| a.to_string()).collect(),
+            ..Agent::default()
+        }
+    }
+
+    #[test]
+    fn test_resolve_entity_by_id_slug_alias_and_fragment() {
+        let agents = vec![
+            agent("a1", "Code Reviewer", &["cr"]),
+            agent("a2", "Code Explainer", &[]),
+            agent("a3", "Writer", &[]),
+        ];
+
+        assert_eq!(resolve_entity(&agents, "a3").unwrap().id, "a3");
+        assert_eq!(resolve_entity(&agents, "code_reviewer").unwrap().id, "a1");
+        assert_eq!(resolve_entity(&agents, "CR").unwrap().id, "a1");
+        assert_eq!(resolve_entity(&agents, "explain").unwrap().id, "a2");
+    }
+
+    #[test]
+    fn test_resolve_entity_reports_ambiguity_and_misses() {
+        let agents = vec![
+            agent("a1", "Code Reviewer", &[]),
+            agent("a2", "Code Explainer", &[]),
+        ];
+
+        let ambiguous = resolve_entity(&agents, "code").unwrap_err();
+        assert!(ambiguous.contains("a1") && ambiguous.contains("a2"));
+
+        let missing = resolve_entity(&agents, "painter").unwrap_err();
+        assert!(missing.starts_with("Agent not found"));
+    }
+}
@@ -0,0 +1,119 @@
+//! Data retention
+//! Usage data collected over time can be purged on demand, either entirely or
+//! everything older than a number of days, and `Settings::retention` purges
+//! old data automatically while the app runs. Library content is never
+//! touched.
+
+use crate::db::Database;
+use crate::models::PurgeReport;
+use chrono::{Duration, Utc};
+
+/// Kinds of collected data that can be purged
+pub const KINDS: &[&str] = &["analytics"];
+
+/// Purge `kind`, keeping the last `older_than_days` days when given
+pub fn purge(
+    db: &Database,
+    kind: &str,
+    older_than_days: Option<u32>,
+) -> Result<PurgeReport, String> {
+    let before = older_than_days
+        .map(|days| (Utc::now().date_naive() - Duration::days(days as i64)).to_string());
+
+    let removed = match kind {
+        "analytics" => db
+            .delete_analytics_before(before.as_deref())
+            .map_err(|e| format!("Failed to purge analytics: {}", e))?,
+        _ => {
+            return Err(format!(
+                "Unknown data kind '{}'; expected one of: {}",
+                kind,
+                KINDS.join(", ")
+            ))
+        }
+    };
+
+    Ok(PurgeReport {
+        kind: kind.to_string(),
+        before,
+        removed,
+    })
+}
+
+/// Purge everything older than the retention periods set in settings
+pub fn enforce(db: &Database) -> Result<Vec<PurgeReport>, String> {
+    let retention = db
+        .get_settings()
+        .map_err(|e| format!("Failed to load settings: {}", e))?
+        .retention;
+
+    let mut reports = Vec::new();
+    if let Some(days) = retention.analytics_days {
+        reports.push(purge(db, "analytics", Some(days))?);
+    }
+    Ok(reports)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_default_data;
+    use crate::insights;
+
+    fn library() -> Database {
+        let db = Database::open(":memory:").unwrap();
+        db.migrate().unwrap();
+        init_default_data(&db).unwrap();
+        let mut settings = db.get_settings().unwrap();
+        settings.insights_enabled = true;
+        db.save_settings(&settings).unwrap();
+        db
+    }
+
+    fn record_days_ago(db: &Database, days: i64) {
+        let at = Utc::now() - Duration::days(days);
+        db.increment_insight_counter(&at.date_naive().to_string(), "edit", "")
+            .unwrap();
+        insights::record_usage(db, "agent", &at).unwrap();
+    }
+
+    #[test]
+    fn test_purge_by_age_or_entirely() {
+        let db = library();
+        record_days_ago(&db, 0);
+        record_days_ago(&db, 10);
+        record_days_ago(&db, 40);
+
+        let report = purge(&db, "analytics", Some(30)).unwrap();
+        assert_eq!(report.removed, 2);
+        assert!(report.before.is_some());
+        assert_eq!(
+            insights::usage_heatmap(&db, "agent", 366, 0).unwrap().total,
+            2
+        );
+
+        assert_eq!(purge(&db, "analytics", None).unwrap().removed, 4);
+        assert_eq!(
+            insights::usage_heatmap(&db, "agent", 366, 0).unwrap().total,
+            0
+        );
+        assert!(purge(&db, "library", None)
+            .unwrap_err()
+            .contains("analytics"));
+    }
+
+    #[test]
+    fn test_enforce_follows_settings() {
+        let db = library();
+        record_days_ago(&db, 3);
+        assert!(enforce(&db).unwrap().is_empty());
+
+        let mut settings = db.get_settings().unwrap();
+        settings.retention.analytics_days = Some(1);
+        db.save_settings(&settings).unwrap();
+        let reports = enforce(&db).unwrap();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].removed, 2);
+        assert_eq!(db.get_settings().unwrap().retention.analytics_days, Some(1));
+    }
+}
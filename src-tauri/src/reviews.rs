@@ -0,0 +1,102 @@
+//! Review reminders for instructions
+//! An instruction can carry a `review_due_at` date. Once it passes, the
+//! instruction is listed by `list_due_for_review`, and the app's background
+//! check raises one notification for each batch that newly falls due.
+
+use crate::models::Instruction;
+use chrono::{DateTime, Utc};
+use std::collections::HashSet;
+
+/// Most instruction names listed in one reminder
+const MAX_NAMED: usize = 3;
+
+/// Enabled instructions whose review date has passed, longest overdue first
+pub fn due_for_review(instructions: &[Instruction], now: DateTime<Utc>) -> Vec<&Instruction> {
+    let mut due: Vec<_> = instructions
+        .iter()
+        .filter(|i| i.enabled && i.review_due_at.is_some_and(|at| at <= now))
+        .collect();
+    due.sort_by_key(|i| i.review_due_at);
+    due
+}
+
+/// Remembers which overdue instructions the user has already been told about
+#[derive(Default)]
+pub struct ReviewReminders {
+    reminded: HashSet<String>,
+}
+
+impl ReviewReminders {
+    /// Notification text for instructions that fell due since the last check,
+    /// if any. An instruction given a new date and falling due again is
+    /// reminded about again.
+    pub fn check(&mut self, instructions: &[Instruction], now: DateTime<Utc>) -> Option<String> {
+        let due = due_for_review(instructions, now);
+        let new: Vec<&str> = due
+            .iter()
+            .filter(|i| !self.reminded.contains(&i.id))
+            .map(|i| i.name.as_str())
+            .collect();
+        self.reminded = due.iter().map(|i| i.id.clone()).collect();
+
+        match new.as_slice() {
+            [] => None,
+            [name] => Some(format!("\"{}\" is overdue for review", name)),
+            names if names.len() <= MAX_NAMED => Some(format!(
+                "{} instructions are overdue for review: {}",
+                names.len(),
+                names.join(", ")
+            )),
+            names => Some(format!(
+                "{} instructions are overdue for review: {} and {} more",
+                names.len(),
+                names[..MAX_NAMED].join(", "),
+                names.len() - MAX_NAMED
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_newly_overdue_instructions_are_reminded_once() {
+        let now = Utc::now();
+        let instruction = |id: &str, due_in_days: Option<i64>, enabled| Instruction {
+            id: id.to_string(),
+            name: id.to_string(),
+            enabled,
+            review_due_at: due_in_days.map(|days| now + Duration::days(days)),
+            ..Instruction::default()
+        };
+        let mut instructions = vec![
+            instruction("later", Some(-1), true),
+            instruction("oldest", Some(-30), true),
+            instruction("future", Some(7), true),
+            instruction("never", None, true),
+            instruction("disabled", Some(-5), false),
+        ];
+
+        let ids: Vec<_> = due_for_review(&instructions, now)
+            .iter()
+            .map(|i| i.id.as_str())
+            .collect();
+        assert_eq!(ids, ["oldest", "later"]);
+
+        let mut reminders = ReviewReminders::default();
+        assert_eq!(
+            reminders.check(&instructions, now).as_deref(),
+            Some("2 instructions are overdue for review: oldest, later")
+        );
+        assert_eq!(reminders.check(&instructions, now), None);
+
+        instructions[2].review_due_at = Some(now - Duration::hours(1));
+        assert_eq!(
+            reminders.check(&instructions, now).as_deref(),
+            Some("\"future\" is overdue for review")
+        );
+    }
+}
@@ -0,0 +1,258 @@
+//! Conditions that limit where an instruction applies
+//! An instruction with a branch pattern is only assembled when the git branch
+//! in the assembly context matches it, e.g. stricter rules on `release/*`.
+//! Patterns are comma-separated globs: `*` matches within one path segment,
+//! `**` matches across segments, `?` matches one character, and a leading `!`
+//! excludes matching branches. File patterns use the same syntax; one
+//! without a `/` also matches a file's name in any directory.
+//! Contexts name the languages, projects and MCP clients an instruction is
+//! written for (`rust`, `frontend`, `claude-code`), so a session is only
+//! given the rules for what it is working on.
+
+use crate::models::Instruction;
+use crate::suggestions;
+
+/// Tidy a user-supplied pattern list; `None` when no patterns are left
+pub fn normalize_branch_pattern(raw: &str) -> Option<String> {
+    let patterns: Vec<&str> = raw
+        .split(',')
+        .map(str::trim)
+        .filter(|p| !p.is_empty() && *p != "!")
+        .collect();
+
+    if patterns.is_empty() {
+        None
+    } else {
+        Some(patterns.join(", "))
+    }
+}
+
+/// File patterns are glob lists too, kept for tools that scope rules by file
+/// (Cursor's `globs`, Copilot's `applyTo`). Assembly doesn't filter on them;
+/// `suggest_skills_for_context` ranks instructions whose files match first.
+pub fn normalize_file_pattern(raw: &str) -> Option<String> {
+    normalize_branch_pattern(raw)
+}
+
+/// Tidy context names into lowercase, dash-separated words without repeats
+pub fn normalize_contexts(contexts: &[String]) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for context in contexts {
+        let context = context
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join("-")
+            .to_lowercase();
+        if !context.is_empty() && !normalized.contains(&context) {
+            normalized.push(context);
+        }
+    }
+    normalized
+}
+
+/// Contexts a session working on `paths` is in besides the ones it names:
+/// each file's extension and language (`rs` and `rust` for `src/db.rs`)
+pub fn path_contexts(paths: &[String]) -> Vec<String> {
+    let extensions = paths.iter().filter_map(|path| {
+        let name = path.rsplit(['/', '\\']).next().unwrap_or(path);
+        name.rsplit_once('.').map(|(_, ext)| ext.to_string())
+    });
+    let contexts: Vec<String> = extensions
+        .chain(suggestions::context_keywords(paths))
+        .collect();
+    normalize_contexts(&contexts)
+}
+
+/// Whether an instruction applies in `contexts` to a session touching
+/// `paths`. Instructions with neither contexts nor a file pattern apply
+/// everywhere; scoped ones when one of their contexts is named or their file
+/// pattern matches one of the files.
+pub fn in_context_scope(instruction: &Instruction, contexts: &[String], paths: &[String]) -> bool {
+    let patterns = instruction
+        .file_pattern
+        .as_deref()
+        .map(str::trim)
+        .filter(|p| !p.is_empty());
+    if instruction.contexts.is_empty() && patterns.is_none() {
+        return true;
+    }
+    let named = instruction
+        .contexts
+        .iter()
+        .any(|context| contexts.iter().any(|c| c.eq_ignore_ascii_case(context)));
+    named || patterns.is_some_and(|patterns| paths.iter().any(|p| file_matches(patterns, p)))
+}
+
+/// Enabled instructions for a session in `contexts` touching `paths`,
+/// highest priority first
+pub fn instructions_for_context<'a>(
+    instructions: &'a [Instruction],
+    contexts: &[String],
+    paths: &[String],
+) -> Vec<&'a Instruction> {
+    let mut contexts = normalize_contexts(contexts);
+    contexts.extend(path_contexts(paths));
+    let mut found: Vec<&Instruction> = instructions
+        .iter()
+        .filter(|i| i.enabled && in_context_scope(i, &contexts, paths))
+        .collect();
+    found.sort_by_key(|i| std::cmp::Reverse(i.priority));
+    found
+}
+
+/// Whether `branch` matches a comma-separated pattern list. A list made only
+/// of exclusions matches every other branch.
+pub fn branch_matches(patterns: &str, branch: &str) -> bool {
+    list_matches(patterns, |pattern| glob_match(pattern, branch))
+}
+
+/// Whether the file at `path` matches a comma-separated pattern list
+pub fn file_matches(patterns: &str, path: &str) -> bool {
+    let path = path.replace('\\', "/");
+    let path = path.trim_start_matches("./");
+    let name = path.rsplit('/').next().unwrap_or(path);
+    list_matches(patterns, |pattern| {
+        glob_match(pattern, path) || (!pattern.contains('/') && glob_match(pattern, name))
+    })
+}
+
+fn list_matches(patterns: &str, matches: impl Fn(&str) -> bool) -> bool {
+    let mut has_inclusions = false;
+    let mut included = false;
+
+    for pattern in patterns.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        match pattern.strip_prefix('!') {
+            Some(excluded) => {
+                if matches(excluded.trim()) {
+                    return false;
+                }
+            }
+            None => {
+                has_inclusions = true;
+                included |= matches(pattern);
+            }
+        }
+    }
+
+    included || !has_inclusions
+}
+
+/// Whether an instruction applies on `branch`. Unscoped instructions always
+/// apply; scoped ones are skipped when the branch is unknown.
+pub fn in_branch_scope(instruction: &Instruction, branch: Option<&str>) -> bool {
+    match instruction
+        .branch_pattern
+        .as_deref()
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+    {
+        None => true,
+        Some(patterns) => branch.is_some_and(|branch| branch_matches(patterns, branch)),
+    }
+}
+
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    glob(&pattern, &text)
+}
+
+fn glob(pattern: &[char], text: &[char]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        ['*', '*', '/', rest @ ..] => {
+            glob(rest, text)
+                || (0..text.len()).any(|i| text[i] == '/' && glob(rest, &text[i + 1..]))
+        }
+        ['*', '*', rest @ ..] => (0..=text.len()).any(|i| glob(rest, &text[i..])),
+        ['*', rest @ ..] => (0..=text.len())
+            .take_while(|&i| i == 0 || text[i - 1] != '/')
+            .any(|i| glob(rest, &text[i..])),
+        ['?', rest @ ..] => matches!(text, [c, ..] if *c != '/') && glob(rest, &text[1..]),
+        [c, rest @ ..] => text.first() == Some(c) && glob(rest, &text[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_branch_patterns() {
+        assert!(branch_matches("release/*", "release/1.4"));
+        assert!(!branch_matches("release/*", "release/1.4/hotfix"));
+        assert!(branch_matches("release/**", "release/1.4/hotfix"));
+        assert!(branch_matches("main, release/*", "main"));
+        assert!(!branch_matches("main, release/*", "feature/login"));
+        assert!(branch_matches("v?.x", "v2.x"));
+
+        assert!(branch_matches("!main", "feature/login"));
+        assert!(!branch_matches("!main", "main"));
+        assert!(!branch_matches(
+            "release/*, !release/legacy",
+            "release/legacy"
+        ));
+    }
+
+    #[test]
+    fn test_file_patterns() {
+        assert!(file_matches("**/*.rs", "src/main.rs"));
+        assert!(file_matches("**/*.rs", "build.rs"));
+        assert!(file_matches("*.ts, *.tsx", "src/components/App.tsx"));
+        assert!(file_matches("src/**", "./src/lib/types.ts"));
+        assert!(!file_matches("src/*.rs", "src/db/mod.rs"));
+        assert!(!file_matches(
+            "**/*.rs, !**/tests/**",
+            "crates/app/tests/api.rs"
+        ));
+    }
+
+    #[test]
+    fn test_instructions_follow_context() {
+        let instruction = |id: &str, contexts: &[&str], file_pattern: Option<&str>| Instruction {
+            id: id.to_string(),
+            contexts: contexts.iter().map(|c| c.to_string()).collect(),
+            file_pattern: file_pattern.map(str::to_string),
+            ..Instruction::default()
+        };
+        let instructions = vec![
+            instruction("everywhere", &[], None),
+            instruction("python", &["python"], None),
+            instruction("rust", &["rust"], None),
+            instruction("ui", &["frontend", "claude-code"], None),
+            instruction("migrations", &[], Some("migrations/*.sql")),
+        ];
+        let found = |contexts: &[&str], paths: &[&str]| -> Vec<String> {
+            let contexts: Vec<String> = contexts.iter().map(|c| c.to_string()).collect();
+            let paths: Vec<String> = paths.iter().map(|p| p.to_string()).collect();
+            instructions_for_context(&instructions, &contexts, &paths)
+                .into_iter()
+                .map(|i| i.id.clone())
+                .collect()
+        };
+
+        assert_eq!(found(&["Rust"], &[]), ["everywhere", "rust"]);
+        assert_eq!(found(&[], &["src/db.rs"]), ["everywhere", "rust"]);
+        assert_eq!(
+            found(&["Claude Code"], &["migrations/030.sql"]),
+            ["everywhere", "ui", "migrations"]
+        );
+        assert_eq!(found(&[], &[]), ["everywhere"]);
+        assert_eq!(
+            normalize_contexts(&[" Claude  Code ".to_string(), "claude-code".to_string()]),
+            ["claude-code"]
+        );
+    }
+
+    #[test]
+    fn test_unknown_branch_skips_scoped_instructions() {
+        let scoped = Instruction {
+            branch_pattern: Some("release/*".to_string()),
+            ..Instruction::default()
+        };
+        assert!(in_branch_scope(&Instruction::default(), None));
+        assert!(!in_branch_scope(&scoped, None));
+        assert!(in_branch_scope(&scoped, Some("release/2.0")));
+        assert!(!in_branch_scope(&scoped, Some("main")));
+    }
+}
@@ -0,0 +1,163 @@
+//! Library search
+//! Ranks agents, skills and instructions against a free-text query. Every
+//! query word has to appear somewhere in an item; a word found in the name
+//! counts most, then tags and aliases, then the description, then the body
+//! (system prompt, template or instruction text).
+
+use crate::models::{Agent, Instruction, SearchResult, Skill, SkillDefinition};
+
+const NAME_WEIGHT: i64 = 10;
+const TAG_WEIGHT: i64 = 6;
+const DESCRIPTION_WEIGHT: i64 = 3;
+const BODY_WEIGHT: i64 = 1;
+
+/// The searchable text of one library item
+struct Document<'a> {
+    entity_type: &'static str,
+    id: &'a str,
+    name: &'a str,
+    description: &'a str,
+    tags: Vec<&'a str>,
+    body: &'a str,
+}
+
+impl Document<'_> {
+    /// Sum of the best field weight each word is found in; `None` unless
+    /// every word is found
+    fn score(&self, words: &[String]) -> Option<i64> {
+        let contains = |text: &str, word: &str| text.to_lowercase().contains(word);
+        words.iter().try_fold(0, |score, word| {
+            let weight = if contains(self.name, word) {
+                NAME_WEIGHT
+            } else if self.tags.iter().any(|tag| contains(tag, word)) {
+                TAG_WEIGHT
+            } else if contains(self.description, word) {
+                DESCRIPTION_WEIGHT
+            } else if contains(self.body, word) {
+                BODY_WEIGHT
+            } else {
+                return None;
+            };
+            Some(score + weight)
+        })
+    }
+}
+
+/// Items matching every word of `query`, best first, at most `limit`.
+/// `types` narrows the kinds searched ("agent", "skill", "instruction");
+/// empty searches all. Disabled skills and instructions are left out.
+pub fn search(
+    agents: &[Agent],
+    skills: &[Skill],
+    instructions: &[Instruction],
+    query: &str,
+    types: &[String],
+    limit: usize,
+) -> Vec<SearchResult> {
+    let words: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+    if words.is_empty() {
+        return Vec::new();
+    }
+
+    let agents = agents.iter().map(|a| Document {
+        entity_type: "agent",
+        id: &a.id,
+        name: &a.name,
+        description: &a.description,
+        tags: a
+            .tags
+            .iter()
+            .chain(&a.aliases)
+            .map(String::as_str)
+            .collect(),
+        body: &a.system_prompt,
+    });
+    let skills = skills.iter().filter(|s| s.enabled).map(|s| Document {
+        entity_type: "skill",
+        id: &s.id,
+        name: &s.name,
+        description: &s.description,
+        tags: s.aliases.iter().map(String::as_str).collect(),
+        body: match &s.definition {
+            SkillDefinition::Prompt { template, .. } => template,
+            _ => "",
+        },
+    });
+    let instructions = instructions.iter().filter(|i| i.enabled).map(|i| Document {
+        entity_type: "instruction",
+        id: &i.id,
+        name: &i.name,
+        description: &i.description,
+        tags: i.tags.iter().map(String::as_str).collect(),
+        body: &i.content,
+    });
+
+    let mut results: Vec<SearchResult> = agents
+        .chain(skills)
+        .chain(instructions)
+        .filter(|doc| types.is_empty() || types.iter().any(|t| t == doc.entity_type))
+        .filter_map(|doc| {
+            Some(SearchResult {
+                score: doc.score(&words)?,
+                entity_type: doc.entity_type.to_string(),
+                id: doc.id.to_string(),
+                name: doc.name.to_string(),
+                description: doc.description.to_string(),
+            })
+        })
+        .collect();
+
+    // Stable sort keeps library order between equal scores
+    results.sort_by_key(|r| std::cmp::Reverse(r.score));
+    results.truncate(limit);
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_word_must_match_and_names_rank_first() {
+        let agents = vec![Agent {
+            id: "reviewer".to_string(),
+            name: "Code Reviewer".to_string(),
+            description: "Reviews pull requests".to_string(),
+            system_prompt: "Check error handling in Rust code.".to_string(),
+            ..Agent::default()
+        }];
+        let instructions = vec![
+            Instruction {
+                id: "rust-errors".to_string(),
+                name: "Rust Error Handling".to_string(),
+                content: "Use thiserror for library errors.".to_string(),
+                ..Instruction::default()
+            },
+            Instruction {
+                id: "off".to_string(),
+                name: "Rust errors (old)".to_string(),
+                enabled: false,
+                ..Instruction::default()
+            },
+        ];
+
+        let results = search(&agents, &[], &instructions, "rust ERROR", &[], 10);
+        let ids: Vec<_> = results.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, ["rust-errors", "reviewer"]);
+        assert_eq!(results[0].score, 2 * NAME_WEIGHT);
+        assert_eq!(results[1].score, 2 * BODY_WEIGHT);
+
+        let agents_only = search(
+            &agents,
+            &[],
+            &instructions,
+            "rust",
+            &["agent".to_string()],
+            10,
+        );
+        assert_eq!(agents_only.len(), 1);
+        assert!(search(&agents, &[], &instructions, "rust python", &[], 10).is_empty());
+        assert!(search(&agents, &[], &instructions, "  ", &[], 10).is_empty());
+        assert_eq!(search(&agents, &[], &instructions, "rust", &[], 1).len(), 1);
+    }
+}
@@ -0,0 +1,247 @@
+//! Privacy scanner for prompt content
+//! Flags credentials, tokens, emails and internal hostnames before content leaves the machine.
+
+use crate::db::ExportData;
+use crate::models::{Agent, Instruction, SecretFinding, Settings, Skill};
+use regex::Regex;
+use std::sync::OnceLock;
+
+/// (kind, pattern, blocking). Credentials block an export; contact details only warn.
+const PATTERNS: &[(&str, &str, bool)] = &[
+    ("private_key", r"-----BEGIN [A-Z ]*PRIVATE KEY-----", true),
+    ("aws_access_key", r"\b(?:AKIA|ASIA)[0-9A-Z]{16}\b", true),
+    ("github_token", r"\bgh[pousr]_[A-Za-z0-9]{36,}\b", true),
+    ("slack_token", r"\bxox[abprs]-[A-Za-z0-9-]{10,}", true),
+    ("google_api_key", r"\bAIza[0-9A-Za-z_-]{35}\b", true),
+    ("api_key", r"\bsk-[A-Za-z0-9_-]{20,}", true),
+    (
+        "jwt",
+        r"\beyJ[A-Za-z0-9_-]{10,}\.[A-Za-z0-9_-]{10,}\.[A-Za-z0-9_-]{10,}",
+        true,
+    ),
+    (
+        "bearer_token",
+        r"(?i)\bbearer\s+[A-Za-z0-9._~+/-]{20,}=*",
+        true,
+    ),
+    (
+        "credential_assignment",
+        r#"(?i)\b(?:api[_-]?key|secret|password|passwd|token)\s*[:=]\s*["']?[A-Za-z0-9/+_.-]{8,}"#,
+        true,
+    ),
+    (
+        "email",
+        r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}\b",
+        false,
+    ),
+    (
+        "internal_hostname",
+        r"(?i)\b[a-z0-9-]+(?:\.[a-z0-9-]+)*\.(?:internal|local|corp|lan|intranet)\b",
+        false,
+    ),
+];
+
+fn patterns() -> &'static [(&'static str, Regex, bool)] {
+    static COMPILED: OnceLock<Vec<(&'static str, Regex, bool)>> = OnceLock::new();
+    COMPILED.get_or_init(|| {
+        PATTERNS
+            .iter()
+            .map(|(kind, pattern, blocking)| (*kind, Regex::new(pattern).unwrap(), *blocking))
+            .collect()
+    })
+}
+
+/// A single match inside a block of text
+struct TextMatch {
+    kind: &'static str,
+    line: usize,
+    excerpt: String,
+    blocking: bool,
+}
+
+fn scan_text(text: &str) -> Vec<TextMatch> {
+    let mut matches = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        for (kind, regex, blocking) in patterns() {
+            for found in regex.find_iter(line) {
+                matches.push(TextMatch {
+                    kind,
+                    line: index + 1,
+                    excerpt: mask(found.as_str()),
+                    blocking: *blocking,
+                });
+            }
+        }
+    }
+    matches
+}
+
+/// Mask everything in free text (such as a log) that looks like a secret or
+/// contact detail
+pub fn mask_text(text: &str) -> String {
+    let mut masked = text.to_string();
+    for (_, regex, _) in patterns() {
+        masked = regex
+            .replace_all(&masked, |found: &regex::Captures| mask(&found[0]))
+            .into_owned();
+    }
+    masked
+}
+
+/// Keep just enough of a match to recognise it without re-leaking the secret
+fn mask(value: &str) -> String {
+    let visible: String = value.chars().take(4).collect();
+    let hidden = value.chars().count().saturating_sub(4).min(12);
+    format!("{}{}", visible, "*".repeat(hidden))
+}
+
+fn push_findings(
+    findings: &mut Vec<SecretFinding>,
+    entity_type: &str,
+    entity_id: &str,
+    entity_name: &str,
+    field: &str,
+    text: &str,
+) {
+    findings.extend(scan_text(text).into_iter().map(|m| SecretFinding {
+        entity_type: entity_type.to_string(),
+        entity_id: entity_id.to_string(),
+        entity_name: entity_name.to_string(),
+        field: field.to_string(),
+        kind: m.kind.to_string(),
+        line: m.line,
+        excerpt: m.excerpt,
+        blocking: m.blocking,
+    }));
+}
+
+pub fn scan_agent(agent: &Agent) -> Vec<SecretFinding> {
+    let mut findings = Vec::new();
+    for (field, text) in [
+        ("description", &agent.description),
+        ("system_prompt", &agent.system_prompt),
+    ] {
+        push_findings(&mut findings, "agent", &agent.id, &agent.name, field, text);
+    }
+    findings
+}
+
+pub fn scan_skill(skill: &Skill) -> Vec<SecretFinding> {
+    let mut findings = Vec::new();
+    let definition = serde_json::to_string_pretty(&skill.definition).unwrap_or_default();
+    for (field, text) in [
+        ("description", &skill.description),
+        ("definition", &definition),
+    ] {
+        push_findings(&mut findings, "skill", &skill.id, &skill.name, field, text);
+    }
+    findings
+}
+
+pub fn scan_instruction(instruction: &Instruction) -> Vec<SecretFinding> {
+    let mut findings = Vec::new();
+    push_findings(
+        &mut findings,
+        "instruction",
+        &instruction.id,
+        &instruction.name,
+        "content",
+        &instruction.content,
+    );
+    findings
+}
+
+/// Scan everything that would leave the machine in a full export
+pub fn scan_export(data: &ExportData) -> Vec<SecretFinding> {
+    let mut findings = Vec::new();
+    findings.extend(data.agents.iter().flat_map(scan_agent));
+    findings.extend(data.skills.iter().flat_map(scan_skill));
+    findings.extend(data.instructions.iter().flat_map(scan_instruction));
+    findings.extend(scan_settings(&data.settings));
+    findings
+}
+
+/// Provider API keys left in the settings, as in exports from older versions
+pub fn scan_settings(settings: &Settings) -> Vec<SecretFinding> {
+    settings
+        .llm_providers
+        .iter()
+        .filter(|p| !p.api_key.is_empty())
+        .map(|p| SecretFinding {
+            entity_type: "settings".to_string(),
+            entity_id: p.name.clone(),
+            entity_name: p.name.clone(),
+            field: "api_key".to_string(),
+            kind: "api_key".to_string(),
+            line: 1,
+            excerpt: mask(&p.api_key),
+            blocking: true,
+        })
+        .collect()
+}
+
+/// Refuse an export when it contains credentials, unless the caller explicitly allowed it
+pub fn guard_export(findings: &[SecretFinding], allow_secrets: bool) -> Result<(), String> {
+    let blocking: Vec<&SecretFinding> = findings.iter().filter(|f| f.blocking).collect();
+    if blocking.is_empty() || allow_secrets {
+        return Ok(());
+    }
+
+    let summary = blocking
+        .iter()
+        .map(|f| {
+            format!(
+                "{} in {} '{}' ({} line {})",
+                f.kind, f.entity_type, f.entity_name, f.field, f.line
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("; ");
+    Err(format!(
+        "Export blocked: {} potential secret(s) found: {}",
+        blocking.len(),
+        summary
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_text_flags_credentials_and_masks_them() {
+        let text = "Use this key:\nOPENAI_API_KEY=sk-abcdefghijklmnopqrstuvwxyz123456\nContact ops@example.com";
+        let matches = scan_text(text);
+
+        let key = matches.iter().find(|m| m.kind == "api_key").unwrap();
+        assert_eq!(key.line, 2);
+        assert!(key.blocking);
+        assert!(!key.excerpt.contains("abcdefghijklmnop"));
+
+        let email = matches.iter().find(|m| m.kind == "email").unwrap();
+        assert_eq!(email.line, 3);
+        assert!(!email.blocking);
+    }
+
+    #[test]
+    fn test_provider_keys_in_settings_block_exports() {
+        let mut settings = Settings::default();
+        settings.llm_providers.push(crate::models::LlmProvider {
+            name: "work".to_string(),
+            kind: "openai".to_string(),
+            model: "gpt-4o".to_string(),
+            base_url: None,
+            api_key: "sk-abcdefghijklmnopqrstuvwxyz".to_string(),
+        });
+        let findings = scan_settings(&settings);
+        assert_eq!(findings.len(), 1);
+        assert!(findings[0].blocking);
+        assert!(!findings[0].excerpt.contains("abcdefghijklmnop"));
+        assert!(scan_settings(&settings.without_api_keys()).is_empty());
+    }
+
+    #[test]
+    fn test_scan_text_ignores_plain_prose() {
+        assert!(scan_text("You are a helpful assistant. Keep answers short.").is_empty());
+    }
+}
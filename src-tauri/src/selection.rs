@@ -0,0 +1,165 @@
+//! Selective export
+//! Narrows a full export down to chosen agents, skills and instructions plus
+//! what they depend on: the base agents the chosen agents inherit from, their
+//! skills, instructions and avatar images, and the snippets any of them
+//! include. Settings are left at their
+//! defaults so a shared bundle never carries provider keys or local paths.
+
+use crate::composition;
+use crate::db::ExportData;
+use crate::models::{Agent, Settings};
+use crate::snippets;
+
+/// The part of `data` made up of the selected items and their dependencies.
+/// Unknown ids are an error; dangling references on a selected agent are
+/// skipped, as assembly skips them.
+pub fn select(
+    data: ExportData,
+    agent_ids: &[String],
+    skill_ids: &[String],
+    instruction_ids: &[String],
+) -> Result<ExportData, String> {
+    ensure_known("Agent", agent_ids, data.agents.iter().map(|a| &a.id))?;
+    ensure_known("Skill", skill_ids, data.skills.iter().map(|s| &s.id))?;
+    ensure_known(
+        "Instruction",
+        instruction_ids,
+        data.instructions.iter().map(|i| &i.id),
+    )?;
+
+    let agent_ids = with_ancestors(&data.agents, agent_ids);
+    let agents: Vec<_> = data
+        .agents
+        .into_iter()
+        .filter(|a| agent_ids.contains(&a.id))
+        .collect();
+    let wanted_skill =
+        |id: &String| skill_ids.contains(id) || agents.iter().any(|a| a.skills.contains(id));
+    let wanted_instruction = |id: &String| {
+        instruction_ids.contains(id) || agents.iter().any(|a| a.instructions.contains(id))
+    };
+    let wanted_attachment = |id: &String| {
+        agents
+            .iter()
+            .any(|a| a.avatar_image_id.as_ref() == Some(id))
+    };
+
+    let skills: Vec<_> = data
+        .skills
+        .into_iter()
+        .filter(|s| wanted_skill(&s.id))
+        .collect();
+    let instructions: Vec<_> = data
+        .instructions
+        .into_iter()
+        .filter(|i| wanted_instruction(&i.id))
+        .collect();
+
+    Ok(ExportData {
+        snippets: snippets::used(&data.snippets, &agents, &skills, &instructions),
+        skills,
+        instructions,
+        attachments: data
+            .attachments
+            .into_iter()
+            .filter(|a| wanted_attachment(&a.id))
+            .collect(),
+        collections: Vec::new(),
+        settings: Settings::default(),
+        agents,
+        exported_at: data.exported_at,
+        version: data.version,
+    })
+}
+
+/// `ids` plus the base agents they inherit from, up to `MAX_DEPTH` levels
+fn with_ancestors(agents: &[Agent], ids: &[String]) -> Vec<String> {
+    let mut wanted = ids.to_vec();
+    for id in ids {
+        let mut parent = agents.iter().find(|a| &a.id == id);
+        for _ in 0..composition::MAX_DEPTH {
+            let Some(base) = parent
+                .and_then(|a| a.parent_agent_id.as_ref())
+                .and_then(|p| agents.iter().find(|a| &a.id == p))
+            else {
+                break;
+            };
+            if !wanted.contains(&base.id) {
+                wanted.push(base.id.clone());
+            }
+            parent = Some(base);
+        }
+    }
+    wanted
+}
+
+fn ensure_known<'a>(
+    kind: &str,
+    ids: &[String],
+    known: impl Iterator<Item = &'a String>,
+) -> Result<(), String> {
+    let known: Vec<_> = known.collect();
+    match ids.iter().find(|id| !known.contains(id)) {
+        Some(id) => Err(format!("{} '{}' not found", kind, id)),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+
+    #[test]
+    fn test_selection_pulls_in_agent_dependencies_only() {
+        let db = Database::open(":memory:").unwrap();
+        db.migrate().unwrap();
+        crate::db::init_default_data(&db).unwrap();
+        let data = db.export_all().unwrap();
+        let agent = data.agents[0].clone();
+        let extra = data
+            .instructions
+            .iter()
+            .find(|i| !agent.instructions.contains(&i.id))
+            .map(|i| i.id.clone());
+
+        let bundle = select(
+            data.clone(),
+            std::slice::from_ref(&agent.id),
+            &[],
+            extra.as_slice(),
+        )
+        .unwrap();
+        assert_eq!(bundle.agents.len(), 1);
+        let skill_ids: Vec<_> = bundle.skills.iter().map(|s| s.id.clone()).collect();
+        assert!(agent.skills.iter().all(|id| skill_ids.contains(id)));
+        assert_eq!(skill_ids.len(), agent.skills.len());
+        let instruction_ids: Vec<_> = bundle.instructions.iter().map(|i| i.id.clone()).collect();
+        assert!(agent
+            .instructions
+            .iter()
+            .all(|id| instruction_ids.contains(id)));
+        assert_eq!(
+            instruction_ids.len(),
+            agent.instructions.len() + extra.iter().count()
+        );
+        assert!(bundle.collections.is_empty());
+
+        let mut child = agent.clone();
+        child.id = "child".to_string();
+        child.skills.clear();
+        child.instructions.clear();
+        child.parent_agent_id = Some(agent.id.clone());
+        let mut with_child = data.clone();
+        with_child.agents.push(child);
+        let bundle = select(with_child, &["child".to_string()], &[], &[]).unwrap();
+        let agent_ids: Vec<_> = bundle.agents.iter().map(|a| a.id.as_str()).collect();
+        assert_eq!(agent_ids, [agent.id.as_str(), "child"]);
+        assert_eq!(bundle.skills.len(), agent.skills.len());
+
+        assert_eq!(
+            select(data, &[], &["nope".to_string()], &[]).unwrap_err(),
+            "Skill 'nope' not found"
+        );
+    }
+}
@@ -0,0 +1,346 @@
+//! Self-check diagnostics
+//! Each check looks at one thing the app depends on (the database, spawning
+//! the MCP server, writable directories, ...) and reports a status with a
+//! hint for fixing it. A failing check never stops the others, so the
+//! troubleshooting page always gets a full report.
+
+use crate::db::Database;
+use crate::models::{SelfCheck, SelfCheckReport};
+use crate::setup;
+use chrono::Utc;
+use serde_json::{json, Value};
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+/// How long a test-spawned MCP server gets to answer `initialize`
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub fn ok(id: &str, name: &str, message: &str) -> SelfCheck {
+    check(id, name, "ok", message, None)
+}
+
+pub fn warning(id: &str, name: &str, message: &str, hint: &str) -> SelfCheck {
+    check(id, name, "warning", message, Some(hint))
+}
+
+pub fn error(id: &str, name: &str, message: &str, hint: &str) -> SelfCheck {
+    check(id, name, "error", message, Some(hint))
+}
+
+pub fn skipped(id: &str, name: &str, message: &str) -> SelfCheck {
+    check(id, name, "skipped", message, None)
+}
+
+fn check(id: &str, name: &str, status: &str, message: &str, hint: Option<&str>) -> SelfCheck {
+    SelfCheck {
+        id: id.to_string(),
+        name: name.to_string(),
+        status: status.to_string(),
+        message: message.to_string(),
+        hint: hint.map(str::to_string),
+    }
+}
+
+/// Roll the checks up into a report
+pub fn report(checks: Vec<SelfCheck>) -> SelfCheckReport {
+    SelfCheckReport {
+        errors: checks.iter().filter(|c| c.status == "error").count(),
+        warnings: checks.iter().filter(|c| c.status == "warning").count(),
+        checks,
+        checked_at: Utc::now(),
+    }
+}
+
+/// The database opens, passes SQLite's integrity check and its settings load
+pub fn check_database(db: &Database) -> SelfCheck {
+    let (id, name) = ("database", "Database");
+    match db.integrity_check() {
+        Ok(result) if result == "ok" => {}
+        Ok(result) => {
+            return error(
+                id,
+                name,
+                &format!("Integrity check failed: {}", result),
+                "Restore the newest verified backup",
+            )
+        }
+        Err(e) => {
+            return error(
+                id,
+                name,
+                &format!("Database is not readable: {}", e),
+                "Check the database file's permissions, or its encryption passphrase",
+            )
+        }
+    }
+    match db.get_settings() {
+        Ok(_) => ok(id, name, "Readable and passes the integrity check"),
+        Err(e) => error(
+            id,
+            name,
+            &format!("Settings can't be loaded: {}", e),
+            "Restart the app so migrations run again",
+        ),
+    }
+}
+
+/// Every migration has been applied
+pub fn check_migrations(db: &Database) -> SelfCheck {
+    let (id, name) = ("migrations", "Database schema");
+    match db.missing_migrations() {
+        Ok(missing) if missing.is_empty() => ok(id, name, "All migrations are applied"),
+        Ok(missing) => error(
+            id,
+            name,
+            &format!("Missing migrations: {}", missing.join(", ")),
+            "Restart the app so migrations run again",
+        ),
+        Err(e) => error(
+            id,
+            name,
+            &format!("Failed to read the schema: {}", e),
+            "Check that the database file isn't corrupted",
+        ),
+    }
+}
+
+/// Start `exe` as a read-only MCP server and complete the `initialize` handshake
+pub fn check_mcp_spawn(exe: &Path, db_path: &Path) -> SelfCheck {
+    let (id, name) = ("mcp_spawn", "MCP server");
+    let child = Command::new(exe)
+        .arg("--mcp")
+        .arg("--read-only")
+        .arg("--db-path")
+        .arg(db_path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(e) => {
+            return error(
+                id,
+                name,
+                &format!("Failed to start {}: {}", exe.display(), e),
+                "Reinstall the app, or check that antivirus isn't blocking it",
+            )
+        }
+    };
+
+    let result = handshake(&mut child);
+    let _ = child.kill();
+    let _ = child.wait();
+
+    match result {
+        Ok(version) => ok(
+            id,
+            name,
+            &format!("Started and answered initialize (protocol {})", version),
+        ),
+        Err(e) => error(
+            id,
+            name,
+            &e,
+            "Run the app with --mcp from a terminal to see the server's errors",
+        ),
+    }
+}
+
+/// Send `initialize` and return the protocol version from the reply
+fn handshake(child: &mut Child) -> Result<String, String> {
+    let mut stdin = child.stdin.take().ok_or("MCP server has no stdin")?;
+    let stdout = child.stdout.take().ok_or("MCP server has no stdout")?;
+
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "initialize",
+        "params": {
+            "protocolVersion": "2024-11-05",
+            "capabilities": {},
+            "clientInfo": { "name": "prompt-forge-self-check", "version": env!("CARGO_PKG_VERSION") }
+        }
+    });
+    writeln!(stdin, "{}", request)
+        .and_then(|_| stdin.flush())
+        .map_err(|e| format!("Failed to send initialize: {}", e))?;
+
+    // Read on a thread so a hung server can't hang the check
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut line = String::new();
+        let _ = BufReader::new(stdout).read_line(&mut line);
+        let _ = tx.send(line);
+    });
+    let line = rx.recv_timeout(HANDSHAKE_TIMEOUT).map_err(|_| {
+        format!(
+            "No reply to initialize within {}s",
+            HANDSHAKE_TIMEOUT.as_secs()
+        )
+    })?;
+    drop(stdin);
+
+    if line.trim().is_empty() {
+        return Err("MCP server exited before answering initialize".to_string());
+    }
+    let reply: Value = serde_json::from_str(line.trim())
+        .map_err(|e| format!("Unreadable reply to initialize: {}", e))?;
+    if let Some(message) = reply.pointer("/error/message").and_then(Value::as_str) {
+        return Err(format!("initialize failed: {}", message));
+    }
+    reply
+        .pointer("/result/protocolVersion")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| "Reply to initialize has no protocol version".to_string())
+}
+
+/// The configured MCP port is free to listen on
+pub fn check_port(port: u16) -> SelfCheck {
+    let (id, name) = ("port", "MCP port");
+    match TcpListener::bind(("127.0.0.1", port)) {
+        Ok(_) => ok(id, name, &format!("Port {} is available", port)),
+        Err(e) => warning(
+            id,
+            name,
+            &format!("Port {} can't be used: {}", port, e),
+            "Pick another port in Settings, or stop the program using this one",
+        ),
+    }
+}
+
+/// Files can be created in `dir`. A directory that doesn't exist yet passes
+/// when it could be created.
+pub fn check_writable_dir(id: &str, name: &str, dir: &Path) -> SelfCheck {
+    let existing = dir.ancestors().find(|p| p.is_dir());
+    let Some(existing) = existing else {
+        return error(
+            id,
+            name,
+            &format!("{} can't be created", dir.display()),
+            "Choose a directory on an existing drive",
+        );
+    };
+
+    let probe = existing.join(format!(".promptforge-check-{}", uuid::Uuid::new_v4()));
+    match fs::write(&probe, b"").and_then(|_| fs::remove_file(&probe)) {
+        Ok(()) if existing == dir => ok(id, name, &format!("{} is writable", dir.display())),
+        Ok(()) => ok(
+            id,
+            name,
+            &format!("{} will be created when first used", dir.display()),
+        ),
+        Err(e) => error(
+            id,
+            name,
+            &format!("Can't write to {}: {}", existing.display(), e),
+            "Fix the directory's permissions or choose another one",
+        ),
+    }
+}
+
+/// A config file we write into exists and can be written, if there is one
+pub fn check_writable_file(id: &str, name: &str, path: &Path) -> SelfCheck {
+    if !path.exists() {
+        return skipped(id, name, &format!("{} doesn't exist", path.display()));
+    }
+    match OpenOptions::new().append(true).open(path) {
+        Ok(_) => ok(id, name, &format!("{} is writable", path.display())),
+        Err(e) => error(
+            id,
+            name,
+            &format!("Can't write to {}: {}", path.display(), e),
+            "Fix the file's permissions, or close the app that has it locked",
+        ),
+    }
+}
+
+/// A client's registered MCP command is this app's executable, at a path
+/// that survives updates and restarts. `expected` is the command registering
+/// now would write.
+pub fn check_registered_command(
+    client: &str,
+    registered: &str,
+    expected: &str,
+    exe: &Path,
+) -> SelfCheck {
+    let id = format!("mcp_command:{}", client);
+    let name = format!("{} MCP command", client);
+    if registered != expected {
+        return warning(
+            &id,
+            &name,
+            &format!("Runs {}, but the app is now at {}", registered, expected),
+            "Register the client again from the setup wizard",
+        );
+    }
+    match setup::exe_path_problem(exe) {
+        Some(problem) => warning(
+            &id,
+            &name,
+            &problem,
+            "Install the app to a permanent location and register the client again",
+        ),
+        None => ok(&id, &name, &format!("Runs {}", registered)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_database_checks_and_report_counts() {
+        let db = Database::open(":memory:").unwrap();
+        db.migrate().unwrap();
+
+        let checks = vec![
+            check_database(&db),
+            check_migrations(&db),
+            warning(
+                "port",
+                "MCP port",
+                "Port 3333 can't be used",
+                "Pick another",
+            ),
+            skipped("provider", "LLM providers", "None configured"),
+        ];
+        assert_eq!(checks[0].status, "ok");
+        assert_eq!(checks[1].status, "ok");
+
+        let report = report(checks);
+        assert_eq!(report.errors, 0);
+        assert_eq!(report.warnings, 1);
+
+        let empty = Database::open(":memory:").unwrap();
+        let unmigrated = check_migrations(&empty);
+        assert_eq!(unmigrated.status, "error");
+        assert!(unmigrated.message.contains("001_initial_schema"));
+    }
+
+    #[test]
+    fn test_writable_checks() {
+        let dir = std::env::temp_dir().join(format!("pf-check-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+
+        assert_eq!(check_writable_dir("d", "Dir", &dir).status, "ok");
+        let later = check_writable_dir("d", "Dir", &dir.join("backups").join("daily"));
+        assert_eq!(later.status, "ok");
+        assert!(later.message.contains("will be created"));
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+
+        let config = dir.join("mcp.json");
+        assert_eq!(check_writable_file("f", "File", &config).status, "skipped");
+        fs::write(&config, "{}").unwrap();
+        assert_eq!(check_writable_file("f", "File", &config).status, "ok");
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
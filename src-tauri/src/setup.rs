@@ -0,0 +1,605 @@
+//! First-run setup
+//! The pieces the setup wizard strings together: bundled template packs to
+//! seed a library from, registering the MCP server with client apps, and
+//! turning an existing CLAUDE.md or AGENTS.md into instructions.
+//! `first_run_setup` runs each step the user asked for and reports them
+//! together.
+
+use crate::db::{self, Database};
+use crate::models::{
+    Agent, ClientRegistration, Instruction, InstructionCategory, SeedSummary, Skill,
+    SkillDefinition, SkillType, TemplatePackInfo,
+};
+use crate::parser;
+use serde_json::{json, Map, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Name of our entry under a client's `mcpServers`
+pub const SERVER_NAME: &str = "prompt-forge";
+
+/// Clients `register_client` knows the config file of. `claude-code-wsl` is
+/// Claude Code running inside WSL's default distribution, from Windows.
+pub const CLIENTS: &[&str] = &["claude-desktop", "claude-code", "claude-code-wsl", "cursor"];
+
+/// Operating systems with their own client config locations
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Platform {
+    Windows,
+    MacOs,
+    Linux,
+}
+
+impl Platform {
+    pub fn current() -> Self {
+        if cfg!(windows) {
+            Platform::Windows
+        } else if cfg!(target_os = "macos") {
+            Platform::MacOs
+        } else {
+            Platform::Linux
+        }
+    }
+}
+
+type Pack = (Vec<Agent>, Vec<Skill>, Vec<Instruction>);
+
+struct TemplatePack {
+    id: &'static str,
+    name: &'static str,
+    description: &'static str,
+    build: fn() -> Pack,
+}
+
+const PACKS: &[TemplatePack] = &[
+    TemplatePack {
+        id: "starter",
+        name: "Starter",
+        description: "A general assistant with code review and explanation skills",
+        build: starter_pack,
+    },
+    TemplatePack {
+        id: "engineering",
+        name: "Engineering team",
+        description: "The starter pack plus a reviewer agent and testing and security guidelines",
+        build: engineering_pack,
+    },
+];
+
+/// The bundled template packs
+pub fn template_packs() -> Vec<TemplatePackInfo> {
+    PACKS
+        .iter()
+        .map(|pack| {
+            let (agents, skills, instructions) = (pack.build)();
+            TemplatePackInfo {
+                id: pack.id.to_string(),
+                name: pack.name.to_string(),
+                description: pack.description.to_string(),
+                agents: agents.len(),
+                skills: skills.len(),
+                instructions: instructions.len(),
+            }
+        })
+        .collect()
+}
+
+/// Add a template pack's entities to the library. Pack entities have fixed
+/// IDs, so any already present (from the defaults or an earlier seed) are
+/// left as the user has them.
+pub fn seed(db: &Database, pack_id: &str) -> Result<SeedSummary, String> {
+    let pack = PACKS
+        .iter()
+        .find(|p| p.id.eq_ignore_ascii_case(pack_id.trim()))
+        .ok_or_else(|| format!("Unknown template pack '{}'", pack_id))?;
+    let (agents, skills, instructions) = (pack.build)();
+    let mut summary = SeedSummary {
+        pack: pack.id.to_string(),
+        added: 0,
+        skipped: 0,
+    };
+
+    for skill in &skills {
+        let exists = db
+            .get_skill(&skill.id)
+            .map_err(|e| format!("Failed to get skill: {}", e))?
+            .is_some();
+        if exists {
+            summary.skipped += 1;
+        } else {
+            db.insert_skill(skill)
+                .map_err(|e| format!("Failed to add skill '{}': {}", skill.name, e))?;
+            summary.added += 1;
+        }
+    }
+    for instruction in &instructions {
+        let exists = db
+            .get_instruction(&instruction.id)
+            .map_err(|e| format!("Failed to get instruction: {}", e))?
+            .is_some();
+        if exists {
+            summary.skipped += 1;
+        } else {
+            db.insert_instruction(instruction)
+                .map_err(|e| format!("Failed to add instruction '{}': {}", instruction.name, e))?;
+            summary.added += 1;
+        }
+    }
+    for agent in &agents {
+        let exists = db
+            .get_agent(&agent.id)
+            .map_err(|e| format!("Failed to get agent: {}", e))?
+            .is_some();
+        if exists {
+            summary.skipped += 1;
+        } else {
+            db.insert_agent(agent)
+                .map_err(|e| format!("Failed to add agent '{}': {}", agent.name, e))?;
+            summary.added += 1;
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Where a client keeps its MCP server list on this machine
+pub fn client_config_path(client: &str) -> Result<PathBuf, String> {
+    if client == "claude-code-wsl" {
+        return wsl_claude_code_config();
+    }
+    let home = dirs::home_dir().ok_or("Couldn't find the home directory")?;
+    let env = |name: &str| std::env::var_os(name).map(PathBuf::from);
+    client_config_path_on(Platform::current(), client, &home, env)
+}
+
+/// Where a client keeps its MCP server list on `platform`, given the home
+/// directory and a lookup for `APPDATA` and `XDG_CONFIG_HOME`
+pub fn client_config_path_on(
+    platform: Platform,
+    client: &str,
+    home: &Path,
+    env: impl Fn(&str) -> Option<PathBuf>,
+) -> Result<PathBuf, String> {
+    match client {
+        "claude-desktop" => {
+            let config_dir = match platform {
+                Platform::Windows => {
+                    env("APPDATA").unwrap_or_else(|| home.join("AppData").join("Roaming"))
+                }
+                Platform::MacOs => home.join("Library").join("Application Support"),
+                Platform::Linux => env("XDG_CONFIG_HOME")
+                    .filter(|dir| dir.is_absolute())
+                    .unwrap_or_else(|| home.join(".config")),
+            };
+            Ok(config_dir.join("Claude").join("claude_desktop_config.json"))
+        }
+        "claude-code" => Ok(home.join(".claude.json")),
+        "claude-code-wsl" if platform != Platform::Windows => {
+            Err("claude-code-wsl is only available on Windows".to_string())
+        }
+        "claude-code-wsl" => Err("Use client_config_path to find WSL's config".to_string()),
+        "cursor" => Ok(home.join(".cursor").join("mcp.json")),
+        other => Err(format!(
+            "Unknown MCP client '{}': use one of {}",
+            other,
+            CLIENTS.join(", ")
+        )),
+    }
+}
+
+/// `~/.claude.json` inside WSL's default distribution, as a Windows path
+fn wsl_claude_code_config() -> Result<PathBuf, String> {
+    if Platform::current() != Platform::Windows {
+        return Err("claude-code-wsl is only available on Windows".to_string());
+    }
+    let output = std::process::Command::new("wsl.exe")
+        .args(["-e", "sh", "-c", "wslpath -w \"$HOME/.claude.json\""])
+        .output()
+        .map_err(|e| format!("Failed to run wsl.exe: {}", e))?;
+    let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if !output.status.success() || path.is_empty() {
+        return Err("WSL isn't installed or has no default distribution".to_string());
+    }
+    Ok(PathBuf::from(path))
+}
+
+/// A Windows path as WSL sees it: `C:\Users\me` becomes `/mnt/c/Users/me`.
+/// Paths without a drive letter are returned unchanged.
+pub fn windows_to_wsl_path(path: &str) -> String {
+    let mut chars = path.chars();
+    match (chars.next(), chars.next(), chars.next()) {
+        (Some(drive), Some(':'), Some('\\' | '/')) if drive.is_ascii_alphabetic() => format!(
+            "/mnt/{}/{}",
+            drive.to_ascii_lowercase(),
+            path[3..].replace('\\', "/")
+        ),
+        _ => path.to_string(),
+    }
+}
+
+/// The `mcpServers` entry for `client`. WSL starts the Windows executable
+/// through its `/mnt` path; the executable still gets Windows paths as
+/// arguments.
+pub fn client_server_entry(client: &str, exe: &Path, db_path: &Path, read_only: bool) -> Value {
+    let mut entry = server_entry(exe, db_path, read_only);
+    if client == "claude-code-wsl" {
+        entry["command"] = json!(windows_to_wsl_path(&exe.to_string_lossy()));
+    }
+    entry
+}
+
+/// Why `exe` may stop working as a registered MCP command after an update or
+/// restart: it's missing, or it runs from a location the OS or the updater
+/// replaces (a temporary directory, a mounted AppImage, macOS App
+/// Translocation)
+pub fn exe_path_problem(exe: &Path) -> Option<String> {
+    let path = exe.to_string_lossy();
+    let normalized = path.replace('\\', "/");
+    if normalized.contains("/AppTranslocation/") {
+        return Some(format!(
+            "{} is a translocated copy macOS removes on restart; move Prompt Forge to Applications",
+            path
+        ));
+    }
+    if normalized.contains("/.mount_") {
+        return Some(format!(
+            "{} is inside a mounted AppImage that changes on every launch",
+            path
+        ));
+    }
+    let temp = std::env::temp_dir();
+    if exe.starts_with(&temp) || normalized.to_lowercase().contains("/appdata/local/temp/") {
+        return Some(format!("{} is in a temporary directory", path));
+    }
+    if !exe.exists() {
+        return Some(format!("{} doesn't exist", path));
+    }
+    None
+}
+
+/// The command our entry in the client config at `config_path` runs, if the
+/// server is registered there
+pub fn registered_command(config_path: &Path) -> Option<String> {
+    let text = fs::read_to_string(config_path).ok()?;
+    let config: Value = serde_json::from_str(&text).ok()?;
+    config["mcpServers"][SERVER_NAME]["command"]
+        .as_str()
+        .map(str::to_string)
+}
+
+/// The `mcpServers` entry that starts this executable as an MCP server
+pub fn server_entry(exe: &Path, db_path: &Path, read_only: bool) -> Value {
+    let mut args = vec![
+        "--mcp".to_string(),
+        "--db-path".to_string(),
+        db_path.to_string_lossy().to_string(),
+    ];
+    if read_only {
+        args.push("--read-only".to_string());
+    }
+    json!({
+        "command": exe.to_string_lossy(),
+        "args": args,
+    })
+}
+
+/// Add or replace our entry in the client config at `config_path`, keeping
+/// everything else in the file
+pub fn register_client(
+    client: &str,
+    config_path: &Path,
+    entry: Value,
+) -> Result<ClientRegistration, String> {
+    let mut config = match fs::read_to_string(config_path) {
+        Ok(text) if !text.trim().is_empty() => serde_json::from_str::<Value>(&text)
+            .map_err(|e| format!("{} isn't valid JSON: {}", config_path.display(), e))?,
+        _ => Value::Object(Map::new()),
+    };
+    let servers = config
+        .as_object_mut()
+        .ok_or_else(|| format!("{} isn't a JSON object", config_path.display()))?
+        .entry("mcpServers")
+        .or_insert_with(|| Value::Object(Map::new()))
+        .as_object_mut()
+        .ok_or_else(|| format!("'mcpServers' in {} isn't an object", config_path.display()))?;
+    let replaced = servers.insert(SERVER_NAME.to_string(), entry).is_some();
+
+    if let Some(parent) = config_path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    let text = serde_json::to_string_pretty(&config).unwrap();
+    fs::write(config_path, text + "\n")
+        .map_err(|e| format!("Failed to write {}: {}", config_path.display(), e))?;
+
+    Ok(ClientRegistration {
+        client: client.to_string(),
+        config_path: config_path.to_string_lossy().to_string(),
+        replaced,
+    })
+}
+
+/// Read a CLAUDE.md as a single pinned instruction. Without a `# ` title the
+/// instruction is named after the project directory it sits in.
+pub fn claude_md_instruction(path: &Path) -> Result<Instruction, String> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    if text.trim().is_empty() {
+        return Err(format!("{} is empty", path.display()));
+    }
+
+    let mut instruction = parser::parse_instruction_from_markdown(&text)?;
+    if instruction.name == Instruction::default().name {
+        let project = path
+            .parent()
+            .and_then(|p| p.file_name())
+            .map(|n| n.to_string_lossy().to_string());
+        instruction.name = match project {
+            Some(project) => format!("{} project guidelines", project),
+            None => "Project guidelines".to_string(),
+        };
+    }
+    instruction.description = format!("Imported from {}", path.display());
+    instruction.tags = vec!["claude-md".to_string()];
+    instruction.pin_to_context = true;
+    Ok(instruction)
+}
+
+/// Guidance files coding agents read from a repository root
+pub const PROJECT_FILES: &[&str] = &["CLAUDE.md", "AGENTS.md"];
+
+/// Read a repository's CLAUDE.md or AGENTS.md as one instruction per section.
+/// `path` is either the file or the repository root, in which case every
+/// project file found there is read.
+pub fn project_instructions(path: &Path) -> Result<Vec<Instruction>, String> {
+    let files: Vec<PathBuf> = if path.is_dir() {
+        PROJECT_FILES
+            .iter()
+            .map(|name| path.join(name))
+            .filter(|file| file.is_file())
+            .collect()
+    } else {
+        vec![path.to_path_buf()]
+    };
+    if files.is_empty() {
+        return Err(format!(
+            "No {} found in {}",
+            PROJECT_FILES.join(" or "),
+            path.display()
+        ));
+    }
+
+    let mut instructions = Vec::new();
+    for file in files {
+        let text = fs::read_to_string(&file)
+            .map_err(|e| format!("Failed to read {}: {}", file.display(), e))?;
+        let tag = file
+            .file_stem()
+            .map(|stem| format!("{}-md", stem.to_string_lossy().to_lowercase()))
+            .unwrap_or_default();
+        for mut instruction in parser::parse_project_instructions(&text) {
+            instruction.description = format!("Imported from {}", file.display());
+            instruction.tags = vec![tag.clone()];
+            instructions.push(instruction);
+        }
+    }
+    if instructions.is_empty() {
+        return Err(format!("{} has no content to import", path.display()));
+    }
+    Ok(instructions)
+}
+
+fn starter_pack() -> Pack {
+    (
+        vec![db::create_default_agent()],
+        db::create_default_skills(),
+        db::create_default_instructions(),
+    )
+}
+
+fn engineering_pack() -> Pack {
+    let (mut agents, mut skills, mut instructions) = starter_pack();
+
+    skills.push(Skill {
+        id: "write-tests".to_string(),
+        name: "Write Tests".to_string(),
+        description: "Write focused tests for a piece of code".to_string(),
+        icon_emoji: "🧪".to_string(),
+        skill_type: SkillType::Prompt,
+        definition: SkillDefinition::Prompt {
+            template: "Write tests for the following code:\n- Cover the main behaviour and the edge cases\n- One behaviour per test, named after what it checks\n- Follow the project's existing test layout and helpers".to_string(),
+            variables: Vec::new(),
+            follows: Vec::new(),
+        },
+        ..Skill::default()
+    });
+
+    instructions.push(Instruction {
+        id: "testing".to_string(),
+        name: "Testing Practices".to_string(),
+        description: "How changes are tested".to_string(),
+        icon_emoji: "🧪".to_string(),
+        category: InstructionCategory::Testing,
+        content: r#"# Testing Practices
+
+- Add or update tests with every behaviour change
+- Prefer small, deterministic tests over broad end-to-end ones
+- Reproduce a bug in a failing test before fixing it
+- Don't weaken an existing test to make it pass"#
+            .to_string(),
+        priority: 6,
+        tags: vec!["testing".to_string()],
+        ..Instruction::default()
+    });
+    instructions.push(Instruction {
+        id: "security".to_string(),
+        name: "Security Guidelines".to_string(),
+        description: "Baseline security rules for code changes".to_string(),
+        icon_emoji: "🔒".to_string(),
+        category: InstructionCategory::Security,
+        content: r#"# Security Guidelines
+
+- Never commit credentials, tokens or private keys
+- Validate and escape all external input
+- Use parameterized queries for database access
+- Flag dependencies with known vulnerabilities"#
+            .to_string(),
+        priority: 9,
+        tags: vec!["security".to_string()],
+        ..Instruction::default()
+    });
+
+    agents.push(Agent {
+        id: "reviewer".to_string(),
+        name: "Reviewer".to_string(),
+        description: "A careful code reviewer focused on correctness, tests and security"
+            .to_string(),
+        avatar_emoji: "🧐".to_string(),
+        system_prompt: "You are a meticulous senior engineer reviewing changes before they merge. Point out bugs, missing tests and security problems first, then style.".to_string(),
+        skills: vec!["code-review".to_string(), "write-tests".to_string()],
+        instructions: vec![
+            "code-style".to_string(),
+            "testing".to_string(),
+            "security".to_string(),
+        ],
+        tags: vec!["review".to_string()],
+        ..Agent::default()
+    });
+
+    (agents, skills, instructions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_default_data;
+
+    #[test]
+    fn test_seed_skips_entities_already_in_library() {
+        let db = Database::open(":memory:").unwrap();
+        db.migrate().unwrap();
+        init_default_data(&db).unwrap();
+
+        let starter = seed(&db, "starter").unwrap();
+        assert_eq!(starter.added, 0);
+        assert_eq!(starter.skipped, 5);
+
+        let engineering = seed(&db, "Engineering").unwrap();
+        assert_eq!(engineering.added, 4);
+        assert!(db.get_agent("reviewer").unwrap().is_some());
+        assert!(seed(&db, "missing").is_err());
+
+        let packs = template_packs();
+        assert_eq!(packs[1].agents + packs[1].skills + packs[1].instructions, 9);
+    }
+
+    #[test]
+    fn test_register_client_keeps_other_servers() {
+        let dir = std::env::temp_dir().join(format!("pf-setup-{}", uuid::Uuid::new_v4()));
+        let config = dir.join("nested").join("mcp.json");
+        let entry = server_entry(Path::new("/opt/pf"), Path::new("/data/pf.db"), true);
+
+        let first = register_client("cursor", &config, entry.clone()).unwrap();
+        assert!(!first.replaced);
+
+        let mut existing: Value =
+            serde_json::from_str(&fs::read_to_string(&config).unwrap()).unwrap();
+        existing["mcpServers"]["other"] = json!({ "command": "other" });
+        existing["theme"] = json!("dark");
+        fs::write(&config, existing.to_string()).unwrap();
+
+        let second = register_client("cursor", &config, entry).unwrap();
+        assert!(second.replaced);
+        let written: Value = serde_json::from_str(&fs::read_to_string(&config).unwrap()).unwrap();
+        assert_eq!(written["theme"], "dark");
+        assert_eq!(written["mcpServers"]["other"]["command"], "other");
+        assert_eq!(written["mcpServers"][SERVER_NAME]["args"][3], "--read-only");
+
+        fs::write(&config, "[]").unwrap();
+        assert!(register_client("cursor", &config, json!({})).is_err());
+        assert!(client_config_path("vscode").is_err());
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_client_paths_per_platform_and_wsl_entry() {
+        let home = Path::new("/home/me");
+        let no_env = |_: &str| None;
+        let desktop = |platform| client_config_path_on(platform, "claude-desktop", home, no_env);
+        assert_eq!(
+            desktop(Platform::MacOs).unwrap(),
+            home.join("Library/Application Support/Claude/claude_desktop_config.json")
+        );
+        assert_eq!(
+            desktop(Platform::Linux).unwrap(),
+            home.join(".config/Claude/claude_desktop_config.json")
+        );
+        let appdata = client_config_path_on(Platform::Windows, "claude-desktop", home, |name| {
+            (name == "APPDATA").then(|| PathBuf::from("/roaming"))
+        });
+        assert_eq!(
+            appdata.unwrap(),
+            Path::new("/roaming/Claude/claude_desktop_config.json")
+        );
+        for platform in [Platform::Windows, Platform::MacOs, Platform::Linux] {
+            assert_eq!(
+                client_config_path_on(platform, "cursor", home, no_env).unwrap(),
+                home.join(".cursor/mcp.json")
+            );
+        }
+        assert!(client_config_path_on(Platform::Linux, "claude-code-wsl", home, no_env).is_err());
+
+        assert_eq!(
+            windows_to_wsl_path(r"C:\Program Files\Prompt Forge\prompt-forge.exe"),
+            "/mnt/c/Program Files/Prompt Forge/prompt-forge.exe"
+        );
+        assert_eq!(windows_to_wsl_path("/usr/bin/pf"), "/usr/bin/pf");
+        let exe = Path::new(r"D:\Apps\pf.exe");
+        let db = Path::new(r"D:\Data\pf.db");
+        let wsl = client_server_entry("claude-code-wsl", exe, db, false);
+        assert_eq!(wsl["command"], "/mnt/d/Apps/pf.exe");
+        assert_eq!(wsl["args"][2], r"D:\Data\pf.db");
+        assert_eq!(
+            client_server_entry("cursor", exe, db, false)["command"],
+            r"D:\Apps\pf.exe"
+        );
+
+        assert!(exe_path_problem(Path::new(
+            "/private/var/folders/x/T/AppTranslocation/1/d/Prompt Forge.app/Contents/MacOS/pf"
+        ))
+        .unwrap()
+        .contains("Applications"));
+        assert!(exe_path_problem(&std::env::temp_dir().join(".mount_pf/pf")).is_some());
+        assert!(exe_path_problem(Path::new("/nonexistent/pf"))
+            .unwrap()
+            .contains("doesn't exist"));
+    }
+
+    #[test]
+    fn test_project_instructions_from_repository_root() {
+        let dir = std::env::temp_dir().join(format!("pf-project-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        assert!(project_instructions(&dir)
+            .unwrap_err()
+            .contains("CLAUDE.md"));
+
+        fs::write(
+            dir.join("CLAUDE.md"),
+            "# Repo\n\n## Code style\nfour spaces\n\n## Testing\ncargo test\n",
+        )
+        .unwrap();
+        fs::write(dir.join("AGENTS.md"), "## Security\nno secrets in logs\n").unwrap();
+
+        let instructions = project_instructions(&dir).unwrap();
+        let names: Vec<&str> = instructions.iter().map(|i| i.name.as_str()).collect();
+        assert_eq!(names, vec!["Code style", "Testing", "Security"]);
+        assert_eq!(instructions[0].tags, vec!["claude-md"]);
+        assert_eq!(instructions[2].tags, vec!["agents-md"]);
+
+        let single = project_instructions(&dir.join("AGENTS.md")).unwrap();
+        assert_eq!(single.len(), 1);
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,215 @@
+//! Reusable prompt snippets
+//! A snippet is a small block of text, such as "Reply with JSON only", that
+//! system prompts, prompt skill templates and instructions include with
+//! `{{> snippet-id}}`. The stored items keep their includes; they are expanded
+//! when the library is loaded for composition, so assembled prompts, tests,
+//! exports and releases get the snippet's current text. Snippets can include
+//! other snippets. An include that names a missing snippet or loops back on
+//! itself is left as written.
+
+use crate::composition::{self, CompositionError, ReferenceGraph};
+use crate::models::{Agent, Instruction, Skill, SkillDefinition, Snippet};
+use chrono::{DateTime, Utc};
+use regex::{Captures, Regex};
+use std::sync::OnceLock;
+
+fn include_pattern() -> &'static Regex {
+    static INCLUDE: OnceLock<Regex> = OnceLock::new();
+    INCLUDE.get_or_init(|| Regex::new(r"\{\{>\s*([A-Za-z0-9_.\-]+)\s*\}\}").unwrap())
+}
+
+/// Ids of the snippets `text` includes directly, in order of first use
+pub fn includes(text: &str) -> Vec<String> {
+    let mut ids: Vec<String> = Vec::new();
+    for caps in include_pattern().captures_iter(text) {
+        if !ids.iter().any(|id| id == &caps[1]) {
+            ids.push(caps[1].to_string());
+        }
+    }
+    ids
+}
+
+/// `text` with its includes expanded, and when the latest of the snippets it
+/// included was edited
+pub fn expand(text: &str, snippets: &[Snippet]) -> (String, Option<DateTime<Utc>>) {
+    let mut latest = None;
+    let expanded = expand_into(text, snippets, &mut Vec::new(), &mut latest);
+    (expanded, latest)
+}
+
+fn expand_into(
+    text: &str,
+    snippets: &[Snippet],
+    chain: &mut Vec<String>,
+    latest: &mut Option<DateTime<Utc>>,
+) -> String {
+    include_pattern()
+        .replace_all(text, |caps: &Captures| {
+            let id = &caps[1];
+            let snippet = snippets.iter().find(|s| s.id == id);
+            let looped = chain.iter().any(|c| c == id) || chain.len() >= composition::MAX_DEPTH;
+            let Some(snippet) = snippet.filter(|_| !looped) else {
+                return caps[0].to_string();
+            };
+            *latest = (*latest).max(Some(snippet.updated_at));
+            chain.push(id.to_string());
+            let expanded = expand_into(snippet.content.trim(), snippets, chain, latest);
+            chain.pop();
+            expanded
+        })
+        .into_owned()
+}
+
+/// Expand the includes in every system prompt, prompt skill template and
+/// instruction. An item counts as edited whenever a snippet it includes was,
+/// so caches keyed on edit times pick up snippet changes.
+pub fn expand_library(
+    snippets: &[Snippet],
+    agents: &mut [Agent],
+    skills: &mut [Skill],
+    instructions: &mut [Instruction],
+) {
+    if snippets.is_empty() {
+        return;
+    }
+    for agent in agents {
+        expand_field(&mut agent.system_prompt, &mut agent.updated_at, snippets);
+    }
+    for skill in skills {
+        if let SkillDefinition::Prompt { template, .. } = &mut skill.definition {
+            expand_field(template, &mut skill.updated_at, snippets);
+        }
+    }
+    for instruction in instructions {
+        expand_field(
+            &mut instruction.content,
+            &mut instruction.updated_at,
+            snippets,
+        );
+    }
+}
+
+fn expand_field(text: &mut String, updated_at: &mut DateTime<Utc>, snippets: &[Snippet]) {
+    if !include_pattern().is_match(text) {
+        return;
+    }
+    let (expanded, latest) = expand(text, snippets);
+    *text = expanded;
+    if let Some(latest) = latest {
+        *updated_at = (*updated_at).max(latest);
+    }
+}
+
+/// The snippets the given items include, directly or through other snippets
+pub fn used(
+    snippets: &[Snippet],
+    agents: &[Agent],
+    skills: &[Skill],
+    instructions: &[Instruction],
+) -> Vec<Snippet> {
+    let templates = skills.iter().filter_map(|s| match &s.definition {
+        SkillDefinition::Prompt { template, .. } => Some(template),
+        _ => None,
+    });
+    let mut wanted: Vec<String> = agents
+        .iter()
+        .map(|a| &a.system_prompt)
+        .chain(templates)
+        .chain(instructions.iter().map(|i| &i.content))
+        .flat_map(|text| includes(text))
+        .collect();
+    let mut used: Vec<Snippet> = Vec::new();
+    while let Some(id) = wanted.pop() {
+        if used.iter().any(|s| s.id == id) {
+            continue;
+        }
+        if let Some(snippet) = snippets.iter().find(|s| s.id == id) {
+            wanted.extend(includes(&snippet.content));
+            used.push(snippet.clone());
+        }
+    }
+    used
+}
+
+/// Refuse a snippet whose includes loop back to it or nest too deep
+pub fn check(snippet: &Snippet, snippets: &[Snippet]) -> Result<(), String> {
+    let mut all: Vec<&Snippet> = snippets.iter().filter(|s| s.id != snippet.id).collect();
+    all.push(snippet);
+    let errors = composition::check(&SnippetGraph(all), &snippet.id);
+    match errors
+        .iter()
+        .find(|e| !matches!(e, CompositionError::Missing { .. }))
+    {
+        Some(error) => Err(format!("Can't save snippet '{}': {}", snippet.name, error)),
+        None => Ok(()),
+    }
+}
+
+/// Snippets and the snippets they include, keyed by id
+struct SnippetGraph<'a>(Vec<&'a Snippet>);
+
+impl ReferenceGraph for SnippetGraph<'_> {
+    fn references(&self, node: &str) -> Option<Vec<String>> {
+        let snippet = self.0.iter().find(|s| s.id == node)?;
+        Some(includes(&snippet.content))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn snippet(id: &str, content: &str) -> Snippet {
+        Snippet {
+            id: id.to_string(),
+            name: id.to_string(),
+            description: String::new(),
+            content: content.to_string(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_includes_are_expanded_at_composition() {
+        let mut json = snippet("json-output", "Reply with JSON only.\n");
+        json.updated_at = Utc::now() + Duration::hours(1);
+        let snippets = vec![
+            json.clone(),
+            snippet("api-rules", "{{> json-output}} Never invent fields."),
+            snippet("loop", "{{>loop}}"),
+        ];
+
+        let (text, latest) = expand("Rules: {{> api-rules }} {{> missing}}", &snippets);
+        assert_eq!(
+            text,
+            "Rules: Reply with JSON only. Never invent fields. {{> missing}}"
+        );
+        assert_eq!(latest, Some(json.updated_at));
+        assert_eq!(expand("{{> loop}}", &snippets).0, "{{>loop}}");
+
+        let mut instructions = vec![Instruction {
+            content: "{{> json-output}}".to_string(),
+            ..Instruction::default()
+        }];
+        let agents = vec![Agent {
+            system_prompt: "{{> api-rules}}".to_string(),
+            ..Agent::default()
+        }];
+        let used: Vec<String> = used(&snippets, &agents, &[], &instructions)
+            .into_iter()
+            .map(|s| s.id)
+            .collect();
+        assert_eq!(used, ["json-output", "api-rules"]);
+
+        expand_library(&snippets, &mut [], &mut [], &mut instructions);
+        assert_eq!(instructions[0].content, "Reply with JSON only.");
+        assert_eq!(instructions[0].updated_at, json.updated_at);
+
+        assert!(check(&snippets[1], &snippets).is_ok());
+        assert!(check(&snippets[2], &snippets).is_err());
+        let edited = snippet("json-output", "{{> api-rules}}");
+        assert!(check(&edited, &snippets).is_err());
+    }
+}
@@ -0,0 +1,142 @@
+//! Claude Code subagents
+//! Renders an agent as a `.claude/agents/<name>.md` file: YAML frontmatter
+//! with the subagent's name, description and tools, followed by the agent's
+//! assembled prompt. Tool skills the agent uses are named as tools of the
+//! Prompt Forge MCP server, so the subagent can call them once the server is
+//! registered with Claude Code.
+
+use crate::assembly::{self, AssemblyOptions};
+use crate::models::{Agent, Instruction, Skill, SkillDefinition};
+use crate::resolve::{resolve_entity, slugify};
+use crate::setup;
+use crate::tool_schema;
+
+/// Subagent names are lowercase letters, digits and hyphens
+pub fn subagent_name(agent: &Agent) -> String {
+    let name = slugify(&agent.name);
+    if name.is_empty() {
+        "prompt-forge-agent".to_string()
+    } else {
+        name
+    }
+}
+
+/// `mcp__prompt-forge__<tool>` for each attached tool skill the MCP server
+/// runs: enabled and with a handler
+pub fn mcp_tools(agent: &Agent, skills: &[Skill]) -> Vec<String> {
+    agent
+        .skills
+        .iter()
+        .filter_map(|reference| resolve_entity(skills, reference).ok())
+        .filter(|skill| match &skill.definition {
+            SkillDefinition::Tool { handler, .. } => skill.enabled && !handler.trim().is_empty(),
+            _ => false,
+        })
+        .map(|skill| {
+            format!(
+                "mcp__{}__{}",
+                setup::SERVER_NAME,
+                tool_schema::mcp_tool_name(&skill.name)
+            )
+        })
+        .collect()
+}
+
+/// The subagent file for `agent`. Claude Code gives a subagent without a
+/// `tools` line every tool, so the line is only written when `builtin_tools`
+/// (e.g. `Read`, `Grep`) narrows it down; the agent's MCP tools are added to
+/// that list.
+pub fn export_subagent(
+    agent: &Agent,
+    skills: &[Skill],
+    instructions: &[Instruction],
+    builtin_tools: &[String],
+) -> String {
+    let description = if agent.description.trim().is_empty() {
+        format!("{} (exported from Prompt Forge)", agent.name)
+    } else {
+        agent
+            .description
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+
+    let mut output = String::from("---\n");
+    output.push_str(&format!("name: {}\n", subagent_name(agent)));
+    output.push_str(&format!(
+        "description: {}\n",
+        serde_json::to_string(&description).unwrap()
+    ));
+    if !builtin_tools.is_empty() {
+        let mut tools: Vec<String> = builtin_tools
+            .iter()
+            .map(|tool| tool.trim().to_string())
+            .filter(|tool| !tool.is_empty())
+            .collect();
+        tools.extend(mcp_tools(agent, skills));
+        output.push_str(&format!("tools: {}\n", tools.join(", ")));
+    }
+    output.push_str("---\n\n");
+
+    let prompt = assembly::agent_prompt(agent, skills, instructions, &AssemblyOptions::default());
+    output.push_str(prompt.trim());
+    output.push('\n');
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{SkillType, ToolParameter};
+
+    #[test]
+    fn test_tools_line_only_when_narrowed() {
+        let search = Skill {
+            id: "search".to_string(),
+            name: "Search Docs".to_string(),
+            skill_type: SkillType::Tool,
+            definition: SkillDefinition::Tool {
+                parameters: vec![ToolParameter {
+                    name: "query".to_string(),
+                    description: String::new(),
+                    param_type: "string".to_string(),
+                    required: true,
+                    default: None,
+                    schema: None,
+                }],
+                handler: "./search.sh".to_string(),
+                sandbox: None,
+            },
+            ..Skill::default()
+        };
+        let agent = Agent {
+            name: "Docs Helper!".to_string(),
+            description: "Answers questions\nabout the docs".to_string(),
+            skills: vec!["search".to_string(), "missing".to_string()],
+            ..Agent::default()
+        };
+        let skills = vec![search];
+
+        let inherit = export_subagent(&agent, &skills, &[], &[]);
+        assert!(inherit.starts_with(
+            "---\nname: docs-helper\ndescription: \"Answers questions about the docs\"\n---\n\n"
+        ));
+        assert!(!inherit.contains("tools:"));
+
+        let narrowed = export_subagent(
+            &agent,
+            &skills,
+            &[],
+            &["Read".to_string(), " Grep ".to_string()],
+        );
+        assert!(narrowed.contains("\ntools: Read, Grep, mcp__prompt-forge__search_docs\n"));
+        assert_eq!(
+            subagent_name(&Agent {
+                name: "🤖".to_string(),
+                ..Agent::default()
+            }),
+            "prompt-forge-agent"
+        );
+    }
+}
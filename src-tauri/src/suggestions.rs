@@ -0,0 +1,253 @@
+//! Skills and instructions suggested for the files a task touches
+//! The context is a file path, a bare extension or a unified diff. Enabled
+//! instructions whose file pattern matches one of its files rank first; then
+//! skills and instructions are searched for the languages and kinds of file
+//! involved (`rust` for `.rs`, `test` for files under a tests directory).
+
+use crate::models::{ContextSuggestion, Instruction, Skill};
+use crate::scope;
+use crate::search;
+use std::collections::HashMap;
+
+/// Score of an instruction whose file pattern matches the context
+const FILE_PATTERN_WEIGHT: i64 = 50;
+
+/// Search keywords for file extensions. Search matches within words, so
+/// Go is looked up as `golang` and plain C is left out.
+const LANGUAGES: &[(&str, &str)] = &[
+    ("cc", "c++"),
+    ("cpp", "c++"),
+    ("cs", "c#"),
+    ("css", "css"),
+    ("go", "golang"),
+    ("hpp", "c++"),
+    ("html", "html"),
+    ("java", "java"),
+    ("js", "javascript"),
+    ("jsx", "react"),
+    ("kt", "kotlin"),
+    ("md", "markdown"),
+    ("php", "php"),
+    ("py", "python"),
+    ("rb", "ruby"),
+    ("rs", "rust"),
+    ("scss", "css"),
+    ("sh", "shell"),
+    ("sql", "sql"),
+    ("svelte", "svelte"),
+    ("swift", "swift"),
+    ("toml", "toml"),
+    ("ts", "typescript"),
+    ("tsx", "react"),
+    ("vue", "vue"),
+    ("yaml", "yaml"),
+    ("yml", "yaml"),
+];
+
+/// Search keywords for well-known file names
+const FILE_NAMES: &[(&str, &str)] = &[
+    ("cargo.toml", "rust"),
+    ("dockerfile", "docker"),
+    ("package.json", "javascript"),
+    ("tsconfig.json", "typescript"),
+];
+
+/// Files named by a path, a bare extension (`rs`, `.rs`) or a unified diff
+pub fn context_paths(path: Option<&str>, diff: Option<&str>) -> Vec<String> {
+    let mut paths: Vec<String> = Vec::new();
+    if let Some(path) = path.map(str::trim).filter(|p| !p.is_empty()) {
+        let extension = path.strip_prefix('.').unwrap_or(path);
+        let is_extension = !extension.contains(['.', '/', '\\'])
+            && (path.starts_with('.') || language(extension).is_some());
+        paths.push(if is_extension {
+            format!("file.{}", extension)
+        } else {
+            path.to_string()
+        });
+    }
+    for path in diff.map(diff_paths).unwrap_or_default() {
+        if !paths.contains(&path) {
+            paths.push(path);
+        }
+    }
+    paths
+}
+
+/// Files a unified diff changes, from its `+++` lines, or its `diff --git`
+/// headers when those are missing
+pub fn diff_paths(diff: &str) -> Vec<String> {
+    let mut paths: Vec<String> = Vec::new();
+    let mut add = |path: &str| {
+        let path = path.split('\t').next().unwrap_or(path).trim();
+        let path = path.strip_prefix("b/").unwrap_or(path);
+        if !path.is_empty() && path != "/dev/null" && !paths.iter().any(|p| p == path) {
+            paths.push(path.to_string());
+        }
+    };
+    let added: Vec<&str> = diff
+        .lines()
+        .filter_map(|line| line.strip_prefix("+++ "))
+        .collect();
+    if added.is_empty() {
+        diff.lines()
+            .filter_map(|line| line.strip_prefix("diff --git "))
+            .filter_map(|header| header.rsplit_once(" b/").map(|(_, path)| path))
+            .for_each(&mut add);
+    } else {
+        added.into_iter().for_each(&mut add);
+    }
+    paths
+}
+
+/// Search keywords describing the given files
+pub fn context_keywords(paths: &[String]) -> Vec<String> {
+    let mut keywords: Vec<String> = Vec::new();
+    for path in paths {
+        let path = path.replace('\\', "/").to_lowercase();
+        let name = path.rsplit('/').next().unwrap_or(&path);
+        let extension = name.rsplit_once('.').map(|(_, ext)| ext);
+        let is_test = path
+            .split('/')
+            .any(|segment| matches!(segment, "test" | "tests" | "spec" | "__tests__"))
+            || name.contains(".test.")
+            || name.contains(".spec.")
+            || name.contains("_test.");
+
+        let found = FILE_NAMES
+            .iter()
+            .find(|(file, _)| *file == name)
+            .map(|(_, keyword)| *keyword)
+            .into_iter()
+            .chain(extension.and_then(language))
+            .chain(is_test.then_some("test"));
+        for keyword in found {
+            if !keywords.iter().any(|k| k == keyword) {
+                keywords.push(keyword.to_string());
+            }
+        }
+    }
+    keywords
+}
+
+/// Skills and instructions for the given files, best first, at most `limit`
+pub fn suggest(
+    skills: &[Skill],
+    instructions: &[Instruction],
+    paths: &[String],
+    limit: usize,
+) -> Vec<ContextSuggestion> {
+    let mut suggestions: HashMap<String, ContextSuggestion> = HashMap::new();
+
+    for instruction in instructions.iter().filter(|i| i.enabled) {
+        let Some(patterns) = instruction.file_pattern.as_deref() else {
+            continue;
+        };
+        let matched: Vec<&str> = paths
+            .iter()
+            .filter(|path| scope::file_matches(patterns, path))
+            .map(String::as_str)
+            .collect();
+        if matched.is_empty() {
+            continue;
+        }
+        suggestions.insert(
+            instruction.id.clone(),
+            ContextSuggestion {
+                entity_type: "instruction".to_string(),
+                id: instruction.id.clone(),
+                name: instruction.name.clone(),
+                description: instruction.description.clone(),
+                score: FILE_PATTERN_WEIGHT,
+                reasons: vec![format!(
+                    "file pattern {} matches {}",
+                    patterns,
+                    matched.join(", ")
+                )],
+            },
+        );
+    }
+
+    let types = ["skill".to_string(), "instruction".to_string()];
+    for keyword in context_keywords(paths) {
+        for result in search::search(&[], skills, instructions, &keyword, &types, usize::MAX) {
+            let suggestion =
+                suggestions
+                    .entry(result.id.clone())
+                    .or_insert_with(|| ContextSuggestion {
+                        entity_type: result.entity_type,
+                        id: result.id,
+                        name: result.name,
+                        description: result.description,
+                        score: 0,
+                        reasons: Vec::new(),
+                    });
+            suggestion.score += result.score;
+            suggestion.reasons.push(format!("mentions {}", keyword));
+        }
+    }
+
+    let mut suggestions: Vec<ContextSuggestion> = suggestions.into_values().collect();
+    suggestions.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.name.cmp(&b.name)));
+    suggestions.truncate(limit);
+    suggestions
+}
+
+fn language(extension: &str) -> Option<&'static str> {
+    let extension = extension.to_lowercase();
+    LANGUAGES
+        .iter()
+        .find(|(ext, _)| *ext == extension)
+        .map(|(_, language)| *language)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_suggestions_follow_file_patterns_and_languages() {
+        let diff = "diff --git a/src/db.rs b/src/db.rs\n\
+                    --- a/src/db.rs\n\
+                    +++ b/src/db.rs\n\
+                    @@ -1 +1 @@\n\
+                    -old\n\
+                    +new\n\
+                    diff --git a/tests/api.rs b/tests/api.rs\n\
+                    --- /dev/null\n\
+                    +++ b/tests/api.rs\n";
+        assert_eq!(diff_paths(diff), ["src/db.rs", "tests/api.rs"]);
+        assert_eq!(
+            context_paths(Some(".ts"), Some(diff)),
+            ["file.ts", "src/db.rs", "tests/api.rs"]
+        );
+        assert_eq!(context_paths(Some("rs"), None), ["file.rs"]);
+        assert_eq!(context_paths(Some("Makefile"), None), ["Makefile"]);
+        assert_eq!(context_keywords(&diff_paths(diff)), ["rust", "test"]);
+
+        let instruction = |id: &str, name: &str, file_pattern: Option<&str>| Instruction {
+            id: id.to_string(),
+            name: name.to_string(),
+            file_pattern: file_pattern.map(str::to_string),
+            ..Instruction::default()
+        };
+        let instructions = vec![
+            instruction("scoped", "Database conventions", Some("src/db.rs")),
+            instruction("rust", "Rust style", None),
+            instruction("python", "Python style", Some("**/*.py")),
+        ];
+        let skills = vec![Skill {
+            id: "tests".to_string(),
+            name: "Write unit tests".to_string(),
+            ..Skill::default()
+        }];
+
+        let suggestions = suggest(&skills, &instructions, &diff_paths(diff), 10);
+        let ids: Vec<_> = suggestions.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(ids, ["scoped", "rust", "tests"]);
+        assert_eq!(
+            suggestions[0].reasons,
+            ["file pattern src/db.rs matches src/db.rs"]
+        );
+        assert_eq!(suggestions[1].reasons, ["mentions rust"]);
+    }
+}
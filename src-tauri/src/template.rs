@@ -0,0 +1,346 @@
+//! Variables in prompt skill templates
+//! A template refers to its inputs as `{{name}}`. Variables can be declared
+//! on the skill with a description and a default; placeholders that aren't
+//! declared are still variables, just required ones without a description.
+//! Dotted placeholders such as `{{workspace.root}}` belong to the workspace
+//! and are left for `Workspace::render`.
+
+use crate::composition;
+use crate::models::{Skill, SkillDefinition, TemplateVariable};
+use regex::{Captures, Regex};
+use serde_json::{json, Map, Value};
+use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
+
+fn placeholder() -> &'static Regex {
+    static PLACEHOLDER: OnceLock<Regex> = OnceLock::new();
+    PLACEHOLDER.get_or_init(|| Regex::new(r"\{\{\s*([A-Za-z_][A-Za-z0-9_\-]*)\s*\}\}").unwrap())
+}
+
+/// Names of the placeholders in `template`, in order of first use
+pub fn placeholders(template: &str) -> Vec<String> {
+    let mut seen = HashSet::new();
+    placeholder()
+        .captures_iter(template)
+        .map(|caps| caps[1].to_string())
+        .filter(|name| seen.insert(name.clone()))
+        .collect()
+}
+
+/// Every variable a template takes: the declared ones, then any other
+/// placeholder it uses
+pub fn variables(template: &str, declared: &[TemplateVariable]) -> Vec<TemplateVariable> {
+    let mut all = declared.to_vec();
+    for name in placeholders(template) {
+        if !declared.iter().any(|v| v.name == name) {
+            all.push(TemplateVariable {
+                name,
+                description: String::new(),
+                default: None,
+            });
+        }
+    }
+    all
+}
+
+/// Check declared variables before a skill is saved
+pub fn validate_variables(declared: &[TemplateVariable]) -> Result<(), String> {
+    let mut seen = HashSet::new();
+    for variable in declared {
+        let name = variable.name.as_str();
+        let valid = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if !valid {
+            return Err(format!(
+                "Invalid variable name '{}': use letters, digits, '_' and '-', starting with a letter or '_'",
+                name
+            ));
+        }
+        if !seen.insert(name) {
+            return Err(format!("Variable '{}' is declared twice", name));
+        }
+    }
+    Ok(())
+}
+
+/// Fill in a template. Variables without an argument take their default;
+/// missing required values and arguments the template doesn't take are
+/// errors, so a typo never renders silently.
+pub fn render(
+    template: &str,
+    declared: &[TemplateVariable],
+    arguments: &Map<String, Value>,
+) -> Result<String, String> {
+    let variables = variables(template, declared);
+
+    let unknown: Vec<&str> = arguments
+        .keys()
+        .filter(|key| !variables.iter().any(|v| &v.name == *key))
+        .map(String::as_str)
+        .collect();
+    if !unknown.is_empty() {
+        let known: Vec<&str> = variables.iter().map(|v| v.name.as_str()).collect();
+        return Err(format!(
+            "Unknown variable{} {}; the template takes: {}",
+            if unknown.len() == 1 { "" } else { "s" },
+            unknown.join(", "),
+            if known.is_empty() {
+                "nothing".to_string()
+            } else {
+                known.join(", ")
+            }
+        ));
+    }
+
+    let mut values = HashMap::new();
+    let mut missing = Vec::new();
+    for variable in &variables {
+        let value = match arguments.get(&variable.name) {
+            Some(Value::String(value)) => Some(value.clone()),
+            Some(Value::Null) | None => variable.default.clone(),
+            Some(value) => Some(value.to_string()),
+        };
+        match value {
+            Some(value) => {
+                values.insert(variable.name.as_str(), value);
+            }
+            None => missing.push(variable.name.as_str()),
+        }
+    }
+    if !missing.is_empty() {
+        return Err(format!("Missing value for {}", missing.join(", ")));
+    }
+
+    Ok(placeholder()
+        .replace_all(template, |caps: &Captures| values[&caps[1]].clone())
+        .into_owned())
+}
+
+/// Render a prompt skill's template with `arguments`
+pub fn render_skill(skill: &Skill, arguments: &Map<String, Value>) -> Result<String, String> {
+    match &skill.definition {
+        SkillDefinition::Prompt {
+            template,
+            variables,
+            ..
+        } => render(template, variables, arguments),
+        _ => Err(format!(
+            "'{}' has no template: only prompt skills can be rendered",
+            skill.name
+        )),
+    }
+}
+
+/// Render a prompt skill after the skills it `follows`, as numbered steps.
+/// Arguments are shared along the chain: each step takes the ones its
+/// template uses, and an argument no step takes is an error.
+pub fn render_skill_chain(
+    skill: &Skill,
+    skills: &[Skill],
+    arguments: &Map<String, Value>,
+) -> Result<String, String> {
+    if composition::follows(skill).is_empty() {
+        return render_skill(skill, arguments);
+    }
+    let chain = composition::skill_chain(skill, skills)?;
+
+    let taken = chain_variables(&chain);
+    if let Some(unknown) = arguments
+        .keys()
+        .find(|key| !taken.iter().any(|v| &v.name == *key))
+    {
+        let known: Vec<&str> = taken.iter().map(|v| v.name.as_str()).collect();
+        return Err(format!(
+            "Unknown variable {}; the chain takes: {}",
+            unknown,
+            known.join(", ")
+        ));
+    }
+
+    let mut steps = Vec::new();
+    for (n, step) in chain.iter().enumerate() {
+        let own: Map<String, Value> = arguments
+            .iter()
+            .filter(|(key, _)| step_variables(step).iter().any(|v| &v.name == *key))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        let prompt = render_skill(step, &own).map_err(|e| format!("{}: {}", step.name, e))?;
+        steps.push(format!(
+            "## Step {}: {}\n\n{}",
+            n + 1,
+            step.name,
+            prompt.trim()
+        ));
+    }
+    Ok(steps.join("\n\n"))
+}
+
+fn step_variables(skill: &Skill) -> Vec<TemplateVariable> {
+    match &skill.definition {
+        SkillDefinition::Prompt {
+            template,
+            variables: declared,
+            ..
+        } => variables(template, declared),
+        _ => Vec::new(),
+    }
+}
+
+/// Every variable a chain of prompt skills takes, first declaration first
+pub fn chain_variables(chain: &[&Skill]) -> Vec<TemplateVariable> {
+    let mut all: Vec<TemplateVariable> = Vec::new();
+    for variable in chain.iter().flat_map(|step| step_variables(step)) {
+        if !all.iter().any(|v| v.name == variable.name) {
+            all.push(variable);
+        }
+    }
+    all
+}
+
+/// JSON Schema for template variables, as taken by an MCP tool: string
+/// properties, required unless they have a default
+pub fn variables_input_schema(variables: &[TemplateVariable]) -> Value {
+    let properties: Map<String, Value> = variables
+        .iter()
+        .map(|v| {
+            let mut property = json!({ "type": "string", "description": v.description });
+            if let Some(default) = &v.default {
+                property["default"] = json!(default);
+            }
+            (v.name.clone(), property)
+        })
+        .collect();
+    let required: Vec<&str> = variables
+        .iter()
+        .filter(|v| v.default.is_none())
+        .map(|v| v.name.as_str())
+        .collect();
+    json!({
+        "type": "object",
+        "properties": properties,
+        "required": required
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn declared() -> Vec<TemplateVariable> {
+        vec![TemplateVariable {
+            name: "focus".to_string(),
+            description: "What to look for".to_string(),
+            default: Some("bugs".to_string()),
+        }]
+    }
+
+    fn args(value: Value) -> Map<String, Value> {
+        value.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn test_render_fills_arguments_and_defaults() {
+        let template = "Review this {{ language }} for {{focus}}:\n\n{{diff}}\n\n\
+                        Max {{limit}} comments in {{workspace.name}}. {{language}} only.";
+        let rendered = render(
+            template,
+            &declared(),
+            &args(json!({ "language": "Rust", "diff": "+ fn main() {}", "limit": 5 })),
+        )
+        .unwrap();
+        assert_eq!(
+            rendered,
+            "Review this Rust for bugs:\n\n+ fn main() {}\n\n\
+             Max 5 comments in {{workspace.name}}. Rust only."
+        );
+
+        let names: Vec<String> = variables(template, &declared())
+            .into_iter()
+            .map(|v| v.name)
+            .collect();
+        assert_eq!(names, vec!["focus", "language", "diff", "limit"]);
+    }
+
+    #[test]
+    fn test_render_rejects_missing_and_unknown_arguments() {
+        let template = "Explain {{code}} for {{focus}}";
+        assert_eq!(
+            render(template, &declared(), &args(json!({}))),
+            Err("Missing value for code".to_string())
+        );
+        assert_eq!(
+            render(
+                template,
+                &declared(),
+                &args(json!({ "code": "x", "cdoe": "y" }))
+            ),
+            Err("Unknown variable cdoe; the template takes: focus, code".to_string())
+        );
+    }
+
+    #[test]
+    fn test_validate_variables() {
+        assert!(validate_variables(&declared()).is_ok());
+
+        let mut twice = declared();
+        twice.extend(declared());
+        assert!(validate_variables(&twice).is_err());
+
+        let mut dotted = declared();
+        dotted[0].name = "workspace.name".to_string();
+        assert!(validate_variables(&dotted).is_err());
+    }
+
+    #[test]
+    fn test_chained_skills_render_in_order_and_reject_cycles() {
+        let prompt = |id: &str, template: &str, follows: &[&str]| Skill {
+            id: id.to_string(),
+            name: id.to_string(),
+            definition: SkillDefinition::Prompt {
+                template: template.to_string(),
+                variables: Vec::new(),
+                follows: follows.iter().map(|s| s.to_string()).collect(),
+            },
+            ..Skill::default()
+        };
+        let mut skills = vec![
+            prompt("analyze", "Analyze {{code}}", &[]),
+            prompt("summarize", "Summarize for {{audience}}", &["analyze"]),
+            prompt("report", "Write it up", &["analyze", "summarize"]),
+        ];
+
+        let rendered = render_skill_chain(
+            &skills[2],
+            &skills,
+            &args(json!({ "code": "main.rs", "audience": "managers" })),
+        )
+        .unwrap();
+        assert_eq!(
+            rendered,
+            "## Step 1: analyze\n\nAnalyze main.rs\n\n\
+             ## Step 2: summarize\n\nSummarize for managers\n\n\
+             ## Step 3: report\n\nWrite it up"
+        );
+        let chain = composition::skill_chain(&skills[2], &skills).unwrap();
+        let names: Vec<String> = chain_variables(&chain)
+            .into_iter()
+            .map(|v| v.name)
+            .collect();
+        assert_eq!(names, ["code", "audience"]);
+        assert!(render_skill_chain(
+            &skills[1],
+            &skills,
+            &args(json!({ "code": "x", "tone": "y" }))
+        )
+        .unwrap_err()
+        .starts_with("Unknown variable tone"));
+
+        skills[0] = prompt("analyze", "Analyze", &["report"]);
+        assert!(render_skill_chain(&skills[2], &skills, &Map::new())
+            .unwrap_err()
+            .contains("Cycle detected"));
+    }
+}
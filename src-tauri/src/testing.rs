@@ -0,0 +1,168 @@
+//! Trying agents out against LLM providers
+//! `test_agent` sends the system prompt the app assembles for an agent, with
+//! a sample user message, to a provider from settings and returns the reply,
+//! so a prompt can be iterated on without leaving the app. The prompt is
+//! checked for credentials first, as it leaves the machine. `compare_agents`
+//! sends one message to two agents, or two releases of one, side by side.
+
+use crate::assembly::{self, AssemblyOptions};
+use crate::llm;
+use crate::models::{
+    Agent, AgentComparison, AgentTestRun, ComparisonSide, Instruction, LlmProvider, Skill,
+};
+use crate::secrets;
+use std::time::Instant;
+
+/// Send `agent`'s assembled prompt and `message` to `provider`
+pub fn run(
+    provider: &LlmProvider,
+    agent: &Agent,
+    skills: &[Skill],
+    instructions: &[Instruction],
+    message: &str,
+    options: &AssemblyOptions,
+) -> Result<AgentTestRun, String> {
+    let message = message.trim();
+    if message.is_empty() {
+        return Err("Write a message to send to the agent".to_string());
+    }
+
+    let findings: Vec<_> = secrets::scan_agent(agent)
+        .into_iter()
+        .chain(
+            skills
+                .iter()
+                .filter(|s| agent.skills.contains(&s.id) && s.enabled)
+                .flat_map(secrets::scan_skill),
+        )
+        .chain(
+            assembly::attached_instructions(agent, instructions, options)
+                .into_iter()
+                .flat_map(secrets::scan_instruction),
+        )
+        .collect();
+    secrets::guard_export(&findings, false)?;
+
+    let system_prompt = assembly::agent_prompt(agent, skills, instructions, options);
+    let started = Instant::now();
+    let completion = llm::complete_with_usage(provider, &system_prompt, message)?;
+
+    Ok(AgentTestRun {
+        agent_id: agent.id.clone(),
+        provider: provider.name.clone(),
+        model: provider.model.clone(),
+        prompt_tokens: assembly::estimate_tokens(&system_prompt),
+        system_prompt,
+        message: message.to_string(),
+        reply: completion.text,
+        duration_ms: started.elapsed().as_millis() as u64,
+        usage: completion.usage,
+    })
+}
+
+/// An agent and the library it is assembled from
+pub struct Subject<'a> {
+    pub agent: &'a Agent,
+    pub skills: &'a [Skill],
+    pub instructions: &'a [Instruction],
+    pub release: Option<String>,
+}
+
+/// Send `message` to both subjects at once. A side that fails carries its
+/// error, so the other side's reply is still shown.
+pub fn compare(
+    provider: &LlmProvider,
+    a: Subject,
+    b: Subject,
+    message: &str,
+    options: &AssemblyOptions,
+) -> Result<AgentComparison, String> {
+    let message = message.trim();
+    if message.is_empty() {
+        return Err("Write a message to send to the agents".to_string());
+    }
+
+    let side = |subject: Subject| {
+        let result = run(
+            provider,
+            subject.agent,
+            subject.skills,
+            subject.instructions,
+            message,
+            options,
+        );
+        ComparisonSide {
+            agent_id: subject.agent.id.clone(),
+            release: subject.release,
+            error: result.as_ref().err().cloned(),
+            run: result.ok(),
+        }
+    };
+    let (a, b) = std::thread::scope(|scope| {
+        let a = scope.spawn(|| side(a));
+        let b = side(b);
+        (a.join(), b)
+    });
+
+    Ok(AgentComparison {
+        message: message.to_string(),
+        a: a.map_err(|_| "The comparison stopped unexpectedly".to_string())?,
+        b,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nothing_is_sent_without_a_message_or_with_secrets() {
+        // Nothing listens here, so reaching the provider would fail differently
+        let provider = LlmProvider {
+            name: "local".to_string(),
+            kind: "openai".to_string(),
+            model: "test".to_string(),
+            base_url: Some("http://127.0.0.1:9".to_string()),
+            api_key: String::new(),
+        };
+        let agent = Agent {
+            instructions: vec!["deploy".to_string()],
+            ..Agent::default()
+        };
+        let instructions = vec![Instruction {
+            id: "deploy".to_string(),
+            content: "Deploy with OPENAI_API_KEY=sk-abcdefghijklmnopqrstuvwxyz123456".to_string(),
+            ..Instruction::default()
+        }];
+        let options = AssemblyOptions::default();
+
+        let empty = run(&provider, &agent, &[], &instructions, "  ", &options);
+        assert_eq!(empty.unwrap_err(), "Write a message to send to the agent");
+
+        let leaked = run(&provider, &agent, &[], &instructions, "Hello", &options);
+        assert!(leaked.unwrap_err().contains("potential secret"));
+
+        let unreachable = run(&provider, &agent, &[], &[], "Hello", &options);
+        assert!(unreachable
+            .unwrap_err()
+            .starts_with("Failed to reach 'local'"));
+
+        let subject = |instructions| Subject {
+            agent: &agent,
+            skills: &[],
+            instructions,
+            release: None,
+        };
+        let comparison = compare(
+            &provider,
+            subject(&instructions),
+            subject(&[]),
+            "Hello",
+            &options,
+        )
+        .unwrap();
+        assert!(comparison.a.error.unwrap().contains("potential secret"));
+        assert!(comparison.b.error.unwrap().starts_with("Failed to reach"));
+        assert!(comparison.a.run.is_none() && comparison.b.run.is_none());
+    }
+}
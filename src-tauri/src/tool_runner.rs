@@ -0,0 +1,325 @@
+//! Runs the handlers of tool skills for the MCP server
+//! A handler is a shell command line (`sh -c`, or `cmd /C` on Windows). It
+//! gets the call's arguments as a JSON object on stdin and as `PF_ARG_<NAME>`
+//! environment variables, and its stdout and stderr become the tool result.
+//! The skill's `ToolSandbox` sets the timeout, working directory, how much of
+//! the environment is passed on and how much output is kept.
+
+use crate::models::{Skill, SkillDefinition, ToolSandbox};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Variables passed to handlers that don't inherit the whole environment
+const BASE_ENV: &[&str] = &[
+    "PATH",
+    "HOME",
+    "USER",
+    "LANG",
+    "LC_ALL",
+    "TMPDIR",
+    "TEMP",
+    "TMP",
+    "SYSTEMROOT",
+    "USERPROFILE",
+    "PATHEXT",
+    "COMSPEC",
+];
+
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Fingerprint of a handler's text. An approval holds only for the text
+/// that was approved, so any edit to the handler withdraws it.
+pub fn handler_digest(handler: &str) -> String {
+    format!("{:x}", Sha256::digest(handler))
+}
+
+/// Whether `skill` is a tool whose handler, as it reads now, is in
+/// `approvals` (handler digests by skill id)
+pub fn is_approved(skill: &Skill, approvals: &HashMap<String, String>) -> bool {
+    match &skill.definition {
+        SkillDefinition::Tool { handler, .. } => {
+            approvals.get(&skill.id) == Some(&handler_digest(handler))
+        }
+        _ => false,
+    }
+}
+
+/// What a handler printed and how it ended
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolOutput {
+    pub stdout: String,
+    pub stderr: String,
+    pub exit_code: Option<i32>, // None when killed by a signal or the timeout
+    pub timed_out: bool,
+    pub truncated: bool,
+}
+
+impl ToolOutput {
+    pub fn success(&self) -> bool {
+        !self.timed_out && self.exit_code == Some(0)
+    }
+
+    /// The tool result: stdout, then stderr and how the handler failed, if it did
+    pub fn text(&self) -> String {
+        let mut text = self.stdout.trim_end().to_string();
+        if !self.stderr.trim().is_empty() {
+            if !text.is_empty() {
+                text.push_str("\n\n");
+            }
+            text.push_str(&format!("stderr:\n{}", self.stderr.trim_end()));
+        }
+        let mut notes = Vec::new();
+        if self.truncated {
+            notes.push("output truncated".to_string());
+        }
+        if self.timed_out {
+            notes.push("timed out".to_string());
+        } else {
+            match self.exit_code {
+                Some(0) => {}
+                Some(code) => notes.push(format!("exit code {}", code)),
+                None => notes.push("killed by a signal".to_string()),
+            }
+        }
+        if !notes.is_empty() {
+            if !text.is_empty() {
+                text.push_str("\n\n");
+            }
+            text.push_str(&format!("[{}]", notes.join(", ")));
+        }
+        text
+    }
+}
+
+/// Environment variable carrying argument `name`, e.g. `PF_ARG_MAX_RESULTS`
+pub fn argument_variable(name: &str) -> String {
+    format!(
+        "PF_ARG_{}",
+        name.to_ascii_uppercase()
+            .replace(|c: char| !c.is_ascii_alphanumeric(), "_")
+    )
+}
+
+/// Run `handler` with `arguments`. `workspace_root` is the working directory
+/// unless the sandbox names one.
+pub fn run(
+    handler: &str,
+    arguments: &Map<String, Value>,
+    sandbox: &ToolSandbox,
+    workspace_root: Option<&Path>,
+) -> Result<ToolOutput, String> {
+    let handler = handler.trim();
+    if handler.is_empty() {
+        return Err("The tool has no handler to run".to_string());
+    }
+
+    let working_dir = match &sandbox.working_dir {
+        Some(dir) => PathBuf::from(dir),
+        None => workspace_root
+            .map(Path::to_path_buf)
+            .unwrap_or_else(std::env::temp_dir),
+    };
+    if !working_dir.is_dir() {
+        return Err(format!(
+            "Working directory {} does not exist",
+            working_dir.display()
+        ));
+    }
+
+    let mut command = shell(handler);
+    command
+        .current_dir(&working_dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if !sandbox.inherit_env {
+        command.env_clear();
+        for name in BASE_ENV {
+            if let Some(value) = std::env::var_os(name) {
+                command.env(name, value);
+            }
+        }
+    }
+    for (name, value) in arguments {
+        let value = match value {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        };
+        command.env(argument_variable(name), value);
+    }
+    #[cfg(unix)]
+    {
+        // Own process group, so a timeout also stops anything the handler started
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+
+    let mut child = command
+        .spawn()
+        .map_err(|e| format!("Failed to start handler: {}", e))?;
+
+    let input = serde_json::to_vec(arguments).unwrap_or_default();
+    let mut stdin = child.stdin.take();
+    let writer = thread::spawn(move || {
+        if let Some(stdin) = stdin.as_mut() {
+            // Handlers that ignore stdin may exit before reading it
+            let _ = stdin.write_all(&input);
+        }
+    });
+    let limit = sandbox.max_output_bytes;
+    let stdout = child.stdout.take().map(|out| read_limited(out, limit));
+    let stderr = child.stderr.take().map(|err| read_limited(err, limit));
+
+    let timeout = Duration::from_secs(sandbox.timeout_secs.max(1));
+    let status = wait(&mut child, timeout)?;
+
+    let _ = writer.join();
+    let (stdout, stdout_truncated) = collect(stdout);
+    let (stderr, stderr_truncated) = collect(stderr);
+
+    Ok(ToolOutput {
+        stdout,
+        stderr,
+        exit_code: status.and_then(|s| s.code()),
+        timed_out: status.is_none(),
+        truncated: stdout_truncated || stderr_truncated,
+    })
+}
+
+fn shell(handler: &str) -> Command {
+    if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.args(["/C", handler]);
+        command
+    } else {
+        let mut command = Command::new("sh");
+        command.args(["-c", handler]);
+        command
+    }
+}
+
+/// Wait for the handler to exit; `None` if it had to be killed
+fn wait(child: &mut Child, timeout: Duration) -> Result<Option<ExitStatus>, String> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child
+            .try_wait()
+            .map_err(|e| format!("Failed to wait for handler: {}", e))?
+        {
+            return Ok(Some(status));
+        }
+        if Instant::now() >= deadline {
+            kill(child);
+            return Ok(None);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+fn kill(child: &mut Child) {
+    #[cfg(unix)]
+    {
+        let _ = Command::new("kill")
+            .args(["-s", "KILL", "--", &format!("-{}", child.id())])
+            .stderr(Stdio::null())
+            .status();
+    }
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// Read a stream to the end on its own thread, keeping at most `limit` bytes
+fn read_limited<R: Read + Send + 'static>(
+    mut reader: R,
+    limit: usize,
+) -> thread::JoinHandle<(Vec<u8>, bool)> {
+    thread::spawn(move || {
+        let mut kept = Vec::new();
+        let mut truncated = false;
+        let mut buf = [0u8; 8192];
+        loop {
+            match reader.read(&mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => {
+                    let room = limit.saturating_sub(kept.len());
+                    kept.extend_from_slice(&buf[..n.min(room)]);
+                    truncated |= n > room;
+                }
+            }
+        }
+        (kept, truncated)
+    })
+}
+
+fn collect(reader: Option<thread::JoinHandle<(Vec<u8>, bool)>>) -> (String, bool) {
+    match reader.and_then(|r| r.join().ok()) {
+        Some((bytes, truncated)) => (String::from_utf8_lossy(&bytes).into_owned(), truncated),
+        None => (String::new(), false),
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn args(value: Value) -> Map<String, Value> {
+        value.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn test_handler_gets_arguments_on_stdin_and_in_env() {
+        let output = run(
+            "echo \"$PF_ARG_MAX_RESULTS $PF_ARG_QUERY\"; cat; echo oops >&2; exit 3",
+            &args(json!({ "query": "sandbox", "max-results": 5 })),
+            &ToolSandbox::default(),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(output.exit_code, Some(3));
+        assert!(!output.success());
+        assert_eq!(
+            output.text(),
+            "5 sandbox\n{\"max-results\":5,\"query\":\"sandbox\"}\n\nstderr:\noops\n\n[exit code 3]"
+        );
+    }
+
+    #[test]
+    fn test_sandbox_limits() {
+        std::env::set_var("PF_TEST_SECRET", "hunter2");
+        let output = run(
+            "echo \"secret=$PF_TEST_SECRET\"; printf 'x%.0s' $(seq 1 100)",
+            &Map::new(),
+            &ToolSandbox {
+                max_output_bytes: 20,
+                ..ToolSandbox::default()
+            },
+            None,
+        )
+        .unwrap();
+        assert_eq!(output.stdout, "secret=\nxxxxxxxxxxxx");
+        assert!(output.truncated && output.success());
+
+        let started = Instant::now();
+        let output = run(
+            "sleep 10",
+            &Map::new(),
+            &ToolSandbox {
+                timeout_secs: 1,
+                ..ToolSandbox::default()
+            },
+            None,
+        )
+        .unwrap();
+        assert!(output.timed_out);
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(output.text(), "[timed out]");
+    }
+}
@@ -0,0 +1,540 @@
+//! Conversion between tool skills and MCP tool definitions
+//! An MCP tool is `{ name, description, inputSchema }` where the schema is a
+//! JSON Schema object; each top-level property maps to one `ToolParameter`,
+//! whose `schema` carries the property's keywords beyond its type,
+//! description and default (`enum`, `minimum`, nested `properties`, ...).
+//! Parameter lists are validated here before they are saved or exported.
+
+use crate::json_schema;
+use crate::models::{Skill, SkillDefinition, SkillType, ToolParameter};
+use crate::resolve::slugify;
+use crate::template;
+use chrono::Utc;
+use serde_json::{json, Map, Value};
+use uuid::Uuid;
+
+const TOOL_ICON: &str = "🔧";
+
+/// JSON Schema types a parameter may declare
+const PARAM_TYPES: [&str; 6] = ["string", "number", "integer", "boolean", "array", "object"];
+
+/// Property keywords held in a parameter's own fields rather than its `schema`
+const OWN_FIELDS: [&str; 3] = ["type", "description", "default"];
+
+/// Check a parameter list for problems that would produce an unusable schema:
+/// missing or duplicate names, unknown types, and defaults of the wrong type.
+/// Every problem is reported at once.
+pub fn validate_parameters(parameters: &[ToolParameter]) -> Result<(), String> {
+    let mut problems = Vec::new();
+
+    for (index, param) in parameters.iter().enumerate() {
+        let name = param.name.trim();
+        if name.is_empty() {
+            problems.push(format!("Parameter {} has no name", index + 1));
+            continue;
+        }
+        if !is_identifier(name) {
+            problems.push(format!(
+                "Parameter '{}' may only contain letters, digits, '_' and '-'",
+                name
+            ));
+        }
+        if parameters[..index].iter().any(|p| p.name.trim() == name) {
+            problems.push(format!("Parameter '{}' is defined more than once", name));
+        }
+        if !PARAM_TYPES.contains(&param.param_type.as_str()) {
+            problems.push(format!(
+                "Parameter '{}' has unknown type '{}' (expected one of: {})",
+                name,
+                param.param_type,
+                PARAM_TYPES.join(", ")
+            ));
+        } else if let Some(default) = &param.default {
+            if !matches_type(default, &param.param_type) {
+                problems.push(format!(
+                    "Default for parameter '{}' is not of type {}",
+                    name, param.param_type
+                ));
+            }
+        }
+
+        let Some(schema) = &param.schema else {
+            continue;
+        };
+        let before = problems.len();
+        json_schema::schema_problems(schema, &format!("Parameter '{}'", name), &mut problems);
+        for key in OWN_FIELDS {
+            if schema.get(key).is_some() {
+                problems.push(format!(
+                    "Parameter '{}': set {} on the parameter, not in its schema",
+                    name, key
+                ));
+            }
+        }
+        if problems.len() == before {
+            if let Some(default) = &param.default {
+                let mut broken = Vec::new();
+                json_schema::value_problems(default, schema, name, &mut broken);
+                problems.extend(broken.into_iter().map(|p| format!("Default {}", p)));
+            }
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("Invalid tool parameters: {}", problems.join("; ")))
+    }
+}
+
+/// Validate the parameters of a tool definition; other skill types always pass
+pub fn validate_definition(definition: &SkillDefinition) -> Result<(), String> {
+    match definition {
+        SkillDefinition::Tool { parameters, .. } => validate_parameters(parameters),
+        SkillDefinition::Prompt { variables, .. } => template::validate_variables(variables),
+        _ => Ok(()),
+    }
+}
+
+/// Check the arguments of a tool call against the tool's parameters and fill
+/// in defaults. Unknown, missing and mistyped arguments are all reported.
+pub fn prepare_arguments(
+    parameters: &[ToolParameter],
+    arguments: &Value,
+) -> Result<Map<String, Value>, String> {
+    let mut arguments = match arguments {
+        Value::Object(arguments) => arguments.clone(),
+        Value::Null => Map::new(),
+        _ => return Err("Tool arguments must be an object".to_string()),
+    };
+
+    let mut problems = Vec::new();
+    for name in arguments.keys() {
+        if !parameters.iter().any(|p| &p.name == name) {
+            problems.push(format!("unknown argument '{}'", name));
+        }
+    }
+    for param in parameters {
+        match arguments.get(&param.name) {
+            Some(value) if !value.is_null() => {
+                json_schema::value_problems(
+                    value,
+                    &parameter_schema(param),
+                    &param.name,
+                    &mut problems,
+                );
+            }
+            _ => match &param.default {
+                Some(default) if !default.is_null() => {
+                    arguments.insert(param.name.clone(), default.clone());
+                }
+                _ if param.required => problems.push(format!("missing argument '{}'", param.name)),
+                _ => {}
+            },
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(arguments)
+    } else {
+        Err(format!("Invalid arguments: {}", problems.join("; ")))
+    }
+}
+
+fn matches_type(value: &Value, param_type: &str) -> bool {
+    value.is_null() || json_schema::is_type(value, param_type)
+}
+
+fn is_identifier(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 64
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// The JSON Schema of one parameter: its type, description and default, then
+/// the keywords in its `schema`
+pub fn parameter_schema(param: &ToolParameter) -> Value {
+    let mut property = Map::new();
+    property.insert("type".to_string(), json!(param.param_type));
+    if !param.description.is_empty() {
+        property.insert("description".to_string(), json!(param.description));
+    }
+    if let Some(default) = &param.default {
+        property.insert("default".to_string(), default.clone());
+    }
+    if let Some(Value::Object(schema)) = &param.schema {
+        for (key, value) in schema {
+            if !OWN_FIELDS.contains(&key.as_str()) {
+                property.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    Value::Object(property)
+}
+
+/// Build the `inputSchema` object for a list of parameters
+pub fn parameters_to_input_schema(parameters: &[ToolParameter]) -> Value {
+    let properties: Map<String, Value> = parameters
+        .iter()
+        .map(|param| (param.name.clone(), parameter_schema(param)))
+        .collect();
+
+    let required: Vec<&str> = parameters
+        .iter()
+        .filter(|p| p.required)
+        .map(|p| p.name.as_str())
+        .collect();
+
+    json!({
+        "type": "object",
+        "properties": properties,
+        "required": required
+    })
+}
+
+/// Arguments for an example call: every required parameter, or the first
+/// one when none is, set to its default, first allowed value, minimum or a
+/// placeholder of its type
+pub fn example_arguments(parameters: &[ToolParameter]) -> Map<String, Value> {
+    let required: Vec<&ToolParameter> = parameters.iter().filter(|p| p.required).collect();
+    let shown = if required.is_empty() {
+        parameters.iter().take(1).collect()
+    } else {
+        required
+    };
+    shown
+        .into_iter()
+        .map(|param| {
+            let placeholder = format!("<{}>", param.name);
+            let keyword = |key: &str| param.schema.as_ref().and_then(|s| s.get(key));
+            let first_allowed = keyword("enum").and_then(|allowed| allowed.get(0));
+            let value = match (&param.default, first_allowed, param.param_type.as_str()) {
+                (Some(default), _, _) if !default.is_null() => default.clone(),
+                (_, Some(allowed), _) => allowed.clone(),
+                (_, _, "number" | "integer") => keyword("minimum")
+                    .filter(|minimum| minimum.is_number())
+                    .cloned()
+                    .unwrap_or(json!(1)),
+                (_, _, "boolean") => json!(true),
+                (_, _, "array") => json!([placeholder]),
+                (_, _, "object") => json!({}),
+                _ => json!(placeholder),
+            };
+            (param.name.clone(), value)
+        })
+        .collect()
+}
+
+/// Read the top-level properties of an `inputSchema` back into parameters
+pub fn parameters_from_input_schema(schema: &Value) -> Result<Vec<ToolParameter>, String> {
+    if schema.is_null() {
+        return Ok(Vec::new());
+    }
+    let schema = schema
+        .as_object()
+        .ok_or_else(|| "inputSchema must be an object".to_string())?;
+
+    let required: Vec<&str> = schema
+        .get("required")
+        .and_then(|r| r.as_array())
+        .map(|r| r.iter().filter_map(|v| v.as_str()).collect())
+        .unwrap_or_default();
+
+    let Some(properties) = schema.get("properties") else {
+        return Ok(Vec::new());
+    };
+    let properties = properties
+        .as_object()
+        .ok_or_else(|| "inputSchema.properties must be an object".to_string())?;
+
+    Ok(properties
+        .iter()
+        .map(|(name, property)| ToolParameter {
+            name: name.clone(),
+            description: property
+                .get("description")
+                .and_then(|d| d.as_str())
+                .unwrap_or("")
+                .to_string(),
+            param_type: property_type(property),
+            required: required.contains(&name.as_str()),
+            default: property.get("default").cloned(),
+            schema: property.as_object().and_then(|property| {
+                let rest: Map<String, Value> = property
+                    .iter()
+                    .filter(|(key, _)| !OWN_FIELDS.contains(&key.as_str()))
+                    .map(|(key, value)| (key.clone(), value.clone()))
+                    .collect();
+                (!rest.is_empty()).then_some(Value::Object(rest))
+            }),
+        })
+        .collect())
+}
+
+/// JSON Schema `type` of a property; union types keep their first non-null member
+fn property_type(property: &Value) -> String {
+    match property.get("type") {
+        Some(Value::String(t)) => t.clone(),
+        Some(Value::Array(types)) => types
+            .iter()
+            .filter_map(|t| t.as_str())
+            .find(|t| *t != "null")
+            .unwrap_or("string")
+            .to_string(),
+        _ => "string".to_string(),
+    }
+}
+
+/// Convert a single MCP tool definition into a tool skill with a fresh ID.
+/// The handler is left empty since MCP definitions don't carry one.
+pub fn skill_from_mcp_tool(tool: &Value) -> Result<Skill, String> {
+    let name = tool
+        .get("name")
+        .and_then(|n| n.as_str())
+        .map(str::trim)
+        .filter(|n| !n.is_empty())
+        .ok_or_else(|| "MCP tool definition is missing a name".to_string())?;
+
+    let schema = tool
+        .get("inputSchema")
+        .or_else(|| tool.get("input_schema"))
+        .unwrap_or(&Value::Null);
+    let parameters = parameters_from_input_schema(schema)
+        .and_then(|parameters| validate_parameters(&parameters).map(|_| parameters))
+        .map_err(|e| format!("Invalid tool '{}': {}", name, e))?;
+
+    Ok(Skill {
+        id: Uuid::new_v4().to_string(),
+        name: name.to_string(),
+        description: tool
+            .get("description")
+            .and_then(|d| d.as_str())
+            .unwrap_or("")
+            .to_string(),
+        icon_emoji: TOOL_ICON.to_string(),
+        skill_type: SkillType::Tool,
+        definition: SkillDefinition::Tool {
+            parameters,
+            handler: String::new(),
+            sandbox: None,
+        },
+        enabled: true,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+        aliases: vec![],
+        usage_count: 0,
+        last_used_at: None,
+    })
+}
+
+/// Parse one tool, an array of tools, or a `tools/list` result (`{ "tools": [...] }`)
+pub fn skills_from_mcp_tools_json(text: &str) -> Result<Vec<Skill>, String> {
+    let value: Value =
+        serde_json::from_str(text).map_err(|e| format!("Invalid MCP tool JSON: {}", e))?;
+
+    let tools = match &value {
+        Value::Array(tools) => tools.as_slice(),
+        Value::Object(map) => match map.get("tools") {
+            Some(Value::Array(tools)) => tools.as_slice(),
+            _ => std::slice::from_ref(&value),
+        },
+        _ => return Err("MCP tool JSON must be an object or an array".to_string()),
+    };
+
+    if tools.is_empty() {
+        return Err("No MCP tool definitions found".to_string());
+    }
+    tools.iter().map(skill_from_mcp_tool).collect()
+}
+
+/// Export a tool skill as an MCP tool definition
+pub fn skill_to_mcp_tool(skill: &Skill) -> Result<Value, String> {
+    let SkillDefinition::Tool { parameters, .. } = &skill.definition else {
+        return Err(format!(
+            "Skill '{}' is not a tool skill and can't be exported as an MCP tool",
+            skill.name
+        ));
+    };
+    validate_parameters(parameters)?;
+
+    Ok(json!({
+        "name": mcp_tool_name(&skill.name),
+        "description": skill.description,
+        "inputSchema": parameters_to_input_schema(parameters)
+    }))
+}
+
+/// MCP tool names are limited to letters, digits, `_` and `-`; names that
+/// already fit are kept so imported tools round-trip unchanged
+pub fn mcp_tool_name(name: &str) -> String {
+    if is_identifier(name) {
+        name.to_string()
+    } else {
+        slugify(name).replace('-', "_")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mcp_tool_round_trips_through_skill() {
+        let text = r#"{"tools": [{
+            "name": "get_weather",
+            "description": "Current weather for a city",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "city": {"type": "string", "description": "City name", "minLength": 2},
+                    "units": {"type": ["string", "null"], "default": "metric"}
+                },
+                "required": ["city"]
+            }
+        }]}"#;
+
+        let skills = skills_from_mcp_tools_json(text).unwrap();
+        assert_eq!(skills.len(), 1);
+        let skill = &skills[0];
+        assert!(matches!(skill.skill_type, SkillType::Tool));
+        let SkillDefinition::Tool { parameters, .. } = &skill.definition else {
+            panic!("expected a tool definition");
+        };
+        let city = parameters.iter().find(|p| p.name == "city").unwrap();
+        assert!(city.required);
+        assert_eq!(city.schema, Some(json!({ "minLength": 2 })));
+        let units = parameters.iter().find(|p| p.name == "units").unwrap();
+        assert_eq!(units.param_type, "string");
+        assert_eq!(units.default, Some(json!("metric")));
+
+        let exported = skill_to_mcp_tool(skill).unwrap();
+        assert_eq!(exported["name"], "get_weather");
+        assert_eq!(exported["inputSchema"]["required"], json!(["city"]));
+        assert_eq!(
+            exported["inputSchema"]["properties"]["city"]["minLength"],
+            2
+        );
+        assert_eq!(
+            exported["inputSchema"]["properties"]["units"]["default"],
+            "metric"
+        );
+    }
+
+    #[test]
+    fn test_non_tool_skills_are_not_exported() {
+        let skill = Skill {
+            name: "Summarize Text".to_string(),
+            ..Skill::default()
+        };
+        assert!(skill_to_mcp_tool(&skill).is_err());
+        assert_eq!(mcp_tool_name("Summarize Text"), "summarize_text");
+    }
+
+    #[test]
+    fn test_example_arguments_cover_required_parameters() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "path": {"type": "string"},
+                "depth": {"type": "integer", "default": 2},
+                "tags": {"type": "array"},
+                "verbose": {"type": "boolean"}
+            },
+            "required": ["path", "depth", "tags"]
+        });
+        let parameters = parameters_from_input_schema(&schema).unwrap();
+        assert_eq!(
+            Value::Object(example_arguments(&parameters)),
+            json!({ "path": "<path>", "depth": 2, "tags": ["<tags>"] })
+        );
+
+        let optional = &parameters[3..];
+        assert_eq!(
+            Value::Object(example_arguments(optional)),
+            json!({ "verbose": true })
+        );
+        assert!(example_arguments(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_validate_parameters_reports_every_problem() {
+        let param = |name: &str, param_type: &str, default: Option<Value>| ToolParameter {
+            name: name.to_string(),
+            description: String::new(),
+            param_type: param_type.to_string(),
+            required: false,
+            default,
+            schema: None,
+        };
+
+        assert!(validate_parameters(&[
+            param("path", "string", Some(json!("."))),
+            param("depth", "integer", Some(json!(2))),
+        ])
+        .is_ok());
+
+        let err = validate_parameters(&[
+            param("path", "string", None),
+            param("path", "text", None),
+            param("", "string", None),
+            param("limit", "integer", Some(json!("ten"))),
+        ])
+        .unwrap_err();
+        assert!(err.contains("'path' is defined more than once"));
+        assert!(err.contains("unknown type 'text'"));
+        assert!(err.contains("Parameter 3 has no name"));
+        assert!(err.contains("Default for parameter 'limit'"));
+
+        let mut mode = param("mode", "string", Some(json!("slow")));
+        mode.schema = Some(json!({ "enum": ["fast", "thorough"] }));
+        let mut depth = param("depth", "integer", None);
+        depth.schema = Some(json!({ "type": "integer", "minimum": 3, "maximum": 1 }));
+        let err = validate_parameters(&[mode, depth]).unwrap_err();
+        assert!(err.contains("Default 'mode' must be one of"));
+        assert!(err.contains("Parameter 'depth': minimum is above maximum"));
+        assert!(err.contains("set type on the parameter, not in its schema"));
+    }
+
+    #[test]
+    fn test_prepare_arguments_fills_defaults_and_reports_problems() {
+        let parameters = vec![
+            ToolParameter {
+                name: "query".to_string(),
+                description: String::new(),
+                param_type: "string".to_string(),
+                required: true,
+                default: None,
+                schema: None,
+            },
+            ToolParameter {
+                name: "limit".to_string(),
+                description: String::new(),
+                param_type: "integer".to_string(),
+                required: false,
+                default: Some(json!(5)),
+                schema: Some(json!({ "minimum": 1, "maximum": 50 })),
+            },
+        ];
+
+        let arguments = prepare_arguments(&parameters, &json!({ "query": "sandbox" })).unwrap();
+        assert_eq!(
+            Value::Object(arguments),
+            json!({ "query": "sandbox", "limit": 5 })
+        );
+
+        assert_eq!(
+            prepare_arguments(&parameters, &json!({ "limit": "ten", "verbose": true })),
+            Err(
+                "Invalid arguments: unknown argument 'verbose'; missing argument 'query'; \
+                 'limit' must be of type integer"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            prepare_arguments(&parameters, &json!({ "query": "sandbox", "limit": 99 })),
+            Err("Invalid arguments: 'limit' breaks maximum 50".to_string())
+        );
+    }
+}
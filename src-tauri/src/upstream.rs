@@ -0,0 +1,117 @@
+//! Instructions kept in step with the pages they were imported from
+//! An instruction imported from a URL remembers the page and a digest of the
+//! content taken from it. Checking fetches each page again and compares
+//! digests, so edits made to the instruction in the app don't count as
+//! upstream changes; only the page changing does.
+
+use crate::models::{Instruction, UpstreamCheck, UpstreamStatus};
+use sha2::{Digest, Sha256};
+
+/// Digest of imported content, ignoring surrounding whitespace
+pub fn content_hash(content: &str) -> String {
+    format!("{:x}", Sha256::digest(content.trim()))
+}
+
+/// Check every instruction with an upstream URL, by name. `fetch` returns a
+/// page's content as it would be imported. An instruction without a digest
+/// is compared with its own content.
+pub fn check(
+    instructions: &[Instruction],
+    mut fetch: impl FnMut(&str) -> Result<String, String>,
+) -> Vec<UpstreamCheck> {
+    let mut linked: Vec<(&Instruction, &str)> = instructions
+        .iter()
+        .filter_map(|i| {
+            let url = i.upstream_url.as_deref().map(str::trim)?;
+            (!url.is_empty()).then_some((i, url))
+        })
+        .collect();
+    linked.sort_by_key(|(i, _)| i.name.to_lowercase());
+
+    linked
+        .into_iter()
+        .map(|(instruction, url)| {
+            let mut found = UpstreamCheck {
+                instruction_id: instruction.id.clone(),
+                name: instruction.name.clone(),
+                upstream_url: url.to_string(),
+                status: UpstreamStatus::Unchanged,
+                upstream_content: None,
+                error: None,
+            };
+            match fetch(url) {
+                Ok(content) => {
+                    let synced = instruction
+                        .upstream_hash
+                        .clone()
+                        .unwrap_or_else(|| content_hash(&instruction.content));
+                    if content_hash(&content) != synced {
+                        found.status = UpstreamStatus::Changed;
+                        found.upstream_content = Some(content);
+                    }
+                }
+                Err(e) => {
+                    found.status = UpstreamStatus::Unreachable;
+                    found.error = Some(e);
+                }
+            }
+            found
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_upstream_changes_are_flagged() {
+        let linked = |name: &str, content: &str, imported: Option<&str>| Instruction {
+            id: name.to_lowercase(),
+            name: name.to_string(),
+            content: content.to_string(),
+            upstream_url: Some(format!("https://wiki.example.com/{}", name)),
+            upstream_hash: imported.map(content_hash),
+            ..Instruction::default()
+        };
+        let instructions = vec![
+            // Edited in the app since import; the page is as it was
+            linked("Style", "Use rustfmt and clippy.", Some("Use rustfmt.")),
+            linked("Security", "No secrets.", Some("No secrets.")),
+            linked("Testing", "Write tests.", None),
+            linked("Gone", "Old page.", Some("Old page.")),
+            Instruction::default(),
+        ];
+        let pages = [
+            ("https://wiki.example.com/Style", "Use rustfmt.\n"),
+            ("https://wiki.example.com/Security", "No secrets in logs."),
+            ("https://wiki.example.com/Testing", "Write tests."),
+        ];
+
+        let checks = check(&instructions, |url| {
+            pages
+                .iter()
+                .find(|(page, _)| *page == url)
+                .map(|(_, content)| content.to_string())
+                .ok_or_else(|| "404 Not Found".to_string())
+        });
+        let found: Vec<(&str, &UpstreamStatus)> = checks
+            .iter()
+            .map(|c| (c.instruction_id.as_str(), &c.status))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("gone", &UpstreamStatus::Unreachable),
+                ("security", &UpstreamStatus::Changed),
+                ("style", &UpstreamStatus::Unchanged),
+                ("testing", &UpstreamStatus::Unchanged),
+            ]
+        );
+        assert_eq!(
+            checks[1].upstream_content.as_deref(),
+            Some("No secrets in logs.")
+        );
+        assert_eq!(checks[0].error.as_deref(), Some("404 Not Found"));
+    }
+}
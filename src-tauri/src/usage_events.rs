@@ -0,0 +1,171 @@
+//! Usage events recorded by the MCP process.
+//! The stdio server never updates counters in place: it appends rows to the
+//! `usage_events` table and the GUI process drains them into the usage counts
+//! of agents, skills and instructions and into insights, so the two
+//! processes never contend over the same rows.
+
+use crate::db::Database;
+use crate::insights::{self, InsightMetric};
+use chrono::{DateTime, Utc};
+use rusqlite::Result as SqliteResult;
+use std::collections::VecDeque;
+
+/// Events kept in memory while the database is busy; the oldest are dropped beyond this
+const MAX_PENDING: usize = 500;
+
+/// Events applied per database round-trip when draining
+const DRAIN_BATCH: usize = 200;
+
+#[derive(Debug, Clone, Copy)]
+pub enum UsageEventKind {
+    AgentApplied,
+    AgentFetched,
+    SkillUsed,
+    InstructionUsed,
+    Insight,
+}
+
+impl UsageEventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UsageEventKind::AgentApplied => "agent_applied",
+            UsageEventKind::AgentFetched => "agent_fetched",
+            UsageEventKind::SkillUsed => "skill_used",
+            UsageEventKind::InstructionUsed => "instruction_used",
+            UsageEventKind::Insight => "insight",
+        }
+    }
+}
+
+struct PendingEvent {
+    kind: UsageEventKind,
+    entity_id: String,
+    payload: String,
+    created_at: DateTime<Utc>,
+}
+
+/// In-memory queue of events waiting to be appended to the shared database
+#[derive(Default)]
+pub struct UsageEventQueue {
+    pending: VecDeque<PendingEvent>,
+}
+
+impl UsageEventQueue {
+    /// Queue an event. Returns false when the queue was full and its oldest
+    /// event was dropped to make room.
+    pub fn push(&mut self, kind: UsageEventKind, entity_id: &str, payload: &str) -> bool {
+        let full = self.pending.len() >= MAX_PENDING;
+        if full {
+            self.pending.pop_front();
+        }
+        self.pending.push_back(PendingEvent {
+            kind,
+            entity_id: entity_id.to_string(),
+            payload: payload.to_string(),
+            created_at: Utc::now(),
+        });
+        !full
+    }
+
+    /// Append queued events in order. Anything that fails (e.g. the GUI holds
+    /// a write lock past the busy timeout) stays queued for the next attempt.
+    pub fn flush(&mut self, db: &Database) -> Result<(), String> {
+        while let Some(event) = self.pending.front() {
+            let result = db.append_usage_event(
+                event.kind.as_str(),
+                &event.entity_id,
+                &event.payload,
+                &event.created_at,
+            );
+            match result {
+                Ok(()) => {
+                    self.pending.pop_front();
+                }
+                Err(e) => {
+                    return Err(format!("Failed to record usage event, will retry: {}", e));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Fold every pending event into usage stats and insights, then delete it.
+/// Only the GUI process calls this. Returns the number of events applied.
+pub fn drain(db: &Database) -> SqliteResult<usize> {
+    let mut applied = 0;
+    loop {
+        let events = db.get_usage_events(DRAIN_BATCH)?;
+        let Some(last_id) = events.last().map(|e| e.id) else {
+            return Ok(applied);
+        };
+
+        for event in &events {
+            match event.kind.as_str() {
+                "agent_applied" | "agent_fetched" => {
+                    db.record_agent_usage_at(&event.entity_id, &event.created_at)?;
+                    insights::record_usage(db, &event.entity_id, &event.created_at)?;
+                }
+                "skill_used" => {
+                    db.record_skill_usage_at(&event.entity_id, &event.created_at)?;
+                    insights::record_usage(db, &event.entity_id, &event.created_at)?;
+                }
+                "instruction_used" => {
+                    db.record_instruction_usage_at(&event.entity_id, &event.created_at)?;
+                    insights::record_usage(db, &event.entity_id, &event.created_at)?;
+                }
+                "insight" => {
+                    if let Some(metric) = InsightMetric::parse(&event.payload) {
+                        insights::record(db, metric, &event.entity_id)?;
+                    }
+                }
+                // Written by a newer server; nothing here knows how to apply it
+                _ => {}
+            }
+        }
+
+        db.delete_usage_events_through(last_id)?;
+        applied += events.len();
+        if events.len() < DRAIN_BATCH {
+            return Ok(applied);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_default_data;
+
+    #[test]
+    fn test_queued_events_are_drained_into_usage_stats() {
+        let dir = std::env::temp_dir().join(format!("pf-usage-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db = Database::open(dir.join("live.db")).unwrap();
+        db.migrate().unwrap();
+        init_default_data(&db).unwrap();
+
+        let mut queue = UsageEventQueue::default();
+        queue.push(UsageEventKind::AgentApplied, "default", "");
+        queue.push(UsageEventKind::AgentApplied, "default", "");
+        queue.push(UsageEventKind::Insight, "get_agent", "mcp_call");
+        queue.push(UsageEventKind::SkillUsed, "code-review", "");
+        queue.push(UsageEventKind::InstructionUsed, "code-style", "");
+        queue.flush(&db).unwrap();
+        assert!(queue.pending.is_empty());
+
+        assert_eq!(drain(&db).unwrap(), 5);
+        assert_eq!(drain(&db).unwrap(), 0);
+
+        let agent = db.get_agent("default").unwrap().unwrap();
+        assert_eq!(agent.usage_count, 2);
+        assert!(agent.last_used_at.is_some());
+        let skill = db.get_skill("code-review").unwrap().unwrap();
+        assert_eq!(skill.usage_count, 1);
+        let instruction = db.get_instruction("code-style").unwrap().unwrap();
+        assert_eq!(instruction.usage_count, 1);
+        assert!(instruction.last_used_at.is_some());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,123 @@
+//! Usage statistics across the library
+//! Ranks agents, skills and instructions by use over time windows. All-time
+//! figures come from each item's `usage_count`; windowed counts come from the
+//! hourly usage buckets, which are only kept when insights are enabled.
+//! Whether an item went unused in a window only needs `last_used_at`.
+
+use crate::models::{Agent, Instruction, Skill, UsageStat, UsageWindow};
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+
+/// Lifetime usage of every item in the library
+pub fn library_usage(
+    agents: &[Agent],
+    skills: &[Skill],
+    instructions: &[Instruction],
+) -> Vec<UsageStat> {
+    let stat = |entity_type: &str, id: &str, name: &str, uses: i32, last_used_at| UsageStat {
+        entity_type: entity_type.to_string(),
+        id: id.to_string(),
+        name: name.to_string(),
+        uses: uses as i64,
+        last_used_at,
+    };
+    agents
+        .iter()
+        .map(|a| stat("agent", &a.id, &a.name, a.usage_count, a.last_used_at))
+        .chain(
+            skills
+                .iter()
+                .map(|s| stat("skill", &s.id, &s.name, s.usage_count, s.last_used_at)),
+        )
+        .chain(
+            instructions
+                .iter()
+                .map(|i| stat("instruction", &i.id, &i.name, i.usage_count, i.last_used_at)),
+        )
+        .collect()
+}
+
+/// The window of the last `days` days before `now` (all time when `None`).
+/// `counts` holds each item's uses since the window started and is ignored
+/// for all time. At most `limit` items are ranked as most used; every unused
+/// item is listed, oldest use first.
+pub fn usage_window(
+    items: &[UsageStat],
+    days: Option<u32>,
+    counts: &HashMap<String, i64>,
+    now: DateTime<Utc>,
+    limit: usize,
+) -> UsageWindow {
+    let since = days.map(|days| now - Duration::days(days as i64));
+    let in_window: Vec<UsageStat> = items
+        .iter()
+        .map(|item| UsageStat {
+            uses: match since {
+                Some(_) => counts.get(&item.id).copied().unwrap_or(0),
+                None => item.uses,
+            },
+            ..item.clone()
+        })
+        .collect();
+
+    let mut most_used: Vec<_> = in_window.iter().filter(|i| i.uses > 0).cloned().collect();
+    most_used.sort_by(|a, b| {
+        b.uses
+            .cmp(&a.uses)
+            .then(b.last_used_at.cmp(&a.last_used_at))
+    });
+    most_used.truncate(limit);
+
+    let mut unused: Vec<_> = in_window
+        .into_iter()
+        .filter(|i| match since {
+            Some(since) => i.last_used_at.is_none_or(|at| at < since),
+            None => i.uses == 0,
+        })
+        .collect();
+    unused.sort_by_key(|i| i.last_used_at);
+
+    UsageWindow {
+        days,
+        most_used,
+        unused,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_windows_rank_recent_uses_and_list_unused_items() {
+        let now = Utc::now();
+        let agents = vec![Agent {
+            id: "busy".to_string(),
+            usage_count: 40,
+            last_used_at: Some(now - Duration::days(60)),
+            ..Agent::default()
+        }];
+        let skills = vec![Skill {
+            id: "recent".to_string(),
+            usage_count: 3,
+            last_used_at: Some(now - Duration::hours(2)),
+            ..Skill::default()
+        }];
+        let instructions = vec![Instruction {
+            id: "never".to_string(),
+            ..Instruction::default()
+        }];
+        let items = library_usage(&agents, &skills, &instructions);
+        let ids = |stats: &[UsageStat]| stats.iter().map(|s| s.id.clone()).collect::<Vec<_>>();
+
+        let all_time = usage_window(&items, None, &HashMap::new(), now, 10);
+        assert_eq!(ids(&all_time.most_used), ["busy", "recent"]);
+        assert_eq!(ids(&all_time.unused), ["never"]);
+
+        let counts = HashMap::from([("recent".to_string(), 3)]);
+        let week = usage_window(&items, Some(7), &counts, now, 1);
+        assert_eq!(ids(&week.most_used), ["recent"]);
+        assert_eq!(week.most_used[0].uses, 3);
+        assert_eq!(ids(&week.unused), ["never", "busy"]);
+    }
+}
@@ -0,0 +1,225 @@
+//! Workspace variables for prompt templates
+//! `{{workspace.name}}`, `{{workspace.root}}` and `{{workspace.branch}}`
+//! describe the project an MCP client is working in; any other `{{workspace.<key>}}` is looked up in an
+//! optional `.promptforge.toml` at the project root. Nested tables flatten to
+//! dotted keys, so `[stack] language = "rust"` becomes `workspace.stack.language`.
+//! Placeholders without a value are left untouched.
+
+use regex::{Captures, Regex};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Per-project settings file read from the workspace root
+pub const CONFIG_FILE: &str = ".promptforge.toml";
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Workspace {
+    pub name: String,           // `name` from the config, else the directory name
+    pub root: String,           // Absolute path of the project root
+    pub branch: Option<String>, // Checked-out git branch, if the root is a repository
+    pub values: BTreeMap<String, String>, // Flattened config values keyed by dotted path
+}
+
+impl Workspace {
+    /// Load the workspace rooted at `root`, reading its config file if present
+    pub fn load(root: &Path) -> Result<Self, String> {
+        if !root.is_dir() {
+            return Err(format!(
+                "Workspace root '{}' is not a directory",
+                root.display()
+            ));
+        }
+
+        let config_path = root.join(CONFIG_FILE);
+        let config = if config_path.is_file() {
+            Some(
+                std::fs::read_to_string(&config_path)
+                    .map_err(|e| format!("Failed to read {}: {}", CONFIG_FILE, e))?,
+            )
+        } else {
+            None
+        };
+
+        let mut workspace = Self::from_config(root, config.as_deref())?;
+        workspace.branch = current_branch(root);
+        Ok(workspace)
+    }
+
+    /// Build a workspace from a root path and the contents of its config file
+    pub fn from_config(root: &Path, config: Option<&str>) -> Result<Self, String> {
+        let mut values = BTreeMap::new();
+        if let Some(text) = config {
+            let table: toml::Table = text
+                .parse()
+                .map_err(|e| format!("Failed to parse {}: {}", CONFIG_FILE, e))?;
+            flatten("", &toml::Value::Table(table), &mut values);
+        }
+
+        let name = values.remove("name").unwrap_or_else(|| {
+            root.file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default()
+        });
+        // The root and branch always come from the project, never the file
+        values.remove("root");
+        values.remove("branch");
+
+        Ok(Self {
+            name,
+            root: root.to_string_lossy().to_string(),
+            branch: None,
+            values,
+        })
+    }
+
+    /// Value of `workspace.<key>`
+    pub fn get(&self, key: &str) -> Option<&str> {
+        match key {
+            "name" => Some(&self.name),
+            "root" => Some(&self.root),
+            "branch" => self.branch.as_deref(),
+            _ => self.values.get(key).map(String::as_str),
+        }
+    }
+
+    /// Replace every `{{workspace.<key>}}` that has a value
+    pub fn render(&self, text: &str) -> String {
+        static PLACEHOLDER: OnceLock<Regex> = OnceLock::new();
+        let placeholder = PLACEHOLDER
+            .get_or_init(|| Regex::new(r"\{\{\s*workspace\.([A-Za-z0-9_.\-]+)\s*\}\}").unwrap());
+
+        placeholder
+            .replace_all(text, |caps: &Captures| match self.get(&caps[1]) {
+                Some(value) => value.to_string(),
+                None => caps[0].to_string(),
+            })
+            .into_owned()
+    }
+}
+
+/// Branch checked out at `root`, read from `.git/HEAD`. Worktrees, whose
+/// `.git` is a file pointing at the real git directory, are followed.
+/// Detached HEADs have no branch.
+fn current_branch(root: &Path) -> Option<String> {
+    let git = root.join(".git");
+    let git_dir = if git.is_file() {
+        let pointer = std::fs::read_to_string(&git).ok()?;
+        root.join(pointer.trim().strip_prefix("gitdir:")?.trim())
+    } else {
+        git
+    };
+
+    let head = std::fs::read_to_string(git_dir.join("HEAD")).ok()?;
+    head.trim()
+        .strip_prefix("ref: refs/heads/")
+        .map(str::to_string)
+}
+
+fn flatten(prefix: &str, value: &toml::Value, values: &mut BTreeMap<String, String>) {
+    let rendered = match value {
+        toml::Value::Table(table) => {
+            for (key, value) in table {
+                let key = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                flatten(&key, value, values);
+            }
+            return;
+        }
+        toml::Value::String(s) => s.clone(),
+        toml::Value::Array(items) => items
+            .iter()
+            .map(|item| match item {
+                toml::Value::String(s) => s.clone(),
+                other => other.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(", "),
+        other => other.to_string(),
+    };
+    values.insert(prefix.to_string(), rendered);
+}
+
+/// Local path for a `file://` root URI as sent in an MCP `roots/list` reply.
+/// Other schemes can't be read from disk and return `None`.
+pub fn root_from_uri(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?;
+    // Drop an optional host ("file://localhost/...")
+    let path = &path[path.find('/')?..];
+    let path = percent_decode(path)?;
+
+    // "/C:/Users/..." on Windows
+    let bytes = path.as_bytes();
+    if bytes.len() >= 3 && bytes[0] == b'/' && bytes[1].is_ascii_alphabetic() && bytes[2] == b':' {
+        return Some(PathBuf::from(&path[1..]));
+    }
+    Some(PathBuf::from(path))
+}
+
+pub(crate) fn percent_decode(text: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut rest = text.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' && tail.len() >= 2 {
+            let hex = std::str::from_utf8(&tail[..2]).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_uses_config_values_and_keeps_unknown_placeholders() {
+        let config = r#"
+            name = "Billing API"
+            test_command = "cargo nextest run"
+
+            [stack]
+            language = "rust"
+            services = ["postgres", "redis"]
+        "#;
+        let workspace = Workspace::from_config(Path::new("/src/billing"), Some(config)).unwrap();
+
+        assert_eq!(
+            workspace.render(
+                "Project {{workspace.name}} at {{ workspace.root }} uses {{workspace.stack.language}} \
+                 with {{workspace.stack.services}}; test with `{{workspace.test_command}}`. \
+                 {{workspace.owner}} {{diff}}"
+            ),
+            "Project Billing API at /src/billing uses rust with postgres, redis; \
+             test with `cargo nextest run`. {{workspace.owner}} {{diff}}"
+        );
+
+        let unnamed = Workspace::from_config(Path::new("/src/billing"), None).unwrap();
+        assert_eq!(unnamed.name, "billing");
+        assert!(Workspace::from_config(Path::new("/src"), Some("name = ")).is_err());
+    }
+
+    #[test]
+    fn test_root_from_uri() {
+        assert_eq!(
+            root_from_uri("file:///home/dev/My%20Project"),
+            Some(PathBuf::from("/home/dev/My Project"))
+        );
+        assert_eq!(
+            root_from_uri("file://localhost/srv/app"),
+            Some(PathBuf::from("/srv/app"))
+        );
+        assert_eq!(
+            root_from_uri("file:///C:/Users/dev/app"),
+            Some(PathBuf::from("C:/Users/dev/app"))
+        );
+        assert_eq!(root_from_uri("https://example.com/repo"), None);
+    }
+}
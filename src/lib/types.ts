@@ -22,6 +22,11 @@ export interface Agent {
   updated_at: string;
   usage_count: number;
   last_used_at: string | null;
+  avatar_image_id?: string | null; // Attachment ID of an image avatar
+  aliases?: string[];
+  instruction_order?: 'priority' | 'attached'; // Defaults to 'priority'
+  include_global_instructions?: boolean; // Defaults to true
+  parent_agent_id?: string | null; // Base agent it inherits what it leaves unset from
 }
 
 export type SkillType = 'prompt' | 'tool' | 'workflow';
@@ -32,6 +37,7 @@ export interface ToolParameter {
   param_type: string;
   required: boolean;
   default?: any;
+  schema?: Record<string, unknown> | null; // Further JSON Schema keywords: enum, minimum, properties, ...
 }
 
 export interface WorkflowStep {
@@ -42,11 +48,24 @@ export interface WorkflowStep {
   outputs: string[];
 }
 
+export interface TemplateVariable {
+  name: string; // Used as {{name}} in the template
+  description: string;
+  default?: string; // Variables without a default are required
+}
+
 export type SkillDefinition =
-  | { type: 'prompt'; template: string }
-  | { type: 'tool'; parameters: ToolParameter[]; handler: string }
+  | { type: 'prompt'; template: string; variables?: TemplateVariable[]; follows?: string[] }
+  | { type: 'tool'; parameters: ToolParameter[]; handler: string; sandbox?: ToolSandbox }
   | { type: 'workflow'; steps: WorkflowStep[] };
 
+export interface ToolSandbox {
+  timeout_secs: number; // The handler is killed after this long
+  working_dir?: string; // Defaults to the client's workspace root, else the temp directory
+  inherit_env: boolean; // Pass the whole environment instead of PATH, HOME and locale only
+  max_output_bytes: number; // Per stream; the rest is dropped
+}
+
 export interface Skill {
   id: string;
   name: string;
@@ -57,6 +76,9 @@ export interface Skill {
   enabled: boolean;
   created_at: string;
   updated_at: string;
+  aliases?: string[];
+  usage_count?: number; // Times served through the MCP server
+  last_used_at?: string | null;
 }
 
 export type InstructionCategory =
@@ -81,6 +103,197 @@ export interface Instruction {
   enabled: boolean;
   created_at: string;
   updated_at: string;
+  pin_to_context?: boolean;
+  subcategory?: string | null; // Path below the category, e.g. 'rust/async'
+  branch_pattern?: string | null; // Comma-separated branch globs, e.g. 'release/*, !release/legacy'
+  contexts?: string[]; // Languages, projects or clients it is for, e.g. 'rust'; empty for all
+  usage_count?: number; // Times served through the MCP server
+  last_used_at?: string | null;
+  review_due_at?: string | null; // Listed by list_due_for_review once past
+  upstream_url?: string | null; // Page the instruction was imported from
+  upstream_synced_at?: string | null; // When its content was last taken from there
+  upstream_hash?: string | null;
+  activation?: InstructionActivation | null; // When it is served, besides being enabled
+}
+
+export interface InstructionActivation {
+  starts_on?: string | null; // "YYYY-MM-DD"
+  ends_on?: string | null;
+  weekdays?: string[]; // "mon" to "sun"
+  hours?: string | null; // Local window such as "09:00-17:00"
+  profiles?: string[];
+}
+
+export interface Snippet {
+  id: string; // Included in prompts as {{> id}}
+  name: string;
+  description: string;
+  content: string;
+  created_at: string;
+  updated_at: string;
+}
+
+export interface LibraryIssue {
+  severity: 'error' | 'warning';
+  entity_type: string;
+  entity_id: string;
+  entity_name: string;
+  message: string;
+}
+
+export interface ConflictingRule {
+  instruction_id: string;
+  instruction_name: string;
+  sentence: string;
+}
+
+export interface InstructionConflict {
+  kind: 'negation' | 'choice'; // One rule forbids what the other asks, or they pick different options
+  topic: string; // Shared keywords, or the choice such as 'indentation: tabs or 2 spaces'
+  first: ConflictingRule;
+  second: ConflictingRule;
+}
+
+export type LibraryItem =
+  | { entity_type: 'agent'; entity: Agent }
+  | { entity_type: 'skill'; entity: Skill }
+  | { entity_type: 'instruction'; entity: Instruction };
+
+export interface SupportedFormat {
+  id: string; // e.g. 'agent-markdown', passed to import_with_format / export_with_format
+  name: string;
+  entity_type: 'agent' | 'skill' | 'instruction';
+  extension: string; // Without the dot
+  can_import: boolean;
+  can_export: boolean;
+}
+
+export interface ParseDiagnostic {
+  line: number; // 1-based
+  column: number; // 1-based
+  severity: 'error' | 'warning';
+  message: string;
+}
+
+export interface ImportPreview<T> {
+  entity: T;
+  diagnostics: ParseDiagnostic[];
+}
+
+export interface PreflightReport {
+  issues: LibraryIssue[];
+  errors: number;
+  warnings: number;
+  blocked: boolean;
+  summary: string; // Included in the sync commit message
+}
+
+export type TraceReason = 'included' | 'disabled' | 'not_attached' | 'missing' | 'out_of_scope';
+
+export interface TraceEntry {
+  entity_type: 'skill' | 'instruction';
+  entity_id: string;
+  entity_name: string;
+  included: boolean;
+  section: string | null;
+  reason: TraceReason;
+}
+
+export interface AppliedAgent {
+  prompt: string;
+  trace?: TraceEntry[];
+}
+
+export type PromptWrapper = 'plain' | 'markdown' | 'xml';
+
+export type PromptExportTarget = { kind: 'file'; path: string } | { kind: 'clipboard' };
+
+export interface PromptExport {
+  agent_id: string;
+  target: PromptExportTarget;
+  characters: number;
+  tokens: number; // Estimated
+}
+
+export interface ReleaseSummary {
+  tag: string;
+  notes: string;
+  created_at: string;
+  agent_count: number;
+  skill_count: number;
+  instruction_count: number;
+}
+
+export interface NotionDatabase {
+  name: string;
+  file_name: string;
+  columns: string[];
+  row_count: number;
+}
+
+export interface NotionMapping {
+  name_column?: string;
+  category_column?: string;
+  tags_column?: string;
+  priority_column?: string;
+  description_column?: string;
+  content_column?: string;
+  default_category?: string;
+}
+
+export interface ChatGptSuggestion {
+  key: string; // Passed back to `import_chatgpt_suggestions` to add it
+  item: LibraryItem;
+  reason: string;
+  occurrences: number; // Chats it appeared in
+  duplicate_of?: string; // Id of a library item saying much the same
+}
+
+export interface NotionImportResult {
+  imported: Instruction[];
+  skipped: string[];
+}
+
+export interface UpstreamCheck {
+  instruction_id: string;
+  name: string;
+  upstream_url: string;
+  status: 'unchanged' | 'changed' | 'unreachable';
+  upstream_content?: string | null; // The page's content now, when it changed
+  error?: string | null;
+}
+
+export interface ListQuery {
+  offset?: number;
+  limit?: number; // 50 when unset, at most 500
+  tags?: string[]; // Agents and instructions carrying every one of these
+  category?: string; // Instructions filed here or below
+  enabled?: boolean; // Skills and instructions
+  query?: string; // Text in the name, description or body
+  sort?: 'name' | 'created_at' | 'updated_at' | 'usage_count' | 'priority';
+  descending?: boolean;
+}
+
+export interface Page<T> {
+  items: T[];
+  total: number; // Items matching the filters across all pages
+  offset: number;
+  limit: number;
+}
+
+export interface BulkUpdate {
+  enabled?: boolean;
+  add_tags?: string[];
+  remove_tags?: string[];
+  category?: string; // Instructions only, a path such as "code_style/rust"
+}
+
+export interface CategoryNode {
+  path: string;
+  name: string;
+  depth: number;
+  instruction_count: number;
+  total_count: number;
 }
 
 export interface Theme {
@@ -95,6 +308,107 @@ export interface Settings {
   mcp_server_enabled: boolean;
   data_directory?: string;
   auto_start_mcp: boolean;
+  notifications?: NotificationSettings;
+  insights_enabled?: boolean;
+  redaction_profiles?: RedactionProfile[];
+  release_channels?: ReleaseChannel[];
+  block_sync_on_errors?: boolean;
+  strict_imports?: boolean;
+  llm_providers?: LlmProvider[]; // The first one is used unless a command names another
+  library_folder?: string; // Mirrored as files and watched for outside edits
+  mcp_write_tools?: boolean; // Lets MCP clients create and update instructions and skills
+  active_profile?: string | null; // Serves the instructions activated for this profile
+  mcp_run_tools?: boolean; // Lets MCP clients run the approved handlers of tool skills
+}
+
+export interface LlmProvider {
+  name: string;
+  kind: 'openai' | 'anthropic'; // 'openai' covers any OpenAI-compatible API
+  model: string;
+  base_url?: string; // Defaults to the vendor's public API
+  api_key: string;
+}
+
+export interface AgentTestRun {
+  agent_id: string;
+  provider: string;
+  model: string;
+  system_prompt: string;
+  prompt_tokens: number; // Estimated
+  message: string;
+  reply: string;
+  duration_ms: number;
+  usage: TokenUsage | null; // As reported by the provider, when it does
+}
+
+export interface TokenUsage {
+  input_tokens: number;
+  output_tokens: number;
+}
+
+export interface AgentComparison {
+  message: string;
+  a: ComparisonSide;
+  b: ComparisonSide;
+}
+
+export interface ComparisonSide {
+  agent_id: string;
+  release: string | null; // null for the live library
+  run: AgentTestRun | null;
+  error: string | null; // Set instead of run when this side failed
+}
+
+export interface SummaryScope {
+  agent_id?: string; // Only what this agent uses (ID or name)
+  category?: string; // Category path such as 'code_style/rust'
+}
+
+export interface OnboardingDocument {
+  title: string;
+  format: 'markdown' | 'html';
+  content: string;
+  generated_by: string; // 'extractive', or the LLM provider's name
+  notice?: string; // Why an LLM-assisted summary fell back to the extractive one
+}
+
+export interface Translation {
+  id: string;
+  entity_type: 'agent' | 'skill' | 'instruction';
+  entity_id: string;
+  locale: string; // e.g. 'de' or 'pt-BR'
+  name: string;
+  description: string;
+  content: string; // System prompt, prompt template or instruction content
+  needs_review: boolean; // Not served until approved
+  provider?: string;
+  created_at: string;
+  updated_at: string;
+}
+
+export interface ReleaseChannel {
+  name: string;
+  tag: string; // Release tag, or 'latest' for the live library
+}
+
+export interface RedactionProfile {
+  name: string;
+  strip_tags: string[];
+  remove_sections: string[]; // Regexes matched against markdown headings
+  replacements: RedactionReplacement[];
+}
+
+export interface RedactionReplacement {
+  find: string;
+  replace: string;
+}
+
+export interface NotificationSettings {
+  mcp_crash: boolean;
+  backup_failed: boolean;
+  sync_failed: boolean;
+  import_completed: boolean;
+  review_due: boolean;
 }
 
 export interface McpStatus {
@@ -104,6 +418,124 @@ export interface McpStatus {
   available_tools: string[];
 }
 
+export interface DailyInsights {
+  day: string;
+  edits: number;
+  applies: number;
+  mcp_calls: number;
+}
+
+export interface WeeklySummary {
+  start_day: string;
+  end_day: string;
+  days: DailyInsights[];
+  total_edits: number;
+  total_applies: number;
+  total_mcp_calls: number;
+  top_categories: [string, number][];
+}
+
+export interface UsageStat {
+  entity_type: 'agent' | 'skill' | 'instruction';
+  id: string;
+  name: string;
+  uses: number; // Within the window
+  last_used_at: string | null;
+}
+
+export interface UsageWindow {
+  days: number | null; // null for all time
+  most_used: UsageStat[];
+  unused: UsageStat[]; // Not used within the window; never used for all time
+}
+
+export interface LibraryWorkspace {
+  name: string;
+  db_path: string;
+  active: boolean; // The workspace the app opens
+}
+
+export interface BackupManifest {
+  version: string;
+  created_at: string;
+  file_name: string;
+  size_bytes: number;
+  sha256: string;
+  agents: number;
+  skills: number;
+  instructions: number;
+}
+
+export interface BackupVerification {
+  path: string;
+  manifest_found: boolean;
+  digest_matches: boolean;
+  integrity_ok: boolean;
+  restorable: boolean;
+  agents: number;
+  skills: number;
+  instructions: number;
+  errors: string[];
+}
+
+export interface LibraryComparison {
+  other: string; // Path of the other database or export
+  differences: LibraryDifference[];
+  same: number; // Items both sides have with the same content
+}
+
+export interface LibraryDifference {
+  entity_type: 'agent' | 'skill' | 'instruction';
+  id: string;
+  name: string;
+  status: 'only_here' | 'only_there' | 'differs';
+  fields: string[]; // Fields whose content differs
+  newer?: 'here' | 'there'; // By last edit, when content differs
+}
+
+export interface GitSyncResult {
+  commit: string | null; // Commit made or pulled; null when nothing changed
+  pushed: boolean;
+  agents: number;
+  skills: number;
+  instructions: number;
+}
+
+export interface EditLock {
+  entity_id: string;
+  owner_id: string;
+  owner_name: string;
+  acquired_at: string;
+  expires_at: string;
+}
+
+export interface SecretFinding {
+  entity_type: 'agent' | 'skill' | 'instruction';
+  entity_id: string;
+  entity_name: string;
+  field: string;
+  kind: string;
+  line: number;
+  excerpt: string;
+  blocking: boolean;
+}
+
+export interface PaletteAction {
+  id: string;
+  title: string;
+  subtitle: string;
+  category: 'agent' | 'skill' | 'instruction' | 'operation';
+  score: number;
+}
+
+export interface ActionOutcome {
+  action_id: string;
+  view?: View;
+  entity_id?: string;
+  output?: string;
+  message: string;
+}
+
 // View state types
 export type View = 'agents' | 'skills' | 'instructions' | 'settings' | 'mcp';
 
@@ -152,7 +584,8 @@ export const defaultInstruction: Partial<Instruction> = {
   content: '',
   priority: 5,
   tags: [],
-  enabled: true
+  enabled: true,
+  pin_to_context: false
 };
 
 export const defaultSettings: Settings = {
@@ -163,5 +596,12 @@ export const defaultSettings: Settings = {
   },
   mcp_server_port: 3333,
   mcp_server_enabled: false,
-  auto_start_mcp: false
+  auto_start_mcp: false,
+  notifications: {
+    mcp_crash: true,
+    backup_failed: true,
+    sync_failed: true,
+    import_completed: true,
+    review_due: true
+  }
 };